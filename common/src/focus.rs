@@ -0,0 +1,294 @@
+//====================================================================
+
+use hecs::{Entity, World};
+
+use crate::GlobalTransform;
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NavDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl NavDirection {
+    #[inline]
+    fn vector(&self) -> glam::Vec2 {
+        match self {
+            NavDirection::Up => glam::Vec2::Y,
+            NavDirection::Down => glam::Vec2::NEG_Y,
+            NavDirection::Left => glam::Vec2::NEG_X,
+            NavDirection::Right => glam::Vec2::X,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FocusAxis {
+    #[default]
+    Vertical,
+    Horizontal,
+}
+
+/// Explicit neighbours. Any direction left as `None` falls back to spatial navigation.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FocusNeighbors {
+    pub up: Option<Entity>,
+    pub down: Option<Entity>,
+    pub left: Option<Entity>,
+    pub right: Option<Entity>,
+}
+
+impl FocusNeighbors {
+    #[inline]
+    fn get(&self, direction: NavDirection) -> Option<Entity> {
+        match direction {
+            NavDirection::Up => self.up,
+            NavDirection::Down => self.down,
+            NavDirection::Left => self.left,
+            NavDirection::Right => self.right,
+        }
+    }
+}
+
+/// Entity that can receive focus. Entities with multiple items (such as menu options)
+/// navigate between their items along `axis` before moving focus to another entity.
+#[derive(Debug, Clone)]
+pub struct Focusable {
+    pub enabled: bool,
+    pub neighbors: FocusNeighbors,
+
+    pub axis: FocusAxis,
+    pub items: usize,
+    pub item: usize,
+    /// Step from the last item back to the first and the other way around, instead of
+    /// stopping at the ends and moving focus to a neighbour.
+    pub wrap: bool,
+}
+
+impl Default for Focusable {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            neighbors: FocusNeighbors::default(),
+            axis: FocusAxis::default(),
+            items: 0,
+            item: 0,
+            wrap: false,
+        }
+    }
+}
+
+impl Focusable {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn with_items(mut self, items: usize, axis: FocusAxis) -> Self {
+        self.items = items;
+        self.axis = axis;
+        self
+    }
+
+    #[inline]
+    pub fn with_wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    #[inline]
+    pub fn with_neighbors(mut self, neighbors: FocusNeighbors) -> Self {
+        self.neighbors = neighbors;
+        self
+    }
+
+    /// Step between items. Returns false when stepping past the first or last item
+    /// without wrapping.
+    fn step_item(&mut self, direction: NavDirection) -> bool {
+        let step = match (self.axis, direction) {
+            (FocusAxis::Vertical, NavDirection::Up) => -1,
+            (FocusAxis::Vertical, NavDirection::Down) => 1,
+            (FocusAxis::Horizontal, NavDirection::Left) => -1,
+            (FocusAxis::Horizontal, NavDirection::Right) => 1,
+            _ => return false,
+        };
+
+        let items = self.items as isize;
+        let next = self.item as isize + step;
+
+        let next = match self.wrap {
+            true if items > 0 => next.rem_euclid(items),
+            _ if next < 0 || next >= items => return false,
+            _ => next,
+        };
+
+        self.item = next as usize;
+        true
+    }
+}
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusEvent {
+    Gained(Entity),
+    Lost(Entity),
+    ItemChanged { entity: Entity, item: usize },
+    Activated { entity: Entity, item: usize },
+    Cancelled(Entity),
+}
+
+/// Tracks the currently focused entity. Events are kept until [`FocusManager::clear_events`]
+/// is called, which the engine does at the end of every frame.
+#[derive(Debug, Default)]
+pub struct FocusManager {
+    focused: Option<Entity>,
+    events: Vec<FocusEvent>,
+}
+
+impl FocusManager {
+    #[inline]
+    pub fn focused(&self) -> Option<Entity> {
+        self.focused
+    }
+
+    #[inline]
+    pub fn events(&self) -> &[FocusEvent] {
+        &self.events
+    }
+
+    #[inline]
+    pub fn clear_events(&mut self) {
+        self.events.clear();
+    }
+
+    pub fn set_focus(&mut self, entity: Option<Entity>) {
+        if self.focused == entity {
+            return;
+        }
+
+        if let Some(previous) = self.focused {
+            self.events.push(FocusEvent::Lost(previous));
+        }
+
+        if let Some(entity) = entity {
+            self.events.push(FocusEvent::Gained(entity));
+        }
+
+        self.focused = entity;
+    }
+
+    /// Move focus in a direction. Focuses the first available entity when nothing is focused.
+    /// Returns true if the focused entity or item changed.
+    pub fn navigate(&mut self, world: &mut World, direction: NavDirection) -> bool {
+        // Drop focus from entities that were despawned or disabled
+        let current = self.focused.filter(|entity| {
+            world
+                .get::<&Focusable>(*entity)
+                .is_ok_and(|focusable| focusable.enabled)
+        });
+
+        let current = match current {
+            Some(current) => current,
+            None => {
+                let first = world
+                    .query_mut::<&Focusable>()
+                    .into_iter()
+                    .filter(|(_, focusable)| focusable.enabled)
+                    .map(|(entity, _)| entity)
+                    .min();
+
+                let changed = first != self.focused;
+                self.set_focus(first);
+                return changed;
+            }
+        };
+
+        //--------------------------------------------------
+
+        let neighbor = {
+            let mut focusable = world.get::<&mut Focusable>(current).unwrap();
+
+            if focusable.step_item(direction) {
+                self.events.push(FocusEvent::ItemChanged {
+                    entity: current,
+                    item: focusable.item,
+                });
+                return true;
+            }
+
+            focusable.neighbors.get(direction)
+        };
+
+        let next = match neighbor {
+            Some(neighbor) => Some(neighbor),
+            None => Self::spatial_neighbor(world, current, direction),
+        };
+
+        match next {
+            Some(next) => {
+                self.set_focus(Some(next));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Activate the focused entity's current item.
+    pub fn activate(&mut self, world: &World) {
+        if let Some(entity) = self.focused {
+            let item = world
+                .get::<&Focusable>(entity)
+                .map(|focusable| focusable.item)
+                .unwrap_or(0);
+
+            self.events.push(FocusEvent::Activated { entity, item });
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        if let Some(entity) = self.focused {
+            self.events.push(FocusEvent::Cancelled(entity));
+        }
+    }
+
+    /// Closest enabled focusable in the given direction, using GlobalTransform x/y.
+    fn spatial_neighbor(
+        world: &mut World,
+        from: Entity,
+        direction: NavDirection,
+    ) -> Option<Entity> {
+        let origin = world
+            .get::<&GlobalTransform>(from)
+            .ok()?
+            .translation()
+            .truncate();
+        let direction = direction.vector();
+
+        world
+            .query_mut::<(&Focusable, &GlobalTransform)>()
+            .into_iter()
+            .filter(|(entity, (focusable, _))| *entity != from && focusable.enabled)
+            .filter_map(|(entity, (_, transform))| {
+                let offset = transform.translation().truncate() - origin;
+
+                let along = offset.dot(direction);
+                if along <= f32::EPSILON {
+                    return None;
+                }
+
+                // Favour candidates that are closely aligned with the direction
+                let across = offset.perp_dot(direction).abs();
+                Some((entity, along + across * 2.))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entity, _)| entity)
+    }
+}
+
+//====================================================================
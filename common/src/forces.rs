@@ -0,0 +1,222 @@
+//====================================================================
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+//====================================================================
+
+/// Global wind, gusting over time and rolling through space as waves.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Wind {
+    pub direction: glam::Vec3,
+    /// Speed of the air without gusts.
+    pub strength: f32,
+    /// How much gusts add to or take from the strength, from 0 to 1.
+    pub gust_strength: f32,
+    /// Gusts per second.
+    pub gust_frequency: f32,
+    /// Distance between gusts as they roll along the wind.
+    pub gust_spacing: f32,
+}
+
+impl Default for Wind {
+    fn default() -> Self {
+        Self {
+            direction: glam::Vec3::X,
+            strength: 0.,
+            gust_strength: 0.5,
+            gust_frequency: 0.3,
+            gust_spacing: 10.,
+        }
+    }
+}
+
+impl Wind {
+    #[inline]
+    pub fn new(direction: glam::Vec3, strength: f32) -> Self {
+        Self {
+            direction,
+            strength,
+            ..Default::default()
+        }
+    }
+
+    /// Air velocity at `position`, `time` seconds in.
+    pub fn velocity_at(&self, position: glam::Vec3, time: f32) -> glam::Vec3 {
+        let direction = self.direction.normalize_or_zero();
+        if self.strength == 0. || direction == glam::Vec3::ZERO {
+            return glam::Vec3::ZERO;
+        }
+
+        // Layered waves travelling downwind, cheap stand in for noise
+        let distance = direction.dot(position) / self.gust_spacing.max(f32::EPSILON);
+        let phase = (time * self.gust_frequency - distance) * std::f32::consts::TAU;
+        let gust =
+            (phase.sin() + (phase * 2.3 + 1.7).sin() * 0.5 + (phase * 4.1 + 0.4).sin() * 0.25)
+                / 1.75;
+
+        direction * self.strength * (1. + gust * self.gust_strength.clamp(0., 1.)).max(0.)
+    }
+}
+
+//--------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ForceFieldShape {
+    Sphere { radius: f32 },
+    Box { half_extents: glam::Vec3 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ForceFieldKind {
+    /// Constant acceleration, e.g. an updraft.
+    Directional(glam::Vec3),
+    /// Acceleration away from the center, or towards it when negative.
+    Radial(f32),
+    /// Acceleration around an axis through the center, e.g. a whirlwind.
+    Vortex { axis: glam::Vec3, strength: f32 },
+}
+
+/// Volume accelerating everything inside it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ForceField {
+    pub center: glam::Vec3,
+    pub shape: ForceFieldShape,
+    pub kind: ForceFieldKind,
+    /// Fade the force out towards the edge of the volume.
+    pub falloff: bool,
+}
+
+impl ForceField {
+    #[inline]
+    pub fn new(center: glam::Vec3, shape: ForceFieldShape, kind: ForceFieldKind) -> Self {
+        Self {
+            center,
+            shape,
+            kind,
+            falloff: false,
+        }
+    }
+
+    #[inline]
+    pub fn with_falloff(mut self) -> Self {
+        self.falloff = true;
+        self
+    }
+
+    /// How far into the volume `position` is, 0 outside to 1 at the center.
+    fn weight_at(&self, position: glam::Vec3) -> f32 {
+        let offset = position - self.center;
+
+        let edge = match self.shape {
+            ForceFieldShape::Sphere { radius } => offset.length() / radius.max(f32::EPSILON),
+            ForceFieldShape::Box { half_extents } => {
+                (offset.abs() / half_extents.max(glam::Vec3::splat(f32::EPSILON))).max_element()
+            }
+        };
+
+        match (edge <= 1., self.falloff) {
+            (false, _) => 0.,
+            (true, false) => 1.,
+            (true, true) => 1. - edge,
+        }
+    }
+
+    pub fn acceleration_at(&self, position: glam::Vec3) -> glam::Vec3 {
+        let weight = self.weight_at(position);
+        if weight == 0. {
+            return glam::Vec3::ZERO;
+        }
+
+        let offset = position - self.center;
+
+        let acceleration = match self.kind {
+            ForceFieldKind::Directional(acceleration) => acceleration,
+            ForceFieldKind::Radial(strength) => offset.normalize_or_zero() * strength,
+            ForceFieldKind::Vortex { axis, strength } => {
+                axis.cross(offset).normalize_or_zero() * strength
+            }
+        };
+
+        acceleration * weight
+    }
+}
+
+pub type ForceFieldId = u32;
+
+//====================================================================
+
+/// Environmental forces shared by every simulation, so particles, cloth and ropes all
+/// agree on which way the wind blows.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Forces {
+    pub wind: Wind,
+    /// Replaces the gravity of simulations using the world's gravity.
+    pub gravity: Option<glam::Vec3>,
+
+    fields: BTreeMap<ForceFieldId, ForceField>,
+    next_field: ForceFieldId,
+    time: f32,
+}
+
+impl Forces {
+    #[inline]
+    pub fn add_field(&mut self, field: ForceField) -> ForceFieldId {
+        let id = self.next_field;
+        self.next_field += 1;
+        self.fields.insert(id, field);
+        id
+    }
+
+    #[inline]
+    pub fn remove_field(&mut self, id: ForceFieldId) -> Option<ForceField> {
+        self.fields.remove(&id)
+    }
+
+    #[inline]
+    pub fn field_mut(&mut self, id: ForceFieldId) -> Option<&mut ForceField> {
+        self.fields.get_mut(&id)
+    }
+
+    #[inline]
+    pub fn fields(&self) -> impl Iterator<Item = (ForceFieldId, &ForceField)> {
+        self.fields.iter().map(|(id, field)| (*id, field))
+    }
+
+    /// Seconds the wind has been gusting for.
+    #[inline]
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    #[inline]
+    pub fn tick(&mut self, delta_seconds: f32) {
+        self.time += delta_seconds;
+    }
+
+    //--------------------------------------------------
+
+    /// The gravity override, or `default` without one.
+    #[inline]
+    pub fn gravity_or(&self, default: glam::Vec3) -> glam::Vec3 {
+        self.gravity.unwrap_or(default)
+    }
+
+    /// Air velocity at `position`, including gusts.
+    #[inline]
+    pub fn wind_at(&self, position: glam::Vec3) -> glam::Vec3 {
+        self.wind.velocity_at(position, self.time)
+    }
+
+    /// Combined acceleration of every force field containing `position`.
+    pub fn acceleration_at(&self, position: glam::Vec3) -> glam::Vec3 {
+        self.fields
+            .values()
+            .map(|field| field.acceleration_at(position))
+            .sum()
+    }
+}
+
+//====================================================================
@@ -2,6 +2,10 @@
 
 use std::fmt::Display;
 
+pub mod focus;
+pub mod forces;
+pub mod profiler;
+
 //====================================================================
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq)]
@@ -44,7 +48,7 @@ impl<T: Display> Display for Size<T> {
 
 //====================================================================
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, serde::Serialize, serde::Deserialize)]
 pub struct GlobalTransform(pub glam::Affine3A);
 
 impl GlobalTransform {
@@ -66,7 +70,7 @@ impl GlobalTransform {
 
 //--------------------------------------------------
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Transform {
     pub translation: glam::Vec3,
     pub rotation: glam::Quat,
@@ -227,10 +231,10 @@ impl Transform {
     }
 }
 
-impl Into<glam::Mat4> for &Transform {
+impl From<&Transform> for glam::Mat4 {
     #[inline]
-    fn into(self) -> glam::Mat4 {
-        glam::Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    fn from(value: &Transform) -> Self {
+        glam::Mat4::from_scale_rotation_translation(value.scale, value.rotation, value.translation)
     }
 }
 
@@ -294,3 +298,42 @@ impl std::ops::Sub<&Self> for Transform {
 }
 
 //====================================================================
+
+/// Opacity of an entity and its descendants, multiplied with that of its ancestors.
+/// Fades the entity's sprites, models and text along with any attached to it.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Opacity(pub f32);
+
+impl Default for Opacity {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+/// Final opacity of an entity after multiplying in its ancestors' [`Opacity`]. Updated by the
+/// engine for entities with an `Opacity` on them or an ancestor. Missing means fully opaque.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlobalOpacity(pub f32);
+
+impl Default for GlobalOpacity {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+impl GlobalOpacity {
+    /// Opacity of an entity that may not have one.
+    #[inline]
+    pub fn of(opacity: Option<&GlobalOpacity>) -> f32 {
+        opacity.map(|opacity| opacity.0).unwrap_or(1.)
+    }
+
+    /// `color` with its alpha multiplied by the opacity.
+    #[inline]
+    pub fn apply(opacity: Option<&GlobalOpacity>, color: [f32; 4]) -> [f32; 4] {
+        let [r, g, b, a] = color;
+        [r, g, b, a * Self::of(opacity)]
+    }
+}
+
+//====================================================================
@@ -0,0 +1,255 @@
+//====================================================================
+
+use std::{
+    cell::Cell,
+    fmt::Write,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use web_time::{Duration, Instant};
+
+//====================================================================
+
+// Checked before locking so scopes cost next to nothing while not recording
+static RECORDING: AtomicBool = AtomicBool::new(false);
+static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+static FINISHED: Mutex<Option<Profile>> = Mutex::new(None);
+
+static NEXT_THREAD: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    static THREAD: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Track of gpu spans, separate from the thread tracks.
+pub const GPU_TRACK: u64 = 0;
+
+/// Track of spans recorded on this thread.
+fn thread_track() -> u64 {
+    THREAD.with(|thread| {
+        if thread.get() == 0 {
+            thread.set(NEXT_THREAD.fetch_add(1, Ordering::Relaxed));
+        }
+        thread.get()
+    })
+}
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProfileSpan {
+    pub name: &'static str,
+    /// E.g. `"system"` or `"render"`.
+    pub category: &'static str,
+    /// Thread the span ran on, or [`GPU_TRACK`].
+    pub track: u64,
+    /// Microseconds since recording started.
+    pub start: f64,
+    pub duration: f64,
+}
+
+/// Spans recorded over one or more frames.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    spans: Vec<ProfileSpan>,
+    /// Start of each frame in microseconds since recording started.
+    frames: Vec<f64>,
+}
+
+impl Profile {
+    #[inline]
+    pub fn spans(&self) -> &[ProfileSpan] {
+        &self.spans
+    }
+
+    #[inline]
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Json readable by chrome://tracing and Perfetto.
+    pub fn to_chrome_trace(&self) -> String {
+        let mut events = Vec::with_capacity(self.spans.len() + self.frames.len() + 2);
+
+        events.push(format!(
+            r#"{{"name":"thread_name","ph":"M","pid":0,"tid":{},"args":{{"name":"GPU"}}}}"#,
+            GPU_TRACK
+        ));
+
+        self.frames.iter().enumerate().for_each(|(index, start)| {
+            events.push(format!(
+                r#"{{"name":"Frame {}","ph":"i","s":"g","pid":0,"tid":1,"ts":{:.3}}}"#,
+                index, start
+            ));
+        });
+
+        self.spans.iter().for_each(|span| {
+            events.push(format!(
+                r#"{{"name":"{}","cat":"{}","ph":"X","pid":0,"tid":{},"ts":{:.3},"dur":{:.3}}}"#,
+                escape(span.name),
+                escape(span.category),
+                span.track,
+                span.start,
+                span.duration
+            ));
+        });
+
+        let mut json = String::from("{\"traceEvents\":[\n");
+        events.iter().enumerate().for_each(|(index, event)| {
+            let separator = if index + 1 < events.len() {
+                ",\n"
+            } else {
+                "\n"
+            };
+            let _ = write!(json, "{}{}", event, separator);
+        });
+        json.push_str("],\"displayTimeUnit\":\"ms\"}\n");
+        json
+    }
+
+    #[inline]
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_chrome_trace())
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+//====================================================================
+
+struct Recorder {
+    started: Instant,
+    profile: Profile,
+    max_frames: Option<u32>,
+}
+
+impl Recorder {
+    #[inline]
+    fn micros(&self, instant: Instant) -> f64 {
+        instant
+            .saturating_duration_since(self.started)
+            .as_secs_f64()
+            * 1_000_000.
+    }
+}
+
+/// Start recording spans, replacing any recording in progress. With `max_frames` the
+/// recording stops by itself after that many frames, see [`take_finished`].
+pub fn start(max_frames: Option<u32>) {
+    *RECORDER.lock().unwrap() = Some(Recorder {
+        started: Instant::now(),
+        profile: Profile::default(),
+        max_frames,
+    });
+    RECORDING.store(true, Ordering::Release);
+}
+
+/// Stop recording, returning what was recorded.
+pub fn stop() -> Option<Profile> {
+    RECORDING.store(false, Ordering::Release);
+    RECORDER
+        .lock()
+        .unwrap()
+        .take()
+        .map(|recorder| recorder.profile)
+}
+
+#[inline]
+pub fn is_recording() -> bool {
+    RECORDING.load(Ordering::Acquire)
+}
+
+/// Recording that stopped after reaching its frame limit.
+#[inline]
+pub fn take_finished() -> Option<Profile> {
+    FINISHED.lock().unwrap().take()
+}
+
+/// Mark the start of a frame, finishing the recording once it has reached its frame limit.
+pub fn begin_frame() {
+    if !is_recording() {
+        return;
+    }
+
+    let mut recorder = RECORDER.lock().unwrap();
+    let Some(active) = recorder.as_mut() else {
+        return;
+    };
+
+    if active
+        .max_frames
+        .is_some_and(|max| active.profile.frames.len() >= max as usize)
+    {
+        RECORDING.store(false, Ordering::Release);
+        *FINISHED.lock().unwrap() = recorder.take().map(|recorder| recorder.profile);
+        return;
+    }
+
+    let now = active.micros(Instant::now());
+    active.profile.frames.push(now);
+}
+
+/// Record a span measured elsewhere, e.g. from gpu timestamps.
+pub fn record_span(
+    track: u64,
+    name: &'static str,
+    category: &'static str,
+    start: Instant,
+    duration: Duration,
+) {
+    if !is_recording() {
+        return;
+    }
+
+    if let Some(recorder) = RECORDER.lock().unwrap().as_mut() {
+        let start = recorder.micros(start);
+        recorder.profile.spans.push(ProfileSpan {
+            name,
+            category,
+            track,
+            start,
+            duration: duration.as_secs_f64() * 1_000_000.,
+        });
+    }
+}
+
+//--------------------------------------------------
+
+/// Times the code until it's dropped. Scopes inside others show nested beneath them.
+#[must_use = "the scope is recorded when dropped"]
+pub struct Scope {
+    name: &'static str,
+    category: &'static str,
+    start: Option<Instant>,
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            record_span(
+                thread_track(),
+                self.name,
+                self.category,
+                start,
+                start.elapsed(),
+            );
+        }
+    }
+}
+
+#[inline]
+pub fn scope(name: &'static str, category: &'static str) -> Scope {
+    Scope {
+        name,
+        category,
+        start: is_recording().then(Instant::now),
+    }
+}
+
+//====================================================================
@@ -0,0 +1,439 @@
+//====================================================================
+
+use std::{
+    any::{Any, TypeId},
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc, RwLock, Weak,
+    },
+};
+
+use renderer::{
+    texture::{LoadedTexture, Texture},
+    RendererState,
+};
+
+use crate::{
+    dialogue::DialogueGraph, inventory::ItemDatabase, mods::ModInfo, quests::QuestDatabase,
+    vfs::Vfs,
+};
+
+//====================================================================
+
+pub type AssetId = u64;
+
+static CURRENT_ASSET_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetStatus {
+    Loading,
+    Loaded,
+    Failed(String),
+}
+
+enum AssetSlot<T> {
+    Loading,
+    Loaded(Arc<T>),
+    Failed(String),
+}
+
+struct AssetEntry<T> {
+    id: AssetId,
+    path: Option<String>,
+    slot: RwLock<AssetSlot<T>>,
+}
+
+impl<T> AssetEntry<T> {
+    fn new(path: Option<String>, slot: AssetSlot<T>) -> Arc<Self> {
+        Arc::new(Self {
+            id: CURRENT_ASSET_ID.fetch_add(1, Ordering::Relaxed),
+            path,
+            slot: RwLock::new(slot),
+        })
+    }
+
+    fn set(&self, slot: AssetSlot<T>) {
+        *self.slot.write().unwrap() = slot;
+    }
+}
+
+//--------------------------------------------------
+
+/// Reference counted handle to an asset owned by the [`AssetServer`].
+/// The asset is freed once every handle to it has been dropped.
+pub struct Handle<T>(Arc<AssetEntry<T>>);
+
+impl<T> Clone for Handle<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0.id == other.0.id
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Handle")
+            .field("id", &self.0.id)
+            .field("path", &self.0.path)
+            .finish()
+    }
+}
+
+impl<T> Handle<T> {
+    #[inline]
+    pub fn id(&self) -> AssetId {
+        self.0.id
+    }
+
+    #[inline]
+    pub fn path(&self) -> Option<&str> {
+        self.0.path.as_deref()
+    }
+
+    /// The asset if it has finished loading.
+    pub fn get(&self) -> Option<Arc<T>> {
+        match &*self.0.slot.read().unwrap() {
+            AssetSlot::Loaded(asset) => Some(asset.clone()),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn is_loaded(&self) -> bool {
+        matches!(&*self.0.slot.read().unwrap(), AssetSlot::Loaded(_))
+    }
+
+    pub fn status(&self) -> AssetStatus {
+        match &*self.0.slot.read().unwrap() {
+            AssetSlot::Loading => AssetStatus::Loading,
+            AssetSlot::Loaded(_) => AssetStatus::Loaded,
+            AssetSlot::Failed(e) => AssetStatus::Failed(e.clone()),
+        }
+    }
+}
+
+//====================================================================
+
+type DecodeResult<D> = Result<D, String>;
+type FinishFn<T, D> = Box<dyn FnOnce(&RendererState, D) -> Result<T, String>>;
+type LoadJob = Box<dyn FnOnce() + Send>;
+
+trait PendingAsset {
+    /// Returns true once the asset is finished, successfully or not.
+    fn poll(&mut self, renderer: &RendererState) -> bool;
+}
+
+struct PendingLoad<T, D> {
+    entry: Weak<AssetEntry<T>>,
+    receiver: mpsc::Receiver<DecodeResult<D>>,
+    finish: Option<FinishFn<T, D>>,
+}
+
+impl<T, D> PendingAsset for PendingLoad<T, D> {
+    fn poll(&mut self, renderer: &RendererState) -> bool {
+        let result = match self.receiver.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return false,
+            Err(mpsc::TryRecvError::Disconnected) => Err("Loading task panicked".into()),
+        };
+
+        // Every handle was dropped while loading
+        let Some(entry) = self.entry.upgrade() else {
+            return true;
+        };
+
+        let result = result.and_then(|data| (self.finish.take().unwrap())(renderer, data));
+
+        match result {
+            Ok(asset) => {
+                log::trace!("Loaded asset {:?}", entry.path);
+                entry.set(AssetSlot::Loaded(Arc::new(asset)));
+            }
+            Err(e) => {
+                log::warn!("Failed to load asset {:?}: {}", entry.path, e);
+                entry.set(AssetSlot::Failed(e));
+            }
+        }
+
+        true
+    }
+}
+
+//====================================================================
+
+/// Central store for assets. Files are read and decoded as [`crate::tasks::TaskQueue`] tasks,
+/// then finished (e.g. uploaded to the gpu) on the main thread at the start of each frame.
+/// Loading the same path twice returns the same handle while the asset is alive.
+///
+/// Files are read through a [`Vfs`], so mounted sources such as mods can replace them.
+#[derive(Default)]
+pub struct AssetServer {
+    paths: BTreeMap<(TypeId, String), Box<dyn WeakEntry>>,
+    pending: Vec<Box<dyn PendingAsset>>,
+    /// Loads waiting to be handed to the task queue.
+    queued: Vec<LoadJob>,
+    vfs: Arc<Vfs>,
+    pub(crate) mods: Vec<ModInfo>,
+}
+
+impl AssetServer {
+    #[inline]
+    pub fn vfs(&self) -> &Vfs {
+        &self.vfs
+    }
+
+    /// Sources as currently mounted, unaffected by later changes.
+    #[inline]
+    pub(crate) fn vfs_snapshot(&self) -> Arc<Vfs> {
+        self.vfs.clone()
+    }
+
+    /// Loads already in progress keep reading from the sources mounted when they started.
+    #[inline]
+    pub fn vfs_mut(&mut self) -> &mut Vfs {
+        Arc::make_mut(&mut self.vfs)
+    }
+
+    /// Add an asset that has already been created.
+    pub fn insert<T: 'static>(&mut self, asset: T) -> Handle<T> {
+        Handle(AssetEntry::new(None, AssetSlot::Loaded(Arc::new(asset))))
+    }
+
+    /// Handle to an asset previously loaded from `path`, if it is still alive.
+    pub fn get<T: 'static>(&self, path: &str) -> Option<Handle<T>> {
+        self.paths
+            .get(&(TypeId::of::<T>(), path.to_string()))
+            .and_then(|entry| entry.as_any().downcast_ref::<Weak<AssetEntry<T>>>())
+            .and_then(|entry| entry.upgrade())
+            .map(Handle)
+    }
+
+    /// Number of assets still loading.
+    #[inline]
+    pub fn loading(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Load an asset from a file. `decode` runs as a background task with the file contents
+    /// and `finish` runs on the main thread with its output.
+    pub fn load_with<T, D>(
+        &mut self,
+        path: &str,
+        decode: impl FnOnce(Vec<u8>) -> Result<D, String> + Send + 'static,
+        finish: impl FnOnce(&RendererState, D) -> Result<T, String> + 'static,
+    ) -> Handle<T>
+    where
+        T: 'static,
+        D: Send + 'static,
+    {
+        if let Some(handle) = self.get(path) {
+            return handle;
+        }
+
+        log::trace!("Loading asset '{}'", path);
+
+        let entry = AssetEntry::new(Some(path.to_string()), AssetSlot::Loading);
+        self.paths.insert(
+            (TypeId::of::<T>(), path.to_string()),
+            Box::new(Arc::downgrade(&entry)),
+        );
+
+        let (sender, receiver) = mpsc::channel();
+        let file_path = path.to_string();
+        let vfs = self.vfs.clone();
+
+        self.queued.push(Box::new(move || {
+            let result = vfs
+                .read(&file_path)
+                .map_err(|e| e.to_string())
+                .and_then(decode);
+            let _ = sender.send(result);
+        }));
+
+        self.pending.push(Box::new(PendingLoad {
+            entry: Arc::downgrade(&entry),
+            receiver,
+            finish: Some(Box::new(finish)),
+        }));
+
+        Handle(entry)
+    }
+
+    /// Load an asset from a file immediately, blocking until it's finished.
+    pub fn load_now_with<T: 'static>(
+        &mut self,
+        path: &str,
+        load: impl FnOnce(Vec<u8>) -> Result<T, String>,
+    ) -> Handle<T> {
+        if let Some(handle) = self.get(path) {
+            if !matches!(handle.status(), AssetStatus::Loading) {
+                return handle;
+            }
+        }
+
+        let slot = match self
+            .vfs
+            .read(path)
+            .map_err(|e| e.to_string())
+            .and_then(load)
+        {
+            Ok(asset) => AssetSlot::Loaded(Arc::new(asset)),
+            Err(e) => {
+                log::warn!("Failed to load asset '{}': {}", path, e);
+                AssetSlot::Failed(e)
+            }
+        };
+
+        let entry = AssetEntry::new(Some(path.to_string()), slot);
+        self.paths.insert(
+            (TypeId::of::<T>(), path.to_string()),
+            Box::new(Arc::downgrade(&entry)),
+        );
+
+        Handle(entry)
+    }
+
+    /// Path an asset was loaded from, if it was loaded through the asset server.
+    pub fn find_path<T: 'static>(&self, asset: &Arc<T>) -> Option<&str> {
+        self.find_path_by::<T>(|loaded| std::ptr::eq(loaded, asset.as_ref()))
+    }
+
+    /// Path of the first loaded asset matching `predicate`.
+    pub fn find_path_by<T: 'static>(&self, predicate: impl Fn(&T) -> bool) -> Option<&str> {
+        let type_id = TypeId::of::<T>();
+
+        self.paths
+            .iter()
+            .filter(|((id, _), _)| *id == type_id)
+            .find(|(_, entry)| {
+                entry
+                    .as_any()
+                    .downcast_ref::<Weak<AssetEntry<T>>>()
+                    .and_then(|entry| entry.upgrade())
+                    .is_some_and(|entry| match &*entry.slot.read().unwrap() {
+                        AssetSlot::Loaded(loaded) => predicate(loaded),
+                        _ => false,
+                    })
+            })
+            .map(|((_, path), _)| path.as_str())
+    }
+
+    pub fn load_texture(&mut self, path: &str) -> Handle<LoadedTexture> {
+        let label = path.to_string();
+
+        self.load_with(path, Ok, move |renderer: &RendererState, bytes: Vec<u8>| {
+            let core = renderer.core();
+            let texture =
+                Texture::from_bytes(core.device(), core.queue(), &bytes, Some(&label), None)
+                    .map_err(|e| e.to_string())?;
+
+            Ok(renderer.load_texture(texture))
+        })
+    }
+
+    /// Load a texture with a generated mip chain and trilinear filtering, for textures seen
+    /// at a distance.
+    pub fn load_texture_with_mipmaps(&mut self, path: &str) -> Handle<LoadedTexture> {
+        let label = path.to_string();
+
+        self.load_with(path, Ok, move |renderer: &RendererState, bytes: Vec<u8>| {
+            let core = renderer.core();
+            let texture =
+                Texture::from_bytes(core.device(), core.queue(), &bytes, Some(&label), None)
+                    .map_err(|e| e.to_string())?;
+
+            Ok(renderer.load_texture_with_mipmaps(texture))
+        })
+    }
+
+    pub fn load_texture_now(
+        &mut self,
+        renderer: &RendererState,
+        path: &str,
+    ) -> Handle<LoadedTexture> {
+        self.load_now_with(path, |bytes| {
+            let core = renderer.core();
+            let texture =
+                Texture::from_bytes(core.device(), core.queue(), &bytes, Some(path), None)
+                    .map_err(|e| e.to_string())?;
+
+            Ok(renderer.load_texture(texture))
+        })
+    }
+
+    /// Load item definitions from a json file. See [`ItemDatabase`].
+    pub fn load_items(&mut self, path: &str) -> Handle<ItemDatabase> {
+        self.load_with(
+            path,
+            |bytes| ItemDatabase::from_json(&bytes).map_err(|e| e.to_string()),
+            |_, items| Ok(items),
+        )
+    }
+
+    /// Load a dialogue graph from a json file. See [`DialogueGraph`].
+    pub fn load_dialogue(&mut self, path: &str) -> Handle<DialogueGraph> {
+        self.load_with(
+            path,
+            |bytes| DialogueGraph::from_json(&bytes).map_err(|e| e.to_string()),
+            |_, dialogue| Ok(dialogue),
+        )
+    }
+
+    /// Load quest definitions from a json file. See [`QuestDatabase`].
+    pub fn load_quests(&mut self, path: &str) -> Handle<QuestDatabase> {
+        self.load_with(
+            path,
+            |bytes| QuestDatabase::from_json(&bytes).map_err(|e| e.to_string()),
+            |_, quests| Ok(quests),
+        )
+    }
+
+    fn finish_pending(&mut self, renderer: &RendererState) {
+        self.pending.retain_mut(|pending| !pending.poll(renderer));
+
+        // Forget paths whose assets have been dropped
+        self.paths.retain(|_, entry| !entry.is_dead());
+    }
+}
+
+/// Type erased weak reference to an asset, so dropped paths can be cleaned up.
+trait WeakEntry {
+    fn is_dead(&self) -> bool;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: 'static> WeakEntry for Weak<AssetEntry<T>> {
+    #[inline]
+    fn is_dead(&self) -> bool {
+        self.strong_count() == 0
+    }
+
+    #[inline]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+//====================================================================
+
+pub(crate) fn process_assets(state: &mut crate::State) {
+    std::mem::take(&mut state.assets.queued)
+        .into_iter()
+        .for_each(|job| {
+            state.tasks.spawn("Load asset", job, |_, ()| {});
+        });
+
+    state.assets.finish_pending(&state.renderer);
+}
+
+//====================================================================
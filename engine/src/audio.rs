@@ -0,0 +1,637 @@
+//====================================================================
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use common::GlobalTransform;
+
+use serde::{Deserialize, Serialize};
+use web_time::Duration;
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum AudioBus {
+    /// Applied on top of every other bus.
+    Master,
+    Music,
+    Sfx,
+    Dialog,
+}
+
+impl AudioBus {
+    pub const ALL: [AudioBus; 4] = [
+        AudioBus::Master,
+        AudioBus::Music,
+        AudioBus::Sfx,
+        AudioBus::Dialog,
+    ];
+}
+
+/// Cutoff treated as no low-pass filter at all.
+pub const LOW_PASS_DISABLED: f32 = 22_000.;
+
+/// Mix applied to a single bus by a [`MixerSnapshot`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BusMix {
+    pub volume: f32,
+    /// Low-pass cutoff in hz, e.g. for underwater or paused audio.
+    pub low_pass: Option<f32>,
+}
+
+impl Default for BusMix {
+    fn default() -> Self {
+        Self {
+            volume: 1.,
+            low_pass: None,
+        }
+    }
+}
+
+impl BusMix {
+    fn lerp(self, to: BusMix, t: f32) -> BusMix {
+        // Cutoffs are blended logarithmically so sweeps sound even
+        let from_cutoff = self.low_pass.unwrap_or(LOW_PASS_DISABLED).max(1.).ln();
+        let to_cutoff = to.low_pass.unwrap_or(LOW_PASS_DISABLED).max(1.).ln();
+        let cutoff = (from_cutoff + (to_cutoff - from_cutoff) * t).exp();
+
+        BusMix {
+            volume: self.volume + (to.volume - self.volume) * t,
+            low_pass: (cutoff < LOW_PASS_DISABLED).then_some(cutoff),
+        }
+    }
+}
+
+//--------------------------------------------------
+
+/// Named mix state, e.g. "paused" or "underwater". Buses not in the snapshot use the default mix.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MixerSnapshot {
+    pub buses: BTreeMap<AudioBus, BusMix>,
+}
+
+impl MixerSnapshot {
+    #[inline]
+    pub fn with_bus(mut self, bus: AudioBus, mix: BusMix) -> Self {
+        self.buses.insert(bus, mix);
+        self
+    }
+
+    #[inline]
+    pub fn bus(&self, bus: AudioBus) -> BusMix {
+        self.buses.get(&bus).copied().unwrap_or_default()
+    }
+
+    fn lerp(&self, to: &MixerSnapshot, t: f32) -> MixerSnapshot {
+        MixerSnapshot {
+            buses: AudioBus::ALL
+                .into_iter()
+                .map(|bus| (bus, self.bus(bus).lerp(to.bus(bus), t)))
+                .collect(),
+        }
+    }
+}
+
+//--------------------------------------------------
+
+/// User facing volumes, e.g. from an audio options menu. Serializable so it can be saved
+/// alongside other settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub volumes: BTreeMap<AudioBus, f32>,
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            volumes: AudioBus::ALL.into_iter().map(|bus| (bus, 1.)).collect(),
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    #[inline]
+    pub fn volume(&self, bus: AudioBus) -> f32 {
+        self.volumes.get(&bus).copied().unwrap_or(1.)
+    }
+
+    #[inline]
+    pub fn set_volume(&mut self, bus: AudioBus, volume: f32) {
+        self.volumes.insert(bus, volume.clamp(0., 1.));
+    }
+}
+
+//====================================================================
+
+struct SnapshotTransition {
+    from: MixerSnapshot,
+    to: MixerSnapshot,
+    duration: Duration,
+    elapsed: Duration,
+}
+
+/// Combines the user's [`AudioSettings`] with the current [`MixerSnapshot`] to give the
+/// final volume and filtering of each bus.
+#[derive(Default)]
+pub struct AudioMixer {
+    settings: AudioSettings,
+    snapshot: MixerSnapshot,
+    transition: Option<SnapshotTransition>,
+}
+
+impl AudioMixer {
+    #[inline]
+    pub fn settings(&self) -> &AudioSettings {
+        &self.settings
+    }
+
+    #[inline]
+    pub fn settings_mut(&mut self) -> &mut AudioSettings {
+        &mut self.settings
+    }
+
+    /// Current mix, part way between two snapshots while transitioning.
+    #[inline]
+    pub fn snapshot(&self) -> &MixerSnapshot {
+        &self.snapshot
+    }
+
+    #[inline]
+    pub fn is_transitioning(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    #[inline]
+    pub fn set_snapshot(&mut self, snapshot: MixerSnapshot) {
+        self.transition = None;
+        self.snapshot = snapshot;
+    }
+
+    /// Blend from the current mix to `snapshot` over `duration`.
+    pub fn transition_to(&mut self, snapshot: MixerSnapshot, duration: Duration) {
+        if duration.is_zero() {
+            self.set_snapshot(snapshot);
+            return;
+        }
+
+        self.transition = Some(SnapshotTransition {
+            from: self.snapshot.clone(),
+            to: snapshot,
+            duration,
+            elapsed: Duration::ZERO,
+        });
+    }
+
+    /// Final volume of a bus, including the master bus.
+    pub fn volume(&self, bus: AudioBus) -> f32 {
+        if self.settings.muted {
+            return 0.;
+        }
+
+        let volume = |bus| self.settings.volume(bus) * self.snapshot.bus(bus).volume;
+
+        match bus {
+            AudioBus::Master => volume(AudioBus::Master),
+            _ => volume(bus) * volume(AudioBus::Master),
+        }
+    }
+
+    /// Final low-pass cutoff of a bus, the lowest of the bus and master cutoffs.
+    pub fn low_pass(&self, bus: AudioBus) -> Option<f32> {
+        let master = self.snapshot.bus(AudioBus::Master).low_pass;
+        let bus = self.snapshot.bus(bus).low_pass;
+
+        match (master, bus) {
+            (Some(master), Some(bus)) => Some(master.min(bus)),
+            (master, bus) => master.or(bus),
+        }
+    }
+
+    fn tick(&mut self, delta: Duration) {
+        let Some(transition) = &mut self.transition else {
+            return;
+        };
+
+        transition.elapsed += delta;
+        let t = transition.elapsed.as_secs_f32() / transition.duration.as_secs_f32();
+
+        if t >= 1. {
+            self.snapshot = self.transition.take().unwrap().to;
+        } else {
+            self.snapshot = transition.from.lerp(&transition.to, t);
+        }
+    }
+}
+
+//====================================================================
+
+pub type VoiceId = u64;
+
+/// How a voice should currently sound, after bus volumes and spatial attenuation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoiceParams {
+    pub volume: f32,
+    /// -1 for fully left, 1 for fully right.
+    pub pan: f32,
+    /// Playback speed, also changing pitch.
+    pub pitch: f32,
+    pub low_pass: Option<f32>,
+    pub looping: bool,
+}
+
+impl Default for VoiceParams {
+    fn default() -> Self {
+        Self {
+            volume: 1.,
+            pan: 0.,
+            pitch: 1.,
+            low_pass: None,
+            looping: false,
+        }
+    }
+}
+
+/// Plays the voices requested through [`Audio`], e.g. by wrapping rodio or kira. Sounds are
+/// named by whatever the backend understands, usually asset paths. The engine doesn't ship
+/// a backend, so games must provide one with [`Audio::set_backend`] to hear anything.
+pub trait AudioBackend {
+    /// Start a voice `start` into the sound. Returns false if the sound couldn't be played.
+    fn play(&mut self, voice: VoiceId, sound: &str, start: Duration, params: &VoiceParams) -> bool;
+    /// Called every frame a voice is playing.
+    fn update(&mut self, voice: VoiceId, params: &VoiceParams);
+    fn stop(&mut self, voice: VoiceId);
+    /// Voices that are no longer playing are forgotten, and their sources stop.
+    fn is_playing(&self, voice: VoiceId) -> bool;
+}
+
+//--------------------------------------------------
+
+/// Plays a sound from the entity's [`GlobalTransform`], panned and attenuated relative to
+/// the [`AudioListener`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioSource {
+    pub sound: String,
+    pub bus: AudioBus,
+    pub volume: f32,
+    pub pitch: f32,
+    pub looping: bool,
+    /// Distance the sound is heard at full volume within.
+    pub min_distance: f32,
+    /// Distance the sound can no longer be heard past.
+    pub max_distance: f32,
+    /// Start playing as soon as possible. Cleared once the sound finishes unless looping.
+    pub playing: bool,
+    voice: Option<VoiceId>,
+}
+
+impl AudioSource {
+    /// Source playing `sound` once on the sfx bus as soon as it's spawned.
+    #[inline]
+    pub fn new(sound: impl Into<String>) -> Self {
+        Self {
+            sound: sound.into(),
+            bus: AudioBus::Sfx,
+            volume: 1.,
+            pitch: 1.,
+            looping: false,
+            min_distance: 1.,
+            max_distance: 30.,
+            playing: true,
+            voice: None,
+        }
+    }
+
+    #[inline]
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    #[inline]
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    #[inline]
+    pub fn stop(&mut self) {
+        self.playing = false;
+    }
+
+    #[inline]
+    pub fn voice(&self) -> Option<VoiceId> {
+        self.voice
+    }
+
+    /// Volume and pan heard by a listener at `listener`.
+    fn spatialize(&self, position: glam::Vec3, listener: &glam::Affine3A) -> (f32, f32) {
+        let offset = position - glam::Vec3::from(listener.translation);
+        let distance = offset.length();
+
+        let range = (self.max_distance - self.min_distance).max(f32::EPSILON);
+        let attenuation = 1. - ((distance - self.min_distance) / range).clamp(0., 1.);
+
+        let right = listener
+            .transform_vector3(glam::Vec3::X)
+            .normalize_or_zero();
+        let pan = offset.normalize_or_zero().dot(right);
+
+        (attenuation * attenuation, pan)
+    }
+}
+
+/// Ears of the scene, usually on the camera. The main camera is used without one.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AudioListener;
+
+//--------------------------------------------------
+
+struct Voice {
+    bus: AudioBus,
+    volume: f32,
+    pitch: f32,
+    looping: bool,
+    /// Attenuation and pan from an [`AudioSource`].
+    spatial: Option<(f32, f32)>,
+}
+
+impl Voice {
+    /// Final params with the mixer applied.
+    fn params(&self, mixer: &AudioMixer) -> VoiceParams {
+        let (attenuation, pan) = self.spatial.unwrap_or((1., 0.));
+
+        VoiceParams {
+            volume: self.volume * attenuation * mixer.volume(self.bus),
+            pan,
+            pitch: self.pitch,
+            low_pass: mixer.low_pass(self.bus),
+            looping: self.looping,
+        }
+    }
+}
+
+/// Sounds playing through the [`AudioBackend`]. Without a backend nothing is heard, but
+/// voices are still tracked so games run the same.
+#[derive(Default)]
+pub struct Audio {
+    backend: Option<Box<dyn AudioBackend>>,
+    /// Whether playing without a backend was already warned about.
+    warned_silent: bool,
+    voices: BTreeMap<VoiceId, Voice>,
+    music: BTreeMap<String, VoiceId>,
+    sources: BTreeSet<VoiceId>,
+    next_voice: VoiceId,
+}
+
+impl Audio {
+    #[inline]
+    pub fn set_backend(&mut self, backend: impl AudioBackend + 'static) {
+        self.backend = Some(Box::new(backend));
+    }
+
+    #[inline]
+    pub fn has_backend(&self) -> bool {
+        self.backend.is_some()
+    }
+
+    /// Play a sound once, without any panning. Bus volumes are applied from the next tick.
+    #[inline]
+    pub fn play(&mut self, sound: &str, bus: AudioBus) -> Option<VoiceId> {
+        self.play_with(sound, bus, 1., 1., false)
+    }
+
+    pub fn play_with(
+        &mut self,
+        sound: &str,
+        bus: AudioBus,
+        volume: f32,
+        pitch: f32,
+        looping: bool,
+    ) -> Option<VoiceId> {
+        let voice = Voice {
+            bus,
+            volume,
+            pitch,
+            looping,
+            spatial: None,
+        };
+        let params = VoiceParams {
+            volume,
+            pitch,
+            looping,
+            ..Default::default()
+        };
+
+        self.start(sound, Duration::ZERO, voice, &params)
+    }
+
+    #[inline]
+    pub fn stop(&mut self, voice: VoiceId) {
+        if self.voices.remove(&voice).is_some() {
+            if let Some(backend) = &mut self.backend {
+                backend.stop(voice);
+            }
+        }
+    }
+
+    #[inline]
+    pub fn is_playing(&self, voice: VoiceId) -> bool {
+        self.voices.contains_key(&voice)
+    }
+
+    /// Change the volume of a voice, before bus volumes are applied.
+    #[inline]
+    pub fn set_volume(&mut self, voice: VoiceId, volume: f32) {
+        if let Some(voice) = self.voices.get_mut(&voice) {
+            voice.volume = volume;
+        }
+    }
+
+    fn start(
+        &mut self,
+        sound: &str,
+        start: Duration,
+        voice: Voice,
+        params: &VoiceParams,
+    ) -> Option<VoiceId> {
+        let id = self.next_voice;
+        self.next_voice += 1;
+
+        let played = match &mut self.backend {
+            Some(backend) => backend.play(id, sound, start, params),
+            None => {
+                if !self.warned_silent {
+                    log::warn!("Playing sounds without an audio backend - nothing will be heard");
+                    self.warned_silent = true;
+                }
+                true
+            }
+        };
+
+        if !played {
+            log::warn!("Unable to play sound '{}'", sound);
+            return None;
+        }
+
+        self.voices.insert(id, voice);
+        Some(id)
+    }
+}
+
+//====================================================================
+
+pub(crate) fn process_audio(state: &mut crate::State) {
+    let listener = state
+        .world
+        .query::<(&AudioListener, &GlobalTransform)>()
+        .iter()
+        .next()
+        .map(|(_, (_, transform))| transform.0)
+        .or_else(|| {
+            let camera = state.main_camera()?;
+            let transform = state.world.get::<&GlobalTransform>(camera).ok()?;
+            Some(transform.0)
+        })
+        .unwrap_or(glam::Affine3A::IDENTITY);
+
+    let audio = &mut state.audio;
+    let mixer = &state.mixer;
+
+    // Forget finished voices
+    if let Some(backend) = &audio.backend {
+        audio.voices.retain(|id, _| backend.is_playing(*id));
+    }
+
+    //--------------------------------------------------
+    // Music
+
+    let music = state
+        .music
+        .voices()
+        .map(|voice| (voice.track().name.clone(), voice))
+        .collect::<BTreeMap<_, _>>();
+
+    let stopped = audio
+        .music
+        .iter()
+        .filter(|(name, id)| !music.contains_key(*name) || !audio.voices.contains_key(*id))
+        .map(|(name, id)| (name.clone(), *id))
+        .collect::<Vec<_>>();
+
+    stopped.into_iter().for_each(|(name, id)| {
+        audio.music.remove(&name);
+        audio.stop(id);
+    });
+
+    music.into_iter().for_each(|(name, music_voice)| {
+        if let Some(voice) = audio
+            .music
+            .get(&name)
+            .and_then(|id| audio.voices.get_mut(id))
+        {
+            voice.volume = music_voice.gain();
+            return;
+        }
+
+        let voice = Voice {
+            bus: AudioBus::Music,
+            volume: music_voice.gain(),
+            pitch: 1.,
+            looping: music_voice.track().loop_start.is_some(),
+            spatial: None,
+        };
+        let params = voice.params(mixer);
+
+        if let Some(id) = audio.start(&name, music_voice.position(), voice, &params) {
+            audio.music.insert(name, id);
+        }
+    });
+
+    //--------------------------------------------------
+    // Sources
+
+    audio
+        .voices
+        .values_mut()
+        .for_each(|voice| voice.spatial = None);
+
+    state
+        .world
+        .query_mut::<(&mut AudioSource, &GlobalTransform)>()
+        .into_iter()
+        .for_each(|(_, (source, transform))| {
+            // Finished or stopped
+            if let Some(id) = source.voice {
+                if !audio.voices.contains_key(&id) {
+                    source.voice = None;
+                    source.playing = false;
+                } else if !source.playing {
+                    audio.stop(id);
+                    source.voice = None;
+                }
+            }
+
+            if !source.playing {
+                return;
+            }
+
+            let (attenuation, pan) = source.spatialize(transform.translation(), &listener);
+            let voice = Voice {
+                bus: source.bus,
+                volume: source.volume,
+                pitch: source.pitch,
+                looping: source.looping,
+                spatial: Some((attenuation, pan)),
+            };
+
+            match source.voice.and_then(|id| audio.voices.get_mut(&id)) {
+                Some(existing) => *existing = voice,
+                None => {
+                    let params = voice.params(mixer);
+                    source.voice = audio.start(&source.sound, Duration::ZERO, voice, &params);
+                    source.playing = source.voice.is_some();
+                }
+            }
+        });
+
+    // Voices whose sources were despawned or removed
+    let orphaned = audio
+        .sources
+        .iter()
+        .filter(|id| {
+            audio
+                .voices
+                .get(*id)
+                .is_some_and(|voice| voice.spatial.is_none())
+        })
+        .copied()
+        .collect::<Vec<_>>();
+
+    orphaned.into_iter().for_each(|id| audio.stop(id));
+
+    audio.sources = audio
+        .voices
+        .iter()
+        .filter(|(_, voice)| voice.spatial.is_some())
+        .map(|(id, _)| *id)
+        .collect();
+
+    //--------------------------------------------------
+
+    if let Some(backend) = &mut audio.backend {
+        audio
+            .voices
+            .iter()
+            .for_each(|(id, voice)| backend.update(*id, &voice.params(mixer)));
+    }
+}
+
+//====================================================================
+
+pub(crate) fn process_mixer(state: &mut crate::State) {
+    let delta = *state.time.delta();
+    state.mixer.tick(delta);
+}
+
+//====================================================================
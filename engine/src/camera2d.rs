@@ -0,0 +1,220 @@
+//====================================================================
+
+use common::{GlobalTransform, Transform};
+use hecs::Entity;
+use renderer::camera::OrthographicCamera;
+
+use crate::{spatial::Parent, State};
+
+//====================================================================
+
+/// Rectangle an orthographic [`CameraFollow`] camera is confined to while its target is
+/// inside. Centered on the entity's [`GlobalTransform`], ignoring rotation and scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraZone {
+    pub half_extents: glam::Vec2,
+    /// Overlapping zones with a higher priority win.
+    pub priority: i32,
+    /// Seconds taken to ease from the previous zone's bounds into this one.
+    pub blend_time: f32,
+}
+
+impl CameraZone {
+    #[inline]
+    pub fn new(half_extents: glam::Vec2) -> Self {
+        Self {
+            half_extents,
+            priority: 0,
+            blend_time: 0.5,
+        }
+    }
+
+    #[inline]
+    fn contains(&self, center: glam::Vec2, point: glam::Vec2) -> bool {
+        (point - center).abs().cmple(self.half_extents).all()
+    }
+}
+
+//--------------------------------------------------
+
+/// Move an orthographic camera towards `target` on the xy plane, clamped to the
+/// [`CameraZone`] the target is in. Leaving every zone keeps the last one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraFollow {
+    pub target: Entity,
+    pub offset: glam::Vec2,
+    /// How quickly the camera catches up. Zero snaps to the target.
+    pub speed: f32,
+
+    zone: Option<Entity>,
+    bounds: Option<Bounds>,
+    blend_from: Option<Bounds>,
+    blend: f32,
+}
+
+impl CameraFollow {
+    #[inline]
+    pub fn new(target: Entity) -> Self {
+        Self {
+            target,
+            offset: glam::Vec2::ZERO,
+            speed: 5.,
+            zone: None,
+            bounds: None,
+            blend_from: None,
+            blend: 1.,
+        }
+    }
+
+    /// Zone the camera is currently confined to.
+    #[inline]
+    pub fn zone(&self) -> Option<Entity> {
+        self.zone
+    }
+
+    /// Whether the camera is still easing between zones.
+    #[inline]
+    pub fn blending(&self) -> bool {
+        self.blend < 1.
+    }
+}
+
+/// Range the camera's center can move in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bounds {
+    min: glam::Vec2,
+    max: glam::Vec2,
+}
+
+impl Bounds {
+    /// Keep the camera's view inside the zone, centering on it when the view is larger.
+    fn new(center: glam::Vec2, zone: &CameraZone, camera: &OrthographicCamera) -> Self {
+        let zone_min = center - zone.half_extents;
+        let zone_max = center + zone.half_extents;
+
+        let min = zone_min - glam::vec2(camera.left, camera.bottom);
+        let max = zone_max - glam::vec2(camera.right, camera.top);
+
+        let middle = (min + max) / 2.;
+        let inverted = min.cmpgt(max);
+
+        Self {
+            min: glam::Vec2::select(inverted, middle, min),
+            max: glam::Vec2::select(inverted, middle, max),
+        }
+    }
+
+    #[inline]
+    fn lerp(&self, to: &Bounds, t: f32) -> Bounds {
+        Bounds {
+            min: self.min.lerp(to.min, t),
+            max: self.max.lerp(to.max, t),
+        }
+    }
+
+    #[inline]
+    fn clamp(&self, point: glam::Vec2) -> glam::Vec2 {
+        point.clamp(self.min, self.max)
+    }
+}
+
+//====================================================================
+
+pub(crate) fn process_camera_follow(state: &mut State) {
+    let delta = state.time.delta_seconds();
+    let world = &mut state.world;
+
+    let zones = world
+        .query::<(&CameraZone, &GlobalTransform)>()
+        .iter()
+        .map(|(entity, (zone, transform))| (entity, *zone, transform.translation().truncate()))
+        .collect::<Vec<_>>();
+
+    let targets = world
+        .query::<&CameraFollow>()
+        .iter()
+        .filter_map(|(entity, follow)| {
+            let target = world.get::<&GlobalTransform>(follow.target).ok()?;
+            Some((entity, target.translation().truncate() + follow.offset))
+        })
+        .collect::<Vec<_>>();
+
+    targets.into_iter().for_each(|(entity, target)| {
+        let Ok((follow, camera, transform, global, parent)) = world.query_one_mut::<(
+            &mut CameraFollow,
+            &OrthographicCamera,
+            &mut Transform,
+            &mut GlobalTransform,
+            Option<&Parent>,
+        )>(entity) else {
+            return;
+        };
+
+        let entered = zones
+            .iter()
+            .filter(|(_, zone, center)| zone.contains(*center, target))
+            .max_by_key(|(_, zone, _)| zone.priority);
+
+        if let Some((zone, _, _)) = entered {
+            if follow.zone != Some(*zone) {
+                follow.blend_from = follow.bounds;
+                follow.blend = 0.;
+                follow.zone = Some(*zone);
+            }
+        }
+
+        let zone = follow
+            .zone
+            .and_then(|zone| zones.iter().find(|(entity, _, _)| *entity == zone));
+
+        let destination = match zone {
+            Some((_, zone, center)) => {
+                follow.blend = match zone.blend_time > 0. {
+                    true => (follow.blend + delta / zone.blend_time).min(1.),
+                    false => 1.,
+                };
+
+                let zone_bounds = Bounds::new(*center, zone, camera);
+                let bounds = match follow.blend_from {
+                    Some(from) if follow.blending() => {
+                        // Smoothstep so the camera eases in and out of the transition
+                        let t = follow.blend * follow.blend * (3. - 2. * follow.blend);
+                        from.lerp(&zone_bounds, t)
+                    }
+                    _ => zone_bounds,
+                };
+
+                follow.bounds = Some(bounds);
+                bounds.clamp(target)
+            }
+
+            // Not entered a zone yet, or it was despawned
+            None => {
+                follow.zone = None;
+                follow.bounds = None;
+                follow.blend = 1.;
+                target
+            }
+        };
+
+        move_towards(follow, transform, destination, delta);
+
+        // Transforms have already been propagated this frame
+        if parent.is_none() {
+            global.0.translation = transform.translation.into();
+        }
+    });
+}
+
+fn move_towards(follow: &CameraFollow, transform: &mut Transform, target: glam::Vec2, delta: f32) {
+    let current = transform.translation.truncate();
+
+    let position = match follow.speed > 0. {
+        true => current.lerp(target, 1. - (-follow.speed * delta).exp()),
+        false => target,
+    };
+
+    transform.translation = position.extend(transform.translation.z);
+}
+
+//====================================================================
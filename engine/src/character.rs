@@ -0,0 +1,282 @@
+//====================================================================
+
+use common::Transform;
+use hecs::Entity;
+
+use crate::{
+    collision::{self, Capsule, Contact},
+    State,
+};
+
+//====================================================================
+
+const MAX_RESOLVE_ITERATIONS: usize = 4;
+/// Distance below the capsule checked for ground while not moving down.
+const GROUND_PROBE: f32 = 0.05;
+
+/// Kinematic capsule moved with move-and-slide against [`collision::Collider`]s.
+/// Set the horizontal part of `velocity` each update, gravity is applied automatically.
+/// Works in 2D as well by keeping movement and colliders on a plane.
+#[derive(Debug, Clone)]
+pub struct CharacterController {
+    pub velocity: glam::Vec3,
+    pub radius: f32,
+    /// Distance between the centers of the two capsule spheres.
+    pub height: f32,
+    pub up: glam::Vec3,
+    /// Tallest ledge that can be walked onto without jumping.
+    pub step_height: f32,
+    /// Steepest walkable slope, in radians.
+    pub slope_limit: f32,
+    /// Acceleration along `-up`.
+    pub gravity: f32,
+
+    grounded: bool,
+    ceiling: bool,
+    wall: bool,
+    ground_normal: Option<glam::Vec3>,
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self {
+            velocity: glam::Vec3::ZERO,
+            radius: 0.4,
+            height: 1.,
+            up: glam::Vec3::Y,
+            step_height: 0.3,
+            slope_limit: 45_f32.to_radians(),
+            gravity: 9.81,
+            grounded: false,
+            ceiling: false,
+            wall: false,
+            ground_normal: None,
+        }
+    }
+}
+
+impl CharacterController {
+    #[inline]
+    pub fn new(radius: f32, height: f32) -> Self {
+        Self {
+            radius,
+            height,
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    pub fn grounded(&self) -> bool {
+        self.grounded
+    }
+
+    #[inline]
+    pub fn hit_ceiling(&self) -> bool {
+        self.ceiling
+    }
+
+    #[inline]
+    pub fn hit_wall(&self) -> bool {
+        self.wall
+    }
+
+    #[inline]
+    pub fn ground_normal(&self) -> Option<glam::Vec3> {
+        self.ground_normal
+    }
+
+    /// Set the velocity along `up`, e.g. to jump.
+    #[inline]
+    pub fn set_vertical_velocity(&mut self, speed: f32) {
+        self.velocity = self.velocity - self.up * self.velocity.dot(self.up) + self.up * speed;
+    }
+
+    #[inline]
+    fn capsule(&self, position: glam::Vec3) -> Capsule {
+        Capsule::new(position, self.up, self.height / 2., self.radius)
+    }
+
+    #[inline]
+    fn is_ground(&self, normal: glam::Vec3) -> bool {
+        normal.dot(self.up) >= self.slope_limit.cos()
+    }
+
+    #[inline]
+    fn is_ceiling(&self, normal: glam::Vec3) -> bool {
+        normal.dot(self.up) <= -self.slope_limit.cos()
+    }
+}
+
+//====================================================================
+
+struct MoveResult {
+    position: glam::Vec3,
+    contacts: Vec<Contact>,
+}
+
+impl MoveResult {
+    fn flags(&self, controller: &CharacterController) -> (bool, bool, bool) {
+        self.contacts
+            .iter()
+            .fold((false, false, false), |acc, contact| {
+                (
+                    acc.0 || controller.is_ground(contact.normal),
+                    acc.1 || controller.is_ceiling(contact.normal),
+                    acc.2
+                        || !(controller.is_ground(contact.normal)
+                            || controller.is_ceiling(contact.normal)),
+                )
+            })
+    }
+}
+
+/// Move the capsule in steps no larger than its radius, pushing it out of anything it overlaps.
+fn move_and_slide(
+    state: &State,
+    entity: Entity,
+    controller: &CharacterController,
+    start: glam::Vec3,
+    motion: glam::Vec3,
+) -> MoveResult {
+    let steps = (motion.length() / (controller.radius * 0.5).max(0.01))
+        .ceil()
+        .max(1.) as u32;
+    let step = motion / steps as f32;
+
+    let mut result = MoveResult {
+        position: start,
+        contacts: Vec::new(),
+    };
+
+    (0..steps).for_each(|_| {
+        result.position += step;
+
+        (0..MAX_RESOLVE_ITERATIONS).all(|_| {
+            let contacts = collision::capsule_contacts(
+                &state.world,
+                controller.capsule(result.position),
+                Some(entity),
+            );
+
+            contacts.iter().for_each(|contact| {
+                result.position += contact.normal * contact.depth;
+            });
+
+            let resolved = contacts.is_empty();
+            result.contacts.extend(contacts);
+            !resolved
+        });
+    });
+
+    result
+}
+
+pub(crate) fn process_controllers(state: &mut State, delta: f32) {
+    let controllers = state
+        .world
+        .query_mut::<(&CharacterController, &Transform)>()
+        .into_iter()
+        .map(|(entity, (controller, transform))| {
+            (entity, controller.clone(), transform.translation)
+        })
+        .collect::<Vec<_>>();
+
+    let results = controllers
+        .into_iter()
+        .map(|(entity, mut controller, position)| {
+            let up = controller.up;
+
+            if !controller.grounded || controller.velocity.dot(up) > 0. {
+                controller.velocity -= up * controller.gravity * delta;
+            }
+
+            let motion = controller.velocity * delta;
+            let mut result = move_and_slide(state, entity, &controller, position, motion);
+            let (_, _, wall) = result.flags(&controller);
+
+            // Retry blocked movement from higher up to climb ledges
+            let horizontal = motion - up * motion.dot(up);
+            if wall
+                && controller.grounded
+                && controller.step_height > 0.
+                && horizontal != glam::Vec3::ZERO
+            {
+                let raised = move_and_slide(
+                    state,
+                    entity,
+                    &controller,
+                    position,
+                    up * controller.step_height,
+                );
+                let across =
+                    move_and_slide(state, entity, &controller, raised.position, horizontal);
+                let lowered = move_and_slide(
+                    state,
+                    entity,
+                    &controller,
+                    across.position,
+                    -up * (controller.step_height + GROUND_PROBE),
+                );
+
+                let progress = |moved: glam::Vec3| {
+                    let moved = moved - position;
+                    (moved - up * moved.dot(up)).length_squared()
+                };
+                let (stepped_ground, _, _) = lowered.flags(&controller);
+
+                if stepped_ground && progress(lowered.position) > progress(result.position) {
+                    result = lowered;
+                }
+            }
+
+            // Probe for ground so standing still doesn't flicker between grounded and falling
+            let probe = move_and_slide(
+                state,
+                entity,
+                &controller,
+                result.position,
+                -up * GROUND_PROBE,
+            );
+            let ground_normal = probe
+                .contacts
+                .iter()
+                .chain(result.contacts.iter())
+                .map(|contact| contact.normal)
+                .find(|normal| controller.is_ground(*normal));
+
+            let (_, ceiling, wall) = result.flags(&controller);
+            controller.grounded = ground_normal.is_some();
+            controller.ceiling = ceiling;
+            controller.wall = wall;
+            controller.ground_normal = ground_normal;
+
+            // Remove velocity going into anything that was hit
+            result.contacts.iter().for_each(|contact| {
+                let into = controller.velocity.dot(contact.normal);
+                if into < 0. {
+                    controller.velocity -= contact.normal * into;
+                }
+            });
+
+            if controller.grounded && controller.velocity.dot(up) < 0. {
+                controller.set_vertical_velocity(0.);
+            }
+
+            (entity, controller, result.position)
+        })
+        .collect::<Vec<_>>();
+
+    results
+        .into_iter()
+        .for_each(|(entity, new_controller, position)| {
+            if let Ok((controller, transform)) = state
+                .world
+                .query_one_mut::<(&mut CharacterController, &mut Transform)>(entity)
+            {
+                *controller = new_controller;
+                transform.translation = position;
+            }
+        });
+}
+
+//====================================================================
@@ -0,0 +1,373 @@
+//====================================================================
+
+use common::{forces::Forces, GlobalTransform};
+
+use crate::{
+    collision::{push_out_sphere, Collider, CollisionLayers, ALL_LAYERS},
+    lod::UpdateLod,
+    parallel::{Access, SystemContext},
+};
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy)]
+struct ClothParticle {
+    position: glam::Vec3,
+    previous: glam::Vec3,
+    /// Position relative to the entity when laid flat.
+    rest: glam::Vec3,
+    pinned: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ClothConstraint {
+    a: usize,
+    b: usize,
+    length: f32,
+}
+
+/// Grid of verlet particles hanging from the entity, simulated every fixed update.
+/// Laid out along +X and down -Y from the entity's origin, with pinned particles following
+/// the entity's [`GlobalTransform`]. Particles are pushed out of [`Collider`]s.
+#[derive(Debug, Clone)]
+pub struct Cloth {
+    /// Replaced by the [`Forces`] gravity override when set.
+    pub gravity: glam::Vec3,
+    /// Velocity of the air around the cloth, on top of the [`Forces`] wind.
+    pub wind: glam::Vec3,
+    /// How strongly the air pushes the cloth's faces.
+    pub drag: f32,
+    /// Velocity lost each step, from 0 to 1.
+    pub damping: f32,
+    /// How much of each constraint is corrected per iteration, from 0 to 1.
+    pub stiffness: f32,
+    pub iterations: u32,
+    /// Distance particles are kept from colliders.
+    pub thickness: f32,
+    pub collision_layers: CollisionLayers,
+
+    columns: u32,
+    rows: u32,
+    particles: Vec<ClothParticle>,
+    constraints: Vec<ClothConstraint>,
+    placed: bool,
+}
+
+impl Cloth {
+    /// Cloth of `columns` by `rows` particles, `spacing` apart, with the top row pinned.
+    pub fn new(columns: u32, rows: u32, spacing: f32) -> Self {
+        let columns = columns.max(2);
+        let rows = rows.max(2);
+
+        let particles = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let rest = glam::vec3(column as f32 * spacing, -(row as f32) * spacing, 0.);
+                ClothParticle {
+                    position: rest,
+                    previous: rest,
+                    rest,
+                    pinned: row == 0,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let index = |column: u32, row: u32| (row * columns + column) as usize;
+        let mut constraints = Vec::new();
+
+        (0..rows).for_each(|row| {
+            (0..columns).for_each(|column| {
+                // Structural, shear and bend constraints
+                let neighbours = [(1, 0), (0, 1), (1, 1), (-1, 1), (2, 0), (0, 2)];
+
+                neighbours.into_iter().for_each(|(x, y)| {
+                    let (other_column, other_row) = (column as i32 + x, row as i32 + y);
+                    if other_column < 0
+                        || other_column >= columns as i32
+                        || other_row >= rows as i32
+                    {
+                        return;
+                    }
+
+                    let (a, b) = (
+                        index(column, row),
+                        index(other_column as u32, other_row as u32),
+                    );
+
+                    constraints.push(ClothConstraint {
+                        a,
+                        b,
+                        length: particles[a].rest.distance(particles[b].rest),
+                    });
+                });
+            });
+        });
+
+        Self {
+            gravity: glam::vec3(0., -9.81, 0.),
+            wind: glam::Vec3::ZERO,
+            drag: 0.5,
+            damping: 0.01,
+            stiffness: 1.,
+            iterations: 8,
+            thickness: 0.02,
+            collision_layers: ALL_LAYERS,
+            columns,
+            rows,
+            particles,
+            constraints,
+            placed: false,
+        }
+    }
+
+    #[inline]
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    #[inline]
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    #[inline]
+    pub fn with_wind(mut self, wind: glam::Vec3) -> Self {
+        self.wind = wind;
+        self
+    }
+
+    /// Pin or unpin the particle at `column`, `row`. Pinned particles follow the entity.
+    pub fn set_pinned(&mut self, column: u32, row: u32, pinned: bool) {
+        if column < self.columns && row < self.rows {
+            self.particles[(row * self.columns + column) as usize].pinned = pinned;
+        }
+    }
+
+    #[inline]
+    pub fn is_pinned(&self, column: u32, row: u32) -> bool {
+        column < self.columns
+            && self
+                .particles
+                .get((row * self.columns + column) as usize)
+                .is_some_and(|particle| particle.pinned)
+    }
+
+    /// Unpin every particle besides the given columns of the top row, e.g. the corners of a cape.
+    pub fn pin_only(&mut self, columns: &[u32]) {
+        let width = self.columns;
+        self.particles
+            .iter_mut()
+            .enumerate()
+            .for_each(|(index, particle)| {
+                let (column, row) = (index as u32 % width, index as u32 / width);
+                particle.pinned = row == 0 && columns.contains(&column);
+            });
+    }
+
+    /// Lay the cloth flat again on the next step.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.placed = false;
+    }
+
+    /// World space particle positions, row by row.
+    #[inline]
+    pub fn positions(&self) -> impl Iterator<Item = glam::Vec3> + '_ {
+        self.particles.iter().map(|particle| particle.position)
+    }
+
+    /// Smooth normal of each particle, matching [`Cloth::indices`] winding.
+    pub fn normals(&self) -> Vec<glam::Vec3> {
+        let mut normals = vec![glam::Vec3::ZERO; self.particles.len()];
+
+        self.indices().chunks_exact(3).for_each(|face| {
+            let [a, b, c] = [face[0], face[1], face[2]].map(|index| index as usize);
+            let normal = (self.particles[b].position - self.particles[a].position)
+                .cross(self.particles[c].position - self.particles[a].position);
+
+            [a, b, c]
+                .into_iter()
+                .for_each(|index| normals[index] += normal);
+        });
+
+        normals
+            .into_iter()
+            .map(|normal| normal.normalize_or(glam::Vec3::NEG_Z))
+            .collect()
+    }
+
+    /// Texture coordinates of each particle, with (0, 0) at the top left.
+    pub fn uvs(&self) -> impl Iterator<Item = glam::Vec2> + '_ {
+        let size = glam::vec2((self.columns - 1) as f32, (self.rows - 1) as f32);
+        (0..self.particles.len() as u32).map(move |index| {
+            glam::vec2((index % self.columns) as f32, (index / self.columns) as f32) / size
+        })
+    }
+
+    /// Triangle list covering the grid, facing -Z when laid flat.
+    pub fn indices(&self) -> Vec<u32> {
+        (0..self.rows - 1)
+            .flat_map(|row| (0..self.columns - 1).map(move |column| (column, row)))
+            .flat_map(|(column, row)| {
+                let top_left = row * self.columns + column;
+                let bottom_left = top_left + self.columns;
+                [
+                    top_left,
+                    top_left + 1,
+                    bottom_left,
+                    bottom_left,
+                    top_left + 1,
+                    bottom_left + 1,
+                ]
+            })
+            .collect()
+    }
+
+    //--------------------------------------------------
+
+    fn step(
+        &mut self,
+        transform: &glam::Affine3A,
+        colliders: &[(Collider, glam::Vec3)],
+        forces: &Forces,
+        delta: f32,
+    ) {
+        if !self.placed {
+            self.particles.iter_mut().for_each(|particle| {
+                particle.position = transform.transform_point3(particle.rest);
+                particle.previous = particle.position;
+            });
+            self.placed = true;
+        }
+
+        let gravity = forces.gravity_or(self.gravity);
+        let mut accelerations = self
+            .particles
+            .iter()
+            .map(|particle| gravity + forces.acceleration_at(particle.position))
+            .collect::<Vec<_>>();
+
+        // Wind pushes along each face's normal, by how much it blows into the face
+        if self.drag > 0. {
+            let inverse_delta = 1. / delta.max(f32::EPSILON);
+
+            self.indices().chunks_exact(3).for_each(|face| {
+                let [a, b, c] =
+                    [face[0], face[1], face[2]].map(|index| &self.particles[index as usize]);
+
+                let Some(normal) = (b.position - a.position)
+                    .cross(c.position - a.position)
+                    .try_normalize()
+                else {
+                    return;
+                };
+
+                let velocity = (a.position - a.previous + b.position - b.previous + c.position
+                    - c.previous)
+                    * inverse_delta
+                    / 3.;
+                let wind = self.wind + forces.wind_at((a.position + b.position + c.position) / 3.);
+                let force = normal * normal.dot(wind - velocity) * self.drag;
+
+                face.iter()
+                    .for_each(|index| accelerations[*index as usize] += force / 3.);
+            });
+        }
+
+        let retain = 1. - self.damping.clamp(0., 1.);
+
+        self.particles
+            .iter_mut()
+            .zip(accelerations)
+            .for_each(|(particle, acceleration)| match particle.pinned {
+                true => {
+                    particle.position = transform.transform_point3(particle.rest);
+                    particle.previous = particle.position;
+                }
+                false => {
+                    let velocity = (particle.position - particle.previous) * retain;
+                    particle.previous = particle.position;
+                    particle.position += velocity + acceleration * delta * delta;
+                }
+            });
+
+        let stiffness = self.stiffness.clamp(0., 1.);
+
+        (0..self.iterations.max(1)).for_each(|_| {
+            self.constraints.iter().for_each(|constraint| {
+                let (a, b) = (self.particles[constraint.a], self.particles[constraint.b]);
+                let offset = b.position - a.position;
+                let distance = offset.length();
+                if distance <= f32::EPSILON {
+                    return;
+                }
+
+                let correction = offset * ((distance - constraint.length) / distance) * stiffness;
+
+                match (a.pinned, b.pinned) {
+                    (true, true) => {}
+                    (true, false) => self.particles[constraint.b].position -= correction,
+                    (false, true) => self.particles[constraint.a].position += correction,
+                    (false, false) => {
+                        self.particles[constraint.a].position += correction / 2.;
+                        self.particles[constraint.b].position -= correction / 2.;
+                    }
+                }
+            });
+
+            self.particles
+                .iter_mut()
+                .filter(|particle| !particle.pinned)
+                .for_each(|particle| {
+                    particle.position =
+                        push_out_sphere(particle.position, self.thickness, colliders);
+                });
+        });
+    }
+}
+
+//====================================================================
+
+#[inline]
+pub(crate) fn cloth_access() -> Access {
+    Access::new()
+        .write::<Cloth>()
+        .read::<Collider>()
+        .read::<GlobalTransform>()
+        .read::<UpdateLod>()
+}
+
+pub(crate) fn process_cloth(ctx: &mut SystemContext) {
+    let lod = ctx.update_lod();
+    let delta = ctx.delta_seconds();
+    let world = ctx.world();
+    let forces = ctx.forces();
+
+    let colliders = world
+        .query::<(&Collider, &GlobalTransform)>()
+        .without::<&Cloth>()
+        .iter()
+        .map(|(_, (collider, transform))| (*collider, transform.translation() + collider.offset))
+        .collect::<Vec<_>>();
+
+    world
+        .query::<(&mut Cloth, &GlobalTransform, Option<&UpdateLod>)>()
+        .iter()
+        .filter(|(entity, (.., update_lod))| lod.due(*entity, *update_lod))
+        .for_each(|(_, (cloth, transform, update_lod))| {
+            let colliders = colliders
+                .iter()
+                .filter(|(collider, _)| collider.layers & cloth.collision_layers != 0)
+                .copied()
+                .collect::<Vec<_>>();
+
+            cloth.step(
+                &transform.0,
+                &colliders,
+                forces,
+                lod.delta(update_lod, delta),
+            );
+        });
+}
+
+//====================================================================
@@ -0,0 +1,422 @@
+//====================================================================
+
+use common::GlobalTransform;
+use hecs::{Entity, World};
+
+//====================================================================
+
+/// Axis aligned collision shape. Rotation and scale of the entity are ignored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColliderShape {
+    Cuboid {
+        half_extents: glam::Vec3,
+    },
+    Sphere {
+        radius: f32,
+    },
+    /// Capsule aligned to an axis, with `half_height` measured between the two sphere centers.
+    Capsule {
+        half_height: f32,
+        radius: f32,
+        axis: glam::Vec3,
+    },
+}
+
+/// Bit mask of collision layers. Queries only hit colliders sharing a layer with their mask.
+pub type CollisionLayers = u32;
+
+pub const ALL_LAYERS: CollisionLayers = u32::MAX;
+
+/// Static collision geometry positioned by the entity's [`GlobalTransform`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Collider {
+    pub shape: ColliderShape,
+    pub offset: glam::Vec3,
+    pub layers: CollisionLayers,
+}
+
+impl Collider {
+    #[inline]
+    pub fn new(shape: ColliderShape) -> Self {
+        Self {
+            shape,
+            offset: glam::Vec3::ZERO,
+            layers: ALL_LAYERS,
+        }
+    }
+
+    #[inline]
+    pub fn cuboid(half_extents: glam::Vec3) -> Self {
+        Self::new(ColliderShape::Cuboid { half_extents })
+    }
+
+    #[inline]
+    pub fn sphere(radius: f32) -> Self {
+        Self::new(ColliderShape::Sphere { radius })
+    }
+
+    #[inline]
+    pub fn with_offset(mut self, offset: glam::Vec3) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    #[inline]
+    pub fn with_layers(mut self, layers: CollisionLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+}
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    pub entity: Entity,
+    /// Direction to push the queried capsule out of the collider.
+    pub normal: glam::Vec3,
+    pub depth: f32,
+}
+
+/// Capsule used for queries, from `start` to `end` with a radius.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capsule {
+    pub start: glam::Vec3,
+    pub end: glam::Vec3,
+    pub radius: f32,
+}
+
+impl Capsule {
+    #[inline]
+    pub fn new(center: glam::Vec3, axis: glam::Vec3, half_height: f32, radius: f32) -> Self {
+        Self {
+            start: center - axis * half_height,
+            end: center + axis * half_height,
+            radius,
+        }
+    }
+
+    #[inline]
+    pub fn sphere(center: glam::Vec3, radius: f32) -> Self {
+        Self::new(center, glam::Vec3::ZERO, 0., radius)
+    }
+
+    /// Closest point on the capsule's segment to `point`.
+    pub fn closest_point(&self, point: glam::Vec3) -> glam::Vec3 {
+        let segment = self.end - self.start;
+        let length_squared = segment.length_squared();
+
+        if length_squared <= f32::EPSILON {
+            return self.start;
+        }
+
+        let t = ((point - self.start).dot(segment) / length_squared).clamp(0., 1.);
+        self.start + segment * t
+    }
+
+    fn at(&self, t: f32) -> glam::Vec3 {
+        self.start.lerp(self.end, t)
+    }
+}
+
+//--------------------------------------------------
+
+/// Every collider overlapping `capsule`, excluding `exclude`.
+pub fn capsule_contacts(world: &World, capsule: Capsule, exclude: Option<Entity>) -> Vec<Contact> {
+    world
+        .query::<(&Collider, &GlobalTransform)>()
+        .iter()
+        .filter(|(entity, _)| Some(*entity) != exclude)
+        .filter_map(|(entity, (collider, transform))| {
+            let center = transform.translation() + collider.offset;
+            let (normal, depth) = capsule_penetration(capsule, collider.shape, center)?;
+
+            Some(Contact {
+                entity,
+                normal,
+                depth,
+            })
+        })
+        .collect()
+}
+
+/// Push a sphere out of every `(collider, center)` it overlaps, for simulated particles.
+pub(crate) fn push_out_sphere(
+    position: glam::Vec3,
+    radius: f32,
+    colliders: &[(Collider, glam::Vec3)],
+) -> glam::Vec3 {
+    colliders.iter().fold(
+        position,
+        |position, (collider, center)| match capsule_penetration(
+            Capsule::sphere(position, radius),
+            collider.shape,
+            *center,
+        ) {
+            Some((normal, depth)) => position + normal * depth,
+            None => position,
+        },
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub entity: Entity,
+    pub point: glam::Vec3,
+    pub normal: glam::Vec3,
+    pub distance: f32,
+}
+
+/// Closest collider on `mask` layers hit by a ray, ignoring `exclude`.
+/// `direction` doesn't need to be normalized.
+pub fn raycast(
+    world: &World,
+    origin: glam::Vec3,
+    direction: glam::Vec3,
+    max_distance: f32,
+    mask: CollisionLayers,
+    exclude: &[Entity],
+) -> Option<RayHit> {
+    let direction = direction.try_normalize()?;
+
+    world
+        .query::<(&Collider, &GlobalTransform)>()
+        .iter()
+        .filter(|(entity, (collider, _))| !exclude.contains(entity) && collider.layers & mask != 0)
+        .filter_map(|(entity, (collider, transform))| {
+            let center = transform.translation() + collider.offset;
+            let (distance, normal) = ray_intersection(origin, direction, collider.shape, center)?;
+
+            (distance <= max_distance).then_some(RayHit {
+                entity,
+                point: origin + direction * distance,
+                normal,
+                distance,
+            })
+        })
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
+
+/// Distance along a normalized ray to the shape and the surface normal there.
+/// Rays starting inside a shape hit it immediately.
+pub fn ray_intersection(
+    origin: glam::Vec3,
+    direction: glam::Vec3,
+    shape: ColliderShape,
+    center: glam::Vec3,
+) -> Option<(f32, glam::Vec3)> {
+    match shape {
+        ColliderShape::Cuboid { half_extents } => ray_cuboid(
+            origin,
+            direction,
+            center - half_extents,
+            center + half_extents,
+        ),
+        ColliderShape::Sphere { radius } => {
+            ray_capsule(origin, direction, Capsule::sphere(center, radius))
+        }
+        ColliderShape::Capsule {
+            half_height,
+            radius,
+            axis,
+        } => ray_capsule(
+            origin,
+            direction,
+            Capsule::new(center, axis, half_height, radius),
+        ),
+    }
+}
+
+fn ray_cuboid(
+    origin: glam::Vec3,
+    direction: glam::Vec3,
+    min: glam::Vec3,
+    max: glam::Vec3,
+) -> Option<(f32, glam::Vec3)> {
+    let inverse = direction.recip();
+    let t0 = (min - origin) * inverse;
+    let t1 = (max - origin) * inverse;
+
+    let near = t0.min(t1);
+    let far = t0.max(t1);
+
+    // NaN from flat cuboids parallel to the ray are ignored by min/max_element
+    let t_near = near.max_element();
+    let t_far = far.min_element();
+
+    if t_near > t_far || t_far < 0. {
+        return None;
+    }
+
+    if t_near < 0. {
+        return Some((0., -direction));
+    }
+
+    let normal = match () {
+        _ if t_near == near.x => glam::Vec3::X * -direction.x.signum(),
+        _ if t_near == near.y => glam::Vec3::Y * -direction.y.signum(),
+        _ => glam::Vec3::Z * -direction.z.signum(),
+    };
+
+    Some((t_near, normal))
+}
+
+fn ray_capsule(
+    origin: glam::Vec3,
+    direction: glam::Vec3,
+    capsule: Capsule,
+) -> Option<(f32, glam::Vec3)> {
+    let normal_at = |distance: f32| {
+        let point = origin + direction * distance;
+        (point - capsule.closest_point(point)).normalize_or(-direction)
+    };
+
+    if origin.distance(capsule.closest_point(origin)) <= capsule.radius {
+        return Some((0., -direction));
+    }
+
+    let ray_sphere = |center: glam::Vec3| {
+        let offset = origin - center;
+        let b = offset.dot(direction);
+        let c = offset.length_squared() - capsule.radius * capsule.radius;
+        let h = b * b - c;
+        (h >= 0.).then(|| -b - h.sqrt()).filter(|t| *t >= 0.)
+    };
+
+    let axis = capsule.end - capsule.start;
+    let axis_length_squared = axis.length_squared();
+
+    if axis_length_squared <= f32::EPSILON {
+        return ray_sphere(capsule.start).map(|t| (t, normal_at(t)));
+    }
+
+    // Cylinder between the two end spheres
+    let offset = origin - capsule.start;
+    let axis_direction = axis.dot(direction);
+    let axis_offset = axis.dot(offset);
+
+    let a = axis_length_squared - axis_direction * axis_direction;
+    let b = axis_length_squared * direction.dot(offset) - axis_offset * axis_direction;
+    let c = axis_length_squared * offset.length_squared()
+        - axis_offset * axis_offset
+        - capsule.radius * capsule.radius * axis_length_squared;
+    let h = b * b - a * c;
+
+    let cylinder = (h >= 0. && a > f32::EPSILON)
+        .then(|| (-b - h.sqrt()) / a)
+        .filter(|t| {
+            let along = axis_offset + t * axis_direction;
+            *t >= 0. && along > 0. && along < axis_length_squared
+        });
+
+    let t = [cylinder, ray_sphere(capsule.start), ray_sphere(capsule.end)]
+        .into_iter()
+        .flatten()
+        .min_by(|a, b| a.total_cmp(b))?;
+
+    Some((t, normal_at(t)))
+}
+
+//--------------------------------------------------
+
+/// Returns the push out direction and depth if the capsule overlaps the shape.
+pub fn capsule_penetration(
+    capsule: Capsule,
+    shape: ColliderShape,
+    center: glam::Vec3,
+) -> Option<(glam::Vec3, f32)> {
+    match shape {
+        ColliderShape::Sphere { radius } => {
+            segment_penetration(capsule, Capsule::sphere(center, radius))
+        }
+        ColliderShape::Capsule {
+            half_height,
+            radius,
+            axis,
+        } => segment_penetration(capsule, Capsule::new(center, axis, half_height, radius)),
+        ColliderShape::Cuboid { half_extents } => {
+            cuboid_penetration(capsule, center - half_extents, center + half_extents)
+        }
+    }
+}
+
+fn segment_penetration(capsule: Capsule, other: Capsule) -> Option<(glam::Vec3, f32)> {
+    // Distance between two segments is convex along either one
+    let t = minimize(|t| {
+        let point = capsule.at(t);
+        point.distance_squared(other.closest_point(point))
+    });
+
+    let point = capsule.at(t);
+    let other_point = other.closest_point(point);
+
+    push_out(point - other_point, capsule.radius + other.radius)
+}
+
+fn cuboid_penetration(
+    capsule: Capsule,
+    min: glam::Vec3,
+    max: glam::Vec3,
+) -> Option<(glam::Vec3, f32)> {
+    let t = minimize(|t| {
+        let point = capsule.at(t);
+        point.distance_squared(point.clamp(min, max))
+    });
+
+    let point = capsule.at(t);
+    let closest = point.clamp(min, max);
+
+    if point != closest {
+        return push_out(point - closest, capsule.radius);
+    }
+
+    // Segment is inside the cuboid, push out along the shallowest axis
+    let to_min = point - min;
+    let to_max = max - point;
+
+    [
+        (glam::Vec3::NEG_X, to_min.x),
+        (glam::Vec3::X, to_max.x),
+        (glam::Vec3::NEG_Y, to_min.y),
+        (glam::Vec3::Y, to_max.y),
+        (glam::Vec3::NEG_Z, to_min.z),
+        (glam::Vec3::Z, to_max.z),
+    ]
+    .into_iter()
+    // Flat cuboids (e.g. 2D colliders) can't be pushed out along their flat axis
+    .filter(|(normal, _)| (max - min).dot(normal.abs()) > 0.)
+    .min_by(|a, b| a.1.total_cmp(&b.1))
+    .map(|(normal, depth)| (normal, depth + capsule.radius))
+}
+
+fn push_out(offset: glam::Vec3, radius: f32) -> Option<(glam::Vec3, f32)> {
+    let distance = offset.length();
+
+    if distance >= radius {
+        return None;
+    }
+
+    match distance > f32::EPSILON {
+        true => Some((offset / distance, radius - distance)),
+        false => Some((glam::Vec3::Y, radius)),
+    }
+}
+
+/// Ternary search for the minimum of a convex function over 0-1.
+fn minimize(f: impl Fn(f32) -> f32) -> f32 {
+    let (mut low, mut high) = (0., 1.);
+
+    (0..24).for_each(|_| {
+        let a = low + (high - low) / 3.;
+        let b = high - (high - low) / 3.;
+
+        match f(a) <= f(b) {
+            true => high = b,
+            false => low = a,
+        }
+    });
+
+    (low + high) / 2.
+}
+
+//====================================================================
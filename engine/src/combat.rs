@@ -0,0 +1,243 @@
+//====================================================================
+
+use common::Transform;
+use hecs::Entity;
+
+use crate::{
+    collision::{self, CollisionLayers, RayHit, ALL_LAYERS},
+    State,
+};
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProjectileResponse {
+    /// Despawn the projectile on hit.
+    Despawn,
+    /// Leave the projectile at the hit point and stop simulating it.
+    Stop,
+    /// Reflect off the surface, keeping `restitution` of the speed.
+    Bounce { restitution: f32 },
+}
+
+/// Moves along its velocity each frame, sweeping a ray over the movement so fast
+/// projectiles can't pass through thin colliders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projectile {
+    pub velocity: glam::Vec3,
+    /// Acceleration along -Y.
+    pub gravity: f32,
+    /// Seconds left before the projectile despawns.
+    pub lifetime: f32,
+    pub mask: CollisionLayers,
+    pub response: ProjectileResponse,
+    /// Entity that fired the projectile, which it never hits.
+    pub owner: Option<Entity>,
+    stopped: bool,
+}
+
+impl Projectile {
+    #[inline]
+    pub fn new(velocity: glam::Vec3, lifetime: f32) -> Self {
+        Self {
+            velocity,
+            gravity: 0.,
+            lifetime,
+            mask: ALL_LAYERS,
+            response: ProjectileResponse::Despawn,
+            owner: None,
+            stopped: false,
+        }
+    }
+
+    #[inline]
+    pub fn with_gravity(mut self, gravity: f32) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    #[inline]
+    pub fn with_mask(mut self, mask: CollisionLayers) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    #[inline]
+    pub fn with_response(mut self, response: ProjectileResponse) -> Self {
+        self.response = response;
+        self
+    }
+
+    #[inline]
+    pub fn with_owner(mut self, owner: Entity) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    #[inline]
+    pub fn stopped(&self) -> bool {
+        self.stopped
+    }
+}
+
+//--------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HitEvent {
+    /// Projectile entity, or `None` for hitscans.
+    pub source: Option<Entity>,
+    pub entity: Entity,
+    pub point: glam::Vec3,
+    pub normal: glam::Vec3,
+}
+
+impl HitEvent {
+    #[inline]
+    fn new(source: Option<Entity>, hit: RayHit) -> Self {
+        Self {
+            source,
+            entity: hit.entity,
+            point: hit.point,
+            normal: hit.normal,
+        }
+    }
+}
+
+/// Projectile and hitscan hits, cleared at the start of each frame.
+#[derive(Default)]
+pub struct HitEvents {
+    events: Vec<HitEvent>,
+}
+
+impl HitEvents {
+    #[inline]
+    pub fn events(&self) -> &[HitEvent] {
+        &self.events
+    }
+
+    /// Hits on a single entity.
+    #[inline]
+    pub fn hits_on(&self, entity: Entity) -> impl Iterator<Item = &HitEvent> {
+        self.events
+            .iter()
+            .filter(move |event| event.entity == entity)
+    }
+}
+
+//====================================================================
+
+impl State {
+    /// Instant ray hit against colliders on `mask` layers, recorded as a [`HitEvent`].
+    pub fn hitscan(
+        &mut self,
+        origin: glam::Vec3,
+        direction: glam::Vec3,
+        max_distance: f32,
+        mask: CollisionLayers,
+        exclude: &[Entity],
+    ) -> Option<RayHit> {
+        let hit = collision::raycast(&self.world, origin, direction, max_distance, mask, exclude)?;
+        self.hits.events.push(HitEvent::new(None, hit));
+        Some(hit)
+    }
+
+    #[inline]
+    pub fn hits(&self) -> &HitEvents {
+        &self.hits
+    }
+}
+
+//====================================================================
+
+const MAX_BOUNCES: usize = 4;
+
+pub(crate) fn clear_hits(state: &mut State) {
+    state.hits.events.clear();
+}
+
+pub(crate) fn process_projectiles(state: &mut State) {
+    let delta = state.time.delta_seconds();
+
+    let projectiles = state
+        .world
+        .query_mut::<(&Projectile, &Transform)>()
+        .into_iter()
+        .filter(|(_, (projectile, _))| !projectile.stopped)
+        .map(|(entity, (projectile, transform))| (entity, *projectile, transform.translation))
+        .collect::<Vec<_>>();
+
+    let mut despawn = Vec::new();
+
+    let results = projectiles
+        .into_iter()
+        .filter_map(|(entity, mut projectile, mut position)| {
+            projectile.lifetime -= delta;
+            if projectile.lifetime <= 0. {
+                despawn.push(entity);
+                return None;
+            }
+
+            projectile.velocity.y -= projectile.gravity * delta;
+            let mut remaining = projectile.velocity.length() * delta;
+
+            for _ in 0..MAX_BOUNCES {
+                let exclude = [Some(entity), projectile.owner];
+                let exclude = exclude.iter().flatten().copied().collect::<Vec<_>>();
+
+                let Some(hit) = collision::raycast(
+                    &state.world,
+                    position,
+                    projectile.velocity,
+                    remaining,
+                    projectile.mask,
+                    &exclude,
+                ) else {
+                    position += projectile.velocity.normalize_or_zero() * remaining;
+                    break;
+                };
+
+                state.hits.events.push(HitEvent::new(Some(entity), hit));
+                remaining -= hit.distance;
+
+                match projectile.response {
+                    ProjectileResponse::Despawn => {
+                        despawn.push(entity);
+                        return None;
+                    }
+                    ProjectileResponse::Stop => {
+                        position = hit.point;
+                        projectile.stopped = true;
+                        projectile.velocity = glam::Vec3::ZERO;
+                        break;
+                    }
+                    ProjectileResponse::Bounce { restitution } => {
+                        // Nudge off the surface so the next sweep doesn't start inside it
+                        position = hit.point + hit.normal * 0.001;
+                        projectile.velocity = projectile.velocity.reflect(hit.normal) * restitution;
+                        remaining *= restitution;
+                    }
+                }
+            }
+
+            Some((entity, projectile, position))
+        })
+        .collect::<Vec<_>>();
+
+    results
+        .into_iter()
+        .for_each(|(entity, new_projectile, position)| {
+            if let Ok((projectile, transform)) = state
+                .world
+                .query_one_mut::<(&mut Projectile, &mut Transform)>(entity)
+            {
+                *projectile = new_projectile;
+                transform.translation = position;
+            }
+        });
+
+    despawn.into_iter().for_each(|entity| {
+        state.world.despawn(entity).ok();
+    });
+}
+
+//====================================================================
@@ -0,0 +1,193 @@
+//====================================================================
+
+use std::path::PathBuf;
+
+use crate::tools::KeyCode;
+
+//====================================================================
+
+/// Keys toggling renderer debug features at runtime. Enabled by default in debug builds.
+#[derive(Debug, Clone)]
+pub struct DebugBindings {
+    pub enabled: bool,
+    /// Pipelines toggled by each key, named by type, e.g. `"ModelRenderer"`.
+    pub pipelines: Vec<(KeyCode, String)>,
+    /// Held with `Digit1` to `Digit9` to toggle that pipeline in draw order.
+    pub pipeline_modifier: Option<KeyCode>,
+    /// Cycle the [`renderer::debug::DebugOverride`] mode.
+    pub cycle_mode: Vec<KeyCode>,
+    /// Flip [`DebugToggles::hud_visible`], along with `hud_pipelines`.
+    pub hud: Vec<KeyCode>,
+    pub hud_pipelines: Vec<String>,
+    /// Show or hide debug lines.
+    pub gizmos: Vec<KeyCode>,
+    /// Start recording a profile, then stop and save it to `profile_path` when pressed again.
+    pub profile: Vec<KeyCode>,
+    pub profile_path: PathBuf,
+    /// Start or stop logging [`renderer::stats::BatchingHint`]s.
+    pub batching_hints: Vec<KeyCode>,
+}
+
+impl Default for DebugBindings {
+    fn default() -> Self {
+        Self {
+            enabled: cfg!(debug_assertions),
+            pipelines: Vec::new(),
+            pipeline_modifier: Some(KeyCode::AltLeft),
+            cycle_mode: vec![KeyCode::F2],
+            hud: vec![KeyCode::F1],
+            hud_pipelines: Vec::new(),
+            gizmos: vec![KeyCode::F3],
+            profile: vec![KeyCode::F4],
+            profile_path: PathBuf::from("profile.json"),
+            batching_hints: vec![KeyCode::F5],
+        }
+    }
+}
+
+/// Current state of the toggles without a renderer setting of their own.
+#[derive(Debug, Clone)]
+pub struct DebugToggles {
+    hud_visible: bool,
+}
+
+impl Default for DebugToggles {
+    fn default() -> Self {
+        Self { hud_visible: true }
+    }
+}
+
+impl DebugToggles {
+    /// Whether the app should draw its HUD.
+    #[inline]
+    pub fn hud_visible(&self) -> bool {
+        self.hud_visible
+    }
+
+    #[inline]
+    pub fn set_hud_visible(&mut self, visible: bool) {
+        self.hud_visible = visible;
+    }
+}
+
+//====================================================================
+
+const DIGITS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+fn toggle_pipeline(state: &mut crate::State, name: &str) {
+    match state.renderer.pipeline_enabled(name) {
+        Some(enabled) => {
+            state.renderer.set_pipeline_enabled(name, !enabled);
+            log::info!(
+                "{} {}",
+                match enabled {
+                    true => "Disabled",
+                    false => "Enabled",
+                },
+                name
+            );
+        }
+        None => log::warn!("No pipeline named {} to toggle", name),
+    }
+}
+
+pub(crate) fn process_debug_keys(state: &mut crate::State) {
+    if !state.debug_bindings.enabled {
+        return;
+    }
+
+    let bindings = &state.debug_bindings;
+    let just_pressed = |keys: &[KeyCode]| keys.iter().any(|key| state.keys.just_pressed(*key));
+
+    let mut toggled = bindings
+        .pipelines
+        .iter()
+        .filter(|(key, _)| state.keys.just_pressed(*key))
+        .map(|(_, name)| name.clone())
+        .collect::<Vec<_>>();
+
+    if let Some(modifier) = bindings.pipeline_modifier {
+        if state.keys.pressed(modifier) {
+            let names = state.renderer.pipelines().collect::<Vec<_>>();
+
+            toggled.extend(
+                DIGITS
+                    .iter()
+                    .zip(names)
+                    .filter(|(key, _)| state.keys.just_pressed(**key))
+                    .map(|(_, (name, _))| name.to_string()),
+            );
+        }
+    }
+
+    let cycle_mode = just_pressed(&bindings.cycle_mode);
+    let toggle_hud = just_pressed(&bindings.hud);
+    let toggle_gizmos = just_pressed(&bindings.gizmos);
+    let toggle_profile = just_pressed(&bindings.profile);
+    let toggle_hints = just_pressed(&bindings.batching_hints);
+
+    if toggle_profile {
+        match state.stop_profile() {
+            Some(profile) => match profile.save(&state.debug_bindings.profile_path) {
+                Ok(()) => log::info!(
+                    "Saved profile of {} frames to {:?}",
+                    profile.frame_count(),
+                    state.debug_bindings.profile_path
+                ),
+                Err(e) => log::error!("Unable to save profile: {}", e),
+            },
+            None => {
+                log::info!("Recording profile");
+                state.start_profile();
+            }
+        }
+    }
+
+    if toggle_hud {
+        state.debug_toggles.hud_visible = !state.debug_toggles.hud_visible;
+        log::info!("HUD visible: {}", state.debug_toggles.hud_visible);
+
+        let visible = state.debug_toggles.hud_visible;
+        let hud_pipelines = state.debug_bindings.hud_pipelines.clone();
+        hud_pipelines.iter().for_each(|name| {
+            if !state.renderer.set_pipeline_enabled(name, visible) {
+                log::warn!("No HUD pipeline named {}", name);
+            }
+        });
+    }
+
+    toggled.iter().for_each(|name| toggle_pipeline(state, name));
+
+    if cycle_mode || toggle_gizmos || toggle_hints {
+        let mut settings = *state.renderer.debug_settings();
+
+        if cycle_mode {
+            settings.mode = settings.mode.next();
+            log::info!("Debug mode: {:?}", settings.mode);
+        }
+
+        if toggle_gizmos {
+            settings.hide_lines = !settings.hide_lines;
+            log::info!("Debug lines visible: {}", !settings.hide_lines);
+        }
+
+        if toggle_hints {
+            settings.log_batching_hints = !settings.log_batching_hints;
+            log::info!("Logging batching hints: {}", settings.log_batching_hints);
+        }
+
+        state.renderer.set_debug_settings(settings);
+    }
+}
+
+//====================================================================
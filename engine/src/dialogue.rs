@@ -0,0 +1,327 @@
+//====================================================================
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::State;
+
+//====================================================================
+
+pub type NodeId = String;
+
+/// Branching conversation loaded from json, usually through
+/// [`crate::assets::AssetServer::load_dialogue`].
+///
+/// ```json
+/// {
+///     "start": "greet",
+///     "nodes": {
+///         "greet": {
+///             "speaker": "Guard",
+///             "text": "Halt!",
+///             "choices": [
+///                 { "text": "Show pass", "next": "pass", "condition": "has_pass" },
+///                 { "text": "Leave" }
+///             ]
+///         },
+///         "pass": { "speaker": "Guard", "text": "Go ahead.", "events": ["open_gate"] }
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DialogueGraph {
+    pub start: NodeId,
+    pub nodes: BTreeMap<NodeId, DialogueNode>,
+}
+
+impl DialogueGraph {
+    #[inline]
+    pub fn from_json(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    #[inline]
+    pub fn node(&self, id: &str) -> Option<&DialogueNode> {
+        self.nodes.get(id)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DialogueNode {
+    pub speaker: Option<String>,
+    pub text: String,
+    /// Player choices. Nodes without choices continue to `next` when advanced.
+    pub choices: Vec<DialogueChoice>,
+    /// Following node. The dialogue ends when there isn't one.
+    pub next: Option<NodeId>,
+    /// Events sent when the node is entered.
+    pub events: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DialogueChoice {
+    pub text: String,
+    /// Following node. The dialogue ends when there isn't one.
+    pub next: Option<NodeId>,
+    /// Name of a condition registered with [`DialogueRunner::add_condition`]
+    /// that must pass for the choice to be shown. Prefix with `!` to negate.
+    pub condition: Option<String>,
+    /// Events sent when the choice is picked.
+    pub events: Vec<String>,
+}
+
+//====================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DialogueEvent {
+    Started,
+    /// A node was entered.
+    Node(NodeId),
+    /// An event named by a node or choice.
+    Event(String),
+    Ended,
+}
+
+enum DialogueAction {
+    Start(Arc<DialogueGraph>, Option<Entity>),
+    Advance,
+    Choose(usize),
+    Stop,
+}
+
+struct ActiveDialogue {
+    graph: Arc<DialogueGraph>,
+    node: NodeId,
+    /// Indices of choices whose conditions passed.
+    available: Vec<usize>,
+    speaker: Option<Entity>,
+}
+
+type DialogueCondition = Box<dyn Fn(&World, Option<Entity>) -> bool>;
+
+/// Runs a single [`DialogueGraph`] at a time. Actions are applied after `App::update`,
+/// which is also when choice conditions are checked against the world.
+#[derive(Default)]
+pub struct DialogueRunner {
+    active: Option<ActiveDialogue>,
+    actions: Vec<DialogueAction>,
+    conditions: BTreeMap<String, DialogueCondition>,
+    events: Vec<DialogueEvent>,
+}
+
+impl DialogueRunner {
+    /// Start a dialogue, replacing any that is running. `speaker` is passed to conditions.
+    #[inline]
+    pub fn start(&mut self, graph: Arc<DialogueGraph>, speaker: Option<Entity>) {
+        self.actions.push(DialogueAction::Start(graph, speaker));
+    }
+
+    /// Continue past a node without choices.
+    #[inline]
+    pub fn advance(&mut self) {
+        self.actions.push(DialogueAction::Advance);
+    }
+
+    /// Pick one of the [`DialogueRunner::choices`] by index.
+    #[inline]
+    pub fn choose(&mut self, index: usize) {
+        self.actions.push(DialogueAction::Choose(index));
+    }
+
+    #[inline]
+    pub fn stop(&mut self) {
+        self.actions.push(DialogueAction::Stop);
+    }
+
+    #[inline]
+    pub fn add_condition(
+        &mut self,
+        name: impl Into<String>,
+        condition: impl Fn(&World, Option<Entity>) -> bool + 'static,
+    ) {
+        self.conditions.insert(name.into(), Box::new(condition));
+    }
+
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    #[inline]
+    pub fn current_id(&self) -> Option<&str> {
+        self.active.as_ref().map(|active| active.node.as_str())
+    }
+
+    #[inline]
+    pub fn current(&self) -> Option<&DialogueNode> {
+        let active = self.active.as_ref()?;
+        active.graph.node(&active.node)
+    }
+
+    #[inline]
+    pub fn speaker(&self) -> Option<Entity> {
+        self.active.as_ref()?.speaker
+    }
+
+    /// Choices of the current node whose conditions passed.
+    pub fn choices(&self) -> Vec<&DialogueChoice> {
+        let (Some(active), Some(node)) = (self.active.as_ref(), self.current()) else {
+            return Vec::new();
+        };
+
+        active
+            .available
+            .iter()
+            .filter_map(|index| node.choices.get(*index))
+            .collect()
+    }
+
+    /// Events from the last frame.
+    #[inline]
+    pub fn events(&self) -> &[DialogueEvent] {
+        &self.events
+    }
+
+    /// Whether an event with `name` was sent last frame.
+    #[inline]
+    pub fn event_sent(&self, name: &str) -> bool {
+        self.events
+            .iter()
+            .any(|event| matches!(event, DialogueEvent::Event(sent) if sent == name))
+    }
+
+    fn check(&self, world: &World, speaker: Option<Entity>, condition: &str) -> bool {
+        let (negate, name) = match condition.strip_prefix('!') {
+            Some(name) => (true, name),
+            None => (false, condition),
+        };
+
+        let passed = match self.conditions.get(name) {
+            Some(condition) => condition(world, speaker),
+            None => {
+                log::warn!("Unknown dialogue condition '{}'", name);
+                false
+            }
+        };
+
+        passed != negate
+    }
+
+    fn enter(&mut self, world: &World, graph: Arc<DialogueGraph>, node: Option<NodeId>) {
+        let speaker = self.active.as_ref().and_then(|active| active.speaker);
+
+        let Some((id, node)) = node.and_then(|id| {
+            let node = graph.node(&id).cloned();
+            if node.is_none() {
+                log::warn!("Dialogue node '{}' doesn't exist", id);
+            }
+            Some(id).zip(node)
+        }) else {
+            self.end();
+            return;
+        };
+
+        let available = node
+            .choices
+            .iter()
+            .enumerate()
+            .filter(|(_, choice)| {
+                choice
+                    .condition
+                    .as_ref()
+                    .is_none_or(|condition| self.check(world, speaker, condition))
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        self.events.push(DialogueEvent::Node(id.clone()));
+        self.events
+            .extend(node.events.into_iter().map(DialogueEvent::Event));
+
+        self.active = Some(ActiveDialogue {
+            graph,
+            node: id,
+            available,
+            speaker,
+        });
+    }
+
+    fn end(&mut self) {
+        if self.active.take().is_some() {
+            self.events.push(DialogueEvent::Ended);
+        }
+    }
+
+    fn apply(&mut self, world: &World, action: DialogueAction) {
+        match action {
+            DialogueAction::Start(graph, speaker) => {
+                self.end();
+                self.events.push(DialogueEvent::Started);
+
+                let start = graph.start.clone();
+                self.active = Some(ActiveDialogue {
+                    graph: graph.clone(),
+                    node: start.clone(),
+                    available: Vec::new(),
+                    speaker,
+                });
+                self.enter(world, graph, Some(start));
+            }
+
+            DialogueAction::Advance => {
+                let Some(active) = &self.active else {
+                    return;
+                };
+                let graph = active.graph.clone();
+                let Some(node) = graph.node(&active.node) else {
+                    return;
+                };
+
+                if !active.available.is_empty() {
+                    log::trace!("Dialogue node '{}' is waiting for a choice", active.node);
+                    return;
+                }
+
+                self.enter(world, graph.clone(), node.next.clone());
+            }
+
+            DialogueAction::Choose(index) => {
+                let Some(active) = &self.active else {
+                    return;
+                };
+                let graph = active.graph.clone();
+                let Some(choice) = active
+                    .available
+                    .get(index)
+                    .and_then(|index| graph.node(&active.node)?.choices.get(*index))
+                else {
+                    log::warn!("Dialogue choice {} isn't available", index);
+                    return;
+                };
+
+                self.events
+                    .extend(choice.events.iter().cloned().map(DialogueEvent::Event));
+                self.enter(world, graph.clone(), choice.next.clone());
+            }
+
+            DialogueAction::Stop => self.end(),
+        }
+    }
+}
+
+//====================================================================
+
+pub(crate) fn process_dialogue(state: &mut State) {
+    let dialogue = &mut state.dialogue;
+    dialogue.events.clear();
+
+    std::mem::take(&mut dialogue.actions)
+        .into_iter()
+        .for_each(|action| dialogue.apply(&state.world, action));
+}
+
+//====================================================================
@@ -0,0 +1,165 @@
+//====================================================================
+
+use std::{
+    any::{Any, TypeId},
+    collections::BTreeMap,
+    marker::PhantomData,
+};
+
+//====================================================================
+
+/// Queue of events of one type. Events are kept for the tick they're sent in and the one
+/// after, so every system sees them once whether it runs before or after the sender.
+pub struct Events<T> {
+    previous: Vec<T>,
+    current: Vec<T>,
+    /// Id of the first event in `previous`.
+    start: u64,
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new(),
+            start: 0,
+        }
+    }
+}
+
+impl<T> Events<T> {
+    #[inline]
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+    }
+
+    /// Reader that only sees events sent after it was created.
+    #[inline]
+    pub fn reader(&self) -> EventReader<T> {
+        EventReader {
+            cursor: self.end(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Events `reader` hasn't read yet, moving it past them.
+    pub fn read<'a>(&'a self, reader: &mut EventReader<T>) -> impl Iterator<Item = &'a T> {
+        let skip = reader.cursor.saturating_sub(self.start) as usize;
+        reader.cursor = self.end();
+
+        self.previous.iter().chain(self.current.iter()).skip(skip)
+    }
+
+    /// Every buffered event, from this tick and the last.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.previous.iter().chain(self.current.iter())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.start = self.end();
+        self.previous.clear();
+        self.current.clear();
+    }
+
+    #[inline]
+    fn end(&self) -> u64 {
+        self.start + self.len() as u64
+    }
+
+    /// Drop the events of the last tick and keep this tick's for one more.
+    fn update(&mut self) {
+        self.start += self.previous.len() as u64;
+        self.previous.clear();
+        std::mem::swap(&mut self.previous, &mut self.current);
+    }
+}
+
+/// Position of a reader in an [`Events`] queue. Readers that fall more than a tick behind
+/// miss the events that were dropped.
+pub struct EventReader<T> {
+    cursor: u64,
+    phantom: PhantomData<fn(T)>,
+}
+
+impl<T> Default for EventReader<T> {
+    /// Reader that sees every event still buffered.
+    fn default() -> Self {
+        Self {
+            cursor: 0,
+            phantom: PhantomData,
+        }
+    }
+}
+
+//--------------------------------------------------
+
+trait EventQueue: Any {
+    fn update(&mut self);
+}
+
+impl<T: 'static> EventQueue for Events<T> {
+    #[inline]
+    fn update(&mut self) {
+        Events::update(self);
+    }
+}
+
+/// [`Events`] of every type, created the first time they're used.
+#[derive(Default)]
+pub struct EventBus {
+    queues: BTreeMap<TypeId, Box<dyn EventQueue>>,
+}
+
+impl EventBus {
+    #[inline]
+    pub fn send<T: 'static>(&mut self, event: T) {
+        self.events_mut::<T>().send(event);
+    }
+
+    #[inline]
+    pub fn events<T: 'static>(&self) -> Option<&Events<T>> {
+        self.queues
+            .get(&TypeId::of::<T>())
+            .and_then(|queue| (queue.as_ref() as &dyn Any).downcast_ref())
+    }
+
+    pub fn events_mut<T: 'static>(&mut self) -> &mut Events<T> {
+        let queue = self
+            .queues
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Events::<T>::default()));
+
+        (queue.as_mut() as &mut dyn Any)
+            .downcast_mut()
+            .expect("event queue stored under another type")
+    }
+
+    /// Events of type `T` that `reader` hasn't read yet.
+    pub fn read<'a, T: 'static>(
+        &'a self,
+        reader: &mut EventReader<T>,
+    ) -> impl Iterator<Item = &'a T> {
+        self.events::<T>()
+            .map(|events| events.read(reader))
+            .into_iter()
+            .flatten()
+    }
+
+    pub(crate) fn update(&mut self) {
+        self.queues.values_mut().for_each(|queue| queue.update());
+    }
+}
+
+//====================================================================
@@ -0,0 +1,85 @@
+//====================================================================
+
+pub use common::focus::*;
+
+use crate::tools::{GamepadButton, KeyCode};
+
+//====================================================================
+
+/// Keys and gamepad buttons used to drive the [`FocusManager`]. Buttons on any gamepad count.
+#[derive(Debug, Clone)]
+pub struct FocusBindings {
+    pub enabled: bool,
+    pub up: Vec<KeyCode>,
+    pub down: Vec<KeyCode>,
+    pub left: Vec<KeyCode>,
+    pub right: Vec<KeyCode>,
+    pub activate: Vec<KeyCode>,
+    pub cancel: Vec<KeyCode>,
+
+    pub up_buttons: Vec<GamepadButton>,
+    pub down_buttons: Vec<GamepadButton>,
+    pub left_buttons: Vec<GamepadButton>,
+    pub right_buttons: Vec<GamepadButton>,
+    pub activate_buttons: Vec<GamepadButton>,
+    pub cancel_buttons: Vec<GamepadButton>,
+}
+
+impl Default for FocusBindings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            up: vec![KeyCode::ArrowUp],
+            down: vec![KeyCode::ArrowDown],
+            left: vec![KeyCode::ArrowLeft],
+            right: vec![KeyCode::ArrowRight],
+            activate: vec![KeyCode::Enter, KeyCode::Space],
+            cancel: vec![KeyCode::Escape],
+
+            up_buttons: vec![GamepadButton::DPadUp],
+            down_buttons: vec![GamepadButton::DPadDown],
+            left_buttons: vec![GamepadButton::DPadLeft],
+            right_buttons: vec![GamepadButton::DPadRight],
+            activate_buttons: vec![GamepadButton::South],
+            cancel_buttons: vec![GamepadButton::East],
+        }
+    }
+}
+
+pub(crate) fn process_focus(state: &mut crate::State) {
+    if !state.focus_bindings.enabled {
+        return;
+    }
+
+    let bindings = &state.focus_bindings;
+    let just_pressed = |keys: &[KeyCode], buttons: &[GamepadButton]| {
+        keys.iter().any(|key| state.keys.just_pressed(*key))
+            || buttons
+                .iter()
+                .any(|button| state.gamepads.just_pressed(*button))
+    };
+
+    let direction = match () {
+        _ if just_pressed(&bindings.up, &bindings.up_buttons) => Some(NavDirection::Up),
+        _ if just_pressed(&bindings.down, &bindings.down_buttons) => Some(NavDirection::Down),
+        _ if just_pressed(&bindings.left, &bindings.left_buttons) => Some(NavDirection::Left),
+        _ if just_pressed(&bindings.right, &bindings.right_buttons) => Some(NavDirection::Right),
+        _ => None,
+    };
+    let activate = just_pressed(&bindings.activate, &bindings.activate_buttons);
+    let cancel = just_pressed(&bindings.cancel, &bindings.cancel_buttons);
+
+    if let Some(direction) = direction {
+        state.focus.navigate(&mut state.world, direction);
+    }
+
+    if activate {
+        state.focus.activate(&state.world);
+    }
+
+    if cancel {
+        state.focus.cancel();
+    }
+}
+
+//====================================================================
@@ -0,0 +1,114 @@
+//====================================================================
+
+use gilrs::{Axis, Button, EventType, Gilrs};
+
+use crate::tools::{GamepadAxis, GamepadBackend, GamepadButton, GamepadEvent};
+
+//====================================================================
+
+/// [`GamepadBackend`] using gilrs.
+pub struct GilrsBackend {
+    gilrs: Gilrs,
+    announced: bool,
+}
+
+impl GilrsBackend {
+    pub fn new() -> Result<Self, Box<gilrs::Error>> {
+        Ok(Self {
+            gilrs: Gilrs::new().map_err(Box::new)?,
+            announced: false,
+        })
+    }
+}
+
+impl GamepadBackend for GilrsBackend {
+    fn poll(&mut self, events: &mut Vec<GamepadEvent>) {
+        // Gamepads connected before the backend was created don't get connection events
+        if !self.announced {
+            self.announced = true;
+            events.extend(
+                self.gilrs
+                    .gamepads()
+                    .map(|(id, _)| GamepadEvent::Connected(id.into())),
+            );
+        }
+
+        while let Some(event) = self.gilrs.next_event() {
+            let id = event.id.into();
+
+            match event.event {
+                EventType::Connected => events.push(GamepadEvent::Connected(id)),
+                EventType::Disconnected => events.push(GamepadEvent::Disconnected(id)),
+
+                EventType::ButtonPressed(button, _) | EventType::ButtonReleased(button, _) => {
+                    if let Some(button) = map_button(button) {
+                        events.push(GamepadEvent::Button {
+                            id,
+                            button,
+                            pressed: matches!(event.event, EventType::ButtonPressed(..)),
+                        });
+                    }
+                }
+
+                // Analog triggers are reported as buttons with a value
+                EventType::ButtonChanged(Button::LeftTrigger2, value, _) => {
+                    events.push(GamepadEvent::Axis {
+                        id,
+                        axis: GamepadAxis::LeftTrigger,
+                        value,
+                    })
+                }
+                EventType::ButtonChanged(Button::RightTrigger2, value, _) => {
+                    events.push(GamepadEvent::Axis {
+                        id,
+                        axis: GamepadAxis::RightTrigger,
+                        value,
+                    })
+                }
+
+                EventType::AxisChanged(axis, value, _) => {
+                    if let Some(axis) = map_axis(axis) {
+                        events.push(GamepadEvent::Axis { id, axis, value });
+                    }
+                }
+
+                _ => {}
+            }
+        }
+    }
+}
+
+fn map_button(button: Button) -> Option<GamepadButton> {
+    Some(match button {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::North => GamepadButton::North,
+        Button::West => GamepadButton::West,
+        Button::LeftTrigger => GamepadButton::LeftBumper,
+        Button::RightTrigger => GamepadButton::RightBumper,
+        Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+        Button::RightTrigger2 => GamepadButton::RightTrigger,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::Mode => GamepadButton::Mode,
+        Button::LeftThumb => GamepadButton::LeftStick,
+        Button::RightThumb => GamepadButton::RightStick,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+fn map_axis(axis: Axis) -> Option<GamepadAxis> {
+    Some(match axis {
+        Axis::LeftStickX => GamepadAxis::LeftStickX,
+        Axis::LeftStickY => GamepadAxis::LeftStickY,
+        Axis::RightStickX => GamepadAxis::RightStickX,
+        Axis::RightStickY => GamepadAxis::RightStickY,
+        _ => return None,
+    })
+}
+
+//====================================================================
@@ -0,0 +1,371 @@
+//====================================================================
+
+use std::collections::BTreeMap;
+
+use hecs::{Entity, World};
+
+use crate::State;
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+    pub invulnerable: bool,
+}
+
+impl Health {
+    #[inline]
+    pub fn new(max: f32) -> Self {
+        Self {
+            current: max,
+            max,
+            invulnerable: false,
+        }
+    }
+
+    #[inline]
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.
+    }
+
+    #[inline]
+    pub fn fraction(&self) -> f32 {
+        match self.max > 0. {
+            true => (self.current / self.max).clamp(0., 1.),
+            false => 0.,
+        }
+    }
+
+    #[inline]
+    pub fn heal(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DamageKind {
+    #[default]
+    Physical,
+    Fire,
+    Cold,
+    Poison,
+    Custom(u32),
+}
+
+/// Multipliers per damage kind applied before flat armor. Kinds without an entry take full damage.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Resistances {
+    pub multipliers: BTreeMap<DamageKind, f32>,
+    /// Flat reduction applied to physical damage.
+    pub armor: f32,
+}
+
+impl Resistances {
+    #[inline]
+    pub fn with(mut self, kind: DamageKind, multiplier: f32) -> Self {
+        self.multipliers.insert(kind, multiplier);
+        self
+    }
+
+    #[inline]
+    pub fn with_armor(mut self, armor: f32) -> Self {
+        self.armor = armor;
+        self
+    }
+
+    pub fn apply(&self, kind: DamageKind, amount: f32) -> f32 {
+        let amount = amount * self.multipliers.get(&kind).copied().unwrap_or(1.);
+
+        match kind {
+            DamageKind::Physical => (amount - self.armor).max(0.),
+            _ => amount,
+        }
+    }
+}
+
+/// What happens to an entity once its health reaches zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OnDeath {
+    #[default]
+    Despawn,
+    /// Despawn after a delay in seconds, e.g. to play a death animation.
+    DespawnAfter(f32),
+    /// Leave the entity alone. It still gets the [`Dead`] marker.
+    Keep,
+}
+
+/// Added to entities that have died.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dead {
+    /// Seconds left before despawning, if despawning later.
+    despawn_in: Option<f32>,
+}
+
+//--------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatusKind {
+    /// Deals fire damage every second.
+    Burn {
+        damage_per_second: f32,
+    },
+    /// Deals poison damage every second.
+    Poison {
+        damage_per_second: f32,
+    },
+    /// Movement speed multiplier. See [`StatusEffects::speed_multiplier`].
+    Slow {
+        multiplier: f32,
+    },
+    Custom(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    /// Seconds left.
+    pub remaining: f32,
+    pub source: Option<Entity>,
+}
+
+/// Timed effects on an entity, ticked and removed when they run out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StatusEffects {
+    effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    #[inline]
+    pub fn add(&mut self, kind: StatusKind, duration: f32, source: Option<Entity>) {
+        self.effects.push(StatusEffect {
+            kind,
+            remaining: duration,
+            source,
+        });
+    }
+
+    #[inline]
+    pub fn effects(&self) -> &[StatusEffect] {
+        &self.effects
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.effects.clear();
+    }
+
+    /// Product of every active slow effect.
+    pub fn speed_multiplier(&self) -> f32 {
+        self.effects
+            .iter()
+            .filter_map(|effect| match effect.kind {
+                StatusKind::Slow { multiplier } => Some(multiplier),
+                _ => None,
+            })
+            .product()
+    }
+}
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Damage {
+    pub target: Entity,
+    pub amount: f32,
+    pub kind: DamageKind,
+    pub source: Option<Entity>,
+}
+
+impl Damage {
+    #[inline]
+    pub fn new(target: Entity, amount: f32) -> Self {
+        Self {
+            target,
+            amount,
+            kind: DamageKind::Physical,
+            source: None,
+        }
+    }
+
+    #[inline]
+    pub fn with_kind(mut self, kind: DamageKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    #[inline]
+    pub fn with_source(mut self, source: Entity) -> Self {
+        self.source = Some(source);
+        self
+    }
+}
+
+/// Damage after resistances and modifiers were applied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DamageEvent {
+    pub damage: Damage,
+    pub applied: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeathEvent {
+    pub entity: Entity,
+    pub source: Option<Entity>,
+}
+
+type DamageModifier = Box<dyn Fn(&World, &Damage, f32) -> f32>;
+
+/// Queued damage along with the damage and death events from the last frame.
+/// Dead entities are despawned no earlier than the frame after their death event.
+#[derive(Default)]
+pub struct HealthEvents {
+    queued: Vec<Damage>,
+    damaged: Vec<DamageEvent>,
+    deaths: Vec<DeathEvent>,
+    modifiers: Vec<DamageModifier>,
+}
+
+impl HealthEvents {
+    /// Queue damage, applied after `App::update`.
+    #[inline]
+    pub fn damage(&mut self, damage: Damage) {
+        self.queued.push(damage);
+    }
+
+    /// Adjust damage after resistances, e.g. for critical hits or difficulty scaling.
+    /// Modifiers run in the order they were added.
+    #[inline]
+    pub fn add_modifier(&mut self, modifier: impl Fn(&World, &Damage, f32) -> f32 + 'static) {
+        self.modifiers.push(Box::new(modifier));
+    }
+
+    #[inline]
+    pub fn damaged(&self) -> &[DamageEvent] {
+        &self.damaged
+    }
+
+    #[inline]
+    pub fn deaths(&self) -> &[DeathEvent] {
+        &self.deaths
+    }
+}
+
+//====================================================================
+
+pub(crate) fn process_health(state: &mut State) {
+    let delta = state.time.delta_seconds();
+    let health = &mut state.health;
+
+    health.damaged.clear();
+    health.deaths.clear();
+
+    // Dead entities are removed a frame later so death events can still read their components
+    let despawn = state
+        .world
+        .query_mut::<&mut Dead>()
+        .into_iter()
+        .filter_map(|(entity, dead)| {
+            let remaining = dead.despawn_in.as_mut()?;
+            *remaining -= delta;
+            (*remaining <= 0.).then_some(entity)
+        })
+        .collect::<Vec<_>>();
+
+    despawn.into_iter().for_each(|entity| {
+        state.world.despawn(entity).ok();
+    });
+
+    // Damage over time from status effects
+    state
+        .world
+        .query_mut::<&mut StatusEffects>()
+        .into_iter()
+        .for_each(|(entity, effects)| {
+            effects.effects.iter_mut().for_each(|effect| {
+                let seconds = delta.min(effect.remaining.max(0.));
+                effect.remaining -= delta;
+
+                let (kind, per_second) = match effect.kind {
+                    StatusKind::Burn { damage_per_second } => (DamageKind::Fire, damage_per_second),
+                    StatusKind::Poison { damage_per_second } => {
+                        (DamageKind::Poison, damage_per_second)
+                    }
+                    _ => return,
+                };
+
+                health.queued.push(Damage {
+                    target: entity,
+                    amount: per_second * seconds,
+                    kind,
+                    source: effect.source,
+                });
+            });
+
+            effects.effects.retain(|effect| effect.remaining > 0.);
+        });
+
+    std::mem::take(&mut health.queued)
+        .into_iter()
+        .for_each(|damage| {
+            let Ok(mut query) = state.world.query_one::<(
+                &Health,
+                Option<&Resistances>,
+                Option<&OnDeath>,
+                Option<&Dead>,
+            )>(damage.target) else {
+                return;
+            };
+
+            let Some((target, resistances, on_death, dead)) = query.get() else {
+                return;
+            };
+
+            if target.invulnerable || dead.is_some() {
+                return;
+            }
+
+            let applied = resistances
+                .map(|resistances| resistances.apply(damage.kind, damage.amount))
+                .unwrap_or(damage.amount);
+            let on_death = on_death.copied().unwrap_or_default();
+            drop(query);
+
+            let applied = health
+                .modifiers
+                .iter()
+                .fold(applied, |applied, modifier| {
+                    modifier(&state.world, &damage, applied)
+                })
+                .max(0.);
+
+            let died = {
+                let mut target = state.world.get::<&mut Health>(damage.target).unwrap();
+                target.current -= applied;
+                target.is_dead()
+            };
+
+            health.damaged.push(DamageEvent { damage, applied });
+
+            if died {
+                health.deaths.push(DeathEvent {
+                    entity: damage.target,
+                    source: damage.source,
+                });
+
+                let despawn_in = match on_death {
+                    OnDeath::Despawn => Some(0.),
+                    OnDeath::DespawnAfter(delay) => Some(delay),
+                    OnDeath::Keep => None,
+                };
+
+                state
+                    .world
+                    .insert_one(damage.target, Dead { despawn_in })
+                    .ok();
+            }
+        });
+}
+
+//====================================================================
@@ -0,0 +1,837 @@
+//====================================================================
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+use hecs::Entity;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::{scene::SceneContext, vfs, State};
+
+#[cfg(target_arch = "wasm32")]
+use web::Client;
+
+//====================================================================
+
+/// Address [`crate::State::start_inspector`] listens on, reachable from this machine only.
+#[cfg(not(target_arch = "wasm32"))]
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+
+#[cfg(not(target_arch = "wasm32"))]
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC11B85";
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_BUFFER: usize = 1 << 20;
+
+/// Command sent by an inspector client as json, e.g. `{"command": "entity", "id": 4294967296}`.
+/// Entity ids are [`Entity::to_bits`].
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum Request {
+    /// Must be the first request of every client, with the token the server was started with.
+    Auth {
+        token: String,
+    },
+    Entities,
+    /// Registered components of an entity.
+    Entity {
+        id: u64,
+    },
+    Stats,
+    Pause,
+    Resume,
+    /// Replace a component, or a field of it given as a dotted path such as `translation.1`.
+    Set {
+        id: u64,
+        component: String,
+        #[serde(default)]
+        field: Option<String>,
+        value: Value,
+    },
+    /// Spawn an entity from components by name, as saved in scenes.
+    Spawn {
+        components: Map<String, Value>,
+    },
+    /// Spawn every entity of a scene file. Paths are asset paths read through the
+    /// [`crate::vfs::Vfs`] and can't leave the asset folder.
+    SpawnScene {
+        path: String,
+    },
+    Despawn {
+        id: u64,
+    },
+}
+
+//====================================================================
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    /// Waiting for the first line to tell the protocol apart.
+    Unknown,
+    /// One json request and response per line.
+    Lines,
+    WebSocket,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct Client {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    /// Bytes not yet accepted by the socket, written on following polls.
+    outgoing: Vec<u8>,
+    protocol: Protocol,
+    authenticated: bool,
+    closed: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Client {
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            buffer: Vec::new(),
+            outgoing: Vec::new(),
+            protocol: Protocol::Unknown,
+            authenticated: false,
+            closed: false,
+        }
+    }
+
+    fn send(&mut self, data: &[u8]) {
+        self.outgoing.extend_from_slice(data);
+
+        if self.outgoing.len() > MAX_BUFFER {
+            log::warn!("Inspector client isn't reading responses - disconnecting");
+            self.closed = true;
+        }
+    }
+
+    /// Write as much of the queued data as the socket takes without blocking.
+    fn flush(&mut self) {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => {
+                    self.closed = true;
+                    break;
+                }
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    log::debug!("Inspector client disconnected: {}", e);
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    fn respond(&mut self, response: &str) {
+        match self.protocol {
+            Protocol::WebSocket => self.send(&websocket_frame(0x1, response.as_bytes())),
+            _ => self.send(format!("{}\n", response).as_bytes()),
+        }
+    }
+
+    /// Requests received in full since the last poll.
+    fn poll(&mut self) -> Vec<String> {
+        let mut chunk = [0; 4096];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    self.closed = true;
+                    break;
+                }
+                Ok(read) => self.buffer.extend_from_slice(&chunk[..read]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    log::debug!("Inspector client disconnected: {}", e);
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+
+        if self.buffer.len() > MAX_BUFFER {
+            log::warn!("Inspector client sent too much data - disconnecting");
+            self.closed = true;
+            return Vec::new();
+        }
+
+        if self.protocol == Protocol::Unknown {
+            self.detect_protocol();
+        }
+
+        match self.protocol {
+            Protocol::Unknown => Vec::new(),
+            Protocol::Lines => self.poll_lines(),
+            Protocol::WebSocket => self.poll_frames(),
+        }
+    }
+
+    fn detect_protocol(&mut self) {
+        if !self.buffer.starts_with(b"GET ") {
+            if self.buffer.contains(&b'\n') {
+                self.protocol = Protocol::Lines;
+            }
+            return;
+        }
+
+        let Some(end) = self
+            .buffer
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+        else {
+            return;
+        };
+
+        let request = String::from_utf8_lossy(&self.buffer[..end]).to_string();
+        self.buffer.drain(..end + 4);
+
+        let key = request.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("sec-websocket-key")
+                .then(|| value.trim().to_string())
+        });
+
+        let Some(key) = key else {
+            self.send(b"HTTP/1.1 400 Bad Request\r\n\r\n");
+            self.closed = true;
+            return;
+        };
+
+        let accept = base64(&sha1(format!("{}{}", key, WEBSOCKET_GUID).as_bytes()));
+        self.send(
+            format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                 Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                accept
+            )
+            .as_bytes(),
+        );
+        self.protocol = Protocol::WebSocket;
+    }
+
+    fn poll_lines(&mut self) -> Vec<String> {
+        let Some(end) = self.buffer.iter().rposition(|byte| *byte == b'\n') else {
+            return Vec::new();
+        };
+
+        let lines = self.buffer.drain(..=end).collect::<Vec<_>>();
+        String::from_utf8_lossy(&lines)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn poll_frames(&mut self) -> Vec<String> {
+        let mut requests = Vec::new();
+
+        while let Some((fin, opcode, payload, len)) = parse_websocket_frame(&self.buffer) {
+            self.buffer.drain(..len);
+
+            match opcode {
+                0x1 if fin => requests.push(String::from_utf8_lossy(&payload).to_string()),
+                0x8 => {
+                    self.send(&websocket_frame(0x8, &[]));
+                    self.closed = true;
+                    break;
+                }
+                0x9 => self.send(&websocket_frame(0xA, &payload)),
+                0xA => {}
+                _ => {
+                    log::warn!("Unsupported inspector websocket frame - disconnecting");
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+
+        requests
+    }
+}
+
+//--------------------------------------------------
+
+/// Debug server letting external tools inspect and edit the running game over TCP, either
+/// one json command per line or as websocket text messages, e.g. from a browser page.
+/// Components are listed and edited through the [`crate::scene::SceneRegistry`].
+///
+/// Clients must first send `{"command": "auth", "token": ...}` with the server's token.
+/// On wasm the game can't listen for connections, so it connects out to a websocket served
+/// by the tool instead, see [`InspectorServer::connect`], and takes the same requests.
+pub struct InspectorServer {
+    #[cfg(not(target_arch = "wasm32"))]
+    listener: TcpListener,
+    token: String,
+    clients: Vec<Client>,
+}
+
+fn check_token(token: String) -> std::io::Result<String> {
+    match token.is_empty() {
+        true => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Inspector token can't be empty",
+        )),
+        false => Ok(token),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl InspectorServer {
+    pub fn bind(address: impl ToSocketAddrs, token: impl Into<String>) -> std::io::Result<Self> {
+        let token = check_token(token.into())?;
+
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+
+        if let Ok(address) = listener.local_addr() {
+            if !address.ip().is_loopback() {
+                log::warn!("Inspector is reachable from other machines on {}", address);
+            }
+            log::info!("Inspector listening on {}", address);
+        }
+
+        Ok(Self {
+            listener,
+            token,
+            clients: Vec::new(),
+        })
+    }
+
+    fn accept(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, address)) => {
+                    if let Err(e) = stream.set_nonblocking(true) {
+                        log::warn!("Unable to accept inspector client {}: {}", address, e);
+                        continue;
+                    }
+
+                    log::info!("Inspector client connected from {}", address);
+                    self.clients.push(Client::new(stream));
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    log::warn!("Inspector unable to accept clients: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl InspectorServer {
+    /// Connect to an inspector tool serving a websocket at `url`, e.g. `ws://127.0.0.1:7878`.
+    /// The tool has to authenticate with `token` before sending any other command.
+    pub fn connect(url: &str, token: impl Into<String>) -> std::io::Result<Self> {
+        let token = check_token(token.into())?;
+        let client = Client::connect(url)?;
+
+        log::info!("Inspector connecting to {}", url);
+
+        Ok(Self {
+            token,
+            clients: vec![client],
+        })
+    }
+}
+
+//--------------------------------------------------
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use std::{
+        cell::{Cell, RefCell},
+        collections::VecDeque,
+        rc::Rc,
+    };
+
+    use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+    use web_sys::{MessageEvent, WebSocket};
+
+    /// Outbound websocket to an inspector tool, used in place of accepted tcp clients.
+    pub(super) struct Client {
+        socket: WebSocket,
+        received: Rc<RefCell<VecDeque<String>>>,
+        disconnected: Rc<Cell<bool>>,
+        pub authenticated: bool,
+        pub closed: bool,
+
+        _on_message: Closure<dyn FnMut(MessageEvent)>,
+        _on_close: Closure<dyn FnMut(JsValue)>,
+    }
+
+    impl Client {
+        pub fn connect(url: &str) -> std::io::Result<Self> {
+            let socket =
+                WebSocket::new(url).map_err(|e| std::io::Error::other(format!("{:?}", e)))?;
+
+            let received = Rc::new(RefCell::new(VecDeque::new()));
+            let disconnected = Rc::new(Cell::new(false));
+
+            let on_message = {
+                let received = received.clone();
+                Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                    match event.data().as_string() {
+                        Some(request) => received.borrow_mut().push_back(request),
+                        None => log::warn!("Ignoring binary inspector message"),
+                    }
+                })
+            };
+
+            let on_close = {
+                let disconnected = disconnected.clone();
+                Closure::<dyn FnMut(JsValue)>::new(move |_| {
+                    disconnected.set(true);
+                })
+            };
+
+            socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+            socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+            socket.set_onerror(Some(on_close.as_ref().unchecked_ref()));
+
+            Ok(Self {
+                socket,
+                received,
+                disconnected,
+                authenticated: false,
+                closed: false,
+                _on_message: on_message,
+                _on_close: on_close,
+            })
+        }
+
+        /// Requests received since the last poll.
+        pub fn poll(&mut self) -> Vec<String> {
+            if self.disconnected.get() {
+                log::info!("Inspector disconnected");
+                self.closed = true;
+            }
+
+            self.received.borrow_mut().drain(..).collect()
+        }
+
+        pub fn respond(&mut self, response: &str) {
+            if self.socket.ready_state() != WebSocket::OPEN {
+                return;
+            }
+
+            if let Err(e) = self.socket.send_with_str(response) {
+                log::warn!("Inspector unable to respond: {:?}", e);
+                self.closed = true;
+            }
+        }
+
+        /// Sent straight away by the browser, so there's nothing to flush.
+        #[inline]
+        pub fn flush(&mut self) {}
+    }
+
+    impl Drop for Client {
+        fn drop(&mut self) {
+            self.socket.set_onmessage(None);
+            self.socket.set_onclose(None);
+            self.socket.set_onerror(None);
+            let _ = self.socket.close();
+        }
+    }
+}
+
+//====================================================================
+
+/// Compared in constant time so the token can't be guessed a byte at a time.
+fn token_matches(token: &str, expected: &str) -> bool {
+    token.len() == expected.len()
+        && token
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Relative asset path without any `..`, so clients can't read files outside the assets.
+fn asset_path(path: &str) -> Result<String, String> {
+    let path = vfs::normalize_path(path);
+
+    let escapes = path.starts_with('/')
+        || path.contains(':')
+        || path.split('/').any(|component| component == "..");
+
+    match escapes {
+        true => Err(format!(
+            "Scene path '{}' must be a relative asset path",
+            path
+        )),
+        false => Ok(path),
+    }
+}
+
+fn entity_from_id(state: &State, id: u64) -> Result<Entity, String> {
+    Entity::from_bits(id)
+        .filter(|entity| state.world.contains(*entity))
+        .ok_or_else(|| format!("No entity with id {}", id))
+}
+
+fn handle_request(state: &mut State, request: Request) -> Result<Value, String> {
+    match request {
+        Request::Auth { .. } => Ok(Value::Null),
+
+        Request::Entities => {
+            let entities = state
+                .world
+                .iter()
+                .map(|entity| {
+                    json!({
+                        "id": entity.entity().to_bits().get(),
+                        "components": state
+                            .scene_registry
+                            .save_entity(entity, &state.assets)
+                            .keys()
+                            .collect::<Vec<_>>(),
+                        "component_count": entity.component_types().count(),
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            Ok(Value::Array(entities))
+        }
+
+        Request::Entity { id } => {
+            let entity = entity_from_id(state, id)?;
+            let entity = state.world.entity(entity).map_err(|e| e.to_string())?;
+
+            Ok(Value::Object(
+                state.scene_registry.save_entity(entity, &state.assets),
+            ))
+        }
+
+        Request::Stats => {
+            let delta = state.time.delta_seconds();
+            let pipelines = state
+                .renderer
+                .stats()
+                .iter()
+                .map(|(name, stats)| {
+                    let counters = stats
+                        .counters()
+                        .map(|(name, value)| (name.to_string(), json!(value)))
+                        .collect::<Map<_, _>>();
+                    (name.to_string(), Value::Object(counters))
+                })
+                .collect::<Map<_, _>>();
+
+            Ok(json!({
+                "fps": if delta > 0. { 1. / delta } else { 0. },
+                "delta_ms": delta * 1000.,
+                "entities": state.world.len(),
+                "paused": state.paused,
+                "pipelines": pipelines,
+            }))
+        }
+
+        Request::Pause => {
+            state.paused = true;
+            Ok(Value::Null)
+        }
+
+        Request::Resume => {
+            state.paused = false;
+            Ok(Value::Null)
+        }
+
+        Request::Set {
+            id,
+            component,
+            field,
+            value,
+        } => {
+            let entity = entity_from_id(state, id)?;
+
+            let value = match field {
+                Some(field) => {
+                    let mut current = state
+                        .scene_registry
+                        .save_entity(
+                            state.world.entity(entity).map_err(|e| e.to_string())?,
+                            &state.assets,
+                        )
+                        .remove(&component)
+                        .ok_or_else(|| format!("Entity {} has no {}", id, component))?;
+
+                    let pointer = format!("/{}", field.replace('.', "/"));
+                    *current
+                        .pointer_mut(&pointer)
+                        .ok_or_else(|| format!("{} has no field {}", component, field))? = value;
+                    current
+                }
+                None => value,
+            };
+
+            let mut components = Map::new();
+            components.insert(component, value);
+
+            let mut builder = state
+                .scene_registry
+                .load_entity(
+                    components,
+                    &mut SceneContext {
+                        renderer: &state.renderer,
+                        assets: &mut state.assets,
+                    },
+                )
+                .map_err(|e| e.to_string())?;
+
+            state
+                .world
+                .insert(entity, builder.build())
+                .map_err(|e| e.to_string())?;
+
+            Ok(Value::Null)
+        }
+
+        Request::Spawn { components } => {
+            let mut builder = state
+                .scene_registry
+                .load_entity(
+                    components,
+                    &mut SceneContext {
+                        renderer: &state.renderer,
+                        assets: &mut state.assets,
+                    },
+                )
+                .map_err(|e| e.to_string())?;
+
+            let entity = state.world.spawn(builder.build());
+            Ok(json!(entity.to_bits().get()))
+        }
+
+        Request::SpawnScene { path } => {
+            let entities = state
+                .load_scene(asset_path(&path)?)
+                .map_err(|e| e.to_string())?;
+
+            Ok(Value::Array(
+                entities
+                    .into_iter()
+                    .map(|entity| json!(entity.to_bits().get()))
+                    .collect(),
+            ))
+        }
+
+        Request::Despawn { id } => {
+            let entity = entity_from_id(state, id)?;
+            state.world.despawn(entity).map_err(|e| e.to_string())?;
+            Ok(Value::Null)
+        }
+    }
+}
+
+pub(crate) fn process_inspector(state: &mut State) {
+    let Some(mut server) = state.inspector.take() else {
+        return;
+    };
+
+    #[cfg(not(target_arch = "wasm32"))]
+    server.accept();
+
+    let token = &server.token;
+
+    server.clients.iter_mut().for_each(|client| {
+        client.poll().into_iter().for_each(|request| {
+            if client.closed {
+                return;
+            }
+
+            let request = serde_json::from_str::<Request>(&request);
+
+            let response = match request {
+                Ok(Request::Auth { token: sent }) if !client.authenticated => {
+                    match token_matches(&sent, token) {
+                        true => {
+                            client.authenticated = true;
+                            json!({ "ok": true, "data": null })
+                        }
+                        false => {
+                            log::warn!("Inspector client sent a wrong token - disconnecting");
+                            client.closed = true;
+                            json!({ "ok": false, "error": "Wrong token" })
+                        }
+                    }
+                }
+                _ if !client.authenticated => {
+                    log::warn!("Inspector client didn't authenticate - disconnecting");
+                    client.closed = true;
+                    json!({ "ok": false, "error": "Authenticate with the auth command first" })
+                }
+                Ok(request) => match handle_request(state, request) {
+                    Ok(data) => json!({ "ok": true, "data": data }),
+                    Err(e) => json!({ "ok": false, "error": e }),
+                },
+                Err(e) => json!({ "ok": false, "error": format!("Invalid request: {}", e) }),
+            };
+
+            client.respond(&response.to_string());
+        });
+
+        // Closing clients get one last try at their pending data
+        client.flush();
+    });
+
+    server.clients.retain(|client| !client.closed);
+    state.inspector = Some(server);
+}
+
+//====================================================================
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Fin flag, opcode, unmasked payload and length in bytes of the first whole frame.
+fn parse_websocket_frame(data: &[u8]) -> Option<(bool, u8, Vec<u8>, usize)> {
+    if data.len() < 2 {
+        return None;
+    }
+
+    let fin = data[0] & 0x80 != 0;
+    let opcode = data[0] & 0x0F;
+    let masked = data[1] & 0x80 != 0;
+
+    let (len, mut offset) = match data[1] & 0x7F {
+        126 => (
+            u16::from_be_bytes(data.get(2..4)?.try_into().ok()?) as usize,
+            4,
+        ),
+        127 => (
+            u64::from_be_bytes(data.get(2..10)?.try_into().ok()?) as usize,
+            10,
+        ),
+        len => (len as usize, 2),
+    };
+
+    let mask = match masked {
+        true => {
+            let mask = data.get(offset..offset + 4)?;
+            offset += 4;
+            Some([mask[0], mask[1], mask[2], mask[3]])
+        }
+        false => None,
+    };
+
+    let end = offset.checked_add(len)?;
+    let mut payload = data.get(offset..end)?.to_vec();
+
+    if let Some(mask) = mask {
+        payload
+            .iter_mut()
+            .enumerate()
+            .for_each(|(index, byte)| *byte ^= mask[index % 4]);
+    }
+
+    Some((fin, opcode, payload, end))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn websocket_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x80 | opcode];
+
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+
+    frame.extend_from_slice(payload);
+    frame
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    message.chunks(64).for_each(|block| {
+        let mut w = [0u32; 80];
+        (0..16)
+            .for_each(|i| w[i] = u32::from_be_bytes(block[i * 4..i * 4 + 4].try_into().unwrap()));
+        (16..80).for_each(|i| w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1));
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+
+        w.iter().enumerate().for_each(|(i, w)| {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*w);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        });
+
+        h[0] = h[0].wrapping_add(a);
+        h[1] = h[1].wrapping_add(b);
+        h[2] = h[2].wrapping_add(c);
+        h[3] = h[3].wrapping_add(d);
+        h[4] = h[4].wrapping_add(e);
+    });
+
+    let mut digest = [0; 20];
+    h.iter()
+        .enumerate()
+        .for_each(|(i, word)| digest[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes()));
+    digest
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    data.chunks(3)
+        .flat_map(|chunk| {
+            let bytes = [
+                chunk[0],
+                *chunk.get(1).unwrap_or(&0),
+                *chunk.get(2).unwrap_or(&0),
+            ];
+            let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+
+            (0..4).map(move |i| match i <= chunk.len() {
+                true => ALPHABET[(bits >> (18 - i * 6)) as usize & 0x3F] as char,
+                false => '=',
+            })
+        })
+        .collect()
+}
+
+//====================================================================
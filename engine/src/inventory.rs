@@ -0,0 +1,325 @@
+//====================================================================
+
+use std::collections::BTreeMap;
+
+use hecs::Entity;
+use serde::{Deserialize, Serialize};
+
+use crate::State;
+
+//====================================================================
+
+pub type ItemId = String;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemDef {
+    pub name: String,
+    /// Path to the icon texture.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Most items a single slot can hold.
+    #[serde(default = "default_max_stack")]
+    pub max_stack: u32,
+    /// Game specific data such as weight, value or damage.
+    #[serde(default)]
+    pub properties: BTreeMap<String, serde_json::Value>,
+}
+
+fn default_max_stack() -> u32 {
+    1
+}
+
+impl ItemDef {
+    #[inline]
+    pub fn property<T: serde::de::DeserializeOwned>(&self, name: &str) -> Option<T> {
+        self.properties
+            .get(name)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+    }
+}
+
+/// Item definitions keyed by id, usually loaded from a json file through
+/// [`crate::assets::AssetServer::load_items`].
+///
+/// ```json
+/// { "potion": { "name": "Potion", "icon": "res/potion.png", "max_stack": 10 } }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ItemDatabase {
+    items: BTreeMap<ItemId, ItemDef>,
+}
+
+impl ItemDatabase {
+    #[inline]
+    pub fn from_json(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    #[inline]
+    pub fn insert(&mut self, id: impl Into<ItemId>, item: ItemDef) {
+        self.items.insert(id.into(), item);
+    }
+
+    #[inline]
+    pub fn get(&self, id: &str) -> Option<&ItemDef> {
+        self.items.get(id)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&ItemId, &ItemDef)> {
+        self.items.iter()
+    }
+
+    /// Stack size of an item. Unknown items don't stack.
+    #[inline]
+    pub fn max_stack(&self, id: &str) -> u32 {
+        self.get(id).map(|item| item.max_stack.max(1)).unwrap_or(1)
+    }
+}
+
+//====================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub item: ItemId,
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InventoryChange {
+    Added { item: ItemId, count: u32 },
+    Removed { item: ItemId, count: u32 },
+    Swapped { from: usize, to: usize },
+}
+
+/// Fixed number of item slots. Changes are reported through [`InventoryEvents`]
+/// once `App::update` has finished.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>,
+    #[serde(skip)]
+    changes: Vec<InventoryChange>,
+}
+
+impl Inventory {
+    #[inline]
+    pub fn new(slots: usize) -> Self {
+        Self {
+            slots: vec![None; slots],
+            changes: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        &self.slots
+    }
+
+    #[inline]
+    pub fn slot(&self, index: usize) -> Option<&ItemStack> {
+        self.slots.get(index)?.as_ref()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(Option::is_none)
+    }
+
+    /// Total count of an item across every slot.
+    pub fn count(&self, item: &str) -> u32 {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|stack| stack.item == item)
+            .map(|stack| stack.count)
+            .sum()
+    }
+
+    #[inline]
+    pub fn contains(&self, item: &str, count: u32) -> bool {
+        self.count(item) >= count
+    }
+
+    /// Add items, topping up existing stacks before using empty slots.
+    /// Returns how many didn't fit.
+    pub fn add(&mut self, items: &ItemDatabase, item: &str, count: u32) -> u32 {
+        let max_stack = items.max_stack(item);
+        let mut remaining = count;
+
+        self.slots
+            .iter_mut()
+            .flatten()
+            .filter(|stack| stack.item == item)
+            .for_each(|stack| {
+                let moved = remaining.min(max_stack.saturating_sub(stack.count));
+                stack.count += moved;
+                remaining -= moved;
+            });
+
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if remaining == 0 {
+                break;
+            }
+
+            let moved = remaining.min(max_stack);
+            *slot = Some(ItemStack {
+                item: item.to_string(),
+                count: moved,
+            });
+            remaining -= moved;
+        }
+
+        if remaining < count {
+            self.changes.push(InventoryChange::Added {
+                item: item.to_string(),
+                count: count - remaining,
+            });
+        }
+
+        remaining
+    }
+
+    /// Remove items, taking from the last stacks first. Returns how many were removed.
+    pub fn remove(&mut self, item: &str, count: u32) -> u32 {
+        let mut remaining = count;
+
+        self.slots.iter_mut().rev().for_each(|slot| {
+            let Some(stack) = slot.as_mut().filter(|stack| stack.item == item) else {
+                return;
+            };
+
+            let moved = remaining.min(stack.count);
+            stack.count -= moved;
+            remaining -= moved;
+
+            if stack.count == 0 {
+                *slot = None;
+            }
+        });
+
+        let removed = count - remaining;
+        if removed > 0 {
+            self.changes.push(InventoryChange::Removed {
+                item: item.to_string(),
+                count: removed,
+            });
+        }
+
+        removed
+    }
+
+    /// Empty a slot, returning what was in it.
+    pub fn take(&mut self, index: usize) -> Option<ItemStack> {
+        let stack = self.slots.get_mut(index)?.take()?;
+
+        self.changes.push(InventoryChange::Removed {
+            item: stack.item.clone(),
+            count: stack.count,
+        });
+
+        Some(stack)
+    }
+
+    /// Swap two slots, merging them instead if they hold the same item.
+    pub fn swap(&mut self, items: &ItemDatabase, from: usize, to: usize) {
+        if from == to || from >= self.slots.len() || to >= self.slots.len() {
+            return;
+        }
+
+        let merge = match (&self.slots[from], &self.slots[to]) {
+            (Some(a), Some(b)) if a.item == b.item => Some(
+                a.count
+                    .min(items.max_stack(&a.item).saturating_sub(b.count)),
+            ),
+            _ => None,
+        };
+
+        match merge {
+            Some(moved) => {
+                self.slots[to].as_mut().unwrap().count += moved;
+                let source = self.slots[from].as_mut().unwrap();
+                source.count -= moved;
+                if source.count == 0 {
+                    self.slots[from] = None;
+                }
+            }
+            None => self.slots.swap(from, to),
+        }
+
+        self.changes.push(InventoryChange::Swapped { from, to });
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| {
+            if let Some(stack) = slot.take() {
+                self.changes.push(InventoryChange::Removed {
+                    item: stack.item,
+                    count: stack.count,
+                });
+            }
+        });
+    }
+}
+
+//--------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InventoryEvent {
+    pub entity: Entity,
+    pub change: InventoryChange,
+}
+
+/// Inventory changes from the last frame.
+#[derive(Default)]
+pub struct InventoryEvents {
+    events: Vec<InventoryEvent>,
+}
+
+impl InventoryEvents {
+    #[inline]
+    pub fn events(&self) -> &[InventoryEvent] {
+        &self.events
+    }
+
+    /// Changes to a single inventory.
+    #[inline]
+    pub fn changes(&self, entity: Entity) -> impl Iterator<Item = &InventoryChange> {
+        self.events
+            .iter()
+            .filter(move |event| event.entity == entity)
+            .map(|event| &event.change)
+    }
+
+    #[inline]
+    pub fn changed(&self, entity: Entity) -> bool {
+        self.events.iter().any(|event| event.entity == entity)
+    }
+}
+
+//====================================================================
+
+pub(crate) fn process_inventories(state: &mut State) {
+    let events = &mut state.inventories.events;
+    events.clear();
+
+    state
+        .world
+        .query_mut::<&mut Inventory>()
+        .into_iter()
+        .for_each(|(entity, inventory)| {
+            events.extend(
+                inventory
+                    .changes
+                    .drain(..)
+                    .map(|change| InventoryEvent { entity, change }),
+            );
+        });
+}
+
+//====================================================================
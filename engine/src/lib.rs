@@ -1,36 +1,192 @@
 //====================================================================
 
-use std::{marker::PhantomData, sync::Arc, time::Duration};
+use std::{marker::PhantomData, path::PathBuf, sync::Arc, time::Duration};
 
-use common::Size;
-use hecs::World;
-use renderer::{camera::CameraUniform, texture::LoadedTexture, RendererState};
-use tools::{Input, KeyCode, MouseButton, MouseInput, Time};
-use window::Window;
+use assets::AssetServer;
+use audio::{Audio, AudioMixer};
+use combat::HitEvents;
+use common::{
+    forces::Forces,
+    profiler::{self, Profile},
+    GlobalTransform, Size, Transform,
+};
+use debug_keys::{DebugBindings, DebugToggles};
+use dialogue::DialogueRunner;
+use events::{EventBus, EventReader};
+use focus::{FocusBindings, FocusManager};
+use health::HealthEvents;
+use hecs::{Entity, EntityBuilder, World};
+use inventory::InventoryEvents;
+use lod::{LodSettings, LodState, LodTick};
+use music::MusicController;
+use physics2d::Physics2D;
+use quests::QuestLog;
+use random::Rng;
+use renderer::{
+    camera::{self, CameraUniform, OrthographicCamera, PerspectiveCamera, RenderTarget},
+    debug::{DebugLines, DebugSettings},
+    lighting::AmbientLight,
+    ordering::{PipelineOrder, PipelineOrderError},
+    shader_library::ShaderLibrary,
+    shadow::ShadowSettings,
+    text_shared::{FontLoadStatus, FontPreload},
+    texture::LoadedTexture,
+    RendererConfig, RendererError, RendererState,
+};
+use replay::{InputEvent, Replay, ReplayConfig};
+use resources::Resources;
+use save::{Autosave, AutosaveConfig, SaveGame};
+use scene::SceneRegistry;
+use schedule::{Schedule, ScheduleError, Stage, System, SystemOrder};
+use spatial::OrphanPolicy;
+use tasks::TaskQueue;
+use tools::{GamepadInput, Input, KeyCode, MouseButton, MouseInput, Time};
+use triggers::TriggerEvents;
+use water::WaterEvents;
+use web_time::Instant;
+use window::{Window, WindowConfig, WindowEvents};
 use winit::{event::WindowEvent, event_loop::ActiveEventLoop};
 
+pub mod assets;
+pub mod audio;
+pub mod camera2d;
+pub mod character;
+pub mod cloth;
+pub mod collision;
+pub mod combat;
+pub mod debug_keys;
+pub mod dialogue;
+pub mod events;
+pub mod focus;
+#[cfg(feature = "gilrs")]
+pub mod gamepad_gilrs;
+pub mod health;
+#[cfg(feature = "inspector")]
+pub mod inspector;
+pub mod inventory;
+pub mod loading;
+pub mod lod;
+pub mod mods;
+pub mod music;
+pub mod parallel;
+pub mod physics2d;
+#[cfg(feature = "physics3d")]
+pub mod physics3d;
+pub mod quests;
+pub mod random;
+pub mod replay;
+pub mod resources;
+pub mod rope;
 mod runner;
+pub mod save;
+pub mod scene;
+pub mod schedule;
 pub mod spatial;
+mod spawning;
+pub mod tasks;
 pub mod tools;
+pub mod triggers;
+pub mod undo;
+pub mod vfs;
+pub mod water;
 pub mod window;
 
 //====================================================================
 
+/// Camera spawned by the engine when the app hasn't spawned one of its own.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DefaultCamera {
+    #[default]
+    None,
+    /// Perspective camera looking down +Z from `DEFAULT_CAMERA_DISTANCE` units back.
+    Perspective,
+    /// Orthographic camera centered on the origin, one unit per pixel.
+    Orthographic,
+}
+
+pub const DEFAULT_CAMERA_DISTANCE: f32 = 500.;
+
+#[derive(Debug, Clone, Default)]
+pub struct EngineConfig {
+    pub window: WindowConfig,
+    pub renderer: RendererConfig,
+    /// Key that captures the next frame with an attached graphics debugger (e.g. RenderDoc).
+    pub capture_key: Option<KeyCode>,
+    pub default_camera: DefaultCamera,
+    pub replay: ReplayConfig,
+    /// Periodically save the game and offer the save to `App::recover` after a crash.
+    pub autosave: Option<AutosaveConfig>,
+    /// Folder of asset packs mounted over the base assets before the app starts.
+    pub mods_directory: Option<PathBuf>,
+}
+
 pub struct Runner<A: App> {
     state: Option<OuterState>,
+    config: EngineConfig,
+    /// Error the renderer failed to start with, ending the event loop.
+    error: Option<RendererError>,
     default_app: PhantomData<A>,
 }
 
 impl<A: App> Runner<A> {
     #[inline]
     pub fn run() {
+        Self::run_with(EngineConfig::default());
+    }
+
+    /// Logs the error if the renderer can't be created, see [`Runner::try_run_with`].
+    pub fn run_with(config: EngineConfig) {
+        if let Err(e) = Self::try_run_with(config) {
+            log::error!("Unable to start App: {}", e);
+        }
+    }
+
+    /// Run until the app exits, or return the error the renderer couldn't be created with
+    /// so it can be shown to the user.
+    pub fn try_run_with(config: EngineConfig) -> Result<(), RendererError> {
+        let mut runner = Self {
+            state: None,
+            config,
+            error: None,
+            default_app: PhantomData,
+        };
+
         winit::event_loop::EventLoop::new()
             .unwrap()
-            .run_app(&mut Self {
-                state: None,
-                default_app: PhantomData,
-            })
+            .run_app(&mut runner)
             .unwrap();
+
+        match runner.error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Run without creating a window or event loop, rendering each frame into an offscreen
+    /// texture until the app calls [`State::exit`]. Read frames back with
+    /// [`RendererAccess::read_frame`]. Sized from `config.window.size`.
+    pub fn run_headless(config: EngineConfig) {
+        let mut state = match OuterState::new_headless::<A>(&config) {
+            Ok(state) => state,
+            Err(e) => {
+                log::error!("Unable to start headless App: {}", e);
+                return;
+            }
+        };
+
+        loop {
+            state.tick();
+
+            if state.state.replay.exit_requested() {
+                log::info!("Replay finished. Closing headless App");
+                break;
+            } else if state.state.exit_requested {
+                log::info!("Closing headless App");
+                break;
+            }
+        }
+
+        state.close();
     }
 }
 
@@ -43,6 +199,35 @@ pub trait App: 'static {
 
     fn resize(&mut self, state: &mut State, size: Size<u32>);
     fn update(&mut self, state: &mut State);
+
+    /// Called zero or more times per frame, before `update`, every `Time::fixed_delta`.
+    fn fixed_update(&mut self, state: &mut State, delta: f32) {
+        let _ = (state, delta);
+    }
+
+    /// Called instead of `update` while the simulation is paused with [`State::set_paused`],
+    /// e.g. to handle the input that resumes it.
+    fn paused_update(&mut self, state: &mut State) {
+        let _ = state;
+    }
+
+    /// Called when the window's close button is pressed. Return false to keep running,
+    /// e.g. to ask about unsaved changes first, then call [`State::exit`] once done.
+    fn close_requested(&mut self, state: &mut State) -> bool {
+        let _ = state;
+        true
+    }
+
+    /// Called once before the app closes, for cleanup.
+    fn on_exit(&mut self, state: &mut State) {
+        let _ = state;
+    }
+
+    /// Called after `new` when the last session didn't shut down cleanly, with its autosave.
+    /// Use [`State::load_game`] to restore it.
+    fn recover(&mut self, state: &mut State, save: SaveGame) {
+        let _ = (state, save);
+    }
 }
 
 //====================================================================
@@ -50,12 +235,50 @@ pub trait App: 'static {
 pub struct State {
     world: World,
     window: Window,
-    target_fps: Duration,
+    /// Shortest time between frames, or `None` to run uncapped.
+    target_frame_time: Option<Duration>,
     renderer: RendererState,
     keys: Input<KeyCode>,
     mouse_buttons: Input<MouseButton>,
     mouse_input: MouseInput,
+    gamepads: GamepadInput,
     time: Time,
+    assets: AssetServer,
+    tasks: TaskQueue,
+    events: EventBus,
+    resources: Resources,
+    schedule: Schedule,
+    workers: Arc<parallel::WorkerPool>,
+    lod: LodState,
+    audio: Audio,
+    mixer: AudioMixer,
+    music: MusicController,
+    forces: Forces,
+    #[cfg(feature = "inspector")]
+    inspector: Option<inspector::InspectorServer>,
+    water: WaterEvents,
+    triggers: TriggerEvents,
+    hits: HitEvents,
+    health: HealthEvents,
+    inventories: InventoryEvents,
+    dialogue: DialogueRunner,
+    quests: QuestLog,
+    rng: Rng,
+    replay: Replay,
+    autosave: Autosave,
+    orphan_policy: OrphanPolicy,
+    scene_registry: SceneRegistry,
+    focus: FocusManager,
+    focus_bindings: FocusBindings,
+    debug_bindings: DebugBindings,
+    debug_toggles: DebugToggles,
+    window_events: WindowEvents,
+    capture_key: Option<KeyCode>,
+    default_camera: Option<Entity>,
+    paused: bool,
+    /// Where the frame limited profile being recorded is saved.
+    profile_path: Option<PathBuf>,
+    exit_requested: bool,
 }
 
 impl State {
@@ -74,6 +297,108 @@ impl State {
         &self.window
     }
 
+    /// Close the app once the current frame finishes.
+    #[inline]
+    pub fn exit(&mut self) {
+        log::info!("Exit requested");
+        self.exit_requested = true;
+    }
+
+    #[inline]
+    pub fn exit_requested(&self) -> bool {
+        self.exit_requested
+    }
+
+    /// Cap the frame rate at `fps`. Zero or less runs uncapped.
+    #[inline]
+    pub fn set_target_fps(&mut self, fps: f32) {
+        self.target_frame_time = (fps > 0.).then(|| Duration::from_secs_f32(1. / fps));
+    }
+
+    /// Run frames as fast as possible, limited only by the present mode.
+    #[inline]
+    pub fn set_uncapped(&mut self) {
+        self.target_frame_time = None;
+    }
+
+    /// Frame rate cap, or `None` when uncapped.
+    #[inline]
+    pub fn target_fps(&self) -> Option<f32> {
+        self.target_frame_time
+            .map(|frame_time| 1. / frame_time.as_secs_f32())
+    }
+
+    /// Skip fixed updates, the [`Stage::Update`] systems and `App::update` while paused,
+    /// e.g. to inspect the world. `App::paused_update` runs in place of `App::update`
+    /// and everything else keeps running.
+    #[inline]
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    #[inline]
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Start recording a profile of every system and pipeline until [`State::stop_profile`].
+    #[inline]
+    pub fn start_profile(&mut self) {
+        self.profile_path = None;
+        profiler::start(None);
+    }
+
+    /// Record a profile of the next `frames` frames, then save it to `path` as a Chrome
+    /// trace, viewable in chrome://tracing or Perfetto.
+    #[inline]
+    pub fn record_profile(&mut self, frames: u32, path: impl Into<PathBuf>) {
+        self.profile_path = Some(path.into());
+        profiler::start(Some(frames));
+    }
+
+    #[inline]
+    pub fn stop_profile(&mut self) -> Option<Profile> {
+        self.profile_path = None;
+        profiler::stop()
+    }
+
+    #[inline]
+    pub fn profiling(&self) -> bool {
+        profiler::is_recording()
+    }
+
+    /// Serve the world to inspector clients on this machine at [`inspector::DEFAULT_ADDRESS`].
+    /// Clients have to authenticate with `token` before sending any other command.
+    /// Not available on wasm, see [`State::connect_inspector`].
+    #[cfg(all(feature = "inspector", not(target_arch = "wasm32")))]
+    pub fn start_inspector(&mut self, token: impl Into<String>) -> std::io::Result<()> {
+        self.start_inspector_at(inspector::DEFAULT_ADDRESS, token)
+    }
+
+    /// Like [`State::start_inspector`] on another address. Anything other than a loopback
+    /// address lets other machines connect.
+    #[cfg(all(feature = "inspector", not(target_arch = "wasm32")))]
+    pub fn start_inspector_at(
+        &mut self,
+        address: impl std::net::ToSocketAddrs,
+        token: impl Into<String>,
+    ) -> std::io::Result<()> {
+        self.inspector = Some(inspector::InspectorServer::bind(address, token)?);
+        Ok(())
+    }
+
+    /// Connect to an inspector tool serving a websocket at `url`, as browsers can't accept
+    /// connections. The tool has to authenticate with `token` before sending any other command.
+    #[cfg(all(feature = "inspector", target_arch = "wasm32"))]
+    pub fn connect_inspector(
+        &mut self,
+        url: &str,
+        token: impl Into<String>,
+    ) -> std::io::Result<()> {
+        self.inspector = Some(inspector::InspectorServer::connect(url, token)?);
+        Ok(())
+    }
+
     #[inline]
     pub fn renderer_mut<'a: 'b, 'b>(&'a mut self) -> RendererAccessMut<'b> {
         RendererAccessMut(self)
@@ -89,29 +414,551 @@ impl State {
         &self.keys
     }
 
+    #[inline]
+    pub fn keys_mut(&mut self) -> &mut Input<KeyCode> {
+        &mut self.keys
+    }
+
     #[inline]
     pub fn mouse_buttons(&self) -> &Input<MouseButton> {
         &self.mouse_buttons
     }
 
+    #[inline]
+    pub fn mouse_buttons_mut(&mut self) -> &mut Input<MouseButton> {
+        &mut self.mouse_buttons
+    }
+
     #[inline]
     pub fn mouse_input(&self) -> &MouseInput {
         &self.mouse_input
     }
 
+    #[inline]
+    pub fn gamepads(&self) -> &GamepadInput {
+        &self.gamepads
+    }
+
+    #[inline]
+    pub fn gamepads_mut(&mut self) -> &mut GamepadInput {
+        &mut self.gamepads
+    }
+
     #[inline]
     pub fn time(&self) -> &Time {
         &self.time
     }
+
+    #[inline]
+    pub fn time_mut(&mut self) -> &mut Time {
+        &mut self.time
+    }
+
+    /// Camera rendered to the whole surface. Either the camera set with [`State::set_main_camera`],
+    /// the engine spawned default camera or the first camera found.
+    pub fn main_camera(&self) -> Option<Entity> {
+        self.renderer
+            .main_camera()
+            .filter(|entity| self.world.contains(*entity))
+            .or_else(|| camera::find_camera(&self.world))
+    }
+
+    #[inline]
+    pub fn set_main_camera(&mut self, camera: Option<Entity>) {
+        self.renderer.set_main_camera(camera);
+    }
+
+    #[inline]
+    pub fn assets(&self) -> &AssetServer {
+        &self.assets
+    }
+
+    #[inline]
+    pub fn assets_mut(&mut self) -> &mut AssetServer {
+        &mut self.assets
+    }
+
+    #[inline]
+    pub fn tasks(&self) -> &TaskQueue {
+        &self.tasks
+    }
+
+    #[inline]
+    pub fn tasks_mut(&mut self) -> &mut TaskQueue {
+        &mut self.tasks
+    }
+
+    #[inline]
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    #[inline]
+    pub fn events_mut(&mut self) -> &mut EventBus {
+        &mut self.events
+    }
+
+    /// Send an event readable until the end of the next tick.
+    #[inline]
+    pub fn send_event<T: 'static>(&mut self, event: T) {
+        self.events.send(event);
+    }
+
+    /// Events of type `T` that `reader` hasn't read yet.
+    #[inline]
+    pub fn read_events<'a, T: 'static>(
+        &'a self,
+        reader: &mut EventReader<T>,
+    ) -> impl Iterator<Item = &'a T> {
+        self.events.read(reader)
+    }
+
+    #[inline]
+    pub fn resources(&self) -> &Resources {
+        &self.resources
+    }
+
+    #[inline]
+    pub fn resources_mut(&mut self) -> &mut Resources {
+        &mut self.resources
+    }
+
+    /// Resources along with the world, for systems needing both.
+    #[inline]
+    pub fn resources_with_world(&mut self) -> (&mut Resources, &mut World) {
+        (&mut self.resources, &mut self.world)
+    }
+
+    #[inline]
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    /// Run `system` every frame in `stage`, after the systems already in it. Systems that
+    /// can't be added are logged and skipped.
+    #[inline]
+    pub fn add_system(&mut self, stage: Stage, name: &'static str, system: System) -> &mut Self {
+        if let Err(e) = self.schedule.add(stage, name, system) {
+            log::error!("Unable to add system: {}", e);
+        }
+        self
+    }
+
+    /// Run `system` in `stage` where `order` places it, e.g.
+    /// `SystemOrder::new().after("engine::physics2d")`.
+    #[inline]
+    pub fn add_system_ordered(
+        &mut self,
+        stage: Stage,
+        name: &'static str,
+        system: System,
+        order: SystemOrder,
+    ) -> Result<&mut Self, ScheduleError> {
+        self.schedule.add_ordered(stage, name, system, order)?;
+        Ok(self)
+    }
+
+    /// Run a [`parallel::ParallelSystem`] every frame in `stage`, alongside the systems
+    /// around it whose access doesn't conflict with `access`. Systems that can't be added
+    /// are logged and skipped.
+    #[inline]
+    pub fn add_parallel_system(
+        &mut self,
+        stage: Stage,
+        name: &'static str,
+        access: parallel::Access,
+        system: parallel::ParallelSystem,
+    ) -> &mut Self {
+        if let Err(e) = self.schedule.add_parallel(stage, name, access, system) {
+            log::error!("Unable to add system: {}", e);
+        }
+        self
+    }
+
+    #[inline]
+    pub fn add_parallel_system_ordered(
+        &mut self,
+        stage: Stage,
+        name: &'static str,
+        access: parallel::Access,
+        system: parallel::ParallelSystem,
+        order: SystemOrder,
+    ) -> Result<&mut Self, ScheduleError> {
+        self.schedule
+            .add_parallel_ordered(stage, name, access, system, order)?;
+        Ok(self)
+    }
+
+    /// Stop running the system named `name`, including engine systems.
+    #[inline]
+    pub fn remove_system(&mut self, name: &str) -> bool {
+        self.schedule.remove(name)
+    }
+
+    /// Throttle [`lod::UpdateLod`] entities in the system named `name`, which reads
+    /// [`State::update_lod`] or [`parallel::SystemContext::update_lod`] to skip them. Engine cloth and ropes are throttled by default.
+    #[inline]
+    pub fn set_system_lod(&mut self, name: &str, enabled: bool) -> bool {
+        self.schedule.set_lod(name, enabled)
+    }
+
+    /// Which [`lod::UpdateLod`] entities the running system should update this tick.
+    #[inline]
+    pub fn update_lod(&self) -> LodTick {
+        self.lod.current
+    }
+
+    #[inline]
+    pub fn lod_settings(&self) -> &LodSettings {
+        &self.lod.settings
+    }
+
+    #[inline]
+    pub fn lod_settings_mut(&mut self) -> &mut LodSettings {
+        &mut self.lod.settings
+    }
+
+    /// Store a global value by its type, returning the one it replaced.
+    #[inline]
+    pub fn insert_resource<T: Send + Sync + 'static>(&mut self, resource: T) -> Option<T> {
+        self.resources.insert(resource)
+    }
+
+    #[inline]
+    pub fn resource<T: 'static>(&self) -> Option<&T> {
+        self.resources.get()
+    }
+
+    #[inline]
+    pub fn resource_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.resources.get_mut()
+    }
+
+    /// Engine resources such as [`Physics2D`] must stay inserted, so removing them does
+    /// nothing and returns `None`.
+    pub fn remove_resource<T: 'static>(&mut self) -> Option<T> {
+        if is_engine_resource::<T>() {
+            log::error!(
+                "Can't remove engine resource {}",
+                std::any::type_name::<T>()
+            );
+            return None;
+        }
+
+        self.resources.remove()
+    }
+
+    #[inline]
+    pub fn audio(&self) -> &Audio {
+        &self.audio
+    }
+
+    #[inline]
+    pub fn audio_mut(&mut self) -> &mut Audio {
+        &mut self.audio
+    }
+
+    #[inline]
+    pub fn mixer(&self) -> &AudioMixer {
+        &self.mixer
+    }
+
+    #[inline]
+    pub fn mixer_mut(&mut self) -> &mut AudioMixer {
+        &mut self.mixer
+    }
+
+    #[inline]
+    pub fn music(&self) -> &MusicController {
+        &self.music
+    }
+
+    #[inline]
+    pub fn music_mut(&mut self) -> &mut MusicController {
+        &mut self.music
+    }
+
+    #[inline]
+    pub fn forces(&self) -> &Forces {
+        &self.forces
+    }
+
+    #[inline]
+    pub fn forces_mut(&mut self) -> &mut Forces {
+        &mut self.forces
+    }
+
+    /// Stored as a resource, so the physics step can run alongside other systems.
+    #[inline]
+    pub fn physics2d(&self) -> &Physics2D {
+        self.resources
+            .get()
+            .expect("Physics2D is an engine resource")
+    }
+
+    #[inline]
+    pub fn physics2d_mut(&mut self) -> &mut Physics2D {
+        self.resources
+            .get_mut()
+            .expect("Physics2D is an engine resource")
+    }
+
+    /// 3D physics settings along with the world, for ray casts.
+    #[cfg(feature = "physics3d")]
+    #[inline]
+    pub fn physics(&self) -> physics3d::PhysicsQuery<'_> {
+        let physics = self.resources.get().expect("Physics is an engine resource");
+        physics3d::PhysicsQuery::new(physics, &self.world)
+    }
+
+    #[cfg(feature = "physics3d")]
+    #[inline]
+    pub fn physics_mut(&mut self) -> &mut physics3d::Physics {
+        self.resources
+            .get_mut()
+            .expect("Physics is an engine resource")
+    }
+
+    /// Bodies entering and leaving water volumes during the last frame.
+    #[inline]
+    pub fn water(&self) -> &WaterEvents {
+        &self.water
+    }
+
+    /// World along with the forces, for simulations outside the engine such as particles.
+    #[inline]
+    pub fn world_with_forces(&mut self) -> (&mut World, &Forces) {
+        (&mut self.world, &self.forces)
+    }
+
+    /// Trigger volume events from the last frame.
+    #[inline]
+    pub fn triggers(&self) -> &TriggerEvents {
+        &self.triggers
+    }
+
+    #[inline]
+    pub fn health(&self) -> &HealthEvents {
+        &self.health
+    }
+
+    #[inline]
+    pub fn health_mut(&mut self) -> &mut HealthEvents {
+        &mut self.health
+    }
+
+    /// Inventory changes from the last frame.
+    #[inline]
+    pub fn inventories(&self) -> &InventoryEvents {
+        &self.inventories
+    }
+
+    #[inline]
+    pub fn dialogue(&self) -> &DialogueRunner {
+        &self.dialogue
+    }
+
+    #[inline]
+    pub fn dialogue_mut(&mut self) -> &mut DialogueRunner {
+        &mut self.dialogue
+    }
+
+    #[inline]
+    pub fn quests(&self) -> &QuestLog {
+        &self.quests
+    }
+
+    #[inline]
+    pub fn quests_mut(&mut self) -> &mut QuestLog {
+        &mut self.quests
+    }
+
+    /// Seeded random numbers. Recorded sessions replay the same sequence.
+    #[inline]
+    pub fn rng_mut(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    /// Focus changes, dropped files and typed text from the last frame.
+    #[inline]
+    pub fn window_events(&self) -> &WindowEvents {
+        &self.window_events
+    }
+
+    #[inline]
+    pub fn focus(&self) -> &FocusManager {
+        &self.focus
+    }
+
+    /// Focus manager along with the world, for navigating focus manually.
+    #[inline]
+    pub fn focus_mut(&mut self) -> (&mut FocusManager, &mut World) {
+        (&mut self.focus, &mut self.world)
+    }
+
+    #[inline]
+    pub fn focus_bindings_mut(&mut self) -> &mut FocusBindings {
+        &mut self.focus_bindings
+    }
+
+    #[inline]
+    pub fn debug_bindings_mut(&mut self) -> &mut DebugBindings {
+        &mut self.debug_bindings
+    }
+
+    #[inline]
+    pub fn debug_toggles(&self) -> &DebugToggles {
+        &self.debug_toggles
+    }
+
+    #[inline]
+    pub fn debug_toggles_mut(&mut self) -> &mut DebugToggles {
+        &mut self.debug_toggles
+    }
+
+    /// Lines drawn for this frame only, by a `DebugRenderer` pipeline if one was added.
+    #[inline]
+    pub fn debug_lines(&mut self) -> &mut DebugLines {
+        self.renderer.debug_lines_mut()
+    }
+
+    /// Lock and hide the cursor and stop tracking its position, leaving only raw
+    /// mouse motion. Disabling restores the cursor where it was when enabled.
+    pub fn set_mouse_look(&mut self, enabled: bool) {
+        if self.mouse_input.mouse_look() == enabled {
+            return;
+        }
+
+        log::trace!("Setting mouse look: {}", enabled);
+
+        if enabled {
+            self.window.grab_cursor(true);
+            self.window.hide_cursor(true);
+        } else {
+            self.window.grab_cursor(false);
+            self.window.hide_cursor(false);
+            self.window.set_cursor_position(self.mouse_input.position());
+        }
+
+        tools::set_mouse_look(&mut self.mouse_input, enabled);
+    }
 }
 
 pub struct RendererAccessMut<'a>(&'a mut State);
 impl<'a> RendererAccessMut<'a> {
+    /// Add a pipeline to its own [`renderer::Renderer::group`], after the pipelines already
+    /// in that group. Pipelines that can't be placed are logged and skipped.
     #[inline]
-    pub fn add_renderer<R: renderer::Renderer>(&mut self, priority: usize) -> &mut Self {
+    pub fn add_renderer<R: renderer::Renderer>(&mut self) -> &mut Self {
+        if let Err(e) = self.add_renderer_ordered::<R>(PipelineOrder::new()) {
+            log::error!("Unable to add pipeline: {}", e);
+        }
+        self
+    }
+
+    /// Add a pipeline placed by `order`, e.g. `PipelineOrder::new().after::<ModelRenderer>()`.
+    #[inline]
+    pub fn add_renderer_ordered<R: renderer::Renderer>(
+        &mut self,
+        order: PipelineOrder,
+    ) -> Result<&mut Self, PipelineOrderError> {
+        self.update_shader_loader();
+        self.0
+            .renderer
+            .add_pipeline::<R>(&mut self.0.world, order)?;
+        Ok(self)
+    }
+
+    /// Add a fullscreen effect, run in ascending `order`. Requires `RendererConfig::post_processing`.
+    #[inline]
+    pub fn add_post_process<P: renderer::post_process::PostProcess>(
+        &mut self,
+        order: usize,
+    ) -> &mut Self {
+        self.update_shader_loader();
+        self.0.renderer.add_post_process::<P>(order);
+        self
+    }
+
+    /// Modules shaders can `#import`. Shaders can also `#include` asset files by path.
+    #[inline]
+    pub fn shader_library_mut(&mut self) -> &mut ShaderLibrary {
+        self.0.renderer.shader_library_mut()
+    }
+
+    /// Read shader includes through the currently mounted asset sources.
+    fn update_shader_loader(&mut self) {
+        let vfs = self.0.assets.vfs_snapshot();
         self.0
             .renderer
-            .add_pipeline::<R>(&mut self.0.world, priority);
+            .shader_library_mut()
+            .set_loader(move |path| vfs.read_to_string(path).ok());
+    }
+
+    #[inline]
+    pub fn post_process_mut<P: renderer::post_process::PostProcess>(&mut self) -> Option<&mut P> {
+        self.0.renderer.post_process_mut::<P>()
+    }
+
+    /// Load a font file at runtime, for text using its family.
+    #[inline]
+    pub fn load_font(&mut self, data: &[u8]) -> &mut Self {
+        self.0.renderer.load_font(data);
+        self
+    }
+
+    /// Load fonts and rasterize glyphs ahead of the first frame.
+    #[inline]
+    pub fn preload_fonts(&mut self, preload: &FontPreload) -> &FontLoadStatus {
+        self.0.renderer.preload_fonts(preload)
+    }
+
+    /// Capture the next frame with an attached graphics debugger such as RenderDoc.
+    #[inline]
+    pub fn capture_next_frame(&mut self) -> &mut Self {
+        self.0.renderer.capture_next_frame();
+        self
+    }
+
+    #[inline]
+    pub fn set_ambient_light(&mut self, ambient: AmbientLight) -> &mut Self {
+        self.0.renderer.set_ambient_light(ambient);
+        self
+    }
+
+    #[inline]
+    pub fn set_shadow_settings(&mut self, settings: ShadowSettings) -> &mut Self {
+        self.0.renderer.set_shadow_settings(settings);
+        self
+    }
+
+    /// Reconfigure the surface to present with `mode`, if the surface supports it.
+    #[inline]
+    pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) -> &mut Self {
+        self.0.renderer.set_present_mode(mode);
+        self
+    }
+
+    #[inline]
+    pub fn set_vsync(&mut self, vsync: bool) -> &mut Self {
+        self.set_present_mode(match vsync {
+            true => wgpu::PresentMode::AutoVsync,
+            false => wgpu::PresentMode::AutoNoVsync,
+        })
+    }
+
+    #[inline]
+    pub fn set_debug_settings(&mut self, settings: DebugSettings) -> &mut Self {
+        self.0.renderer.set_debug_settings(settings);
+        self
+    }
+
+    /// Enable or disable an added pipeline by its type name, e.g. `"ModelRenderer"`.
+    #[inline]
+    pub fn set_pipeline_enabled(&mut self, name: &str, enabled: bool) -> &mut Self {
+        if !self.0.renderer.set_pipeline_enabled(name, enabled) {
+            log::warn!("No pipeline named {}", name);
+        }
         self
     }
 }
@@ -127,13 +974,65 @@ impl<'a> RendererAccess<'a> {
         self.0.renderer.spawn_camera(builder, camera)
     }
 
+    #[inline]
+    pub fn create_render_target(&self, size: Size<u32>) -> RenderTarget {
+        self.0.renderer.create_render_target(size)
+    }
+
     #[inline]
     pub fn clone_default_texture(&self) -> Arc<LoadedTexture> {
         self.0.renderer.default_texture.clone()
     }
 
     pub fn core(&self) -> &renderer::RendererCore {
-        &self.0.renderer.core()
+        self.0.renderer.core()
+    }
+
+    #[inline]
+    pub fn stats(&self) -> &renderer::stats::RenderStats {
+        self.0.renderer.stats()
+    }
+
+    /// Names of the added pipelines in draw order, along with whether they're enabled.
+    #[inline]
+    pub fn pipelines(&self) -> impl Iterator<Item = (&'static str, bool)> + '_ {
+        self.0.renderer.pipelines()
+    }
+
+    /// Last rendered frame when running headless, see [`Runner::run_headless`].
+    #[inline]
+    pub fn read_frame(&self) -> Option<image::RgbaImage> {
+        self.0.renderer.read_frame()
+    }
+
+    #[inline]
+    pub fn font_status(&self) -> &FontLoadStatus {
+        self.0.renderer.font_status()
+    }
+
+    #[inline]
+    pub fn debug_settings(&self) -> &DebugSettings {
+        self.0.renderer.debug_settings()
+    }
+
+    #[inline]
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.0.renderer.core().present_mode()
+    }
+
+    #[inline]
+    pub fn vsync(&self) -> bool {
+        !matches!(
+            self.present_mode(),
+            wgpu::PresentMode::AutoNoVsync
+                | wgpu::PresentMode::Immediate
+                | wgpu::PresentMode::Mailbox
+        )
+    }
+
+    #[inline]
+    pub fn supported_present_modes(&self) -> &[wgpu::PresentMode] {
+        self.0.renderer.core().supported_present_modes()
     }
 }
 
@@ -145,27 +1044,111 @@ struct OuterState {
 }
 
 impl OuterState {
-    pub(crate) fn new<A: App>(event_loop: &ActiveEventLoop) -> Self {
-        let window = Window::new(event_loop);
+    pub(crate) fn new<A: App>(
+        event_loop: &ActiveEventLoop,
+        config: &EngineConfig,
+    ) -> Result<Self, RendererError> {
+        let window = Window::new(event_loop, &config.window);
         #[cfg(not(target_arch = "wasm32"))]
         let window_size = window.size();
         #[cfg(target_arch = "wasm32")]
-        let window_size = Size::new(450, 400);
+        let window_size = config.window.size.unwrap_or(Size::new(450, 400));
+
+        let renderer = RendererState::try_new_with_config(
+            window.inner().unwrap().clone(),
+            window_size,
+            config.renderer.clone(),
+        )?;
 
-        let renderer = RendererState::new(window.0.clone(), window_size);
+        Ok(Self::from_parts::<A>(window, renderer, window_size, config))
+    }
+
+    pub(crate) fn new_headless<A: App>(config: &EngineConfig) -> Result<Self, RendererError> {
+        let window_size = config.window.size.unwrap_or(Size::new(1280, 720));
+
+        let window = Window::headless(window_size);
+        let renderer = RendererState::try_new_headless(window_size, config.renderer.clone())?;
+
+        Ok(Self::from_parts::<A>(window, renderer, window_size, config))
+    }
+
+    fn from_parts<A: App>(
+        window: Window,
+        renderer: RendererState,
+        window_size: Size<u32>,
+        config: &EngineConfig,
+    ) -> Self {
+        let workers = Arc::new(parallel::WorkerPool::new());
 
         let mut state = State {
             world: World::new(),
             window,
-            target_fps: Duration::from_secs_f32(1. / 75.),
+            target_frame_time: Some(Duration::from_secs_f32(1. / 75.)),
             renderer,
             keys: Input::default(),
             mouse_buttons: Input::default(),
             mouse_input: MouseInput::default(),
+            gamepads: GamepadInput::default(),
             time: Time::default(),
+            assets: AssetServer::default(),
+            tasks: TaskQueue::new(workers.clone()),
+            events: EventBus::default(),
+            resources: Resources::default(),
+            schedule: schedule::engine_schedule(),
+            workers,
+            lod: LodState::default(),
+            audio: Audio::default(),
+            mixer: AudioMixer::default(),
+            music: MusicController::default(),
+            forces: Forces::default(),
+            #[cfg(feature = "inspector")]
+            inspector: None,
+            water: WaterEvents::default(),
+            triggers: TriggerEvents::default(),
+            hits: HitEvents::default(),
+            health: HealthEvents::default(),
+            inventories: InventoryEvents::default(),
+            dialogue: DialogueRunner::default(),
+            quests: QuestLog::default(),
+            rng: Rng::default(),
+            replay: Replay::new(&config.replay),
+            autosave: Autosave::new(config.autosave.clone()),
+            orphan_policy: OrphanPolicy::default(),
+            scene_registry: SceneRegistry::default(),
+            focus: FocusManager::default(),
+            focus_bindings: FocusBindings::default(),
+            debug_bindings: DebugBindings::default(),
+            debug_toggles: DebugToggles::default(),
+            window_events: WindowEvents::default(),
+            capture_key: config.capture_key,
+            default_camera: None,
+            paused: false,
+            profile_path: None,
+            exit_requested: false,
         };
 
-        let app = Box::new(A::new(&mut state));
+        state.resources.insert(Physics2D::default());
+        #[cfg(feature = "physics3d")]
+        state.resources.insert(physics3d::Physics::default());
+
+        if let Some(directory) = &config.mods_directory {
+            state.assets.load_mods(directory);
+        }
+
+        if let Some(seed) = state.replay.seed() {
+            state.rng = Rng::new(seed);
+        }
+
+        let mut app = Box::new(A::new(&mut state));
+
+        if let Some(save) = state.autosave.take_recovered() {
+            log::info!("Offering autosave to app for recovery");
+            app.recover(&mut state, save);
+        }
+
+        if state.main_camera().is_none() {
+            spawn_default_camera(&mut state, config.default_camera, window_size);
+        }
 
         Self { state, app }
     }
@@ -193,48 +1176,154 @@ impl OuterState {
                 };
 
                 self.state.renderer.resize(size);
+                resize_default_camera(&mut self.state, size);
                 self.app.resize(&mut self.state, size);
             }
 
-            WindowEvent::CloseRequested => {
-                log::info!("Window close requested. Closing App");
-                event_loop.exit();
-            }
+            WindowEvent::CloseRequested => match self.app.close_requested(&mut self.state) {
+                true => {
+                    log::info!("Window close requested. Closing App");
+                    self.exit(event_loop);
+                }
+                false => log::info!("Window close request refused by app"),
+            },
 
             WindowEvent::Destroyed => log::error!("Window was destroyed."),
 
             WindowEvent::KeyboardInput { event, .. } => {
                 if let winit::keyboard::PhysicalKey::Code(key) = event.physical_key {
-                    tools::process_inputs(&mut self.state.keys, key, event.state.is_pressed());
+                    replay::process_input(
+                        &mut self.state,
+                        InputEvent::Key {
+                            key,
+                            pressed: event.state.is_pressed(),
+                        },
+                    );
+                }
+
+                if let (true, Some(text)) = (event.state.is_pressed(), event.text) {
+                    // Control characters such as backspace are left to key input
+                    let text = text.chars().filter(|c| !c.is_control()).collect::<String>();
+                    if !text.is_empty() {
+                        self.state
+                            .window_events
+                            .push(window::WindowEvent::Text(text));
+                    }
                 }
             }
 
+            WindowEvent::Ime(winit::event::Ime::Commit(text)) => {
+                self.state
+                    .window_events
+                    .push(window::WindowEvent::Text(text));
+            }
+
+            WindowEvent::Focused(focused) => {
+                log::trace!("Window focused: {}", focused);
+
+                // Releases happening while unfocused are never seen, so let go of everything
+                if !focused {
+                    tools::pressed_inputs(&self.state.keys)
+                        .into_iter()
+                        .for_each(|key| {
+                            replay::process_input(
+                                &mut self.state,
+                                InputEvent::Key {
+                                    key,
+                                    pressed: false,
+                                },
+                            )
+                        });
+                    tools::pressed_inputs(&self.state.mouse_buttons)
+                        .into_iter()
+                        .for_each(|button| {
+                            replay::process_input(
+                                &mut self.state,
+                                InputEvent::MouseButton {
+                                    button,
+                                    pressed: false,
+                                },
+                            )
+                        });
+                }
+
+                self.state
+                    .window_events
+                    .push(window::WindowEvent::Focused(focused));
+            }
+
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                log::info!("Window scale factor changed to {}", scale_factor);
+                self.state
+                    .window_events
+                    .push(window::WindowEvent::ScaleFactorChanged(scale_factor));
+            }
+
+            WindowEvent::DroppedFile(path) => {
+                self.state
+                    .window_events
+                    .push(window::WindowEvent::FileDropped(path));
+            }
+
+            WindowEvent::HoveredFile(path) => {
+                self.state
+                    .window_events
+                    .push(window::WindowEvent::FileHovered(path));
+            }
+
+            WindowEvent::HoveredFileCancelled => {
+                self.state
+                    .window_events
+                    .push(window::WindowEvent::FileHoverCancelled);
+            }
+
             WindowEvent::MouseInput { state, button, .. } => {
-                tools::process_inputs(&mut self.state.mouse_buttons, button, state.is_pressed());
+                replay::process_input(
+                    &mut self.state,
+                    InputEvent::MouseButton {
+                        button,
+                        pressed: state.is_pressed(),
+                    },
+                );
             }
 
             WindowEvent::CursorMoved { position, .. } => {
-                tools::process_mouse_position(&mut self.state.mouse_input, position.into());
+                replay::process_input(
+                    &mut self.state,
+                    InputEvent::CursorMoved(glam::vec2(position.x as f32, position.y as f32)),
+                );
             }
 
-            WindowEvent::MouseWheel { delta, .. } => match delta {
-                winit::event::MouseScrollDelta::LineDelta(x, y) => {
-                    tools::process_mouse_scroll(&mut self.state.mouse_input, (x, y))
-                }
-                winit::event::MouseScrollDelta::PixelDelta(physical_position) => {
-                    tools::process_mouse_scroll(
-                        &mut self.state.mouse_input,
-                        (physical_position.x as f32, physical_position.y as f32),
-                    )
-                }
-            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let delta = match delta {
+                    winit::event::MouseScrollDelta::LineDelta(x, y) => glam::vec2(x, y),
+                    winit::event::MouseScrollDelta::PixelDelta(physical_position) => {
+                        glam::vec2(physical_position.x as f32, physical_position.y as f32)
+                    }
+                };
+                replay::process_input(&mut self.state, InputEvent::Scroll(delta));
+            }
             //
             WindowEvent::RedrawRequested => {
-                event_loop.set_control_flow(winit::event_loop::ControlFlow::wait_duration(
-                    self.state.target_fps,
-                ));
+                let frame_start = Instant::now();
 
                 self.tick();
+
+                // Wait out the rest of the frame rather than the whole frame time after it
+                event_loop.set_control_flow(match self.state.target_frame_time {
+                    Some(frame_time) => winit::event_loop::ControlFlow::WaitUntil(
+                        (frame_start + frame_time).max(Instant::now()),
+                    ),
+                    None => winit::event_loop::ControlFlow::Poll,
+                });
+
+                if self.state.replay.exit_requested() {
+                    log::info!("Replay finished. Closing App");
+                    self.exit(event_loop);
+                } else if self.state.exit_requested {
+                    log::info!("Closing App");
+                    self.exit(event_loop);
+                }
             }
 
             _ => {}
@@ -247,32 +1336,153 @@ impl OuterState {
         _device_id: winit::event::DeviceId,
         event: winit::event::DeviceEvent,
     ) {
-        match event {
-            winit::event::DeviceEvent::MouseMotion { delta } => {
-                tools::process_mouse_motion(&mut self.state.mouse_input, delta);
-            }
-            _ => {}
+        if let winit::event::DeviceEvent::MouseMotion { delta } = event {
+            replay::process_input(
+                &mut self.state,
+                InputEvent::Motion(glam::vec2(delta.0 as f32, delta.1 as f32)),
+            );
         }
     }
 
+    fn exit(&mut self, event_loop: &ActiveEventLoop) {
+        self.close();
+        event_loop.exit();
+    }
+
+    fn close(&mut self) {
+        self.app.on_exit(&mut self.state);
+        replay::close(&mut self.state);
+        save::close(&mut self.state);
+    }
+
     #[inline]
     pub fn request_redraw(&self) {
-        self.state.window.0.request_redraw();
+        if let Some(window) = self.state.window.inner() {
+            window.request_redraw();
+        }
     }
 
     pub fn tick(&mut self) {
+        profiler::begin_frame();
+        if let Some(profile) = profiler::take_finished() {
+            if let Some(path) = self.state.profile_path.take() {
+                match profile.save(&path) {
+                    Ok(()) => log::info!("Saved profile to {:?}", path),
+                    Err(e) => log::error!("Unable to save profile to {:?}: {}", path, e),
+                }
+            }
+        }
+
+        let _frame = profiler::scope("frame", "engine");
+
         tools::tick_time(&mut self.state.time);
+        self.state.forces.tick(self.state.time.delta_seconds());
+        schedule::run_stage(&mut self.state, Stage::PreUpdate);
+
+        let fixed_steps = match self.state.paused {
+            true => 0,
+            false => tools::tick_fixed_time(&mut self.state.time),
+        };
+        let fixed_delta = self.state.time.fixed_delta_seconds();
+        self.state.physics2d_mut().clear_events();
+        self.state.water.clear_events();
+        (0..fixed_steps).for_each(|_| {
+            let _scope = profiler::scope("App::fixed_update", "app");
+            self.app.fixed_update(&mut self.state, fixed_delta);
+            schedule::run_stage(&mut self.state, Stage::FixedUpdate);
+        });
+
+        match self.state.paused {
+            true => {
+                let _scope = profiler::scope("App::paused_update", "app");
+                self.app.paused_update(&mut self.state);
+            }
+            false => {
+                schedule::run_stage(&mut self.state, Stage::Update);
+
+                let _scope = profiler::scope("App::update", "app");
+                self.app.update(&mut self.state);
+            }
+        }
+        schedule::run_stage(&mut self.state, Stage::PostUpdate);
+        schedule::run_stage(&mut self.state, Stage::Render);
+        schedule::run_stage(&mut self.state, Stage::Last);
+    }
+}
 
-        self.app.update(&mut self.state);
+//====================================================================
 
-        spatial::process_global_transform(&mut self.state);
-        spatial::process_transform_hierarchy(&mut self.state);
+/// Resources the engine inserts itself and expects to always find.
+fn is_engine_resource<T: 'static>() -> bool {
+    let id = std::any::TypeId::of::<T>();
 
-        self.state.renderer.tick(&mut self.state.world);
+    #[cfg(feature = "physics3d")]
+    if id == std::any::TypeId::of::<physics3d::Physics>() {
+        return true;
+    }
+
+    id == std::any::TypeId::of::<Physics2D>()
+}
+
+fn spawn_default_camera(state: &mut State, camera: DefaultCamera, size: Size<u32>) {
+    let mut builder = EntityBuilder::new();
+
+    match camera {
+        DefaultCamera::None => {
+            log::warn!("No camera spawned - nothing will be rendered until a camera exists");
+            return;
+        }
+        DefaultCamera::Perspective => {
+            log::info!("Spawning default perspective camera");
+            state.renderer.spawn_camera(
+                &mut builder,
+                PerspectiveCamera {
+                    aspect: size.width as f32 / size.height as f32,
+                    ..Default::default()
+                },
+            );
+            builder.add(Transform::from_translation((
+                0.,
+                0.,
+                -DEFAULT_CAMERA_DISTANCE,
+            )));
+        }
+        DefaultCamera::Orthographic => {
+            log::info!("Spawning default orthographic camera");
+            state.renderer.spawn_camera(
+                &mut builder,
+                OrthographicCamera::new_centered(size.width as f32 / 2., size.height as f32 / 2.),
+            );
+            builder.add(Transform::default());
+        }
+    }
+
+    builder.add(GlobalTransform::default());
+
+    let entity = state.world.spawn(builder.build());
+    state.default_camera = Some(entity);
+    state.renderer.set_main_camera(Some(entity));
+}
+
+fn resize_default_camera(state: &mut State, size: Size<u32>) {
+    let Some(entity) = state.default_camera else {
+        return;
+    };
+
+    let Ok((perspective, orthographic)) = state.world.query_one_mut::<(
+        Option<&mut PerspectiveCamera>,
+        Option<&mut OrthographicCamera>,
+    )>(entity) else {
+        state.default_camera = None;
+        return;
+    };
+
+    if let Some(perspective) = perspective {
+        perspective.aspect = size.width as f32 / size.height as f32;
+    }
 
-        tools::reset_input(&mut self.state.keys);
-        tools::reset_input(&mut self.state.mouse_buttons);
-        tools::reset_mouse_input(&mut self.state.mouse_input);
+    if let Some(orthographic) = orthographic {
+        orthographic.set_size(size.width as f32, size.height as f32);
     }
 }
 
@@ -0,0 +1,216 @@
+//====================================================================
+
+use std::collections::VecDeque;
+
+use common::Size;
+use web_time::{Duration, Instant};
+
+use crate::{App, State};
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadStatus {
+    Done,
+    /// Task is waiting on something (e.g. a background load) and will be polled again next frame.
+    Pending,
+}
+
+type LoadTask = Box<dyn FnMut(&mut State) -> LoadStatus>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoadProgress {
+    pub completed: usize,
+    pub total: usize,
+    /// Name of the task currently being worked on.
+    pub current: Option<&'static str>,
+}
+
+impl LoadProgress {
+    #[inline]
+    pub fn fraction(&self) -> f32 {
+        match self.total {
+            0 => 1.,
+            total => self.completed as f32 / total as f32,
+        }
+    }
+
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        self.completed >= self.total
+    }
+}
+
+/// Loading work spread across frames so the window stays responsive.
+pub struct LoadQueue {
+    tasks: VecDeque<(&'static str, LoadTask)>,
+    completed: usize,
+    total: usize,
+    budget: Duration,
+}
+
+impl Default for LoadQueue {
+    fn default() -> Self {
+        Self {
+            tasks: VecDeque::new(),
+            completed: 0,
+            total: 0,
+            budget: Duration::from_millis(8),
+        }
+    }
+}
+
+impl LoadQueue {
+    /// Time spent running tasks each frame. At least one task is always run.
+    #[inline]
+    pub fn set_budget(&mut self, budget: Duration) {
+        self.budget = budget;
+    }
+
+    /// Add a task that completes in a single call.
+    pub fn push(&mut self, name: &'static str, task: impl FnOnce(&mut State) + 'static) {
+        let mut task = Some(task);
+        self.push_polled(name, move |state| {
+            if let Some(task) = task.take() {
+                task(state);
+            }
+            LoadStatus::Done
+        });
+    }
+
+    /// Add a task that is polled every frame until it returns [`LoadStatus::Done`].
+    pub fn push_polled(
+        &mut self,
+        name: &'static str,
+        task: impl FnMut(&mut State) -> LoadStatus + 'static,
+    ) {
+        self.tasks.push_back((name, Box::new(task)));
+        self.total += 1;
+    }
+
+    #[inline]
+    pub fn progress(&self) -> LoadProgress {
+        LoadProgress {
+            completed: self.completed,
+            total: self.total,
+            current: self.tasks.front().map(|(name, _)| *name),
+        }
+    }
+
+    /// Run tasks until the frame budget is used up or only pending tasks remain.
+    pub fn run(&mut self, state: &mut State) {
+        let start = Instant::now();
+        let mut pending = VecDeque::new();
+
+        while let Some((name, mut task)) = self.tasks.pop_front() {
+            match task(state) {
+                LoadStatus::Done => {
+                    log::trace!("Finished loading task '{}'", name);
+                    self.completed += 1;
+                }
+                LoadStatus::Pending => pending.push_back((name, task)),
+            }
+
+            if start.elapsed() >= self.budget {
+                break;
+            }
+        }
+
+        // Keep pending tasks ahead of untouched ones so they're polled first next frame
+        pending.append(&mut self.tasks);
+        self.tasks = pending;
+    }
+}
+
+//====================================================================
+
+/// Lightweight app shown while a [`LoadQueue`] runs.
+pub trait LoadingScreen: 'static {
+    /// Create the loading screen and queue up everything that needs loading.
+    fn new(state: &mut State, queue: &mut LoadQueue) -> Self
+    where
+        Self: Sized;
+
+    fn update(&mut self, state: &mut State, progress: LoadProgress);
+
+    /// Called once loading completes, before the game app is created. Clean up any loading entities here.
+    fn finish(self, state: &mut State)
+    where
+        Self: Sized,
+    {
+        let _ = state;
+    }
+
+    fn resize(&mut self, state: &mut State, size: Size<u32>) {
+        let _ = (state, size);
+    }
+}
+
+enum Phase<L, G> {
+    Loading { screen: L, queue: LoadQueue },
+    Running(G),
+    Swapping,
+}
+
+/// App that runs loading screen `L` until its queue is finished before switching to `G`.
+/// Use as `Runner::<Loading<MyLoadingScreen, MyGame>>::run()`.
+pub struct Loading<L: LoadingScreen, G: App> {
+    phase: Phase<L, G>,
+}
+
+impl<L: LoadingScreen, G: App> App for Loading<L, G> {
+    fn new(state: &mut State) -> Self {
+        let mut queue = LoadQueue::default();
+        let screen = L::new(state, &mut queue);
+
+        Self {
+            phase: Phase::Loading { screen, queue },
+        }
+    }
+
+    fn resize(&mut self, state: &mut State, size: Size<u32>) {
+        match &mut self.phase {
+            Phase::Loading { screen, .. } => screen.resize(state, size),
+            Phase::Running(app) => app.resize(state, size),
+            Phase::Swapping => {}
+        }
+    }
+
+    fn fixed_update(&mut self, state: &mut State, delta: f32) {
+        if let Phase::Running(app) = &mut self.phase {
+            app.fixed_update(state, delta);
+        }
+    }
+
+    fn update(&mut self, state: &mut State) {
+        match &mut self.phase {
+            Phase::Loading { screen, queue } => {
+                queue.run(state);
+
+                let progress = queue.progress();
+                screen.update(state, progress);
+
+                if !progress.is_done() {
+                    return;
+                }
+            }
+            Phase::Running(app) => return app.update(state),
+            Phase::Swapping => return,
+        }
+
+        log::info!("Loading finished - starting app");
+
+        if let Phase::Loading { screen, .. } = std::mem::replace(&mut self.phase, Phase::Swapping) {
+            screen.finish(state);
+        }
+
+        self.phase = Phase::Running(G::new(state));
+    }
+    fn paused_update(&mut self, state: &mut State) {
+        if let Phase::Running(app) = &mut self.phase {
+            app.paused_update(state);
+        }
+    }
+}
+
+//====================================================================
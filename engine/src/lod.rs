@@ -0,0 +1,160 @@
+//====================================================================
+
+use common::GlobalTransform;
+use hecs::Entity;
+
+use crate::{schedule::Stage, State};
+
+//====================================================================
+
+/// Where [`UpdateLod`] distances are measured from, usually the player. The main camera is
+/// used without one.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LodAnchor;
+
+/// Run the expensive systems of this entity less often the further it is from the
+/// [`LodAnchor`]. Only systems that opted in with [`State::set_system_lod`] are throttled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UpdateLod {
+    /// Multiplies the distance of each level, e.g. 2 to stay at full rate twice as far.
+    pub range_scale: f32,
+    interval: u32,
+}
+
+impl Default for UpdateLod {
+    fn default() -> Self {
+        Self {
+            range_scale: 1.,
+            interval: 1,
+        }
+    }
+}
+
+impl UpdateLod {
+    #[inline]
+    pub fn new(range_scale: f32) -> Self {
+        Self {
+            range_scale,
+            ..Default::default()
+        }
+    }
+
+    /// Ticks between updates at the current distance.
+    #[inline]
+    pub fn interval(&self) -> u32 {
+        self.interval
+    }
+}
+
+//--------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LodSettings {
+    /// Distance each level starts at with the ticks between updates past it, sorted by
+    /// distance. Nearer than the first level updates every tick.
+    pub levels: Vec<(f32, u32)>,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            levels: vec![(40., 2), (80., 4), (160., 8)],
+        }
+    }
+}
+
+impl LodSettings {
+    pub fn interval(&self, distance: f32) -> u32 {
+        self.levels
+            .iter()
+            .take_while(|(start, _)| distance >= *start)
+            .last()
+            .map(|(_, interval)| (*interval).max(1))
+            .unwrap_or(1)
+    }
+}
+
+//--------------------------------------------------
+
+/// Whether [`UpdateLod`] entities are due in the system currently running. Get it from
+/// [`State::update_lod`] before querying the world.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LodTick {
+    /// The running system opted in to throttling.
+    pub enabled: bool,
+    /// Times the running system's stage has run.
+    pub tick: u64,
+}
+
+impl LodTick {
+    /// Entities update on different ticks of their interval, spreading the work out.
+    #[inline]
+    pub fn due(&self, entity: Entity, lod: Option<&UpdateLod>) -> bool {
+        match (self.enabled, lod) {
+            (true, Some(lod)) if lod.interval > 1 => {
+                (self.tick + entity.id() as u64).is_multiple_of(lod.interval as u64)
+            }
+            _ => true,
+        }
+    }
+
+    /// `delta` scaled up to cover the ticks skipped since the entity last updated.
+    #[inline]
+    pub fn delta(&self, lod: Option<&UpdateLod>, delta: f32) -> f32 {
+        match (self.enabled, lod) {
+            (true, Some(lod)) => delta * lod.interval as f32,
+            _ => delta,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct LodState {
+    pub settings: LodSettings,
+    pub current: LodTick,
+    ticks: [u64; 6],
+}
+
+impl LodState {
+    /// Count a run of `stage`, returning the tick its systems see.
+    #[inline]
+    pub fn begin_stage(&mut self, stage: Stage) -> u64 {
+        let tick = self.ticks[stage as usize];
+        self.ticks[stage as usize] += 1;
+        tick
+    }
+}
+
+//====================================================================
+
+pub(crate) fn process_update_lod(state: &mut State) {
+    let anchor = state
+        .world
+        .query::<(&LodAnchor, &GlobalTransform)>()
+        .iter()
+        .next()
+        .map(|(_, (_, transform))| transform.translation())
+        .or_else(|| {
+            let camera = state.main_camera()?;
+            let transform = state.world.get::<&GlobalTransform>(camera).ok()?;
+            Some(transform.translation())
+        });
+
+    let settings = &state.lod.settings;
+
+    state
+        .world
+        .query_mut::<(&mut UpdateLod, Option<&GlobalTransform>)>()
+        .into_iter()
+        .for_each(|(_, (lod, transform))| {
+            lod.interval = match (anchor, transform) {
+                (Some(anchor), Some(transform)) => {
+                    let distance = anchor.distance(transform.translation());
+                    settings.interval(distance / lod.range_scale.max(f32::EPSILON))
+                }
+                _ => 1,
+            };
+        });
+}
+
+//====================================================================
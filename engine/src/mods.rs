@@ -0,0 +1,155 @@
+//====================================================================
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    assets::AssetServer,
+    vfs::{DirectorySource, PakSource, VfsSource, ZipSource},
+};
+
+//====================================================================
+
+const MANIFEST_FILE: &str = "mod.json";
+
+/// Optional `mod.json` at the root of a pack.
+///
+/// ```json
+/// { "name": "Better Textures", "version": "1.2", "priority": 10 }
+/// ```
+#[derive(Debug, Default, Deserialize)]
+struct ModManifest {
+    name: Option<String>,
+    #[serde(default)]
+    version: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    priority: i32,
+}
+
+/// A mounted asset pack. Files in a pack replace base assets with the same path, and packs
+/// with a higher priority replace those with a lower one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModInfo {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub priority: i32,
+    /// Folder or archive the pack was loaded from.
+    pub path: PathBuf,
+}
+
+fn open_pack(path: &Path) -> std::io::Result<Option<Arc<dyn VfsSource>>> {
+    if path.is_dir() {
+        return Ok(Some(Arc::new(DirectorySource::new(path))));
+    }
+
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "zip" => Ok(Some(Arc::new(ZipSource::open(path)?))),
+        "pak" => Ok(Some(Arc::new(PakSource::open(path)?))),
+        _ => Ok(None),
+    }
+}
+
+fn read_manifest(source: &dyn VfsSource, path: &Path) -> ModInfo {
+    let manifest = match source.read(MANIFEST_FILE) {
+        Some(Ok(bytes)) => serde_json::from_slice::<ModManifest>(&bytes).unwrap_or_else(|e| {
+            log::warn!("Invalid mod manifest in {:?}: {}", path, e);
+            ModManifest::default()
+        }),
+        Some(Err(e)) => {
+            log::warn!("Unable to read mod manifest in {:?}: {}", path, e);
+            ModManifest::default()
+        }
+        None => ModManifest::default(),
+    };
+
+    let name = manifest.name.unwrap_or_else(|| {
+        path.file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    });
+
+    ModInfo {
+        name,
+        version: manifest.version,
+        description: manifest.description,
+        priority: manifest.priority,
+        path: path.to_path_buf(),
+    }
+}
+
+//====================================================================
+
+impl AssetServer {
+    /// Mount every pack (folder, zip or pak file) in `directory`, returning how many were mounted.
+    /// Assets that are already loaded keep using the files they were loaded from.
+    pub fn load_mods(&mut self, directory: impl AsRef<Path>) -> usize {
+        let directory = directory.as_ref();
+
+        let entries = match std::fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::info!("No mods loaded from {:?}: {}", directory, e);
+                return 0;
+            }
+        };
+
+        let mut paths = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        let packs = paths
+            .into_iter()
+            .filter_map(|path| match open_pack(&path) {
+                Ok(source) => source.map(|source| (path, source)),
+                Err(e) => {
+                    log::error!("Unable to open mod {:?}: {}", path, e);
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let count = packs.len();
+
+        packs.into_iter().for_each(|(path, source)| {
+            let info = read_manifest(source.as_ref(), &path);
+            log::info!("Loaded mod '{}' with priority {}", info.name, info.priority);
+
+            self.mount_mod(info, source);
+        });
+
+        count
+    }
+
+    /// Mount a single pack over the base assets.
+    pub fn mount_mod(&mut self, info: ModInfo, source: Arc<dyn VfsSource>) {
+        self.vfs_mut().mount(info.priority, source);
+
+        let index = self
+            .mods
+            .iter()
+            .position(|loaded| loaded.priority < info.priority)
+            .unwrap_or(self.mods.len());
+        self.mods.insert(index, info);
+    }
+
+    /// Mounted packs from highest to lowest priority.
+    #[inline]
+    pub fn mods(&self) -> &[ModInfo] {
+        &self.mods
+    }
+}
+
+//====================================================================
@@ -0,0 +1,326 @@
+//====================================================================
+
+use std::collections::VecDeque;
+
+use web_time::Duration;
+
+use crate::State;
+
+//====================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MusicTrack {
+    /// Name or path of the audio to play.
+    pub name: String,
+    pub length: Duration,
+    /// Where playback jumps back to once the track ends, after any intro.
+    /// Tracks without a loop start finish and move on to the next queued track.
+    pub loop_start: Option<Duration>,
+    pub bpm: Option<f32>,
+    pub beats_per_bar: u32,
+}
+
+impl MusicTrack {
+    #[inline]
+    pub fn new(name: impl Into<String>, length: Duration) -> Self {
+        Self {
+            name: name.into(),
+            length,
+            loop_start: None,
+            bpm: None,
+            beats_per_bar: 4,
+        }
+    }
+
+    /// Loop the whole track.
+    #[inline]
+    pub fn looping(mut self) -> Self {
+        self.loop_start = Some(Duration::ZERO);
+        self
+    }
+
+    /// Play `intro` once, then loop the rest of the track.
+    #[inline]
+    pub fn with_intro(mut self, intro: Duration) -> Self {
+        self.loop_start = Some(intro.min(self.length));
+        self
+    }
+
+    #[inline]
+    pub fn with_bpm(mut self, bpm: f32, beats_per_bar: u32) -> Self {
+        self.bpm = Some(bpm);
+        self.beats_per_bar = beats_per_bar.max(1);
+        self
+    }
+
+    fn beat_length(&self) -> Option<f32> {
+        self.bpm.filter(|bpm| *bpm > 0.).map(|bpm| 60. / bpm)
+    }
+}
+
+//--------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MusicBeat {
+    /// Beats since the track started, counting loops.
+    pub beat: u64,
+    pub bar: u64,
+    /// Beat within the bar, starting at 0.
+    pub beat_in_bar: u32,
+}
+
+impl MusicBeat {
+    #[inline]
+    pub fn is_bar_start(&self) -> bool {
+        self.beat_in_bar == 0
+    }
+}
+
+//--------------------------------------------------
+
+/// Currently playing (or fading out) track. Audio backends follow these to play the music.
+#[derive(Debug, Clone)]
+pub struct MusicVoice {
+    track: MusicTrack,
+    position: Duration,
+    played: Duration,
+    gain: f32,
+    fade: Option<(f32, f32)>,
+}
+
+impl MusicVoice {
+    fn new(track: MusicTrack, fade_in: Duration) -> Self {
+        let (gain, fade) = match fade_in.is_zero() {
+            true => (1., None),
+            false => (0., Some((1., fade_in.as_secs_f32()))),
+        };
+
+        Self {
+            track,
+            position: Duration::ZERO,
+            played: Duration::ZERO,
+            gain,
+            fade,
+        }
+    }
+
+    #[inline]
+    pub fn track(&self) -> &MusicTrack {
+        &self.track
+    }
+
+    /// Position within the track.
+    #[inline]
+    pub fn position(&self) -> Duration {
+        self.position
+    }
+
+    /// Crossfade gain, from 0 to 1.
+    #[inline]
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    fn fade_to(&mut self, target: f32, duration: Duration) {
+        match duration.is_zero() {
+            true => {
+                self.gain = target;
+                self.fade = None;
+            }
+            false => self.fade = Some((target, duration.as_secs_f32())),
+        }
+    }
+
+    /// Returns true once the track has ended.
+    fn tick(&mut self, delta: Duration, beats: &mut Vec<MusicBeat>) -> bool {
+        if let Some((target, duration)) = self.fade {
+            let step = delta.as_secs_f32() / duration;
+            self.gain = match target > self.gain {
+                true => (self.gain + step).min(target),
+                false => (self.gain - step).max(target),
+            };
+
+            if self.gain == target {
+                self.fade = None;
+            }
+        }
+
+        let previous = self.played;
+        self.played += delta;
+        self.position += delta;
+
+        if let Some(beat_length) = self.track.beat_length() {
+            let first = (previous.as_secs_f32() / beat_length).ceil() as u64;
+            let last = (self.played.as_secs_f32() / beat_length).ceil() as u64;
+            let beats_per_bar = self.track.beats_per_bar as u64;
+
+            beats.extend((first..last).map(|beat| MusicBeat {
+                beat,
+                bar: beat / beats_per_bar,
+                beat_in_bar: (beat % beats_per_bar) as u32,
+            }));
+        }
+
+        if self.position < self.track.length {
+            return false;
+        }
+
+        match self.track.loop_start {
+            Some(loop_start) if self.track.length > loop_start => {
+                let loop_length = self.track.length - loop_start;
+                let overflow = (self.position - loop_start).as_nanos() % loop_length.as_nanos();
+                self.position = loop_start + Duration::from_nanos(overflow as u64);
+                false
+            }
+            _ => true,
+        }
+    }
+}
+
+//====================================================================
+
+type BeatCallback = Box<dyn FnMut(&mut State, MusicBeat)>;
+
+/// Plays music tracks one after another, crossfading between them, and reports the
+/// beats of the current track for rhythm synced gameplay.
+pub struct MusicController {
+    current: Option<MusicVoice>,
+    fading: Vec<MusicVoice>,
+    queue: VecDeque<MusicTrack>,
+    crossfade: Duration,
+    beats: Vec<MusicBeat>,
+    callbacks: Vec<BeatCallback>,
+}
+
+impl Default for MusicController {
+    fn default() -> Self {
+        Self {
+            current: None,
+            fading: Vec::new(),
+            queue: VecDeque::new(),
+            crossfade: Duration::from_secs(2),
+            beats: Vec::new(),
+            callbacks: Vec::new(),
+        }
+    }
+}
+
+impl MusicController {
+    /// Crossfade used when a track finishes and the next queued track starts.
+    #[inline]
+    pub fn set_crossfade(&mut self, crossfade: Duration) {
+        self.crossfade = crossfade;
+    }
+
+    /// Crossfade from the current track to `track`.
+    pub fn play(&mut self, track: MusicTrack, crossfade: Duration) {
+        log::trace!("Playing music track '{}'", track.name);
+
+        self.fade_out_current(crossfade);
+        self.current = Some(MusicVoice::new(track, crossfade));
+    }
+
+    /// Play `track` after the current track and any other queued tracks.
+    #[inline]
+    pub fn queue(&mut self, track: MusicTrack) {
+        self.queue.push_back(track);
+    }
+
+    /// Crossfade to the next queued track, or fade out if there isn't one.
+    pub fn skip(&mut self, crossfade: Duration) {
+        match self.queue.pop_front() {
+            Some(track) => self.play(track, crossfade),
+            None => self.stop(crossfade),
+        }
+    }
+
+    /// Fade out the current track and clear the queue.
+    pub fn stop(&mut self, fade_out: Duration) {
+        self.queue.clear();
+        self.fade_out_current(fade_out);
+    }
+
+    #[inline]
+    pub fn current(&self) -> Option<&MusicVoice> {
+        self.current.as_ref()
+    }
+
+    /// Every playing voice, including tracks still fading out.
+    #[inline]
+    pub fn voices(&self) -> impl Iterator<Item = &MusicVoice> {
+        self.current.iter().chain(self.fading.iter())
+    }
+
+    /// Beats of the current track that happened this frame.
+    #[inline]
+    pub fn beats(&self) -> &[MusicBeat] {
+        &self.beats
+    }
+
+    /// Call `callback` for every beat of the current track. Only tracks with a bpm have beats.
+    #[inline]
+    pub fn on_beat(&mut self, callback: impl FnMut(&mut State, MusicBeat) + 'static) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    fn fade_out_current(&mut self, fade_out: Duration) {
+        if let Some(mut voice) = self.current.take() {
+            voice.fade_to(0., fade_out);
+            self.fading.push(voice);
+        }
+    }
+
+    fn tick(&mut self, delta: Duration) {
+        self.beats.clear();
+
+        let mut ignored = Vec::new();
+        self.fading.retain_mut(|voice| {
+            let finished = voice.tick(delta, &mut ignored);
+            !finished && voice.gain > 0.
+        });
+
+        let finished = match &mut self.current {
+            Some(voice) => voice.tick(delta, &mut self.beats),
+            None => false,
+        };
+
+        // Start the next track early enough to crossfade into it
+        let next_due = self.current.as_ref().is_some_and(|voice| {
+            voice.track.loop_start.is_none()
+                && voice.track.length.saturating_sub(voice.position) <= self.crossfade
+        });
+
+        if finished || (next_due && !self.queue.is_empty()) {
+            match self.queue.pop_front() {
+                Some(track) => self.play(track, self.crossfade),
+                None => self.current = None,
+            }
+        }
+    }
+}
+
+//====================================================================
+
+pub(crate) fn process_music(state: &mut State) {
+    let delta = *state.time.delta();
+    state.music.tick(delta);
+
+    if state.music.beats.is_empty() || state.music.callbacks.is_empty() {
+        return;
+    }
+
+    // Callbacks are taken out so they can be given the state
+    let mut callbacks = std::mem::take(&mut state.music.callbacks);
+    let beats = state.music.beats.clone();
+
+    beats.into_iter().for_each(|beat| {
+        callbacks
+            .iter_mut()
+            .for_each(|callback| callback(state, beat))
+    });
+
+    callbacks.append(&mut state.music.callbacks);
+    state.music.callbacks = callbacks;
+}
+
+//====================================================================
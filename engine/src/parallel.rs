@@ -0,0 +1,480 @@
+//====================================================================
+
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Condvar, Mutex},
+    thread::JoinHandle,
+};
+
+use common::forces::Forces;
+use hecs::{CommandBuffer, World};
+
+use crate::{lod::LodTick, resources::Resources, tools::Time};
+
+//====================================================================
+
+/// System that only touches the world and resources through a [`SystemContext`], run
+/// alongside the others around it whose [`Access`] doesn't conflict.
+pub type ParallelSystem = fn(&mut SystemContext);
+
+/// Components and resources a [`ParallelSystem`] reads and writes. Systems writing something
+/// another reads or writes never run at the same time. Querying components that weren't
+/// declared can panic when another system is using them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Access {
+    reads: Vec<TypeId>,
+    writes: Vec<TypeId>,
+    resource_reads: Vec<TypeId>,
+    resource_writes: Vec<TypeId>,
+}
+
+impl Access {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn read<T: hecs::Component>(mut self) -> Self {
+        self.reads.push(TypeId::of::<T>());
+        self
+    }
+
+    #[inline]
+    pub fn write<T: hecs::Component>(mut self) -> Self {
+        self.writes.push(TypeId::of::<T>());
+        self
+    }
+
+    #[inline]
+    pub fn read_resource<T: 'static>(mut self) -> Self {
+        self.resource_reads.push(TypeId::of::<T>());
+        self
+    }
+
+    #[inline]
+    pub fn write_resource<T: 'static>(mut self) -> Self {
+        self.resource_writes.push(TypeId::of::<T>());
+        self
+    }
+
+    /// Whether the systems with these accesses can't run at the same time.
+    pub fn conflicts(&self, other: &Access) -> bool {
+        let overlaps = |writes: &[TypeId], reads: &[TypeId], other_writes: &[TypeId]| {
+            writes
+                .iter()
+                .any(|id| reads.contains(id) || other_writes.contains(id))
+        };
+
+        overlaps(&self.writes, &other.reads, &other.writes)
+            || overlaps(&other.writes, &self.reads, &self.writes)
+            || overlaps(
+                &self.resource_writes,
+                &other.resource_reads,
+                &other.resource_writes,
+            )
+            || overlaps(
+                &other.resource_writes,
+                &self.resource_reads,
+                &self.resource_writes,
+            )
+    }
+}
+
+//====================================================================
+
+/// What a [`ParallelSystem`] can reach while it runs. Structural changes to the world, like
+/// spawning or inserting components, are queued with [`SystemContext::commands`] and applied
+/// once every system running with it has finished.
+pub struct SystemContext<'a> {
+    name: &'static str,
+    world: &'a World,
+    time: &'a Time,
+    forces: &'a Forces,
+    delta: f32,
+    lod: LodTick,
+    access: &'a Access,
+    resources: &'a Resources,
+    /// Resources the system writes, moved out of [`Resources`] while it runs.
+    written: Vec<(TypeId, Box<dyn Any + Send + Sync>)>,
+    commands: CommandBuffer,
+}
+
+impl<'a> SystemContext<'a> {
+    #[inline]
+    pub fn world(&self) -> &'a World {
+        self.world
+    }
+
+    #[inline]
+    pub fn time(&self) -> &'a Time {
+        self.time
+    }
+
+    #[inline]
+    pub fn forces(&self) -> &'a Forces {
+        self.forces
+    }
+
+    /// Seconds the stage steps by, the fixed delta in [`crate::schedule::Stage::FixedUpdate`].
+    #[inline]
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta
+    }
+
+    /// Which [`crate::lod::UpdateLod`] entities the system should update this tick.
+    #[inline]
+    pub fn update_lod(&self) -> LodTick {
+        self.lod
+    }
+
+    #[inline]
+    pub fn commands(&mut self) -> &mut CommandBuffer {
+        &mut self.commands
+    }
+
+    /// Resource of type `T`. Panics if the system's [`Access`] doesn't read or write it.
+    pub fn resource<T: 'static>(&self) -> Option<&T> {
+        let id = TypeId::of::<T>();
+
+        if !self.access.resource_reads.contains(&id) && !self.access.resource_writes.contains(&id) {
+            panic!(
+                "System {} didn't declare access to resource {}",
+                self.name,
+                type_name::<T>()
+            );
+        }
+
+        match self.written.iter().find(|(written, _)| *written == id) {
+            Some((_, resource)) => resource.downcast_ref(),
+            None => self.resources.get(),
+        }
+    }
+
+    /// Resource of type `T`. Panics if the system's [`Access`] doesn't write it.
+    pub fn resource_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        let id = TypeId::of::<T>();
+
+        if !self.access.resource_writes.contains(&id) {
+            panic!(
+                "System {} didn't declare writing resource {}",
+                self.name,
+                type_name::<T>()
+            );
+        }
+
+        self.written
+            .iter_mut()
+            .find(|(written, _)| *written == id)
+            .and_then(|(_, resource)| resource.downcast_mut())
+    }
+}
+
+//====================================================================
+
+pub(crate) struct BatchEntry {
+    pub name: &'static str,
+    pub system: ParallelSystem,
+    pub access: Access,
+    pub lod: bool,
+}
+
+/// Systems whose accesses don't conflict, run together by [`run_batch`].
+#[derive(Default)]
+pub(crate) struct Batch {
+    entries: Vec<BatchEntry>,
+}
+
+impl Batch {
+    #[inline]
+    pub fn conflicts(&self, access: &Access) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.access.conflicts(access))
+    }
+
+    #[inline]
+    pub fn push(&mut self, entry: BatchEntry) {
+        self.entries.push(entry);
+    }
+
+    #[inline]
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.iter().any(|entry| entry.name == name)
+    }
+}
+
+/// World, resources and engine data the systems of a batch share.
+pub(crate) struct BatchState<'a> {
+    pub world: &'a mut World,
+    pub resources: &'a mut Resources,
+    pub time: &'a Time,
+    pub forces: &'a Forces,
+    pub delta: f32,
+    pub tick: u64,
+    pub workers: &'a WorkerPool,
+}
+
+/// Run every system in `batch` at once, then apply their commands in order.
+pub(crate) fn run_batch(batch: &mut Batch, state: BatchState) {
+    let entries = std::mem::take(&mut batch.entries);
+    if entries.is_empty() {
+        return;
+    }
+
+    let written = entries
+        .iter()
+        .map(|entry| {
+            entry
+                .access
+                .resource_writes
+                .iter()
+                .filter_map(|id| Some((*id, state.resources.take(*id)?)))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut contexts = entries
+        .iter()
+        .zip(written)
+        .map(|(entry, written)| SystemContext {
+            name: entry.name,
+            world: &*state.world,
+            time: state.time,
+            forces: state.forces,
+            delta: state.delta,
+            lod: LodTick {
+                enabled: entry.lod,
+                tick: state.tick,
+            },
+            access: &entry.access,
+            resources: &*state.resources,
+            written,
+            commands: CommandBuffer::new(),
+        })
+        .collect::<Vec<_>>();
+
+    let systems = entries.iter().map(|entry| entry.system).collect::<Vec<_>>();
+
+    state.workers.run(&systems, &mut contexts);
+
+    let finished = contexts
+        .into_iter()
+        .map(|context| (context.written, context.commands))
+        .collect::<Vec<_>>();
+
+    finished.into_iter().for_each(|(written, mut commands)| {
+        written
+            .into_iter()
+            .for_each(|(id, resource)| state.resources.restore(id, resource));
+        commands.run_on(state.world);
+    });
+}
+
+fn run_serial(systems: &[ParallelSystem], contexts: &mut [SystemContext]) {
+    systems
+        .iter()
+        .zip(contexts.iter_mut())
+        .for_each(|(system, context)| {
+            let _scope = common::profiler::scope(context.name, "system");
+            system(context);
+        });
+}
+
+//====================================================================
+
+pub(crate) type Job = Box<dyn FnOnce() + Send + 'static>;
+type BorrowedJob<'a> = Box<dyn FnOnce() + Send + 'a>;
+
+/// Threads shared by the system batches of the schedule and background work such as
+/// [`crate::tasks::TaskQueue`] tasks, started once with the [`crate::State`] so nothing pays
+/// for spawning threads while running.
+pub(crate) struct WorkerPool {
+    queue: Arc<PoolQueue>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+#[derive(Default)]
+struct PoolQueue {
+    jobs: Mutex<PoolJobs>,
+    ready: Condvar,
+}
+
+#[derive(Default)]
+struct PoolJobs {
+    /// Shares of the batch being run, taken before any background job.
+    batch: VecDeque<Job>,
+    background: VecDeque<Job>,
+    closed: bool,
+}
+
+impl PoolQueue {
+    fn push(&self, job: Job, batch: bool) {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        match batch {
+            true => jobs.batch.push_back(job),
+            false => jobs.background.push_back(job),
+        }
+        drop(jobs);
+
+        self.ready.notify_one();
+    }
+
+    #[inline]
+    fn take_batch_job(&self) -> Option<Job> {
+        self.jobs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .batch
+            .pop_front()
+    }
+
+    /// Blocks until there is a job, or returns None once the pool is closed.
+    fn next(&self) -> Option<Job> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+
+        loop {
+            if let Some(job) = jobs
+                .batch
+                .pop_front()
+                .or_else(|| jobs.background.pop_front())
+            {
+                return Some(job);
+            }
+
+            if jobs.closed {
+                return None;
+            }
+
+            jobs = self.ready.wait(jobs).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+impl WorkerPool {
+    /// One worker per core besides the calling thread, which runs a share of each batch too,
+    /// but always at least one so background work can make progress.
+    /// No workers on wasm, where batches run one system after another.
+    pub fn new() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let count = std::thread::available_parallelism()
+            .map(|count| count.get())
+            .unwrap_or(1)
+            .saturating_sub(1)
+            .max(1);
+        #[cfg(target_arch = "wasm32")]
+        let count = 0;
+
+        log::debug!("Starting {} engine workers", count);
+
+        let queue = Arc::new(PoolQueue::default());
+
+        let workers = (0..count)
+            .filter_map(|index| {
+                let queue = queue.clone();
+
+                std::thread::Builder::new()
+                    .name(format!("engine worker {}", index))
+                    .spawn(move || {
+                        while let Some(job) = queue.next() {
+                            // Batch jobs report their own panics, background jobs are
+                            // expected to catch theirs
+                            let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                        }
+                    })
+                    .map_err(|e| log::warn!("Unable to start engine worker thread: {}", e))
+                    .ok()
+            })
+            .collect();
+
+        Self { queue, workers }
+    }
+
+    /// Run `job` on a worker once the batches queued before it have been taken.
+    #[inline]
+    pub fn spawn(&self, job: Job) {
+        self.queue.push(job, false);
+    }
+
+    /// Split the systems between the workers and the calling thread, returning once all of
+    /// them have finished. Batches of a single system run inline. Shares no worker has
+    /// picked up by the time the calling thread finishes its own are run on it too, so a
+    /// batch never waits on background jobs.
+    fn run(&self, systems: &[ParallelSystem], contexts: &mut [SystemContext]) {
+        let threads = (self.workers.len() + 1).min(systems.len());
+
+        if threads <= 1 {
+            run_serial(systems, contexts);
+            return;
+        }
+
+        let share = systems.len().div_ceil(threads);
+        let (finished, done) = mpsc::channel::<bool>();
+
+        let mut shares = systems.chunks(share).zip(contexts.chunks_mut(share));
+        let first = shares.next();
+
+        let sent = shares
+            .map(|(systems, contexts)| {
+                let finished = finished.clone();
+                let job: BorrowedJob = Box::new(move || {
+                    let result =
+                        panic::catch_unwind(AssertUnwindSafe(|| run_serial(systems, contexts)));
+                    let _ = finished.send(result.is_ok());
+                });
+
+                // SAFETY: the job only borrows from this call, which doesn't return until every
+                // job it queued has run, even if one of the systems panics. Jobs are never
+                // dropped unrun while a batch is going, as the queue only closes on drop.
+                let job = unsafe { std::mem::transmute::<BorrowedJob, Job>(job) };
+                self.queue.push(job, true);
+            })
+            .count();
+
+        drop(finished);
+
+        let first = first.map(|(systems, contexts)| {
+            panic::catch_unwind(AssertUnwindSafe(|| run_serial(systems, contexts)))
+        });
+
+        while let Some(job) = self.queue.take_batch_job() {
+            job();
+        }
+
+        let mut panicked = false;
+        for _ in 0..sent {
+            match done.recv() {
+                Ok(ok) => panicked |= !ok,
+                Err(_) => break,
+            }
+        }
+
+        if let Some(Err(payload)) = first {
+            panic::resume_unwind(payload);
+        }
+
+        if panicked {
+            panic!("A parallel system panicked on an engine worker thread");
+        }
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // Workers stop once they're idle, dropping any background jobs left
+        self.queue
+            .jobs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .closed = true;
+        self.queue.ready.notify_all();
+
+        self.workers.drain(..).for_each(|worker| {
+            let _ = worker.join();
+        });
+    }
+}
+
+//====================================================================
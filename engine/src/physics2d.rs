@@ -0,0 +1,643 @@
+//====================================================================
+
+use std::collections::BTreeSet;
+
+use common::{GlobalTransform, Transform};
+use hecs::Entity;
+
+use crate::{
+    collision::{CollisionLayers, ALL_LAYERS},
+    parallel::{Access, SystemContext},
+};
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BodyType2D {
+    #[default]
+    Dynamic,
+    /// Moved only by its velocity, pushing dynamic bodies out of the way.
+    Kinematic,
+    Static,
+}
+
+/// Body moved on the XY plane by the 2D physics step. Reads and writes the entity's
+/// [`Transform`], so bodies shouldn't have a parent. Needs a [`Collider2D`] to collide.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RigidBody2D {
+    pub body_type: BodyType2D,
+    pub velocity: glam::Vec2,
+    /// Radians per second around Z. Collisions don't spin bodies.
+    pub angular_velocity: f32,
+    pub mass: f32,
+    /// Bounciness, from 0 to 1.
+    pub restitution: f32,
+    pub friction: f32,
+    pub gravity_scale: f32,
+    /// Velocity lost per second.
+    pub damping: f32,
+}
+
+impl Default for RigidBody2D {
+    fn default() -> Self {
+        Self {
+            body_type: BodyType2D::Dynamic,
+            velocity: glam::Vec2::ZERO,
+            angular_velocity: 0.,
+            mass: 1.,
+            restitution: 0.,
+            friction: 0.5,
+            gravity_scale: 1.,
+            damping: 0.,
+        }
+    }
+}
+
+impl RigidBody2D {
+    #[inline]
+    pub fn new(body_type: BodyType2D) -> Self {
+        Self {
+            body_type,
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    pub fn dynamic() -> Self {
+        Self::new(BodyType2D::Dynamic)
+    }
+
+    #[inline]
+    pub fn kinematic() -> Self {
+        Self::new(BodyType2D::Kinematic)
+    }
+
+    #[inline]
+    pub fn with_velocity(mut self, velocity: glam::Vec2) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    #[inline]
+    pub fn with_mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    #[inline]
+    pub fn with_restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
+    }
+
+    fn inverse_mass(&self) -> f32 {
+        match self.body_type {
+            BodyType2D::Dynamic => 1. / self.mass.max(f32::EPSILON),
+            BodyType2D::Kinematic | BodyType2D::Static => 0.,
+        }
+    }
+}
+
+//--------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Collider2DShape {
+    /// Box ignoring the entity's rotation.
+    Aabb {
+        half_extents: glam::Vec2,
+    },
+    Circle {
+        radius: f32,
+    },
+    /// Convex polygon with points in counter clockwise order, rotated with the entity.
+    Convex {
+        points: Vec<glam::Vec2>,
+    },
+}
+
+/// 2D collision shape on the XY plane. Without a [`RigidBody2D`] the collider is static and
+/// positioned by the entity's [`GlobalTransform`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collider2D {
+    pub shape: Collider2DShape,
+    pub offset: glam::Vec2,
+    /// Colliders only touch when sharing a layer.
+    pub layers: CollisionLayers,
+    /// Reports collisions without pushing anything.
+    pub sensor: bool,
+}
+
+impl Collider2D {
+    #[inline]
+    pub fn new(shape: Collider2DShape) -> Self {
+        Self {
+            shape,
+            offset: glam::Vec2::ZERO,
+            layers: ALL_LAYERS,
+            sensor: false,
+        }
+    }
+
+    #[inline]
+    pub fn aabb(half_extents: glam::Vec2) -> Self {
+        Self::new(Collider2DShape::Aabb { half_extents })
+    }
+
+    #[inline]
+    pub fn circle(radius: f32) -> Self {
+        Self::new(Collider2DShape::Circle { radius })
+    }
+
+    #[inline]
+    pub fn convex(points: Vec<glam::Vec2>) -> Self {
+        Self::new(Collider2DShape::Convex { points })
+    }
+
+    #[inline]
+    pub fn with_offset(mut self, offset: glam::Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    #[inline]
+    pub fn with_layers(mut self, layers: CollisionLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    #[inline]
+    pub fn as_sensor(mut self) -> Self {
+        self.sensor = true;
+        self
+    }
+}
+
+//--------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Collision2DEventKind {
+    Started,
+    Ended,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Collision2DEvent {
+    pub a: Entity,
+    pub b: Entity,
+    pub kind: Collision2DEventKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact2D {
+    pub a: Entity,
+    pub b: Entity,
+    /// Direction from `a` towards `b`.
+    pub normal: glam::Vec2,
+    pub depth: f32,
+    /// Whether either collider is a sensor.
+    pub sensor: bool,
+}
+
+/// Settings of the 2D physics step along with its collisions.
+pub struct Physics2D {
+    /// Replaced by the [`crate::State::forces`] gravity override when set.
+    pub gravity: glam::Vec2,
+    /// Fraction of each overlap corrected per step, from 0 to 1.
+    pub correction: f32,
+
+    touching: BTreeSet<(Entity, Entity)>,
+    contacts: Vec<Contact2D>,
+    events: Vec<Collision2DEvent>,
+}
+
+impl Default for Physics2D {
+    fn default() -> Self {
+        Self {
+            gravity: glam::vec2(0., -9.81),
+            correction: 0.8,
+            touching: BTreeSet::new(),
+            contacts: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+}
+
+impl Physics2D {
+    /// Collisions that started or ended during the last frame.
+    #[inline]
+    pub fn events(&self) -> &[Collision2DEvent] {
+        &self.events
+    }
+
+    /// Contacts found by the last physics step.
+    #[inline]
+    pub fn contacts(&self) -> &[Contact2D] {
+        &self.contacts
+    }
+
+    /// Contacts involving `entity`, with `entity` as `a`.
+    pub fn contacts_of(&self, entity: Entity) -> impl Iterator<Item = Contact2D> + '_ {
+        self.contacts.iter().filter_map(move |contact| {
+            if contact.a == entity {
+                Some(*contact)
+            } else if contact.b == entity {
+                Some(Contact2D {
+                    a: contact.b,
+                    b: contact.a,
+                    normal: -contact.normal,
+                    ..*contact
+                })
+            } else {
+                None
+            }
+        })
+    }
+
+    #[inline]
+    pub fn is_touching(&self, a: Entity, b: Entity) -> bool {
+        self.touching.contains(&(a.min(b), a.max(b)))
+    }
+
+    pub(crate) fn clear_events(&mut self) {
+        self.events.clear();
+    }
+}
+
+//====================================================================
+
+/// Collider moved into world space.
+enum WorldShape {
+    Circle {
+        center: glam::Vec2,
+        radius: f32,
+    },
+    Polygon {
+        center: glam::Vec2,
+        points: Vec<glam::Vec2>,
+    },
+}
+
+impl WorldShape {
+    fn new(collider: &Collider2D, translation: glam::Vec3, rotation: glam::Quat) -> Self {
+        let center = translation.truncate() + collider.offset;
+
+        match &collider.shape {
+            Collider2DShape::Aabb { half_extents } => WorldShape::Polygon {
+                center,
+                points: [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)]
+                    .into_iter()
+                    .map(|(x, y)| center + glam::vec2(x, y) * *half_extents)
+                    .collect(),
+            },
+            Collider2DShape::Circle { radius } => WorldShape::Circle {
+                center,
+                radius: *radius,
+            },
+            Collider2DShape::Convex { points } => WorldShape::Polygon {
+                center,
+                points: points
+                    .iter()
+                    .map(|point| center + (rotation * point.extend(0.)).truncate())
+                    .collect(),
+            },
+        }
+    }
+
+    fn center(&self) -> glam::Vec2 {
+        match self {
+            WorldShape::Circle { center, .. } | WorldShape::Polygon { center, .. } => *center,
+        }
+    }
+
+    fn bounds(&self) -> (glam::Vec2, glam::Vec2) {
+        match self {
+            WorldShape::Circle { center, radius } => (*center - *radius, *center + *radius),
+            WorldShape::Polygon { center, points } => {
+                points.iter().fold((*center, *center), |(min, max), point| {
+                    (min.min(*point), max.max(*point))
+                })
+            }
+        }
+    }
+
+    fn project(&self, axis: glam::Vec2) -> (f32, f32) {
+        match self {
+            WorldShape::Circle { center, radius } => {
+                let center = center.dot(axis);
+                (center - radius, center + radius)
+            }
+            WorldShape::Polygon { points, .. } => {
+                points
+                    .iter()
+                    .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), point| {
+                        let distance = point.dot(axis);
+                        (min.min(distance), max.max(distance))
+                    })
+            }
+        }
+    }
+
+    /// Separating axes to test against `other`.
+    fn axes(&self, other: &WorldShape, axes: &mut Vec<glam::Vec2>) {
+        match self {
+            WorldShape::Circle { center, .. } => {
+                let closest = match other {
+                    WorldShape::Circle { center, .. } => *center,
+                    WorldShape::Polygon { points, .. } => points
+                        .iter()
+                        .copied()
+                        .min_by(|a, b| {
+                            a.distance_squared(*center)
+                                .total_cmp(&b.distance_squared(*center))
+                        })
+                        .unwrap_or(*center),
+                };
+
+                axes.extend((closest - *center).try_normalize());
+            }
+            WorldShape::Polygon { points, .. } => {
+                axes.extend((0..points.len()).filter_map(|index| {
+                    let edge = points[(index + 1) % points.len()] - points[index];
+                    edge.perp().try_normalize()
+                }));
+            }
+        }
+    }
+}
+
+/// Direction from `a` towards `b` and how deep they overlap, using separating axes.
+fn collide(a: &WorldShape, b: &WorldShape) -> Option<(glam::Vec2, f32)> {
+    let mut axes = Vec::new();
+    a.axes(b, &mut axes);
+    b.axes(a, &mut axes);
+
+    // Circles sharing a center
+    if axes.is_empty() {
+        axes.push(glam::Vec2::Y);
+    }
+
+    let mut best = (glam::Vec2::ZERO, f32::INFINITY);
+
+    for axis in axes {
+        let (a_min, a_max) = a.project(axis);
+        let (b_min, b_max) = b.project(axis);
+
+        let overlap = a_max.min(b_max) - a_min.max(b_min);
+        if overlap <= 0. {
+            return None;
+        }
+
+        if overlap < best.1 {
+            best = (axis, overlap);
+        }
+    }
+
+    let (mut normal, depth) = best;
+    if (b.center() - a.center()).dot(normal) < 0. {
+        normal = -normal;
+    }
+
+    Some((normal, depth))
+}
+
+//====================================================================
+
+struct PhysicsBody {
+    entity: Entity,
+    shape: WorldShape,
+    bounds: (glam::Vec2, glam::Vec2),
+    layers: CollisionLayers,
+    sensor: bool,
+    /// Moved by the physics step, so worth testing against static colliders.
+    moving: bool,
+    inverse_mass: f32,
+    restitution: f32,
+    friction: f32,
+    velocity: glam::Vec2,
+    correction: glam::Vec2,
+}
+
+#[inline]
+pub(crate) fn physics_access() -> Access {
+    Access::new()
+        .write::<RigidBody2D>()
+        .write::<Transform>()
+        .read::<Collider2D>()
+        .read::<GlobalTransform>()
+        .write_resource::<Physics2D>()
+}
+
+pub(crate) fn process_physics(ctx: &mut SystemContext) {
+    let delta = ctx.delta_seconds();
+    let forces = ctx.forces();
+    let world = ctx.world();
+    let Some(physics) = ctx.resource_mut::<Physics2D>() else {
+        return;
+    };
+
+    let gravity = forces
+        .gravity
+        .map(|gravity| gravity.truncate())
+        .unwrap_or(physics.gravity);
+
+    //--------------------------------------------------
+    // Integrate
+
+    world
+        .query::<(&mut RigidBody2D, &mut Transform)>()
+        .iter()
+        .for_each(|(_, (body, transform))| {
+            match body.body_type {
+                BodyType2D::Static => return,
+                BodyType2D::Kinematic => {}
+                BodyType2D::Dynamic => {
+                    let acceleration = gravity * body.gravity_scale
+                        + forces.acceleration_at(transform.translation).truncate();
+
+                    body.velocity += acceleration * delta;
+                    body.velocity /= 1. + body.damping.max(0.) * delta;
+                }
+            }
+
+            transform.translation += body.velocity.extend(0.) * delta;
+            transform.rotation =
+                glam::Quat::from_rotation_z(body.angular_velocity * delta) * transform.rotation;
+        });
+
+    //--------------------------------------------------
+    // Broadphase
+
+    let mut bodies = world
+        .query::<(
+            &Collider2D,
+            Option<&RigidBody2D>,
+            Option<&Transform>,
+            Option<&GlobalTransform>,
+        )>()
+        .iter()
+        .filter_map(|(entity, (collider, body, transform, global))| {
+            let (translation, rotation) = match (body, transform, global) {
+                (Some(_), Some(transform), _) | (None, Some(transform), None) => {
+                    (transform.translation, transform.rotation)
+                }
+                (None, _, Some(global)) => {
+                    let (_, rotation, translation) = global.to_scale_rotation_translation();
+                    (translation, rotation)
+                }
+                _ => return None,
+            };
+
+            let shape = WorldShape::new(collider, translation, rotation);
+            let body = body
+                .copied()
+                .unwrap_or(RigidBody2D::new(BodyType2D::Static));
+
+            Some(PhysicsBody {
+                entity,
+                bounds: shape.bounds(),
+                shape,
+                layers: collider.layers,
+                sensor: collider.sensor,
+                moving: body.body_type != BodyType2D::Static,
+                inverse_mass: body.inverse_mass(),
+                restitution: body.restitution,
+                friction: body.friction,
+                velocity: body.velocity,
+                correction: glam::Vec2::ZERO,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Sweep along x
+    bodies.sort_by(|a, b| a.bounds.0.x.total_cmp(&b.bounds.0.x));
+
+    let mut pairs = Vec::new();
+
+    (0..bodies.len()).for_each(|first| {
+        let a = &bodies[first];
+
+        bodies[first + 1..]
+            .iter()
+            .enumerate()
+            .take_while(|(_, b)| b.bounds.0.x <= a.bounds.1.x)
+            .filter(|(_, b)| {
+                (a.moving || b.moving)
+                    && a.layers & b.layers != 0
+                    && b.bounds.0.y <= a.bounds.1.y
+                    && a.bounds.0.y <= b.bounds.1.y
+            })
+            .for_each(|(offset, _)| pairs.push((first, first + 1 + offset)));
+    });
+
+    //--------------------------------------------------
+    // Narrowphase and solve
+
+    let mut contacts = Vec::new();
+
+    pairs.into_iter().for_each(|(first, second)| {
+        let (a, b) = (&bodies[first], &bodies[second]);
+
+        let Some((normal, depth)) = collide(&a.shape, &b.shape) else {
+            return;
+        };
+
+        let sensor = a.sensor || b.sensor;
+
+        // Keep contacts in entity order so events are stable
+        contacts.push(match a.entity < b.entity {
+            true => Contact2D {
+                a: a.entity,
+                b: b.entity,
+                normal,
+                depth,
+                sensor,
+            },
+            false => Contact2D {
+                a: b.entity,
+                b: a.entity,
+                normal: -normal,
+                depth,
+                sensor,
+            },
+        });
+
+        let total_inverse_mass = a.inverse_mass + b.inverse_mass;
+        if sensor || total_inverse_mass <= 0. {
+            return;
+        }
+
+        // Push apart
+        let correction = normal * depth * physics.correction.clamp(0., 1.) / total_inverse_mass;
+        let (a_correction, b_correction) =
+            (correction * a.inverse_mass, correction * b.inverse_mass);
+
+        // Bounce and slide
+        let relative = b.velocity - a.velocity;
+        let speed = relative.dot(normal);
+
+        let (a_impulse, b_impulse) = match speed < 0. {
+            true => {
+                let restitution = a.restitution.max(b.restitution).clamp(0., 1.);
+                let impulse = -(1. + restitution) * speed / total_inverse_mass;
+
+                let tangent = (relative - normal * speed).normalize_or_zero();
+                let friction = (a.friction * b.friction).max(0.).sqrt();
+                let tangent_impulse = (-relative.dot(tangent) / total_inverse_mass)
+                    .clamp(-impulse * friction, impulse * friction);
+
+                let impulse = normal * impulse + tangent * tangent_impulse;
+                (impulse * a.inverse_mass, impulse * b.inverse_mass)
+            }
+            false => (glam::Vec2::ZERO, glam::Vec2::ZERO),
+        };
+
+        let a = &mut bodies[first];
+        a.correction -= a_correction;
+        a.velocity -= a_impulse;
+
+        let b = &mut bodies[second];
+        b.correction += b_correction;
+        b.velocity += b_impulse;
+    });
+
+    bodies
+        .iter()
+        .filter(|body| body.inverse_mass > 0.)
+        .for_each(|body| {
+            if let Ok(mut query) =
+                world.query_one::<(&mut RigidBody2D, &mut Transform)>(body.entity)
+            {
+                if let Some((rigid_body, transform)) = query.get() {
+                    rigid_body.velocity = body.velocity;
+                    transform.translation += body.correction.extend(0.);
+                }
+            }
+        });
+
+    //--------------------------------------------------
+    // Events
+
+    let touching = contacts
+        .iter()
+        .map(|contact| (contact.a, contact.b))
+        .collect::<BTreeSet<_>>();
+
+    let event = |(a, b): &(Entity, Entity), kind| Collision2DEvent { a: *a, b: *b, kind };
+
+    physics.events.extend(
+        physics
+            .touching
+            .difference(&touching)
+            .map(|pair| event(pair, Collision2DEventKind::Ended)),
+    );
+    physics.events.extend(
+        touching
+            .difference(&physics.touching)
+            .map(|pair| event(pair, Collision2DEventKind::Started)),
+    );
+
+    physics.touching = touching;
+    physics.contacts = contacts;
+}
+
+//====================================================================
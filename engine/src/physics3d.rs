@@ -0,0 +1,287 @@
+//====================================================================
+
+use common::{GlobalTransform, Transform};
+use hecs::{Entity, World};
+
+use crate::{
+    collision::{self, Capsule, Collider, ColliderShape, CollisionLayers, RayHit, ALL_LAYERS},
+    parallel::{Access, SystemContext},
+};
+
+//====================================================================
+
+const MAX_RESOLVE_ITERATIONS: usize = 4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BodyType {
+    #[default]
+    Dynamic,
+    /// Moved only by its velocity, without being pushed by anything.
+    Kinematic,
+}
+
+/// Body moved by the 3D physics step, colliding with every other [`Collider`] using its own.
+/// Reads and writes the entity's [`Transform`] and [`GlobalTransform`], so bodies shouldn't
+/// have a parent. Bodies collide as their sphere or capsule, with cuboids treated as the
+/// sphere inside them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RigidBody {
+    pub body_type: BodyType,
+    pub velocity: glam::Vec3,
+    pub mass: f32,
+    /// Bounciness, from 0 to 1.
+    pub restitution: f32,
+    pub friction: f32,
+    pub gravity_scale: f32,
+    /// Velocity lost per second.
+    pub damping: f32,
+}
+
+impl Default for RigidBody {
+    fn default() -> Self {
+        Self {
+            body_type: BodyType::Dynamic,
+            velocity: glam::Vec3::ZERO,
+            mass: 1.,
+            restitution: 0.,
+            friction: 0.5,
+            gravity_scale: 1.,
+            damping: 0.,
+        }
+    }
+}
+
+impl RigidBody {
+    #[inline]
+    pub fn dynamic() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn kinematic() -> Self {
+        Self {
+            body_type: BodyType::Kinematic,
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    pub fn with_velocity(mut self, velocity: glam::Vec3) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    #[inline]
+    pub fn with_mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    #[inline]
+    pub fn with_restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
+    }
+}
+
+//--------------------------------------------------
+
+/// Settings of the 3D physics step.
+///
+/// This is a small built-in solver, not rapier3d, and is only meant for simple props:
+/// - Bodies only translate. There's no rotation, angular velocity or torque.
+/// - Bodies collide as spheres and capsules, with cuboid colliders treated as the sphere
+///   inside them. The colliders they hit keep their full shape, ignoring rotation.
+/// - Contacts are resolved by pushing bodies apart a few times per step, without
+///   a constraint solver, joints or continuous collision detection.
+pub struct Physics {
+    /// Replaced by the [`crate::State::forces`] gravity override when set.
+    pub gravity: glam::Vec3,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Self {
+            gravity: glam::vec3(0., -9.81, 0.),
+        }
+    }
+}
+
+/// [`Physics`] along with the world, for queries.
+pub struct PhysicsQuery<'a> {
+    physics: &'a Physics,
+    world: &'a World,
+}
+
+impl<'a> PhysicsQuery<'a> {
+    #[inline]
+    pub(crate) fn new(physics: &'a Physics, world: &'a World) -> Self {
+        Self { physics, world }
+    }
+
+    #[inline]
+    pub fn settings(&self) -> &Physics {
+        self.physics
+    }
+
+    /// Closest collider hit by a ray. `direction` doesn't need to be normalized.
+    #[inline]
+    pub fn raycast(&self, origin: glam::Vec3, direction: glam::Vec3) -> Option<RayHit> {
+        self.raycast_filtered(origin, direction, f32::INFINITY, ALL_LAYERS, &[])
+    }
+
+    #[inline]
+    pub fn raycast_filtered(
+        &self,
+        origin: glam::Vec3,
+        direction: glam::Vec3,
+        max_distance: f32,
+        mask: CollisionLayers,
+        exclude: &[Entity],
+    ) -> Option<RayHit> {
+        collision::raycast(self.world, origin, direction, max_distance, mask, exclude)
+    }
+}
+
+//====================================================================
+
+/// Shape a body collides as.
+fn body_capsule(collider: &Collider, position: glam::Vec3) -> Capsule {
+    let center = position + collider.offset;
+
+    match collider.shape {
+        ColliderShape::Sphere { radius } => Capsule::sphere(center, radius),
+        ColliderShape::Capsule {
+            half_height,
+            radius,
+            axis,
+        } => Capsule::new(center, axis, half_height, radius),
+        ColliderShape::Cuboid { half_extents } => {
+            Capsule::sphere(center, half_extents.min_element())
+        }
+    }
+}
+
+#[inline]
+pub(crate) fn physics_access() -> Access {
+    Access::new()
+        .write::<RigidBody>()
+        .write::<Transform>()
+        .write::<GlobalTransform>()
+        .read::<Collider>()
+        .read_resource::<Physics>()
+}
+
+pub(crate) fn process_physics(ctx: &mut SystemContext) {
+    let delta = ctx.delta_seconds();
+    let forces = ctx.forces();
+    let world = ctx.world();
+    let Some(physics) = ctx.resource::<Physics>() else {
+        return;
+    };
+
+    let gravity = forces.gravity_or(physics.gravity);
+
+    let bodies = world
+        .query::<(&RigidBody, &Transform, Option<&Collider>)>()
+        .iter()
+        .map(|(entity, (body, transform, collider))| {
+            (entity, *body, transform.translation, collider.copied())
+        })
+        .collect::<Vec<_>>();
+
+    let results = bodies
+        .into_iter()
+        .map(|(entity, mut body, mut position, collider)| {
+            if body.body_type == BodyType::Dynamic {
+                let acceleration = gravity * body.gravity_scale + forces.acceleration_at(position);
+
+                body.velocity += acceleration * delta;
+                body.velocity /= 1. + body.damping.max(0.) * delta;
+            }
+
+            position += body.velocity * delta;
+
+            let Some(collider) = collider.filter(|_| body.body_type == BodyType::Dynamic) else {
+                return (entity, body, position);
+            };
+
+            (0..MAX_RESOLVE_ITERATIONS).all(|_| {
+                let contacts = collision::capsule_contacts(
+                    world,
+                    body_capsule(&collider, position),
+                    Some(entity),
+                );
+
+                contacts
+                    .iter()
+                    .filter(|contact| {
+                        world
+                            .get::<&Collider>(contact.entity)
+                            .is_ok_and(|other| other.layers & collider.layers != 0)
+                    })
+                    .for_each(|contact| {
+                        let other = world.get::<&RigidBody>(contact.entity).ok();
+
+                        // Bodies both push out of each other, so each moves its share of the way
+                        let share = match other.as_deref() {
+                            Some(other) if other.body_type == BodyType::Dynamic => {
+                                let mass = body.mass.max(f32::EPSILON);
+                                other.mass.max(f32::EPSILON) / (mass + other.mass.max(f32::EPSILON))
+                            }
+                            _ => 1.,
+                        };
+                        position += contact.normal * contact.depth * share;
+
+                        let other_velocity = other
+                            .as_deref()
+                            .map(|other| other.velocity)
+                            .unwrap_or(glam::Vec3::ZERO);
+                        let restitution = other
+                            .as_deref()
+                            .map(|other| other.restitution.max(body.restitution))
+                            .unwrap_or(body.restitution)
+                            .clamp(0., 1.);
+
+                        let relative = body.velocity - other_velocity;
+                        let speed = relative.dot(contact.normal);
+                        if speed >= 0. {
+                            return;
+                        }
+
+                        let normal_change = -(1. + restitution) * speed;
+                        let tangent = relative - contact.normal * speed;
+                        let slide = (tangent.length() - normal_change * body.friction).max(0.);
+
+                        body.velocity = other_velocity
+                            + contact.normal * (speed + normal_change)
+                            + tangent.normalize_or_zero() * slide;
+                    });
+
+                !contacts.is_empty()
+            });
+
+            (entity, body, position)
+        })
+        .collect::<Vec<_>>();
+
+    // Global transforms are kept in sync so later steps collide with where bodies are now
+    results.into_iter().for_each(|(entity, body, position)| {
+        let Ok(mut query) = world
+            .query_one::<(&mut RigidBody, &mut Transform, Option<&mut GlobalTransform>)>(entity)
+        else {
+            return;
+        };
+
+        if let Some((rigid_body, transform, global)) = query.get() {
+            *rigid_body = body;
+            transform.translation = position;
+
+            if let Some(global) = global {
+                global.0 = transform.to_affine();
+            }
+        }
+    });
+}
+
+//====================================================================
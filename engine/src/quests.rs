@@ -0,0 +1,343 @@
+//====================================================================
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dialogue::DialogueEvent,
+    inventory::{InventoryChange, ItemId},
+    triggers::TriggerEventKind,
+    State,
+};
+
+//====================================================================
+
+pub type QuestId = String;
+
+/// Marks entities for kill and reach objectives. Kill objectives count deaths of entities
+/// with a matching tag and reach objectives count entering trigger volumes with one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuestTag(pub String);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ObjectiveKind {
+    Kill {
+        tag: String,
+    },
+    Collect {
+        item: ItemId,
+    },
+    Reach {
+        tag: String,
+    },
+    /// Counted with [`QuestLog::notify`] or dialogue events of the same name.
+    Custom {
+        name: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectiveDef {
+    pub description: String,
+    #[serde(flatten)]
+    pub kind: ObjectiveKind,
+    #[serde(default = "default_count")]
+    pub count: u32,
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuestDef {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub objectives: Vec<ObjectiveDef>,
+}
+
+/// Quest definitions keyed by id, usually loaded from a json file through
+/// [`crate::assets::AssetServer::load_quests`].
+///
+/// ```json
+/// {
+///     "rats": {
+///         "name": "Rat Problem",
+///         "objectives": [
+///             { "description": "Kill rats", "type": "kill", "tag": "rat", "count": 5 },
+///             { "description": "Return to the inn", "type": "reach", "tag": "inn" }
+///         ]
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct QuestDatabase {
+    quests: BTreeMap<QuestId, QuestDef>,
+}
+
+impl QuestDatabase {
+    #[inline]
+    pub fn from_json(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    #[inline]
+    pub fn insert(&mut self, id: impl Into<QuestId>, quest: QuestDef) {
+        self.quests.insert(id.into(), quest);
+    }
+
+    #[inline]
+    pub fn get(&self, id: &str) -> Option<&QuestDef> {
+        self.quests.get(id)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&QuestId, &QuestDef)> {
+        self.quests.iter()
+    }
+}
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuestStatus {
+    Active,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuestProgress {
+    pub status: QuestStatus,
+    /// Count towards each objective.
+    pub objectives: Vec<u32>,
+}
+
+/// Progress of every started quest. Serializable so it can be written to save files.
+pub type QuestSave = BTreeMap<QuestId, QuestProgress>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuestEvent {
+    Started(QuestId),
+    ObjectiveCompleted { quest: QuestId, objective: usize },
+    Completed(QuestId),
+    Failed(QuestId),
+}
+
+/// Started quests and their progress. Objectives are counted after `App::update`
+/// from death, inventory, trigger and dialogue events.
+#[derive(Default)]
+pub struct QuestLog {
+    database: Arc<QuestDatabase>,
+    progress: QuestSave,
+    notified: Vec<(String, u32)>,
+    queued: Vec<QuestEvent>,
+    events: Vec<QuestEvent>,
+}
+
+impl QuestLog {
+    #[inline]
+    pub fn set_database(&mut self, database: Arc<QuestDatabase>) {
+        self.database = database;
+    }
+
+    #[inline]
+    pub fn database(&self) -> &QuestDatabase {
+        &self.database
+    }
+
+    /// Start a quest. Does nothing if it has already been started.
+    pub fn start(&mut self, id: &str) {
+        if self.progress.contains_key(id) {
+            return;
+        }
+
+        let Some(quest) = self.database.get(id) else {
+            log::warn!("Unable to start unknown quest '{}'", id);
+            return;
+        };
+
+        self.progress.insert(
+            id.to_string(),
+            QuestProgress {
+                status: QuestStatus::Active,
+                objectives: vec![0; quest.objectives.len()],
+            },
+        );
+        self.queued.push(QuestEvent::Started(id.to_string()));
+    }
+
+    pub fn fail(&mut self, id: &str) {
+        if let Some(progress) = self
+            .progress
+            .get_mut(id)
+            .filter(|progress| progress.status == QuestStatus::Active)
+        {
+            progress.status = QuestStatus::Failed;
+            self.queued.push(QuestEvent::Failed(id.to_string()));
+        }
+    }
+
+    /// Count towards custom objectives named `name`.
+    #[inline]
+    pub fn notify(&mut self, name: impl Into<String>, amount: u32) {
+        self.notified.push((name.into(), amount));
+    }
+
+    #[inline]
+    pub fn progress(&self, id: &str) -> Option<&QuestProgress> {
+        self.progress.get(id)
+    }
+
+    #[inline]
+    pub fn status(&self, id: &str) -> Option<QuestStatus> {
+        self.progress.get(id).map(|progress| progress.status)
+    }
+
+    /// Active quests along with their definitions.
+    pub fn active(&self) -> impl Iterator<Item = (&str, &QuestDef, &QuestProgress)> {
+        self.progress
+            .iter()
+            .filter(|(_, progress)| progress.status == QuestStatus::Active)
+            .filter_map(|(id, progress)| Some((id.as_str(), self.database.get(id)?, progress)))
+    }
+
+    /// Events from the last frame.
+    #[inline]
+    pub fn events(&self) -> &[QuestEvent] {
+        &self.events
+    }
+
+    #[inline]
+    pub fn save(&self) -> QuestSave {
+        self.progress.clone()
+    }
+
+    /// Replace all progress with saved progress.
+    #[inline]
+    pub fn load(&mut self, save: QuestSave) {
+        self.progress = save;
+    }
+
+    fn count(&mut self, matches: impl Fn(&ObjectiveKind) -> bool, amount: u32) {
+        let database = self.database.clone();
+
+        self.progress
+            .iter_mut()
+            .filter(|(_, progress)| progress.status == QuestStatus::Active)
+            .for_each(|(id, progress)| {
+                let Some(quest) = database.get(id) else {
+                    return;
+                };
+
+                quest
+                    .objectives
+                    .iter()
+                    .zip(progress.objectives.iter_mut())
+                    .enumerate()
+                    .filter(|(_, (objective, current))| {
+                        **current < objective.count && matches(&objective.kind)
+                    })
+                    .for_each(|(index, (objective, current))| {
+                        *current = (*current + amount).min(objective.count);
+
+                        if *current == objective.count {
+                            self.events.push(QuestEvent::ObjectiveCompleted {
+                                quest: id.clone(),
+                                objective: index,
+                            });
+                        }
+                    });
+
+                let complete = quest
+                    .objectives
+                    .iter()
+                    .zip(&progress.objectives)
+                    .all(|(objective, current)| *current >= objective.count);
+
+                if complete {
+                    progress.status = QuestStatus::Completed;
+                    self.events.push(QuestEvent::Completed(id.clone()));
+                }
+            });
+    }
+}
+
+//====================================================================
+
+pub(crate) fn process_quests(state: &mut State) {
+    let quests = &mut state.quests;
+    quests.events.clear();
+    quests.events.append(&mut quests.queued);
+
+    let tag = |entity| {
+        state
+            .world
+            .get::<&QuestTag>(entity)
+            .ok()
+            .map(|tag| tag.0.clone())
+    };
+
+    let killed = state
+        .health
+        .deaths()
+        .iter()
+        .filter_map(|death| tag(death.entity))
+        .collect::<Vec<_>>();
+
+    let reached = state
+        .triggers
+        .events()
+        .iter()
+        .filter(|event| event.kind == TriggerEventKind::Enter)
+        .filter_map(|event| tag(event.trigger))
+        .collect::<Vec<_>>();
+
+    killed.into_iter().for_each(|killed| {
+        quests.count(
+            |kind| matches!(kind, ObjectiveKind::Kill { tag } if *tag == killed),
+            1,
+        )
+    });
+
+    reached.into_iter().for_each(|reached| {
+        quests.count(
+            |kind| matches!(kind, ObjectiveKind::Reach { tag } if *tag == reached),
+            1,
+        )
+    });
+
+    state.inventories.events().iter().for_each(|event| {
+        if let InventoryChange::Added { item, count } = &event.change {
+            quests.count(
+                |kind| matches!(kind, ObjectiveKind::Collect { item: wanted } if wanted == item),
+                *count,
+            );
+        }
+    });
+
+    let dialogue = state
+        .dialogue
+        .events()
+        .iter()
+        .filter_map(|event| match event {
+            DialogueEvent::Event(name) => Some((name.clone(), 1)),
+            _ => None,
+        });
+
+    std::mem::take(&mut quests.notified)
+        .into_iter()
+        .chain(dialogue)
+        .for_each(|(name, amount)| {
+            quests.count(
+                |kind| matches!(kind, ObjectiveKind::Custom { name: wanted } if *wanted == name),
+                amount,
+            )
+        });
+}
+
+//====================================================================
@@ -0,0 +1,87 @@
+//====================================================================
+
+use serde::{Deserialize, Serialize};
+
+//====================================================================
+
+/// Small seeded random number generator (SplitMix64). The same seed always gives the same
+/// sequence on every platform, so gameplay using [`crate::State::rng_mut`] can be replayed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    #[inline]
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Seed from the system clock.
+    pub fn from_time() -> Self {
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or(0);
+
+        Self::new(seed)
+    }
+
+    /// Current state. Passing it to [`Rng::new`] continues the same sequence.
+    #[inline]
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    #[inline]
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Between 0 (inclusive) and 1 (exclusive).
+    #[inline]
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    #[inline]
+    pub fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// Between `min` (inclusive) and `max` (exclusive). Returns `min` if the range is empty.
+    #[inline]
+    pub fn range_u32(&mut self, min: u32, max: u32) -> u32 {
+        match max > min {
+            true => min + (self.next_u64() % (max - min) as u64) as u32,
+            false => min,
+        }
+    }
+
+    #[inline]
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    #[inline]
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        items.get(self.range_u32(0, items.len() as u32) as usize)
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::from_time()
+    }
+}
+
+//====================================================================
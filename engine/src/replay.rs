@@ -0,0 +1,410 @@
+//====================================================================
+
+use std::{
+    error::Error,
+    fmt::Display,
+    hash::Hasher,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rustc_hash::FxHasher;
+use serde::{Deserialize, Serialize};
+use web_time::Duration;
+
+use crate::{
+    random::Rng,
+    tools::{self, GamepadEvent, KeyCode, MouseButton},
+    State,
+};
+
+//====================================================================
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl Error for ReplayError {}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "Unable to access recording file: {}", e),
+            ReplayError::Json(e) => write!(f, "Invalid recording json: {}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for ReplayError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    Key { key: KeyCode, pressed: bool },
+    MouseButton { button: MouseButton, pressed: bool },
+    CursorMoved(glam::Vec2),
+    Scroll(glam::Vec2),
+    Motion(glam::Vec2),
+    Gamepad(GamepadEvent),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    pub delta: Duration,
+    pub events: Vec<InputEvent>,
+}
+
+/// Seed and per frame input of a session. Playing it back from startup with the same app
+/// should end in the same state, as long as the app only uses [`State::rng_mut`] for
+/// randomness and [`crate::tools::Time`] for timing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub seed: u64,
+    pub frames: Vec<RecordedFrame>,
+    /// [`state_hash`] once the last frame finished.
+    pub hash: Option<u64>,
+}
+
+impl Recording {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+//====================================================================
+
+#[derive(Debug, Clone, Default)]
+pub enum ReplayConfig {
+    #[default]
+    None,
+    /// Record from startup. Saved to `path` when the window is closed, if set.
+    Record { seed: u64, path: Option<PathBuf> },
+    /// Play a recording from startup, ignoring real input until it finishes.
+    Play {
+        recording: Arc<Recording>,
+        /// Close the app once the recording finishes, e.g. when checking replays in CI.
+        exit_when_done: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayResult {
+    pub expected: Option<u64>,
+    pub actual: u64,
+}
+
+impl ReplayResult {
+    /// Whether the replay ended in the recorded state. True when nothing was recorded to compare.
+    #[inline]
+    pub fn matches(&self) -> bool {
+        self.expected.is_none_or(|expected| expected == self.actual)
+    }
+}
+
+enum ReplayMode {
+    Live,
+    Recording {
+        recording: Recording,
+        path: Option<PathBuf>,
+    },
+    Playing {
+        recording: Arc<Recording>,
+        frame: usize,
+        exit_when_done: bool,
+    },
+}
+
+pub struct Replay {
+    mode: ReplayMode,
+    /// Input received since the last frame started.
+    pending: Vec<InputEvent>,
+    result: Option<ReplayResult>,
+    exit: bool,
+}
+
+impl Replay {
+    pub(crate) fn new(config: &ReplayConfig) -> Self {
+        let mode = match config {
+            ReplayConfig::None => ReplayMode::Live,
+            ReplayConfig::Record { seed, path } => ReplayMode::Recording {
+                recording: Recording {
+                    seed: *seed,
+                    ..Default::default()
+                },
+                path: path.clone(),
+            },
+            ReplayConfig::Play {
+                recording,
+                exit_when_done,
+            } => ReplayMode::Playing {
+                recording: recording.clone(),
+                frame: 0,
+                exit_when_done: *exit_when_done,
+            },
+        };
+
+        Self {
+            mode,
+            pending: Vec::new(),
+            result: None,
+            exit: false,
+        }
+    }
+
+    /// Seed the rng should start with, if recording or playing.
+    pub(crate) fn seed(&self) -> Option<u64> {
+        match &self.mode {
+            ReplayMode::Live => None,
+            ReplayMode::Recording { recording, .. } => Some(recording.seed),
+            ReplayMode::Playing { recording, .. } => Some(recording.seed),
+        }
+    }
+
+    #[inline]
+    pub fn is_recording(&self) -> bool {
+        matches!(self.mode, ReplayMode::Recording { .. })
+    }
+
+    #[inline]
+    pub fn is_playing(&self) -> bool {
+        matches!(self.mode, ReplayMode::Playing { .. })
+    }
+
+    /// Current frame of the recording being played.
+    #[inline]
+    pub fn frame(&self) -> Option<usize> {
+        match &self.mode {
+            ReplayMode::Playing { frame, .. } => Some(*frame),
+            _ => None,
+        }
+    }
+
+    /// Outcome of the last finished playback.
+    #[inline]
+    pub fn result(&self) -> Option<ReplayResult> {
+        self.result
+    }
+
+    #[inline]
+    pub(crate) fn exit_requested(&self) -> bool {
+        self.exit
+    }
+}
+
+//====================================================================
+
+/// Hash of every scene registered component in the world along with the rng state.
+/// Equal hashes after a replay mean the simulation ran the same way.
+pub fn state_hash(state: &State) -> u64 {
+    let mut hasher = FxHasher::default();
+
+    match state.scene_registry.save(&state.world, &state.assets) {
+        Ok(scene) => hasher.write(scene.as_bytes()),
+        Err(e) => log::warn!("Unable to serialize world for state hash: {}", e),
+    }
+    hasher.write_u64(state.rng.state());
+
+    hasher.finish()
+}
+
+impl State {
+    #[inline]
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Start recording from the current frame with a fresh seed.
+    /// Replays only match if they also start from this point, so prefer recording from startup.
+    pub fn start_recording(&mut self, seed: u64) {
+        log::info!("Recording started with seed {}", seed);
+
+        self.rng = Rng::new(seed);
+        self.replay.mode = ReplayMode::Recording {
+            recording: Recording {
+                seed,
+                ..Default::default()
+            },
+            path: None,
+        };
+    }
+
+    /// Stop recording, returning everything recorded so far along with the current state hash.
+    pub fn finish_recording(&mut self) -> Option<Recording> {
+        let hash = state_hash(self);
+
+        match std::mem::replace(&mut self.replay.mode, ReplayMode::Live) {
+            ReplayMode::Recording { mut recording, .. } => {
+                recording.hash = Some(hash);
+                log::info!(
+                    "Recording finished after {} frames with hash {:x}",
+                    recording.frames.len(),
+                    hash
+                );
+                Some(recording)
+            }
+            mode => {
+                self.replay.mode = mode;
+                None
+            }
+        }
+    }
+}
+
+//====================================================================
+
+/// Apply input from the window, unless a replay is playing.
+pub(crate) fn process_input(state: &mut State, event: InputEvent) {
+    match state.replay.mode {
+        ReplayMode::Live => apply_input(state, event),
+        ReplayMode::Recording { .. } => {
+            apply_input(state, event);
+            state.replay.pending.push(event);
+        }
+        ReplayMode::Playing { .. } => {}
+    }
+}
+
+fn apply_input(state: &mut State, event: InputEvent) {
+    match event {
+        InputEvent::Key { key, pressed } => tools::process_inputs(&mut state.keys, key, pressed),
+        InputEvent::MouseButton { button, pressed } => {
+            tools::process_inputs(&mut state.mouse_buttons, button, pressed)
+        }
+        InputEvent::CursorMoved(position) => tools::process_mouse_position(
+            &mut state.mouse_input,
+            (position.x as f64, position.y as f64),
+        ),
+        InputEvent::Scroll(delta) => {
+            tools::process_mouse_scroll(&mut state.mouse_input, (delta.x, delta.y))
+        }
+        InputEvent::Motion(delta) => {
+            tools::process_mouse_motion(&mut state.mouse_input, (delta.x as f64, delta.y as f64))
+        }
+        InputEvent::Gamepad(_) => {}
+    }
+}
+
+/// Start a frame. Records this frame's time and input, or replaces them with recorded ones.
+pub(crate) fn process_replay(state: &mut State) {
+    if let ReplayMode::Playing {
+        recording, frame, ..
+    } = &mut state.replay.mode
+    {
+        let recorded = recording.frames.get(*frame).cloned();
+        *frame += 1;
+
+        match recorded {
+            Some(recorded) => play_frame(state, recorded),
+            None => finish_playback(state),
+        }
+        return;
+    }
+
+    let events = tools::poll_gamepads(&mut state.gamepads);
+
+    if let ReplayMode::Recording { recording, .. } = &mut state.replay.mode {
+        let mut recorded = std::mem::take(&mut state.replay.pending);
+        recorded.extend(events.iter().copied().map(InputEvent::Gamepad));
+
+        recording.frames.push(RecordedFrame {
+            delta: *state.time.delta(),
+            events: recorded,
+        });
+    }
+
+    tools::process_gamepads(&mut state.gamepads, events);
+}
+
+fn play_frame(state: &mut State, recorded: RecordedFrame) {
+    tools::set_delta(&mut state.time, recorded.delta);
+
+    let gamepad_events = recorded
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            InputEvent::Gamepad(event) => Some(*event),
+            _ => None,
+        })
+        .collect();
+
+    recorded
+        .events
+        .into_iter()
+        .for_each(|event| apply_input(state, event));
+    tools::process_gamepads(&mut state.gamepads, gamepad_events);
+}
+
+fn finish_playback(state: &mut State) {
+    let ReplayMode::Playing {
+        recording,
+        exit_when_done,
+        ..
+    } = std::mem::replace(&mut state.replay.mode, ReplayMode::Live)
+    else {
+        return;
+    };
+
+    let result = ReplayResult {
+        expected: recording.hash,
+        actual: state_hash(state),
+    };
+
+    match result.matches() {
+        true => log::info!(
+            "Replay of {} frames finished with hash {:x}",
+            recording.frames.len(),
+            result.actual
+        ),
+        false => log::error!(
+            "Replay diverged - expected hash {:x} but got {:x}",
+            result.expected.unwrap_or_default(),
+            result.actual
+        ),
+    }
+
+    state.replay.result = Some(result);
+    state.replay.exit = exit_when_done;
+
+    // Carry on with live input
+    let events = tools::poll_gamepads(&mut state.gamepads);
+    tools::process_gamepads(&mut state.gamepads, events);
+}
+
+/// Save the recording if it was set to be saved on close.
+pub(crate) fn close(state: &mut State) {
+    let ReplayMode::Recording {
+        path: Some(path), ..
+    } = &state.replay.mode
+    else {
+        return;
+    };
+
+    let path = path.clone();
+    if let Some(recording) = state.finish_recording() {
+        match recording.save(&path) {
+            Ok(()) => log::info!("Saved recording to {:?}", path),
+            Err(e) => log::error!("Unable to save recording to {:?}: {}", path, e),
+        }
+    }
+}
+
+//====================================================================
@@ -0,0 +1,86 @@
+//====================================================================
+
+use std::{
+    any::{Any, TypeId},
+    collections::BTreeMap,
+};
+
+//====================================================================
+
+type Resource = Box<dyn Any + Send + Sync>;
+
+/// Global values stored by type, such as settings, scores or asset handles. Resources are
+/// `Send + Sync` so parallel systems can share them, see [`crate::parallel::Access`].
+#[derive(Default)]
+pub struct Resources {
+    resources: BTreeMap<TypeId, Resource>,
+}
+
+impl Resources {
+    /// Store `resource`, returning the one it replaced.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, resource: T) -> Option<T> {
+        self.resources
+            .insert(TypeId::of::<T>(), Box::new(resource))
+            .and_then(|old| old.downcast().ok())
+            .map(|old| *old)
+    }
+
+    /// Resource of type `T`, inserting its default value first if there isn't one.
+    pub fn init<T: Default + Send + Sync + 'static>(&mut self) -> &mut T {
+        self.resources
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(T::default()))
+            .downcast_mut()
+            .expect("resource stored under another type")
+    }
+
+    #[inline]
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.resources
+            .get(&TypeId::of::<T>())
+            .and_then(|resource| resource.downcast_ref())
+    }
+
+    #[inline]
+    pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.resources
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|resource| resource.downcast_mut())
+    }
+
+    #[inline]
+    pub fn remove<T: 'static>(&mut self) -> Option<T> {
+        self.resources
+            .remove(&TypeId::of::<T>())
+            .and_then(|resource| resource.downcast().ok())
+            .map(|resource| *resource)
+    }
+
+    #[inline]
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<T>())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.resources.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty()
+    }
+
+    /// Move a resource out while a parallel system writes it.
+    #[inline]
+    pub(crate) fn take(&mut self, id: TypeId) -> Option<Resource> {
+        self.resources.remove(&id)
+    }
+
+    #[inline]
+    pub(crate) fn restore(&mut self, id: TypeId, resource: Resource) {
+        self.resources.insert(id, resource);
+    }
+}
+
+//====================================================================
@@ -0,0 +1,274 @@
+//====================================================================
+
+use common::{forces::Forces, GlobalTransform};
+use hecs::Entity;
+
+use crate::{
+    collision::{push_out_sphere, Collider, CollisionLayers, ALL_LAYERS},
+    lod::UpdateLod,
+    parallel::{Access, SystemContext},
+};
+
+//====================================================================
+
+/// Where an end of a [`Rope`] is held.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RopeAnchor {
+    /// Hangs freely.
+    Free,
+    /// Follows an entity's [`GlobalTransform`], offset in the entity's space.
+    Entity { entity: Entity, offset: glam::Vec3 },
+    /// Fixed in world space.
+    Point(glam::Vec3),
+}
+
+impl RopeAnchor {
+    #[inline]
+    pub fn entity(entity: Entity) -> Self {
+        Self::Entity {
+            entity,
+            offset: glam::Vec3::ZERO,
+        }
+    }
+}
+
+/// Chain of verlet points between two anchors, simulated every fixed update. Starts as a
+/// straight line from its start anchor, or from the entity's [`GlobalTransform`] when the
+/// start is free. Points are pushed out of [`Collider`]s.
+#[derive(Debug, Clone)]
+pub struct Rope {
+    pub start: RopeAnchor,
+    pub end: RopeAnchor,
+    /// Replaced by the [`Forces`] gravity override when set.
+    pub gravity: glam::Vec3,
+    /// Velocity lost each step, from 0 to 1.
+    pub damping: f32,
+    /// How strongly the [`Forces`] wind drags points along.
+    pub drag: f32,
+    pub iterations: u32,
+    /// Distance points are kept from colliders.
+    pub thickness: f32,
+    pub collision_layers: CollisionLayers,
+
+    length: f32,
+    points: Vec<glam::Vec3>,
+    previous: Vec<glam::Vec3>,
+    placed: bool,
+}
+
+impl Rope {
+    /// Rope of `segments` links with a total `length`, held at the start and free at the end.
+    pub fn new(start: RopeAnchor, segments: u32, length: f32) -> Self {
+        let points = vec![glam::Vec3::ZERO; segments.max(1) as usize + 1];
+
+        Self {
+            start,
+            end: RopeAnchor::Free,
+            gravity: glam::vec3(0., -9.81, 0.),
+            damping: 0.01,
+            drag: 0.2,
+            iterations: 16,
+            thickness: 0.02,
+            collision_layers: ALL_LAYERS,
+            length: length.max(0.),
+            previous: points.clone(),
+            points,
+            placed: false,
+        }
+    }
+
+    #[inline]
+    pub fn with_end(mut self, end: RopeAnchor) -> Self {
+        self.end = end;
+        self
+    }
+
+    #[inline]
+    pub fn length(&self) -> f32 {
+        self.length
+    }
+
+    /// Change the rest length, e.g. to reel a grappling hook in or out.
+    #[inline]
+    pub fn set_length(&mut self, length: f32) {
+        self.length = length.max(0.);
+    }
+
+    #[inline]
+    pub fn segments(&self) -> usize {
+        self.points.len() - 1
+    }
+
+    /// World space points from start to end.
+    #[inline]
+    pub fn points(&self) -> &[glam::Vec3] {
+        &self.points
+    }
+
+    #[inline]
+    pub fn start_position(&self) -> glam::Vec3 {
+        self.points[0]
+    }
+
+    #[inline]
+    pub fn end_position(&self) -> glam::Vec3 {
+        self.points[self.points.len() - 1]
+    }
+
+    /// Lay the rope out straight again on the next step.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.placed = false;
+    }
+
+    //--------------------------------------------------
+
+    fn step(
+        &mut self,
+        origin: glam::Vec3,
+        anchors: (Option<glam::Vec3>, Option<glam::Vec3>),
+        colliders: &[(Collider, glam::Vec3)],
+        forces: &Forces,
+        delta: f32,
+    ) {
+        let (start, end) = anchors;
+        let last = self.points.len() - 1;
+        let segment = self.length / last as f32;
+
+        if !self.placed {
+            let from = start.unwrap_or(origin);
+            let direction = match end {
+                Some(end) => (end - from).normalize_or(glam::Vec3::NEG_Y),
+                None => glam::Vec3::NEG_Y,
+            };
+
+            self.points
+                .iter_mut()
+                .enumerate()
+                .for_each(|(index, point)| *point = from + direction * segment * index as f32);
+            self.previous.copy_from_slice(&self.points);
+            self.placed = true;
+        }
+
+        let retain = 1. - self.damping.clamp(0., 1.);
+        let gravity = forces.gravity_or(self.gravity);
+        let inverse_delta = 1. / delta.max(f32::EPSILON);
+
+        self.points
+            .iter_mut()
+            .zip(self.previous.iter_mut())
+            .for_each(|(point, previous)| {
+                let velocity = (*point - *previous) * retain;
+                let wind = (forces.wind_at(*point) - velocity * inverse_delta) * self.drag;
+                let acceleration = gravity + forces.acceleration_at(*point) + wind;
+
+                *previous = *point;
+                *point += velocity + acceleration * delta * delta;
+            });
+
+        let pinned = |index: usize| match index {
+            0 => start,
+            index if index == last => end,
+            _ => None,
+        };
+
+        (0..self.iterations.max(1)).for_each(|_| {
+            [0, last].into_iter().for_each(|index| {
+                if let Some(anchor) = pinned(index) {
+                    self.points[index] = anchor;
+                }
+            });
+
+            (0..last).for_each(|index| {
+                let offset = self.points[index + 1] - self.points[index];
+                let distance = offset.length();
+                if distance <= f32::EPSILON {
+                    return;
+                }
+
+                let correction = offset * ((distance - segment) / distance);
+
+                match (pinned(index).is_some(), pinned(index + 1).is_some()) {
+                    (true, true) => {}
+                    (true, false) => self.points[index + 1] -= correction,
+                    (false, true) => self.points[index] += correction,
+                    (false, false) => {
+                        self.points[index] += correction / 2.;
+                        self.points[index + 1] -= correction / 2.;
+                    }
+                }
+            });
+
+            (0..=last)
+                .filter(|index| pinned(*index).is_none())
+                .for_each(|index| {
+                    self.points[index] =
+                        push_out_sphere(self.points[index], self.thickness, colliders);
+                });
+        });
+    }
+}
+
+//====================================================================
+
+#[inline]
+pub(crate) fn rope_access() -> Access {
+    Access::new()
+        .write::<Rope>()
+        .read::<Collider>()
+        .read::<GlobalTransform>()
+        .read::<UpdateLod>()
+}
+
+pub(crate) fn process_ropes(ctx: &mut SystemContext) {
+    let lod = ctx.update_lod();
+    let delta = ctx.delta_seconds();
+    let world = ctx.world();
+    let forces = ctx.forces();
+
+    let colliders = world
+        .query::<(&Collider, &GlobalTransform)>()
+        .without::<&Rope>()
+        .iter()
+        .map(|(_, (collider, transform))| (*collider, transform.translation() + collider.offset))
+        .collect::<Vec<_>>();
+
+    let anchor = |anchor: &RopeAnchor| match anchor {
+        RopeAnchor::Free => None,
+        RopeAnchor::Entity { entity, offset } => world
+            .get::<&GlobalTransform>(*entity)
+            .ok()
+            .map(|transform| transform.0.transform_point3(*offset)),
+        RopeAnchor::Point(point) => Some(*point),
+    };
+
+    let ropes = world
+        .query::<(&Rope, &GlobalTransform, Option<&UpdateLod>)>()
+        .iter()
+        .filter(|(entity, (.., update_lod))| lod.due(*entity, *update_lod))
+        .map(|(entity, (rope, transform, update_lod))| {
+            (
+                entity,
+                transform.translation(),
+                (anchor(&rope.start), anchor(&rope.end)),
+                lod.delta(update_lod, delta),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    ropes
+        .into_iter()
+        .for_each(|(entity, origin, anchors, delta)| {
+            if let Ok(mut rope) = world.get::<&mut Rope>(entity) {
+                let colliders = colliders
+                    .iter()
+                    .filter(|(collider, _)| collider.layers & rope.collision_layers != 0)
+                    .copied()
+                    .collect::<Vec<_>>();
+
+                rope.step(origin, anchors, &colliders, forces, delta);
+            }
+        });
+}
+
+//====================================================================
@@ -12,7 +12,14 @@ impl<A: App> ApplicationHandler for Runner<A> {
 
         match self.state {
             Some(_) => log::warn!("State already exists."),
-            None => self.state = Some(OuterState::new::<A>(event_loop)),
+            None => match OuterState::new::<A>(event_loop, &self.config) {
+                Ok(state) => self.state = Some(state),
+                Err(e) => {
+                    log::error!("Unable to create renderer: {}", e);
+                    self.error = Some(e);
+                    event_loop.exit();
+                }
+            },
         }
     }
 
@@ -33,7 +40,9 @@ impl<A: App> ApplicationHandler for Runner<A> {
         cause: winit::event::StartCause,
     ) {
         if let Some(state) = &mut self.state {
-            if let winit::event::StartCause::ResumeTimeReached { .. } = cause {
+            if let winit::event::StartCause::ResumeTimeReached { .. }
+            | winit::event::StartCause::Poll = cause
+            {
                 state.request_redraw();
             }
         }
@@ -0,0 +1,241 @@
+//====================================================================
+
+use std::path::{Path, PathBuf};
+
+use hecs::Entity;
+use serde::{Deserialize, Serialize};
+use web_time::Duration;
+
+use crate::{
+    quests::QuestSave,
+    scene::{SceneContext, SceneError},
+    State,
+};
+
+//====================================================================
+
+const AUTOSAVE_FILE: &str = "autosave.json";
+const LOCK_FILE: &str = "session.lock";
+
+/// Everything needed to restore a game: entities with scene registered components and
+/// quest progress.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SaveGame {
+    /// Seconds since the unix epoch when the save was made.
+    pub timestamp: u64,
+    /// World as saved by [`crate::scene::SceneRegistry::save`].
+    pub scene: String,
+    pub quests: QuestSave,
+}
+
+impl SaveGame {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// Written to a temporary file first and then renamed, so a crash while saving
+    /// never leaves a half written save behind.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        let path = path.as_ref();
+        let temp = path.with_extension("tmp");
+
+        std::fs::write(&temp, serde_json::to_string(self)?)?;
+        std::fs::rename(temp, path)?;
+        Ok(())
+    }
+}
+
+impl State {
+    pub fn save_game(&self) -> Result<SaveGame, SceneError> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or(0);
+
+        Ok(SaveGame {
+            timestamp,
+            scene: self.scene_registry.save(&self.world, &self.assets)?,
+            quests: self.quests.save(),
+        })
+    }
+
+    /// Replace the world and quest progress with a save, returning the spawned entities.
+    pub fn load_game(&mut self, save: &SaveGame) -> Result<Vec<Entity>, SceneError> {
+        let mut world = hecs::World::new();
+
+        let entities = self.scene_registry.load(
+            &save.scene,
+            &mut world,
+            &mut SceneContext {
+                renderer: &self.renderer,
+                assets: &mut self.assets,
+            },
+        )?;
+
+        self.world = world;
+        self.quests.load(save.quests.clone());
+
+        log::info!("Loaded save game with {} entities", entities.len());
+        Ok(entities)
+    }
+}
+
+//====================================================================
+
+#[derive(Debug, Clone)]
+pub struct AutosaveConfig {
+    /// Folder for the autosave and the session lock file.
+    pub directory: PathBuf,
+    pub interval: Duration,
+}
+
+impl Default for AutosaveConfig {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("saves"),
+            interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Periodically saves the game in the background. A lock file is kept for as long as the app
+/// runs, so if it is still there on startup the last session crashed and its autosave is
+/// offered to `App::recover`.
+#[derive(Default)]
+pub struct Autosave {
+    config: Option<AutosaveConfig>,
+    elapsed: Duration,
+    requested: bool,
+    saving: bool,
+    recovered: Option<SaveGame>,
+}
+
+impl Autosave {
+    pub(crate) fn new(config: Option<AutosaveConfig>) -> Self {
+        let Some(config) = config else {
+            return Self::default();
+        };
+
+        let lock = config.directory.join(LOCK_FILE);
+        let autosave = config.directory.join(AUTOSAVE_FILE);
+
+        let recovered = match lock.exists() && autosave.exists() {
+            true => {
+                log::warn!("Last session did not shut down cleanly");
+                SaveGame::load(&autosave)
+                    .inspect_err(|e| log::error!("Unable to read autosave {:?}: {}", autosave, e))
+                    .ok()
+            }
+            false => None,
+        };
+
+        if let Err(e) = std::fs::create_dir_all(&config.directory)
+            .and_then(|_| std::fs::write(&lock, std::process::id().to_string()))
+        {
+            log::error!("Unable to create session lock {:?}: {}", lock, e);
+        }
+
+        Self {
+            config: Some(config),
+            recovered,
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    /// Whether an autosave is currently being written.
+    #[inline]
+    pub fn is_saving(&self) -> bool {
+        self.saving
+    }
+
+    /// Autosave at the start of the next frame instead of waiting for the interval.
+    #[inline]
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    #[inline]
+    pub fn set_interval(&mut self, interval: Duration) {
+        if let Some(config) = &mut self.config {
+            config.interval = interval;
+        }
+    }
+
+    #[inline]
+    pub(crate) fn take_recovered(&mut self) -> Option<SaveGame> {
+        self.recovered.take()
+    }
+}
+
+impl State {
+    #[inline]
+    pub fn autosave(&self) -> &Autosave {
+        &self.autosave
+    }
+
+    #[inline]
+    pub fn autosave_mut(&mut self) -> &mut Autosave {
+        &mut self.autosave
+    }
+}
+
+//====================================================================
+
+/// Serialize the game on the main thread when an autosave is due and write it on a task.
+pub(crate) fn process_autosave(state: &mut State) {
+    let autosave = &mut state.autosave;
+    let Some(config) = &autosave.config else {
+        return;
+    };
+
+    autosave.elapsed += *state.time.delta();
+
+    if autosave.saving || (!autosave.requested && autosave.elapsed < config.interval) {
+        return;
+    }
+
+    let path = config.directory.join(AUTOSAVE_FILE);
+    autosave.elapsed = Duration::ZERO;
+    autosave.requested = false;
+
+    let save = match state.save_game() {
+        Ok(save) => save,
+        Err(e) => {
+            log::error!("Unable to autosave: {}", e);
+            return;
+        }
+    };
+
+    state.autosave.saving = true;
+    state.tasks.spawn(
+        "Autosave",
+        move || save.save(&path).map(|_| path),
+        |state, result| {
+            state.autosave.saving = false;
+            match result {
+                Ok(path) => log::debug!("Autosaved to {:?}", path),
+                Err(e) => log::error!("Unable to write autosave: {}", e),
+            }
+        },
+    );
+}
+
+/// Remove the session lock so the next startup knows this one shut down cleanly.
+pub(crate) fn close(state: &mut State) {
+    let Some(config) = &state.autosave.config else {
+        return;
+    };
+
+    let lock = config.directory.join(LOCK_FILE);
+    if let Err(e) = std::fs::remove_file(&lock) {
+        log::warn!("Unable to remove session lock {:?}: {}", lock, e);
+    }
+}
+
+//====================================================================
@@ -0,0 +1,274 @@
+//====================================================================
+
+use std::{collections::BTreeMap, error::Error, fmt::Display, path::Path};
+
+use common::{GlobalTransform, Opacity, Transform};
+use hecs::{Component, Entity, EntityBuilder, EntityRef, World};
+use renderer::{
+    camera::{CameraViewport, OrthographicCamera, PerspectiveCamera},
+    RendererState,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{assets::AssetServer, spatial::Billboard, State};
+
+//====================================================================
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Component { name: String, message: String },
+}
+
+impl Error for SceneError {}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "Unable to access scene file: {}", e),
+            SceneError::Json(e) => write!(f, "Invalid scene json: {}", e),
+            SceneError::Component { name, message } => {
+                write!(f, "Unable to load component '{}': {}", name, message)
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for SceneError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for SceneError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+//====================================================================
+
+/// Resources available to components while they're being loaded.
+pub struct SceneContext<'a> {
+    pub renderer: &'a RendererState,
+    pub assets: &'a mut AssetServer,
+}
+
+type SaveFn = Box<dyn Fn(EntityRef, &AssetServer) -> Option<serde_json::Value>>;
+type LoadFn =
+    Box<dyn Fn(serde_json::Value, &mut EntityBuilder, &mut SceneContext) -> Result<(), String>>;
+
+/// Components that can be saved to and loaded from scenes, keyed by name.
+/// Components that aren't registered are skipped when saving.
+pub struct SceneRegistry {
+    components: BTreeMap<&'static str, (SaveFn, LoadFn)>,
+}
+
+impl Default for SceneRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            components: BTreeMap::new(),
+        };
+
+        registry
+            .register::<Transform>("Transform")
+            .register::<GlobalTransform>("GlobalTransform")
+            .register::<Billboard>("Billboard")
+            .register::<Opacity>("Opacity")
+            .register::<CameraViewport>("CameraViewport")
+            .register_camera::<PerspectiveCamera>("PerspectiveCamera")
+            .register_camera::<OrthographicCamera>("OrthographicCamera");
+
+        registry
+    }
+}
+
+impl SceneRegistry {
+    /// Register a component that can be (de)serialized as is.
+    pub fn register<T>(&mut self, name: &'static str) -> &mut Self
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        self.register_with(
+            name,
+            |entity, _| {
+                entity
+                    .get::<&T>()
+                    .and_then(|c| serde_json::to_value(&*c).ok())
+            },
+            |value, builder, _| {
+                builder.add(serde_json::from_value::<T>(value).map_err(|e| e.to_string())?);
+                Ok(())
+            },
+        )
+    }
+
+    /// Register a component with custom save and load functions, for components that
+    /// reference gpu resources or assets.
+    pub fn register_with(
+        &mut self,
+        name: &'static str,
+        save: impl Fn(EntityRef, &AssetServer) -> Option<serde_json::Value> + 'static,
+        load: impl Fn(serde_json::Value, &mut EntityBuilder, &mut SceneContext) -> Result<(), String>
+            + 'static,
+    ) -> &mut Self {
+        if self
+            .components
+            .insert(name, (Box::new(save), Box::new(load)))
+            .is_some()
+        {
+            log::warn!("Scene component '{}' registered twice", name);
+        }
+        self
+    }
+
+    fn register_camera<T>(&mut self, name: &'static str) -> &mut Self
+    where
+        T: renderer::camera::CameraUniform + Component + Serialize + DeserializeOwned,
+    {
+        self.register_with(
+            name,
+            |entity, _| {
+                entity
+                    .get::<&T>()
+                    .and_then(|c| serde_json::to_value(&*c).ok())
+            },
+            |value, builder, context| {
+                let camera = serde_json::from_value::<T>(value).map_err(|e| e.to_string())?;
+                context.renderer.spawn_camera(builder, camera);
+                Ok(())
+            },
+        )
+    }
+
+    //--------------------------------------------------
+
+    /// Names of every registered component.
+    #[inline]
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.components.keys().copied()
+    }
+
+    /// Registered components of `entity` by name.
+    pub fn save_entity(
+        &self,
+        entity: EntityRef,
+        assets: &AssetServer,
+    ) -> serde_json::Map<String, serde_json::Value> {
+        self.components
+            .iter()
+            .filter_map(|(name, (save, _))| {
+                save(entity, assets).map(|value| (name.to_string(), value))
+            })
+            .collect()
+    }
+
+    /// Builder of the components saved by [`SceneRegistry::save_entity`].
+    pub fn load_entity(
+        &self,
+        components: serde_json::Map<String, serde_json::Value>,
+        context: &mut SceneContext,
+    ) -> Result<EntityBuilder, SceneError> {
+        let mut builder = EntityBuilder::new();
+
+        components.into_iter().try_for_each(|(name, value)| {
+            match self.components.get(name.as_str()) {
+                Some((_, load)) => load(value, &mut builder, context)
+                    .map_err(|message| SceneError::Component { name, message }),
+                None => {
+                    log::warn!("Skipping unregistered scene component '{}'", name);
+                    Ok(())
+                }
+            }
+        })?;
+
+        Ok(builder)
+    }
+
+    pub fn save(&self, world: &World, assets: &AssetServer) -> Result<String, SceneError> {
+        let entities = world
+            .iter()
+            .map(|entity| self.save_entity(entity, assets))
+            .filter(|components| !components.is_empty())
+            .collect();
+
+        Ok(serde_json::to_string_pretty(&SceneData { entities })?)
+    }
+
+    /// Spawn every entity in a scene, returning the spawned entities.
+    pub fn load(
+        &self,
+        json: &str,
+        world: &mut World,
+        context: &mut SceneContext,
+    ) -> Result<Vec<Entity>, SceneError> {
+        let data = serde_json::from_str::<SceneData>(json)?;
+
+        // Build everything before spawning so a bad component doesn't leave a half loaded scene
+        let builders = data
+            .entities
+            .into_iter()
+            .map(|components| self.load_entity(components, context))
+            .collect::<Result<Vec<_>, SceneError>>()?;
+
+        // Grow entity storage once rather than as the scene spawns
+        world.reserve::<()>(builders.len() as u32);
+
+        Ok(builders
+            .into_iter()
+            .map(|mut builder| world.spawn(builder.build()))
+            .collect())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SceneData {
+    entities: Vec<serde_json::Map<String, serde_json::Value>>,
+}
+
+//====================================================================
+
+impl State {
+    /// Asset server along with the renderer, for loading assets that need the gpu immediately.
+    #[inline]
+    pub fn scene_context(&mut self) -> SceneContext<'_> {
+        SceneContext {
+            renderer: &self.renderer,
+            assets: &mut self.assets,
+        }
+    }
+
+    #[inline]
+    pub fn scene_registry_mut(&mut self) -> &mut SceneRegistry {
+        &mut self.scene_registry
+    }
+
+    /// Save every entity with registered components to a json file.
+    pub fn save_scene(&self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        let json = self.scene_registry.save(&self.world, &self.assets)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Spawn a scene saved with [`State::save_scene`] into the world. Assets referenced
+    /// by the scene are loaded before returning.
+    pub fn load_scene(&mut self, path: impl AsRef<Path>) -> Result<Vec<Entity>, SceneError> {
+        let path = path.as_ref();
+        let json = self.assets.vfs().read_to_string(&path.to_string_lossy())?;
+
+        let entities = self.scene_registry.load(
+            &json,
+            &mut self.world,
+            &mut SceneContext {
+                renderer: &self.renderer,
+                assets: &mut self.assets,
+            },
+        )?;
+
+        log::info!("Loaded {} entities from scene {:?}", entities.len(), path);
+        Ok(entities)
+    }
+}
+
+//====================================================================
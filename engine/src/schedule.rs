@@ -0,0 +1,656 @@
+//====================================================================
+
+use std::{error::Error, fmt::Display};
+
+use common::profiler;
+
+use crate::{
+    lod::LodTick,
+    parallel::{self, Access, Batch, BatchEntry, BatchState, ParallelSystem},
+    State,
+};
+
+//====================================================================
+
+/// System with the whole state, run on its own.
+pub type System = fn(&mut State);
+
+/// Part of the frame systems run in, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    /// Start of the frame, after input is received.
+    PreUpdate,
+    /// Every `Time::fixed_delta`, after `App::fixed_update`.
+    FixedUpdate,
+    /// Before `App::update`.
+    Update,
+    /// After `App::update`, once transforms are final for the frame.
+    PostUpdate,
+    Render,
+    /// End of the frame, where input and events are reset.
+    Last,
+}
+
+impl Stage {
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::PreUpdate => "PreUpdate",
+            Stage::FixedUpdate => "FixedUpdate",
+            Stage::Update => "Update",
+            Stage::PostUpdate => "PostUpdate",
+            Stage::Render => "Render",
+            Stage::Last => "Last",
+        }
+    }
+}
+
+/// Where a system runs in its stage, given when adding it. Constraints on systems that
+/// haven't been added are applied once they are.
+#[derive(Debug, Clone, Default)]
+pub struct SystemOrder {
+    before: Vec<&'static str>,
+    after: Vec<&'static str>,
+}
+
+impl SystemOrder {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn before(mut self, system: &'static str) -> Self {
+        self.before.push(system);
+        self
+    }
+
+    #[inline]
+    pub fn after(mut self, system: &'static str) -> Self {
+        self.after.push(system);
+        self
+    }
+}
+
+//--------------------------------------------------
+
+#[derive(Debug)]
+pub enum ScheduleError {
+    AlreadyAdded(&'static str),
+    /// Constraint against the stage order, e.g. a render system before an update one.
+    StageConflict {
+        first: &'static str,
+        second: &'static str,
+    },
+    /// Constraints that can't all be met.
+    Cycle(&'static str),
+}
+
+impl Error for ScheduleError {}
+
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleError::AlreadyAdded(name) => {
+                write!(f, "System {} has already been added", name)
+            }
+            ScheduleError::StageConflict { first, second } => write!(
+                f,
+                "{} can't run before {} as it's in a later stage",
+                first, second
+            ),
+            ScheduleError::Cycle(name) => {
+                write!(f, "Ordering constraints of {} form a cycle", name)
+            }
+        }
+    }
+}
+
+//====================================================================
+
+#[derive(Clone)]
+enum SystemKind {
+    Exclusive(System),
+    Parallel(ParallelSystem, Access),
+}
+
+struct SystemEntry {
+    name: &'static str,
+    stage: Stage,
+    order: SystemOrder,
+    system: SystemKind,
+    /// Throttles [`crate::lod::UpdateLod`] entities, see [`crate::lod::LodTick`].
+    lod: bool,
+}
+
+/// Systems run by the engine each frame, sorted by stage then ordering constraints then the
+/// order they were added in. Engine systems are named `engine::*`.
+///
+/// Neighbouring [`ParallelSystem`]s whose [`Access`] doesn't conflict run at the same time on
+/// native platforms. Exclusive systems run on their own between them.
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<SystemEntry>,
+}
+
+impl Schedule {
+    /// Add a system after those already in `stage`.
+    #[inline]
+    pub fn add(
+        &mut self,
+        stage: Stage,
+        name: &'static str,
+        system: System,
+    ) -> Result<(), ScheduleError> {
+        self.add_ordered(stage, name, system, SystemOrder::new())
+    }
+
+    /// Add a system placed by `order`, e.g. `SystemOrder::new().after("engine::physics2d")`.
+    #[inline]
+    pub fn add_ordered(
+        &mut self,
+        stage: Stage,
+        name: &'static str,
+        system: System,
+        order: SystemOrder,
+    ) -> Result<(), ScheduleError> {
+        self.insert(stage, name, SystemKind::Exclusive(system), order)
+    }
+
+    /// Add a parallel system after those already in `stage`.
+    #[inline]
+    pub fn add_parallel(
+        &mut self,
+        stage: Stage,
+        name: &'static str,
+        access: Access,
+        system: ParallelSystem,
+    ) -> Result<(), ScheduleError> {
+        self.add_parallel_ordered(stage, name, access, system, SystemOrder::new())
+    }
+
+    /// Add a parallel system placed by `order`. Ordering constraints hold even between
+    /// systems that could otherwise run together.
+    #[inline]
+    pub fn add_parallel_ordered(
+        &mut self,
+        stage: Stage,
+        name: &'static str,
+        access: Access,
+        system: ParallelSystem,
+        order: SystemOrder,
+    ) -> Result<(), ScheduleError> {
+        self.insert(stage, name, SystemKind::Parallel(system, access), order)
+    }
+
+    fn insert(
+        &mut self,
+        stage: Stage,
+        name: &'static str,
+        system: SystemKind,
+        order: SystemOrder,
+    ) -> Result<(), ScheduleError> {
+        if self.contains(name) {
+            return Err(ScheduleError::AlreadyAdded(name));
+        }
+
+        self.systems.push(SystemEntry {
+            name,
+            stage,
+            order,
+            system,
+            lod: false,
+        });
+
+        match resolve(&self.systems) {
+            Ok(order) => {
+                let mut systems = self.systems.drain(..).map(Some).collect::<Vec<_>>();
+                self.systems = order
+                    .into_iter()
+                    .filter_map(|index| systems[index].take())
+                    .collect();
+                Ok(())
+            }
+            Err(e) => {
+                self.systems.pop();
+                Err(e)
+            }
+        }
+    }
+
+    /// Remove the system named `name`, returning false if there isn't one.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.systems.len();
+        self.systems.retain(|entry| entry.name != name);
+        self.systems.len() != len
+    }
+
+    /// Opt the system named `name` in or out of update throttling by distance, returning
+    /// false if there isn't one.
+    pub fn set_lod(&mut self, name: &str, enabled: bool) -> bool {
+        match self.systems.iter_mut().find(|entry| entry.name == name) {
+            Some(entry) => {
+                entry.lod = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[inline]
+    pub fn lod(&self, name: &str) -> bool {
+        self.systems
+            .iter()
+            .any(|entry| entry.name == name && entry.lod)
+    }
+
+    #[inline]
+    pub fn contains(&self, name: &str) -> bool {
+        self.systems.iter().any(|entry| entry.name == name)
+    }
+
+    /// Access of the parallel system named `name`, or None if it's exclusive or missing.
+    pub fn access(&self, name: &str) -> Option<&Access> {
+        self.systems
+            .iter()
+            .find(|entry| entry.name == name)
+            .and_then(|entry| match &entry.system {
+                SystemKind::Exclusive(_) => None,
+                SystemKind::Parallel(_, access) => Some(access),
+            })
+    }
+
+    /// Names of the systems in `stage` in the order they run.
+    pub fn names(&self, stage: Stage) -> impl Iterator<Item = &'static str> + '_ {
+        self.systems
+            .iter()
+            .filter(move |entry| entry.stage == stage)
+            .map(|entry| entry.name)
+    }
+
+    /// Systems of `stage` in order, along with the systems each parallel one must run after.
+    fn systems(&self, stage: Stage) -> Vec<(&'static str, SystemKind, bool, Vec<&'static str>)> {
+        let systems = self
+            .systems
+            .iter()
+            .filter(|entry| entry.stage == stage)
+            .collect::<Vec<_>>();
+
+        systems
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                let after = systems[..index]
+                    .iter()
+                    .filter(|earlier| {
+                        entry.order.after.contains(&earlier.name)
+                            || earlier.order.before.contains(&entry.name)
+                    })
+                    .map(|earlier| earlier.name)
+                    .collect();
+
+                (entry.name, entry.system.clone(), entry.lod, after)
+            })
+            .collect()
+    }
+}
+
+/// Run every system in `stage`. Systems added or removed while running take effect from
+/// the next run.
+pub(crate) fn run_stage(state: &mut State, stage: Stage) {
+    let _scope = profiler::scope(stage.name(), "stage");
+
+    let tick = state.lod.begin_stage(stage);
+    let delta = match stage {
+        Stage::FixedUpdate => state.time.fixed_delta_seconds(),
+        _ => state.time.delta_seconds(),
+    };
+
+    let mut batch = Batch::default();
+
+    let flush = |state: &mut State, batch: &mut Batch| {
+        parallel::run_batch(
+            batch,
+            BatchState {
+                world: &mut state.world,
+                resources: &mut state.resources,
+                time: &state.time,
+                forces: &state.forces,
+                delta,
+                tick,
+                workers: &state.workers,
+            },
+        )
+    };
+
+    state
+        .schedule
+        .systems(stage)
+        .into_iter()
+        .for_each(|(name, system, lod, after)| match system {
+            SystemKind::Exclusive(system) => {
+                flush(state, &mut batch);
+
+                let _scope = profiler::scope(name, "system");
+                state.lod.current = LodTick { enabled: lod, tick };
+                system(state);
+                state.lod.current = LodTick::default();
+            }
+            SystemKind::Parallel(system, access) => {
+                if batch.conflicts(&access) || after.iter().any(|name| batch.contains(name)) {
+                    flush(state, &mut batch);
+                }
+
+                batch.push(BatchEntry {
+                    name,
+                    system,
+                    access,
+                    lod,
+                });
+            }
+        });
+
+    flush(state, &mut batch);
+}
+
+//====================================================================
+
+fn resolve(systems: &[SystemEntry]) -> Result<Vec<usize>, ScheduleError> {
+    let index_of = |name: &&'static str| systems.iter().position(|entry| entry.name == *name);
+
+    // Edges of systems run before others
+    let mut edges = Vec::new();
+
+    systems.iter().enumerate().for_each(|(index, entry)| {
+        edges.extend(
+            entry
+                .order
+                .before
+                .iter()
+                .filter_map(index_of)
+                .map(|other| (index, other)),
+        );
+        edges.extend(
+            entry
+                .order
+                .after
+                .iter()
+                .filter_map(index_of)
+                .map(|other| (other, index)),
+        );
+    });
+
+    for (first, second) in &edges {
+        if systems[*first].stage > systems[*second].stage {
+            return Err(ScheduleError::StageConflict {
+                first: systems[*first].name,
+                second: systems[*second].name,
+            });
+        }
+    }
+
+    // Stages already order systems across them
+    edges.retain(|(first, second)| systems[*first].stage == systems[*second].stage);
+
+    let mut incoming = vec![0; systems.len()];
+    edges.iter().for_each(|(_, second)| incoming[*second] += 1);
+
+    let mut order = Vec::with_capacity(systems.len());
+    let mut placed = vec![false; systems.len()];
+
+    while order.len() < systems.len() {
+        let Some(next) = (0..systems.len())
+            .filter(|index| !placed[*index] && incoming[*index] == 0)
+            .min_by_key(|index| (systems[*index].stage, *index))
+        else {
+            let stuck = (0..systems.len()).find(|index| !placed[*index]).unwrap();
+            return Err(ScheduleError::Cycle(systems[stuck].name));
+        };
+
+        placed[next] = true;
+        order.push(next);
+
+        edges
+            .iter()
+            .filter(|(first, _)| *first == next)
+            .for_each(|(_, second)| incoming[*second] -= 1);
+    }
+
+    Ok(order)
+}
+
+//====================================================================
+
+/// Schedule of the systems the engine runs itself.
+pub(crate) fn engine_schedule() -> Schedule {
+    use crate::{
+        assets, audio, camera2d, character, cloth, combat, debug_keys, dialogue, focus, health,
+        inventory, lod, music, physics2d, quests, replay, rope, save, spatial, tasks, tools,
+        triggers, water, window,
+    };
+    use SystemKind::{Exclusive, Parallel};
+
+    let systems: Vec<(Stage, &'static str, SystemKind)> = vec![
+        (
+            Stage::PreUpdate,
+            "engine::replay",
+            Exclusive(replay::process_replay),
+        ),
+        (
+            Stage::PreUpdate,
+            "engine::assets",
+            Exclusive(assets::process_assets),
+        ),
+        (
+            Stage::PreUpdate,
+            "engine::tasks",
+            Exclusive(tasks::process_tasks),
+        ),
+        (
+            Stage::PreUpdate,
+            "engine::autosave",
+            Exclusive(save::process_autosave),
+        ),
+        (
+            Stage::PreUpdate,
+            "engine::mixer",
+            Exclusive(audio::process_mixer),
+        ),
+        (
+            Stage::PreUpdate,
+            "engine::music",
+            Exclusive(music::process_music),
+        ),
+        (
+            Stage::PreUpdate,
+            "engine::focus",
+            Exclusive(focus::process_focus),
+        ),
+        (
+            Stage::PreUpdate,
+            "engine::device_recovered",
+            Exclusive(|state| {
+                if state.renderer.take_recovered() {
+                    state
+                        .window_events
+                        .push(window::WindowEvent::DeviceRecovered);
+                }
+            }),
+        ),
+        (
+            Stage::PreUpdate,
+            "engine::debug_keys",
+            Exclusive(debug_keys::process_debug_keys),
+        ),
+        #[cfg(feature = "inspector")]
+        (
+            Stage::PreUpdate,
+            "engine::inspector",
+            Exclusive(crate::inspector::process_inspector),
+        ),
+        (
+            Stage::PreUpdate,
+            "engine::capture_key",
+            Exclusive(|state| {
+                if let Some(key) = state.capture_key {
+                    if state.keys.just_pressed(key) {
+                        state.renderer.capture_next_frame();
+                    }
+                }
+            }),
+        ),
+        (
+            Stage::FixedUpdate,
+            "engine::water",
+            Exclusive(|state| {
+                let delta = state.time.fixed_delta_seconds();
+                water::process_water(state, delta);
+            }),
+        ),
+        (
+            Stage::FixedUpdate,
+            "engine::physics2d",
+            Parallel(physics2d::process_physics, physics2d::physics_access()),
+        ),
+        #[cfg(feature = "physics3d")]
+        (
+            Stage::FixedUpdate,
+            "engine::physics3d",
+            Parallel(
+                crate::physics3d::process_physics,
+                crate::physics3d::physics_access(),
+            ),
+        ),
+        (
+            Stage::FixedUpdate,
+            "engine::character",
+            Exclusive(|state| {
+                let delta = state.time.fixed_delta_seconds();
+                character::process_controllers(state, delta);
+            }),
+        ),
+        (
+            Stage::FixedUpdate,
+            "engine::cloth",
+            Parallel(cloth::process_cloth, cloth::cloth_access()),
+        ),
+        (
+            Stage::FixedUpdate,
+            "engine::rope",
+            Parallel(rope::process_ropes, rope::rope_access()),
+        ),
+        (
+            Stage::Update,
+            "engine::clear_hits",
+            Exclusive(combat::clear_hits),
+        ),
+        (
+            Stage::Update,
+            "engine::projectiles",
+            Exclusive(combat::process_projectiles),
+        ),
+        (
+            Stage::PostUpdate,
+            "engine::health",
+            Exclusive(health::process_health),
+        ),
+        (
+            Stage::PostUpdate,
+            "engine::inventory",
+            Exclusive(inventory::process_inventories),
+        ),
+        (
+            Stage::PostUpdate,
+            "engine::dialogue",
+            Exclusive(dialogue::process_dialogue),
+        ),
+        (
+            Stage::PostUpdate,
+            "engine::quests",
+            Exclusive(quests::process_quests),
+        ),
+        (
+            Stage::PostUpdate,
+            "engine::orphans",
+            Exclusive(spatial::process_orphans),
+        ),
+        (
+            Stage::PostUpdate,
+            "engine::global_transform",
+            Parallel(
+                spatial::process_global_transform,
+                spatial::global_transform_access(),
+            ),
+        ),
+        (
+            Stage::PostUpdate,
+            "engine::opacity",
+            Parallel(spatial::process_opacity, spatial::opacity_access()),
+        ),
+        (
+            Stage::PostUpdate,
+            "engine::update_lod",
+            Exclusive(lod::process_update_lod),
+        ),
+        (
+            Stage::PostUpdate,
+            "engine::camera_follow",
+            Exclusive(camera2d::process_camera_follow),
+        ),
+        (
+            Stage::PostUpdate,
+            "engine::billboards",
+            Exclusive(spatial::process_billboards),
+        ),
+        (
+            Stage::PostUpdate,
+            "engine::audio",
+            Exclusive(audio::process_audio),
+        ),
+        (
+            Stage::PostUpdate,
+            "engine::triggers",
+            Exclusive(triggers::process_triggers),
+        ),
+        (
+            Stage::Render,
+            "engine::render",
+            Exclusive(|state| {
+                state.renderer.tick(&mut state.world);
+            }),
+        ),
+        (
+            Stage::Last,
+            "engine::reset_input",
+            Exclusive(|state| {
+                tools::reset_input(&mut state.keys);
+                tools::reset_input(&mut state.mouse_buttons);
+                tools::reset_mouse_input(&mut state.mouse_input);
+                tools::reset_gamepads(&mut state.gamepads);
+            }),
+        ),
+        (
+            Stage::Last,
+            "engine::clear_events",
+            Exclusive(|state| {
+                state.focus.clear_events();
+                state.window_events.clear();
+                state.events.update();
+            }),
+        ),
+    ];
+
+    let mut schedule = Schedule::default();
+    systems.into_iter().for_each(|(stage, name, system)| {
+        schedule
+            .insert(stage, name, system, SystemOrder::new())
+            .expect("engine systems are unique");
+    });
+
+    schedule.set_lod("engine::cloth", true);
+    schedule.set_lod("engine::rope", true);
+
+    schedule
+}
+
+//====================================================================
@@ -1,98 +1,456 @@
 //====================================================================
 
-use std::collections::{HashMap, HashSet};
+use std::{error::Error, fmt::Display};
 
-use common::{GlobalTransform, Transform};
-use hecs::{Entity, World};
+use common::{GlobalOpacity, GlobalTransform, Opacity, Transform};
+use hecs::{Entity, Without, World};
+
+use crate::{
+    parallel::{Access, SystemContext},
+    State,
+};
 
 //====================================================================
 
-pub(crate) fn process_global_transform(state: &mut crate::State) {
-    state
-        .world
-        .query_mut::<(&Transform, &mut GlobalTransform)>()
-        .into_iter()
-        .for_each(|(_, (transform, global))| global.0 = transform.to_affine());
+/// Parent of an entity in the transform hierarchy. The entity's [`Transform`] is then
+/// relative to the parent's [`GlobalTransform`].
+///
+/// Managed through [`State::attach`] and [`State::detach`] which keep [`Children`] in sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parent(pub(crate) Entity);
+
+impl Parent {
+    #[inline]
+    pub fn get(&self) -> Entity {
+        self.0
+    }
+}
+
+/// Children of an entity in the transform hierarchy, in the order they were attached.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Children(pub(crate) Vec<Entity>);
+
+impl Children {
+    #[inline]
+    pub fn entities(&self) -> &[Entity] {
+        &self.0
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// What happens to children whose parent was despawned without [`State::despawn_recursive`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OrphanPolicy {
+    /// Become roots, keeping their last world space transform.
+    #[default]
+    ToWorld,
+    /// Despawn along with all their descendants.
+    Despawn,
+}
+
+/// Turn the entity's [`GlobalTransform`] to face the main camera every frame, keeping its
+/// position and scale. Applied after the hierarchy is updated, so children aren't turned with it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Billboard {
+    /// Only turn around the Y axis, keeping the entity upright.
+    pub lock_y: bool,
+}
+
+//--------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HierarchyError {
+    NoEntity(Entity),
+    /// The parent is the child or one of its descendants.
+    Cycle {
+        child: Entity,
+        parent: Entity,
+    },
+}
+
+impl Error for HierarchyError {}
+
+impl Display for HierarchyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HierarchyError::NoEntity(entity) => write!(f, "Entity '{:?}' does not exist", entity),
+            HierarchyError::Cycle { child, parent } => write!(
+                f,
+                "Attaching '{:?}' to '{:?}' would create a cycle",
+                child, parent
+            ),
+        }
+    }
 }
 
 //====================================================================
 
-#[derive(Debug)]
-pub struct LocalTransform {
-    pub parent: Entity,
-    pub transform: Transform,
+impl State {
+    /// Attach `child` to `parent`, moving it from its previous parent if it had one.
+    /// The child's [`Transform`] is kept as is, so it becomes relative to the parent.
+    pub fn attach(&mut self, child: Entity, parent: Entity) -> Result<(), HierarchyError> {
+        [child, parent]
+            .into_iter()
+            .try_for_each(|entity| match self.world.contains(entity) {
+                true => Ok(()),
+                false => Err(HierarchyError::NoEntity(entity)),
+            })?;
+
+        let mut ancestor = Some(parent);
+        while let Some(current) = ancestor {
+            if current == child {
+                return Err(HierarchyError::Cycle { child, parent });
+            }
+            ancestor = self
+                .world
+                .get::<&Parent>(current)
+                .ok()
+                .map(|parent| parent.0);
+        }
+
+        self.detach(child);
+
+        match self.world.satisfies::<&Children>(parent).unwrap_or(false) {
+            true => self
+                .world
+                .get::<&mut Children>(parent)
+                .unwrap()
+                .0
+                .push(child),
+            false => self
+                .world
+                .insert_one(parent, Children(vec![child]))
+                .unwrap(),
+        }
+
+        if !self
+            .world
+            .satisfies::<&GlobalTransform>(child)
+            .unwrap_or(false)
+        {
+            self.world
+                .insert_one(child, GlobalTransform::default())
+                .unwrap();
+        }
+        self.world.insert_one(child, Parent(parent)).unwrap();
+
+        Ok(())
+    }
+
+    /// Remove `child` from its parent, making it a root. Its [`Transform`] is kept as is,
+    /// so it is now relative to the world.
+    pub fn detach(&mut self, child: Entity) {
+        let Ok(Parent(parent)) = self.world.remove_one::<Parent>(child) else {
+            return;
+        };
+
+        let empty = match self.world.get::<&mut Children>(parent) {
+            Ok(mut children) => {
+                children.0.retain(|entity| *entity != child);
+                children.0.is_empty()
+            }
+            Err(_) => false,
+        };
+
+        if empty {
+            self.world.remove_one::<Children>(parent).ok();
+        }
+    }
+
+    /// Despawn an entity along with all of its descendants.
+    pub fn despawn_recursive(&mut self, entity: Entity) {
+        self.detach(entity);
+        despawn_descendants(&mut self.world, entity);
+    }
+
+    #[inline]
+    pub fn orphan_policy(&self) -> OrphanPolicy {
+        self.orphan_policy
+    }
+
+    #[inline]
+    pub fn set_orphan_policy(&mut self, policy: OrphanPolicy) {
+        self.orphan_policy = policy;
+    }
 }
 
-pub(crate) fn process_transform_hierarchy(state: &mut crate::State) {
-    #[derive(Default)]
-    struct Hierarchy {
-        entries: HashSet<Entity>,
-        links: HashMap<Entity, Vec<Entity>>,
+fn despawn_descendants(world: &mut World, entity: Entity) {
+    let mut stack = vec![entity];
+
+    while let Some(current) = stack.pop() {
+        if let Ok(children) = world.get::<&Children>(current) {
+            stack.extend_from_slice(&children.0);
+        }
+        world.despawn(current).ok();
     }
+}
+
+//====================================================================
+
+/// Apply the [`OrphanPolicy`] to entities whose parent was despawned without detaching them.
+pub(crate) fn process_orphans(state: &mut State) {
+    let orphans = state
+        .world
+        .query::<&Parent>()
+        .iter()
+        .filter(|(_, parent)| !state.world.contains(parent.0))
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
 
-    let hierarchy = state.world.query_mut::<&LocalTransform>().into_iter().fold(
-        Hierarchy::default(),
-        |mut acc, (entity, local)| {
-            acc.entries.insert(entity);
+    orphans
+        .into_iter()
+        .for_each(|orphan| match state.orphan_policy {
+            OrphanPolicy::ToWorld => {
+                state.world.remove_one::<Parent>(orphan).ok();
 
-            acc.links
-                .entry(local.parent)
-                .or_insert(Vec::new())
-                .push(entity);
+                if let Ok((transform, global)) = state
+                    .world
+                    .query_one_mut::<(&mut Transform, &GlobalTransform)>(orphan)
+                {
+                    let (scale, rotation, translation) = global.to_scale_rotation_translation();
+                    *transform = Transform {
+                        translation,
+                        rotation,
+                        scale,
+                    };
+                }
+            }
+            OrphanPolicy::Despawn => despawn_descendants(&mut state.world, orphan),
+        });
+}
+
+#[inline]
+pub(crate) fn global_transform_access() -> Access {
+    Access::new()
+        .read::<Transform>()
+        .write::<GlobalTransform>()
+        .read::<Parent>()
+        .read::<Children>()
+}
 
-            acc
-        },
-    );
+/// Update every [`GlobalTransform`], roots first and then down through [`Children`].
+/// Entities are only written to if they or one of their ancestors changed.
+pub(crate) fn process_global_transform(ctx: &mut SystemContext) {
+    let world = ctx.world();
 
-    let roots = hierarchy
-        .links
-        .keys()
-        .filter(|val| !hierarchy.entries.contains(val))
+    let mut stack = world
+        .query::<Without<(Option<&Transform>, &mut GlobalTransform, Option<&Children>), &Parent>>()
+        .iter()
+        .filter_map(|(entity, (transform, global, children))| {
+            let dirty = match transform {
+                Some(transform) => set_global(global, transform.to_affine()),
+                // Set elsewhere, so assume it changed
+                None => true,
+            };
+
+            children.map(|_| (entity, global.0, dirty))
+        })
         .collect::<Vec<_>>();
 
-    roots.into_iter().for_each(|root| {
-        let root_transform = match state.world.get::<&GlobalTransform>(*root) {
-            Ok(transform) => transform.0,
-            Err(_) => {
-                log::warn!(
-                    "Entity '{:?}' is a root transform for '{:?}' but is missing a GlobalTransform component.",
-                    root, 
-                    hierarchy.links.get(root)
-                );
-                glam::Affine3A::IDENTITY
+    let mut dead = Vec::new();
+
+    while let Some((parent, parent_global, parent_dirty)) = stack.pop() {
+        let Ok(children) = world.get::<&Children>(parent).map(|c| c.0.clone()) else {
+            continue;
+        };
+
+        children.into_iter().for_each(|child| {
+            match cascade_transform(world, parent, child, parent_global, parent_dirty) {
+                Some(next) => stack.push(next),
+                None if !world.contains(child) => dead.push((parent, child)),
+                None => {}
             }
+        });
+    }
+
+    // Despawned children are dropped once the systems running alongside are done reading
+    let mut parents = dead.iter().map(|(parent, _)| *parent).collect::<Vec<_>>();
+    parents.dedup();
+
+    parents.into_iter().for_each(|parent| {
+        let Ok(children) = world.get::<&Children>(parent) else {
+            return;
         };
 
-        hierarchy
-            .links
-            .get(root)
-            .unwrap()
-            .into_iter()
-            .for_each(|child| {
-                cascade_transform(&mut state.world, &hierarchy.links, *child, root_transform);
-            });
+        let alive = children
+            .0
+            .iter()
+            .filter(|child| !dead.contains(&(parent, **child)))
+            .copied()
+            .collect();
+
+        ctx.commands().insert_one(parent, Children(alive));
     });
 }
 
+pub(crate) fn process_billboards(state: &mut State) {
+    let Some(camera) = state
+        .main_camera()
+        .and_then(|camera| state.world.get::<&GlobalTransform>(camera).ok())
+        .map(|transform| transform.translation())
+    else {
+        return;
+    };
+
+    state
+        .world
+        .query_mut::<(&Billboard, &mut GlobalTransform)>()
+        .into_iter()
+        .for_each(|(_, (billboard, global))| {
+            let (scale, _, translation) = global.to_scale_rotation_translation();
+
+            // Facing away from the camera, so the entity's +Z points the same way as the view
+            let mut forward = translation - camera;
+            if billboard.lock_y {
+                forward.y = 0.;
+            }
+
+            let Some(forward) = forward.try_normalize() else {
+                return;
+            };
+
+            let right = glam::Vec3::Y
+                .cross(forward)
+                .try_normalize()
+                .unwrap_or(glam::Vec3::X);
+            let up = forward.cross(right);
+            let rotation = glam::Quat::from_mat3(&glam::Mat3::from_cols(right, up, forward));
+
+            global.0 =
+                glam::Affine3A::from_scale_rotation_translation(scale, rotation, translation);
+        });
+}
+
+/// Returns the child's global transform and whether it changed if it has children of its own.
 fn cascade_transform(
-    world: &mut World,
-    links: &HashMap<Entity, Vec<Entity>>,
-    current: Entity,
-    mut transform: glam::Affine3A,
-) {
-    if let Ok(local) = world.get::<&LocalTransform>(current) {
-        transform *= local.transform.to_affine();
+    world: &World,
+    parent: Entity,
+    child: Entity,
+    parent_global: glam::Affine3A,
+    parent_dirty: bool,
+) -> Option<(Entity, glam::Affine3A, bool)> {
+    let mut query = world
+        .query_one::<(
+            Option<&Transform>,
+            &mut GlobalTransform,
+            Option<&Parent>,
+            Option<&Children>,
+        )>(child)
+        .ok()?;
+    let (transform, global, child_parent, children) = query.get()?;
+
+    if child_parent.map(|p| p.0) != Some(parent) {
+        log::warn!(
+            "Entity '{:?}' is listed as a child of '{:?}' but has a different parent",
+            child,
+            parent
+        );
+        return None;
     }
 
-    if let Ok(mut entity_transform) = world.get::<&mut GlobalTransform>(current) {
-        entity_transform.0 = transform;
+    let local = transform
+        .map(|transform| transform.to_affine())
+        .unwrap_or(glam::Affine3A::IDENTITY);
+
+    let dirty = match parent_dirty {
+        true => {
+            global.0 = parent_global * local;
+            true
+        }
+        false => set_global(global, parent_global * local),
+    };
+
+    children.map(|_| (child, global.0, dirty))
+}
+
+/// Returns true if the transform changed.
+#[inline]
+fn set_global(global: &mut GlobalTransform, transform: glam::Affine3A) -> bool {
+    match global.0 == transform {
+        true => false,
+        false => {
+            global.0 = transform;
+            true
+        }
     }
+}
 
-    if let Some(child_links) = links.get(&current) {
-        child_links
-            .into_iter()
-            .for_each(|child| cascade_transform(world, links, *child, transform))
+//====================================================================
+
+#[inline]
+pub(crate) fn opacity_access() -> Access {
+    Access::new()
+        .read::<Opacity>()
+        .write::<GlobalOpacity>()
+        .read::<Parent>()
+        .read::<Children>()
+}
+
+/// Update every [`GlobalOpacity`] from the [`Opacity`] of each entity and its ancestors.
+/// Entities that no longer have an opacity anywhere above them go back to fully opaque.
+pub(crate) fn process_opacity(ctx: &mut SystemContext) {
+    let world = ctx.world();
+
+    world
+        .query::<&mut GlobalOpacity>()
+        .iter()
+        .for_each(|(_, global)| global.0 = 1.);
+
+    // Topmost entities with an opacity, which the rest are multiplied from
+    let mut stack = world
+        .query::<&Opacity>()
+        .iter()
+        .filter(|(entity, _)| {
+            let mut ancestor = world.get::<&Parent>(*entity).ok().map(|parent| parent.0);
+            while let Some(current) = ancestor {
+                if world.satisfies::<&Opacity>(current).unwrap_or(false) {
+                    return false;
+                }
+                ancestor = world.get::<&Parent>(current).ok().map(|parent| parent.0);
+            }
+            true
+        })
+        .map(|(entity, _)| (entity, 1.))
+        .collect::<Vec<_>>();
+
+    let mut missing = Vec::new();
+
+    while let Some((entity, parent_opacity)) = stack.pop() {
+        let Ok(mut query) = world.query_one::<(
+            Option<&Opacity>,
+            Option<&mut GlobalOpacity>,
+            Option<&Children>,
+        )>(entity) else {
+            continue;
+        };
+        let Some((opacity, global, children)) = query.get() else {
+            continue;
+        };
+
+        let value = parent_opacity * opacity.map(|opacity| opacity.0).unwrap_or(1.);
+
+        match global {
+            Some(global) => global.0 = value,
+            None => missing.push((entity, value)),
+        }
+
+        if let Some(children) = children {
+            stack.extend(children.0.iter().map(|child| (*child, value)));
+        }
     }
+
+    missing.into_iter().for_each(|(entity, value)| {
+        ctx.commands().insert_one(entity, GlobalOpacity(value));
+    });
 }
 
 //====================================================================
@@ -0,0 +1,44 @@
+//====================================================================
+
+use hecs::{Bundle, DynamicBundle, Entity};
+
+use crate::State;
+
+//====================================================================
+
+impl State {
+    /// Create the archetype for entities with exactly the components in `B` and make room for
+    /// `additional` more of them, so a wave of spawns later doesn't reallocate mid frame.
+    #[inline]
+    pub fn reserve_archetype<B: Bundle + 'static>(&mut self, additional: u32) {
+        self.world.reserve::<B>(additional);
+    }
+
+    /// Spawn many entities with the same components at once, reserving space for all of them
+    /// up front.
+    pub fn spawn_batch<B, I>(&mut self, bundles: I) -> Vec<Entity>
+    where
+        B: Bundle + Send + Sync + 'static,
+        I: IntoIterator<Item = B>,
+    {
+        let bundles = bundles.into_iter().collect::<Vec<_>>();
+        self.world.reserve::<B>(bundles.len() as u32);
+        self.world.spawn_batch(bundles).collect()
+    }
+
+    /// Add components to many entities. Entities that don't exist are skipped.
+    /// Returns how many entities the components were added to.
+    pub fn insert_batch<B, I>(&mut self, components: I) -> usize
+    where
+        B: DynamicBundle,
+        I: IntoIterator<Item = (Entity, B)>,
+    {
+        components
+            .into_iter()
+            .map(|(entity, bundle)| self.world.insert(entity, bundle))
+            .filter(Result::is_ok)
+            .count()
+    }
+}
+
+//====================================================================
@@ -0,0 +1,176 @@
+//====================================================================
+
+use std::{
+    any::Any,
+    collections::{BTreeMap, VecDeque},
+    panic::AssertUnwindSafe,
+    sync::Arc,
+};
+
+use web_time::{Duration, Instant};
+
+use crate::{parallel::WorkerPool, State};
+
+//====================================================================
+
+pub type TaskId = u64;
+
+type TaskOutput = Box<dyn Any + Send>;
+type TaskResult = Result<TaskOutput, String>;
+type Job = Box<dyn FnOnce() -> TaskOutput + Send>;
+type Callback = Box<dyn FnOnce(&mut State, TaskOutput)>;
+
+/// Deferred CPU work run off the main thread, on the engine workers that also run parallel
+/// systems. Completion callbacks run on the main thread at
+/// the start of a frame, limited by a time budget so many finishing tasks can't spike a frame.
+/// On wasm there are no worker threads, so the work itself is time sliced on the main thread.
+pub struct TaskQueue {
+    next_id: TaskId,
+    budget: Duration,
+
+    callbacks: BTreeMap<TaskId, (&'static str, Callback)>,
+    finished: VecDeque<(TaskId, TaskResult)>,
+    completed: Vec<TaskId>,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    workers: Arc<WorkerPool>,
+    #[cfg(not(target_arch = "wasm32"))]
+    result_sender: std::sync::mpsc::Sender<(TaskId, TaskResult)>,
+    #[cfg(not(target_arch = "wasm32"))]
+    results: std::sync::mpsc::Receiver<(TaskId, TaskResult)>,
+    #[cfg(target_arch = "wasm32")]
+    queued: VecDeque<(TaskId, Job)>,
+}
+
+impl TaskQueue {
+    pub(crate) fn new(workers: Arc<WorkerPool>) -> Self {
+        #[cfg(target_arch = "wasm32")]
+        drop(workers);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let (result_sender, results) = std::sync::mpsc::channel();
+
+        Self {
+            next_id: 0,
+            budget: Duration::from_millis(4),
+            callbacks: BTreeMap::new(),
+            finished: VecDeque::new(),
+            completed: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            workers,
+            #[cfg(not(target_arch = "wasm32"))]
+            result_sender,
+            #[cfg(not(target_arch = "wasm32"))]
+            results,
+            #[cfg(target_arch = "wasm32")]
+            queued: VecDeque::new(),
+        }
+    }
+
+    /// Time spent on the main thread each frame running callbacks (and tasks on wasm).
+    #[inline]
+    pub fn set_budget(&mut self, budget: Duration) {
+        self.budget = budget;
+    }
+
+    /// Queue `work` to run in the background, then `on_complete` with its output on the main thread.
+    pub fn spawn<T: Send + 'static>(
+        &mut self,
+        name: &'static str,
+        work: impl FnOnce() -> T + Send + 'static,
+        on_complete: impl FnOnce(&mut State, T) + 'static,
+    ) -> TaskId {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let job: Job = Box::new(move || Box::new(work()));
+        let callback: Callback =
+            Box::new(move |state, output| on_complete(state, *output.downcast::<T>().unwrap()));
+
+        log::trace!("Spawning task {} '{}'", id, name);
+        self.callbacks.insert(id, (name, callback));
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let sender = self.result_sender.clone();
+            self.workers.spawn(Box::new(move || {
+                let _ = sender.send((id, run_job(job)));
+            }));
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        self.queued.push_back((id, job));
+
+        id
+    }
+
+    /// Number of tasks that haven't had their callback run yet.
+    #[inline]
+    pub fn pending(&self) -> usize {
+        self.callbacks.len()
+    }
+
+    #[inline]
+    pub fn is_pending(&self, id: TaskId) -> bool {
+        self.callbacks.contains_key(&id)
+    }
+
+    /// Tasks whose callbacks ran this frame.
+    #[inline]
+    pub fn completed(&self) -> &[TaskId] {
+        &self.completed
+    }
+
+    fn next_finished(&mut self) -> Option<(TaskId, TaskResult)> {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.finished.extend(self.results.try_iter());
+
+        #[cfg(target_arch = "wasm32")]
+        if self.finished.is_empty() {
+            if let Some((id, job)) = self.queued.pop_front() {
+                self.finished.push_back((id, run_job(job)));
+            }
+        }
+
+        self.finished.pop_front()
+    }
+}
+
+fn run_job(job: Job) -> TaskResult {
+    std::panic::catch_unwind(AssertUnwindSafe(job)).map_err(|e| {
+        e.downcast_ref::<&str>()
+            .map(|e| e.to_string())
+            .or_else(|| e.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Unknown panic".into())
+    })
+}
+
+//====================================================================
+
+pub(crate) fn process_tasks(state: &mut State) {
+    state.tasks.completed.clear();
+
+    let start = Instant::now();
+
+    while let Some((id, result)) = state.tasks.next_finished() {
+        let Some((name, callback)) = state.tasks.callbacks.remove(&id) else {
+            continue;
+        };
+
+        match result {
+            Ok(output) => {
+                log::trace!("Finished task {} '{}'", id, name);
+                callback(state, output);
+            }
+            Err(e) => log::error!("Task {} '{}' panicked: {}", id, name, e),
+        }
+
+        state.tasks.completed.push(id);
+
+        if start.elapsed() >= state.tasks.budget {
+            break;
+        }
+    }
+}
+
+//====================================================================
@@ -1,11 +1,12 @@
 //====================================================================
 
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     hash::{BuildHasherDefault, Hash},
 };
 
 use rustc_hash::FxHasher;
+use serde::{Deserialize, Serialize};
 use web_time::{Duration, Instant};
 
 //====================================================================
@@ -21,6 +22,16 @@ pub struct Time {
     last_frame: Instant,
     delta: Duration,
     delta_seconds: f32,
+
+    fixed_delta: Duration,
+    fixed_accumulator: Duration,
+    max_fixed_steps: u32,
+
+    frame_count: u64,
+    smoothed_fps: f32,
+    /// Recent frame times in seconds, oldest first.
+    frame_times: VecDeque<f32>,
+    history_len: usize,
 }
 
 impl Default for Time {
@@ -30,6 +41,14 @@ impl Default for Time {
             last_frame: Instant::now(),
             delta: Duration::ZERO,
             delta_seconds: 0.,
+            fixed_delta: Duration::from_secs_f64(1. / 60.),
+            fixed_accumulator: Duration::ZERO,
+            max_fixed_steps: 8,
+
+            frame_count: 0,
+            smoothed_fps: 0.,
+            frame_times: VecDeque::new(),
+            history_len: 120,
         }
     }
 }
@@ -50,6 +69,115 @@ impl Time {
     pub fn delta_seconds(&self) -> f32 {
         self.delta_seconds
     }
+
+    #[inline]
+    pub fn fixed_delta(&self) -> &Duration {
+        &self.fixed_delta
+    }
+
+    #[inline]
+    pub fn fixed_delta_seconds(&self) -> f32 {
+        self.fixed_delta.as_secs_f32()
+    }
+
+    /// How far between the last and next fixed update this frame is (0-1).
+    /// Use to interpolate anything moved in `App::fixed_update` when rendering.
+    #[inline]
+    pub fn interpolation(&self) -> f32 {
+        (self.fixed_accumulator.as_secs_f32() / self.fixed_delta.as_secs_f32()).clamp(0., 1.)
+    }
+
+    #[inline]
+    pub fn set_fixed_delta(&mut self, fixed_delta: Duration) {
+        self.fixed_delta = fixed_delta.max(Duration::from_micros(100));
+    }
+
+    /// Most fixed updates run in a single frame. Time past this is dropped
+    /// so a slow frame can't snowball into ever more fixed updates.
+    #[inline]
+    pub fn set_max_fixed_steps(&mut self, steps: u32) {
+        self.max_fixed_steps = steps.max(1);
+    }
+
+    /// Frames run since the app started.
+    #[inline]
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Frames per second, smoothed over the last few frames.
+    #[inline]
+    pub fn fps(&self) -> f32 {
+        self.smoothed_fps
+    }
+
+    /// Recent frame times in seconds, oldest first.
+    #[inline]
+    pub fn frame_times(&self) -> impl Iterator<Item = f32> + '_ {
+        self.frame_times.iter().copied()
+    }
+
+    /// Number of frame times kept for the frame time stats.
+    #[inline]
+    pub fn set_history_len(&mut self, len: usize) {
+        self.history_len = len.max(1);
+        while self.frame_times.len() > self.history_len {
+            self.frame_times.pop_front();
+        }
+    }
+
+    #[inline]
+    pub fn min_frame_time(&self) -> f32 {
+        self.frame_times().reduce(f32::min).unwrap_or(0.)
+    }
+
+    #[inline]
+    pub fn max_frame_time(&self) -> f32 {
+        self.frame_times().reduce(f32::max).unwrap_or(0.)
+    }
+
+    #[inline]
+    pub fn average_frame_time(&self) -> f32 {
+        match self.frame_times.is_empty() {
+            true => 0.,
+            false => self.frame_times().sum::<f32>() / self.frame_times.len() as f32,
+        }
+    }
+
+    /// Frame time that `percentile` (0-100) of recent frames were at or under, e.g. 99 for
+    /// the slowest frames without outliers.
+    pub fn frame_time_percentile(&self, percentile: f32) -> f32 {
+        if self.frame_times.is_empty() {
+            return 0.;
+        }
+
+        let mut sorted = self.frame_times().collect::<Vec<_>>();
+        sorted.sort_by(f32::total_cmp);
+
+        let index = (percentile.clamp(0., 100.) / 100. * (sorted.len() - 1) as f32).round();
+        sorted[index as usize]
+    }
+
+    fn record_frame(&mut self) {
+        const FPS_SMOOTHING: f32 = 0.1;
+
+        self.frame_count += 1;
+
+        if self.delta_seconds <= 0. {
+            return;
+        }
+
+        let fps = 1. / self.delta_seconds;
+        self.smoothed_fps = match self.smoothed_fps == 0. {
+            true => fps,
+            false => self.smoothed_fps + (fps - self.smoothed_fps) * FPS_SMOOTHING,
+        };
+
+        self.frame_times.push_back(self.delta_seconds);
+        if self.frame_times.len() > self.history_len {
+            self.frame_times.pop_front();
+        }
+    }
 }
 
 pub fn tick_time(time: &mut Time) {
@@ -57,6 +185,35 @@ pub fn tick_time(time: &mut Time) {
     time.delta_seconds = time.delta.as_secs_f32();
 
     time.last_frame = Instant::now();
+    time.record_frame();
+}
+
+/// Replace this frame's delta, e.g. with a recorded one while replaying.
+pub(crate) fn set_delta(time: &mut Time, delta: Duration) {
+    time.delta = delta;
+    time.delta_seconds = delta.as_secs_f32();
+}
+
+/// Number of fixed updates to run this frame.
+pub fn tick_fixed_time(time: &mut Time) -> u32 {
+    time.fixed_accumulator += time.delta;
+
+    let mut steps = 0;
+    while time.fixed_accumulator >= time.fixed_delta {
+        if steps == time.max_fixed_steps {
+            log::debug!(
+                "Fixed update falling behind - dropping {:?}",
+                time.fixed_accumulator
+            );
+            time.fixed_accumulator = Duration::ZERO;
+            break;
+        }
+
+        time.fixed_accumulator -= time.fixed_delta;
+        steps += 1;
+    }
+
+    steps
 }
 
 //====================================================================
@@ -68,6 +225,11 @@ pub struct Input<T> {
     pressed: HashSet<T, Hasher>,
     just_pressed: HashSet<T, Hasher>,
     released: HashSet<T, Hasher>,
+
+    pressed_at: HashMap<T, Instant, Hasher>,
+    last_tap: HashMap<T, Instant, Hasher>,
+    double_tapped: HashSet<T, Hasher>,
+    double_tap_window: Duration,
 }
 
 impl<T> Default for Input<T> {
@@ -76,6 +238,10 @@ impl<T> Default for Input<T> {
             pressed: HashSet::default(),
             just_pressed: HashSet::default(),
             released: HashSet::default(),
+            pressed_at: HashMap::default(),
+            last_tap: HashMap::default(),
+            double_tapped: HashSet::default(),
+            double_tap_window: Duration::from_millis(250),
         }
     }
 }
@@ -99,6 +265,51 @@ where
     pub fn released(&self, input: T) -> bool {
         self.released.contains(&input)
     }
+
+    #[inline]
+    pub fn all_pressed(&self, inputs: impl IntoIterator<Item = T>) -> bool {
+        inputs.into_iter().all(|input| self.pressed(input))
+    }
+
+    #[inline]
+    pub fn any_pressed(&self, inputs: impl IntoIterator<Item = T>) -> bool {
+        inputs.into_iter().any(|input| self.pressed(input))
+    }
+
+    /// True on the frame the last input of a chord is pressed, e.g. `[ControlLeft, KeyS]`.
+    pub fn just_pressed_chord(&self, inputs: impl IntoIterator<Item = T>) -> bool {
+        let mut any_just_pressed = false;
+
+        inputs.into_iter().all(|input| {
+            any_just_pressed |= self.just_pressed.contains(&input);
+            self.pressed(input)
+        }) && any_just_pressed
+    }
+
+    /// How long an input has been held down. Zero if it isn't pressed.
+    #[inline]
+    pub fn held_for(&self, input: T) -> Duration {
+        self.pressed_at
+            .get(&input)
+            .map(|pressed_at| pressed_at.elapsed())
+            .unwrap_or(Duration::ZERO)
+    }
+
+    /// True on the frame an input is pressed for the second time within the double tap window.
+    #[inline]
+    pub fn double_tapped(&self, input: T) -> bool {
+        self.double_tapped.contains(&input)
+    }
+
+    #[inline]
+    pub fn double_tap_window(&self) -> Duration {
+        self.double_tap_window
+    }
+
+    #[inline]
+    pub fn set_double_tap_window(&mut self, window: Duration) {
+        self.double_tap_window = window;
+    }
 }
 
 pub(crate) fn process_inputs<T>(input: &mut Input<T>, val: T, pressed: bool)
@@ -107,19 +318,42 @@ where
 {
     match pressed {
         true => {
-            input.pressed.insert(val);
+            // Ignore key repeats for hold and double tap tracking
+            if input.pressed.insert(val) {
+                let now = Instant::now();
+                input.pressed_at.insert(val, now);
+
+                match input.last_tap.get(&val) {
+                    Some(last) if now.duration_since(*last) <= input.double_tap_window => {
+                        input.double_tapped.insert(val);
+                        // Require a fresh pair of taps for the next double tap
+                        input.last_tap.remove(&val);
+                    }
+                    _ => {
+                        input.last_tap.insert(val, now);
+                    }
+                }
+            }
+
             input.just_pressed.insert(val);
         }
         false => {
             input.pressed.remove(&val);
+            input.pressed_at.remove(&val);
             input.released.insert(val);
         }
     }
 }
 
+/// Inputs currently held, so they can be released when the window loses focus.
+pub(crate) fn pressed_inputs<T: Copy>(input: &Input<T>) -> Vec<T> {
+    input.pressed.iter().copied().collect()
+}
+
 pub(crate) fn reset_input<T>(input: &mut Input<T>) {
     input.just_pressed.clear();
     input.released.clear();
+    input.double_tapped.clear();
 }
 
 //--------------------------------------------------
@@ -130,6 +364,7 @@ pub struct MouseInput {
     screen_position: glam::Vec2,
     motion_delta: glam::Vec2,
     scroll: glam::Vec2,
+    mouse_look: bool,
 }
 
 impl MouseInput {
@@ -152,13 +387,32 @@ impl MouseInput {
     pub fn scroll(&self) -> glam::Vec2 {
         self.scroll
     }
+
+    /// While in mouse look mode the cursor position is frozen and
+    /// only [`MouseInput::motion_delta`] should be used.
+    #[inline]
+    pub fn mouse_look(&self) -> bool {
+        self.mouse_look
+    }
 }
 
 #[inline]
 pub(crate) fn process_mouse_position(input: &mut MouseInput, position: (f64, f64)) {
+    // Position updates while locked are either bogus or the cursor being recentered
+    if input.mouse_look {
+        return;
+    }
+
     input.position = glam::vec2(position.0 as f32, position.1 as f32);
 }
 
+pub(crate) fn set_mouse_look(input: &mut MouseInput, enabled: bool) {
+    input.mouse_look = enabled;
+
+    // Drop any motion from before the switch so the camera doesn't jump
+    input.motion_delta = glam::Vec2::ZERO;
+}
+
 #[inline]
 pub(crate) fn process_mouse_motion(input: &mut MouseInput, delta: (f64, f64)) {
     input.motion_delta += glam::vec2(delta.0 as f32, delta.1 as f32);
@@ -175,3 +429,258 @@ pub(crate) fn reset_mouse_input(input: &mut MouseInput) {
 }
 
 //====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    Mode,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    /// 0 to 1.
+    LeftTrigger,
+    /// 0 to 1.
+    RightTrigger,
+}
+
+pub type GamepadId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GamepadEvent {
+    Connected(GamepadId),
+    Disconnected(GamepadId),
+    Button {
+        id: GamepadId,
+        button: GamepadButton,
+        pressed: bool,
+    },
+    /// Raw axis value, before any dead zone is applied.
+    Axis {
+        id: GamepadId,
+        axis: GamepadAxis,
+        value: f32,
+    },
+}
+
+/// Source of gamepad events, polled at the start of every frame.
+pub trait GamepadBackend {
+    fn poll(&mut self, events: &mut Vec<GamepadEvent>);
+}
+
+//--------------------------------------------------
+
+#[derive(Debug, Default)]
+pub struct Gamepad {
+    buttons: Input<GamepadButton>,
+    axes: BTreeMap<GamepadAxis, f32>,
+    stick_dead_zone: f32,
+    trigger_dead_zone: f32,
+}
+
+impl Gamepad {
+    #[inline]
+    pub fn buttons(&self) -> &Input<GamepadButton> {
+        &self.buttons
+    }
+
+    #[inline]
+    pub fn raw_axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or(0.)
+    }
+
+    /// Axis value with dead zones applied. Stick axes are better read through
+    /// [`Gamepad::left_stick`] and [`Gamepad::right_stick`], which use a radial dead zone.
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        let value = self.raw_axis(axis);
+
+        match axis {
+            GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger => {
+                apply_dead_zone(value, self.trigger_dead_zone)
+            }
+            _ => value.signum() * apply_dead_zone(value.abs(), self.stick_dead_zone),
+        }
+    }
+
+    #[inline]
+    pub fn left_stick(&self) -> glam::Vec2 {
+        self.stick(GamepadAxis::LeftStickX, GamepadAxis::LeftStickY)
+    }
+
+    #[inline]
+    pub fn right_stick(&self) -> glam::Vec2 {
+        self.stick(GamepadAxis::RightStickX, GamepadAxis::RightStickY)
+    }
+
+    fn stick(&self, x: GamepadAxis, y: GamepadAxis) -> glam::Vec2 {
+        let raw = glam::vec2(self.raw_axis(x), self.raw_axis(y));
+        let length = raw.length();
+
+        match length > 0. {
+            true => raw / length * apply_dead_zone(length.min(1.), self.stick_dead_zone),
+            false => glam::Vec2::ZERO,
+        }
+    }
+}
+
+/// Zero inside the dead zone, then rescaled so the output still covers 0 to 1.
+#[inline]
+fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    match value <= dead_zone {
+        true => 0.,
+        false => ((value - dead_zone) / (1. - dead_zone)).min(1.),
+    }
+}
+
+/// Every connected gamepad. Needs a [`GamepadBackend`] to receive any input.
+pub struct GamepadInput {
+    backend: Option<Box<dyn GamepadBackend>>,
+    gamepads: BTreeMap<GamepadId, Gamepad>,
+    events: Vec<GamepadEvent>,
+    stick_dead_zone: f32,
+    trigger_dead_zone: f32,
+}
+
+impl Default for GamepadInput {
+    fn default() -> Self {
+        Self {
+            backend: None,
+            gamepads: BTreeMap::new(),
+            events: Vec::new(),
+            stick_dead_zone: 0.15,
+            trigger_dead_zone: 0.05,
+        }
+    }
+}
+
+impl GamepadInput {
+    #[inline]
+    pub fn set_backend(&mut self, backend: impl GamepadBackend + 'static) {
+        self.backend = Some(Box::new(backend));
+    }
+
+    #[inline]
+    pub fn set_dead_zones(&mut self, stick: f32, trigger: f32) {
+        self.stick_dead_zone = stick.clamp(0., 0.99);
+        self.trigger_dead_zone = trigger.clamp(0., 0.99);
+
+        self.gamepads.values_mut().for_each(|gamepad| {
+            gamepad.stick_dead_zone = self.stick_dead_zone;
+            gamepad.trigger_dead_zone = self.trigger_dead_zone;
+        });
+    }
+
+    #[inline]
+    pub fn gamepad(&self, id: GamepadId) -> Option<&Gamepad> {
+        self.gamepads.get(&id)
+    }
+
+    #[inline]
+    pub fn gamepads(&self) -> impl Iterator<Item = (GamepadId, &Gamepad)> {
+        self.gamepads.iter().map(|(id, gamepad)| (*id, gamepad))
+    }
+
+    /// First connected gamepad, for single player games.
+    #[inline]
+    pub fn first(&self) -> Option<&Gamepad> {
+        self.gamepads.values().next()
+    }
+
+    /// Events received this frame, including connections and disconnections.
+    #[inline]
+    pub fn events(&self) -> &[GamepadEvent] {
+        &self.events
+    }
+
+    /// Pressed on any gamepad.
+    #[inline]
+    pub fn pressed(&self, button: GamepadButton) -> bool {
+        self.gamepads
+            .values()
+            .any(|gamepad| gamepad.buttons.pressed(button))
+    }
+
+    #[inline]
+    pub fn just_pressed(&self, button: GamepadButton) -> bool {
+        self.gamepads
+            .values()
+            .any(|gamepad| gamepad.buttons.just_pressed(button))
+    }
+
+    #[inline]
+    pub fn released(&self, button: GamepadButton) -> bool {
+        self.gamepads
+            .values()
+            .any(|gamepad| gamepad.buttons.released(button))
+    }
+
+    fn gamepad_mut(&mut self, id: GamepadId) -> &mut Gamepad {
+        self.gamepads.entry(id).or_insert_with(|| Gamepad {
+            stick_dead_zone: self.stick_dead_zone,
+            trigger_dead_zone: self.trigger_dead_zone,
+            ..Default::default()
+        })
+    }
+}
+
+/// Events from the gamepad backend since the last frame.
+pub(crate) fn poll_gamepads(input: &mut GamepadInput) -> Vec<GamepadEvent> {
+    let mut events = Vec::new();
+
+    if let Some(backend) = &mut input.backend {
+        backend.poll(&mut events);
+    }
+
+    events
+}
+
+pub(crate) fn process_gamepads(input: &mut GamepadInput, events: Vec<GamepadEvent>) {
+    events.iter().for_each(|event| match *event {
+        GamepadEvent::Connected(id) => {
+            log::info!("Gamepad {} connected", id);
+            input.gamepad_mut(id);
+        }
+        GamepadEvent::Disconnected(id) => {
+            log::info!("Gamepad {} disconnected", id);
+            input.gamepads.remove(&id);
+        }
+        GamepadEvent::Button {
+            id,
+            button,
+            pressed,
+        } => process_inputs(&mut input.gamepad_mut(id).buttons, button, pressed),
+        GamepadEvent::Axis { id, axis, value } => {
+            input.gamepad_mut(id).axes.insert(axis, value);
+        }
+    });
+
+    input.events = events;
+}
+
+pub(crate) fn reset_gamepads(input: &mut GamepadInput) {
+    input
+        .gamepads
+        .values_mut()
+        .for_each(|gamepad| reset_input(&mut gamepad.buttons));
+}
+
+//====================================================================
@@ -0,0 +1,173 @@
+//====================================================================
+
+use std::collections::BTreeSet;
+
+use common::GlobalTransform;
+use hecs::Entity;
+
+use crate::collision::{self, Capsule, ColliderShape};
+
+//====================================================================
+
+/// Volume that reports [`TriggerEvent`]s when [`TriggerActivator`]s move in and out of it.
+/// Positioned by the entity's [`GlobalTransform`], ignoring rotation and scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TriggerVolume {
+    pub shape: ColliderShape,
+    pub offset: glam::Vec3,
+    pub enabled: bool,
+}
+
+impl TriggerVolume {
+    #[inline]
+    pub fn new(shape: ColliderShape) -> Self {
+        Self {
+            shape,
+            offset: glam::Vec3::ZERO,
+            enabled: true,
+        }
+    }
+
+    #[inline]
+    pub fn cuboid(half_extents: glam::Vec3) -> Self {
+        Self::new(ColliderShape::Cuboid { half_extents })
+    }
+
+    #[inline]
+    pub fn sphere(radius: f32) -> Self {
+        Self::new(ColliderShape::Sphere { radius })
+    }
+}
+
+/// Entity that sets off [`TriggerVolume`]s, treated as a sphere around its [`GlobalTransform`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TriggerActivator {
+    pub radius: f32,
+}
+
+//--------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerEventKind {
+    Enter,
+    Stay,
+    Exit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TriggerEvent {
+    pub trigger: Entity,
+    pub activator: Entity,
+    pub kind: TriggerEventKind,
+}
+
+/// Trigger events from the last frame.
+#[derive(Default)]
+pub struct TriggerEvents {
+    overlapping: BTreeSet<(Entity, Entity)>,
+    events: Vec<TriggerEvent>,
+}
+
+impl TriggerEvents {
+    #[inline]
+    pub fn events(&self) -> &[TriggerEvent] {
+        &self.events
+    }
+
+    /// Events of a single trigger.
+    #[inline]
+    pub fn trigger_events(&self, trigger: Entity) -> impl Iterator<Item = &TriggerEvent> {
+        self.events
+            .iter()
+            .filter(move |event| event.trigger == trigger)
+    }
+
+    /// Activators that entered `trigger` this frame.
+    #[inline]
+    pub fn entered(&self, trigger: Entity) -> impl Iterator<Item = Entity> + '_ {
+        self.of_kind(trigger, TriggerEventKind::Enter)
+    }
+
+    /// Activators that left `trigger` this frame.
+    #[inline]
+    pub fn exited(&self, trigger: Entity) -> impl Iterator<Item = Entity> + '_ {
+        self.of_kind(trigger, TriggerEventKind::Exit)
+    }
+
+    /// Whether `activator` is inside `trigger`.
+    #[inline]
+    pub fn is_inside(&self, trigger: Entity, activator: Entity) -> bool {
+        self.overlapping.contains(&(trigger, activator))
+    }
+
+    fn of_kind(
+        &self,
+        trigger: Entity,
+        kind: TriggerEventKind,
+    ) -> impl Iterator<Item = Entity> + '_ {
+        self.trigger_events(trigger)
+            .filter(move |event| event.kind == kind)
+            .map(|event| event.activator)
+    }
+}
+
+//====================================================================
+
+pub(crate) fn process_triggers(state: &mut crate::State) {
+    let world = &state.world;
+
+    let activators = world
+        .query::<(&TriggerActivator, &GlobalTransform)>()
+        .iter()
+        .map(|(entity, (activator, transform))| {
+            (
+                entity,
+                Capsule::sphere(transform.translation(), activator.radius),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let overlapping = world
+        .query::<(&TriggerVolume, &GlobalTransform)>()
+        .iter()
+        .filter(|(_, (trigger, _))| trigger.enabled)
+        .flat_map(|(trigger_entity, (trigger, transform))| {
+            let center = transform.translation() + trigger.offset;
+
+            activators
+                .iter()
+                .filter(move |(entity, _)| *entity != trigger_entity)
+                .filter(move |(_, sphere)| {
+                    collision::capsule_penetration(*sphere, trigger.shape, center).is_some()
+                })
+                .map(move |(entity, _)| (trigger_entity, *entity))
+        })
+        .collect::<BTreeSet<_>>();
+
+    let triggers = &mut state.triggers;
+    triggers.events.clear();
+
+    let event = |(trigger, activator): &(Entity, Entity), kind| TriggerEvent {
+        trigger: *trigger,
+        activator: *activator,
+        kind,
+    };
+
+    triggers.events.extend(
+        triggers
+            .overlapping
+            .difference(&overlapping)
+            .map(|pair| event(pair, TriggerEventKind::Exit)),
+    );
+
+    triggers.events.extend(overlapping.iter().map(
+        |pair| match triggers.overlapping.contains(pair) {
+            true => event(pair, TriggerEventKind::Stay),
+            false => event(pair, TriggerEventKind::Enter),
+        },
+    ));
+
+    triggers.overlapping = overlapping;
+}
+
+//====================================================================
@@ -0,0 +1,324 @@
+//====================================================================
+
+use common::Transform;
+use hecs::{BuiltEntityClone, Component, Entity, EntityBuilderClone, World};
+
+//====================================================================
+
+pub trait Command: 'static {
+    fn apply(&mut self, world: &mut World);
+    fn undo(&mut self, world: &mut World);
+}
+
+//====================================================================
+
+#[derive(Default)]
+pub struct UndoStack {
+    undo: Vec<Box<dyn Command>>,
+    redo: Vec<Box<dyn Command>>,
+    limit: Option<usize>,
+}
+
+impl UndoStack {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            limit: Some(limit),
+            ..Default::default()
+        }
+    }
+
+    /// Apply a command to the world and record it. Clears any commands that could be redone.
+    pub fn execute(&mut self, world: &mut World, mut command: impl Command) {
+        command.apply(world);
+
+        self.redo.clear();
+        self.undo.push(Box::new(command));
+
+        if let Some(limit) = self.limit {
+            if self.undo.len() > limit {
+                let overflow = self.undo.len() - limit;
+                self.undo.drain(..overflow);
+            }
+        }
+    }
+
+    pub fn undo(&mut self, world: &mut World) -> bool {
+        match self.undo.pop() {
+            Some(mut command) => {
+                command.undo(world);
+                self.redo.push(command);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self, world: &mut World) -> bool {
+        match self.redo.pop() {
+            Some(mut command) => {
+                command.apply(world);
+                self.undo.push(command);
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[inline]
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    #[inline]
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+//====================================================================
+
+/// Multiple commands applied and undone as a single step.
+#[derive(Default)]
+pub struct CommandGroup(Vec<Box<dyn Command>>);
+
+impl CommandGroup {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn with(mut self, command: impl Command) -> Self {
+        self.0.push(Box::new(command));
+        self
+    }
+}
+
+impl Command for CommandGroup {
+    fn apply(&mut self, world: &mut World) {
+        self.0.iter_mut().for_each(|command| command.apply(world));
+    }
+
+    fn undo(&mut self, world: &mut World) {
+        self.0
+            .iter_mut()
+            .rev()
+            .for_each(|command| command.undo(world));
+    }
+}
+
+//--------------------------------------------------
+
+pub struct InsertComponent<T: Component> {
+    entity: Entity,
+    component: Option<T>,
+    previous: Option<T>,
+}
+
+impl<T: Component> InsertComponent<T> {
+    #[inline]
+    pub fn new(entity: Entity, component: T) -> Self {
+        Self {
+            entity,
+            component: Some(component),
+            previous: None,
+        }
+    }
+}
+
+impl<T: Component> Command for InsertComponent<T> {
+    fn apply(&mut self, world: &mut World) {
+        // Keep the component so the command can be redone if the entity comes back
+        if !world.contains(self.entity) {
+            log::warn!(
+                "Undo: Unable to insert component on missing entity {:?}",
+                self.entity
+            );
+            return;
+        }
+
+        let component = match self.component.take() {
+            Some(component) => component,
+            None => return,
+        };
+
+        self.previous = world.remove_one::<T>(self.entity).ok();
+        world.insert_one(self.entity, component).ok();
+    }
+
+    fn undo(&mut self, world: &mut World) {
+        if !world.contains(self.entity) {
+            log::warn!(
+                "Undo: Unable to remove component from missing entity {:?}",
+                self.entity
+            );
+            return;
+        }
+
+        self.component = world.remove_one::<T>(self.entity).ok();
+
+        if let Some(previous) = self.previous.take() {
+            world.insert_one(self.entity, previous).ok();
+        }
+    }
+}
+
+//--------------------------------------------------
+
+pub struct SetTransform {
+    entity: Entity,
+    transform: Transform,
+}
+
+impl SetTransform {
+    #[inline]
+    pub fn new(entity: Entity, transform: Transform) -> Self {
+        Self { entity, transform }
+    }
+
+    fn swap(&mut self, world: &mut World) {
+        match world.get::<&mut Transform>(self.entity) {
+            Ok(mut transform) => std::mem::swap(&mut *transform, &mut self.transform),
+            Err(_) => log::warn!("Undo: Entity {:?} has no Transform to set", self.entity),
+        }
+    }
+}
+
+impl Command for SetTransform {
+    #[inline]
+    fn apply(&mut self, world: &mut World) {
+        self.swap(world);
+    }
+
+    #[inline]
+    fn undo(&mut self, world: &mut World) {
+        self.swap(world);
+    }
+}
+
+//--------------------------------------------------
+
+pub struct SpawnEntity {
+    entity: Option<Entity>,
+    components: BuiltEntityClone,
+}
+
+impl SpawnEntity {
+    #[inline]
+    pub fn new(components: EntityBuilderClone) -> Self {
+        Self {
+            entity: None,
+            components: components.build(),
+        }
+    }
+
+    /// Entity spawned by this command. Only available once the command has been applied.
+    #[inline]
+    pub fn entity(&self) -> Option<Entity> {
+        self.entity
+    }
+}
+
+impl Command for SpawnEntity {
+    fn apply(&mut self, world: &mut World) {
+        // Reuse the same entity id on redo so later commands still point at the right entity
+        let entity = *self.entity.get_or_insert_with(|| world.reserve_entity());
+
+        if slot_taken(world, entity) {
+            log::warn!("Undo: Unable to respawn {:?}, its id is in use", entity);
+            return;
+        }
+
+        world.spawn_at(entity, &self.components);
+    }
+
+    fn undo(&mut self, world: &mut World) {
+        if let Some(entity) = self.entity {
+            world.despawn(entity).ok();
+        }
+    }
+}
+
+//--------------------------------------------------
+
+// spawn_at despawns whatever currently holds the handle's id, which may be
+// an unrelated entity that reused the slot after the original was despawned.
+// World::find_entity_from_id is unsafe for dead ids, so scan instead.
+fn slot_taken(world: &World, entity: Entity) -> bool {
+    world
+        .iter()
+        .any(|entity_ref| entity_ref.entity().id() == entity.id())
+}
+
+type ComponentCloner = fn(&World, Entity, &mut EntityBuilderClone);
+
+/// Despawn an entity, restoring it on undo. Only components registered
+/// with [`DespawnEntity::track`] are restored.
+pub struct DespawnEntity {
+    entity: Entity,
+    tracked: Vec<ComponentCloner>,
+    snapshot: Option<BuiltEntityClone>,
+}
+
+impl DespawnEntity {
+    #[inline]
+    pub fn new(entity: Entity) -> Self {
+        Self {
+            entity,
+            tracked: Vec::new(),
+            snapshot: None,
+        }
+    }
+
+    pub fn track<T: Component + Clone>(mut self) -> Self {
+        self.tracked.push(|world, entity, builder| {
+            if let Ok(component) = world.get::<&T>(entity) {
+                builder.add((*component).clone());
+            }
+        });
+        self
+    }
+}
+
+impl Command for DespawnEntity {
+    fn apply(&mut self, world: &mut World) {
+        let mut builder = EntityBuilderClone::new();
+        self.tracked
+            .iter()
+            .for_each(|cloner| cloner(world, self.entity, &mut builder));
+
+        self.snapshot = Some(builder.build());
+
+        if world.despawn(self.entity).is_err() {
+            log::warn!("Undo: Unable to despawn missing entity {:?}", self.entity);
+        }
+    }
+
+    fn undo(&mut self, world: &mut World) {
+        if let Some(snapshot) = self.snapshot.take() {
+            if slot_taken(world, self.entity) {
+                log::warn!(
+                    "Undo: Unable to restore {:?}, its id is in use",
+                    self.entity
+                );
+                return;
+            }
+
+            world.spawn_at(self.entity, &snapshot);
+        }
+    }
+}
+
+//====================================================================
@@ -0,0 +1,746 @@
+//====================================================================
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+
+//====================================================================
+
+/// Somewhere asset files can be read from, e.g. a folder or an archive.
+pub trait VfsSource: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// `None` if the source doesn't contain `path`.
+    fn read(&self, path: &str) -> Option<std::io::Result<Vec<u8>>>;
+
+    fn contains(&self, path: &str) -> bool;
+}
+
+/// Asset paths use forward slashes and no leading `./`.
+pub fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    path.trim_start_matches("./").to_string()
+}
+
+//====================================================================
+
+/// Name of the manifest written at the root of a baked pack.
+pub const BAKE_MANIFEST: &str = "bake_manifest.json";
+
+/// Baked files in a pack. When a source containing a manifest is mounted, reading one of
+/// the original paths from it reads the baked file instead.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BakeManifest {
+    pub version: u32,
+    /// Original path to baked path.
+    pub files: BTreeMap<String, String>,
+}
+
+#[derive(Clone)]
+struct Mount {
+    priority: i32,
+    source: Arc<dyn VfsSource>,
+    redirects: BTreeMap<String, String>,
+}
+
+impl Mount {
+    #[inline]
+    fn resolve<'a>(&'a self, path: &'a str) -> &'a str {
+        self.redirects.get(path).map(String::as_str).unwrap_or(path)
+    }
+}
+
+/// Sources mounted over the regular filesystem. Paths are looked up in each source from
+/// highest to lowest priority before falling back to reading them from disk as is,
+/// or fetching them relative to the page on wasm.
+#[derive(Clone, Default)]
+pub struct Vfs {
+    mounts: Vec<Mount>,
+}
+
+impl Vfs {
+    /// Mount a source. Sources with the same priority are searched in the order they were mounted.
+    /// If the source has a [`BAKE_MANIFEST`] its baked files replace the originals.
+    pub fn mount(&mut self, priority: i32, source: Arc<dyn VfsSource>) {
+        log::debug!("Mounting '{}' with priority {}", source.name(), priority);
+
+        let redirects = match source.read(BAKE_MANIFEST) {
+            Some(Ok(bytes)) => match serde_json::from_slice::<BakeManifest>(&bytes) {
+                Ok(manifest) => manifest
+                    .files
+                    .into_iter()
+                    .map(|(from, to)| (normalize_path(&from), normalize_path(&to)))
+                    .collect(),
+                Err(e) => {
+                    log::warn!("Invalid bake manifest in '{}': {}", source.name(), e);
+                    BTreeMap::new()
+                }
+            },
+            Some(Err(e)) => {
+                log::warn!("Unable to read bake manifest in '{}': {}", source.name(), e);
+                BTreeMap::new()
+            }
+            None => BTreeMap::new(),
+        };
+
+        let index = self
+            .mounts
+            .iter()
+            .position(|mount| mount.priority < priority)
+            .unwrap_or(self.mounts.len());
+
+        self.mounts.insert(
+            index,
+            Mount {
+                priority,
+                source,
+                redirects,
+            },
+        );
+    }
+
+    pub fn unmount(&mut self, name: &str) {
+        self.mounts.retain(|mount| mount.source.name() != name);
+    }
+
+    #[inline]
+    pub fn mounts(&self) -> impl Iterator<Item = &dyn VfsSource> {
+        self.mounts.iter().map(|mount| mount.source.as_ref())
+    }
+
+    pub fn read(&self, path: &str) -> std::io::Result<Vec<u8>> {
+        let normalized = normalize_path(path);
+
+        self.mounts
+            .iter()
+            .find_map(|mount| mount.source.read(mount.resolve(&normalized)))
+            .unwrap_or_else(|| read_fallback(path))
+    }
+
+    #[inline]
+    pub fn read_to_string(&self, path: &str) -> std::io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    /// Name of the mounted source `path` would be read from, if not the fallback.
+    pub fn source_of(&self, path: &str) -> Option<&str> {
+        let normalized = normalize_path(path);
+
+        self.mounts
+            .iter()
+            .find(|mount| mount.source.contains(mount.resolve(&normalized)))
+            .map(|mount| mount.source.name())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[inline]
+fn read_fallback(path: &str) -> std::io::Result<Vec<u8>> {
+    std::fs::read(path)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[inline]
+fn read_fallback(path: &str) -> std::io::Result<Vec<u8>> {
+    http::fetch(path)
+}
+
+//====================================================================
+
+pub struct DirectorySource {
+    name: String,
+    root: PathBuf,
+}
+
+impl DirectorySource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        Self {
+            name: root.display().to_string(),
+            root,
+        }
+    }
+}
+
+impl VfsSource for DirectorySource {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn read(&self, path: &str) -> Option<std::io::Result<Vec<u8>>> {
+        let path = self.root.join(path);
+        path.is_file().then(|| std::fs::read(path))
+    }
+
+    #[inline]
+    fn contains(&self, path: &str) -> bool {
+        self.root.join(path).is_file()
+    }
+}
+
+//====================================================================
+
+struct ZipEntry {
+    compressed: bool,
+    compressed_size: u64,
+    size: u64,
+    header_offset: u64,
+}
+
+/// Zip archive read in place. Only the index is kept in memory, files are read on demand.
+/// Supports stored and deflated entries (no zip64 or encryption).
+pub struct ZipSource {
+    name: String,
+    path: PathBuf,
+    entries: BTreeMap<String, ZipEntry>,
+}
+
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+const CENTRAL_DIRECTORY_HEADER: u32 = 0x02014b50;
+const LOCAL_FILE_HEADER: u32 = 0x04034b50;
+/// Sizes and offsets with this value are stored in zip64 records instead.
+const ZIP64_MARKER: u32 = u32::MAX;
+
+impl ZipSource {
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+
+        // End of central directory is at least 22 bytes and ends with a comment of up to 64KiB
+        let length = file.seek(SeekFrom::End(0))?;
+        let tail_length = length.min(22 + u16::MAX as u64);
+        file.seek(SeekFrom::Start(length - tail_length))?;
+
+        let mut tail = vec![0; tail_length as usize];
+        file.read_exact(&mut tail)?;
+
+        let end = (0..tail.len().saturating_sub(21))
+            .rev()
+            .find(|index| read_u32(&tail, *index) == END_OF_CENTRAL_DIRECTORY)
+            .ok_or_else(|| invalid("Missing end of central directory"))?;
+
+        let count = read_u16(&tail, end + 10);
+        let directory_size = read_u32(&tail, end + 12);
+        let directory_offset = read_u32(&tail, end + 16);
+
+        if count == u16::MAX || directory_size == ZIP64_MARKER || directory_offset == ZIP64_MARKER {
+            return Err(invalid("Zip64 archives aren't supported"));
+        }
+
+        let (count, directory_size, directory_offset) = (
+            count as usize,
+            directory_size as u64,
+            directory_offset as u64,
+        );
+
+        // Sizes are checked against the file before allocating anything for them
+        if directory_offset
+            .checked_add(directory_size)
+            .is_none_or(|directory_end| directory_end > length)
+        {
+            return Err(invalid("Central directory out of range"));
+        }
+
+        let mut directory = vec![0; directory_size as usize];
+        file.seek(SeekFrom::Start(directory_offset))?;
+        file.read_exact(&mut directory)?;
+
+        let mut entries = BTreeMap::new();
+        let mut offset = 0;
+
+        for _ in 0..count {
+            if offset + 46 > directory.len()
+                || read_u32(&directory, offset) != CENTRAL_DIRECTORY_HEADER
+            {
+                return Err(invalid("Corrupt central directory"));
+            }
+
+            let method = read_u16(&directory, offset + 10);
+            let compressed_size = read_u32(&directory, offset + 20);
+            let size = read_u32(&directory, offset + 24);
+            let name_length = read_u16(&directory, offset + 28) as usize;
+            let extra_length = read_u16(&directory, offset + 30) as usize;
+            let comment_length = read_u16(&directory, offset + 32) as usize;
+            let header_offset = read_u32(&directory, offset + 42);
+
+            if [compressed_size, size, header_offset].contains(&ZIP64_MARKER) {
+                return Err(invalid("Zip64 archives aren't supported"));
+            }
+
+            let (compressed_size, size, header_offset) =
+                (compressed_size as u64, size as u64, header_offset as u64);
+
+            if header_offset
+                .checked_add(30 + compressed_size)
+                .is_none_or(|entry_end| entry_end > length)
+            {
+                return Err(invalid("Zip entry out of range"));
+            }
+
+            let name = directory
+                .get(offset + 46..offset + 46 + name_length)
+                .ok_or_else(|| invalid("Corrupt central directory"))?;
+            let name = normalize_path(&String::from_utf8_lossy(name));
+
+            offset += 46 + name_length + extra_length + comment_length;
+
+            if name.ends_with('/') {
+                continue;
+            }
+
+            if method != 0 && method != 8 {
+                log::warn!(
+                    "Skipping '{}' in {:?} - unsupported compression method {}",
+                    name,
+                    path,
+                    method
+                );
+                continue;
+            }
+
+            entries.insert(
+                name,
+                ZipEntry {
+                    compressed: method == 8,
+                    compressed_size,
+                    size,
+                    header_offset,
+                },
+            );
+        }
+
+        Ok(Self {
+            name: path.display().to_string(),
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    /// Paths of every file in the archive.
+    #[inline]
+    pub fn files(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|name| name.as_str())
+    }
+
+    fn read_entry(&self, entry: &ZipEntry) -> std::io::Result<Vec<u8>> {
+        let mut file = File::open(&self.path)?;
+
+        let mut header = [0; 30];
+        file.seek(SeekFrom::Start(entry.header_offset))?;
+        file.read_exact(&mut header)?;
+
+        if read_u32(&header, 0) != LOCAL_FILE_HEADER {
+            return Err(invalid("Corrupt local file header"));
+        }
+
+        let skip = read_u16(&header, 26) as u64 + read_u16(&header, 28) as u64;
+        let data_offset = file.seek(SeekFrom::Current(skip as i64))?;
+
+        if data_offset + entry.compressed_size > file.metadata()?.len() {
+            return Err(invalid("Zip entry out of range"));
+        }
+
+        let mut compressed = Vec::new();
+        (&mut file)
+            .take(entry.compressed_size)
+            .read_to_end(&mut compressed)?;
+
+        let data = match entry.compressed {
+            true => {
+                // Never inflate past the size the directory claims
+                let mut data = Vec::new();
+                flate2::read::DeflateDecoder::new(compressed.as_slice())
+                    .take(entry.size + 1)
+                    .read_to_end(&mut data)?;
+                data
+            }
+            false => compressed,
+        };
+
+        match data.len() as u64 == entry.size {
+            true => Ok(data),
+            false => Err(invalid("Zip entry size doesn't match its data")),
+        }
+    }
+}
+
+impl VfsSource for ZipSource {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn read(&self, path: &str) -> Option<std::io::Result<Vec<u8>>> {
+        self.entries.get(path).map(|entry| self.read_entry(entry))
+    }
+
+    #[inline]
+    fn contains(&self, path: &str) -> bool {
+        self.entries.contains_key(path)
+    }
+}
+
+//====================================================================
+
+/// Files compiled into the binary, usually created with [`crate::embed_assets`].
+pub struct EmbeddedSource {
+    name: String,
+    files: BTreeMap<String, &'static [u8]>,
+}
+
+impl EmbeddedSource {
+    pub fn new(name: impl Into<String>, files: &[(&str, &'static [u8])]) -> Self {
+        Self {
+            name: name.into(),
+            files: files
+                .iter()
+                .map(|(path, bytes)| (normalize_path(path), *bytes))
+                .collect(),
+        }
+    }
+}
+
+impl VfsSource for EmbeddedSource {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    fn read(&self, path: &str) -> Option<std::io::Result<Vec<u8>>> {
+        self.files.get(path).map(|bytes| Ok(bytes.to_vec()))
+    }
+
+    #[inline]
+    fn contains(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+}
+
+/// Embed files relative to the calling crate's root into an [`EmbeddedSource`].
+///
+/// ```ignore
+/// let source = engine::embed_assets!("core", ["res/player.png", "res/items.json"]);
+/// state.assets_mut().vfs_mut().mount(0, Arc::new(source));
+/// ```
+#[macro_export]
+macro_rules! embed_assets {
+    ($name:expr, [$($path:literal),* $(,)?]) => {
+        $crate::vfs::EmbeddedSource::new(
+            $name,
+            &[$((
+                $path,
+                include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $path)) as &'static [u8],
+            )),*],
+        )
+    };
+}
+
+//====================================================================
+
+const PAK_MAGIC: &[u8; 4] = b"HPAK";
+const PAK_VERSION: u32 = 1;
+const PAK_DEFLATE: u8 = 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PakCompression {
+    #[default]
+    None,
+    Deflate,
+}
+
+struct PakEntry {
+    offset: u64,
+    size: u64,
+    stored_size: u64,
+    deflated: bool,
+}
+
+/// Single packed data file written with [`write_pak`]. Like [`ZipSource`] only the index is kept
+/// in memory.
+///
+/// Layout is a header (`HPAK`, version, file count), then for each file its path, offset,
+/// size, stored size and flags, followed by the file data.
+pub struct PakSource {
+    name: String,
+    path: PathBuf,
+    entries: BTreeMap<String, PakEntry>,
+}
+
+impl PakSource {
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let file_length = file.metadata()?.len();
+        let mut file = std::io::BufReader::new(file);
+
+        let mut header = [0; 12];
+        file.read_exact(&mut header)?;
+
+        if &header[0..4] != PAK_MAGIC {
+            return Err(invalid("Not a pak file"));
+        }
+        if read_u32(&header, 4) != PAK_VERSION {
+            return Err(invalid("Unsupported pak version"));
+        }
+
+        let count = read_u32(&header, 8);
+        let mut entries = BTreeMap::new();
+
+        for _ in 0..count {
+            let mut length = [0; 2];
+            file.read_exact(&mut length)?;
+
+            let mut name = vec![0; u16::from_le_bytes(length) as usize];
+            file.read_exact(&mut name)?;
+
+            let mut entry = [0; 25];
+            file.read_exact(&mut entry)?;
+
+            let entry = PakEntry {
+                offset: read_u64(&entry, 0),
+                size: read_u64(&entry, 8),
+                stored_size: read_u64(&entry, 16),
+                deflated: entry[24] & PAK_DEFLATE != 0,
+            };
+
+            // Checked here so reading an entry never allocates more than the file holds
+            if entry
+                .offset
+                .checked_add(entry.stored_size)
+                .is_none_or(|end| end > file_length)
+            {
+                return Err(invalid("Pak entry out of range"));
+            }
+
+            entries.insert(normalize_path(&String::from_utf8_lossy(&name)), entry);
+        }
+
+        Ok(Self {
+            name: path.display().to_string(),
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    #[inline]
+    pub fn files(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|name| name.as_str())
+    }
+
+    fn read_entry(&self, entry: &PakEntry) -> std::io::Result<Vec<u8>> {
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(entry.offset))?;
+
+        let mut stored = Vec::new();
+        (&mut file)
+            .take(entry.stored_size)
+            .read_to_end(&mut stored)?;
+
+        let data = match entry.deflated {
+            true => {
+                // Never inflate past the size the index claims
+                let mut data = Vec::new();
+                flate2::read::DeflateDecoder::new(stored.as_slice())
+                    .take(entry.size.saturating_add(1))
+                    .read_to_end(&mut data)?;
+                data
+            }
+            false => stored,
+        };
+
+        match data.len() as u64 == entry.size {
+            true => Ok(data),
+            false => Err(invalid("Pak entry size doesn't match its data")),
+        }
+    }
+}
+
+impl VfsSource for PakSource {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn read(&self, path: &str) -> Option<std::io::Result<Vec<u8>>> {
+        self.entries.get(path).map(|entry| self.read_entry(entry))
+    }
+
+    #[inline]
+    fn contains(&self, path: &str) -> bool {
+        self.entries.contains_key(path)
+    }
+}
+
+/// Pack files (path and contents) into a single file readable with [`PakSource`].
+/// Compressed files that don't get any smaller are stored as is.
+pub fn write_pak(
+    path: impl AsRef<Path>,
+    files: impl IntoIterator<Item = (String, Vec<u8>)>,
+    compression: PakCompression,
+) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let files = files
+        .into_iter()
+        .map(|(name, data)| {
+            let stored = match compression {
+                PakCompression::None => None,
+                PakCompression::Deflate => {
+                    let mut encoder = flate2::write::DeflateEncoder::new(
+                        Vec::new(),
+                        flate2::Compression::default(),
+                    );
+                    encoder.write_all(&data)?;
+                    Some(encoder.finish()?).filter(|stored| stored.len() < data.len())
+                }
+            };
+
+            Ok((normalize_path(&name), data, stored))
+        })
+        .collect::<std::io::Result<Vec<_>>>()?;
+
+    let index_size = files
+        .iter()
+        .map(|(name, _, _)| 2 + name.len() + 25)
+        .sum::<usize>();
+    let mut offset = (12 + index_size) as u64;
+
+    let mut pak = Vec::new();
+    pak.extend_from_slice(PAK_MAGIC);
+    pak.extend_from_slice(&PAK_VERSION.to_le_bytes());
+    pak.extend_from_slice(&(files.len() as u32).to_le_bytes());
+
+    files.iter().for_each(|(name, data, stored)| {
+        let stored_size = stored.as_ref().map(Vec::len).unwrap_or(data.len()) as u64;
+
+        pak.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        pak.extend_from_slice(name.as_bytes());
+        pak.extend_from_slice(&offset.to_le_bytes());
+        pak.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        pak.extend_from_slice(&stored_size.to_le_bytes());
+        pak.push(match stored {
+            Some(_) => PAK_DEFLATE,
+            None => 0,
+        });
+
+        offset += stored_size;
+    });
+
+    files.iter().for_each(|(_, data, stored)| {
+        pak.extend_from_slice(stored.as_ref().unwrap_or(data));
+    });
+
+    std::fs::write(path, pak)
+}
+
+//====================================================================
+
+/// Files fetched over http relative to `base_url`. Requests are synchronous, so only use
+/// this from loading code that already runs off the frame, like the asset server.
+#[cfg(target_arch = "wasm32")]
+pub struct HttpSource {
+    base_url: String,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl HttpSource {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl VfsSource for HttpSource {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.base_url
+    }
+
+    fn read(&self, path: &str) -> Option<std::io::Result<Vec<u8>>> {
+        match http::fetch(&format!("{}/{}", self.base_url, path)) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            result => Some(result),
+        }
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        http::exists(&format!("{}/{}", self.base_url, path))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod http {
+    use std::io::{Error, ErrorKind};
+
+    fn request(method: &str, url: &str) -> std::io::Result<web_sys::XmlHttpRequest> {
+        let to_error = |e| Error::new(ErrorKind::Other, format!("{:?}", e));
+
+        let request = web_sys::XmlHttpRequest::new().map_err(to_error)?;
+        request
+            .open_with_async(method, url, false)
+            .map_err(to_error)?;
+        // Synchronous requests can't ask for binary responses, so read bytes as characters
+        request
+            .override_mime_type("text/plain; charset=x-user-defined")
+            .map_err(to_error)?;
+        request.send().map_err(to_error)?;
+
+        match request.status().map_err(to_error)? {
+            200..=299 => Ok(request),
+            404 => Err(Error::new(ErrorKind::NotFound, url.to_string())),
+            status => Err(Error::new(
+                ErrorKind::Other,
+                format!("Request for '{}' failed with status {}", url, status),
+            )),
+        }
+    }
+
+    pub(super) fn fetch(url: &str) -> std::io::Result<Vec<u8>> {
+        let text = request("GET", url)?
+            .response_text()
+            .map_err(|e| Error::new(ErrorKind::Other, format!("{:?}", e)))?
+            .unwrap_or_default();
+
+        Ok(text.chars().map(|c| c as u32 as u8).collect())
+    }
+
+    #[inline]
+    pub(super) fn exists(url: &str) -> bool {
+        request("HEAD", url).is_ok()
+    }
+}
+
+//====================================================================
+
+#[inline]
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+#[inline]
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[inline]
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+#[inline]
+fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+//====================================================================
@@ -0,0 +1,291 @@
+//====================================================================
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use common::{GlobalTransform, Transform};
+use hecs::Entity;
+
+use crate::{
+    audio::AudioBus,
+    physics2d::{BodyType2D, Collider2D, Collider2DShape, RigidBody2D},
+    State,
+};
+
+//====================================================================
+
+/// Ring spreading out from where something broke the surface, for water renderers to draw.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ripple {
+    pub position: glam::Vec3,
+    /// Seconds since the ripple started.
+    pub age: f32,
+    pub strength: f32,
+}
+
+/// Box of water around the entity's [`GlobalTransform`], with its surface at the top.
+/// Floats and slows [`RigidBody2D`]s inside it, which ignore the box's depth.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaterVolume {
+    pub half_extents: glam::Vec3,
+    /// Upwards push on a fully submerged body, as a multiple of gravity.
+    /// Bodies float above 1 and sink below it.
+    pub buoyancy: f32,
+    /// Velocity lost per second while fully submerged.
+    pub drag: f32,
+    /// Velocity of the water, e.g. for rivers.
+    pub flow: glam::Vec3,
+    /// Sound played through the sfx bus when a body enters.
+    pub splash_sound: Option<String>,
+    /// Seconds ripples last for.
+    pub ripple_lifetime: f32,
+
+    ripples: Vec<Ripple>,
+}
+
+impl WaterVolume {
+    pub fn new(half_extents: glam::Vec3) -> Self {
+        Self {
+            half_extents,
+            buoyancy: 1.5,
+            drag: 2.,
+            flow: glam::Vec3::ZERO,
+            splash_sound: None,
+            ripple_lifetime: 2.,
+            ripples: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn with_splash_sound(mut self, sound: impl Into<String>) -> Self {
+        self.splash_sound = Some(sound.into());
+        self
+    }
+
+    #[inline]
+    pub fn ripples(&self) -> &[Ripple] {
+        &self.ripples
+    }
+
+    /// Start a ripple on the surface above `position`.
+    pub fn add_ripple(&mut self, transform: &GlobalTransform, position: glam::Vec3, strength: f32) {
+        let surface = transform.translation().y + self.half_extents.y;
+
+        self.ripples.push(Ripple {
+            position: glam::vec3(position.x, surface, position.z),
+            age: 0.,
+            strength,
+        });
+    }
+}
+
+//--------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaterEventKind {
+    Enter,
+    Exit,
+}
+
+/// Body crossing the surface of a [`WaterVolume`], for splash particles and sounds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WaterEvent {
+    pub water: Entity,
+    pub body: Entity,
+    pub kind: WaterEventKind,
+    pub position: glam::Vec3,
+    /// Speed of the body as it crossed.
+    pub speed: f32,
+}
+
+/// Water events from the last frame.
+#[derive(Default)]
+pub struct WaterEvents {
+    inside: BTreeSet<(Entity, Entity)>,
+    events: Vec<WaterEvent>,
+}
+
+impl WaterEvents {
+    #[inline]
+    pub fn events(&self) -> &[WaterEvent] {
+        &self.events
+    }
+
+    /// Whether `body` is touching the water of `water`.
+    #[inline]
+    pub fn is_inside(&self, water: Entity, body: Entity) -> bool {
+        self.inside.contains(&(water, body))
+    }
+
+    pub(crate) fn clear_events(&mut self) {
+        self.events.clear();
+    }
+}
+
+//====================================================================
+
+/// Half height of the collider, treating bodies without one as points.
+fn half_height(collider: Option<&Collider2D>) -> f32 {
+    match collider.map(|collider| &collider.shape) {
+        Some(Collider2DShape::Aabb { half_extents }) => half_extents.y,
+        Some(Collider2DShape::Circle { radius }) => *radius,
+        Some(Collider2DShape::Convex { points }) => {
+            points.iter().map(|point| point.length()).fold(0., f32::max)
+        }
+        None => 0.,
+    }
+}
+
+pub(crate) fn process_water(state: &mut State, delta: f32) {
+    let gravity = state
+        .forces
+        .gravity
+        .map(|gravity| gravity.truncate())
+        .unwrap_or(state.physics2d().gravity);
+
+    let world = &mut state.world;
+
+    // Age ripples
+    world
+        .query_mut::<&mut WaterVolume>()
+        .into_iter()
+        .for_each(|(_, water)| {
+            water
+                .ripples
+                .iter_mut()
+                .for_each(|ripple| ripple.age += delta);
+            let lifetime = water.ripple_lifetime;
+            water.ripples.retain(|ripple| ripple.age < lifetime);
+        });
+
+    let volumes = world
+        .query::<(&WaterVolume, &GlobalTransform)>()
+        .iter()
+        .map(|(entity, (water, transform))| {
+            let center = transform.translation();
+            let bounds = (center - water.half_extents, center + water.half_extents);
+            (
+                entity,
+                bounds,
+                water.buoyancy,
+                water.drag,
+                water.flow.truncate(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    //--------------------------------------------------
+
+    let mut inside = BTreeMap::new();
+
+    world
+        .query_mut::<(&mut RigidBody2D, &Transform, Option<&Collider2D>)>()
+        .into_iter()
+        .filter(|(_, (body, _, _))| body.body_type == BodyType2D::Dynamic)
+        .for_each(|(entity, (body, transform, collider))| {
+            let position = transform.translation;
+            let half_height = half_height(collider).max(f32::EPSILON);
+
+            volumes
+                .iter()
+                .for_each(|(water, (min, max), buoyancy, drag, flow)| {
+                    let outside = position.x < min.x
+                        || position.x > max.x
+                        || position.y + half_height < min.y
+                        || position.y - half_height > max.y;
+
+                    if outside {
+                        return;
+                    }
+
+                    inside.insert((*water, entity), (position, max.y, body.velocity.length()));
+
+                    let submerged =
+                        ((max.y - (position.y - half_height)) / (half_height * 2.)).clamp(0., 1.);
+
+                    body.velocity -= gravity * *buoyancy * submerged * delta;
+                    body.velocity =
+                        *flow + (body.velocity - *flow) / (1. + drag.max(0.) * submerged * delta);
+                });
+        });
+
+    //--------------------------------------------------
+
+    let water_events = &mut state.water;
+
+    let exited = water_events
+        .inside
+        .iter()
+        .filter(|pair| !inside.contains_key(*pair))
+        .filter_map(|(water, body)| {
+            let position = world.get::<&Transform>(*body).ok()?.translation;
+            let speed = world
+                .get::<&RigidBody2D>(*body)
+                .map(|body| body.velocity.length())
+                .unwrap_or(0.);
+            let surface = world
+                .get::<&GlobalTransform>(*water)
+                .ok()
+                .zip(world.get::<&WaterVolume>(*water).ok())
+                .map(|(transform, volume)| transform.translation().y + volume.half_extents.y)
+                .unwrap_or(position.y);
+
+            Some((
+                *water,
+                *body,
+                WaterEventKind::Exit,
+                position,
+                surface,
+                speed,
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    let entered = inside
+        .iter()
+        .filter(|(pair, _)| !water_events.inside.contains(*pair))
+        .map(|((water, body), (position, surface, speed))| {
+            (
+                *water,
+                *body,
+                WaterEventKind::Enter,
+                *position,
+                *surface,
+                *speed,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    exited
+        .into_iter()
+        .chain(entered)
+        .for_each(|(water, body, kind, position, surface, speed)| {
+            water_events.events.push(WaterEvent {
+                water,
+                body,
+                kind,
+                position,
+                speed,
+            });
+
+            let Ok(mut volume) = world.get::<&mut WaterVolume>(water) else {
+                return;
+            };
+
+            volume.ripples.push(Ripple {
+                position: glam::vec3(position.x, surface, position.z),
+                age: 0.,
+                strength: speed,
+            });
+
+            if let (WaterEventKind::Enter, Some(sound)) = (kind, &volume.splash_sound) {
+                let volume = (speed / 10.).clamp(0.1, 1.);
+                state
+                    .audio
+                    .play_with(sound, AudioBus::Sfx, volume, 1., false);
+            }
+        });
+
+    water_events.inside = inside.into_keys().collect();
+}
+
+//====================================================================
@@ -1,28 +1,196 @@
 //====================================================================
 
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use common::Size;
-use winit::{event_loop::ActiveEventLoop, window::WindowAttributes};
+use winit::{
+    dpi::PhysicalSize,
+    event_loop::ActiveEventLoop,
+    monitor::MonitorHandle,
+    window::{Fullscreen, WindowAttributes},
+};
 
 //====================================================================
 
-pub struct Window(pub(crate) Arc<winit::window::Window>);
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    /// Borderless fullscreen on the current monitor.
+    Borderless,
+    /// Exclusive fullscreen using the current monitor's best video mode.
+    Fullscreen,
+}
+
+/// Window events besides input and resizing, collected each frame in [`WindowEvents`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowEvent {
+    Focused(bool),
+    /// Ratio of physical to logical pixels changed, e.g. moving to another monitor.
+    ScaleFactorChanged(f64),
+    FileDropped(PathBuf),
+    FileHovered(PathBuf),
+    FileHoverCancelled,
+    /// Text typed this frame, including composed (IME) text. For text boxes rather than
+    /// controls, as it isn't recorded in replays.
+    Text(String),
+    /// Gpu device was lost and recreated. Textures, models and other gpu resources need
+    /// creating again, while pipelines and cameras are rebuilt by the renderer.
+    DeviceRecovered,
+}
+
+/// Window events from the last frame. See [`crate::State::window_events`].
+#[derive(Debug, Default)]
+pub struct WindowEvents {
+    events: Vec<WindowEvent>,
+}
+
+impl WindowEvents {
+    #[inline]
+    pub fn events(&self) -> &[WindowEvent] {
+        &self.events
+    }
+
+    /// Latest focus change this frame, if any.
+    #[inline]
+    pub fn focus_changed(&self) -> Option<bool> {
+        self.events.iter().rev().find_map(|event| match event {
+            WindowEvent::Focused(focused) => Some(*focused),
+            _ => None,
+        })
+    }
+
+    #[inline]
+    pub fn dropped_files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.events.iter().filter_map(|event| match event {
+            WindowEvent::FileDropped(path) => Some(path),
+            _ => None,
+        })
+    }
+
+    /// All text typed this frame.
+    pub fn text(&self) -> String {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                WindowEvent::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[inline]
+    pub(crate) fn push(&mut self, event: WindowEvent) {
+        self.events.push(event);
+    }
+
+    #[inline]
+    pub(crate) fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+//--------------------------------------------------
+
+#[derive(Debug, Clone)]
+pub struct WindowConfig {
+    pub title: String,
+    /// Initial inner size. Uses the platform default if unset.
+    pub size: Option<Size<u32>>,
+    pub min_size: Option<Size<u32>>,
+    pub max_size: Option<Size<u32>>,
+    pub mode: WindowMode,
+    pub resizable: bool,
+    pub decorations: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: "Hecs Engine".into(),
+            size: None,
+            min_size: None,
+            max_size: None,
+            mode: WindowMode::Windowed,
+            resizable: true,
+            decorations: true,
+        }
+    }
+}
+
+impl WindowConfig {
+    fn attributes(&self, event_loop: &ActiveEventLoop) -> WindowAttributes {
+        let to_physical = |size: Size<u32>| PhysicalSize::new(size.width, size.height);
+
+        let mut attributes = WindowAttributes::default()
+            .with_title(&self.title)
+            .with_resizable(self.resizable)
+            .with_decorations(self.decorations);
+
+        if let Some(size) = self.size {
+            attributes = attributes.with_inner_size(to_physical(size));
+        }
+        if let Some(size) = self.min_size {
+            attributes = attributes.with_min_inner_size(to_physical(size));
+        }
+        if let Some(size) = self.max_size {
+            attributes = attributes.with_max_inner_size(to_physical(size));
+        }
+
+        attributes.with_fullscreen(fullscreen(event_loop.primary_monitor(), self.mode))
+    }
+}
+
+fn fullscreen(monitor: Option<MonitorHandle>, mode: WindowMode) -> Option<Fullscreen> {
+    match mode {
+        WindowMode::Windowed => None,
+        WindowMode::Borderless => Some(Fullscreen::Borderless(None)),
+        WindowMode::Fullscreen => {
+            let video_mode = monitor.and_then(|monitor| {
+                monitor.video_modes().max_by_key(|mode| {
+                    (
+                        mode.size().width * mode.size().height,
+                        mode.refresh_rate_millihertz(),
+                    )
+                })
+            });
+
+            match video_mode {
+                Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+                None => {
+                    log::warn!("No video modes available, falling back to borderless fullscreen");
+                    Some(Fullscreen::Borderless(None))
+                }
+            }
+        }
+    }
+}
+
+//====================================================================
+
+/// Window the app renders into. Headless windows have no winit window, see
+/// [`crate::Runner::run_headless`], and ignore cursor and mode changes.
+pub struct Window {
+    inner: Option<Arc<winit::window::Window>>,
+    headless_size: Size<u32>,
+}
+
 impl Window {
-    pub(super) fn new(event_loop: &ActiveEventLoop) -> Self {
+    pub(super) fn new(event_loop: &ActiveEventLoop, config: &WindowConfig) -> Self {
         log::info!("Creating new window");
 
         let window = event_loop
-            .create_window(WindowAttributes::default())
+            .create_window(config.attributes(event_loop))
             .unwrap();
 
         #[cfg(target_arch = "wasm32")]
         {
-            use winit::{dpi::PhysicalSize, platform::web::WindowExtWebSys};
+            use winit::platform::web::WindowExtWebSys;
 
             log::info!("Adding canvas to window");
 
-            if let None = window.request_inner_size(PhysicalSize::new(450, 400)) {
+            let size = config.size.unwrap_or(Size::new(450, 400));
+            if let None = window.request_inner_size(PhysicalSize::new(size.width, size.height)) {
                 log::warn!(
                     "Wasm Window Resize Warning: Got none when requesting window inner size"
                 );
@@ -39,12 +207,33 @@ impl Window {
                 .expect("Couldn't append canvas to document body.");
         }
 
-        Self(Arc::new(window))
+        Self {
+            inner: Some(Arc::new(window)),
+            headless_size: Size::new(0, 0),
+        }
+    }
+
+    pub(super) fn headless(size: Size<u32>) -> Self {
+        log::info!("Creating headless window");
+
+        Self {
+            inner: None,
+            headless_size: size,
+        }
+    }
+
+    #[inline]
+    pub fn is_headless(&self) -> bool {
+        self.inner.is_none()
     }
 
     #[inline]
     pub fn size(&self) -> Size<u32> {
-        let window_size = self.0.inner_size();
+        let Some(window) = &self.inner else {
+            return self.headless_size;
+        };
+
+        let window_size = window.inner_size();
 
         Size {
             width: window_size.width,
@@ -55,14 +244,18 @@ impl Window {
     #[cfg(not(target_arch = "wasm32"))]
     #[inline]
     pub fn confine_cursor(&self, confined: bool) {
+        let Some(window) = &self.inner else {
+            return;
+        };
+
         log::trace!("Confining window cursor: {}", confined);
 
-        self.0
-            .set_cursor_grab(match confined {
-                true => winit::window::CursorGrabMode::Confined,
-                false => winit::window::CursorGrabMode::None,
-            })
-            .unwrap();
+        if let Err(e) = window.set_cursor_grab(match confined {
+            true => winit::window::CursorGrabMode::Confined,
+            false => winit::window::CursorGrabMode::None,
+        }) {
+            log::warn!("Unable to confine window cursor: {}", e);
+        }
     }
 
     #[cfg(target_arch = "wasm32")]
@@ -73,13 +266,91 @@ impl Window {
 
     #[inline]
     pub fn hide_cursor(&self, hidden: bool) {
-        log::trace!("Hiding window cursor: {}", hidden);
-        self.0.set_cursor_visible(!hidden);
+        if let Some(window) = &self.inner {
+            log::trace!("Hiding window cursor: {}", hidden);
+            window.set_cursor_visible(!hidden);
+        }
+    }
+
+    /// Lock the cursor in place, falling back to confining it on platforms without locking.
+    /// Returns false if neither is supported.
+    pub(crate) fn grab_cursor(&self, grabbed: bool) -> bool {
+        use winit::window::CursorGrabMode;
+
+        let Some(window) = &self.inner else {
+            return false;
+        };
+
+        if !grabbed {
+            window.set_cursor_grab(CursorGrabMode::None).ok();
+            return true;
+        }
+
+        match window
+            .set_cursor_grab(CursorGrabMode::Locked)
+            .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
+        {
+            Ok(()) => true,
+            Err(e) => {
+                log::warn!("Unable to grab window cursor: {}", e);
+                false
+            }
+        }
+    }
+
+    pub(crate) fn set_cursor_position(&self, position: glam::Vec2) {
+        let Some(window) = &self.inner else {
+            return;
+        };
+
+        if let Err(e) =
+            window.set_cursor_position(winit::dpi::PhysicalPosition::new(position.x, position.y))
+        {
+            log::trace!("Unable to restore cursor position: {}", e);
+        }
+    }
+
+    /// Always 1 when headless.
+    #[inline]
+    pub fn scale_factor(&self) -> f64 {
+        self.inner
+            .as_ref()
+            .map_or(1., |window| window.scale_factor())
+    }
+
+    /// Always true when headless.
+    #[inline]
+    pub fn has_focus(&self) -> bool {
+        self.inner.as_ref().is_none_or(|window| window.has_focus())
+    }
+
+    /// Allow composing text with an input method editor. Call while a text box is focused.
+    #[inline]
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        if let Some(window) = &self.inner {
+            window.set_ime_allowed(allowed);
+        }
+    }
+
+    #[inline]
+    pub fn set_title(&self, title: &str) {
+        if let Some(window) = &self.inner {
+            window.set_title(title);
+        }
+    }
+
+    /// Switch between windowed and fullscreen modes at runtime.
+    pub fn set_mode(&self, mode: WindowMode) {
+        if let Some(window) = &self.inner {
+            log::trace!("Setting window mode: {:?}", mode);
+            window.set_fullscreen(fullscreen(window.current_monitor(), mode));
+        }
     }
 
+    /// The winit window, or `None` when headless.
     #[inline]
-    pub fn inner(&self) -> &winit::window::Window {
-        &self.0
+    pub fn inner(&self) -> Option<&Arc<winit::window::Window>> {
+        self.inner.as_ref()
     }
 }
 
@@ -0,0 +1,92 @@
+//====================================================================
+
+use common::GlobalTransform;
+use hecs::World;
+use renderer::{
+    camera::{self, CameraWgpu},
+    shared::SharedRenderResources,
+    Renderer, RendererCore,
+};
+
+//====================================================================
+
+type DrawFn = dyn FnMut(&mut wgpu::RenderPass, &wgpu::BindGroup, &GlobalTransform) + Send + Sync;
+
+/// One-off draw for an entity, called during [`CustomDrawRenderer`]'s render with the
+/// render pass, the active camera bind group and the entity's transform.
+/// Pipelines and buffers used by the closure must be created by the user.
+pub struct CustomDraw {
+    draw: Box<DrawFn>,
+}
+
+impl CustomDraw {
+    #[inline]
+    pub fn new(
+        draw: impl FnMut(&mut wgpu::RenderPass, &wgpu::BindGroup, &GlobalTransform)
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self {
+            draw: Box::new(draw),
+        }
+    }
+}
+
+//====================================================================
+
+/// Runs every [`CustomDraw`] component. Added like any other pipeline, so its
+/// [`renderer::ordering::PipelineOrder`] decides when custom draws happen relative to the
+/// other pipelines.
+pub struct CustomDrawRenderer;
+
+impl Renderer for CustomDrawRenderer {
+    fn new(_core: &RendererCore, _shared: &mut SharedRenderResources, _world: &mut World) -> Self
+    where
+        Self: Sized,
+    {
+        Self
+    }
+
+    fn prep(
+        &mut self,
+        _core: &RendererCore,
+        _shared: &mut SharedRenderResources,
+        _world: &mut World,
+    ) {
+    }
+
+    fn render(
+        &mut self,
+        render_pass: &mut wgpu::RenderPass,
+        shared: &mut SharedRenderResources,
+        world: &mut World,
+    ) {
+        let camera = shared
+            .active_camera()
+            .or_else(|| camera::find_camera(world))
+            .and_then(|entity| world.get::<&CameraWgpu>(entity).ok());
+
+        let camera = match camera {
+            Some(camera) => camera,
+            None => {
+                log::warn!("No camera available for custom draw renderer");
+                return;
+            }
+        };
+
+        let mut draws = 0;
+
+        world
+            .query::<(&mut CustomDraw, &GlobalTransform)>()
+            .iter()
+            .for_each(|(_, (custom, transform))| {
+                (custom.draw)(render_pass, camera.bind_group(), transform);
+                draws += 1;
+            });
+
+        shared.stats_mut().add_counter("custom_draws", draws);
+    }
+}
+
+//====================================================================
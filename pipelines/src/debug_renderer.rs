@@ -0,0 +1,101 @@
+//====================================================================
+
+use hecs::World;
+use renderer::{
+    camera,
+    debug::DebugLineVertex,
+    ordering::RenderGroup,
+    shared::{SharedRenderResources, Vertex},
+    tools, Renderer, RendererCore,
+};
+
+//====================================================================
+
+/// Draws the lines queued in [`renderer::debug::DebugLines`] this frame as a single line list.
+/// Lines are depth tested but don't write depth.
+pub struct DebugRenderer {
+    pipeline: wgpu::RenderPipeline,
+    vertices: tools::InstanceBuffer<DebugLineVertex>,
+}
+
+impl Renderer for DebugRenderer {
+    fn group() -> RenderGroup {
+        RenderGroup::Overlay
+    }
+
+    fn new(core: &RendererCore, shared: &mut SharedRenderResources, _world: &mut World) -> Self
+    where
+        Self: Sized,
+    {
+        let pipeline = tools::create_pipeline(
+            core.device(),
+            core.target_format(),
+            "Debug Line Pipeline",
+            &[shared.camera_bind_group_layout()],
+            &[DebugLineVertex::desc()],
+            &shared.preprocess_shader(&renderer::include_shader!("src/shaders/debug_lines.wgsl")),
+            tools::RenderPipelineDescriptor {
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::LineList,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: core.depth_format(),
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                fragment_targets: Some(&[Some(wgpu::ColorTargetState {
+                    format: core.target_format(),
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::all(),
+                })]),
+                ..Default::default()
+            },
+        );
+
+        Self {
+            pipeline,
+            vertices: tools::InstanceBuffer::new(core.device(), &[]),
+        }
+    }
+
+    fn prep(
+        &mut self,
+        core: &RendererCore,
+        shared: &mut SharedRenderResources,
+        _world: &mut World,
+    ) {
+        self.vertices
+            .update(core.device(), core.queue(), shared.debug_lines().vertices());
+
+        let lines = self.vertices.count() / 2;
+        shared.stats_mut().add_counter("debug_lines", lines as u64);
+    }
+
+    fn render(
+        &mut self,
+        pass: &mut wgpu::RenderPass,
+        shared: &mut SharedRenderResources,
+        world: &mut World,
+    ) {
+        if self.vertices.count() == 0 {
+            return;
+        }
+
+        let Some(camera) = camera::active_camera(world, shared) else {
+            log::warn!("No camera available for debug renderer");
+            return;
+        };
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, camera.bind_group(), &[]);
+        pass.set_vertex_buffer(0, self.vertices.buffer().slice(..));
+        pass.draw(0..self.vertices.count(), 0..1);
+
+        shared.stats_mut().add_counter("draw_calls", 1);
+    }
+}
+
+//====================================================================
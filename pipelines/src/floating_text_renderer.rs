@@ -0,0 +1,358 @@
+//====================================================================
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use common::{GlobalOpacity, GlobalTransform};
+use hecs::{Entity, World};
+use renderer::{
+    camera::{CameraWgpu, OrthographicCamera, PerspectiveCamera},
+    ordering::RenderGroup,
+    shared::Vertex,
+    text_shared::{Attrs, Color, Metrics, TextBuffer, TextBufferDescriptor, TextVertex, Wrap},
+    tools, Renderer,
+};
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy)]
+pub enum FloatingTextMotion {
+    Static,
+    /// Constant screen space velocity in pixels per second.
+    Linear(glam::Vec2),
+    /// Travel the given pixel distance over the text lifetime, slowing down towards the end.
+    EaseOut(glam::Vec2),
+}
+
+impl FloatingTextMotion {
+    fn offset(&self, age: f32, lifetime: f32) -> glam::Vec2 {
+        match self {
+            FloatingTextMotion::Static => glam::Vec2::ZERO,
+            FloatingTextMotion::Linear(velocity) => *velocity * age,
+            FloatingTextMotion::EaseOut(distance) => {
+                let t = (age / lifetime).clamp(0., 1.);
+                *distance * (1. - (1. - t) * (1. - t))
+            }
+        }
+    }
+}
+
+/// Screen space text drawn above the entity's GlobalTransform.
+/// Entities are despawned by [`sys_tick_floating_text`] once their lifetime runs out.
+#[derive(Debug, Clone)]
+pub struct FloatingText {
+    pub text: String,
+    pub font_size: f32,
+    pub color: [f32; 4],
+
+    /// Screen space offset in pixels from the projected anchor.
+    pub offset: glam::Vec2,
+    pub motion: FloatingTextMotion,
+
+    pub lifetime: f32,
+    /// Fraction of the lifetime after which the text starts fading out.
+    pub fade_start: f32,
+    pub age: f32,
+}
+
+impl Default for FloatingText {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            font_size: 24.,
+            color: [1., 1., 1., 1.],
+            offset: glam::Vec2::ZERO,
+            motion: FloatingTextMotion::EaseOut(glam::vec2(0., 60.)),
+            lifetime: 1.,
+            fade_start: 0.5,
+            age: 0.,
+        }
+    }
+}
+
+impl FloatingText {
+    #[inline]
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    fn alpha(&self) -> f32 {
+        let t = (self.age / self.lifetime).clamp(0., 1.);
+
+        match t > self.fade_start {
+            true => 1. - (t - self.fade_start) / (1. - self.fade_start).max(f32::EPSILON),
+            false => 1.,
+        }
+    }
+}
+
+/// Age all floating text and despawn any that have expired.
+pub fn sys_tick_floating_text(world: &mut World, delta_seconds: f32) {
+    let expired = world
+        .query_mut::<&mut FloatingText>()
+        .into_iter()
+        .filter_map(|(entity, text)| {
+            text.age += delta_seconds;
+            (text.age >= text.lifetime).then_some(entity)
+        })
+        .collect::<Vec<_>>();
+
+    expired.into_iter().for_each(|entity| {
+        world.despawn(entity).ok();
+    });
+}
+
+//====================================================================
+
+struct FloatingTextData {
+    position_uniform_buffer: wgpu::Buffer,
+    position_uniform_bind_group: wgpu::BindGroup,
+
+    text: String,
+    font_size: f32,
+    text_buffer: TextBuffer,
+}
+
+pub struct FloatingTextRenderer {
+    pipeline: wgpu::RenderPipeline,
+    position_bind_group_layout: wgpu::BindGroupLayout,
+
+    screen_camera: CameraWgpu,
+    instances: BTreeMap<Entity, FloatingTextData>,
+}
+
+impl Renderer for FloatingTextRenderer {
+    fn group() -> RenderGroup {
+        RenderGroup::Overlay
+    }
+
+    fn new(
+        core: &renderer::RendererCore,
+        shared: &mut renderer::shared::SharedRenderResources,
+        _world: &mut World,
+    ) -> Self {
+        let position_bind_group_layout =
+            core.device()
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("Floating Text Position Bind Group Layout"),
+                    entries: &[tools::bgl_uniform_entry(0, wgpu::ShaderStages::VERTEX)],
+                });
+
+        let pipeline = tools::create_pipeline(
+            core.device(),
+            core.target_format(),
+            "Floating Text Renderer",
+            &[
+                shared.camera_bind_group_layout(),
+                shared.text_resources().text_atlas.bind_group_layout(),
+                &position_bind_group_layout,
+            ],
+            &[TextVertex::desc()],
+            &shared.preprocess_shader(&renderer::include_shader!("src/shaders/text.wgsl")),
+            tools::RenderPipelineDescriptor {
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleStrip,
+                    ..Default::default()
+                },
+                fragment_targets: Some(&[Some(wgpu::ColorTargetState {
+                    format: core.target_format(),
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::all(),
+                })]),
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: core.depth_format(),
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                ..Default::default()
+            },
+        );
+
+        let screen_camera = shared.create_camera(core.device(), &screen_camera(core));
+
+        Self {
+            pipeline,
+            position_bind_group_layout,
+            screen_camera,
+            instances: BTreeMap::new(),
+        }
+    }
+
+    fn prep(
+        &mut self,
+        core: &renderer::RendererCore,
+        shared: &mut renderer::shared::SharedRenderResources,
+        world: &mut World,
+    ) {
+        self.screen_camera.update_camera(
+            core.queue(),
+            &screen_camera(core),
+            &glam::Affine3A::IDENTITY,
+        );
+
+        let view_projection = match world
+            .query_mut::<(&PerspectiveCamera, &CameraWgpu)>()
+            .into_iter()
+            .next()
+        {
+            Some((_, (_, camera))) => camera.matrices().view_projection,
+            None => return,
+        };
+
+        let half_size = glam::vec2(
+            core.config().width as f32 / 2.,
+            core.config().height as f32 / 2.,
+        );
+
+        let mut previous = self.instances.keys().copied().collect::<BTreeSet<_>>();
+
+        world
+            .query_mut::<(&FloatingText, &GlobalTransform, Option<&GlobalOpacity>)>()
+            .into_iter()
+            .for_each(|(entity, (floating, transform, opacity))| {
+                // Skip anything behind the camera
+                let clip = view_projection * transform.translation().extend(1.);
+                if clip.w <= 0. {
+                    return;
+                }
+
+                previous.remove(&entity);
+
+                let data = self.instances.entry(entity).or_insert_with(|| {
+                    let position_uniform_buffer = tools::buffer(
+                        core.device(),
+                        tools::BufferType::Uniform,
+                        "Floating Text Position",
+                        &[glam::Mat4::IDENTITY],
+                    );
+
+                    let position_uniform_bind_group =
+                        core.device().create_bind_group(&wgpu::BindGroupDescriptor {
+                            label: Some("Floating Text Position Bind Group"),
+                            layout: &self.position_bind_group_layout,
+                            entries: &[wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: position_uniform_buffer.as_entire_binding(),
+                            }],
+                        });
+
+                    let text_buffer = TextBuffer::new(
+                        core.device(),
+                        &mut shared.text_resources_mut().font_system,
+                        &TextBufferDescriptor {
+                            metrics: Metrics::new(floating.font_size, floating.font_size),
+                            word_wrap: Wrap::None,
+                            text: &floating.text,
+                            width: None,
+                            ..Default::default()
+                        },
+                    );
+
+                    FloatingTextData {
+                        position_uniform_buffer,
+                        position_uniform_bind_group,
+                        text: floating.text.clone(),
+                        font_size: floating.font_size,
+                        text_buffer,
+                    }
+                });
+
+                //--------------------------------------------------
+                // Update text
+
+                let font_system = &mut shared.text_resources_mut().font_system;
+
+                if data.text != floating.text {
+                    data.text_buffer
+                        .set_text(font_system, &floating.text, Attrs::new());
+                    data.text = floating.text.clone();
+                }
+
+                if data.font_size != floating.font_size {
+                    data.text_buffer.set_metrics(
+                        font_system,
+                        Metrics::new(floating.font_size, floating.font_size),
+                    );
+                    data.font_size = floating.font_size;
+                }
+
+                let [r, g, b, a] = GlobalOpacity::apply(opacity, floating.color);
+                data.text_buffer.set_color(Color::rgba(
+                    (r * 255.) as u8,
+                    (g * 255.) as u8,
+                    (b * 255.) as u8,
+                    (a * floating.alpha() * 255.) as u8,
+                ));
+
+                if let Some(rebuild) = renderer::text_shared::prep(
+                    core.device(),
+                    core.queue(),
+                    shared.text_resources_mut(),
+                    &mut data.text_buffer,
+                ) {
+                    tools::update_instance_buffer(
+                        core.device(),
+                        core.queue(),
+                        "Floating Text Vertex Buffer",
+                        &mut data.text_buffer.vertex_buffer,
+                        &mut data.text_buffer.vertex_count,
+                        &rebuild,
+                    );
+                }
+
+                //--------------------------------------------------
+                // Update position
+
+                let ndc = clip.truncate() / clip.w;
+                let screen_pos = ndc.truncate() * half_size
+                    + floating.offset
+                    + floating.motion.offset(floating.age, floating.lifetime)
+                    - glam::vec2(data.text_buffer.width() / 2., 0.);
+
+                core.queue().write_buffer(
+                    &data.position_uniform_buffer,
+                    0,
+                    bytemuck::cast_slice(&[glam::Mat4::from_translation(screen_pos.extend(0.))]),
+                );
+            });
+
+        previous.into_iter().for_each(|to_remove| {
+            self.instances.remove(&to_remove);
+        });
+    }
+
+    fn render(
+        &mut self,
+        pass: &mut wgpu::RenderPass,
+        shared: &mut renderer::shared::SharedRenderResources,
+        _world: &mut World,
+    ) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, self.screen_camera.bind_group(), &[]);
+        pass.set_bind_group(1, shared.text_resources().text_atlas.bind_group(), &[]);
+
+        self.instances.values().for_each(|instance| {
+            pass.set_vertex_buffer(0, instance.text_buffer.vertex_buffer.slice(..));
+            pass.set_bind_group(2, &instance.position_uniform_bind_group, &[]);
+            pass.draw(0..4, 0..instance.text_buffer.vertex_count);
+        });
+
+        let stats = shared.stats_mut();
+        stats.add_counter("draw_calls", self.instances.len() as u64);
+        stats.add_counter("instances", self.instances.len() as u64);
+    }
+}
+
+#[inline]
+fn screen_camera(core: &renderer::RendererCore) -> OrthographicCamera {
+    OrthographicCamera::new_centered(
+        core.config().width as f32 / 2.,
+        core.config().height as f32 / 2.,
+    )
+}
+
+//====================================================================
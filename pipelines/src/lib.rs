@@ -1,6 +1,22 @@
 //====================================================================
 
+pub mod custom_draw;
+pub mod debug_renderer;
+pub mod floating_text_renderer;
+pub mod lightmap;
+pub mod material;
+pub mod material_animation;
+pub mod model_loader;
 pub mod model_renderer;
+pub mod particle_renderer;
+pub mod polyline_renderer;
+pub mod post_effects;
+pub mod primitives;
+pub mod skeleton;
+pub mod skybox_renderer;
+pub mod sprite_animation;
+pub mod sprite_sheet;
+pub mod text3d_renderer;
 pub mod texture_renderer;
 pub mod ui3d_renderer;
 
@@ -0,0 +1,308 @@
+//====================================================================
+
+use std::sync::Arc;
+
+use common::GlobalTransform;
+use hecs::World;
+use renderer::{
+    lighting::{AmbientLight, DirectionalLight, PointLight},
+    shared::SharedRenderResources,
+    texture::{LoadedTexture, Texture},
+};
+
+use crate::model_loader::MeshData;
+
+//====================================================================
+
+/// Brightest light a lightmap can hold, as a multiple of the unlit texture color.
+/// Must match the model shader.
+pub const LIGHTMAP_RANGE: f32 = 2.;
+
+/// Lights and shadow casting geometry lightmaps are baked with.
+#[derive(Debug, Clone, Default)]
+pub struct BakeScene {
+    pub ambient: AmbientLight,
+    pub directional: Vec<DirectionalLight>,
+    /// Point lights with their world positions.
+    pub point: Vec<(glam::Vec3, PointLight)>,
+    occluders: Vec<[glam::Vec3; 3]>,
+}
+
+impl BakeScene {
+    /// Every directional and point light in the world. Occluders still need adding.
+    pub fn from_world(world: &World, ambient: AmbientLight) -> Self {
+        let directional = world
+            .query::<&DirectionalLight>()
+            .iter()
+            .map(|(_, light)| *light)
+            .collect();
+
+        let point = world
+            .query::<(&PointLight, &GlobalTransform)>()
+            .iter()
+            .map(|(_, (light, transform))| (transform.translation(), *light))
+            .collect();
+
+        Self {
+            ambient,
+            directional,
+            point,
+            occluders: Vec::new(),
+        }
+    }
+
+    /// Cast shadows from a mesh placed at `transform`.
+    pub fn add_occluder(&mut self, mesh: &MeshData, transform: glam::Mat4) {
+        self.occluders
+            .extend(mesh.indices.chunks_exact(3).map(|face| {
+                [face[0], face[1], face[2]]
+                    .map(|index| transform.transform_point3(mesh.vertices[index as usize].pos()))
+            }));
+    }
+
+    /// Diffuse light reaching a point, the same as the realtime lighting minus specular.
+    /// Occluders are ignored without a shadow bias.
+    fn irradiance(
+        &self,
+        position: glam::Vec3,
+        normal: glam::Vec3,
+        shadow_bias: Option<f32>,
+    ) -> glam::Vec3 {
+        // Scenes without any lights are left unlit, like the realtime shader
+        if self.directional.is_empty() && self.point.is_empty() {
+            return glam::Vec3::ONE;
+        }
+
+        let shadowed = |direction, distance| match shadow_bias {
+            Some(bias) => self.occluded(position + normal * bias, direction, distance),
+            None => false,
+        };
+
+        let mut sum = glam::Vec3::from(self.ambient.color) * self.ambient.intensity;
+
+        self.directional.iter().for_each(|light| {
+            let to_light = -light.direction.normalize_or(glam::Vec3::NEG_Y);
+            let diffuse = normal.dot(to_light);
+
+            if diffuse > 0. && !shadowed(to_light, f32::INFINITY) {
+                sum += glam::Vec3::from(light.color) * light.intensity * diffuse;
+            }
+        });
+
+        self.point.iter().for_each(|(light_position, light)| {
+            let to_light = *light_position - position;
+            let distance = to_light.length();
+            let direction = to_light / distance.max(0.0001);
+            let diffuse = normal.dot(direction);
+
+            if diffuse <= 0. || shadowed(direction, distance) {
+                return;
+            }
+
+            let range = light.range.max(0.0001);
+            let falloff = (1. - (distance / range).powi(4)).clamp(0., 1.);
+            let attenuation = falloff * falloff / (distance * distance + 1.);
+
+            sum += glam::Vec3::from(light.color) * light.intensity * diffuse * attenuation;
+        });
+
+        sum
+    }
+
+    /// Whether any occluder is hit by a ray before `max_distance`.
+    fn occluded(&self, origin: glam::Vec3, direction: glam::Vec3, max_distance: f32) -> bool {
+        self.occluders.iter().any(|[a, b, c]| {
+            // Moller-Trumbore
+            let edge_1 = *b - *a;
+            let edge_2 = *c - *a;
+            let p = direction.cross(edge_2);
+            let determinant = edge_1.dot(p);
+
+            if determinant.abs() < f32::EPSILON {
+                return false;
+            }
+
+            let inverse = 1. / determinant;
+            let t_vec = origin - *a;
+            let u = t_vec.dot(p) * inverse;
+            if !(0. ..=1.).contains(&u) {
+                return false;
+            }
+
+            let q = t_vec.cross(edge_1);
+            let v = direction.dot(q) * inverse;
+            if v < 0. || u + v > 1. {
+                return false;
+            }
+
+            let t = edge_2.dot(q) * inverse;
+            t > 0. && t < max_distance
+        })
+    }
+}
+
+//====================================================================
+
+/// Bakes the lighting of static meshes into textures on the cpu, using each vertex's
+/// lightmap uv. Slow, so bake offline and save the images, or on a background task.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightmapBaker {
+    /// Width and height of baked lightmaps.
+    pub size: u32,
+    pub shadows: bool,
+    /// Distance shadow rays start off the surface, to avoid shadowing itself.
+    pub shadow_bias: f32,
+    /// Texels the lighting is grown past each face's edges, hiding seams when sampled.
+    pub padding: u32,
+}
+
+impl Default for LightmapBaker {
+    fn default() -> Self {
+        Self {
+            size: 256,
+            shadows: true,
+            shadow_bias: 0.01,
+            padding: 2,
+        }
+    }
+}
+
+impl LightmapBaker {
+    /// Bake the lighting of `mesh` placed at `transform`.
+    pub fn bake(
+        &self,
+        mesh: &MeshData,
+        transform: glam::Mat4,
+        scene: &BakeScene,
+    ) -> image::RgbaImage {
+        let size = self.size.max(1);
+        let normal_matrix = glam::Mat3::from_mat4(transform).inverse().transpose();
+        let shadow_bias = self.shadows.then_some(self.shadow_bias);
+
+        let mut texels = vec![None; (size * size) as usize];
+
+        mesh.indices.chunks_exact(3).for_each(|face| {
+            let vertices = [face[0], face[1], face[2]].map(|index| &mesh.vertices[index as usize]);
+
+            let positions = vertices.map(|vertex| transform.transform_point3(vertex.pos()));
+            let normals = vertices.map(|vertex| normal_matrix * vertex.normal());
+            let uvs = vertices.map(|vertex| vertex.lightmap_uv() * size as f32);
+
+            let min = uvs[0].min(uvs[1]).min(uvs[2]).floor().max(glam::Vec2::ZERO);
+            let max = uvs[0]
+                .max(uvs[1])
+                .max(uvs[2])
+                .ceil()
+                .min(glam::Vec2::splat(size as f32));
+
+            let area = edge(uvs[0], uvs[1], uvs[2]);
+            if area.abs() <= f32::EPSILON {
+                return;
+            }
+
+            (min.y as u32..max.y as u32).for_each(|y| {
+                (min.x as u32..max.x as u32).for_each(|x| {
+                    let point = glam::vec2(x as f32 + 0.5, y as f32 + 0.5);
+                    let weights = glam::vec3(
+                        edge(uvs[1], uvs[2], point),
+                        edge(uvs[2], uvs[0], point),
+                        edge(uvs[0], uvs[1], point),
+                    ) / area;
+
+                    if weights.min_element() < 0. {
+                        return;
+                    }
+
+                    let position = positions[0] * weights.x
+                        + positions[1] * weights.y
+                        + positions[2] * weights.z;
+                    let normal =
+                        (normals[0] * weights.x + normals[1] * weights.y + normals[2] * weights.z)
+                            .normalize_or(glam::Vec3::Y);
+
+                    texels[(y * size + x) as usize] =
+                        Some(scene.irradiance(position, normal, shadow_bias));
+                });
+            });
+        });
+
+        (0..self.padding).for_each(|_| dilate(&mut texels, size));
+
+        let ambient = glam::Vec3::from(scene.ambient.color) * scene.ambient.intensity;
+
+        image::RgbaImage::from_fn(size, size, |x, y| {
+            let light = texels[(y * size + x) as usize].unwrap_or(ambient) / LIGHTMAP_RANGE;
+            let [r, g, b] = light
+                .to_array()
+                .map(|channel| (linear_to_srgb(channel.clamp(0., 1.)) * 255.).round() as u8);
+            image::Rgba([r, g, b, 255])
+        })
+    }
+}
+
+/// Upload a baked lightmap for [`crate::model_renderer::Model::lightmap`].
+pub fn load_lightmap(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    shared: &SharedRenderResources,
+    image: &image::RgbaImage,
+) -> Arc<LoadedTexture> {
+    let texture = Texture::from_image(
+        device,
+        queue,
+        &image::DynamicImage::ImageRgba8(image.clone()),
+        Some("Lightmap"),
+        Some(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        }),
+    );
+
+    Arc::new(LoadedTexture::load_texture(device, shared, texture))
+}
+
+//====================================================================
+
+#[inline]
+fn edge(a: glam::Vec2, b: glam::Vec2, point: glam::Vec2) -> f32 {
+    (b - a).perp_dot(point - a)
+}
+
+/// Fill empty texels bordering baked ones with the average of their baked neighbours.
+fn dilate(texels: &mut [Option<glam::Vec3>], size: u32) {
+    let source = texels.to_vec();
+    let size = size as i32;
+
+    (0..size).for_each(|y| {
+        (0..size).for_each(|x| {
+            let index = (y * size + x) as usize;
+            if source[index].is_some() {
+                return;
+            }
+
+            let (sum, count) = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                .into_iter()
+                .map(|(dx, dy)| (x + dx, y + dy))
+                .filter(|(x, y)| (0..size).contains(x) && (0..size).contains(y))
+                .filter_map(|(x, y)| source[(y * size + x) as usize])
+                .fold((glam::Vec3::ZERO, 0), |(sum, count), light| {
+                    (sum + light, count + 1)
+                });
+
+            if count > 0 {
+                texels[index] = Some(sum / count as f32);
+            }
+        });
+    });
+}
+
+#[inline]
+fn linear_to_srgb(value: f32) -> f32 {
+    match value <= 0.0031308 {
+        true => value * 12.92,
+        false => 1.055 * value.powf(1. / 2.4) - 0.055,
+    }
+}
+
+//====================================================================
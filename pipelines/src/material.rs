@@ -0,0 +1,182 @@
+//====================================================================
+
+use std::sync::{atomic::AtomicU32, Arc};
+
+use renderer::texture::LoadedTexture;
+
+//====================================================================
+
+pub type MaterialId = u32;
+
+static CURRENT_MATERIAL_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Surface of a model mesh, shared between meshes through an `Arc`. Each material gets its
+/// own bind group in the model renderer, created the first time it's drawn.
+///
+/// Materials can't be changed once created, clone one to make a changed copy.
+#[derive(Debug)]
+pub struct Material {
+    id: MaterialId,
+    base_color: [f32; 4],
+    metallic: f32,
+    roughness: f32,
+    emissive: [f32; 3],
+
+    base_color_texture: Arc<LoadedTexture>,
+    /// Tangent space normals, in a non srgb texture.
+    normal_map: Option<Arc<LoadedTexture>>,
+    /// Roughness in green and metallic in blue, in a non srgb texture.
+    metallic_roughness_map: Option<Arc<LoadedTexture>>,
+    emissive_map: Option<Arc<LoadedTexture>>,
+}
+
+impl Material {
+    pub fn new(base_color_texture: Arc<LoadedTexture>) -> Self {
+        Self {
+            id: CURRENT_MATERIAL_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+            base_color: [1., 1., 1., 1.],
+            metallic: 0.,
+            roughness: 0.5,
+            emissive: [0., 0., 0.],
+            base_color_texture,
+            normal_map: None,
+            metallic_roughness_map: None,
+            emissive_map: None,
+        }
+    }
+
+    #[inline]
+    pub fn with_base_color(mut self, base_color: [f32; 4]) -> Self {
+        self.base_color = base_color;
+        self
+    }
+
+    #[inline]
+    pub fn with_metallic(mut self, metallic: f32) -> Self {
+        self.metallic = metallic.clamp(0., 1.);
+        self
+    }
+
+    #[inline]
+    pub fn with_roughness(mut self, roughness: f32) -> Self {
+        self.roughness = roughness.clamp(0., 1.);
+        self
+    }
+
+    /// Light given off regardless of the lights around, multiplied by the emissive map.
+    #[inline]
+    pub fn with_emissive(mut self, emissive: [f32; 3]) -> Self {
+        self.emissive = emissive;
+        self
+    }
+
+    #[inline]
+    pub fn with_base_color_texture(mut self, texture: Arc<LoadedTexture>) -> Self {
+        self.base_color_texture = texture;
+        self
+    }
+
+    #[inline]
+    pub fn with_normal_map(mut self, texture: Arc<LoadedTexture>) -> Self {
+        self.normal_map = Some(texture);
+        self
+    }
+
+    #[inline]
+    pub fn with_metallic_roughness_map(mut self, texture: Arc<LoadedTexture>) -> Self {
+        self.metallic_roughness_map = Some(texture);
+        self
+    }
+
+    #[inline]
+    pub fn with_emissive_map(mut self, texture: Arc<LoadedTexture>) -> Self {
+        self.emissive_map = Some(texture);
+        self
+    }
+
+    #[inline]
+    pub fn id(&self) -> MaterialId {
+        self.id
+    }
+
+    #[inline]
+    pub fn base_color(&self) -> [f32; 4] {
+        self.base_color
+    }
+
+    #[inline]
+    pub fn metallic(&self) -> f32 {
+        self.metallic
+    }
+
+    #[inline]
+    pub fn roughness(&self) -> f32 {
+        self.roughness
+    }
+
+    #[inline]
+    pub fn emissive(&self) -> [f32; 3] {
+        self.emissive
+    }
+
+    #[inline]
+    pub fn base_color_texture(&self) -> &Arc<LoadedTexture> {
+        &self.base_color_texture
+    }
+
+    #[inline]
+    pub fn normal_map(&self) -> Option<&Arc<LoadedTexture>> {
+        self.normal_map.as_ref()
+    }
+
+    #[inline]
+    pub fn metallic_roughness_map(&self) -> Option<&Arc<LoadedTexture>> {
+        self.metallic_roughness_map.as_ref()
+    }
+
+    #[inline]
+    pub fn emissive_map(&self) -> Option<&Arc<LoadedTexture>> {
+        self.emissive_map.as_ref()
+    }
+
+    /// Base color texture followed by whichever maps are set.
+    pub fn textures(&self) -> impl Iterator<Item = &Arc<LoadedTexture>> {
+        std::iter::once(&self.base_color_texture)
+            .chain(&self.normal_map)
+            .chain(&self.metallic_roughness_map)
+            .chain(&self.emissive_map)
+    }
+}
+
+/// Clones get their own id, and with it their own bind group.
+impl Clone for Material {
+    fn clone(&self) -> Self {
+        Self {
+            id: CURRENT_MATERIAL_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+            base_color: self.base_color,
+            metallic: self.metallic,
+            roughness: self.roughness,
+            emissive: self.emissive,
+            base_color_texture: self.base_color_texture.clone(),
+            normal_map: self.normal_map.clone(),
+            metallic_roughness_map: self.metallic_roughness_map.clone(),
+            emissive_map: self.emissive_map.clone(),
+        }
+    }
+}
+
+impl From<Arc<LoadedTexture>> for Material {
+    #[inline]
+    fn from(value: Arc<LoadedTexture>) -> Self {
+        Self::new(value)
+    }
+}
+
+impl PartialEq for Material {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+//====================================================================
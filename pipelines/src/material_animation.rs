@@ -0,0 +1,43 @@
+//====================================================================
+
+// Animations evaluated in the model and texture shaders from the globals time, so they
+// don't need updating every frame. Add them next to a `Model`, `Sprite` or `AtlasSprite`.
+
+//====================================================================
+
+/// Scroll texture uvs by this much every second. Wraps around, so it needs a repeating
+/// sampler to tile.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UvScroll(pub glam::Vec2);
+
+/// Brighten and darken the color, scaling it by `1 + amplitude * sin(time)` with
+/// `frequency` pulses per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorPulse {
+    pub frequency: f32,
+    pub amplitude: f32,
+}
+
+impl Default for ColorPulse {
+    fn default() -> Self {
+        Self {
+            frequency: 1.,
+            amplitude: 0.5,
+        }
+    }
+}
+
+/// Instance data as scroll xy, pulse frequency and pulse amplitude.
+pub(crate) fn instance_animation(
+    scroll: Option<&UvScroll>,
+    pulse: Option<&ColorPulse>,
+) -> glam::Vec4 {
+    let scroll = scroll.map(|scroll| scroll.0).unwrap_or_default();
+    let (frequency, amplitude) = pulse
+        .map(|pulse| (pulse.frequency, pulse.amplitude))
+        .unwrap_or_default();
+
+    glam::vec4(scroll.x, scroll.y, frequency, amplitude)
+}
+
+//====================================================================
@@ -0,0 +1,1164 @@
+//====================================================================
+
+use std::{collections::BTreeMap, error::Error, fmt::Display, path::Path, sync::Arc};
+
+use common::Transform;
+
+use renderer::{
+    shared::{ModelVertex, SharedRenderResources},
+    texture::{LoadedTexture, Texture, TextureData},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    material::Material,
+    model_renderer::{Mesh, Model},
+    skeleton::{AnimationChannel, AnimationClip, Interpolation, Joint, Keyframes, Skeleton},
+};
+
+//====================================================================
+
+#[derive(Debug)]
+pub enum ModelLoadError {
+    Io(std::io::Error),
+    Obj(tobj::LoadError),
+    Gltf(gltf::Error),
+    UnsupportedFormat(String),
+    /// A mesh index points past the end of its vertices.
+    InvalidIndex {
+        index: u32,
+        vertices: usize,
+    },
+}
+
+impl Error for ModelLoadError {}
+
+impl Display for ModelLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelLoadError::Io(e) => write!(f, "Unable to read model file: {}", e),
+            ModelLoadError::Obj(e) => write!(f, "Unable to load obj file: {}", e),
+            ModelLoadError::Gltf(e) => write!(f, "Unable to load gltf file: {}", e),
+            ModelLoadError::UnsupportedFormat(format) => {
+                write!(f, "Unsupported model format '{}'", format)
+            }
+            ModelLoadError::InvalidIndex { index, vertices } => write!(
+                f,
+                "Mesh index {} is out of range of its {} vertices",
+                index, vertices
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for ModelLoadError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<tobj::LoadError> for ModelLoadError {
+    fn from(value: tobj::LoadError) -> Self {
+        Self::Obj(value)
+    }
+}
+
+impl From<gltf::Error> for ModelLoadError {
+    fn from(value: gltf::Error) -> Self {
+        Self::Gltf(value)
+    }
+}
+
+//====================================================================
+
+/// Reads a file for a loader, e.g. from disk or a virtual filesystem.
+pub type ReadFn<'a> = &'a dyn Fn(&Path) -> std::io::Result<Vec<u8>>;
+
+#[inline]
+fn read_fs(path: &Path) -> std::io::Result<Vec<u8>> {
+    std::fs::read(path)
+}
+
+//====================================================================
+
+#[derive(Default)]
+pub struct MeshData {
+    pub vertices: Vec<ModelVertex>,
+    pub indices: Vec<u32>,
+    /// Index into [`ModelData::materials`].
+    pub material: Option<usize>,
+    /// Per vertex tangents with the bitangent sign in w. Empty unless calculated with
+    /// [`MeshData::calculate_tangents`] or loaded from a baked model.
+    pub tangents: Vec<glam::Vec4>,
+    /// Min and max corners, if calculated with [`MeshData::calculate_bounds`] or baked.
+    pub bounds: Option<(glam::Vec3, glam::Vec3)>,
+}
+
+/// Factors and maps of a [`Material`], with maps as indices into [`ModelData::textures`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaterialData {
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: [f32; 3],
+    pub base_color_texture: Option<usize>,
+    pub normal_map: Option<usize>,
+    pub metallic_roughness_map: Option<usize>,
+    pub emissive_map: Option<usize>,
+}
+
+impl Default for MaterialData {
+    fn default() -> Self {
+        Self {
+            base_color: [1., 1., 1., 1.],
+            metallic: 0.,
+            roughness: 0.5,
+            emissive: [0., 0., 0.],
+            base_color_texture: None,
+            normal_map: None,
+            metallic_roughness_map: None,
+            emissive_map: None,
+        }
+    }
+}
+
+/// Model parsed from disk but not yet uploaded to the gpu. Parsing doesn't need the
+/// renderer, so it can be done on a background thread.
+pub struct ModelData {
+    pub meshes: Vec<MeshData>,
+    pub materials: Vec<MaterialData>,
+    pub textures: Vec<TextureData>,
+    /// Skeleton of the skinned meshes, from the first skin of a gltf file.
+    pub skeleton: Option<Skeleton>,
+    pub animations: Vec<AnimationClip>,
+}
+
+impl ModelData {
+    /// Load an obj, gltf or glb file based on its extension.
+    #[inline]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ModelLoadError> {
+        Self::load_with(path, &read_fs)
+    }
+
+    /// Like [`ModelData::load`], reading the model and any files it references with `read`.
+    /// Baked models are loaded whatever their extension.
+    pub fn load_with(path: impl AsRef<Path>, read: ReadFn) -> Result<Self, ModelLoadError> {
+        let path = path.as_ref();
+        let bytes = read(path)?;
+
+        if Self::is_baked(&bytes) {
+            return Ok(Self::from_baked(&bytes)?);
+        }
+
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        // Don't read the model file a second time
+        let read = |file: &Path| match file == path {
+            true => Ok(bytes.clone()),
+            false => read(file),
+        };
+
+        match extension.as_str() {
+            "obj" => Self::from_obj_with(path, &read),
+            "gltf" | "glb" => Self::from_gltf_with(path, &read),
+            _ => Err(ModelLoadError::UnsupportedFormat(extension)),
+        }
+    }
+
+    /// Load an obj file. Materials keep their diffuse color and texture, emissive color and
+    /// normal map.
+    #[inline]
+    pub fn from_obj(path: impl AsRef<Path>) -> Result<Self, ModelLoadError> {
+        Self::from_obj_with(path, &read_fs)
+    }
+
+    pub fn from_obj_with(path: impl AsRef<Path>, read: ReadFn) -> Result<Self, ModelLoadError> {
+        let path = path.as_ref();
+        let directory = path.parent().unwrap_or(Path::new(""));
+
+        let obj = read(path)?;
+        let (models, materials) =
+            tobj::load_obj_buf(&mut obj.as_slice(), &tobj::GPU_LOAD_OPTIONS, |mtl| {
+                let mtl = read(&directory.join(mtl)).map_err(|e| {
+                    log::warn!("Unable to read material file {:?}: {}", mtl, e);
+                    tobj::LoadError::OpenFileFailed
+                })?;
+                tobj::load_mtl_buf(&mut mtl.as_slice())
+            })?;
+
+        let materials = materials.unwrap_or_else(|e| {
+            log::warn!("Unable to load materials for {:?}: {}", path, e);
+            Vec::new()
+        });
+
+        // Textures that can't be loaded are left out of their material
+        let mut textures = Vec::new();
+        let mut load_texture = |file: &Option<String>| {
+            let texture_path = directory.join(file.as_ref()?);
+            let image = read(&texture_path)
+                .map_err(image::ImageError::IoError)
+                .and_then(|bytes| image::load_from_memory(&bytes));
+
+            match image {
+                Ok(image) => {
+                    textures.push(TextureData::from_image(&image, false));
+                    Some(textures.len() - 1)
+                }
+                Err(e) => {
+                    log::warn!("Unable to load texture {:?}: {}", texture_path, e);
+                    None
+                }
+            }
+        };
+
+        let materials = materials
+            .iter()
+            .map(|material| {
+                let [r, g, b] = material.diffuse.unwrap_or([1.; 3]);
+
+                MaterialData {
+                    base_color: [r, g, b, material.dissolve.unwrap_or(1.)],
+                    emissive: material.emissive.unwrap_or_default(),
+                    base_color_texture: load_texture(&material.diffuse_texture),
+                    normal_map: load_texture(&material.normal_texture),
+                    ..Default::default()
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let meshes = models
+            .into_iter()
+            .map(|model| -> Result<_, ModelLoadError> {
+                let mesh = model.mesh;
+
+                let vertices = mesh
+                    .positions
+                    .chunks_exact(3)
+                    .enumerate()
+                    .map(|(index, pos)| {
+                        let uv = mesh
+                            .texcoords
+                            .get(index * 2..index * 2 + 2)
+                            .map(|uv| glam::vec2(uv[0], 1. - uv[1]))
+                            .unwrap_or_default();
+
+                        let normal = mesh
+                            .normals
+                            .get(index * 3..index * 3 + 3)
+                            .map(glam::Vec3::from_slice)
+                            .unwrap_or_default();
+
+                        ModelVertex::new(glam::Vec3::from_slice(pos), uv, normal)
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut mesh_data = MeshData {
+                    vertices,
+                    indices: mesh.indices,
+                    material: mesh.material_id.filter(|id| *id < materials.len()),
+                    ..Default::default()
+                };
+                mesh_data.validate_indices()?;
+
+                if mesh.normals.is_empty() {
+                    mesh_data.calculate_normals();
+                }
+
+                Ok(mesh_data)
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            meshes,
+            materials,
+            textures,
+            skeleton: None,
+            animations: Vec::new(),
+        })
+    }
+
+    /// Load a gltf or glb file, flattening the node hierarchy of the default scene.
+    /// Materials keep their metallic roughness factors and maps, normal map and emissive.
+    /// Meshes skinned to the first skin
+    /// are kept in their bind pose for the skeleton to move, along with the animations of it.
+    #[inline]
+    pub fn from_gltf(path: impl AsRef<Path>) -> Result<Self, ModelLoadError> {
+        Self::from_gltf_with(path, &read_fs)
+    }
+
+    pub fn from_gltf_with(path: impl AsRef<Path>, read: ReadFn) -> Result<Self, ModelLoadError> {
+        let path = path.as_ref();
+        let directory = path.parent().unwrap_or(Path::new(""));
+
+        let gltf::Gltf { document, mut blob } = gltf::Gltf::from_slice(&read(path)?)?;
+
+        let buffers = document
+            .buffers()
+            .map(|buffer| gltf_buffer(buffer, directory, &mut blob, read))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let textures = document
+            .images()
+            .map(|image| gltf_texture(image, directory, &buffers, read))
+            .collect::<Vec<_>>();
+
+        let materials = document
+            .materials()
+            .map(|material| gltf_material(&material, &textures))
+            .collect();
+
+        let skin = gltf_skin(&document, &buffers);
+        let animations = match &skin {
+            Some(skin) => gltf_animations(&document, &buffers, skin),
+            None => Vec::new(),
+        };
+
+        let mut meshes = Vec::new();
+
+        let scene = document
+            .default_scene()
+            .or_else(|| document.scenes().next());
+
+        if let Some(scene) = scene {
+            scene.nodes().try_for_each(|node| {
+                gltf_node(
+                    &node,
+                    glam::Mat4::IDENTITY,
+                    &buffers,
+                    skin.as_ref(),
+                    &mut meshes,
+                )
+            })?;
+        }
+
+        // Images that couldn't be converted are replaced with a default texture
+        let textures = textures
+            .into_iter()
+            .map(|image| {
+                let image = image.unwrap_or_else(|| image::RgbaImage::new(1, 1).into());
+                TextureData::from_image(&image, false)
+            })
+            .collect();
+
+        Ok(Self {
+            meshes,
+            materials,
+            textures,
+            skeleton: skin.map(|skin| skin.skeleton),
+            animations,
+        })
+    }
+
+    /// Upload meshes, materials and textures to the gpu. Meshes without a material share a
+    /// default one.
+    pub fn build(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &SharedRenderResources,
+    ) -> Model {
+        let sampler = wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        };
+
+        // Maps holding data rather than colors aren't srgb
+        let linear = |index: usize| {
+            self.materials.iter().any(|material| {
+                material.normal_map == Some(index) || material.metallic_roughness_map == Some(index)
+            })
+        };
+
+        let textures = self
+            .textures
+            .iter()
+            .enumerate()
+            .map(|(index, data)| {
+                let format = match linear(index) {
+                    true => wgpu::TextureFormat::Rgba8Unorm,
+                    false => wgpu::TextureFormat::Rgba8UnormSrgb,
+                };
+                let texture = Texture::from_data_with_format(
+                    device,
+                    queue,
+                    data,
+                    format,
+                    Some("Model Texture"),
+                    Some(&sampler),
+                );
+
+                // Textures that weren't baked with mips get them generated on the gpu
+                Arc::new(LoadedTexture::load_texture_with_mipmaps(
+                    device,
+                    queue,
+                    shared,
+                    texture,
+                    Some(&sampler),
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        let default_texture = Arc::new(LoadedTexture::load_texture(
+            device,
+            shared,
+            Texture::from_color(device, queue, [255; 3], Some("Model Default Texture"), None),
+        ));
+
+        let texture = |index: Option<usize>| index.and_then(|index| textures.get(index)).cloned();
+
+        let materials = self
+            .materials
+            .iter()
+            .map(|data| {
+                let mut material = Material::new(
+                    texture(data.base_color_texture).unwrap_or_else(|| default_texture.clone()),
+                )
+                .with_base_color(data.base_color)
+                .with_metallic(data.metallic)
+                .with_roughness(data.roughness)
+                .with_emissive(data.emissive);
+
+                if let Some(map) = texture(data.normal_map) {
+                    material = material.with_normal_map(map);
+                }
+                if let Some(map) = texture(data.metallic_roughness_map) {
+                    material = material.with_metallic_roughness_map(map);
+                }
+                if let Some(map) = texture(data.emissive_map) {
+                    material = material.with_emissive_map(map);
+                }
+
+                Arc::new(material)
+            })
+            .collect::<Vec<_>>();
+
+        let default_material = Arc::new(Material::new(default_texture));
+
+        let meshes = self
+            .meshes
+            .iter()
+            .map(|mesh| {
+                let material = mesh
+                    .material
+                    .and_then(|index| materials.get(index))
+                    .unwrap_or(&default_material)
+                    .clone();
+
+                (
+                    Arc::new(Mesh::load_mesh(device, &mesh.vertices, &mesh.indices)),
+                    material,
+                )
+            })
+            .collect();
+
+        Model {
+            skeleton: self.skeleton.clone().map(Arc::new),
+            animations: self.animations.iter().cloned().map(Arc::new).collect(),
+            ..Model::new(meshes)
+        }
+    }
+}
+
+impl MeshData {
+    /// Error on the first index outside of the vertices. Normals and tangents are only
+    /// calculated for valid meshes.
+    pub fn validate_indices(&self) -> Result<(), ModelLoadError> {
+        match self
+            .indices
+            .iter()
+            .find(|index| **index as usize >= self.vertices.len())
+        {
+            Some(index) => Err(ModelLoadError::InvalidIndex {
+                index: *index,
+                vertices: self.vertices.len(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Smooth normals averaged from the faces sharing each vertex.
+    pub fn calculate_normals(&mut self) {
+        let mut normals = vec![glam::Vec3::ZERO; self.vertices.len()];
+
+        self.indices.chunks_exact(3).for_each(|face| {
+            let [a, b, c] = [face[0], face[1], face[2]].map(|index| index as usize);
+            let (pos_a, pos_b, pos_c) = (
+                self.vertices[a].pos(),
+                self.vertices[b].pos(),
+                self.vertices[c].pos(),
+            );
+
+            let normal = (pos_b - pos_a).cross(pos_c - pos_a);
+            normals[a] += normal;
+            normals[b] += normal;
+            normals[c] += normal;
+        });
+
+        self.vertices
+            .iter_mut()
+            .zip(normals)
+            .for_each(|(vertex, normal)| {
+                *vertex = ModelVertex::new(vertex.pos(), vertex.uv(), normal.normalize_or_zero())
+                    .with_lightmap_uv(vertex.lightmap_uv())
+                    .with_joints(vertex.joints(), vertex.weights())
+            });
+    }
+
+    /// Tangents from each face's uvs, averaged between the faces sharing each vertex and
+    /// made orthogonal to the vertex normal.
+    pub fn calculate_tangents(&mut self) {
+        let mut tangents = vec![glam::Vec3::ZERO; self.vertices.len()];
+        let mut bitangents = vec![glam::Vec3::ZERO; self.vertices.len()];
+
+        self.indices.chunks_exact(3).for_each(|face| {
+            let [a, b, c] = [face[0], face[1], face[2]].map(|index| index as usize);
+            let [a_vertex, b_vertex, c_vertex] = [a, b, c].map(|index| &self.vertices[index]);
+
+            let edge_1 = b_vertex.pos() - a_vertex.pos();
+            let edge_2 = c_vertex.pos() - a_vertex.pos();
+            let uv_1 = b_vertex.uv() - a_vertex.uv();
+            let uv_2 = c_vertex.uv() - a_vertex.uv();
+
+            let determinant = uv_1.x * uv_2.y - uv_2.x * uv_1.y;
+            if determinant.abs() <= f32::EPSILON {
+                return;
+            }
+
+            let tangent = (edge_1 * uv_2.y - edge_2 * uv_1.y) / determinant;
+            let bitangent = (edge_2 * uv_1.x - edge_1 * uv_2.x) / determinant;
+
+            [a, b, c].into_iter().for_each(|index| {
+                tangents[index] += tangent;
+                bitangents[index] += bitangent;
+            });
+        });
+
+        self.tangents = self
+            .vertices
+            .iter()
+            .zip(tangents.into_iter().zip(bitangents))
+            .map(|(vertex, (tangent, bitangent))| {
+                let normal = vertex.normal();
+                let tangent = (tangent - normal * normal.dot(tangent)).normalize_or_zero();
+                let sign = match normal.cross(tangent).dot(bitangent) < 0. {
+                    true => -1.,
+                    false => 1.,
+                };
+
+                tangent.extend(sign)
+            })
+            .collect();
+    }
+
+    pub fn calculate_bounds(&mut self) {
+        self.bounds = self
+            .vertices
+            .iter()
+            .map(|vertex| (vertex.pos(), vertex.pos()))
+            .reduce(|(min, max), (pos, _)| (min.min(pos), max.max(pos)));
+    }
+}
+
+//--------------------------------------------------
+
+const BAKED_MAGIC: &[u8; 4] = b"HMDL";
+const BAKED_VERSION: u32 = 4;
+
+impl ModelData {
+    /// Whether `bytes` were written by [`ModelData::to_baked`].
+    #[inline]
+    pub fn is_baked(bytes: &[u8]) -> bool {
+        bytes.starts_with(BAKED_MAGIC)
+    }
+
+    /// Header (magic, version, texture and mesh counts), then each texture as a baked
+    /// [`TextureData`], each mesh's material, vertices, indices, tangents and bounds, and
+    /// finally the materials, skeleton and animations as json.
+    pub fn to_baked(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let write_u32 =
+            |bytes: &mut Vec<u8>, value: u32| bytes.extend_from_slice(&value.to_le_bytes());
+
+        bytes.extend_from_slice(BAKED_MAGIC);
+        write_u32(&mut bytes, BAKED_VERSION);
+        write_u32(&mut bytes, self.textures.len() as u32);
+        write_u32(&mut bytes, self.meshes.len() as u32);
+
+        self.textures.iter().for_each(|texture| {
+            let texture = texture.to_baked();
+            write_u32(&mut bytes, texture.len() as u32);
+            bytes.extend_from_slice(&texture);
+        });
+
+        self.meshes.iter().for_each(|mesh| {
+            write_u32(
+                &mut bytes,
+                mesh.material.map(|index| index as u32).unwrap_or(u32::MAX),
+            );
+            write_u32(&mut bytes, mesh.vertices.len() as u32);
+            write_u32(&mut bytes, mesh.indices.len() as u32);
+            write_u32(&mut bytes, mesh.tangents.len() as u32);
+            write_u32(&mut bytes, mesh.bounds.is_some() as u32);
+
+            let (min, max) = mesh.bounds.unwrap_or_default();
+            bytes.extend_from_slice(bytemuck::cast_slice(&[min, max]));
+            bytes.extend_from_slice(bytemuck::cast_slice(&mesh.vertices));
+            bytes.extend_from_slice(bytemuck::cast_slice(&mesh.indices));
+            bytes.extend_from_slice(bytemuck::cast_slice(&mesh.tangents));
+        });
+
+        let json = serde_json::to_vec(&(&self.materials, &self.skeleton, &self.animations))
+            .expect("materials and skeletons serialize to json");
+        write_u32(&mut bytes, json.len() as u32);
+        bytes.extend_from_slice(&json);
+
+        bytes
+    }
+
+    pub fn from_baked(bytes: &[u8]) -> std::io::Result<Self> {
+        if !Self::is_baked(bytes) {
+            return Err(baked_error("Not a baked model"));
+        }
+
+        let mut reader = BakedReader { bytes, offset: 4 };
+
+        if reader.u32()? != BAKED_VERSION {
+            return Err(baked_error("Unsupported baked model version"));
+        }
+
+        let texture_count = reader.u32()?;
+        let mesh_count = reader.u32()?;
+
+        let textures = (0..texture_count)
+            .map(|_| {
+                let length = reader.u32()? as usize;
+                TextureData::from_baked(reader.take(length)?)
+            })
+            .collect::<std::io::Result<_>>()?;
+
+        let meshes = (0..mesh_count)
+            .map(|_| {
+                let material = reader.u32()?;
+                let [vertices, indices, tangents, has_bounds] =
+                    [reader.u32()?, reader.u32()?, reader.u32()?, reader.u32()?];
+
+                let bounds = reader.pod::<glam::Vec3>(2)?;
+
+                let mesh = MeshData {
+                    vertices: reader.pod(vertices as usize)?,
+                    indices: reader.pod(indices as usize)?,
+                    material: (material != u32::MAX).then_some(material as usize),
+                    tangents: reader.pod(tangents as usize)?,
+                    bounds: (has_bounds != 0).then(|| (bounds[0], bounds[1])),
+                };
+
+                mesh.validate_indices()
+                    .map_err(|_| baked_error("Baked mesh index out of range"))?;
+
+                Ok(mesh)
+            })
+            .collect::<std::io::Result<_>>()?;
+
+        let length = reader.u32()? as usize;
+        let (materials, skeleton, animations) = serde_json::from_slice(reader.take(length)?)
+            .map_err(|_| baked_error("Invalid baked materials or skeleton"))?;
+
+        Ok(Self {
+            meshes,
+            materials,
+            textures,
+            skeleton,
+            animations,
+        })
+    }
+}
+
+struct BakedReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> BakedReader<'a> {
+    fn take(&mut self, length: usize) -> std::io::Result<&'a [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.offset..self.offset + length)
+            .ok_or_else(|| baked_error("Baked model is truncated"))?;
+        self.offset += length;
+        Ok(bytes)
+    }
+
+    #[inline]
+    fn u32(&mut self) -> std::io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    #[inline]
+    fn pod<T: bytemuck::Pod>(&mut self, count: usize) -> std::io::Result<Vec<T>> {
+        let bytes = self.take(count * std::mem::size_of::<T>())?;
+        Ok(bytemuck::pod_collect_to_vec(bytes))
+    }
+}
+
+#[inline]
+fn baked_error(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+//--------------------------------------------------
+
+/// Skeleton of a gltf skin with the order its joints were sorted into.
+struct GltfSkin {
+    index: usize,
+    skeleton: Skeleton,
+    /// Sorted joint of each of the skin's joints.
+    remap: Vec<usize>,
+    /// Sorted joint of each joint node.
+    nodes: BTreeMap<usize, usize>,
+}
+
+fn gltf_skin(document: &gltf::Document, buffers: &[gltf::buffer::Data]) -> Option<GltfSkin> {
+    let skin = document.skins().next()?;
+    if document.skins().len() > 1 {
+        log::warn!("Only the first skin of a gltf file is used");
+    }
+
+    let mut parents = BTreeMap::new();
+    document.nodes().for_each(|node| {
+        node.children().for_each(|child| {
+            parents.insert(child.index(), node.index());
+        });
+    });
+
+    let joints = skin.joints().collect::<Vec<_>>();
+    let joint_nodes = joints
+        .iter()
+        .enumerate()
+        .map(|(joint, node)| (node.index(), joint))
+        .collect::<BTreeMap<_, _>>();
+
+    let parent_joint = |node: usize| {
+        parents
+            .get(&node)
+            .and_then(|parent| joint_nodes.get(parent).copied())
+    };
+
+    // Parents before children, by how many joints are above each
+    let depth = |mut joint: usize| {
+        let mut depth = 0;
+        while let Some(parent) = parent_joint(joints[joint].index()) {
+            joint = parent;
+            depth += 1;
+            if depth > joints.len() {
+                break;
+            }
+        }
+        depth
+    };
+    let mut order = (0..joints.len()).collect::<Vec<_>>();
+    order.sort_by_key(|joint| depth(*joint));
+
+    let mut remap = vec![0; joints.len()];
+    order
+        .iter()
+        .enumerate()
+        .for_each(|(sorted, joint)| remap[*joint] = sorted);
+
+    let inverse_binds = skin
+        .reader(|buffer| Some(&buffers[buffer.index()]))
+        .read_inverse_bind_matrices()
+        .map(|matrices| matrices.map(|matrix| glam::Mat4::from_cols_array_2d(&matrix)))
+        .map(|matrices| matrices.collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let skeleton_joints = order
+        .iter()
+        .map(|joint| {
+            let node = &joints[*joint];
+            let (translation, rotation, scale) = node.transform().decomposed();
+
+            Joint {
+                name: node
+                    .name()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("joint_{}", joint)),
+                parent: parent_joint(node.index()).map(|parent| remap[parent]),
+                rest: Transform::from_scale_rotation_translation(
+                    glam::Vec3::from_array(scale),
+                    glam::Quat::from_array(rotation),
+                    glam::Vec3::from_array(translation),
+                ),
+                inverse_bind: inverse_binds
+                    .get(*joint)
+                    .copied()
+                    .unwrap_or(glam::Mat4::IDENTITY),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Nodes above the root joints, which the skeleton is placed by
+    let mut root = glam::Mat4::IDENTITY;
+    let mut ancestor = order
+        .first()
+        .and_then(|joint| parents.get(&joints[*joint].index()).copied());
+    while let Some(node) = ancestor.and_then(|index| document.nodes().nth(index)) {
+        root = glam::Mat4::from_cols_array_2d(&node.transform().matrix()) * root;
+        ancestor = parents.get(&node.index()).copied();
+    }
+
+    let nodes = joint_nodes
+        .iter()
+        .map(|(node, joint)| (*node, remap[*joint]))
+        .collect();
+
+    Some(GltfSkin {
+        index: skin.index(),
+        skeleton: Skeleton {
+            joints: skeleton_joints,
+            root,
+        },
+        remap,
+        nodes,
+    })
+}
+
+fn gltf_animations(
+    document: &gltf::Document,
+    buffers: &[gltf::buffer::Data],
+    skin: &GltfSkin,
+) -> Vec<AnimationClip> {
+    document
+        .animations()
+        .map(|animation| {
+            let channels = animation
+                .channels()
+                .filter_map(|channel| {
+                    let joint = *skin.nodes.get(&channel.target().node().index())?;
+                    let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+
+                    let (interpolation, cubic) = match channel.sampler().interpolation() {
+                        gltf::animation::Interpolation::Step => (Interpolation::Step, false),
+                        gltf::animation::Interpolation::Linear => (Interpolation::Linear, false),
+                        // Tangents are dropped, keeping the values between them
+                        gltf::animation::Interpolation::CubicSpline => {
+                            (Interpolation::Linear, true)
+                        }
+                    };
+
+                    fn values<T>(values: impl Iterator<Item = T>, cubic: bool) -> Vec<T> {
+                        match cubic {
+                            true => values.skip(1).step_by(3).collect(),
+                            false => values.collect(),
+                        }
+                    }
+
+                    use gltf::animation::util::ReadOutputs;
+                    let keyframes = match reader.read_outputs()? {
+                        ReadOutputs::Translations(outputs) => Keyframes::Translation(values(
+                            outputs.map(glam::Vec3::from_array),
+                            cubic,
+                        )),
+                        ReadOutputs::Rotations(outputs) => Keyframes::Rotation(values(
+                            outputs.into_f32().map(glam::Quat::from_array),
+                            cubic,
+                        )),
+                        ReadOutputs::Scales(outputs) => {
+                            Keyframes::Scale(values(outputs.map(glam::Vec3::from_array), cubic))
+                        }
+                        ReadOutputs::MorphTargetWeights(_) => return None,
+                    };
+
+                    Some(AnimationChannel {
+                        joint,
+                        times: reader.read_inputs()?.collect(),
+                        keyframes,
+                        interpolation,
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let duration = channels
+                .iter()
+                .filter_map(|channel| channel.times.last().copied())
+                .fold(0., f32::max);
+
+            AnimationClip {
+                name: animation
+                    .name()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("animation_{}", animation.index())),
+                duration,
+                channels,
+            }
+        })
+        .collect()
+}
+
+fn gltf_node(
+    node: &gltf::Node,
+    parent: glam::Mat4,
+    buffers: &[gltf::buffer::Data],
+    skin: Option<&GltfSkin>,
+    meshes: &mut Vec<MeshData>,
+) -> Result<(), ModelLoadError> {
+    let transform = parent * glam::Mat4::from_cols_array_2d(&node.transform().matrix());
+
+    // Skinned meshes stay in their bind pose, ignoring the node they're on
+    let remap = match (node.skin(), skin) {
+        (Some(node_skin), Some(skin)) if node_skin.index() == skin.index => Some(&skin.remap),
+        (Some(_), _) => {
+            log::warn!("Loading mesh of an unused gltf skin without its skeleton");
+            None
+        }
+        _ => None,
+    };
+    let mesh_transform = match remap {
+        Some(_) => glam::Mat4::IDENTITY,
+        None => transform,
+    };
+    let normal_matrix = glam::Mat3::from_mat4(mesh_transform).inverse().transpose();
+
+    if let Some(mesh) = node.mesh() {
+        mesh.primitives()
+            .try_for_each(|primitive| -> Result<(), ModelLoadError> {
+                let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+                let Some(positions) = reader.read_positions() else {
+                    log::warn!("Skipping gltf primitive without positions");
+                    return Ok(());
+                };
+                let positions = positions.collect::<Vec<_>>();
+
+                let mut uvs = reader
+                    .read_tex_coords(0)
+                    .map(|uvs| uvs.into_f32().map(glam::Vec2::from_array));
+                let mut lightmap_uvs = reader
+                    .read_tex_coords(1)
+                    .map(|uvs| uvs.into_f32().map(glam::Vec2::from_array));
+                let mut normals = reader
+                    .read_normals()
+                    .map(|normals| normals.map(glam::Vec3::from_array));
+                let has_normals = normals.is_some();
+
+                let mut joints = remap.and_then(|remap| {
+                    let joints = reader.read_joints(0)?.into_u16();
+                    Some(joints.map(|joints| {
+                        joints.map(|joint| remap.get(joint as usize).copied().unwrap_or(0) as u16)
+                    }))
+                });
+                let mut weights = remap
+                    .and_then(|_| reader.read_weights(0))
+                    .map(|weights| weights.into_f32());
+
+                let vertices = positions
+                    .iter()
+                    .map(|pos| {
+                        let pos = mesh_transform.transform_point3(glam::Vec3::from_array(*pos));
+                        let uv = uvs.as_mut().and_then(|uvs| uvs.next()).unwrap_or_default();
+                        let normal = normals
+                            .as_mut()
+                            .and_then(|normals| normals.next())
+                            .map(|normal| (normal_matrix * normal).normalize_or_zero())
+                            .unwrap_or_default();
+
+                        let lightmap_uv = lightmap_uvs
+                            .as_mut()
+                            .and_then(|uvs| uvs.next())
+                            .unwrap_or(uv);
+
+                        let vertex =
+                            ModelVertex::new(pos, uv, normal).with_lightmap_uv(lightmap_uv);
+
+                        match (
+                            joints.as_mut().and_then(|joints| joints.next()),
+                            weights.as_mut().and_then(|weights| weights.next()),
+                        ) {
+                            (Some(joints), Some(weights)) => vertex.with_joints(joints, weights),
+                            _ => vertex,
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                let indices = match reader.read_indices() {
+                    Some(indices) => indices.into_u32().collect(),
+                    None => (0..vertices.len() as u32).collect(),
+                };
+
+                let mut mesh_data = MeshData {
+                    vertices,
+                    indices,
+                    material: primitive.material().index(),
+                    ..Default::default()
+                };
+                mesh_data.validate_indices()?;
+
+                if !has_normals {
+                    mesh_data.calculate_normals();
+                }
+
+                meshes.push(mesh_data);
+                Ok(())
+            })?;
+    }
+
+    node.children()
+        .try_for_each(|child| gltf_node(&child, transform, buffers, skin, meshes))
+}
+
+/// Maps whose images couldn't be loaded are left out.
+fn gltf_material(
+    material: &gltf::Material,
+    textures: &[Option<image::DynamicImage>],
+) -> MaterialData {
+    let loaded = |texture: gltf::Texture| {
+        let index = texture.source().index();
+        textures
+            .get(index)
+            .is_some_and(|image| image.is_some())
+            .then_some(index)
+    };
+
+    let pbr = material.pbr_metallic_roughness();
+
+    MaterialData {
+        base_color: pbr.base_color_factor(),
+        metallic: pbr.metallic_factor(),
+        roughness: pbr.roughness_factor(),
+        emissive: material.emissive_factor(),
+        base_color_texture: pbr
+            .base_color_texture()
+            .and_then(|info| loaded(info.texture())),
+        normal_map: material
+            .normal_texture()
+            .and_then(|normal| loaded(normal.texture())),
+        metallic_roughness_map: pbr
+            .metallic_roughness_texture()
+            .and_then(|info| loaded(info.texture())),
+        emissive_map: material
+            .emissive_texture()
+            .and_then(|info| loaded(info.texture())),
+    }
+}
+
+fn gltf_buffer(
+    buffer: gltf::Buffer,
+    directory: &Path,
+    blob: &mut Option<Vec<u8>>,
+    read: ReadFn,
+) -> Result<gltf::buffer::Data, ModelLoadError> {
+    let data = match buffer.source() {
+        gltf::buffer::Source::Uri(uri) if !uri.contains(':') => {
+            let mut bytes = read(&directory.join(uri))?;
+            bytes.resize(bytes.len().next_multiple_of(4), 0);
+            gltf::buffer::Data(bytes)
+        }
+        // Embedded data uris and the glb binary chunk
+        source => gltf::buffer::Data::from_source_and_blob(source, None, blob)?,
+    };
+
+    if data.len() < buffer.length() {
+        return Err(gltf::Error::BufferLength {
+            buffer: buffer.index(),
+            expected: buffer.length(),
+            actual: data.len(),
+        }
+        .into());
+    }
+
+    Ok(data)
+}
+
+/// Images that can't be loaded are logged and left out.
+fn gltf_texture(
+    image: gltf::Image,
+    directory: &Path,
+    buffers: &[gltf::buffer::Data],
+    read: ReadFn,
+) -> Option<image::DynamicImage> {
+    match image.source() {
+        gltf::image::Source::Uri { uri, .. } if !uri.contains(':') => {
+            let path = directory.join(uri);
+            read(&path)
+                .map_err(image::ImageError::IoError)
+                .and_then(|bytes| image::load_from_memory(&bytes))
+                .inspect_err(|e| log::warn!("Unable to load texture {:?}: {}", path, e))
+                .ok()
+        }
+        // Embedded data uris and buffer views. The base path is only used for external files.
+        source => gltf::image::Data::from_source(source, Some(Path::new("")), buffers)
+            .inspect_err(|e| log::warn!("Unable to load gltf image {}: {}", image.index(), e))
+            .ok()
+            .and_then(gltf_image),
+    }
+}
+
+fn gltf_image(data: gltf::image::Data) -> Option<image::DynamicImage> {
+    use gltf::image::Format;
+
+    let (width, height) = (data.width, data.height);
+
+    let image =
+        match data.format {
+            Format::R8G8B8A8 => image::RgbaImage::from_raw(width, height, data.pixels)
+                .map(image::DynamicImage::from),
+            Format::R8G8B8 => {
+                image::RgbImage::from_raw(width, height, data.pixels).map(image::DynamicImage::from)
+            }
+            Format::R8G8 => image::GrayAlphaImage::from_raw(width, height, data.pixels)
+                .map(image::DynamicImage::from),
+            Format::R8 => image::GrayImage::from_raw(width, height, data.pixels)
+                .map(image::DynamicImage::from),
+            format => {
+                log::warn!("Unsupported gltf image format {:?}", format);
+                return None;
+            }
+        };
+
+    if image.is_none() {
+        log::warn!("Invalid gltf image data");
+    }
+
+    image
+}
+
+//====================================================================
+
+impl Model {
+    /// Load an obj, gltf or glb file based on its extension.
+    pub fn load(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &SharedRenderResources,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, ModelLoadError> {
+        Ok(ModelData::load(path)?.build(device, queue, shared))
+    }
+
+    pub fn from_obj(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &SharedRenderResources,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, ModelLoadError> {
+        Ok(ModelData::from_obj(path)?.build(device, queue, shared))
+    }
+
+    pub fn from_gltf(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &SharedRenderResources,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, ModelLoadError> {
+        Ok(ModelData::from_gltf(path)?.build(device, queue, shared))
+    }
+}
+
+//====================================================================
@@ -1,17 +1,27 @@
 //====================================================================
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::BTreeMap,
     sync::{atomic::AtomicU32, Arc},
 };
 
-use common::GlobalTransform;
+use common::{GlobalOpacity, GlobalTransform};
 use renderer::{
-    camera::{CameraWgpu, PerspectiveCamera},
-    shared::{ModelVertex, Vertex},
-    texture::{LoadedTexture, TextureId},
-    tools::{self, InstanceBuffer},
-    Renderer, WgpuWrapper,
+    cache::{CacheSize, ResourceCache},
+    camera,
+    reflection::{ProbeTexture, ProbeTextureId, ReflectionProbe},
+    shared::{ModelVertex, SharedRenderResources, Vertex},
+    texture::{LoadedTexture, Texture, TextureId},
+    tools::{self, InstanceBuffer, RasterState},
+    PrePass, Renderer, RendererCore, WgpuWrapper,
+};
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    material::{Material, MaterialId},
+    material_animation::{self, ColorPulse, UvScroll},
+    skeleton::{AnimationClip, SkeletalAnimation, Skeleton},
 };
 
 //====================================================================
@@ -25,6 +35,7 @@ pub struct Mesh {
     vertex_buffer: WgpuWrapper<wgpu::Buffer>,
     index_buffer: WgpuWrapper<wgpu::Buffer>,
     index_count: u32,
+    dynamic: bool,
 }
 
 impl Mesh {
@@ -40,14 +51,104 @@ impl Mesh {
             vertex_buffer: WgpuWrapper::new(vertex_buffer),
             index_buffer: WgpuWrapper::new(index_buffer),
             index_count,
+            dynamic: false,
         }
     }
+
+    /// Mesh whose vertices can be rewritten each frame with [`Mesh::update_vertices`], e.g. for
+    /// simulated cloth. The number of vertices and the indices stay fixed.
+    pub fn load_dynamic_mesh(
+        device: &wgpu::Device,
+        vertices: &[ModelVertex],
+        indices: &[u32],
+    ) -> Self {
+        let id = CURRENT_MESH_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Dynamic Mesh Vertex Buffer"),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+        let index_buffer = tools::buffer(device, tools::BufferType::Index, "Mesh", indices);
+
+        Self {
+            id,
+            vertex_buffer: WgpuWrapper::new(vertex_buffer),
+            index_buffer: WgpuWrapper::new(index_buffer),
+            index_count: indices.len() as u32,
+            dynamic: true,
+        }
+    }
+
+    /// Overwrite the vertices of a dynamic mesh. Ignored for other meshes or if the number of
+    /// vertices changed.
+    pub fn update_vertices(&self, queue: &wgpu::Queue, vertices: &[ModelVertex]) {
+        let size = std::mem::size_of_val(vertices) as u64;
+
+        if !self.dynamic || size != self.vertex_buffer.inner().size() {
+            log::warn!("Unable to update vertices of mesh '{}'", self.id);
+            return;
+        }
+
+        queue.write_buffer(
+            self.vertex_buffer.inner(),
+            0,
+            bytemuck::cast_slice(vertices),
+        );
+    }
 }
 
+impl CacheSize for Mesh {
+    #[inline]
+    fn cache_size(&self) -> u64 {
+        self.vertex_buffer.inner().size() + self.index_buffer.inner().size()
+    }
+}
+
+#[derive(Clone)]
 pub struct Model {
-    pub meshes: Vec<(Arc<Mesh>, Arc<LoadedTexture>)>,
+    /// Meshes can share a material, which is then bound once for all of them.
+    pub meshes: Vec<(Arc<Mesh>, Arc<Material>)>,
     pub color: [f32; 4],
     pub scale: glam::Vec3,
+    /// Applied as `uv * uv_scale + uv_offset`. Tiling past 0-1 needs a repeating sampler.
+    pub uv_offset: glam::Vec2,
+    pub uv_scale: glam::Vec2,
+    /// Baked lighting sampled with each vertex's lightmap uv in place of realtime lights, see
+    /// [`crate::lightmap::LightmapBaker`]. Shared by every mesh of the model.
+    pub lightmap: Option<Arc<LoadedTexture>>,
+    /// How much of the surroundings captured by [`ReflectionProbe`]s the model reflects,
+    /// from 0 to 1. Only the probes around the model's origin are used.
+    pub reflectivity: f32,
+    /// Joints the meshes are skinned to, posed by a [`SkeletalAnimation`].
+    pub skeleton: Option<Arc<Skeleton>>,
+    pub animations: Vec<Arc<AnimationClip>>,
+    /// Drawn into the shadow map, see [`renderer::shadow::ShadowSettings`]. Translucent
+    /// models and those drawn as lines or points never cast shadows.
+    pub casts_shadows: bool,
+}
+
+impl Model {
+    #[inline]
+    pub fn new(meshes: Vec<(Arc<Mesh>, Arc<Material>)>) -> Self {
+        Self {
+            meshes,
+            color: [1., 1., 1., 1.],
+            scale: glam::Vec3::ONE,
+            uv_offset: glam::Vec2::ZERO,
+            uv_scale: glam::Vec2::ONE,
+            lightmap: None,
+            reflectivity: 0.,
+            skeleton: None,
+            animations: Vec::new(),
+            casts_shadows: true,
+        }
+    }
+
+    #[inline]
+    pub fn animation(&self, name: &str) -> Option<&Arc<AnimationClip>> {
+        self.animations.iter().find(|clip| clip.name == name)
+    }
 }
 
 #[repr(C)]
@@ -55,8 +156,15 @@ pub struct Model {
 struct ModelInstance {
     pub transform: glam::Mat4,
     pub color: glam::Vec4,
-    pub normal: glam::Mat3,
     pub scale: glam::Vec3,
+    pub reflectivity: f32,
+    pub uv_offset: glam::Vec2,
+    pub uv_scale: glam::Vec2,
+    pub entity_id: u32,
+    /// Index of the instance's first joint matrix, or [`NO_JOINTS`].
+    pub joint_offset: u32,
+    pub pad: [u32; 2],
+    pub animation: glam::Vec4,
 }
 
 impl Vertex for ModelInstance {
@@ -66,11 +174,11 @@ impl Vertex for ModelInstance {
             4 => Float32x4,
             5 => Float32x4,
             6 => Float32x4,
-            7 => Float32x4, // Color
-            8 => Float32x3, // Normal
-            9 => Float32x3,
-            10 => Float32x3,
-            11 => Float32x3, // Scale
+            7 => Float32x4,  // Color
+            11 => Float32x4, // Scale + Reflectivity
+            12 => Float32x4, // Uv offset + Uv scale
+            13 => Uint32x4,  // Entity id + Joint offset
+            14 => Float32x4, // Animation
         ];
 
         wgpu::VertexBufferLayout {
@@ -81,14 +189,479 @@ impl Vertex for ModelInstance {
     }
 }
 
+/// Joint offset of instances without a skeleton. Must match the model shader.
+const NO_JOINTS: u32 = u32::MAX;
+
+/// Joint matrices fitting in a uniform buffer where vertex shaders can't read storage
+/// buffers. Must match the `pipelines::joints` uniform module.
+#[cfg(target_arch = "wasm32")]
+const MAX_UNIFORM_JOINTS: usize = 256;
+
+/// Joint matrices of every skinned instance in the frame, bound with the probes.
+struct JointBuffer {
+    buffer: wgpu::Buffer,
+    capacity: usize,
+}
+
+impl JointBuffer {
+    fn new(device: &wgpu::Device, capacity: usize) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let usage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST;
+        #[cfg(target_arch = "wasm32")]
+        let (usage, capacity) = (
+            wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            capacity.max(MAX_UNIFORM_JOINTS),
+        );
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Model Joints Buffer"),
+            size: (capacity.max(1) * std::mem::size_of::<glam::Mat4>()) as u64,
+            usage,
+            mapped_at_creation: false,
+        });
+
+        Self { buffer, capacity }
+    }
+}
+
+//====================================================================
+
+/// Up to two reflection probes blended by a model, innermost first.
+type ProbeSet = [Option<ProbeTextureId>; 2];
+
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Debug, Default)]
+struct ProbeRaw {
+    /// Blend distance in w.
+    center: glam::Vec4,
+    /// 1 in w when the probe is used.
+    half_extents: glam::Vec4,
+}
+
+/// Probe placement copied out of the world each frame.
+#[derive(Clone, Copy)]
+struct ProbeInfo {
+    id: ProbeTextureId,
+    center: glam::Vec3,
+    probe: ProbeRaw,
+    volume: f32,
+}
+
+/// Group 2 of the model pipelines, the debug override and joint matrices along with a
+/// probe set.
+struct ProbeBindGroup {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl ProbeBindGroup {
+    fn new(
+        core: &RendererCore,
+        shared: &SharedRenderResources,
+        layout: &wgpu::BindGroupLayout,
+        cubes: [&Texture; 2],
+        joints: &JointBuffer,
+    ) -> Self {
+        let buffer = tools::buffer(
+            core.device(),
+            tools::BufferType::Uniform,
+            "Model Probes",
+            &[ProbeRaw::default(); 2],
+        );
+
+        let bind_group = core.device().create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Model Probes Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: shared.debug_buffer().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&cubes[0].view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&cubes[1].view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(&cubes[0].sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: joints.buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self { buffer, bind_group }
+    }
+}
+
+//====================================================================
+
+renderer::wgsl_struct! {
+    struct MaterialUniform {
+        base_color: glam::Vec4,
+        emissive: glam::Vec4,
+        metallic: f32,
+        roughness: f32,
+        normal_map: u32,
+        _pad: u32,
+    }
+}
+
+/// Group 1 of the model pipelines, created the first time a [`Material`] is drawn.
+struct MaterialBindGroup {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    /// Keeps the material's textures alive for as long as the bind group, and tells which
+    /// textures it samples.
+    material: Arc<Material>,
+}
+
+impl MaterialBindGroup {
+    fn new(
+        core: &RendererCore,
+        layout: &wgpu::BindGroupLayout,
+        default_texture: &Texture,
+        material: Arc<Material>,
+    ) -> Self {
+        let [r, g, b] = material.emissive();
+
+        let buffer = tools::buffer(
+            core.device(),
+            tools::BufferType::Uniform,
+            "Model Material",
+            &[MaterialUniform {
+                base_color: material.base_color().into(),
+                emissive: glam::vec4(r, g, b, 0.),
+                metallic: material.metallic(),
+                roughness: material.roughness(),
+                normal_map: material.normal_map().is_some() as u32,
+                _pad: 0,
+            }],
+        );
+
+        let base_color = material.base_color_texture().texture();
+        let [normal, metallic_roughness, emissive] = [
+            material.normal_map(),
+            material.metallic_roughness_map(),
+            material.emissive_map(),
+        ]
+        .map(|map| map.map(|map| map.texture()).unwrap_or(default_texture));
+
+        let bind_group = core.device().create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Model Material Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&base_color.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&base_color.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&normal.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&metallic_roughness.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(&emissive.view),
+                },
+            ],
+        });
+
+        Self {
+            buffer,
+            bind_group,
+            material,
+        }
+    }
+}
+
+impl CacheSize for MaterialBindGroup {
+    #[inline]
+    fn cache_size(&self) -> u64 {
+        self.buffer.size()
+    }
+}
+
 //====================================================================
 
+/// Index into the model renderer's pipeline variants.
+type PipelineId = usize;
+
+/// What each model pipeline variant is created for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PipelineKey {
+    raster: RasterState,
+    /// Lightmap bound at group 3 in place of the realtime lights.
+    lightmapped: bool,
+    /// Alpha blended without writing depth, drawn after the opaque variants.
+    translucent: bool,
+}
+
+/// Instances are batched by pipeline, mesh, material, lightmap, reflection probes and whether
+/// they cast shadows.
+type InstanceKey = (
+    PipelineId,
+    MeshId,
+    MaterialId,
+    Option<TextureId>,
+    ProbeSet,
+    bool,
+);
+
 pub struct ModelRenderer {
-    pipeline: wgpu::RenderPipeline,
+    /// Pipeline for each variant used, the default first.
+    pipelines: Vec<(PipelineKey, wgpu::RenderPipeline)>,
+    /// Depth only pipeline drawing into the shadow map.
+    shadow_pipeline: wgpu::RenderPipeline,
+
+    material_bind_group_layout: wgpu::BindGroupLayout,
+    /// White texture bound in place of the maps a material doesn't have.
+    default_texture: Texture,
+    probe_bind_group_layout: wgpu::BindGroupLayout,
+    /// Black cube bound in place of missing probes.
+    empty_probe: Texture,
+    /// Bind group without any probes, also used while probes are captured.
+    no_probes: ProbeBindGroup,
+    probe_sets: BTreeMap<ProbeSet, ProbeBindGroup>,
+    joints: JointBuffer,
+
+    /// Lightmaps of the models drawn.
+    texture_storage: ResourceCache<TextureId, Arc<LoadedTexture>>,
+    material_storage: ResourceCache<MaterialId, MaterialBindGroup>,
+    mesh_storage: ResourceCache<MeshId, Arc<Mesh>>,
+    instances: ResourceCache<InstanceKey, InstanceBuffer<ModelInstance>>,
+}
+
+impl ModelRenderer {
+    fn create_pipeline(
+        core: &RendererCore,
+        shared: &renderer::shared::SharedRenderResources,
+        material_bind_group_layout: &wgpu::BindGroupLayout,
+        probe_bind_group_layout: &wgpu::BindGroupLayout,
+        key: PipelineKey,
+    ) -> wgpu::RenderPipeline {
+        let (label, group_3, fragment_entry) = match key.lightmapped {
+            true => (
+                "Lightmapped Model Pipeline",
+                shared.texture_bind_group_layout(),
+                "fs_lightmapped",
+            ),
+            false => (
+                "Model Pipeline",
+                shared.lights_bind_group_layout(),
+                "fs_main",
+            ),
+        };
+
+        let translucent_targets = [Some(wgpu::ColorTargetState {
+            format: core.target_format(),
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::all(),
+        })];
+
+        let mut desc = tools::RenderPipelineDescriptor {
+            fragment_entry: Some(fragment_entry),
+            ..Default::default()
+        }
+        .with_depth_stencil(core.depth_format())
+        .with_raster_state(key.raster);
+
+        if key.translucent {
+            desc.fragment_targets = Some(&translucent_targets);
+            if let Some(depth_stencil) = &mut desc.depth_stencil {
+                depth_stencil.depth_write_enabled = false;
+            }
+        }
+
+        tools::create_pipeline(
+            core.device(),
+            core.target_format(),
+            label,
+            &[
+                shared.camera_bind_group_layout(),
+                material_bind_group_layout,
+                probe_bind_group_layout,
+                group_3,
+            ],
+            &[ModelVertex::desc(), ModelInstance::desc()],
+            &shared.preprocess_shader(&renderer::include_shader!("src/shaders/model.wgsl")),
+            desc,
+        )
+    }
+
+    fn create_shadow_pipeline(
+        core: &RendererCore,
+        shared: &renderer::shared::SharedRenderResources,
+        material_bind_group_layout: &wgpu::BindGroupLayout,
+        probe_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::RenderPipeline {
+        // Both faces are drawn so single sided meshes still block the light
+        let desc = tools::RenderPipelineDescriptor {
+            vertex_entry: Some("vs_shadow"),
+            depth_only: true,
+            ..Default::default()
+        }
+        .with_depth_stencil(Texture::DEPTH_FORMAT)
+        .with_raster_state(RasterState::double_sided());
+
+        tools::create_pipeline(
+            core.device(),
+            core.target_format(),
+            "Model Shadow Pipeline",
+            &[
+                shared.camera_bind_group_layout(),
+                material_bind_group_layout,
+                probe_bind_group_layout,
+            ],
+            &[ModelVertex::desc(), ModelInstance::desc()],
+            &shared.preprocess_shader(&renderer::include_shader!("src/shaders/model.wgsl")),
+            desc,
+        )
+    }
+
+    fn pipeline_id(
+        &mut self,
+        core: &RendererCore,
+        shared: &renderer::shared::SharedRenderResources,
+        key: PipelineKey,
+    ) -> PipelineId {
+        match self
+            .pipelines
+            .iter()
+            .position(|(existing, _)| *existing == key)
+        {
+            Some(id) => id,
+            None => {
+                log::trace!("Creating model pipeline for {:?}", key);
+                let pipeline = Self::create_pipeline(
+                    core,
+                    shared,
+                    &self.material_bind_group_layout,
+                    &self.probe_bind_group_layout,
+                    key,
+                );
+                self.pipelines.push((key, pipeline));
+                self.pipelines.len() - 1
+            }
+        }
+    }
+
+    /// Upload the frame's joint matrices, growing the buffer and recreating the bind groups
+    /// holding it when they don't fit.
+    fn prep_joints(
+        &mut self,
+        core: &RendererCore,
+        shared: &SharedRenderResources,
+        joints: &[glam::Mat4],
+    ) {
+        #[cfg(target_arch = "wasm32")]
+        let joints = match joints.len() > MAX_UNIFORM_JOINTS {
+            true => {
+                log::warn!(
+                    "Only {} joints can be skinned per frame",
+                    MAX_UNIFORM_JOINTS
+                );
+                &joints[..MAX_UNIFORM_JOINTS]
+            }
+            false => joints,
+        };
+
+        if joints.len() > self.joints.capacity {
+            self.joints = JointBuffer::new(core.device(), joints.len().next_power_of_two());
+            self.probe_sets.clear();
+            self.no_probes = ProbeBindGroup::new(
+                core,
+                shared,
+                &self.probe_bind_group_layout,
+                [&self.empty_probe, &self.empty_probe],
+                &self.joints,
+            );
+        }
+
+        if !joints.is_empty() {
+            core.queue()
+                .write_buffer(&self.joints.buffer, 0, bytemuck::cast_slice(joints));
+        }
+    }
+
+    /// Innermost two probes containing `position`.
+    fn probe_set(probes: &[ProbeInfo], position: glam::Vec3) -> ProbeSet {
+        let mut containing = probes.iter().filter(|probe| {
+            (position - probe.center)
+                .abs()
+                .cmple(probe.probe.half_extents.truncate())
+                .all()
+        });
+
+        // Probes are sorted by volume
+        [containing.next(), containing.next()].map(|probe| probe.map(|probe| probe.id))
+    }
+
+    /// Create bind groups for new probe sets, drop unused ones and upload probe placements.
+    fn prep_probe_sets(
+        &mut self,
+        core: &RendererCore,
+        shared: &SharedRenderResources,
+        world: &hecs::World,
+        probes: &[ProbeInfo],
+        used: &[ProbeSet],
+    ) {
+        self.probe_sets.retain(|set, _| used.contains(set));
+
+        let mut textures = world.query::<&ProbeTexture>();
+        let textures = textures
+            .iter()
+            .map(|(_, texture)| (texture.id(), texture.cube()))
+            .collect::<BTreeMap<_, _>>();
 
-    texture_storage: HashMap<u32, Arc<LoadedTexture>>,
-    mesh_storage: HashMap<u32, Arc<Mesh>>,
-    instances: HashMap<MeshId, HashMap<TextureId, tools::InstanceBuffer<ModelInstance>>>,
+        used.iter().for_each(|set| {
+            let bind_group = self.probe_sets.entry(*set).or_insert_with(|| {
+                let cubes = set.map(|id| {
+                    id.and_then(|id| textures.get(&id).copied())
+                        .unwrap_or(&self.empty_probe)
+                });
+                ProbeBindGroup::new(
+                    core,
+                    shared,
+                    &self.probe_bind_group_layout,
+                    cubes,
+                    &self.joints,
+                )
+            });
+
+            let raw = set.map(|id| {
+                probes
+                    .iter()
+                    .find(|probe| Some(probe.id) == id)
+                    .map(|probe| probe.probe)
+                    .unwrap_or_default()
+            });
+
+            core.queue()
+                .write_buffer(&bind_group.buffer, 0, bytemuck::cast_slice(&raw));
+        });
+    }
 }
 
 impl Renderer for ModelRenderer {
@@ -97,152 +670,417 @@ impl Renderer for ModelRenderer {
         shared: &mut renderer::shared::SharedRenderResources,
         _world: &mut hecs::World,
     ) -> Self {
-        let pipeline = tools::create_pipeline(
+        let material_bind_group_layout =
+            core.device()
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("Model Material Bind Group Layout"),
+                    entries: &[
+                        tools::bgl_uniform_entry(0, wgpu::ShaderStages::FRAGMENT),
+                        tools::bgl_texture_entry(1),
+                        tools::bgl_sampler_entry(2),
+                        tools::bgl_texture_entry(3),
+                        tools::bgl_texture_entry(4),
+                        tools::bgl_texture_entry(5),
+                    ],
+                });
+
+        let default_texture = Texture::from_color(
             core.device(),
-            core.config(),
-            "Model Pipeline",
-            &[
-                shared.camera_bind_group_layout(),
-                shared.texture_bind_group_layout(),
-            ],
-            &[ModelVertex::desc(), ModelInstance::desc()],
-            include_str!("shaders/model.wgsl"),
-            tools::RenderPipelineDescriptor::default()
-                .with_depth_stencil()
-                .with_backface_culling(),
+            core.queue(),
+            [255; 3],
+            Some("Model Default Material Texture"),
+            None,
+        );
+
+        let probe_bind_group_layout =
+            core.device()
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("Model Probes Bind Group Layout"),
+                    entries: &[
+                        tools::bgl_uniform_entry(0, wgpu::ShaderStages::VERTEX_FRAGMENT),
+                        tools::bgl_uniform_entry(1, wgpu::ShaderStages::FRAGMENT),
+                        tools::bgl_cube_texture_entry(2),
+                        tools::bgl_cube_texture_entry(3),
+                        tools::bgl_sampler_entry(4),
+                        #[cfg(not(target_arch = "wasm32"))]
+                        tools::bgl_storage_entry(5, wgpu::ShaderStages::VERTEX),
+                        #[cfg(target_arch = "wasm32")]
+                        tools::bgl_uniform_entry(5, wgpu::ShaderStages::VERTEX),
+                    ],
+                });
+
+        #[cfg(not(target_arch = "wasm32"))]
+        renderer::add_shader_module!(
+            shared.shader_library_mut(),
+            "pipelines::joints",
+            "src/shaders/modules/joints.wgsl"
+        );
+        #[cfg(target_arch = "wasm32")]
+        renderer::add_shader_module!(
+            shared.shader_library_mut(),
+            "pipelines::joints",
+            "src/shaders/modules/joints_uniform.wgsl"
+        );
+
+        let empty_probe = Texture::create_cube_render_texture(
+            core.device(),
+            1,
+            core.target_format(),
+            "Empty Reflection Probe",
+        );
+        let joints = JointBuffer::new(core.device(), 64);
+        let no_probes = ProbeBindGroup::new(
+            core,
+            shared,
+            &probe_bind_group_layout,
+            [&empty_probe, &empty_probe],
+            &joints,
+        );
+
+        let key = PipelineKey {
+            raster: RasterState::default(),
+            lightmapped: false,
+            translucent: false,
+        };
+        let pipeline = Self::create_pipeline(
+            core,
+            shared,
+            &material_bind_group_layout,
+            &probe_bind_group_layout,
+            key,
+        );
+        let shadow_pipeline = Self::create_shadow_pipeline(
+            core,
+            shared,
+            &material_bind_group_layout,
+            &probe_bind_group_layout,
         );
 
         Self {
-            pipeline,
-            texture_storage: HashMap::default(),
-            mesh_storage: HashMap::default(),
-            instances: HashMap::default(),
+            pipelines: vec![(key, pipeline)],
+            shadow_pipeline,
+            material_bind_group_layout,
+            default_texture,
+            probe_bind_group_layout,
+            empty_probe,
+            no_probes,
+            probe_sets: BTreeMap::new(),
+            joints,
+            texture_storage: ResourceCache::default(),
+            material_storage: ResourceCache::default(),
+            mesh_storage: ResourceCache::default(),
+            instances: ResourceCache::default(),
         }
     }
 
     fn prep(
         &mut self,
         core: &renderer::RendererCore,
-        _shared: &mut renderer::shared::SharedRenderResources,
+        shared: &mut renderer::shared::SharedRenderResources,
         world: &mut hecs::World,
     ) {
-        let mut previous = self
-            .instances
-            .iter()
-            .flat_map(|(mesh_id, textures)| {
-                textures.keys().map(|texture_id| (*mesh_id, *texture_id))
+        let gpu_cache = shared.gpu_cache_mut();
+        let evicted = self.instances.collect(gpu_cache)
+            + self.mesh_storage.collect(gpu_cache)
+            + self.texture_storage.collect(gpu_cache)
+            + self.material_storage.collect(gpu_cache);
+
+        let mut buffers_resized = 0;
+
+        let mut probes = world
+            .query_mut::<(&ReflectionProbe, &ProbeTexture, &GlobalTransform)>()
+            .into_iter()
+            .map(|(_, (probe, texture, transform))| ProbeInfo {
+                id: texture.id(),
+                center: transform.translation(),
+                probe: ProbeRaw {
+                    center: transform.translation().extend(probe.blend_distance),
+                    half_extents: probe.half_extents.extend(1.),
+                },
+                volume: probe.half_extents.x * probe.half_extents.y * probe.half_extents.z,
             })
-            .collect::<HashSet<_>>();
+            .collect::<Vec<_>>();
+        probes.sort_by(|a, b| a.volume.total_cmp(&b.volume));
 
-        let mut meshes_used = HashSet::new();
-        let mut textures_used = HashSet::new();
+        let mut joints = Vec::new();
 
         let instances = world
-            .query_mut::<(&GlobalTransform, &Model)>()
+            .query_mut::<(
+                &GlobalTransform,
+                &Model,
+                Option<&UvScroll>,
+                Option<&ColorPulse>,
+                Option<&RasterState>,
+                Option<&GlobalOpacity>,
+                Option<&SkeletalAnimation>,
+            )>()
             .into_iter()
-            .fold(HashMap::new(), |mut acc, (_, (transform, model))| {
-                model.meshes.iter().for_each(|(mesh, texture)| {
-                    let mesh_entry = acc.entry(mesh.id).or_insert_with(|| {
-                        if !self.mesh_storage.contains_key(&mesh.id) {
-                            self.mesh_storage.insert(mesh.id, mesh.clone());
+            .fold(
+                BTreeMap::new(),
+                |mut acc, (entity, (transform, model, scroll, pulse, raster, opacity, animation))| {
+                    let color = GlobalOpacity::apply(opacity, model.color);
+
+                    let key = PipelineKey {
+                        raster: raster.copied().unwrap_or_default(),
+                        lightmapped: model.lightmap.is_some(),
+                        translucent: color[3] < 1.,
+                    };
+                    let pipeline = self.pipeline_id(core, shared, key);
+
+                    let casts_shadows = model.casts_shadows
+                        && !key.translucent
+                        && key.raster.topology == wgpu::PrimitiveTopology::TriangleList;
+
+                    let probe_set = match model.reflectivity > 0. {
+                        true => Self::probe_set(&probes, transform.translation()),
+                        false => [None; 2],
+                    };
+
+                    let lightmap = model.lightmap.as_ref().map(|lightmap| {
+                        self.texture_storage
+                            .use_or_insert_with(lightmap.id(), || lightmap.clone());
+                        lightmap.id()
+                    });
+
+                    let joint_offset = match (&model.skeleton, animation) {
+                        (Some(_), Some(animation)) if !animation.joint_matrices().is_empty() => {
+                            joints.extend_from_slice(animation.joint_matrices());
+                            (joints.len() - animation.joint_matrices().len()) as u32
                         }
+                        _ => NO_JOINTS,
+                    };
 
-                        meshes_used.insert(mesh.id);
+                    model.meshes.iter().for_each(|(mesh, material)| {
+                        self.mesh_storage
+                            .use_or_insert_with(mesh.id, || mesh.clone());
+                        self.material_storage
+                            .use_or_insert_with(material.id(), || {
+                                MaterialBindGroup::new(
+                                    core,
+                                    &self.material_bind_group_layout,
+                                    &self.default_texture,
+                                    material.clone(),
+                                )
+                            });
 
-                        HashMap::new()
+                        acc.entry((
+                            pipeline,
+                            mesh.id,
+                            material.id(),
+                            lightmap,
+                            probe_set,
+                            casts_shadows,
+                        ))
+                            .or_insert_with(Vec::new)
+                            .push(ModelInstance {
+                                transform: transform.to_matrix(),
+                                color: color.into(),
+                                scale: model.scale,
+                                reflectivity: model.reflectivity,
+                                uv_offset: model.uv_offset,
+                                uv_scale: model.uv_scale,
+                                entity_id: entity.id(),
+                                joint_offset,
+                                pad: [0; 2],
+                                animation: material_animation::instance_animation(scroll, pulse),
+                            });
                     });
 
-                    let rotation = transform.to_scale_rotation_translation().1;
-                    let normal_matrix = glam::Mat3::from_quat(rotation);
-
-                    mesh_entry
-                        .entry(texture.id())
-                        .or_insert_with(|| {
-                            if !self.texture_storage.contains_key(&texture.id()) {
-                                self.texture_storage.insert(texture.id(), texture.clone());
-                            }
-
-                            textures_used.insert(texture.id());
-
-                            Vec::new()
-                        })
-                        .push(ModelInstance {
-                            transform: transform.to_matrix(),
-                            color: model.color.into(),
-                            normal: normal_matrix,
-                            scale: model.scale,
-                        });
-                });
+                    acc
+                },
+            );
 
-                acc
-            });
+        self.prep_joints(core, shared, &joints);
+
+        let mut probe_sets = instances
+            .keys()
+            .map(|(.., probe_set, _)| *probe_set)
+            .filter(|probe_set| probe_set[0].is_some())
+            .collect::<Vec<_>>();
+        probe_sets.sort();
+        probe_sets.dedup();
+        self.prep_probe_sets(core, shared, world, &probes, &probe_sets);
 
-        instances.into_iter().for_each(|(mesh_id, texture_data)| {
-            texture_data.into_iter().for_each(|(texture_id, raw)| {
-                previous.remove(&(mesh_id, texture_id));
+        instances.into_iter().for_each(|(key, raw)| {
+            let created = !self.instances.contains(&key);
 
-                self.instances
-                    .entry(mesh_id)
-                    .or_insert(HashMap::default())
-                    .entry(texture_id)
-                    .and_modify(|instance| instance.update(core.device(), core.queue(), &raw))
-                    .or_insert_with(|| InstanceBuffer::new(core.device(), &raw));
+            let instance = self.instances.use_or_insert_with(key, || {
+                buffers_resized += 1;
+                InstanceBuffer::new(core.device(), &raw)
             });
-        });
 
-        previous.into_iter().for_each(|(mesh_id, texture_id)| {
-            log::trace!("Removing model instance {} - {}", mesh_id, texture_id);
-            self.instances
-                .get_mut(&mesh_id)
-                .unwrap()
-                .remove(&texture_id);
+            if !created && instance.update(core.device(), core.queue(), &raw) {
+                buffers_resized += 1;
+            }
         });
 
-        self.texture_storage
-            .retain(|texture_id, _| textures_used.contains(texture_id));
+        let stats = shared.stats_mut();
+        stats.add_counter("buffers_resized", buffers_resized);
+        stats.add_counter("resources_evicted", evicted as u64);
+        stats.set_gauge("meshes", self.mesh_storage.len() as f64);
+        stats.set_gauge("textures", self.texture_storage.len() as f64);
+        stats.set_gauge("materials", self.material_storage.len() as f64);
+    }
+
+    fn pre_passes(&self) -> &[PrePass] {
+        &[PrePass::Shadow]
+    }
+
+    fn render_pre_pass(
+        &mut self,
+        pre_pass: PrePass,
+        pass: &mut wgpu::RenderPass,
+        shared: &mut SharedRenderResources,
+        _world: &mut hecs::World,
+    ) {
+        if pre_pass != PrePass::Shadow {
+            return;
+        }
+
+        pass.set_pipeline(&self.shadow_pipeline);
+        pass.set_bind_group(0, shared.shadow_camera_bind_group(), &[]);
+        // Every probe bind group holds the same joint matrices
+        pass.set_bind_group(2, &self.no_probes.bind_group, &[]);
 
-        self.mesh_storage
-            .retain(|mesh_id, _| meshes_used.contains(mesh_id));
+        let mut current_mesh = None;
+
+        self.instances
+            .used()
+            .filter(|((.., casts_shadows), _)| *casts_shadows)
+            .for_each(|((_, mesh_id, material_id, ..), instance)| {
+                let (Some(mesh), Some(material)) = (
+                    self.mesh_storage.get(mesh_id),
+                    self.material_storage.get(material_id),
+                ) else {
+                    return;
+                };
+
+                if current_mesh != Some(*mesh_id) {
+                    current_mesh = Some(*mesh_id);
+
+                    pass.set_vertex_buffer(0, mesh.vertex_buffer.inner().slice(..));
+                    pass.set_index_buffer(
+                        mesh.index_buffer.inner().slice(..),
+                        wgpu::IndexFormat::Uint32,
+                    );
+                }
+
+                pass.set_bind_group(1, &material.bind_group, &[]);
+                pass.set_vertex_buffer(1, instance.buffer().slice(..));
+                pass.draw_indexed(0..mesh.index_count, 0, 0..instance.count());
+
+                shared.stats_mut().add_counter("shadow_draw_calls", 1);
+            });
     }
 
     fn render(
         &mut self,
         pass: &mut wgpu::RenderPass,
-        _shared: &mut renderer::shared::SharedRenderResources,
+        shared: &mut renderer::shared::SharedRenderResources,
         world: &mut hecs::World,
     ) {
-        let camera = match world
-            .query_mut::<(&PerspectiveCamera, &CameraWgpu)>()
-            .into_iter()
-            .next()
-        {
-            Some((_, (_, camera))) => camera,
+        let camera = match camera::active_camera(world, shared) {
+            Some(camera) => camera,
             None => {
-                log::warn!("No perspective camera available for texture renderer");
+                log::warn!("No camera available for model renderer");
                 return;
             }
         };
 
-        pass.set_pipeline(&self.pipeline);
         pass.set_bind_group(0, camera.bind_group(), &[]);
 
-        self.instances.iter().for_each(|(mesh_id, instance)| {
-            let mesh = self.mesh_storage.get(mesh_id).unwrap();
+        // The probe being captured can't be sampled, so reflections are left out of captures
+        let capturing = shared.capturing_probe();
+        let mut current_probes = None;
 
-            pass.set_vertex_buffer(0, mesh.vertex_buffer.inner().slice(..));
-            pass.set_index_buffer(
-                mesh.index_buffer.inner().slice(..),
-                wgpu::IndexFormat::Uint32,
-            );
+        // Instances are only marked used while they have something to draw, and are
+        // ordered by pipeline then mesh so each is bound once. Translucent ones go last.
+        let mut current_pipeline = None;
+        let mut batch: Option<(MeshId, u32)> = None;
+
+        let mut used = self.instances.used().collect::<Vec<_>>();
+        used.sort_by_key(|((pipeline_id, ..), _)| self.pipelines[*pipeline_id].0.translucent);
+
+        used.into_iter().for_each(
+            |((pipeline_id, mesh_id, material_id, lightmap_id, probe_set, _), instance)| {
+                let (Some(mesh), Some(material)) = (
+                    self.mesh_storage.get(mesh_id),
+                    self.material_storage.get(material_id),
+                ) else {
+                    return;
+                };
+
+                let samples_target = material
+                    .material
+                    .textures()
+                    .map(|texture| texture.id())
+                    .chain(*lightmap_id)
+                    .any(|texture| shared.is_active_target(texture));
 
-            instance.iter().for_each(|(texture_id, instance)| {
-                let texture = self.texture_storage.get(texture_id).unwrap();
+                if samples_target {
+                    return;
+                }
 
-                pass.set_bind_group(1, texture.bind_group(), &[]);
+                // Group 3 holds either the lights or this batch's lightmap
+                match lightmap_id {
+                    Some(lightmap_id) => match self.texture_storage.get(lightmap_id) {
+                        Some(lightmap) => pass.set_bind_group(3, lightmap.bind_group(), &[]),
+                        None => return,
+                    },
+                    None if current_pipeline != Some(*pipeline_id) => {
+                        pass.set_bind_group(3, shared.lights_bind_group(), &[])
+                    }
+                    None => {}
+                }
+
+                let probes = match capturing {
+                    true => [None; 2],
+                    false => *probe_set,
+                };
+
+                if current_probes != Some(probes) {
+                    current_probes = Some(probes);
+
+                    let bind_group = self.probe_sets.get(&probes).unwrap_or(&self.no_probes);
+                    pass.set_bind_group(2, &bind_group.bind_group, &[]);
+                }
+
+                if current_pipeline != Some(*pipeline_id) {
+                    current_pipeline = Some(*pipeline_id);
+                    pass.set_pipeline(&self.pipelines[*pipeline_id].1);
+                }
+
+                if batch.is_none_or(|(current, _)| current != *mesh_id) {
+                    if let Some((_, count)) = batch {
+                        shared.stats_mut().record_batch("mesh", count);
+                    }
+                    batch = Some((*mesh_id, 0));
+
+                    pass.set_vertex_buffer(0, mesh.vertex_buffer.inner().slice(..));
+                    pass.set_index_buffer(
+                        mesh.index_buffer.inner().slice(..),
+                        wgpu::IndexFormat::Uint32,
+                    );
+                }
+
+                pass.set_bind_group(1, &material.bind_group, &[]);
                 pass.set_vertex_buffer(1, instance.buffer().slice(..));
                 pass.draw_indexed(0..mesh.index_count, 0, 0..instance.count());
-            });
-        });
+
+                if let Some((_, count)) = &mut batch {
+                    *count += instance.count();
+                }
+
+                shared.stats_mut().add_counter("draw_calls", 1);
+                shared
+                    .stats_mut()
+                    .add_counter("instances", instance.count() as u64);
+            },
+        );
+
+        if let Some((_, count)) = batch {
+            shared.stats_mut().record_batch("mesh", count);
+        }
     }
 }
 
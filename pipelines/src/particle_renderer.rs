@@ -0,0 +1,546 @@
+//====================================================================
+
+use std::{
+    ops::{Add, Mul, Range},
+    sync::{atomic::AtomicU32, Arc},
+};
+
+use common::{forces::Forces, GlobalTransform};
+use hecs::World;
+use renderer::{
+    camera,
+    ordering::RenderGroup,
+    shared::{TextureRectVertex, Vertex},
+    texture::LoadedTexture,
+    tools, Renderer,
+};
+
+//====================================================================
+
+/// Value changing over a particle's life. Keys are `(t, value)` pairs with `t` from 0 at
+/// birth to 1 at death, sorted by `t`, and values are blended linearly between them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParticleCurve<T> {
+    keys: Vec<(f32, T)>,
+}
+
+impl<T> ParticleCurve<T>
+where
+    T: Copy + Add<Output = T> + Mul<f32, Output = T>,
+{
+    #[inline]
+    pub fn new(mut keys: Vec<(f32, T)>) -> Self {
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { keys }
+    }
+
+    #[inline]
+    pub fn constant(value: T) -> Self {
+        Self {
+            keys: vec![(0., value)],
+        }
+    }
+
+    #[inline]
+    pub fn linear(start: T, end: T) -> Self {
+        Self {
+            keys: vec![(0., start), (1., end)],
+        }
+    }
+
+    pub fn sample(&self, t: f32) -> Option<T> {
+        let next = self.keys.iter().position(|(key, _)| *key > t);
+
+        match next {
+            None => self.keys.last().map(|(_, value)| *value),
+            Some(0) => self.keys.first().map(|(_, value)| *value),
+            Some(next) => {
+                let (start, from) = self.keys[next - 1];
+                let (end, to) = self.keys[next];
+                let blend = (t - start) / (end - start);
+                Some(from * (1. - blend) + to * blend)
+            }
+        }
+    }
+}
+
+//--------------------------------------------------
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ParticleBlend {
+    #[default]
+    Alpha,
+    /// Brightens whatever is behind, for fire, sparks and magic.
+    Additive,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Particle {
+    position: glam::Vec3,
+    velocity: glam::Vec3,
+    rotation: f32,
+    spin: f32,
+    age: f32,
+    lifetime: f32,
+}
+
+static NEXT_SEED: AtomicU32 = AtomicU32::new(0x9E37_79B9);
+
+/// Spawns particles at the entity's [`GlobalTransform`], simulated on the cpu by
+/// [`sys_tick_particles`] and drawn as camera facing quads by [`ParticleRenderer`].
+/// Particles live in world space, so they're left behind when the emitter moves.
+pub struct ParticleEmitter {
+    pub texture: Arc<LoadedTexture>,
+    pub blend: ParticleBlend,
+    /// Particles spawned per second while `emitting`.
+    pub rate: f32,
+    pub emitting: bool,
+    pub max_particles: usize,
+
+    /// Seconds each particle lives for, picked between the two.
+    pub lifetime: (f32, f32),
+    /// Radius of the sphere particles spawn in.
+    pub spawn_radius: f32,
+    /// Starting velocity, rotated with the emitter.
+    pub velocity: glam::Vec3,
+    /// Angle in radians the starting velocity is randomly turned by.
+    pub spread: f32,
+    /// Starting speed is scaled by a random amount up to this, from 0 to 1.
+    pub speed_variance: f32,
+    /// Starting spin in radians per second, picked between plus and minus this.
+    pub spin: f32,
+    /// Kept even with a [`Forces`] gravity override, as particles like smoke rise.
+    pub gravity: glam::Vec3,
+    /// How strongly the [`Forces`] wind carries particles along.
+    pub drag: f32,
+    /// World space distance over which particles fade in front of geometry instead of
+    /// clipping with a hard edge, or 0 to disable. Requires a copyable depth format.
+    pub fade_distance: f32,
+
+    /// Scales the particle's velocity.
+    pub speed_over_lifetime: ParticleCurve<f32>,
+    pub size_over_lifetime: ParticleCurve<f32>,
+    pub color_over_lifetime: ParticleCurve<glam::Vec4>,
+
+    particles: Vec<Particle>,
+    pending: f32,
+    seed: u32,
+}
+
+impl ParticleEmitter {
+    pub fn new(texture: Arc<LoadedTexture>) -> Self {
+        Self {
+            texture,
+            blend: ParticleBlend::Alpha,
+            rate: 10.,
+            emitting: true,
+            max_particles: 1000,
+            lifetime: (1., 1.),
+            spawn_radius: 0.,
+            velocity: glam::Vec3::Y,
+            spread: 0.3,
+            speed_variance: 0.,
+            spin: 0.,
+            gravity: glam::Vec3::ZERO,
+            drag: 0.,
+            fade_distance: 0.,
+            speed_over_lifetime: ParticleCurve::constant(1.),
+            size_over_lifetime: ParticleCurve::constant(0.1),
+            color_over_lifetime: ParticleCurve::linear(glam::Vec4::ONE, glam::vec4(1., 1., 1., 0.)),
+            particles: Vec::new(),
+            pending: 0.,
+            seed: NEXT_SEED.fetch_add(0x9E37_79B9, std::sync::atomic::Ordering::Relaxed) | 1,
+        }
+    }
+
+    /// Spawn `count` particles on the next tick, on top of the continuous rate.
+    #[inline]
+    pub fn burst(&mut self, count: u32) {
+        self.pending += count as f32;
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    #[inline]
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Uniform random number from 0 to 1.
+    fn random(&mut self) -> f32 {
+        // Xorshift
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        (self.seed >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn random_range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.random()
+    }
+
+    fn random_unit(&mut self) -> glam::Vec3 {
+        let z = self.random_range(-1., 1.);
+        let angle = self.random_range(0., std::f32::consts::TAU);
+        let radius = (1. - z * z).sqrt();
+        glam::vec3(radius * angle.cos(), radius * angle.sin(), z)
+    }
+
+    fn spawn(&mut self, transform: &GlobalTransform) {
+        let (_, rotation, translation) = transform.to_scale_rotation_translation();
+
+        let offset = self.random_unit() * self.spawn_radius * self.random().cbrt();
+
+        // Tilt the velocity by up to spread, then twist it randomly around where it pointed
+        let velocity = rotation * self.velocity;
+        let direction = velocity.normalize_or(glam::Vec3::Y);
+        let twist = self.random_range(0., std::f32::consts::TAU);
+        let tilt = self.random() * self.spread;
+        let spread = glam::Quat::from_axis_angle(direction, twist)
+            * glam::Quat::from_axis_angle(direction.any_orthonormal_vector(), tilt);
+        let speed = 1. - self.random() * self.speed_variance.clamp(0., 1.);
+
+        let particle = Particle {
+            position: translation + offset,
+            velocity: spread * velocity * speed,
+            rotation: self.random_range(0., std::f32::consts::TAU),
+            spin: self.random_range(-self.spin, self.spin),
+            age: 0.,
+            lifetime: self
+                .random_range(self.lifetime.0, self.lifetime.1)
+                .max(0.0001),
+        };
+
+        self.particles.push(particle);
+    }
+
+    fn tick(&mut self, transform: &GlobalTransform, forces: &Forces, delta_seconds: f32) {
+        let gravity = self.gravity;
+        let drag = self.drag;
+
+        self.particles.retain_mut(|particle| {
+            particle.age += delta_seconds;
+
+            let wind = (forces.wind_at(particle.position) - particle.velocity) * drag;
+            let acceleration = gravity + forces.acceleration_at(particle.position) + wind;
+            particle.velocity += acceleration * delta_seconds;
+
+            particle.age < particle.lifetime
+        });
+
+        self.particles.iter_mut().for_each(|particle| {
+            let t = particle.age / particle.lifetime;
+            let speed = self.speed_over_lifetime.sample(t).unwrap_or(1.);
+            particle.position += particle.velocity * speed * delta_seconds;
+            particle.rotation += particle.spin * delta_seconds;
+        });
+
+        if self.emitting {
+            self.pending += self.rate * delta_seconds;
+        }
+
+        while self.pending >= 1. {
+            self.pending -= 1.;
+
+            if self.particles.len() < self.max_particles {
+                self.spawn(transform);
+            }
+        }
+    }
+}
+
+/// Age and spawn particles of every [`ParticleEmitter`], pushed around by `forces`.
+/// Call once per frame.
+pub fn sys_tick_particles(world: &mut World, forces: &Forces, delta_seconds: f32) {
+    world
+        .query_mut::<(&mut ParticleEmitter, &GlobalTransform)>()
+        .into_iter()
+        .for_each(|(_, (emitter, transform))| emitter.tick(transform, forces, delta_seconds));
+}
+
+//====================================================================
+
+/// Consecutive instances drawn with the same texture and blending.
+struct ParticleBatch {
+    texture: Arc<LoadedTexture>,
+    blend: ParticleBlend,
+    soft: bool,
+    instances: Range<u32>,
+}
+
+/// Draws the particles of every [`ParticleEmitter`] back to front, batched while
+/// neighbouring particles share a texture and blend mode.
+pub struct ParticleRenderer {
+    alpha_pipeline: wgpu::RenderPipeline,
+    additive_pipeline: wgpu::RenderPipeline,
+    soft_alpha_pipeline: wgpu::RenderPipeline,
+    soft_additive_pipeline: wgpu::RenderPipeline,
+    has_soft: bool,
+
+    instances: tools::InstanceBuffer<ParticleInstance>,
+    batches: Vec<ParticleBatch>,
+}
+
+impl Renderer for ParticleRenderer {
+    fn group() -> RenderGroup {
+        RenderGroup::Transparent
+    }
+
+    fn new(
+        core: &renderer::RendererCore,
+        shared: &mut renderer::shared::SharedRenderResources,
+        _world: &mut World,
+    ) -> Self {
+        let pipeline = |label, blend, soft: bool| {
+            let layouts = [
+                shared.camera_bind_group_layout(),
+                shared.texture_bind_group_layout(),
+                shared.debug_bind_group_layout(),
+                shared.depth_bind_group_layout(),
+            ];
+
+            tools::create_pipeline(
+                core.device(),
+                core.target_format(),
+                label,
+                match soft {
+                    true => &layouts[..],
+                    false => &layouts[..3],
+                },
+                &[TextureRectVertex::desc(), ParticleInstance::desc()],
+                &shared.preprocess_shader(&renderer::include_shader!("src/shaders/particle.wgsl")),
+                tools::RenderPipelineDescriptor {
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    // Particles are hidden behind geometry but don't hide each other
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: core.depth_format(),
+                        depth_write_enabled: false,
+                        depth_compare: wgpu::CompareFunction::Less,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    fragment_targets: Some(&[Some(wgpu::ColorTargetState {
+                        format: core.target_format(),
+                        blend: Some(blend),
+                        write_mask: wgpu::ColorWrites::all(),
+                    })]),
+                    fragment_entry: soft.then_some("fs_soft"),
+                    ..Default::default()
+                },
+            )
+        };
+
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::OVER,
+        };
+
+        let alpha = wgpu::BlendState::ALPHA_BLENDING;
+
+        Self {
+            alpha_pipeline: pipeline("Particle Pipeline", alpha, false),
+            additive_pipeline: pipeline("Additive Particle Pipeline", additive, false),
+            soft_alpha_pipeline: pipeline("Soft Particle Pipeline", alpha, true),
+            soft_additive_pipeline: pipeline("Soft Additive Particle Pipeline", additive, true),
+            has_soft: false,
+            instances: tools::InstanceBuffer::new(core.device(), &[]),
+            batches: Vec::new(),
+        }
+    }
+
+    fn prep(
+        &mut self,
+        core: &renderer::RendererCore,
+        shared: &mut renderer::shared::SharedRenderResources,
+        world: &mut World,
+    ) {
+        let camera = camera::active_camera(world, shared).map(|camera| camera.matrices().position);
+
+        let mut sorted = Vec::new();
+
+        world
+            .query_mut::<&ParticleEmitter>()
+            .into_iter()
+            .for_each(|(entity, emitter)| {
+                emitter.particles.iter().for_each(|particle| {
+                    let t = particle.age / particle.lifetime;
+                    let size = emitter.size_over_lifetime.sample(t).unwrap_or(0.1);
+                    let color = emitter
+                        .color_over_lifetime
+                        .sample(t)
+                        .unwrap_or(glam::Vec4::ONE);
+
+                    let distance = camera
+                        .map(|camera| camera.distance_squared(particle.position))
+                        .unwrap_or(0.);
+
+                    sorted.push((
+                        distance,
+                        (emitter.blend, emitter.fade_distance > 0.),
+                        emitter.texture.clone(),
+                        ParticleInstance {
+                            position: particle.position.extend(size),
+                            color,
+                            rotation: particle.rotation,
+                            entity_id: entity.id(),
+                            fade_distance: emitter.fade_distance.max(0.),
+                            pad: 0,
+                        },
+                    ));
+                });
+            });
+
+        // Furthest first. Ties keep blend, softness and texture order so they still batch.
+        sorted.sort_by(|a, b| {
+            b.0.total_cmp(&a.0)
+                .then(a.1.cmp(&b.1))
+                .then(a.2.id().cmp(&b.2.id()))
+        });
+
+        self.batches.clear();
+        let mut raw = Vec::with_capacity(sorted.len());
+
+        sorted
+            .into_iter()
+            .for_each(|(_, (blend, soft), texture, instance)| {
+                let index = raw.len() as u32;
+                raw.push(instance);
+
+                match self.batches.last_mut() {
+                    Some(batch)
+                        if batch.blend == blend
+                            && batch.soft == soft
+                            && batch.texture.id() == texture.id() =>
+                    {
+                        batch.instances.end = index + 1;
+                    }
+                    _ => self.batches.push(ParticleBatch {
+                        texture,
+                        blend,
+                        soft,
+                        instances: index..index + 1,
+                    }),
+                }
+            });
+
+        let buffers_resized = self.instances.update(core.device(), core.queue(), &raw);
+
+        self.has_soft = self.batches.iter().any(|batch| batch.soft);
+
+        let stats = shared.stats_mut();
+        stats.add_counter("buffers_resized", buffers_resized as u64);
+        stats.set_gauge("particles", raw.len() as f64);
+    }
+
+    #[inline]
+    fn reads_depth(&self) -> bool {
+        self.has_soft
+    }
+
+    fn render(
+        &mut self,
+        pass: &mut wgpu::RenderPass,
+        shared: &mut renderer::shared::SharedRenderResources,
+        world: &mut World,
+    ) {
+        if self.batches.is_empty() {
+            return;
+        }
+
+        let camera = match camera::active_camera(world, shared) {
+            Some(camera) => camera,
+            None => {
+                log::warn!("No camera available for particle renderer");
+                return;
+            }
+        };
+
+        let quad = shared.quad();
+        quad.bind(pass, 0);
+        let index_count = quad.index_count();
+
+        pass.set_vertex_buffer(1, self.instances.buffer().slice(..));
+
+        // Without a depth copy soft particles fall back to being drawn as regular particles
+        let depth_bind_group = shared.depth_bind_group().filter(|_| self.has_soft);
+        let mut current = None;
+
+        self.batches.iter().for_each(|batch| {
+            let soft = batch.soft && depth_bind_group.is_some();
+
+            if current != Some((batch.blend, soft)) {
+                pass.set_pipeline(match (batch.blend, soft) {
+                    (ParticleBlend::Alpha, false) => &self.alpha_pipeline,
+                    (ParticleBlend::Additive, false) => &self.additive_pipeline,
+                    (ParticleBlend::Alpha, true) => &self.soft_alpha_pipeline,
+                    (ParticleBlend::Additive, true) => &self.soft_additive_pipeline,
+                });
+                if soft {
+                    pass.set_bind_group(3, depth_bind_group.unwrap(), &[]);
+                }
+                pass.set_bind_group(0, camera.bind_group(), &[]);
+                pass.set_bind_group(2, shared.debug_bind_group(), &[]);
+                current = Some((batch.blend, soft));
+            }
+
+            if shared.is_active_target(batch.texture.id()) {
+                return;
+            }
+
+            pass.set_bind_group(1, batch.texture.bind_group(), &[]);
+            pass.draw_indexed(0..index_count, 0, batch.instances.clone());
+        });
+
+        let stats = shared.stats_mut();
+        stats.add_counter("draw_calls", self.batches.len() as u64);
+        stats.add_counter("instances", self.instances.count() as u64);
+        self.batches
+            .iter()
+            .for_each(|batch| stats.record_batch("particle", batch.instances.len() as u32));
+    }
+}
+
+//====================================================================
+
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Debug)]
+struct ParticleInstance {
+    /// Position xyz and size w.
+    position: glam::Vec4,
+    color: glam::Vec4,
+    rotation: f32,
+    /// Used to pick a color when debug overrides are enabled.
+    entity_id: u32,
+    fade_distance: f32,
+    pad: u32,
+}
+
+impl Vertex for ParticleInstance {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+            2 => Float32x4, // Position + Size
+            3 => Float32x4, // Color
+            4 => Float32,   // Rotation
+            5 => Uint32,    // Entity id
+            6 => Float32,   // Fade distance
+        ];
+
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &VERTEX_ATTRIBUTES,
+        }
+    }
+}
+
+//====================================================================
@@ -0,0 +1,170 @@
+//====================================================================
+
+use hecs::World;
+use renderer::{
+    camera,
+    debug::DebugLineVertex,
+    ordering::RenderGroup,
+    shared::{SharedRenderResources, Vertex},
+    tools, Renderer, RendererCore,
+};
+
+//====================================================================
+
+/// Line through world space points, drawn as a flat ribbon turned towards the camera.
+/// The entity's transform is ignored, so ropes and trails can write their points directly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polyline {
+    pub points: Vec<glam::Vec3>,
+    pub width: f32,
+    pub color: [f32; 4],
+}
+
+impl Polyline {
+    #[inline]
+    pub fn new(width: f32, color: [f32; 4]) -> Self {
+        Self {
+            points: Vec::new(),
+            width,
+            color,
+        }
+    }
+
+    /// Two triangles for each segment, facing `camera`.
+    fn triangles(&self, camera: glam::Vec3, vertices: &mut Vec<DebugLineVertex>) {
+        let half_width = self.width / 2.;
+        let color = self.color;
+
+        // Offset of each point from the line, averaged between its two segments
+        let sides = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(index, point)| {
+                let before = self.points[index.saturating_sub(1)];
+                let after = self.points[(index + 1).min(self.points.len() - 1)];
+
+                (after - before).cross(camera - *point).normalize_or_zero() * half_width
+            })
+            .collect::<Vec<_>>();
+
+        self.points
+            .windows(2)
+            .zip(sides.windows(2))
+            .for_each(|(points, sides)| {
+                let corners = [
+                    points[0] - sides[0],
+                    points[0] + sides[0],
+                    points[1] - sides[1],
+                    points[1] + sides[1],
+                ];
+
+                [0, 1, 2, 2, 1, 3].into_iter().for_each(|corner| {
+                    vertices.push(DebugLineVertex {
+                        position: corners[corner],
+                        color,
+                    })
+                });
+            });
+    }
+}
+
+//--------------------------------------------------
+
+/// Draws every [`Polyline`] in a single draw call.
+pub struct PolylineRenderer {
+    pipeline: wgpu::RenderPipeline,
+    vertices: tools::InstanceBuffer<DebugLineVertex>,
+}
+
+impl Renderer for PolylineRenderer {
+    fn group() -> RenderGroup {
+        RenderGroup::Transparent
+    }
+
+    fn new(core: &RendererCore, shared: &mut SharedRenderResources, _world: &mut World) -> Self
+    where
+        Self: Sized,
+    {
+        // Same vertices as debug lines, just as triangles
+        let pipeline = tools::create_pipeline(
+            core.device(),
+            core.target_format(),
+            "Polyline Pipeline",
+            &[shared.camera_bind_group_layout()],
+            &[DebugLineVertex::desc()],
+            &shared.preprocess_shader(&renderer::include_shader!("src/shaders/debug_lines.wgsl")),
+            tools::RenderPipelineDescriptor {
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: core.depth_format(),
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                fragment_targets: Some(&[Some(wgpu::ColorTargetState {
+                    format: core.target_format(),
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::all(),
+                })]),
+                ..Default::default()
+            },
+        );
+
+        Self {
+            pipeline,
+            vertices: tools::InstanceBuffer::new(core.device(), &[]),
+        }
+    }
+
+    fn prep(&mut self, core: &RendererCore, shared: &mut SharedRenderResources, world: &mut World) {
+        let Some(camera) =
+            camera::active_camera(world, shared).map(|camera| camera.matrices().position)
+        else {
+            return;
+        };
+
+        let mut vertices = Vec::new();
+
+        world
+            .query_mut::<&Polyline>()
+            .into_iter()
+            .for_each(|(_, polyline)| polyline.triangles(camera, &mut vertices));
+
+        let buffers_resized = self.vertices.update(core.device(), core.queue(), &vertices);
+
+        let stats = shared.stats_mut();
+        stats.add_counter("buffers_resized", buffers_resized as u64);
+        stats.add_counter("polyline_segments", vertices.len() as u64 / 6);
+    }
+
+    fn render(
+        &mut self,
+        pass: &mut wgpu::RenderPass,
+        shared: &mut SharedRenderResources,
+        world: &mut World,
+    ) {
+        if self.vertices.count() == 0 {
+            return;
+        }
+
+        let Some(camera) = camera::active_camera(world, shared) else {
+            log::warn!("No camera available for polyline renderer");
+            return;
+        };
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, camera.bind_group(), &[]);
+        pass.set_vertex_buffer(0, self.vertices.buffer().slice(..));
+        pass.draw(0..self.vertices.count(), 0..1);
+
+        shared.stats_mut().add_counter("draw_calls", 1);
+    }
+}
+
+//====================================================================
@@ -0,0 +1,434 @@
+//====================================================================
+
+use common::Size;
+use renderer::{
+    post_process::{self, PostProcess},
+    shared::SharedRenderResources,
+    texture::{LoadedTexture, Texture},
+    tools, RendererCore,
+};
+
+//====================================================================
+
+// Built in post processing effects. A typical chain is bloom, tonemap, fxaa then vignette:
+//
+// renderer.add_post_process::<Bloom>(0);
+// renderer.add_post_process::<Tonemap>(10);
+// renderer.add_post_process::<Fxaa>(20);
+// renderer.add_post_process::<Vignette>(30);
+
+/// Uniform of four floats with a meaning that depends on the effect.
+struct EffectParams {
+    buffer: wgpu::Buffer,
+    bind_group_layout: tools::BindLayout,
+    bind_group: wgpu::BindGroup,
+}
+
+impl EffectParams {
+    fn new(device: &wgpu::Device, label: &str) -> Self {
+        let buffer = tools::buffer(
+            device,
+            tools::BufferType::Uniform,
+            label,
+            &[glam::Vec4::ZERO],
+        );
+
+        let bind_group_layout = tools::BindGroupLayoutBuilder::new(label)
+            .uniform::<glam::Vec4>("params", wgpu::ShaderStages::FRAGMENT)
+            .build(device);
+
+        let bind_group = bind_group_layout.bind_group(device, &[buffer.as_entire_binding()]);
+
+        Self {
+            buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    #[inline]
+    fn write(&self, queue: &wgpu::Queue, params: glam::Vec4) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[params]));
+    }
+}
+
+fn effect_pipeline(
+    core: &RendererCore,
+    label: &str,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    fragment_entry: &str,
+) -> wgpu::RenderPipeline {
+    tools::create_pipeline(
+        core.device(),
+        post_process::HDR_FORMAT,
+        label,
+        bind_group_layouts,
+        &[],
+        &renderer::include_shader!("src/shaders/post_effects.wgsl"),
+        tools::RenderPipelineDescriptor {
+            fragment_entry: Some(fragment_entry),
+            ..Default::default()
+        },
+    )
+}
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TonemapOperator {
+    Reinhard,
+    /// Filmic curve with more contrast than Reinhard.
+    #[default]
+    Aces,
+}
+
+/// Map the hdr scene into 0-1 so bright areas roll off instead of clipping.
+pub struct Tonemap {
+    pub enabled: bool,
+    /// Scene colors are multiplied by this before being mapped.
+    pub exposure: f32,
+    pub operator: TonemapOperator,
+
+    pipeline: wgpu::RenderPipeline,
+    params: EffectParams,
+}
+
+impl PostProcess for Tonemap {
+    fn new(core: &RendererCore, shared: &mut SharedRenderResources) -> Self {
+        let params = EffectParams::new(core.device(), "Tonemap Params");
+        let pipeline = effect_pipeline(
+            core,
+            "Tonemap Pipeline",
+            &[
+                shared.texture_bind_group_layout(),
+                params.bind_group_layout.layout(),
+            ],
+            "fs_tonemap",
+        );
+
+        Self {
+            enabled: true,
+            exposure: 1.,
+            operator: TonemapOperator::default(),
+            pipeline,
+            params,
+        }
+    }
+
+    #[inline]
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn apply(
+        &mut self,
+        core: &RendererCore,
+        _shared: &mut SharedRenderResources,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &LoadedTexture,
+        output: &wgpu::TextureView,
+    ) {
+        let operator = match self.operator {
+            TonemapOperator::Reinhard => 0.,
+            TonemapOperator::Aces => 1.,
+        };
+        self.params
+            .write(core.queue(), glam::vec4(self.exposure, operator, 0., 0.));
+
+        post_process::fullscreen_pass(
+            encoder,
+            "Tonemap Pass",
+            &self.pipeline,
+            &[input.bind_group(), &self.params.bind_group],
+            output,
+        );
+    }
+}
+
+//====================================================================
+
+/// Fast approximate anti-aliasing. Works best after tonemapping.
+pub struct Fxaa {
+    pub enabled: bool,
+    /// Furthest distance in pixels to blur along an edge.
+    pub span_max: f32,
+    pub reduce_mul: f32,
+    pub reduce_min: f32,
+
+    pipeline: wgpu::RenderPipeline,
+    params: EffectParams,
+}
+
+impl PostProcess for Fxaa {
+    fn new(core: &RendererCore, shared: &mut SharedRenderResources) -> Self {
+        let params = EffectParams::new(core.device(), "Fxaa Params");
+        let pipeline = effect_pipeline(
+            core,
+            "Fxaa Pipeline",
+            &[
+                shared.texture_bind_group_layout(),
+                params.bind_group_layout.layout(),
+            ],
+            "fs_fxaa",
+        );
+
+        Self {
+            enabled: true,
+            span_max: 8.,
+            reduce_mul: 1. / 8.,
+            reduce_min: 1. / 128.,
+            pipeline,
+            params,
+        }
+    }
+
+    #[inline]
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn apply(
+        &mut self,
+        core: &RendererCore,
+        _shared: &mut SharedRenderResources,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &LoadedTexture,
+        output: &wgpu::TextureView,
+    ) {
+        self.params.write(
+            core.queue(),
+            glam::vec4(self.span_max, self.reduce_mul, self.reduce_min, 0.),
+        );
+
+        post_process::fullscreen_pass(
+            encoder,
+            "Fxaa Pass",
+            &self.pipeline,
+            &[input.bind_group(), &self.params.bind_group],
+            output,
+        );
+    }
+}
+
+//====================================================================
+
+/// Darken the edges of the screen.
+pub struct Vignette {
+    pub enabled: bool,
+    /// How dark the edges get, from 0 to 1.
+    pub intensity: f32,
+    /// Distance from the center, where 1 is the corners, that darkening starts.
+    pub radius: f32,
+    /// Distance over which it fades to full intensity.
+    pub smoothness: f32,
+
+    pipeline: wgpu::RenderPipeline,
+    params: EffectParams,
+}
+
+impl PostProcess for Vignette {
+    fn new(core: &RendererCore, shared: &mut SharedRenderResources) -> Self {
+        let params = EffectParams::new(core.device(), "Vignette Params");
+        let pipeline = effect_pipeline(
+            core,
+            "Vignette Pipeline",
+            &[
+                shared.texture_bind_group_layout(),
+                params.bind_group_layout.layout(),
+            ],
+            "fs_vignette",
+        );
+
+        Self {
+            enabled: true,
+            intensity: 0.4,
+            radius: 0.5,
+            smoothness: 0.5,
+            pipeline,
+            params,
+        }
+    }
+
+    #[inline]
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn apply(
+        &mut self,
+        core: &RendererCore,
+        _shared: &mut SharedRenderResources,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &LoadedTexture,
+        output: &wgpu::TextureView,
+    ) {
+        self.params.write(
+            core.queue(),
+            glam::vec4(self.intensity, self.radius, self.smoothness, 0.),
+        );
+
+        post_process::fullscreen_pass(
+            encoder,
+            "Vignette Pass",
+            &self.pipeline,
+            &[input.bind_group(), &self.params.bind_group],
+            output,
+        );
+    }
+}
+
+//====================================================================
+
+/// Glow around parts of the scene brighter than `threshold`. Run before tonemapping so it
+/// can see hdr values.
+pub struct Bloom {
+    pub enabled: bool,
+    pub threshold: f32,
+    pub intensity: f32,
+    /// Blur spread in half resolution pixels.
+    pub radius: f32,
+
+    bright_pipeline: wgpu::RenderPipeline,
+    horizontal_pipeline: wgpu::RenderPipeline,
+    vertical_pipeline: wgpu::RenderPipeline,
+    combine_pipeline: wgpu::RenderPipeline,
+    params: EffectParams,
+
+    /// Half resolution textures blurred between, recreated when the input size changes.
+    targets: Option<(Size<u32>, [LoadedTexture; 2])>,
+}
+
+impl Bloom {
+    fn resize_targets(
+        &mut self,
+        core: &RendererCore,
+        shared: &SharedRenderResources,
+        size: Size<u32>,
+    ) {
+        if self.targets.as_ref().map(|(existing, _)| *existing) != Some(size) {
+            let half = Size::new((size.width / 2).max(1), (size.height / 2).max(1));
+
+            let targets = ["Bloom Target A", "Bloom Target B"].map(|label| {
+                let mut texture = Texture::create_render_texture(
+                    core.device(),
+                    half,
+                    post_process::HDR_FORMAT,
+                    label,
+                );
+                texture.sampler = core.device().create_sampler(&wgpu::SamplerDescriptor {
+                    mag_filter: wgpu::FilterMode::Linear,
+                    min_filter: wgpu::FilterMode::Linear,
+                    ..Default::default()
+                });
+
+                LoadedTexture::load_texture(core.device(), shared, texture)
+            });
+
+            self.targets = Some((size, targets));
+        }
+    }
+}
+
+impl PostProcess for Bloom {
+    fn new(core: &RendererCore, shared: &mut SharedRenderResources) -> Self {
+        let params = EffectParams::new(core.device(), "Bloom Params");
+        let layouts = [
+            shared.texture_bind_group_layout(),
+            params.bind_group_layout.layout(),
+        ];
+
+        let bright_pipeline =
+            effect_pipeline(core, "Bloom Bright Pipeline", &layouts, "fs_bloom_bright");
+        let horizontal_pipeline = effect_pipeline(
+            core,
+            "Bloom Horizontal Pipeline",
+            &layouts,
+            "fs_bloom_horizontal",
+        );
+        let vertical_pipeline = effect_pipeline(
+            core,
+            "Bloom Vertical Pipeline",
+            &layouts,
+            "fs_bloom_vertical",
+        );
+        let combine_pipeline = effect_pipeline(
+            core,
+            "Bloom Combine Pipeline",
+            &[
+                shared.texture_bind_group_layout(),
+                params.bind_group_layout.layout(),
+                shared.texture_bind_group_layout(),
+            ],
+            "fs_bloom_combine",
+        );
+
+        Self {
+            enabled: true,
+            threshold: 1.,
+            intensity: 0.6,
+            radius: 1.5,
+            bright_pipeline,
+            horizontal_pipeline,
+            vertical_pipeline,
+            combine_pipeline,
+            params,
+            targets: None,
+        }
+    }
+
+    #[inline]
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn apply(
+        &mut self,
+        core: &RendererCore,
+        shared: &mut SharedRenderResources,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &LoadedTexture,
+        output: &wgpu::TextureView,
+    ) {
+        self.params.write(
+            core.queue(),
+            glam::vec4(self.threshold, self.intensity, self.radius, 0.),
+        );
+
+        let size = input.texture().texture.size();
+        self.resize_targets(core, shared, Size::new(size.width, size.height));
+
+        let [a, b] = &self.targets.as_ref().unwrap().1;
+        let params = &self.params.bind_group;
+
+        post_process::fullscreen_pass(
+            encoder,
+            "Bloom Bright Pass",
+            &self.bright_pipeline,
+            &[input.bind_group(), params],
+            &a.texture().view,
+        );
+        post_process::fullscreen_pass(
+            encoder,
+            "Bloom Horizontal Pass",
+            &self.horizontal_pipeline,
+            &[a.bind_group(), params],
+            &b.texture().view,
+        );
+        post_process::fullscreen_pass(
+            encoder,
+            "Bloom Vertical Pass",
+            &self.vertical_pipeline,
+            &[b.bind_group(), params],
+            &a.texture().view,
+        );
+        post_process::fullscreen_pass(
+            encoder,
+            "Bloom Combine Pass",
+            &self.combine_pipeline,
+            &[input.bind_group(), params, a.bind_group()],
+            output,
+        );
+    }
+}
+
+//====================================================================
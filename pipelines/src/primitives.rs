@@ -0,0 +1,379 @@
+//====================================================================
+
+use std::{collections::BTreeMap, f32::consts::TAU};
+
+use renderer::shared::ModelVertex;
+
+use crate::{model_loader::MeshData, model_renderer::Mesh};
+
+//====================================================================
+
+// Procedurally generated meshes centered on the origin with +Y up. Winding matches
+// `renderer::shared::CUBE_INDICES` so they work with back face culling.
+
+impl MeshData {
+    /// Upload to the gpu as a single mesh.
+    #[inline]
+    pub fn to_mesh(&self, device: &wgpu::Device) -> Mesh {
+        Mesh::load_mesh(device, &self.vertices, &self.indices)
+    }
+
+    /// Add another mesh's vertices and indices to this one.
+    pub fn append(&mut self, other: MeshData) {
+        let offset = self.vertices.len() as u32;
+        self.vertices.extend(other.vertices);
+        self.indices
+            .extend(other.indices.into_iter().map(|index| index + offset));
+    }
+}
+
+/// Indices for a grid of `rows + 1` by `columns + 1` vertices laid out row by row.
+fn grid_indices(columns: u32, rows: u32) -> Vec<u32> {
+    let stride = columns + 1;
+
+    (0..rows)
+        .flat_map(|row| {
+            (0..columns).flat_map(move |column| {
+                let top_left = row * stride + column;
+                let top_right = top_left + 1;
+                let bottom_left = top_left + stride;
+                let bottom_right = bottom_left + 1;
+
+                [
+                    top_left,
+                    bottom_right,
+                    bottom_left,
+                    top_left,
+                    top_right,
+                    bottom_right,
+                ]
+            })
+        })
+        .collect()
+}
+
+//--------------------------------------------------
+
+/// Point on a profile revolved around the Y axis.
+struct ProfilePoint {
+    radius: f32,
+    y: f32,
+    /// Outward normal as (radial, y).
+    normal: glam::Vec2,
+    v: f32,
+}
+
+/// Sweep a profile (top to bottom) around the Y axis.
+fn revolve(profile: &[ProfilePoint], segments: u32) -> MeshData {
+    let segments = segments.max(3);
+
+    let vertices = profile
+        .iter()
+        .flat_map(|point| {
+            (0..=segments).map(move |segment| {
+                let u = segment as f32 / segments as f32;
+                let (sin, cos) = (u * TAU).sin_cos();
+                let direction = glam::vec3(cos, 0., -sin);
+
+                ModelVertex::new(
+                    direction * point.radius + glam::Vec3::Y * point.y,
+                    glam::vec2(u, point.v),
+                    (direction * point.normal.x + glam::Vec3::Y * point.normal.y)
+                        .normalize_or_zero(),
+                )
+            })
+        })
+        .collect();
+
+    MeshData {
+        vertices,
+        indices: grid_indices(segments, profile.len().saturating_sub(1) as u32),
+        ..Default::default()
+    }
+}
+
+/// Flat disk facing up or down.
+fn disk(radius: f32, y: f32, segments: u32, up: bool) -> MeshData {
+    let segments = segments.max(3);
+    let normal = match up {
+        true => glam::Vec3::Y,
+        false => glam::Vec3::NEG_Y,
+    };
+
+    let vertices = std::iter::once(ModelVertex::new(
+        glam::vec3(0., y, 0.),
+        glam::vec2(0.5, 0.5),
+        normal,
+    ))
+    .chain((0..=segments).map(|segment| {
+        let (sin, cos) = (segment as f32 / segments as f32 * TAU).sin_cos();
+        ModelVertex::new(
+            glam::vec3(cos * radius, y, -sin * radius),
+            glam::vec2(0.5 + cos * 0.5, 0.5 - sin * 0.5),
+            normal,
+        )
+    }))
+    .collect();
+
+    let indices = (1..=segments)
+        .flat_map(|segment| match up {
+            true => [0, segment + 1, segment],
+            false => [0, segment, segment + 1],
+        })
+        .collect();
+
+    MeshData {
+        vertices,
+        indices,
+        ..Default::default()
+    }
+}
+
+//====================================================================
+
+/// Plane on the XZ axes facing +Y, split into `subdivisions` quads along each axis.
+pub fn plane(size: glam::Vec2, subdivisions: glam::UVec2) -> MeshData {
+    let subdivisions = subdivisions.max(glam::UVec2::ONE);
+
+    let vertices = (0..=subdivisions.y)
+        .flat_map(|row| {
+            (0..=subdivisions.x).map(move |column| {
+                let uv = glam::uvec2(column, row).as_vec2() / subdivisions.as_vec2();
+                let position = (uv - 0.5) * size;
+
+                ModelVertex::new(glam::vec3(position.x, 0., position.y), uv, glam::Vec3::Y)
+            })
+        })
+        .collect();
+
+    MeshData {
+        vertices,
+        indices: grid_indices(subdivisions.x, subdivisions.y),
+        ..Default::default()
+    }
+}
+
+/// Sphere made of latitude rings and longitude segments.
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> MeshData {
+    let rings = rings.max(2);
+
+    let profile = (0..=rings)
+        .map(|ring| {
+            let v = ring as f32 / rings as f32;
+            let (sin, cos) = (v * TAU / 2.).sin_cos();
+
+            ProfilePoint {
+                radius: sin * radius,
+                y: cos * radius,
+                normal: glam::vec2(sin, cos),
+                v,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    revolve(&profile, segments)
+}
+
+/// Sphere made of evenly sized triangles by subdividing an icosahedron. Uvs are
+/// spherically mapped, so textures will show a seam.
+pub fn ico_sphere(radius: f32, subdivisions: u32) -> MeshData {
+    let t = (1. + 5f32.sqrt()) / 2.;
+
+    let mut positions = [
+        (-1., t, 0.),
+        (1., t, 0.),
+        (-1., -t, 0.),
+        (1., -t, 0.),
+        (0., -1., t),
+        (0., 1., t),
+        (0., -1., -t),
+        (0., 1., -t),
+        (t, 0., -1.),
+        (t, 0., 1.),
+        (-t, 0., -1.),
+        (-t, 0., 1.),
+    ]
+    .map(|position| glam::Vec3::from(position).normalize())
+    .to_vec();
+
+    let mut faces: Vec<[u32; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    (0..subdivisions).for_each(|_| {
+        let mut midpoints = BTreeMap::new();
+        let mut midpoint = |a: u32, b: u32| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let position = (positions[a as usize] + positions[b as usize]).normalize();
+                positions.push(position);
+                positions.len() as u32 - 1
+            })
+        };
+
+        faces = faces
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    });
+
+    let vertices = positions
+        .iter()
+        .map(|normal| {
+            let uv = glam::vec2(
+                0.5 + (-normal.z).atan2(normal.x) / TAU,
+                0.5 - normal.y.asin() / (TAU / 2.),
+            );
+            ModelVertex::new(*normal * radius, uv, *normal)
+        })
+        .collect();
+
+    // Faces above are wound counter clockwise from outside, the opposite of the engine
+    let indices = faces.into_iter().flat_map(|[a, b, c]| [a, c, b]).collect();
+
+    MeshData {
+        vertices,
+        indices,
+        ..Default::default()
+    }
+}
+
+/// Cylinder along the Y axis with capped ends.
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> MeshData {
+    let half = height / 2.;
+
+    let mut mesh = revolve(
+        &[
+            ProfilePoint {
+                radius,
+                y: half,
+                normal: glam::Vec2::X,
+                v: 0.,
+            },
+            ProfilePoint {
+                radius,
+                y: -half,
+                normal: glam::Vec2::X,
+                v: 1.,
+            },
+        ],
+        segments,
+    );
+
+    mesh.append(disk(radius, half, segments, true));
+    mesh.append(disk(radius, -half, segments, false));
+    mesh
+}
+
+/// Cone along the Y axis with its point at the top and a capped base.
+pub fn cone(radius: f32, height: f32, segments: u32) -> MeshData {
+    let half = height / 2.;
+    let normal = glam::vec2(height, radius).normalize_or_zero();
+
+    let mut mesh = revolve(
+        &[
+            ProfilePoint {
+                radius: 0.,
+                y: half,
+                normal,
+                v: 0.,
+            },
+            ProfilePoint {
+                radius,
+                y: -half,
+                normal,
+                v: 1.,
+            },
+        ],
+        segments,
+    );
+
+    mesh.append(disk(radius, -half, segments, false));
+    mesh
+}
+
+/// Cylinder along the Y axis with hemispheres on each end. `height` is the total height,
+/// including the hemispheres.
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> MeshData {
+    let rings = rings.max(1);
+    let half = (height / 2. - radius).max(0.);
+
+    // One hemisphere each side of the body, duplicating the ring where they meet the body
+    let hemisphere = |top: bool| {
+        (0..=rings).map(move |ring| {
+            let angle = ring as f32 / rings as f32 * TAU / 4.;
+            let (sin, cos) = match top {
+                true => angle.sin_cos(),
+                false => (angle + TAU / 4.).sin_cos(),
+            };
+            let offset = if top { half } else { -half };
+
+            (sin, cos, offset)
+        })
+    };
+
+    let points = hemisphere(true)
+        .chain(hemisphere(false))
+        .collect::<Vec<_>>();
+    let total = height.max(f32::EPSILON);
+
+    let profile = points
+        .into_iter()
+        .map(|(sin, cos, offset)| {
+            let y = cos * radius + offset;
+            ProfilePoint {
+                radius: sin * radius,
+                y,
+                normal: glam::vec2(sin, cos),
+                v: 0.5 - y / total,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    revolve(&profile, segments)
+}
+
+/// Torus around the Y axis. `radius` is to the center of the tube.
+pub fn torus(radius: f32, tube_radius: f32, segments: u32, sides: u32) -> MeshData {
+    let sides = sides.max(3);
+
+    let profile = (0..=sides)
+        .map(|side| {
+            let v = side as f32 / sides as f32;
+            let (sin, cos) = (v * TAU).sin_cos();
+
+            // Start at the top of the tube and go around the outside first
+            ProfilePoint {
+                radius: radius + sin * tube_radius,
+                y: cos * tube_radius,
+                normal: glam::vec2(sin, cos),
+                v,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    revolve(&profile, segments)
+}
+
+//====================================================================
@@ -1,35 +1,53 @@
 //====================================================================
 // Uniforms
 
-struct Camera {
-    projection: mat4x4<f32>,
-    position: vec3<f32>,
+#import renderer::camera
+#import renderer::globals
+#import renderer::lighting
+#import renderer::debug
+#import pipelines::joints
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+@group(0) @binding(1) var<uniform> globals: Globals;
+
+struct Material {
+    base_color: vec4<f32>,
+    emissive: vec4<f32>,
+    metallic: f32,
+    roughness: f32,
+    normal_map: u32, // 1 when the normal map is used
+    _pad: u32,
 }
 
-// struct GlobalLightData {
-//     ambient_color: vec3<f32>,
-//     ambient_strength: f32,
-// }
+// Maps a material doesn't have are white
+@group(1) @binding(0) var<uniform> material: Material;
+@group(1) @binding(1) var base_color_texture: texture_2d<f32>;
+@group(1) @binding(2) var texture_sampler: sampler;
+@group(1) @binding(3) var normal_map: texture_2d<f32>;
+@group(1) @binding(4) var metallic_roughness_map: texture_2d<f32>; // Roughness in g, metallic in b
+@group(1) @binding(5) var emissive_map: texture_2d<f32>;
 
-// struct LightData {
-//     light_count: i32,
-// }
+@group(2) @binding(0) var<uniform> debug_override: DebugOverride;
 
-// struct Light {
-//     position: vec4<f32>,
-//     direction: vec4<f32>,
-//     diffuse_color: vec4<f32>,
-//     specular_color: vec4<f32>,
-// }
+struct ReflectionProbe {
+    center: vec4<f32>, // Blend distance in w
+    half_extents: vec4<f32>, // 1 in w when used
+}
 
-@group(0) @binding(0) var<uniform> camera: Camera;
+// Innermost probe first
+@group(2) @binding(1) var<uniform> probes: array<ReflectionProbe, 2>;
+@group(2) @binding(2) var probe_0: texture_cube<f32>;
+@group(2) @binding(3) var probe_1: texture_cube<f32>;
+@group(2) @binding(4) var probe_sampler: sampler;
 
-// @group(1) @binding(0) var<uniform> global_lighting: GlobalLightData;
-// @group(1) @binding(1) var<uniform> light_data: LightData;
-// @group(1) @binding(2) var<storage, read> light_array: array<Light>;
+@group(3) @binding(0) var<uniform> lights: Lights;
+@group(3) @binding(1) var<uniform> shadow: Shadow;
+@group(3) @binding(2) var shadow_map: texture_depth_2d;
+@group(3) @binding(3) var shadow_sampler: sampler_comparison;
 
-@group(1) @binding(0) var texture: texture_2d<f32>;
-@group(1) @binding(1) var texture_sampler: sampler;
+// Lightmapped pipeline only, bound in place of the lights
+@group(3) @binding(0) var lightmap: texture_2d<f32>;
+@group(3) @binding(1) var lightmap_sampler: sampler;
 
 
 //====================================================================
@@ -39,6 +57,9 @@ struct VertexIn {
     @location(0) vertex_position: vec3<f32>,
     @location(1) uv: vec2<f32>,
     @location(2) normal: vec3<f32>,
+    @location(15) lightmap_uv: vec2<f32>,
+    @location(8) joints: vec4<u32>,
+    @location(9) weights: vec4<f32>,
 
     // Instance
     @location(3) transform_1: vec4<f32>,
@@ -48,11 +69,11 @@ struct VertexIn {
 
     @location(7) color: vec4<f32>,
 
-    @location(8) normal_0: vec3<f32>,
-    @location(9) normal_1: vec3<f32>,
-    @location(10) normal_2: vec3<f32>,
+    @location(11) scale: vec4<f32>, // Reflectivity in w
 
-    @location(11) scale: vec3<f32>,
+    @location(12) uv_transform: vec4<f32>, // Offset xy, scale zw
+    @location(13) ids: vec2<u32>, // Entity id, first joint matrix
+    @location(14) animation: vec4<f32>,
 }
 
 struct VertexOut {
@@ -61,6 +82,33 @@ struct VertexOut {
     @location(1) uv: vec2<f32>,
     @location(2) normal: vec3<f32>,
     @location(3) color: vec4<f32>,
+    @location(4) @interpolate(flat) entity_id: u32,
+    @location(5) lightmap_uv: vec2<f32>,
+    @location(6) reflectivity: f32,
+}
+
+//====================================================================
+
+const TAU: f32 = 6.2831853;
+
+// Uv scroll xy, color pulse frequency and amplitude
+fn animate_uv(uv: vec2<f32>, animation: vec4<f32>) -> vec2<f32> {
+    return uv + fract(animation.xy * globals.time);
+}
+
+fn animate_color(color: vec4<f32>, animation: vec4<f32>) -> vec4<f32> {
+    let pulse = 1. + animation.w * sin(globals.time * animation.z * TAU);
+    return vec4<f32>(color.rgb * pulse, color.a);
+}
+
+// Unskinned instances have no joint matrices
+const NO_JOINTS: u32 = 0xffffffffu;
+
+fn skin_matrix(joints: vec4<u32>, weights: vec4<f32>, offset: u32) -> mat4x4<f32> {
+    return joint_matrices[offset + joints.x] * weights.x
+        + joint_matrices[offset + joints.y] * weights.y
+        + joint_matrices[offset + joints.z] * weights.z
+        + joint_matrices[offset + joints.w] * weights.w;
 }
 
 //====================================================================
@@ -76,13 +124,24 @@ fn vs_main(in: VertexIn) -> VertexOut {
         in.transform_4,
     );
 
+    // Rotation of the transform, without its scale
     let normal_matrix = mat3x3<f32>(
-        in.normal_0,
-        in.normal_1,
-        in.normal_2,
+        normalize(in.transform_1.xyz),
+        normalize(in.transform_2.xyz),
+        normalize(in.transform_3.xyz),
     );
 
-    let vertex_position = in.vertex_position * in.scale;
+    var vertex_position = in.vertex_position;
+    var vertex_normal = in.normal;
+
+    let weight = dot(in.weights, vec4<f32>(1.));
+    if in.ids.y != NO_JOINTS && weight > 0. {
+        let skin = skin_matrix(in.joints, in.weights / weight, in.ids.y);
+        vertex_position = (skin * vec4<f32>(vertex_position, 1.)).xyz;
+        vertex_normal = (skin * vec4<f32>(vertex_normal, 0.)).xyz;
+    }
+
+    vertex_position = vertex_position * in.scale.xyz;
 
     let world_position = transform * vec4<f32>(vertex_position, 1.);
 
@@ -91,53 +150,233 @@ fn vs_main(in: VertexIn) -> VertexOut {
         * world_position;
 
     out.position = world_position.xyz;
-    out.uv = in.uv;
-    out.normal = normal_matrix * in.normal;
-    out.color = in.color;
+    out.uv = animate_uv(in.uv * in.uv_transform.zw + in.uv_transform.xy, in.animation);
+    out.normal = normal_matrix * vertex_normal;
+    out.color = animate_color(in.color, in.animation);
+    out.entity_id = in.ids.x;
+    out.lightmap_uv = in.lightmap_uv;
+    out.reflectivity = in.scale.w;
 
     return out;
 }
 
+// Depth only, from the shadow casting light
+@vertex
+fn vs_shadow(in: VertexIn) -> @builtin(position) vec4<f32> {
+    let transform = mat4x4<f32>(
+        in.transform_1,
+        in.transform_2,
+        in.transform_3,
+        in.transform_4,
+    );
+
+    var vertex_position = in.vertex_position;
+
+    let weight = dot(in.weights, vec4<f32>(1.));
+    if in.ids.y != NO_JOINTS && weight > 0. {
+        let skin = skin_matrix(in.joints, in.weights / weight, in.ids.y);
+        vertex_position = (skin * vec4<f32>(vertex_position, 1.)).xyz;
+    }
+
+    return camera.projection * transform * vec4<f32>(vertex_position * in.scale.xyz, 1.);
+}
+
 //====================================================================
 
-// const DEFAULT_MATERIAL_SHININESS: f32 = 32.;
+const SPECULAR_STRENGTH: f32 = 0.25;
 
-@fragment
-fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+// Material after sampling its maps
+struct Surface {
+    albedo: vec4<f32>,
+    normal: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+    emissive: vec3<f32>,
+}
 
-    // let ambient = vec3<f32>(global_lighting.ambient_strength * global_lighting.ambient_color);
+fn light_contribution(surface: Surface, view_dir: vec3<f32>, light_dir: vec3<f32>, color: vec3<f32>) -> vec3<f32> {
+    // Metals have no diffuse and tint their highlights, rough surfaces spread them out.
+    // A roughness of 0.5 gives a shininess of 32.
+    let diffuse = max(dot(surface.normal, light_dir), 0.) * (1. - surface.metallic) * surface.albedo.rgb;
 
-    // // let light_count = bitcast<i32>(arrayLength(&light_array));
-    // let light_count = light_data.light_count;
+    let shininess = exp2(10. * (1. - surface.roughness));
+    let half_dir = normalize(view_dir + light_dir);
+    let specular_color = mix(vec3<f32>(SPECULAR_STRENGTH), surface.albedo.rgb, surface.metallic);
+    let specular = pow(max(dot(surface.normal, half_dir), 0.), shininess) * specular_color;
 
-    // var sum_diffuse = vec3<f32>();
-    // var sum_specular = vec3<f32>();
+    return color * (diffuse + specular);
+}
 
-    // for (var i = 0; i < light_count; i += 1) {
+// 0 in shadow to 1 lit, filtered over 3x3 texels to soften the edges
+fn shadow_factor(position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    let clip = shadow.view_projection * vec4<f32>(position + normal * shadow.normal_bias, 1.);
+    let ndc = clip.xyz / clip.w;
+    let uv = ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+
+    // Nothing outside the shadow map is shadowed
+    if any(uv < vec2<f32>(0.)) || any(uv > vec2<f32>(1.)) || ndc.z > 1. {
+        return 1.;
+    }
+
+    var lit = 0.;
+    for (var x = -1; x <= 1; x += 1) {
+        for (var y = -1; y <= 1; y += 1) {
+            let offset = vec2<f32>(f32(x), f32(y)) * shadow.texel_size;
+            lit += textureSampleCompareLevel(shadow_map, shadow_sampler, uv + offset, ndc.z - shadow.bias);
+        }
+    }
+
+    return lit / 9.;
+}
 
-    //     // Calculate Diffuse Color
-    //     let norm = normalize(in.normal);
-    //     let light_dir = normalize(light_array[i].position.xyz - in.position);
+fn lighting(position: vec3<f32>, surface: Surface) -> vec3<f32> {
+    // Scenes without any lights are left unlit
+    if lights.directional_count == 0u && lights.point_count == 0u {
+        return surface.albedo.rgb + surface.emissive;
+    }
 
-    //     let diffuse_strength = max(dot(norm, light_dir), 0.0);
-    //     sum_diffuse += light_array[i].diffuse_color.xyz * diffuse_strength;
+    let view_dir = normalize(camera.position - position);
 
-    //     // Specular
-    //     let view_dir = normalize(camera.position - in.position);
-    //     let half_dir = normalize(view_dir + light_dir);
-    //     let specular_strength = pow(max(dot(norm, half_dir), 0.0), DEFAULT_MATERIAL_SHININESS);
-    //     sum_specular += light_array[i].specular_color.xyz * specular_strength;
-    // }
+    var sum = lights.ambient.rgb * surface.albedo.rgb + surface.emissive;
 
-    // let result = (
-    //     ambient
-    //     + sum_diffuse
-    //     + sum_specular
-    // ) * textureSample(texture, texture_sampler, in.uv).xyz;
-    
-    // return vec4(result, 1.0) * in.color;
+    for (var i = 0u; i < lights.directional_count; i += 1u) {
+        let light = lights.directional[i];
+        var contribution = light_contribution(surface, view_dir, -light.direction.xyz, light.color.rgb);
+
+        if i == shadow.light {
+            contribution *= shadow_factor(position, surface.normal);
+        }
+
+        sum += contribution;
+    }
+
+    for (var i = 0u; i < lights.point_count; i += 1u) {
+        let light = lights.point[i];
+        let to_light = light.position_range.xyz - position;
+        let distance = length(to_light);
+
+        // Smooth falloff reaching zero at the light's range
+        let range = max(light.position_range.w, 0.0001);
+        let falloff = clamp(1. - pow(distance / range, 4.), 0., 1.);
+        let attenuation = falloff * falloff / (distance * distance + 1.);
+
+        sum += light_contribution(surface, view_dir, to_light / max(distance, 0.0001), light.color.rgb) * attenuation;
+    }
+
+    return sum;
+}
+
+// Point on the probe's box hit by the reflection, relative to the probe, so nearby objects
+// line up with their reflections.
+fn box_project(position: vec3<f32>, direction: vec3<f32>, probe: ReflectionProbe) -> vec3<f32> {
+    let box_min = probe.center.xyz - probe.half_extents.xyz;
+    let box_max = probe.center.xyz + probe.half_extents.xyz;
+
+    let first = (box_max - position) / direction;
+    let second = (box_min - position) / direction;
+    let furthest = max(first, second);
+    let distance = min(furthest.x, min(furthest.y, furthest.z));
+
+    return position + direction * distance - probe.center.xyz;
+}
+
+// 1 inside the probe's box, fading to 0 over the blend distance at its edges
+fn probe_weight(position: vec3<f32>, probe: ReflectionProbe) -> f32 {
+    let inside = probe.half_extents.xyz - abs(position - probe.center.xyz);
+    let edge = min(inside.x, min(inside.y, inside.z));
+
+    return clamp(edge / max(probe.center.w, 0.0001), 0., 1.) * probe.half_extents.w;
+}
+
+fn reflect_probes(color: vec3<f32>, position: vec3<f32>, normal: vec3<f32>, reflectivity: f32) -> vec3<f32> {
+    let direction = reflect(normalize(position - camera.position), normalize(normal));
+
+    // Sampled outside of any branches so derivatives stay valid
+    let sample_0 = textureSampleLevel(probe_0, probe_sampler, box_project(position, direction, probes[0]), 0.).rgb;
+    let sample_1 = textureSampleLevel(probe_1, probe_sampler, box_project(position, direction, probes[1]), 0.).rgb;
+
+    // The inner probe takes what it covers, the outer fills in the rest
+    let weight_0 = probe_weight(position, probes[0]);
+    let weight_1 = min(probe_weight(position, probes[1]), 1. - weight_0);
+    let total = weight_0 + weight_1;
+
+    if total <= 0. {
+        return color;
+    }
+
+    let reflection = (sample_0 * weight_0 + sample_1 * weight_1) / total;
+    return mix(color, reflection, clamp(reflectivity, 0., 1.) * total);
+}
+
+fn debug_color(entity_id: u32, color: vec4<f32>) -> vec4<f32> {
+    if debug_override.mode == 1u && debug_override.palette_len > 0u {
+        // Scramble ids so neighbouring entities don't get neighbouring colors
+        let hash = (entity_id * 2654435761u) >> 16u;
+        return debug_override.palette[hash % debug_override.palette_len];
+    }
+
+    return color;
+}
+
+// Bends the vertex normal by a tangent space normal, with the tangents worked out from screen
+// space derivatives so meshes don't need them.
+fn perturb_normal(normal: vec3<f32>, position: vec3<f32>, uv: vec2<f32>, sampled: vec3<f32>) -> vec3<f32> {
+    let dp_dx = dpdx(position);
+    let dp_dy = dpdy(position);
+    let duv_dx = dpdx(uv);
+    let duv_dy = dpdy(uv);
+
+    let dp_dy_perp = cross(dp_dy, normal);
+    let dp_dx_perp = cross(normal, dp_dx);
+    let tangent = dp_dy_perp * duv_dx.x + dp_dx_perp * duv_dy.x;
+    let bitangent = dp_dy_perp * duv_dx.y + dp_dx_perp * duv_dy.y;
+
+    let scale = inverseSqrt(max(dot(tangent, tangent), dot(bitangent, bitangent)));
+    let tangent_normal = sampled * 2. - 1.;
+
+    return normalize(mat3x3<f32>(tangent * scale, bitangent * scale, normal) * tangent_normal);
+}
+
+fn surface(in: VertexOut) -> Surface {
+    // Sampled outside of any branches so derivatives stay valid
+    let base_color = textureSample(base_color_texture, texture_sampler, in.uv);
+    let normal_sample = textureSample(normal_map, texture_sampler, in.uv).rgb;
+    let metallic_roughness = textureSample(metallic_roughness_map, texture_sampler, in.uv);
+    let emissive = textureSample(emissive_map, texture_sampler, in.uv).rgb;
+
+    let vertex_normal = normalize(in.normal);
+    let mapped_normal = perturb_normal(vertex_normal, in.position, in.uv, normal_sample);
+
+    var out: Surface;
+    out.albedo = in.color * material.base_color * base_color;
+    out.normal = select(vertex_normal, mapped_normal, material.normal_map == 1u);
+    out.metallic = clamp(material.metallic * metallic_roughness.b, 0., 1.);
+    out.roughness = clamp(material.roughness * metallic_roughness.g, 0., 1.);
+    out.emissive = material.emissive.rgb * emissive;
+
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    let surface = surface(in);
+    let lit = lighting(in.position, surface);
+    let color = vec4<f32>(reflect_probes(lit, in.position, surface.normal, in.reflectivity), surface.albedo.a);
+
+    return debug_color(in.entity_id, color);
+}
+
+// Must match LIGHTMAP_RANGE in lightmap.rs
+const LIGHTMAP_RANGE: f32 = 2.;
+
+@fragment
+fn fs_lightmapped(in: VertexOut) -> @location(0) vec4<f32> {
+    let surface = surface(in);
+    let light = textureSample(lightmap, lightmap_sampler, in.lightmap_uv).rgb * LIGHTMAP_RANGE;
+    let lit = surface.albedo.rgb * light + surface.emissive;
+    let color = vec4<f32>(reflect_probes(lit, in.position, surface.normal, in.reflectivity), surface.albedo.a);
 
-    return in.color * textureSample(texture, texture_sampler, in.uv);
+    return debug_color(in.entity_id, color);
 }
 
 //====================================================================
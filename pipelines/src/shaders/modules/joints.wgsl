@@ -0,0 +1,6 @@
+//====================================================================
+// Joint matrices of every skinned model instance drawn this frame
+
+@group(2) @binding(5) var<storage, read> joint_matrices: array<mat4x4<f32>>;
+
+//====================================================================
@@ -0,0 +1,7 @@
+//====================================================================
+// Joint matrices of every skinned model instance drawn this frame, for targets without
+// storage buffers in vertex shaders. Must match MAX_UNIFORM_JOINTS in model_renderer.rs
+
+@group(2) @binding(5) var<uniform> joint_matrices: array<mat4x4<f32>, 256>;
+
+//====================================================================
@@ -0,0 +1,117 @@
+//====================================================================
+// Uniforms
+
+#import renderer::camera
+#import renderer::debug
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+@group(1) @binding(0) var texture: texture_2d<f32>;
+@group(1) @binding(1) var texture_sampler: sampler;
+
+@group(2) @binding(0) var<uniform> debug_override: DebugOverride;
+
+// Soft particles only
+struct DepthParams {
+    z_near: f32,
+    z_far: f32,
+}
+
+@group(3) @binding(0) var scene_depth: texture_depth_2d;
+@group(3) @binding(1) var<uniform> depth_params: DepthParams;
+
+
+//====================================================================
+
+struct VertexIn {
+    // Vertex
+    @location(0) vertex_position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+
+    // Instance
+    @location(2) position: vec4<f32>, // Position xyz, size w
+    @location(3) color: vec4<f32>,
+    @location(4) rotation: f32,
+    @location(5) entity_id: u32,
+    @location(6) fade_distance: f32,
+}
+
+struct VertexOut {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) @interpolate(flat) entity_id: u32,
+    @location(3) fade_distance: f32,
+}
+
+//====================================================================
+
+@vertex
+fn vs_main(in: VertexIn) -> VertexOut {
+    var out: VertexOut;
+
+    // Face the camera
+    let forward = normalize(in.position.xyz - camera.position);
+    var right = cross(vec3<f32>(0., 1., 0.), forward);
+    if length(right) < 0.0001 {
+        right = vec3<f32>(1., 0., 0.);
+    }
+    right = normalize(right);
+    let up = cross(forward, right);
+
+    let c = cos(in.rotation);
+    let s = sin(in.rotation);
+    let corner = vec2<f32>(
+        in.vertex_position.x * c - in.vertex_position.y * s,
+        in.vertex_position.x * s + in.vertex_position.y * c,
+    ) * in.position.w;
+
+    let world_position = in.position.xyz + right * corner.x + up * corner.y;
+
+    out.clip_position = camera.projection * vec4<f32>(world_position, 1.);
+    out.uv = in.uv;
+    out.color = in.color;
+    out.entity_id = in.entity_id;
+    out.fade_distance = in.fade_distance;
+
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    let color = textureSample(texture, texture_sampler, in.uv) * in.color;
+
+    return apply_debug_override(color, in.entity_id);
+}
+
+fn apply_debug_override(color: vec4<f32>, entity_id: u32) -> vec4<f32> {
+    if debug_override.mode == 1u && debug_override.palette_len > 0u {
+        let hash = (entity_id * 2654435761u) >> 16u;
+        return debug_override.palette[hash % debug_override.palette_len];
+    }
+
+    return color;
+}
+
+fn linear_depth(depth: f32) -> f32 {
+    return depth_params.z_near * depth_params.z_far
+        / (depth_params.z_far - depth * (depth_params.z_far - depth_params.z_near));
+}
+
+@fragment
+fn fs_soft(in: VertexOut) -> @location(0) vec4<f32> {
+    var color = apply_debug_override(
+        textureSample(texture, texture_sampler, in.uv) * in.color,
+        in.entity_id,
+    );
+
+    if in.fade_distance > 0. {
+        let scene = textureLoad(scene_depth, vec2<i32>(in.clip_position.xy), 0);
+        let difference = linear_depth(scene) - linear_depth(in.clip_position.z);
+        color.a *= clamp(difference / in.fade_distance, 0., 1.);
+    }
+
+    return color;
+}
+
+//====================================================================
@@ -0,0 +1,166 @@
+//====================================================================
+// Uniforms
+
+@group(0) @binding(0) var input: texture_2d<f32>;
+@group(0) @binding(1) var input_sampler: sampler;
+
+// Meaning depends on the effect
+@group(1) @binding(0) var<uniform> params: vec4<f32>;
+
+// Bloom combine only
+@group(2) @binding(0) var bloom: texture_2d<f32>;
+@group(2) @binding(1) var bloom_sampler: sampler;
+
+//====================================================================
+
+struct VertexOut {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// Single triangle covering the whole target
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOut {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOut;
+    out.clip_position = vec4<f32>(uv.x * 2. - 1., 1. - uv.y * 2., 0., 1.);
+    out.uv = uv;
+
+    return out;
+}
+
+fn luma(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+//====================================================================
+// Tonemap - exposure x, operator y (0 reinhard, 1 aces)
+
+@fragment
+fn fs_tonemap(in: VertexOut) -> @location(0) vec4<f32> {
+    let sample = textureSample(input, input_sampler, in.uv);
+    let color = max(sample.rgb * params.x, vec3<f32>(0.));
+
+    var mapped = color / (color + vec3<f32>(1.));
+
+    if params.y > 0.5 {
+        // Narkowicz's fit of the ACES filmic curve
+        mapped = clamp(
+            (color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14),
+            vec3<f32>(0.),
+            vec3<f32>(1.),
+        );
+    }
+
+    return vec4<f32>(mapped, sample.a);
+}
+
+//====================================================================
+// Fxaa - span max x, reduce mul y, reduce min z
+
+@fragment
+fn fs_fxaa(in: VertexOut) -> @location(0) vec4<f32> {
+    let texel = 1. / vec2<f32>(textureDimensions(input));
+
+    let middle = textureSample(input, input_sampler, in.uv);
+    let north_west = textureSample(input, input_sampler, in.uv + vec2<f32>(-1., -1.) * texel).rgb;
+    let north_east = textureSample(input, input_sampler, in.uv + vec2<f32>(1., -1.) * texel).rgb;
+    let south_west = textureSample(input, input_sampler, in.uv + vec2<f32>(-1., 1.) * texel).rgb;
+    let south_east = textureSample(input, input_sampler, in.uv + vec2<f32>(1., 1.) * texel).rgb;
+
+    let luma_nw = luma(north_west);
+    let luma_ne = luma(north_east);
+    let luma_sw = luma(south_west);
+    let luma_se = luma(south_east);
+    let luma_m = luma(middle.rgb);
+
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // Blur along the edge, perpendicular to the luma gradient
+    var direction = vec2<f32>(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+
+    let reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * params.y, params.z);
+    let scale = 1. / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, vec2<f32>(-params.x), vec2<f32>(params.x)) * texel;
+
+    let color_a = 0.5 * (
+        textureSample(input, input_sampler, in.uv + direction * (1. / 3. - 0.5)).rgb
+        + textureSample(input, input_sampler, in.uv + direction * (2. / 3. - 0.5)).rgb
+    );
+    let color_b = color_a * 0.5 + 0.25 * (
+        textureSample(input, input_sampler, in.uv + direction * -0.5).rgb
+        + textureSample(input, input_sampler, in.uv + direction * 0.5).rgb
+    );
+
+    let luma_b = luma(color_b);
+    let color = select(color_b, color_a, luma_b < luma_min || luma_b > luma_max);
+
+    return vec4<f32>(color, middle.a);
+}
+
+//====================================================================
+// Vignette - intensity x, radius y, smoothness z
+
+@fragment
+fn fs_vignette(in: VertexOut) -> @location(0) vec4<f32> {
+    let sample = textureSample(input, input_sampler, in.uv);
+
+    let distance = length(in.uv - vec2<f32>(0.5)) * 1.41421356;
+    let falloff = smoothstep(params.y, params.y + max(params.z, 0.0001), distance);
+
+    return vec4<f32>(sample.rgb * (1. - falloff * params.x), sample.a);
+}
+
+//====================================================================
+// Bloom - threshold x, intensity y, radius z
+
+const BLUR_WEIGHTS = array<f32, 5>(0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+@fragment
+fn fs_bloom_bright(in: VertexOut) -> @location(0) vec4<f32> {
+    let color = textureSample(input, input_sampler, in.uv).rgb;
+
+    // Soft threshold so bright areas don't pop in
+    let brightness = max(color.r, max(color.g, color.b));
+    let contribution = max(brightness - params.x, 0.) / max(brightness, 0.0001);
+
+    return vec4<f32>(color * contribution, 1.);
+}
+
+fn blur(uv: vec2<f32>, direction: vec2<f32>) -> vec4<f32> {
+    let step = direction * params.z / vec2<f32>(textureDimensions(input));
+
+    var color = textureSample(input, input_sampler, uv).rgb * BLUR_WEIGHTS[0];
+    for (var i = 1; i < 5; i += 1) {
+        let offset = step * f32(i);
+        color += textureSample(input, input_sampler, uv + offset).rgb * BLUR_WEIGHTS[i];
+        color += textureSample(input, input_sampler, uv - offset).rgb * BLUR_WEIGHTS[i];
+    }
+
+    return vec4<f32>(color, 1.);
+}
+
+@fragment
+fn fs_bloom_horizontal(in: VertexOut) -> @location(0) vec4<f32> {
+    return blur(in.uv, vec2<f32>(1., 0.));
+}
+
+@fragment
+fn fs_bloom_vertical(in: VertexOut) -> @location(0) vec4<f32> {
+    return blur(in.uv, vec2<f32>(0., 1.));
+}
+
+@fragment
+fn fs_bloom_combine(in: VertexOut) -> @location(0) vec4<f32> {
+    let sample = textureSample(input, input_sampler, in.uv);
+    let glow = textureSample(bloom, bloom_sampler, in.uv).rgb;
+
+    return vec4<f32>(sample.rgb + glow * params.y, sample.a);
+}
+
+//====================================================================
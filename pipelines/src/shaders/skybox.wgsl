@@ -0,0 +1,45 @@
+//====================================================================
+// Uniforms
+
+#import renderer::camera
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+@group(1) @binding(0) var skybox: texture_cube<f32>;
+@group(1) @binding(1) var skybox_sampler: sampler;
+
+//====================================================================
+
+struct VertexIn {
+    @location(0) vertex_position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+}
+
+struct VertexOut {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) direction: vec3<f32>,
+}
+
+//====================================================================
+
+@vertex
+fn vs_main(in: VertexIn) -> VertexOut {
+    var out: VertexOut;
+
+    // Centered on the camera and pushed to the far plane
+    let position = camera.projection * vec4<f32>(camera.position + in.vertex_position, 1.);
+    out.clip_position = position.xyww;
+    out.direction = in.vertex_position;
+
+    return out;
+}
+
+//====================================================================
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    return textureSample(skybox, skybox_sampler, in.direction);
+}
+
+//====================================================================
@@ -1,10 +1,7 @@
 //====================================================================
 // Uniforms
 
-struct Camera {
-    projection: mat4x4<f32>,
-    position: vec3<f32>,
-}
+#import renderer::camera
 
 struct Position {
     transform: mat4x4<f32>,
@@ -84,7 +81,7 @@ fn vs_main(in: VertexIn) -> VertexOut {
     out.color = vec4<f32>(
         f32((in.color & 0x00ff0000u) >> 16u) / 255.,
         f32((in.color & 0x0000ff00u) >> 8u) / 255.,
-        f32(in.color & 0x00ff0000u) / 255.,
+        f32(in.color & 0x000000ffu) / 255.,
         f32((in.color & 0xff000000u) >> 24u) / 255.,
     );
 
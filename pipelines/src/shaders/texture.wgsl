@@ -1,16 +1,27 @@
 //====================================================================
 // Uniforms
 
-struct Camera {
-    projection: mat4x4<f32>,
-    position: vec3<f32>,
-}
+#import renderer::camera
+#import renderer::globals
+#import renderer::debug
 
 @group(0) @binding(0) var<uniform> camera: Camera;
+@group(0) @binding(1) var<uniform> globals: Globals;
 
 @group(1) @binding(0) var texture: texture_2d<f32>;
 @group(1) @binding(1) var texture_sampler: sampler;
 
+@group(2) @binding(0) var<uniform> debug_override: DebugOverride;
+
+// Soft sprites only
+struct DepthParams {
+    z_near: f32,
+    z_far: f32,
+}
+
+@group(3) @binding(0) var scene_depth: texture_depth_2d;
+@group(3) @binding(1) var<uniform> depth_params: DepthParams;
+
 
 //====================================================================
 
@@ -20,18 +31,37 @@ struct VertexIn {
     @location(1) uv: vec2<f32>,
 
     // Instance
-    @location(2) size: vec2<f32>,
+    @location(2) size: vec3<f32>, // Size xy, fade distance z
+    @location(9) entity_id: u32,
     @location(3) transform_1: vec4<f32>,
     @location(4) transform_2: vec4<f32>,
     @location(5) transform_3: vec4<f32>,
     @location(6) transform_4: vec4<f32>,
     @location(7) color: vec4<f32>,
+    @location(8) uv_transform: vec4<f32>, // Offset xy, scale zw
+    @location(10) animation: vec4<f32>,
 }
 
 struct VertexOut {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) uv: vec2<f32>,
     @location(1) color: vec4<f32>,
+    @location(2) fade_distance: f32,
+    @location(3) @interpolate(flat) entity_id: u32,
+}
+
+//====================================================================
+
+const TAU: f32 = 6.2831853;
+
+// Uv scroll xy, color pulse frequency and amplitude
+fn animate_uv(uv: vec2<f32>, animation: vec4<f32>) -> vec2<f32> {
+    return uv + fract(animation.xy * globals.time);
+}
+
+fn animate_color(color: vec4<f32>, animation: vec4<f32>) -> vec4<f32> {
+    let pulse = 1. + animation.w * sin(globals.time * animation.z * TAU);
+    return vec4<f32>(color.rgb * pulse, color.a);
 }
 
 //====================================================================
@@ -47,15 +77,17 @@ fn vs_main(in: VertexIn) -> VertexOut {
         in.transform_4,
     );
 
-    let vertex_pos = in.vertex_position * in.size;
+    let vertex_pos = in.vertex_position * in.size.xy;
 
     out.clip_position =
         camera.projection
         * transform
         * vec4<f32>(vertex_pos, 1., 1.);
 
-    out.uv = in.uv;
-    out.color = in.color;
+    out.uv = animate_uv(in.uv * in.uv_transform.zw + in.uv_transform.xy, in.animation);
+    out.color = animate_color(in.color, in.animation);
+    out.fade_distance = in.size.z;
+    out.entity_id = in.entity_id;
 
     return out;
 }
@@ -64,7 +96,38 @@ fn vs_main(in: VertexIn) -> VertexOut {
 fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
     let tex_color = textureSample(texture, texture_sampler, in.uv);
     
-    return tex_color * in.color;
+    return apply_debug_override(tex_color * in.color, in.entity_id);
+}
+
+fn apply_debug_override(color: vec4<f32>, entity_id: u32) -> vec4<f32> {
+    if debug_override.mode == 1u && debug_override.palette_len > 0u {
+        // Scramble ids so neighbouring entities don't get neighbouring colors
+        let hash = (entity_id * 2654435761u) >> 16u;
+        return debug_override.palette[hash % debug_override.palette_len];
+    }
+
+    return color;
+}
+
+fn linear_depth(depth: f32) -> f32 {
+    return depth_params.z_near * depth_params.z_far
+        / (depth_params.z_far - depth * (depth_params.z_far - depth_params.z_near));
+}
+
+@fragment
+fn fs_soft(in: VertexOut) -> @location(0) vec4<f32> {
+    var color = apply_debug_override(
+        textureSample(texture, texture_sampler, in.uv) * in.color,
+        in.entity_id,
+    );
+
+    if in.fade_distance > 0. {
+        let scene = textureLoad(scene_depth, vec2<i32>(in.clip_position.xy), 0);
+        let difference = linear_depth(scene) - linear_depth(in.clip_position.z);
+        color.a *= clamp(difference / in.fade_distance, 0., 1.);
+    }
+
+    return color;
 }
 
 //====================================================================
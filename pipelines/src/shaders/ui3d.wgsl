@@ -1,10 +1,7 @@
 //====================================================================
 // Uniforms
 
-struct Camera {
-    projection: mat4x4<f32>,
-    position: vec3<f32>,
-}
+#import renderer::camera
 
 struct Ui {
     size: vec4<f32>,
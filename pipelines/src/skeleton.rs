@@ -0,0 +1,306 @@
+//====================================================================
+
+use std::sync::Arc;
+
+use common::Transform;
+use serde::{Deserialize, Serialize};
+
+use crate::model_renderer::Model;
+
+//====================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Joint {
+    pub name: String,
+    /// Always before this joint in [`Skeleton::joints`].
+    pub parent: Option<usize>,
+    /// Pose relative to the parent when no animation moves the joint.
+    pub rest: Transform,
+    /// Moves vertices from the mesh into the joint's space.
+    pub inverse_bind: glam::Mat4,
+}
+
+/// Joints vertices are skinned to, indexed by [`renderer::shared::ModelVertex::joints`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Skeleton {
+    pub joints: Vec<Joint>,
+    /// Transform of whatever the root joints are attached to in the model.
+    pub root: glam::Mat4,
+}
+
+impl Skeleton {
+    #[inline]
+    pub fn joint(&self, name: &str) -> Option<usize> {
+        self.joints.iter().position(|joint| joint.name == name)
+    }
+
+    /// Pose of every joint at rest.
+    #[inline]
+    pub fn rest_pose(&self) -> Vec<Transform> {
+        self.joints.iter().map(|joint| joint.rest.clone()).collect()
+    }
+
+    /// Matrices moving bind pose vertices to where `pose` puts them, in model space.
+    pub fn joint_matrices(&self, pose: &[Transform], matrices: &mut Vec<glam::Mat4>) {
+        matrices.clear();
+
+        // Global transforms first, relying on parents coming before their children
+        self.joints.iter().enumerate().for_each(|(index, joint)| {
+            let local = pose.get(index).unwrap_or(&joint.rest).to_matrix();
+            let parent = match joint.parent {
+                Some(parent) => matrices[parent],
+                None => self.root,
+            };
+            matrices.push(parent * local);
+        });
+
+        matrices
+            .iter_mut()
+            .zip(&self.joints)
+            .for_each(|(matrix, joint)| *matrix *= joint.inverse_bind);
+    }
+}
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Interpolation {
+    Step,
+    #[default]
+    Linear,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Keyframes {
+    Translation(Vec<glam::Vec3>),
+    Rotation(Vec<glam::Quat>),
+    Scale(Vec<glam::Vec3>),
+}
+
+/// Keyframes of one property of a joint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimationChannel {
+    pub joint: usize,
+    /// Seconds of each keyframe, in order.
+    pub times: Vec<f32>,
+    pub keyframes: Keyframes,
+    pub interpolation: Interpolation,
+}
+
+impl AnimationChannel {
+    /// Keyframes either side of `time` and how far between them it is.
+    fn keys(&self, time: f32) -> Option<(usize, usize, f32)> {
+        let last = self.times.len().checked_sub(1)?;
+        let next = self.times.partition_point(|key| *key <= time);
+
+        Some(match next {
+            0 => (0, 0, 0.),
+            next if next > last => (last, last, 0.),
+            next => {
+                let (start, end) = (self.times[next - 1], self.times[next]);
+                let amount = match self.interpolation {
+                    Interpolation::Step => 0.,
+                    Interpolation::Linear => (time - start) / (end - start).max(f32::EPSILON),
+                };
+                (next - 1, next, amount)
+            }
+        })
+    }
+
+    fn sample(&self, time: f32, transform: &mut Transform) {
+        let Some((from, to, amount)) = self.keys(time) else {
+            return;
+        };
+
+        match &self.keyframes {
+            Keyframes::Translation(values) => {
+                if let (Some(from), Some(to)) = (values.get(from), values.get(to)) {
+                    transform.translation = from.lerp(*to, amount);
+                }
+            }
+            Keyframes::Rotation(values) => {
+                if let (Some(from), Some(to)) = (values.get(from), values.get(to)) {
+                    transform.rotation = from.slerp(*to, amount).normalize();
+                }
+            }
+            Keyframes::Scale(values) => {
+                if let (Some(from), Some(to)) = (values.get(from), values.get(to)) {
+                    transform.scale = from.lerp(*to, amount);
+                }
+            }
+        }
+    }
+}
+
+/// Joint keyframes of a [`Skeleton`], e.g. a walk cycle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimationClip {
+    pub name: String,
+    /// Seconds until the last keyframe.
+    pub duration: f32,
+    pub channels: Vec<AnimationChannel>,
+}
+
+impl AnimationClip {
+    /// Overwrite the joints of `pose` the clip animates with their pose at `time`.
+    pub fn sample(&self, time: f32, pose: &mut [Transform]) {
+        self.channels.iter().for_each(|channel| {
+            if let Some(transform) = pose.get_mut(channel.joint) {
+                channel.sample(time, transform);
+            }
+        });
+    }
+}
+
+//====================================================================
+
+/// Plays an [`AnimationClip`] on the skeleton of the [`Model`] on the same entity. Advanced by
+/// [`sys_tick_skeletal_animations`], then used by the model renderer to skin the model.
+#[derive(Debug, Clone)]
+pub struct SkeletalAnimation {
+    pub speed: f32,
+    pub looping: bool,
+
+    clip: Option<Arc<AnimationClip>>,
+    time: f32,
+    playing: bool,
+    joint_matrices: Vec<glam::Mat4>,
+}
+
+impl Default for SkeletalAnimation {
+    fn default() -> Self {
+        Self {
+            speed: 1.,
+            looping: true,
+            clip: None,
+            time: 0.,
+            playing: true,
+            joint_matrices: Vec::new(),
+        }
+    }
+}
+
+impl SkeletalAnimation {
+    #[inline]
+    pub fn new(clip: Arc<AnimationClip>) -> Self {
+        Self {
+            clip: Some(clip),
+            ..Default::default()
+        }
+    }
+
+    /// Play `clip` from the start.
+    #[inline]
+    pub fn play(&mut self, clip: Arc<AnimationClip>) {
+        self.clip = Some(clip);
+        self.time = 0.;
+        self.playing = true;
+    }
+
+    /// Play the model's animation named `name`, returning false if it has none.
+    pub fn play_named(&mut self, model: &Model, name: &str) -> bool {
+        match model.animation(name) {
+            Some(clip) => {
+                self.play(clip.clone());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Go back to the skeleton's rest pose.
+    #[inline]
+    pub fn stop(&mut self) {
+        self.clip = None;
+        self.time = 0.;
+    }
+
+    #[inline]
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    #[inline]
+    pub fn resume(&mut self) {
+        self.playing = true;
+    }
+
+    #[inline]
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    #[inline]
+    pub fn clip(&self) -> Option<&Arc<AnimationClip>> {
+        self.clip.as_ref()
+    }
+
+    #[inline]
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    #[inline]
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time.max(0.);
+    }
+
+    /// Whether a clip that doesn't loop reached its end.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        match &self.clip {
+            Some(clip) => !self.looping && self.time >= clip.duration,
+            None => false,
+        }
+    }
+
+    /// Matrices of the last sampled pose, empty until the first tick.
+    #[inline]
+    pub fn joint_matrices(&self) -> &[glam::Mat4] {
+        &self.joint_matrices
+    }
+
+    fn advance(&mut self, delta_seconds: f32) {
+        let Some(clip) = &self.clip else {
+            return;
+        };
+
+        if !self.playing {
+            return;
+        }
+
+        self.time += delta_seconds * self.speed;
+
+        if clip.duration <= 0. {
+            self.time = 0.;
+        } else if self.looping {
+            self.time = self.time.rem_euclid(clip.duration);
+        } else {
+            self.time = self.time.clamp(0., clip.duration);
+        }
+    }
+}
+
+/// Advance all skeletal animations by `delta_seconds` and sample their poses. Call every
+/// update.
+pub fn sys_tick_skeletal_animations(world: &hecs::World, delta_seconds: f32) {
+    world
+        .query::<(&mut SkeletalAnimation, &Model)>()
+        .iter()
+        .for_each(|(_, (animation, model))| {
+            let Some(skeleton) = &model.skeleton else {
+                return;
+            };
+
+            animation.advance(delta_seconds);
+
+            let mut pose = skeleton.rest_pose();
+            if let Some(clip) = &animation.clip {
+                clip.sample(animation.time, &mut pose);
+            }
+
+            skeleton.joint_matrices(&pose, &mut animation.joint_matrices);
+        });
+}
+
+//====================================================================
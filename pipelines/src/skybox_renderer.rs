@@ -0,0 +1,158 @@
+//====================================================================
+
+use std::sync::Arc;
+
+use hecs::World;
+use renderer::{
+    camera,
+    ordering::RenderGroup,
+    shared::{ModelVertex, SharedRenderResources, Vertex},
+    texture::{LoadedTexture, Texture},
+    tools, Renderer, RendererCore,
+};
+
+//====================================================================
+
+/// Cube texture drawn behind everything. Add it to a camera to only use it for that camera,
+/// otherwise the first skybox found is used.
+#[derive(Clone)]
+pub struct Skybox {
+    pub texture: Arc<LoadedTexture>,
+}
+
+impl Skybox {
+    /// Texture must be loaded with [`LoadedTexture::load_cube_texture`].
+    #[inline]
+    pub fn new(texture: Arc<LoadedTexture>) -> Self {
+        Self { texture }
+    }
+
+    /// Faces ordered right, left, top, bottom, front, back.
+    pub fn from_faces(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &SharedRenderResources,
+        faces: &[image::DynamicImage; 6],
+    ) -> Self {
+        let texture = Texture::from_cube_faces(device, queue, faces, Some("Skybox"));
+        Self::new(Arc::new(LoadedTexture::load_cube_texture(
+            device, shared, texture,
+        )))
+    }
+
+    /// Equirectangular panorama, such as a hdr environment map.
+    pub fn from_equirect(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &SharedRenderResources,
+        image: &image::DynamicImage,
+    ) -> Self {
+        let texture = Texture::from_equirect(device, queue, image, Some("Skybox"));
+        Self::new(Arc::new(LoadedTexture::load_cube_texture(
+            device, shared, texture,
+        )))
+    }
+}
+
+//====================================================================
+
+/// Draws the [`Skybox`] for each camera at the far plane. Depth tested without writing depth,
+/// so it only fills pixels nothing else has drawn to. Drawn before the scene so transparent
+/// pipelines blend over the sky.
+pub struct SkyboxRenderer {
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Renderer for SkyboxRenderer {
+    fn group() -> RenderGroup {
+        RenderGroup::PreScene
+    }
+
+    fn new(core: &RendererCore, shared: &mut SharedRenderResources, _world: &mut World) -> Self
+    where
+        Self: Sized,
+    {
+        let pipeline = tools::create_pipeline(
+            core.device(),
+            core.target_format(),
+            "Skybox Pipeline",
+            &[
+                shared.camera_bind_group_layout(),
+                shared.cube_texture_bind_group_layout(),
+            ],
+            &[ModelVertex::desc()],
+            &shared.preprocess_shader(&renderer::include_shader!("src/shaders/skybox.wgsl")),
+            tools::RenderPipelineDescriptor {
+                // Viewed from inside the cube
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: core.depth_format(),
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                ..Default::default()
+            },
+        );
+
+        Self { pipeline }
+    }
+
+    fn prep(
+        &mut self,
+        _core: &RendererCore,
+        _shared: &mut SharedRenderResources,
+        _world: &mut World,
+    ) {
+    }
+
+    fn render(
+        &mut self,
+        pass: &mut wgpu::RenderPass,
+        shared: &mut SharedRenderResources,
+        world: &mut World,
+    ) {
+        let active = shared
+            .active_camera()
+            .or_else(|| camera::find_camera(world));
+
+        let skybox = active
+            .and_then(|entity| {
+                world
+                    .get::<&Skybox>(entity)
+                    .ok()
+                    .map(|skybox| (*skybox).clone())
+            })
+            .or_else(|| {
+                world
+                    .query_mut::<&Skybox>()
+                    .into_iter()
+                    .next()
+                    .map(|(_, skybox)| skybox.clone())
+            });
+
+        let Some(skybox) = skybox else {
+            return;
+        };
+
+        let Some(camera) = camera::active_camera(world, shared) else {
+            return;
+        };
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, camera.bind_group(), &[]);
+        pass.set_bind_group(1, skybox.texture.bind_group(), &[]);
+
+        let cube = shared.cube();
+        cube.bind(pass, 0);
+        pass.draw_indexed(0..cube.index_count(), 0, 0..1);
+
+        shared.stats_mut().add_counter("draw_calls", 1);
+    }
+}
+
+//====================================================================
@@ -0,0 +1,299 @@
+//====================================================================
+
+use std::collections::VecDeque;
+
+use crate::texture_renderer::{AtlasSprite, Sprite, SpriteTrim};
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlipbookFrame {
+    /// Region of the [`AtlasSprite`]'s atlas.
+    Atlas(usize),
+    /// Uvs of the [`Sprite`]'s texture, covering the whole sprite.
+    Uv {
+        uv_offset: glam::Vec2,
+        uv_scale: glam::Vec2,
+    },
+    /// Uvs of the [`Sprite`]'s texture covering part of the sprite, e.g. a trimmed sprite
+    /// sheet frame.
+    Trimmed {
+        uv_offset: glam::Vec2,
+        uv_scale: glam::Vec2,
+        trim: SpriteTrim,
+    },
+}
+
+/// Frames shown one after another at a fixed rate, or for their own durations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlipbookClip {
+    pub frames: Vec<FlipbookFrame>,
+    pub fps: f32,
+    /// Seconds each frame is shown for, used instead of `fps` when not empty.
+    pub durations: Vec<f32>,
+    /// Start over once finished, instead of holding the last frame.
+    pub looping: bool,
+    /// Play back to the first frame once the last is reached.
+    pub ping_pong: bool,
+}
+
+impl FlipbookClip {
+    #[inline]
+    pub fn new(frames: Vec<FlipbookFrame>, fps: f32) -> Self {
+        Self {
+            frames,
+            fps,
+            durations: Vec::new(),
+            looping: true,
+            ping_pong: false,
+        }
+    }
+
+    /// Clip of atlas regions, e.g. `FlipbookClip::atlas(4..8, 12.)`.
+    #[inline]
+    pub fn atlas(indices: impl IntoIterator<Item = usize>, fps: f32) -> Self {
+        Self::new(indices.into_iter().map(FlipbookFrame::Atlas).collect(), fps)
+    }
+
+    /// Clip of a row of `columns` equally sized frames, left to right, from the uvs of the
+    /// first.
+    pub fn uv_row(uv_offset: glam::Vec2, uv_scale: glam::Vec2, columns: usize, fps: f32) -> Self {
+        let frames = (0..columns)
+            .map(|column| FlipbookFrame::Uv {
+                uv_offset: uv_offset + glam::vec2(uv_scale.x * column as f32, 0.),
+                uv_scale,
+            })
+            .collect();
+
+        Self::new(frames, fps)
+    }
+
+    #[inline]
+    pub fn with_durations(mut self, durations: Vec<f32>) -> Self {
+        self.durations = durations;
+        self
+    }
+
+    /// Seconds `frame` is shown for, or `None` if the clip doesn't advance.
+    pub fn frame_duration(&self, frame: usize) -> Option<f32> {
+        let duration = match self.durations.is_empty() {
+            true => 1. / self.fps,
+            false => *self.durations.get(frame)?,
+        };
+
+        (duration.is_finite() && duration > 0.).then_some(duration)
+    }
+
+    #[inline]
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    #[inline]
+    pub fn with_ping_pong(mut self, ping_pong: bool) -> Self {
+        self.ping_pong = ping_pong;
+        self
+    }
+}
+
+//====================================================================
+
+/// Flipbook playback writing the current frame into the [`Sprite`] or [`AtlasSprite`] on
+/// the same entity. Advanced by [`sys_tick_sprite_animations`].
+#[derive(Debug, Clone)]
+pub struct SpriteAnimation {
+    pub speed: f32,
+
+    clip: FlipbookClip,
+    queue: VecDeque<FlipbookClip>,
+    playing: bool,
+    finished: bool,
+    frame: usize,
+    timer: f32,
+    forwards: bool,
+}
+
+impl SpriteAnimation {
+    pub fn new(clip: FlipbookClip) -> Self {
+        Self {
+            speed: 1.,
+            clip,
+            queue: VecDeque::new(),
+            playing: true,
+            finished: false,
+            frame: 0,
+            timer: 0.,
+            forwards: true,
+        }
+    }
+
+    /// Play `clip` from its first frame, dropping any queued clips.
+    pub fn play(&mut self, clip: FlipbookClip) {
+        self.queue.clear();
+        self.start(clip);
+    }
+
+    /// Play `clip` once the current clip and those queued before it finish. Looping clips
+    /// finish at the end of their current loop.
+    #[inline]
+    pub fn queue(&mut self, clip: FlipbookClip) {
+        self.queue.push_back(clip);
+    }
+
+    #[inline]
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    #[inline]
+    pub fn resume(&mut self) {
+        self.playing = true;
+    }
+
+    /// Go back to the first frame of the current clip.
+    #[inline]
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.timer = 0.;
+        self.forwards = true;
+        self.finished = false;
+    }
+
+    #[inline]
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Whether a clip that doesn't loop reached its end with nothing queued after it.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    #[inline]
+    pub fn clip(&self) -> &FlipbookClip {
+        &self.clip
+    }
+
+    #[inline]
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    #[inline]
+    pub fn frame_index(&self) -> usize {
+        self.frame
+    }
+
+    #[inline]
+    pub fn frame(&self) -> Option<&FlipbookFrame> {
+        self.clip.frames.get(self.frame)
+    }
+
+    fn start(&mut self, clip: FlipbookClip) {
+        self.clip = clip;
+        self.playing = true;
+        self.restart();
+    }
+
+    fn advance(&mut self) {
+        let last = self.clip.frames.len().saturating_sub(1);
+
+        match (self.forwards, self.clip.ping_pong) {
+            (true, _) if self.frame < last => {
+                self.frame += 1;
+                return;
+            }
+            (true, true) if last > 0 => {
+                self.forwards = false;
+                self.frame -= 1;
+                return;
+            }
+            (false, _) if self.frame > 0 => {
+                self.frame -= 1;
+                return;
+            }
+            _ => {}
+        }
+
+        // Reached the end of the clip
+        if let Some(clip) = self.queue.pop_front() {
+            self.start(clip);
+        } else if self.clip.looping {
+            self.forwards = true;
+            self.frame = match self.clip.ping_pong {
+                true => 1.min(last),
+                false => 0,
+            };
+        } else {
+            self.finished = true;
+        }
+    }
+
+    fn tick(&mut self, delta_seconds: f32) {
+        if !self.playing || self.finished {
+            return;
+        }
+
+        self.timer += delta_seconds * self.speed;
+
+        while let Some(frame_time) = self.clip.frame_duration(self.frame) {
+            if self.timer < frame_time || self.finished {
+                break;
+            }
+
+            self.timer -= frame_time;
+            self.advance();
+        }
+    }
+}
+
+/// Advance all sprite animations by `delta_seconds`, then write their frames into the
+/// sprites on the same entities. Call every update.
+pub fn sys_tick_sprite_animations(world: &hecs::World, delta_seconds: f32) {
+    world
+        .query::<(
+            &mut SpriteAnimation,
+            Option<&mut Sprite>,
+            Option<&mut AtlasSprite>,
+        )>()
+        .iter()
+        .for_each(|(_, (animation, sprite, atlas_sprite))| {
+            animation.tick(delta_seconds);
+
+            match (animation.frame(), sprite, atlas_sprite) {
+                (Some(FlipbookFrame::Atlas(index)), _, Some(atlas_sprite)) => {
+                    atlas_sprite.index = *index;
+                }
+                (
+                    Some(FlipbookFrame::Uv {
+                        uv_offset,
+                        uv_scale,
+                    }),
+                    Some(sprite),
+                    _,
+                ) => {
+                    sprite.uv_offset = *uv_offset;
+                    sprite.uv_scale = *uv_scale;
+                    sprite.trim = None;
+                }
+                (
+                    Some(FlipbookFrame::Trimmed {
+                        uv_offset,
+                        uv_scale,
+                        trim,
+                    }),
+                    Some(sprite),
+                    _,
+                ) => {
+                    sprite.uv_offset = *uv_offset;
+                    sprite.uv_scale = *uv_scale;
+                    sprite.trim = Some(*trim);
+                }
+                _ => {}
+            }
+        });
+}
+
+//====================================================================
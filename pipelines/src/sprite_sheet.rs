@@ -0,0 +1,435 @@
+//====================================================================
+
+use std::{error::Error, fmt::Display, sync::Arc};
+
+use renderer::{
+    shared::SharedRenderResources,
+    texture::{LoadedTexture, Texture},
+};
+
+use crate::{
+    sprite_animation::{FlipbookClip, FlipbookFrame},
+    texture_renderer::SpriteTrim,
+};
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteFrame {
+    pub uv_start: glam::Vec2,
+    pub uv_end: glam::Vec2,
+    /// Size of the frame in pixels.
+    pub size: glam::Vec2,
+    /// Top left of the frame in pixels within the untrimmed image. Zero unless the frame
+    /// was trimmed.
+    pub offset: glam::Vec2,
+    /// Size of the untrimmed image in pixels.
+    pub source_size: glam::Vec2,
+    /// Pivot point in pixels from the top left of the untrimmed image.
+    pub pivot: glam::Vec2,
+    /// Duration of the frame in seconds.
+    pub duration: f32,
+}
+
+impl SpriteFrame {
+    /// Placement of the frame within a sprite sized to the untrimmed image, with the pivot
+    /// at the entity.
+    pub fn trim(&self) -> SpriteTrim {
+        let source_size = self.source_size.max(glam::Vec2::ONE);
+        let center = self.offset + self.size / 2. - self.pivot;
+
+        SpriteTrim {
+            offset: glam::vec2(center.x, -center.y) / source_size,
+            scale: self.size / source_size,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoopDirection {
+    #[default]
+    Forward,
+    Reverse,
+    PingPong,
+    /// Ping pong starting from the last frame.
+    PingPongReverse,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationTag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    pub direction: LoopDirection,
+}
+
+/// Played by a [`crate::sprite_animation::SpriteAnimation`] with one of the sheet's clips,
+/// on an entity with a [`crate::texture_renderer::Sprite`] showing the sheet's texture.
+pub struct SpriteSheet {
+    pub texture: Arc<LoadedTexture>,
+    pub frames: Vec<SpriteFrame>,
+    pub tags: Vec<AnimationTag>,
+}
+
+impl SpriteSheet {
+    #[inline]
+    pub fn tag(&self, name: &str) -> Option<&AnimationTag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    /// Clip of the named tag's frames, shown for their own durations.
+    pub fn clip(&self, tag: &str) -> Option<FlipbookClip> {
+        let tag = self.tag(tag)?;
+        let frames = self.frames.get(tag.from..=tag.to)?;
+
+        let mut clip = frames_clip(frames);
+        match tag.direction {
+            LoopDirection::Forward => {}
+            LoopDirection::Reverse => {
+                clip.frames.reverse();
+                clip.durations.reverse();
+            }
+            LoopDirection::PingPong => clip.ping_pong = true,
+            LoopDirection::PingPongReverse => {
+                clip.frames.reverse();
+                clip.durations.reverse();
+                clip.ping_pong = true;
+            }
+        }
+
+        Some(clip)
+    }
+
+    /// Clip of every frame in the sheet, in order.
+    #[inline]
+    pub fn all_frames_clip(&self) -> FlipbookClip {
+        frames_clip(&self.frames)
+    }
+}
+
+fn frames_clip(frames: &[SpriteFrame]) -> FlipbookClip {
+    let durations = frames.iter().map(|frame| frame.duration).collect();
+
+    FlipbookClip::new(
+        frames
+            .iter()
+            .map(|frame| FlipbookFrame::Trimmed {
+                uv_offset: frame.uv_start,
+                uv_scale: frame.uv_end - frame.uv_start,
+                trim: frame.trim(),
+            })
+            .collect(),
+        0.,
+    )
+    .with_durations(durations)
+}
+
+//====================================================================
+
+#[derive(Debug)]
+pub enum SpriteSheetError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Image(image::ImageError),
+    InvalidFrameRange { tag: String },
+}
+
+impl Error for SpriteSheetError {}
+
+impl Display for SpriteSheetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpriteSheetError::Io(e) => write!(f, "Unable to read sprite sheet file: {}", e),
+            SpriteSheetError::Json(e) => write!(f, "Invalid sprite sheet json: {}", e),
+            SpriteSheetError::Image(e) => write!(f, "Unable to load sprite sheet image: {}", e),
+            SpriteSheetError::InvalidFrameRange { tag } => {
+                write!(f, "Animation tag '{}' references missing frames", tag)
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for SpriteSheetError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for SpriteSheetError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+impl From<image::ImageError> for SpriteSheetError {
+    fn from(value: image::ImageError) -> Self {
+        Self::Image(value)
+    }
+}
+
+//====================================================================
+
+pub mod aseprite {
+    use std::{path::Path, sync::Arc};
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Rect {
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+    }
+
+    #[derive(Deserialize)]
+    struct Point {
+        x: f32,
+        y: f32,
+    }
+
+    #[derive(Deserialize)]
+    struct Dimensions {
+        w: f32,
+        h: f32,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Frame {
+        frame: Rect,
+        duration: f32,
+        #[serde(default)]
+        trimmed: bool,
+        sprite_source_size: Option<Rect>,
+        source_size: Option<Dimensions>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Frames {
+        Hash(serde_json::Map<String, serde_json::Value>),
+        Array(Vec<Frame>),
+    }
+
+    #[derive(Deserialize)]
+    struct FrameTag {
+        name: String,
+        from: usize,
+        to: usize,
+        #[serde(default)]
+        direction: String,
+    }
+
+    #[derive(Deserialize)]
+    struct SliceKey {
+        frame: usize,
+        bounds: Rect,
+        pivot: Option<Point>,
+    }
+
+    #[derive(Deserialize)]
+    struct Slice {
+        keys: Vec<SliceKey>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Meta {
+        image: Option<String>,
+        size: Dimensions,
+        #[serde(default)]
+        frame_tags: Vec<FrameTag>,
+        #[serde(default)]
+        slices: Vec<Slice>,
+    }
+
+    #[derive(Deserialize)]
+    struct Document {
+        frames: Frames,
+        meta: Meta,
+    }
+
+    /// Frame, tag and pivot data parsed from an Aseprite json export.
+    pub struct AsepriteData {
+        pub image: Option<String>,
+        pub frames: Vec<SpriteFrame>,
+        pub tags: Vec<AnimationTag>,
+    }
+
+    /// Parse an Aseprite json export (either the hash or array frame layout).
+    pub fn parse(json: &str) -> Result<AsepriteData, SpriteSheetError> {
+        let document = serde_json::from_str::<Document>(json)?;
+
+        let raw_frames = match document.frames {
+            Frames::Array(frames) => frames,
+            Frames::Hash(map) => map
+                .into_iter()
+                .map(|(_, value)| serde_json::from_value::<Frame>(value))
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+
+        let sheet_size = glam::vec2(document.meta.size.w, document.meta.size.h);
+
+        let mut frames = raw_frames
+            .into_iter()
+            .map(|frame| {
+                let start = glam::vec2(frame.frame.x, frame.frame.y);
+                let size = glam::vec2(frame.frame.w, frame.frame.h);
+
+                let offset = match (frame.trimmed, &frame.sprite_source_size) {
+                    (true, Some(source)) => glam::vec2(source.x, source.y),
+                    _ => glam::Vec2::ZERO,
+                };
+                let source_size = frame
+                    .source_size
+                    .map(|source| glam::vec2(source.w, source.h))
+                    .unwrap_or(size);
+
+                SpriteFrame {
+                    uv_start: start / sheet_size,
+                    uv_end: (start + size) / sheet_size,
+                    size,
+                    offset,
+                    source_size,
+                    pivot: source_size / 2.,
+                    duration: frame.duration / 1000.,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // Slice pivots apply from their key frame onwards, relative to the slice's bounds
+        document.meta.slices.iter().for_each(|slice| {
+            let mut keys = slice
+                .keys
+                .iter()
+                .filter_map(|key| {
+                    let pivot = key.pivot.as_ref()?;
+                    Some((
+                        key.frame,
+                        glam::vec2(key.bounds.x + pivot.x, key.bounds.y + pivot.y),
+                    ))
+                })
+                .collect::<Vec<_>>();
+            keys.sort_by_key(|(frame, _)| *frame);
+
+            keys.iter().enumerate().for_each(|(index, (start, pivot))| {
+                let end = keys
+                    .get(index + 1)
+                    .map(|(frame, _)| *frame)
+                    .unwrap_or(frames.len());
+
+                frames
+                    .iter_mut()
+                    .take(end)
+                    .skip(*start)
+                    .for_each(|frame| frame.pivot = *pivot);
+            });
+        });
+
+        let tags = document
+            .meta
+            .frame_tags
+            .into_iter()
+            .map(|tag| {
+                if tag.from > tag.to || tag.to >= frames.len() {
+                    return Err(SpriteSheetError::InvalidFrameRange { tag: tag.name });
+                }
+
+                let direction = match tag.direction.as_str() {
+                    "reverse" => LoopDirection::Reverse,
+                    "pingpong" => LoopDirection::PingPong,
+                    "pingpong_reverse" => LoopDirection::PingPongReverse,
+                    _ => LoopDirection::Forward,
+                };
+
+                Ok(AnimationTag {
+                    name: tag.name,
+                    from: tag.from,
+                    to: tag.to,
+                    direction,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(AsepriteData {
+            image: document.meta.image,
+            frames,
+            tags,
+        })
+    }
+
+    /// Build a sprite sheet from an Aseprite json export and its image bytes.
+    pub fn load_from_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &SharedRenderResources,
+        json: &str,
+        image: &[u8],
+    ) -> Result<SpriteSheet, SpriteSheetError> {
+        build(device, queue, shared, parse(json)?, image)
+    }
+
+    fn build(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &SharedRenderResources,
+        data: AsepriteData,
+        image: &[u8],
+    ) -> Result<SpriteSheet, SpriteSheetError> {
+        let texture = Texture::from_bytes(
+            device,
+            queue,
+            image,
+            data.image.as_deref(),
+            Some(&wgpu::SamplerDescriptor {
+                mag_filter: wgpu::FilterMode::Nearest,
+                min_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
+            }),
+        )?;
+
+        Ok(SpriteSheet {
+            texture: Arc::new(LoadedTexture::load_texture(device, shared, texture)),
+            frames: data.frames,
+            tags: data.tags,
+        })
+    }
+
+    /// Load an Aseprite json export from disk, along with the image it references.
+    #[inline]
+    pub fn load(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &SharedRenderResources,
+        path: impl AsRef<Path>,
+    ) -> Result<SpriteSheet, SpriteSheetError> {
+        load_with(device, queue, shared, path, |path| std::fs::read(path))
+    }
+
+    /// Like [`load`], reading the json and image with `read`, e.g. from a virtual filesystem.
+    pub fn load_with(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &SharedRenderResources,
+        path: impl AsRef<Path>,
+        read: impl Fn(&Path) -> std::io::Result<Vec<u8>>,
+    ) -> Result<SpriteSheet, SpriteSheetError> {
+        let path = path.as_ref();
+        let json = String::from_utf8(read(path)?)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let data = parse(&json)?;
+
+        let image_path = match &data.image {
+            Some(image) => path.with_file_name(image),
+            None => path.with_extension("png"),
+        };
+        let image = read(&image_path)?;
+
+        build(device, queue, shared, data, &image)
+    }
+}
+
+//====================================================================
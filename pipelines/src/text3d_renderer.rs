@@ -0,0 +1,317 @@
+//====================================================================
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use common::{GlobalOpacity, GlobalTransform};
+use hecs::{Entity, World};
+use renderer::{
+    camera,
+    ordering::RenderGroup,
+    shared::Vertex,
+    text_shared::{Attrs, Color, Metrics, TextBuffer, TextBufferDescriptor, TextVertex, Wrap},
+    tools, Renderer,
+};
+
+//====================================================================
+
+/// Label drawn in the world at the entity's GlobalTransform, e.g. a nameplate or damage
+/// number. Centered horizontally on the anchor.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Text3d {
+    pub text: String,
+    pub font_size: f32,
+    pub color: [f32; 4],
+    /// World units per pixel of text.
+    pub scale: f32,
+    /// World space offset from the entity, e.g. to place a nameplate above its head.
+    pub offset: glam::Vec3,
+    /// Face the camera being rendered, keeping the entity's scale but not its rotation.
+    pub billboard: bool,
+    /// Hidden behind geometry in front of it. Otherwise drawn over everything.
+    pub depth_test: bool,
+}
+
+impl Default for Text3d {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            font_size: 30.,
+            color: [1., 1., 1., 1.],
+            scale: 1.,
+            offset: glam::Vec3::ZERO,
+            billboard: true,
+            depth_test: true,
+        }
+    }
+}
+
+impl Text3d {
+    #[inline]
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+}
+
+//====================================================================
+
+renderer::wgsl_struct! {
+    struct Text3dUniform {
+        transform: glam::Mat4,
+        /// Pixel offset of the text from the anchor.
+        origin: glam::Vec2,
+        billboard: u32,
+        _pad: u32,
+    }
+}
+
+struct Text3dData {
+    uniform_buffer: wgpu::Buffer,
+    uniform_bind_group: wgpu::BindGroup,
+
+    text: String,
+    font_size: f32,
+    depth_test: bool,
+    text_buffer: TextBuffer,
+}
+
+pub struct Text3dRenderer {
+    pipeline: wgpu::RenderPipeline,
+    overlay_pipeline: wgpu::RenderPipeline,
+    uniform_bind_group_layout: wgpu::BindGroupLayout,
+
+    instances: BTreeMap<Entity, Text3dData>,
+}
+
+impl Renderer for Text3dRenderer {
+    fn group() -> RenderGroup {
+        RenderGroup::Transparent
+    }
+
+    fn new(
+        core: &renderer::RendererCore,
+        shared: &mut renderer::shared::SharedRenderResources,
+        _world: &mut World,
+    ) -> Self {
+        let uniform_bind_group_layout =
+            core.device()
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("Text3d Uniform Bind Group Layout"),
+                    entries: &[tools::bgl_uniform_entry(0, wgpu::ShaderStages::VERTEX)],
+                });
+
+        let create_pipeline = |label: &str, depth_compare: wgpu::CompareFunction| {
+            tools::create_pipeline(
+                core.device(),
+                core.target_format(),
+                label,
+                &[
+                    shared.camera_bind_group_layout(),
+                    shared.text_resources().text_atlas.bind_group_layout(),
+                    &uniform_bind_group_layout,
+                ],
+                &[TextVertex::desc()],
+                &shared.preprocess_shader(&renderer::include_shader!("src/shaders/text3d.wgsl")),
+                tools::RenderPipelineDescriptor {
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    fragment_targets: Some(&[Some(wgpu::ColorTargetState {
+                        format: core.target_format(),
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::all(),
+                    })]),
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: core.depth_format(),
+                        depth_write_enabled: false,
+                        depth_compare,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    ..Default::default()
+                },
+            )
+        };
+
+        Self {
+            pipeline: create_pipeline("Text3d Renderer", wgpu::CompareFunction::LessEqual),
+            overlay_pipeline: create_pipeline(
+                "Text3d Overlay Renderer",
+                wgpu::CompareFunction::Always,
+            ),
+            uniform_bind_group_layout,
+            instances: BTreeMap::new(),
+        }
+    }
+
+    fn prep(
+        &mut self,
+        core: &renderer::RendererCore,
+        shared: &mut renderer::shared::SharedRenderResources,
+        world: &mut World,
+    ) {
+        let mut previous = self.instances.keys().copied().collect::<BTreeSet<_>>();
+
+        world
+            .query_mut::<(&Text3d, &GlobalTransform, Option<&GlobalOpacity>)>()
+            .into_iter()
+            .for_each(|(entity, (text, transform, opacity))| {
+                previous.remove(&entity);
+
+                let data = self.instances.entry(entity).or_insert_with(|| {
+                    let uniform_buffer = tools::buffer(
+                        core.device(),
+                        tools::BufferType::Uniform,
+                        "Text3d",
+                        &[Text3dUniform {
+                            transform: glam::Mat4::IDENTITY,
+                            origin: glam::Vec2::ZERO,
+                            billboard: 0,
+                            _pad: 0,
+                        }],
+                    );
+
+                    let uniform_bind_group =
+                        core.device().create_bind_group(&wgpu::BindGroupDescriptor {
+                            label: Some("Text3d Bind Group"),
+                            layout: &self.uniform_bind_group_layout,
+                            entries: &[wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: uniform_buffer.as_entire_binding(),
+                            }],
+                        });
+
+                    let text_buffer = TextBuffer::new(
+                        core.device(),
+                        &mut shared.text_resources_mut().font_system,
+                        &TextBufferDescriptor {
+                            metrics: Metrics::new(text.font_size, text.font_size),
+                            word_wrap: Wrap::None,
+                            text: &text.text,
+                            width: None,
+                            ..Default::default()
+                        },
+                    );
+
+                    Text3dData {
+                        uniform_buffer,
+                        uniform_bind_group,
+                        text: text.text.clone(),
+                        font_size: text.font_size,
+                        depth_test: text.depth_test,
+                        text_buffer,
+                    }
+                });
+
+                //--------------------------------------------------
+                // Update text
+
+                let font_system = &mut shared.text_resources_mut().font_system;
+
+                if data.text != text.text {
+                    data.text_buffer
+                        .set_text(font_system, &text.text, Attrs::new());
+                    data.text = text.text.clone();
+                }
+
+                if data.font_size != text.font_size {
+                    data.text_buffer
+                        .set_metrics(font_system, Metrics::new(text.font_size, text.font_size));
+                    data.font_size = text.font_size;
+                }
+
+                data.depth_test = text.depth_test;
+
+                let [r, g, b, a] = GlobalOpacity::apply(opacity, text.color);
+                data.text_buffer.set_color(Color::rgba(
+                    (r * 255.) as u8,
+                    (g * 255.) as u8,
+                    (b * 255.) as u8,
+                    (a * 255.) as u8,
+                ));
+
+                if let Some(rebuild) = renderer::text_shared::prep(
+                    core.device(),
+                    core.queue(),
+                    shared.text_resources_mut(),
+                    &mut data.text_buffer,
+                ) {
+                    tools::update_instance_buffer(
+                        core.device(),
+                        core.queue(),
+                        "Text3d Vertex Buffer",
+                        &mut data.text_buffer.vertex_buffer,
+                        &mut data.text_buffer.vertex_count,
+                        &rebuild,
+                    );
+                    shared.stats_mut().add_counter("text_rebuilds", 1);
+                }
+
+                //--------------------------------------------------
+                // Update transform
+
+                let uniform = Text3dUniform {
+                    transform: glam::Mat4::from_translation(text.offset)
+                        * transform.to_matrix()
+                        * glam::Mat4::from_scale(glam::Vec3::splat(text.scale)),
+                    origin: glam::vec2(-data.text_buffer.width() / 2., 0.),
+                    billboard: text.billboard as u32,
+                    _pad: 0,
+                };
+
+                core.queue().write_buffer(
+                    &data.uniform_buffer,
+                    0,
+                    bytemuck::cast_slice(&[uniform]),
+                );
+            });
+
+        previous.into_iter().for_each(|to_remove| {
+            self.instances.remove(&to_remove);
+        });
+    }
+
+    fn render(
+        &mut self,
+        pass: &mut wgpu::RenderPass,
+        shared: &mut renderer::shared::SharedRenderResources,
+        world: &mut World,
+    ) {
+        if self.instances.is_empty() {
+            return;
+        }
+
+        let Some(camera) = camera::active_camera(world, shared) else {
+            log::warn!("No camera available for text3d renderer");
+            return;
+        };
+
+        pass.set_bind_group(0, camera.bind_group(), &[]);
+        pass.set_bind_group(1, shared.text_resources().text_atlas.bind_group(), &[]);
+
+        [(true, &self.pipeline), (false, &self.overlay_pipeline)]
+            .into_iter()
+            .for_each(|(depth_test, pipeline)| {
+                pass.set_pipeline(pipeline);
+
+                self.instances
+                    .values()
+                    .filter(|instance| instance.depth_test == depth_test)
+                    .for_each(|instance| {
+                        pass.set_vertex_buffer(0, instance.text_buffer.vertex_buffer.slice(..));
+                        pass.set_bind_group(2, &instance.uniform_bind_group, &[]);
+                        pass.draw(0..4, 0..instance.text_buffer.vertex_count);
+                    });
+            });
+
+        let stats = shared.stats_mut();
+        stats.add_counter("draw_calls", self.instances.len() as u64);
+        stats.add_counter("instances", self.instances.len() as u64);
+    }
+}
+
+//====================================================================
@@ -1,20 +1,18 @@
 //====================================================================
 
-use std::{
-    collections::{HashMap, HashSet},
-    sync::Arc,
-};
+use std::{ops::Range, sync::Arc};
 
-use common::GlobalTransform;
+use common::{GlobalOpacity, GlobalTransform};
 use renderer::{
-    camera::{CameraWgpu, PerspectiveCamera},
-    shared::{
-        TextureRectVertex, Vertex, TEXTURE_RECT_INDEX_COUNT, TEXTURE_RECT_INDICES,
-        TEXTURE_RECT_VERTICES,
-    },
-    texture::{LoadedTexture, TextureId},
+    camera,
+    ordering::RenderGroup,
+    shared::{TextureRectVertex, Vertex},
+    texture::LoadedTexture,
     tools, Renderer,
 };
+use serde::{Deserialize, Serialize};
+
+use crate::material_animation::{self, ColorPulse, UvScroll};
 
 //====================================================================
 
@@ -22,21 +20,299 @@ pub struct Sprite {
     pub texture: Arc<LoadedTexture>,
     pub size: glam::Vec2,
     pub color: [f32; 4],
+    /// Applied as `uv * uv_scale + uv_offset`. Tiling past 0-1 needs a repeating sampler.
+    pub uv_offset: glam::Vec2,
+    pub uv_scale: glam::Vec2,
+    /// Higher layers are drawn over lower ones. Within a layer sprites are drawn back to front.
+    pub layer: i32,
+    /// Set by sprite sheet animations to place trimmed frames, see [`SpriteTrim`].
+    pub trim: Option<SpriteTrim>,
+}
+
+impl Sprite {
+    #[inline]
+    pub fn new(texture: Arc<LoadedTexture>, size: glam::Vec2) -> Self {
+        Self {
+            texture,
+            size,
+            color: [1., 1., 1., 1.],
+            uv_offset: glam::Vec2::ZERO,
+            uv_scale: glam::Vec2::ONE,
+            layer: 0,
+            trim: None,
+        }
+    }
+}
+
+/// Part of a [`Sprite`]'s `size` its quad covers, for frames trimmed out of a larger image.
+/// Both are fractions of `size`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteTrim {
+    /// Offset of the quad's center from the entity, with y up.
+    pub offset: glam::Vec2,
+    pub scale: glam::Vec2,
+}
+
+/// Region of a [`TextureAtlas`] in uv space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasRect {
+    pub uv_offset: glam::Vec2,
+    pub uv_scale: glam::Vec2,
+    /// Size of the region in pixels.
+    pub size: glam::Vec2,
+}
+
+/// Single texture split into many regions. Every [`AtlasSprite`] using the same atlas
+/// is drawn in one draw call.
+#[derive(Debug)]
+pub struct TextureAtlas {
+    texture: Arc<LoadedTexture>,
+    rects: Vec<AtlasRect>,
+}
+
+impl TextureAtlas {
+    #[inline]
+    pub fn new(texture: Arc<LoadedTexture>) -> Self {
+        Self {
+            texture,
+            rects: Vec::new(),
+        }
+    }
+
+    /// Split a texture into equally sized tiles, indexed left to right, top to bottom.
+    pub fn from_grid(
+        texture: Arc<LoadedTexture>,
+        tile_size: glam::UVec2,
+        columns: u32,
+        rows: u32,
+        padding: glam::UVec2,
+    ) -> Self {
+        let mut atlas = Self::new(texture);
+
+        (0..rows).for_each(|row| {
+            (0..columns).for_each(|column| {
+                let min = glam::uvec2(column, row) * (tile_size + padding);
+                atlas.add_rect(min, tile_size);
+            })
+        });
+
+        atlas
+    }
+
+    /// Add a region in pixels from the top left of the texture, returning its index.
+    pub fn add_rect(&mut self, min: glam::UVec2, size: glam::UVec2) -> usize {
+        let texture_size = self.texture.texture().texture.size();
+        let texture_size = glam::vec2(texture_size.width as f32, texture_size.height as f32);
+
+        self.rects.push(AtlasRect {
+            uv_offset: min.as_vec2() / texture_size,
+            uv_scale: size.as_vec2() / texture_size,
+            size: size.as_vec2(),
+        });
+
+        self.rects.len() - 1
+    }
+
+    #[inline]
+    pub fn texture(&self) -> &Arc<LoadedTexture> {
+        &self.texture
+    }
+
+    #[inline]
+    pub fn rect(&self, index: usize) -> Option<&AtlasRect> {
+        self.rects.get(index)
+    }
+
+    #[inline]
+    pub fn rects(&self) -> &[AtlasRect] {
+        &self.rects
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.rects.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    /// Atlas with a rect for each region of a packed layout, in the same order.
+    pub fn from_layout(texture: Arc<LoadedTexture>, layout: &AtlasLayout) -> Self {
+        let mut atlas = Self::new(texture);
+        layout.regions.iter().for_each(|region| {
+            atlas.add_rect(region.min.into(), region.size.into());
+        });
+        atlas
+    }
+}
+
+//--------------------------------------------------
+
+/// Named regions of a packed atlas texture, e.g. written by the asset baker.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtlasLayout {
+    /// Path of the packed texture.
+    pub texture: String,
+    pub size: [u32; 2],
+    pub regions: Vec<AtlasRegion>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtlasRegion {
+    pub name: String,
+    /// Top left corner in pixels.
+    pub min: [u32; 2],
+    pub size: [u32; 2],
+}
+
+impl AtlasLayout {
+    /// Index of a region, matching [`TextureAtlas::rect`] for atlases made with
+    /// [`TextureAtlas::from_layout`].
+    #[inline]
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.regions.iter().position(|region| region.name == name)
+    }
+
+    /// Pack named images into rows, tallest first, with `padding` pixels between them.
+    /// The atlas is square with a power of two size, large enough to fit everything.
+    pub fn pack(
+        texture: impl Into<String>,
+        images: &[(String, glam::UVec2)],
+        padding: u32,
+    ) -> Self {
+        let mut order = (0..images.len()).collect::<Vec<_>>();
+        order.sort_by_key(|index| std::cmp::Reverse(images[*index].1.y));
+
+        let area = images
+            .iter()
+            .map(|(_, size)| ((size.x + padding) * (size.y + padding)) as u64)
+            .sum::<u64>();
+        let widest = images
+            .iter()
+            .map(|(_, size)| size.x + padding)
+            .max()
+            .unwrap_or(1);
+
+        let mut size = ((area as f64).sqrt().ceil() as u32)
+            .max(widest)
+            .next_power_of_two();
+
+        loop {
+            if let Some(regions) = Self::pack_rows(images, &order, size, padding) {
+                return Self {
+                    texture: texture.into(),
+                    size: [size, size],
+                    regions,
+                };
+            }
+            size *= 2;
+        }
+    }
+
+    fn pack_rows(
+        images: &[(String, glam::UVec2)],
+        order: &[usize],
+        size: u32,
+        padding: u32,
+    ) -> Option<Vec<AtlasRegion>> {
+        let mut regions = vec![None; images.len()];
+        let mut cursor = glam::UVec2::ZERO;
+        let mut row_height = 0;
+
+        for &index in order {
+            let (name, image_size) = &images[index];
+
+            if cursor.x + image_size.x > size {
+                cursor = glam::uvec2(0, cursor.y + row_height + padding);
+                row_height = 0;
+            }
+
+            if cursor.y + image_size.y > size {
+                return None;
+            }
+
+            regions[index] = Some(AtlasRegion {
+                name: name.clone(),
+                min: cursor.into(),
+                size: (*image_size).into(),
+            });
+
+            cursor.x += image_size.x + padding;
+            row_height = row_height.max(image_size.y);
+        }
+
+        regions.into_iter().collect()
+    }
+}
+
+pub struct AtlasSprite {
+    pub atlas: Arc<TextureAtlas>,
+    pub index: usize,
+    pub size: glam::Vec2,
+    pub color: [f32; 4],
+    /// See [`Sprite::layer`].
+    pub layer: i32,
+}
+
+impl AtlasSprite {
+    /// Sprite sized to the region's pixel size.
+    pub fn new(atlas: Arc<TextureAtlas>, index: usize) -> Self {
+        let size = atlas
+            .rect(index)
+            .map(|rect| rect.size)
+            .unwrap_or(glam::Vec2::ONE);
+
+        Self {
+            atlas,
+            index,
+            size,
+            color: [1., 1., 1., 1.],
+            layer: 0,
+        }
+    }
+}
+
+/// Fade a sprite out where it intersects scene geometry instead of clipping with a hard edge.
+/// Requires a copyable depth format - otherwise the sprite is drawn as normal.
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct SoftSprite {
+    /// World space distance over which the sprite fades in front of geometry.
+    pub fade_distance: f32,
+}
+
+impl Default for SoftSprite {
+    fn default() -> Self {
+        Self { fade_distance: 1. }
+    }
 }
 
 //====================================================================
 
+/// Consecutive instances drawn with the same texture.
+struct TextureBatch {
+    texture: Arc<LoadedTexture>,
+    soft: bool,
+    instances: Range<u32>,
+}
+
+/// Draws sprites sorted by layer and then back to front, so alpha blending works.
+/// Sprites sharing a texture are batched while they're next to each other in that order.
 pub struct TextureRenderer {
     pipeline: wgpu::RenderPipeline,
+    soft_pipeline: wgpu::RenderPipeline,
+    has_soft: bool,
 
-    vertex_buffer: wgpu::Buffer,
-    index_buffer: wgpu::Buffer,
-    index_count: u32,
-
-    instances: HashMap<TextureId, TextureInstanceBuffer>,
+    instances: tools::InstanceBuffer<InstanceTexture>,
+    batches: Vec<TextureBatch>,
 }
 
 impl Renderer for TextureRenderer {
+    fn group() -> RenderGroup {
+        RenderGroup::Transparent
+    }
+
     fn new(
         core: &renderer::RendererCore,
         shared: &mut renderer::shared::SharedRenderResources,
@@ -44,135 +320,275 @@ impl Renderer for TextureRenderer {
     ) -> Self {
         let pipeline = tools::create_pipeline(
             core.device(),
-            core.config(),
+            core.target_format(),
             "Texture Pipeline",
             &[
                 shared.camera_bind_group_layout(),
                 shared.texture_bind_group_layout(),
+                shared.debug_bind_group_layout(),
             ],
             &[TextureRectVertex::desc(), InstanceTexture::desc()],
-            include_str!("shaders/texture.wgsl"),
+            &shared.preprocess_shader(&renderer::include_shader!("src/shaders/texture.wgsl")),
             tools::RenderPipelineDescriptor {
                 primitive: wgpu::PrimitiveState {
                     topology: wgpu::PrimitiveTopology::TriangleStrip,
                     ..Default::default()
                 },
+                // Equal so sprites on the same plane can still be layered
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: core.depth_format(),
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                fragment_targets: Some(&[Some(wgpu::ColorTargetState {
+                    format: core.target_format(),
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::all(),
+                })]),
                 ..Default::default()
-            }
-            .with_depth_stencil(),
-        );
-
-        let vertex_buffer = tools::buffer(
-            core.device(),
-            tools::BufferType::Vertex,
-            "Texture",
-            &TEXTURE_RECT_VERTICES,
+            },
         );
 
-        let index_buffer = tools::buffer(
+        let soft_pipeline = tools::create_pipeline(
             core.device(),
-            tools::BufferType::Index,
-            "Texture",
-            &TEXTURE_RECT_INDICES,
+            core.target_format(),
+            "Soft Texture Pipeline",
+            &[
+                shared.camera_bind_group_layout(),
+                shared.texture_bind_group_layout(),
+                shared.debug_bind_group_layout(),
+                shared.depth_bind_group_layout(),
+            ],
+            &[TextureRectVertex::desc(), InstanceTexture::desc()],
+            &shared.preprocess_shader(&renderer::include_shader!("src/shaders/texture.wgsl")),
+            tools::RenderPipelineDescriptor {
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleStrip,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: core.depth_format(),
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                fragment_targets: Some(&[Some(wgpu::ColorTargetState {
+                    format: core.target_format(),
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::all(),
+                })]),
+                fragment_entry: Some("fs_soft"),
+                ..Default::default()
+            },
         );
-        let index_count = TEXTURE_RECT_INDEX_COUNT;
-
-        let instances = HashMap::default();
 
         Self {
             pipeline,
-            vertex_buffer,
-            index_buffer,
-            index_count,
-            instances,
+            soft_pipeline,
+            has_soft: false,
+            instances: tools::InstanceBuffer::new(core.device(), &[]),
+            batches: Vec::new(),
         }
     }
 
     fn prep(
         &mut self,
         core: &renderer::RendererCore,
-        _shared: &mut renderer::shared::SharedRenderResources,
+        shared: &mut renderer::shared::SharedRenderResources,
         world: &mut hecs::World,
     ) {
-        let mut previous = self.instances.keys().map(|id| *id).collect::<HashSet<_>>();
-        let mut textures_to_add = HashMap::new();
+        let matrices = camera::active_camera(world, shared).map(|camera| *camera.matrices());
 
-        let instances = world
-            .query_mut::<(&GlobalTransform, &Sprite)>()
+        // Depth is 0-1 from near to far
+        let depth = |transform: &GlobalTransform| match &matrices {
+            Some(matrices) => matrices.world_to_ndc(transform.translation()).z,
+            None => 0.,
+        };
+
+        let mut sorted = Vec::new();
+
+        world
+            .query_mut::<(
+                &GlobalTransform,
+                &Sprite,
+                Option<&SoftSprite>,
+                Option<&UvScroll>,
+                Option<&ColorPulse>,
+                Option<&GlobalOpacity>,
+            )>()
             .into_iter()
-            .fold(HashMap::new(), |mut acc, (_, (transform, sprite))| {
-                let instance = InstanceTexture {
-                    size: sprite.size,
-                    pad: [0.; 2],
-                    transform: transform.to_matrix(),
-                    color: sprite.color.into(),
-                };
+            .for_each(
+                |(entity, (transform, sprite, soft, scroll, pulse, opacity))| {
+                    let (size, matrix) = match sprite.trim {
+                        Some(trim) => (
+                            sprite.size * trim.scale,
+                            transform.to_matrix()
+                                * glam::Mat4::from_translation(
+                                    (sprite.size * trim.offset).extend(0.),
+                                ),
+                        ),
+                        None => (sprite.size, transform.to_matrix()),
+                    };
+
+                    sorted.push((
+                        sprite.layer,
+                        depth(transform),
+                        sprite.texture.clone(),
+                        InstanceTexture {
+                            size,
+                            fade_distance: soft.map(|soft| soft.fade_distance).unwrap_or(0.),
+                            entity_id: entity.id(),
+                            transform: matrix,
+                            color: GlobalOpacity::apply(opacity, sprite.color).into(),
+                            uv_offset: sprite.uv_offset,
+                            uv_scale: sprite.uv_scale,
+                            animation: material_animation::instance_animation(scroll, pulse),
+                        },
+                    ));
+                },
+            );
+
+        world
+            .query_mut::<(
+                &GlobalTransform,
+                &AtlasSprite,
+                Option<&SoftSprite>,
+                Option<&UvScroll>,
+                Option<&ColorPulse>,
+                Option<&GlobalOpacity>,
+            )>()
+            .into_iter()
+            .for_each(
+                |(entity, (transform, sprite, soft, scroll, pulse, opacity))| {
+                    let Some(rect) = sprite.atlas.rect(sprite.index) else {
+                        log::warn!(
+                            "Atlas sprite index {} out of range ({} rects)",
+                            sprite.index,
+                            sprite.atlas.len()
+                        );
+                        return;
+                    };
+
+                    sorted.push((
+                        sprite.layer,
+                        depth(transform),
+                        sprite.atlas.texture().clone(),
+                        InstanceTexture {
+                            size: sprite.size,
+                            fade_distance: soft.map(|soft| soft.fade_distance).unwrap_or(0.),
+                            entity_id: entity.id(),
+                            transform: transform.to_matrix(),
+                            color: GlobalOpacity::apply(opacity, sprite.color).into(),
+                            uv_offset: rect.uv_offset,
+                            uv_scale: rect.uv_scale,
+                            animation: material_animation::instance_animation(scroll, pulse),
+                        },
+                    ));
+                },
+            );
 
-                acc.entry(sprite.texture.id())
-                    .or_insert_with(|| {
-                        if !self.instances.contains_key(&sprite.texture.id()) {
-                            textures_to_add.insert(sprite.texture.id(), sprite.texture.clone());
-                        }
+        // Lowest layer first, then furthest first. Ties keep texture order so they still batch.
+        sorted.sort_by(|a, b| {
+            a.0.cmp(&b.0)
+                .then(b.1.total_cmp(&a.1))
+                .then(a.2.id().cmp(&b.2.id()))
+        });
 
-                        Vec::new()
-                    })
-                    .push(instance);
+        self.batches.clear();
+        let mut raw = Vec::with_capacity(sorted.len());
+
+        sorted.into_iter().for_each(|(_, _, texture, instance)| {
+            let index = raw.len() as u32;
+            let soft = instance.fade_distance > 0.;
+            raw.push(instance);
+
+            match self.batches.last_mut() {
+                Some(batch) if batch.soft == soft && batch.texture.id() == texture.id() => {
+                    batch.instances.end = index + 1;
+                }
+                _ => self.batches.push(TextureBatch {
+                    texture,
+                    soft,
+                    instances: index..index + 1,
+                }),
+            }
+        });
 
-                acc
-            });
+        let buffers_resized = self.instances.update(core.device(), core.queue(), &raw);
 
-        instances.into_iter().for_each(|(id, raw)| {
-            previous.remove(&id);
-
-            self.instances
-                .entry(id)
-                .and_modify(|instance| {
-                    instance.update(core.device(), core.queue(), raw.as_slice());
-                })
-                .or_insert_with(|| {
-                    TextureInstanceBuffer::new(
-                        core.device(),
-                        textures_to_add.remove(&id).unwrap(),
-                        raw.as_slice(),
-                    )
-                });
-        });
+        self.has_soft = self.batches.iter().any(|batch| batch.soft);
 
-        previous.into_iter().for_each(|to_remove| {
-            log::trace!("Removing texture instance {}", to_remove);
-            self.instances.remove(&to_remove);
-        });
+        let stats = shared.stats_mut();
+        stats.add_counter("buffers_resized", buffers_resized as u64);
+        stats.set_gauge("texture_batches", self.batches.len() as f64);
+    }
+
+    #[inline]
+    fn reads_depth(&self) -> bool {
+        self.has_soft
     }
 
     fn render(
         &mut self,
         pass: &mut wgpu::RenderPass,
-        _shared: &mut renderer::shared::SharedRenderResources,
+        shared: &mut renderer::shared::SharedRenderResources,
         world: &mut hecs::World,
     ) {
-        let camera = match world
-            .query_mut::<(&PerspectiveCamera, &CameraWgpu)>()
-            .into_iter()
-            .next()
-        {
-            Some((_, (_, camera))) => camera,
+        if self.batches.is_empty() {
+            return;
+        }
+
+        let camera = match camera::active_camera(world, shared) {
+            Some(camera) => camera,
             None => {
-                log::warn!("No perspective camera available for texture renderer");
+                log::warn!("No camera available for texture renderer");
                 return;
             }
         };
 
-        pass.set_pipeline(&self.pipeline);
-        pass.set_bind_group(0, camera.bind_group(), &[]);
+        let quad = shared.quad();
+        quad.bind(pass, 0);
+        let index_count = quad.index_count();
+
+        pass.set_vertex_buffer(1, self.instances.buffer().slice(..));
+
+        // Without a depth copy soft sprites fall back to being drawn as regular sprites
+        let depth_bind_group = shared.depth_bind_group().filter(|_| self.has_soft);
+        let mut current_soft = None;
+
+        self.batches.iter().for_each(|batch| {
+            let soft = batch.soft && depth_bind_group.is_some();
+
+            if current_soft != Some(soft) {
+                match soft {
+                    true => {
+                        pass.set_pipeline(&self.soft_pipeline);
+                        pass.set_bind_group(3, depth_bind_group.unwrap(), &[]);
+                    }
+                    false => pass.set_pipeline(&self.pipeline),
+                }
+                pass.set_bind_group(0, camera.bind_group(), &[]);
+                pass.set_bind_group(2, shared.debug_bind_group(), &[]);
+                current_soft = Some(soft);
+            }
 
-        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            if shared.is_active_target(batch.texture.id()) {
+                return;
+            }
 
-        self.instances.iter().for_each(|(_, instance)| {
-            pass.set_bind_group(1, instance.texture.bind_group(), &[]);
-            pass.set_vertex_buffer(1, instance.buffer.buffer().slice(..));
-            pass.draw_indexed(0..self.index_count, 0, 0..instance.buffer.count());
+            pass.set_bind_group(1, batch.texture.bind_group(), &[]);
+            pass.draw_indexed(0..index_count, 0, batch.instances.clone());
         });
+
+        let stats = shared.stats_mut();
+        stats.add_counter("draw_calls", self.batches.len() as u64);
+        stats.add_counter("instances", self.instances.count() as u64);
+        self.batches
+            .iter()
+            .for_each(|batch| stats.record_batch("texture", batch.instances.len() as u32));
     }
 }
 
@@ -182,20 +598,29 @@ impl Renderer for TextureRenderer {
 #[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Debug)]
 pub struct InstanceTexture {
     pub size: glam::Vec2,
-    pub pad: [f32; 2],
+    pub fade_distance: f32,
+    /// Used to pick a color when debug overrides are enabled.
+    pub entity_id: u32,
     pub transform: glam::Mat4,
     pub color: glam::Vec4,
+    pub uv_offset: glam::Vec2,
+    pub uv_scale: glam::Vec2,
+    /// Uv scroll xy, color pulse frequency and amplitude.
+    pub animation: glam::Vec4,
 }
 
 impl Vertex for InstanceTexture {
     fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
-        const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
-            2 => Float32x4, // Transform
-            3 => Float32x4,
+        const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 9] = wgpu::vertex_attr_array![
+            2 => Float32x3, // Size + Fade distance
+            9 => Uint32,    // Entity id
+            3 => Float32x4, // Transform
             4 => Float32x4,
             5 => Float32x4,
-            6 => Float32x4, // Color
-            7 => Float32x4, // Size
+            6 => Float32x4,
+            7 => Float32x4, // Color
+            8 => Float32x4, // Uv offset + Uv scale
+            10 => Float32x4, // Animation
         ];
 
         wgpu::VertexBufferLayout {
@@ -206,28 +631,4 @@ impl Vertex for InstanceTexture {
     }
 }
 
-struct TextureInstanceBuffer {
-    texture: Arc<LoadedTexture>,
-    buffer: tools::InstanceBuffer<InstanceTexture>,
-}
-
-impl TextureInstanceBuffer {
-    #[inline]
-    pub fn new(
-        device: &wgpu::Device,
-        texture: Arc<LoadedTexture>,
-        data: &[InstanceTexture],
-    ) -> Self {
-        Self {
-            texture,
-            buffer: tools::InstanceBuffer::new(device, data),
-        }
-    }
-
-    #[inline]
-    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[InstanceTexture]) {
-        self.buffer.update(device, queue, data);
-    }
-}
-
 //====================================================================
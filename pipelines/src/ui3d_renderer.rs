@@ -1,20 +1,28 @@
 //====================================================================
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet};
 
-use common::GlobalTransform;
+use common::{
+    focus::{FocusAxis, FocusEvent, FocusManager, Focusable},
+    GlobalOpacity, GlobalTransform,
+};
 use hecs::Entity;
 use renderer::{
-    camera::{CameraWgpu, PerspectiveCamera},
+    camera,
+    ordering::RenderGroup,
     shared::Vertex,
-    text_shared::{Metrics, TextBuffer, TextBufferDescriptor, TextResources, TextVertex, Wrap},
-    texture::Texture,
+    text_shared::{
+        Color, Metrics, TextBuffer, TextBufferDescriptor, TextResources, TextVertex, Wrap,
+    },
     tools, Renderer,
 };
 
 //====================================================================
 
-#[derive(Debug, Clone)]
+/// Menu of options drawn in the world. Rendered as is, so add an `engine::spatial::Billboard`
+/// to keep it facing the camera.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct Ui3d {
     pub menu_color: [f32; 4],
     pub selection_color: [f32; 4],
@@ -36,6 +44,45 @@ impl Default for Ui3d {
     }
 }
 
+//--------------------------------------------------
+
+/// What happened to a focusable [`Ui3d`] menu this frame. See [`ui3d_events`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ui3dEvent {
+    SelectionChanged { entity: Entity, index: usize },
+    OptionChosen { entity: Entity, index: usize },
+    Cancelled(Entity),
+}
+
+/// Events for [`Ui3d`] menus with a [`Focusable`], navigated by the focus manager's bindings.
+/// Read them each update, e.g. `ui3d_events(state.focus(), state.world())`.
+pub fn ui3d_events(focus: &FocusManager, world: &hecs::World) -> Vec<Ui3dEvent> {
+    let is_ui = |entity: Entity| world.satisfies::<&Ui3d>(entity).unwrap_or(false);
+
+    focus
+        .events()
+        .iter()
+        .filter_map(|event| match *event {
+            FocusEvent::ItemChanged { entity, item } if is_ui(entity) => {
+                Some(Ui3dEvent::SelectionChanged {
+                    entity,
+                    index: item,
+                })
+            }
+            FocusEvent::Activated { entity, item } if is_ui(entity) => {
+                Some(Ui3dEvent::OptionChosen {
+                    entity,
+                    index: item,
+                })
+            }
+            FocusEvent::Cancelled(entity) if is_ui(entity) => Some(Ui3dEvent::Cancelled(entity)),
+            _ => None,
+        })
+        .collect()
+}
+
+//====================================================================
+
 #[derive(Debug)]
 struct Ui3dData {
     ui_uniform_buffer: wgpu::Buffer,
@@ -57,10 +104,14 @@ pub struct Ui3dRenderer {
     ui_uniform_bind_group_layout: wgpu::BindGroupLayout,
     ui_position_uniform_bind_group_layout: wgpu::BindGroupLayout,
 
-    instances: HashMap<Entity, Ui3dData>,
+    instances: BTreeMap<Entity, Ui3dData>,
 }
 
 impl Renderer for Ui3dRenderer {
+    fn group() -> RenderGroup {
+        RenderGroup::Transparent
+    }
+
     fn new(
         core: &renderer::RendererCore,
         shared: &mut renderer::shared::SharedRenderResources,
@@ -85,7 +136,7 @@ impl Renderer for Ui3dRenderer {
 
         let ui_pipeline = tools::create_pipeline(
             core.device(),
-            core.config(),
+            core.target_format(),
             "Ui Renderer",
             &[
                 shared.camera_bind_group_layout(),
@@ -93,7 +144,7 @@ impl Renderer for Ui3dRenderer {
                 &ui_position_uniform_bind_group_layout,
             ],
             &[],
-            include_str!("shaders/ui3d.wgsl"),
+            &shared.preprocess_shader(&renderer::include_shader!("src/shaders/ui3d.wgsl")),
             tools::RenderPipelineDescriptor {
                 primitive: wgpu::PrimitiveState {
                     topology: wgpu::PrimitiveTopology::TriangleStrip,
@@ -101,12 +152,12 @@ impl Renderer for Ui3dRenderer {
                     ..Default::default()
                 },
                 fragment_targets: Some(&[Some(wgpu::ColorTargetState {
-                    format: core.config().format,
+                    format: core.target_format(),
                     blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                     write_mask: wgpu::ColorWrites::all(),
                 })]),
                 depth_stencil: Some(wgpu::DepthStencilState {
-                    format: Texture::DEPTH_FORMAT,
+                    format: core.depth_format(),
                     depth_write_enabled: false,
                     depth_compare: wgpu::CompareFunction::Always,
                     stencil: wgpu::StencilState::default(),
@@ -118,7 +169,7 @@ impl Renderer for Ui3dRenderer {
 
         let text_pipeline = tools::create_pipeline(
             core.device(),
-            core.config(),
+            core.target_format(),
             "Ui Text Renderer",
             &[
                 shared.camera_bind_group_layout(),
@@ -126,7 +177,7 @@ impl Renderer for Ui3dRenderer {
                 &ui_position_uniform_bind_group_layout,
             ],
             &[TextVertex::desc()],
-            include_str!("shaders/text.wgsl"),
+            &shared.preprocess_shader(&renderer::include_shader!("src/shaders/text.wgsl")),
             tools::RenderPipelineDescriptor {
                 primitive: wgpu::PrimitiveState {
                     topology: wgpu::PrimitiveTopology::TriangleStrip,
@@ -134,12 +185,12 @@ impl Renderer for Ui3dRenderer {
                     ..Default::default()
                 },
                 fragment_targets: Some(&[Some(wgpu::ColorTargetState {
-                    format: core.config().format,
+                    format: core.target_format(),
                     blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                     write_mask: wgpu::ColorWrites::all(),
                 })]),
                 depth_stencil: Some(wgpu::DepthStencilState {
-                    format: Texture::DEPTH_FORMAT,
+                    format: core.depth_format(),
                     depth_write_enabled: false,
                     depth_compare: wgpu::CompareFunction::Always,
                     stencil: wgpu::StencilState::default(),
@@ -154,7 +205,7 @@ impl Renderer for Ui3dRenderer {
             text_pipeline,
             ui_uniform_bind_group_layout,
             ui_position_uniform_bind_group_layout,
-            instances: HashMap::default(),
+            instances: BTreeMap::default(),
         }
     }
 
@@ -166,33 +217,26 @@ impl Renderer for Ui3dRenderer {
     ) {
         //--------------------------------------------------
 
-        let camera_pos: glam::Vec3 = match world
-            .query::<(&PerspectiveCamera, &GlobalTransform)>()
-            .into_iter()
-            .next()
-        {
-            Some((_, (_, transform))) => transform.translation(),
-            None => return,
-        };
-
-        // Force all ui to look at camera
+        // Focusable menus take their selection from the focused item
         world
-            .query::<(&mut GlobalTransform, &Ui3d)>()
-            .iter()
-            .for_each(|(_, (transform, _))| {
-                transform.0 =
-                    glam::Affine3A::look_at_lh(transform.translation(), camera_pos, glam::Vec3::Y)
+            .query_mut::<(&mut Ui3d, &mut Focusable)>()
+            .into_iter()
+            .for_each(|(_, (ui, focusable))| {
+                focusable.axis = FocusAxis::Vertical;
+                focusable.items = ui.options.len();
+                focusable.item = focusable.item.min(focusable.items.saturating_sub(1));
+                ui.selected = focusable.item as u8;
             });
 
         //--------------------------------------------------
 
-        let mut previous = self.instances.keys().map(|id| *id).collect::<HashSet<_>>();
+        let mut previous = self.instances.keys().copied().collect::<BTreeSet<_>>();
 
         // Prep all ui
         world
-            .query_mut::<(&Ui3d, &GlobalTransform)>()
+            .query_mut::<(&Ui3d, &GlobalTransform, Option<&GlobalOpacity>)>()
             .into_iter()
-            .for_each(|(entity, (ui, transform))| {
+            .for_each(|(entity, (ui, transform, opacity))| {
                 previous.remove(&entity);
 
                 //--------------------------------------------------
@@ -210,6 +254,14 @@ impl Renderer for Ui3dRenderer {
                 //--------------------------------------------------
                 // Build Text
 
+                let color = data.text_buffer.color();
+                data.text_buffer.set_color(Color::rgba(
+                    color.r(),
+                    color.g(),
+                    color.b(),
+                    (GlobalOpacity::of(opacity) * 255.) as u8,
+                ));
+
                 if let Some(rebuild) = renderer::text_shared::prep(
                     core.device(),
                     core.queue(),
@@ -225,6 +277,7 @@ impl Renderer for Ui3dRenderer {
                         &mut data.text_buffer.vertex_count,
                         &rebuild,
                     );
+                    shared.stats_mut().add_counter("text_rebuilds", 1);
                 }
 
                 //--------------------------------------------------
@@ -275,8 +328,8 @@ impl Renderer for Ui3dRenderer {
 
                 let ui_raw = UiUniformRaw {
                     size: ui_size,
-                    menu_color: ui.menu_color.into(),
-                    selection_color: ui.selection_color.into(),
+                    menu_color: GlobalOpacity::apply(opacity, ui.menu_color).into(),
+                    selection_color: GlobalOpacity::apply(opacity, ui.selection_color).into(),
                     selection_range_y: glam::vec2(
                         option_range * selected,
                         option_range * (selected + 1.),
@@ -308,14 +361,10 @@ impl Renderer for Ui3dRenderer {
         shared: &mut renderer::shared::SharedRenderResources,
         world: &mut hecs::World,
     ) {
-        let camera = match world
-            .query_mut::<(&PerspectiveCamera, &CameraWgpu)>()
-            .into_iter()
-            .next()
-        {
-            Some((_, (_, camera))) => camera,
+        let camera = match camera::active_camera(world, shared) {
+            Some(camera) => camera,
             None => {
-                log::warn!("No perspective camera available for texture renderer");
+                log::warn!("No camera available for ui3d renderer");
                 return;
             }
         };
@@ -326,7 +375,7 @@ impl Renderer for Ui3dRenderer {
         // Draw UI background
         render_pass.set_pipeline(&self.ui_pipeline);
 
-        self.instances.values().into_iter().for_each(|instance| {
+        self.instances.values().for_each(|instance| {
             render_pass.set_bind_group(1, &instance.ui_uniform_bind_group, &[]);
             render_pass.set_bind_group(2, &instance.ui_position_uniform_bind_group, &[]);
             render_pass.draw(0..4, 0..1);
@@ -336,11 +385,15 @@ impl Renderer for Ui3dRenderer {
         render_pass.set_pipeline(&self.text_pipeline);
         render_pass.set_bind_group(1, shared.text_resources().text_atlas.bind_group(), &[]);
 
-        self.instances.values().into_iter().for_each(|instance| {
+        self.instances.values().for_each(|instance| {
             render_pass.set_vertex_buffer(0, instance.text_buffer.vertex_buffer.slice(..));
             render_pass.set_bind_group(2, &instance.ui_position_uniform_bind_group, &[]);
             render_pass.draw(0..4, 0..instance.text_buffer.vertex_count);
         });
+
+        let stats = shared.stats_mut();
+        stats.add_counter("draw_calls", self.instances.len() as u64 * 2);
+        stats.add_counter("instances", self.instances.len() as u64);
     }
 }
 
@@ -412,11 +465,7 @@ impl Ui3dRenderer {
             &TextBufferDescriptor {
                 metrics: Metrics::new(10., 10.),
                 word_wrap: Wrap::None,
-                // attributes: todo!(),
                 text: &text,
-                // width: todo!(),
-                // height: todo!(),
-                // color: todo!(),
                 ..Default::default()
             },
         );
@@ -0,0 +1,247 @@
+//====================================================================
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use web_time::{Duration, Instant};
+
+use crate::{texture::LoadedTexture, tools::InstanceBuffer};
+
+//====================================================================
+
+/// How long pipelines keep gpu resources that are no longer being drawn, so they don't
+/// have to be recreated if they come back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetentionPolicy {
+    /// Unused resources are freed once they haven't been used for this long.
+    pub keep_unused: Duration,
+    /// When unused resources across all pipelines take more bytes than this, the least
+    /// recently used are freed early.
+    pub unused_budget: Option<u64>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            keep_unused: Duration::from_secs(10),
+            unused_budget: Some(256 * 1024 * 1024),
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Free resources as soon as they aren't used.
+    pub const NONE: Self = Self {
+        keep_unused: Duration::ZERO,
+        unused_budget: Some(0),
+    };
+}
+
+//====================================================================
+
+/// Approximate gpu memory used by a resource.
+pub trait CacheSize {
+    fn cache_size(&self) -> u64;
+}
+
+impl<T: CacheSize> CacheSize for Arc<T> {
+    #[inline]
+    fn cache_size(&self) -> u64 {
+        self.as_ref().cache_size()
+    }
+}
+
+impl CacheSize for LoadedTexture {
+    fn cache_size(&self) -> u64 {
+        let texture = &self.texture().texture;
+        let format = texture.format();
+        let (block_width, block_height) = format.block_dimensions();
+        let block_size = format.block_copy_size(None).unwrap_or(4) as u64;
+
+        (0..texture.mip_level_count())
+            .map(|level| {
+                let size = texture
+                    .size()
+                    .mip_level_size(level, texture.dimension())
+                    .physical_size(format);
+
+                (size.width / block_width) as u64
+                    * (size.height / block_height) as u64
+                    * size.depth_or_array_layers as u64
+                    * block_size
+            })
+            .sum()
+    }
+}
+
+impl<T: bytemuck::Pod> CacheSize for InstanceBuffer<T> {
+    #[inline]
+    fn cache_size(&self) -> u64 {
+        self.buffer().size()
+    }
+}
+
+//====================================================================
+
+/// Shared retention state for every [`ResourceCache`]. Unused resources reported by all
+/// caches in a frame decide what has to be freed to stay within the budget next frame.
+#[derive(Debug)]
+pub struct GpuCache {
+    policy: RetentionPolicy,
+    now: Instant,
+    /// Unused resources last used before this are freed to stay within the budget.
+    pressure_cutoff: Option<Instant>,
+    unused: Vec<(Instant, u64)>,
+    unused_bytes: u64,
+}
+
+impl Default for GpuCache {
+    fn default() -> Self {
+        Self {
+            policy: RetentionPolicy::default(),
+            now: Instant::now(),
+            pressure_cutoff: None,
+            unused: Vec::new(),
+            unused_bytes: 0,
+        }
+    }
+}
+
+impl GpuCache {
+    #[inline]
+    pub fn policy(&self) -> &RetentionPolicy {
+        &self.policy
+    }
+
+    #[inline]
+    pub fn set_policy(&mut self, policy: RetentionPolicy) {
+        self.policy = policy;
+    }
+
+    /// Bytes of unused resources kept alive as of the last frame.
+    #[inline]
+    pub fn unused_bytes(&self) -> u64 {
+        self.unused_bytes
+    }
+
+    pub(crate) fn begin_frame(&mut self) {
+        self.now = Instant::now();
+        self.unused_bytes = self.unused.iter().map(|(_, size)| size).sum();
+
+        // Keep the most recently used resources that fit in the budget
+        self.pressure_cutoff = match self.policy.unused_budget {
+            Some(budget) if self.unused_bytes > budget => {
+                self.unused
+                    .sort_by_key(|(last_used, _)| std::cmp::Reverse(*last_used));
+
+                let mut kept = 0;
+                self.unused
+                    .iter()
+                    .find(|(_, size)| {
+                        kept += size;
+                        kept > budget
+                    })
+                    .map(|(last_used, _)| *last_used + Duration::from_nanos(1))
+            }
+            _ => None,
+        };
+
+        self.unused.clear();
+    }
+
+    fn should_free(&self, last_used: Instant) -> bool {
+        self.now.saturating_duration_since(last_used) >= self.policy.keep_unused
+            || self
+                .pressure_cutoff
+                .is_some_and(|cutoff| last_used < cutoff)
+    }
+}
+
+//====================================================================
+
+struct CacheEntry<V> {
+    value: V,
+    last_used: Instant,
+    used: bool,
+}
+
+/// Gpu resources owned by a pipeline. Resources are marked as used each frame they are
+/// drawn and freed by [`ResourceCache::collect`] once the [`RetentionPolicy`] stops
+/// keeping them.
+pub struct ResourceCache<K, V> {
+    entries: BTreeMap<K, CacheEntry<V>>,
+}
+
+impl<K, V> Default for ResourceCache<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: Ord + Copy, V: CacheSize> ResourceCache<K, V> {
+    /// Mark a resource as used this frame, creating it if it isn't cached.
+    pub fn use_or_insert_with(&mut self, key: K, create: impl FnOnce() -> V) -> &mut V {
+        let entry = self.entries.entry(key).or_insert_with(|| CacheEntry {
+            value: create(),
+            last_used: Instant::now(),
+            used: false,
+        });
+
+        entry.used = true;
+        &mut entry.value
+    }
+
+    #[inline]
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+
+    #[inline]
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Resources marked as used since the last collect, in key order.
+    pub fn used(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| entry.used)
+            .map(|(key, entry)| (key, &entry.value))
+    }
+
+    /// Number of cached resources, used or not.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Free resources the policy no longer keeps and start a new frame of marking. Call once
+    /// a frame before marking what is drawn, returning how many were freed.
+    pub fn collect(&mut self, gpu: &mut GpuCache) -> usize {
+        let before = self.entries.len();
+
+        self.entries.retain(|_, entry| {
+            if std::mem::take(&mut entry.used) {
+                entry.last_used = gpu.now;
+                return true;
+            }
+
+            if gpu.should_free(entry.last_used) {
+                return false;
+            }
+
+            gpu.unused.push((entry.last_used, entry.value.cache_size()));
+            true
+        });
+
+        before - self.entries.len()
+    }
+}
+
+//====================================================================
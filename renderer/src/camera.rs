@@ -1,15 +1,21 @@
 //====================================================================
 
-use common::GlobalTransform;
-use hecs::World;
+use std::sync::Arc;
 
-use crate::WgpuWrapper;
+use common::{GlobalTransform, Size};
+use hecs::{Entity, Without, World};
+
+use crate::{
+    shared::SharedRenderResources,
+    texture::{LoadedTexture, Texture},
+    WgpuWrapper,
+};
 
 //====================================================================
 
 pub(crate) fn sys_prep_perspective_cameras(world: &mut World, queue: &wgpu::Queue) {
     world
-        .query_mut::<(&CameraWgpu, &PerspectiveCamera, &GlobalTransform)>()
+        .query_mut::<(&mut CameraWgpu, &PerspectiveCamera, &GlobalTransform)>()
         .into_iter()
         .for_each(|(_, (camera, perspective, transform))| {
             camera.update_camera(queue, perspective, &transform.0)
@@ -18,28 +24,199 @@ pub(crate) fn sys_prep_perspective_cameras(world: &mut World, queue: &wgpu::Queu
 
 pub(crate) fn sys_prep_orthographic_cameras(world: &mut World, queue: &wgpu::Queue) {
     world
-        .query_mut::<(&CameraWgpu, &OrthographicCamera, &GlobalTransform)>()
+        .query_mut::<(&mut CameraWgpu, &OrthographicCamera, &GlobalTransform)>()
         .into_iter()
         .for_each(|(_, (camera, orthographic, transform))| {
             camera.update_camera(queue, orthographic, &transform.0)
         });
 }
 
+/// Keep the aspect ratio of perspective cameras rendering into a render target in sync with it.
+pub(crate) fn sys_prep_target_cameras(world: &mut World) {
+    world
+        .query_mut::<(&mut PerspectiveCamera, &RenderTarget)>()
+        .into_iter()
+        .for_each(|(_, (perspective, target))| {
+            let size = target.size();
+            perspective.aspect = size.width as f32 / size.height.max(1) as f32;
+        });
+}
+
+/// Keep the aspect ratio of perspective cameras rendering into a viewport in sync with it.
+pub(crate) fn sys_prep_viewport_cameras(world: &mut World, surface_size: Size<u32>) {
+    world
+        .query_mut::<(&mut PerspectiveCamera, &CameraViewport)>()
+        .into_iter()
+        .for_each(|(_, (perspective, viewport))| {
+            let (_, _, width, height) = viewport.to_pixels(surface_size);
+            if width > 0. && height > 0. {
+                perspective.aspect = width / height;
+            }
+        });
+}
+
+/// Camera the pipelines should currently render with. Set by the renderer for each
+/// [`CameraViewport`], otherwise the main camera. See [`find_camera`].
+pub fn active_camera<'a>(
+    world: &'a mut World,
+    shared: &SharedRenderResources,
+) -> Option<&'a CameraWgpu> {
+    let entity = match shared.active_camera() {
+        Some(entity) => entity,
+        None => find_camera(world)?,
+    };
+
+    world.query_one_mut::<&CameraWgpu>(entity).ok()
+}
+
+/// First perspective camera, otherwise the first orthographic camera.
+/// Cameras rendering into a [`RenderTarget`] are ignored.
+pub fn find_camera(world: &World) -> Option<Entity> {
+    world
+        .query::<Without<(&PerspectiveCamera, &CameraWgpu), &RenderTarget>>()
+        .iter()
+        .next()
+        .map(|(entity, _)| entity)
+        .or_else(|| {
+            world
+                .query::<Without<(&OrthographicCamera, &CameraWgpu), &RenderTarget>>()
+                .iter()
+                .next()
+                .map(|(entity, _)| entity)
+        })
+}
+
+//====================================================================
+
+/// Render a camera into part of the surface. Values are normalized (0-1) from the top left.
+/// Cameras without a viewport are only rendered when no camera has one.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CameraViewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Cameras are rendered in ascending order.
+    pub order: i32,
+}
+
+impl Default for CameraViewport {
+    fn default() -> Self {
+        Self {
+            x: 0.,
+            y: 0.,
+            width: 1.,
+            height: 1.,
+            order: 0,
+        }
+    }
+}
+
+impl CameraViewport {
+    #[inline]
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            order: 0,
+        }
+    }
+
+    /// Largest viewport with the given aspect ratio, centered with bars on either side.
+    pub fn letterbox(aspect: f32, surface_size: Size<u32>) -> Self {
+        let surface_aspect = surface_size.width as f32 / surface_size.height.max(1) as f32;
+
+        match surface_aspect > aspect {
+            true => {
+                let width = aspect / surface_aspect;
+                Self::new((1. - width) / 2., 0., width, 1.)
+            }
+            false => {
+                let height = surface_aspect / aspect;
+                Self::new(0., (1. - height) / 2., 1., height)
+            }
+        }
+    }
+
+    /// Viewport in pixels as (x, y, width, height), clamped to the surface.
+    pub fn to_pixels(&self, surface_size: Size<u32>) -> (f32, f32, f32, f32) {
+        let surface_width = surface_size.width as f32;
+        let surface_height = surface_size.height as f32;
+
+        let x = (self.x.clamp(0., 1.) * surface_width).floor();
+        let y = (self.y.clamp(0., 1.) * surface_height).floor();
+        let width = (self.width * surface_width).min(surface_width - x).max(0.);
+        let height = (self.height * surface_height)
+            .min(surface_height - y)
+            .max(0.);
+
+        (x, y, width, height)
+    }
+}
+
+//====================================================================
+
+/// Render a camera into an offscreen texture instead of the surface. The texture can be
+/// drawn like any other texture, but draws using it are skipped by the camera rendering
+/// into it. See [`crate::shared::SharedRenderResources::is_active_target`].
+/// Pipelines that read depth aren't rendered into targets.
+pub struct RenderTarget {
+    texture: Arc<LoadedTexture>,
+    depth: Arc<WgpuWrapper<Texture>>,
+    pub clear_color: wgpu::Color,
+    pub active: bool,
+    /// Targets are rendered in ascending order, before the surface.
+    pub order: i32,
+}
+
+impl RenderTarget {
+    pub(crate) fn new(texture: LoadedTexture, depth: Texture) -> Self {
+        Self {
+            texture: Arc::new(texture),
+            depth: Arc::new(WgpuWrapper::new(depth)),
+            clear_color: wgpu::Color::BLACK,
+            active: true,
+            order: 0,
+        }
+    }
+
+    #[inline]
+    pub fn texture(&self) -> &Arc<LoadedTexture> {
+        &self.texture
+    }
+
+    #[inline]
+    pub fn size(&self) -> Size<u32> {
+        let size = self.texture.texture().texture.size();
+        Size::new(size.width, size.height)
+    }
+
+    #[inline]
+    pub(crate) fn depth(&self) -> &Arc<WgpuWrapper<Texture>> {
+        &self.depth
+    }
+}
+
 //====================================================================
 
 pub struct CameraWgpu {
     pub(crate) camera_buffer: WgpuWrapper<wgpu::Buffer>,
     pub(crate) camera_bind_group: WgpuWrapper<wgpu::BindGroup>,
+    pub(crate) matrices: CameraMatrices,
 }
 
 impl CameraWgpu {
     #[inline]
     pub fn update_camera<C: CameraUniform>(
-        &self,
+        &mut self,
         queue: &wgpu::Queue,
         camera: &C,
         transform: &glam::Affine3A,
     ) {
+        self.matrices = camera.get_matrices(transform);
+
         queue
             .write_buffer_with(
                 self.camera_buffer.inner(),
@@ -47,9 +224,17 @@ impl CameraWgpu {
                 wgpu::BufferSize::new(std::mem::size_of::<CameraUniformRaw>() as u64).unwrap(),
             )
             .unwrap()
-            .copy_from_slice(bytemuck::cast_slice(
-                &[camera.get_camera_uniform(transform)],
-            ));
+            .copy_from_slice(bytemuck::cast_slice(&[CameraUniformRaw::new(
+                self.matrices.view_projection,
+                self.matrices.position,
+            )]));
+    }
+
+    /// Matrices from the last time the camera was updated, which happens
+    /// before pipelines are prepped each frame.
+    #[inline]
+    pub fn matrices(&self) -> &CameraMatrices {
+        &self.matrices
     }
 
     #[inline]
@@ -64,6 +249,14 @@ pub trait CameraUniform {
     fn get_projection_matrix(&self) -> glam::Mat4;
     fn get_view_matrix(&self, transform: &glam::Affine3A) -> glam::Mat4;
 
+    fn get_matrices(&self, transform: &glam::Affine3A) -> CameraMatrices {
+        CameraMatrices::new(
+            self.get_view_matrix(transform),
+            self.get_projection_matrix(),
+            transform.translation.into(),
+        )
+    }
+
     #[inline]
     fn get_camera_uniform(&self, transform: &glam::Affine3A) -> CameraUniformRaw {
         CameraUniformRaw::new(
@@ -93,7 +286,98 @@ impl CameraUniformRaw {
 
 //--------------------------------------------------
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraMatrices {
+    pub view: glam::Mat4,
+    pub projection: glam::Mat4,
+    pub view_projection: glam::Mat4,
+    pub position: glam::Vec3,
+    pub frustum: Frustum,
+}
+
+impl Default for CameraMatrices {
+    fn default() -> Self {
+        Self::new(glam::Mat4::IDENTITY, glam::Mat4::IDENTITY, glam::Vec3::ZERO)
+    }
+}
+
+impl CameraMatrices {
+    pub fn new(view: glam::Mat4, projection: glam::Mat4, position: glam::Vec3) -> Self {
+        let view_projection = projection * view;
+
+        Self {
+            view,
+            projection,
+            view_projection,
+            position,
+            frustum: Frustum::from_view_projection(&view_projection),
+        }
+    }
+
+    /// Project a world position into normalized device coordinates.
+    #[inline]
+    pub fn world_to_ndc(&self, position: glam::Vec3) -> glam::Vec3 {
+        self.view_projection.project_point3(position)
+    }
+
+    /// Ray (origin, direction) through a point in normalized device coordinates, for picking.
+    pub fn ndc_to_ray(&self, ndc: glam::Vec2) -> (glam::Vec3, glam::Vec3) {
+        let inverse = self.view_projection.inverse();
+        let near = inverse.project_point3(ndc.extend(0.));
+        let far = inverse.project_point3(ndc.extend(1.));
+
+        (near, (far - near).normalize_or_zero())
+    }
+}
+
+/// View frustum as six inward facing planes (xyz normal, w distance).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    pub planes: [glam::Vec4; 6],
+}
+
+impl Frustum {
+    /// Extract planes from a view projection matrix with a 0-1 depth range.
+    pub fn from_view_projection(view_projection: &glam::Mat4) -> Self {
+        let row = |index| view_projection.row(index);
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        let planes = [w + x, w - x, w + y, w - y, z, w - z].map(|plane| {
+            let length = plane.truncate().length();
+            match length > f32::EPSILON {
+                true => plane / length,
+                false => plane,
+            }
+        });
+
+        Self { planes }
+    }
+
+    #[inline]
+    pub fn contains_point(&self, point: glam::Vec3) -> bool {
+        self.intersects_sphere(point, 0.)
+    }
+
+    pub fn intersects_sphere(&self, center: glam::Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+    }
+
+    pub fn intersects_aabb(&self, min: glam::Vec3, max: glam::Vec3) -> bool {
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+
+            // Corner furthest along the plane normal
+            let corner = glam::Vec3::select(normal.cmpge(glam::Vec3::ZERO), max, min);
+            normal.dot(corner) + plane.w >= 0.
+        })
+    }
+}
+
+//--------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct OrthographicCamera {
     pub left: f32,
     pub right: f32,
@@ -136,16 +420,14 @@ impl CameraUniform for OrthographicCamera {
 
 impl OrthographicCamera {
     fn get_projection(&self) -> glam::Mat4 {
-        let projection_matrix = glam::Mat4::orthographic_lh(
+        glam::Mat4::orthographic_lh(
             self.left,
             self.right,
             self.bottom,
             self.top,
             self.z_near,
             self.z_far,
-        );
-
-        projection_matrix
+        )
 
         // let transform_matrix =
         //     glam::Mat4::from_rotation_translation(self.rotation, -self.translation);
@@ -186,7 +468,7 @@ impl OrthographicCamera {
 
 //--------------------------------------------------
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct PerspectiveCamera {
     pub up: glam::Vec3,
     pub aspect: f32,
@@ -201,7 +483,7 @@ impl Default for PerspectiveCamera {
     fn default() -> Self {
         Self {
             up: glam::Vec3::Y,
-            aspect: 1.7777777778,
+            aspect: 1.777_777_8,
             fovy: 45.,
             z_near: 0.1,
             z_far: 1000000.,
@@ -0,0 +1,239 @@
+//====================================================================
+
+use crate::shared::Vertex;
+
+//====================================================================
+
+/// Renderer wide override replacing material colors, for debugging entity identity and occlusion.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DebugOverride {
+    #[default]
+    None,
+    /// Flat color per entity picked from the palette.
+    EntityColors,
+}
+
+impl DebugOverride {
+    /// Following mode, wrapping back to `None`.
+    #[inline]
+    pub fn next(self) -> Self {
+        match self {
+            DebugOverride::None => DebugOverride::EntityColors,
+            DebugOverride::EntityColors => DebugOverride::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DebugPalette {
+    /// Okabe-Ito palette, distinguishable with the common forms of color blindness.
+    #[default]
+    OkabeIto,
+    /// Paul Tol's bright qualitative palette, also color blind safe.
+    TolBright,
+    /// Fully saturated colors with maximum contrast for normal color vision.
+    Saturated,
+}
+
+impl DebugPalette {
+    pub fn colors(&self) -> &'static [[f32; 4]] {
+        match self {
+            DebugPalette::OkabeIto => &[
+                [0.902, 0.624, 0.000, 1.],
+                [0.337, 0.706, 0.914, 1.],
+                [0.000, 0.620, 0.451, 1.],
+                [0.941, 0.894, 0.259, 1.],
+                [0.000, 0.447, 0.698, 1.],
+                [0.835, 0.369, 0.000, 1.],
+                [0.800, 0.475, 0.655, 1.],
+                [0.000, 0.000, 0.000, 1.],
+            ],
+            DebugPalette::TolBright => &[
+                [0.267, 0.467, 0.667, 1.],
+                [0.400, 0.800, 0.933, 1.],
+                [0.133, 0.533, 0.200, 1.],
+                [0.800, 0.733, 0.267, 1.],
+                [0.933, 0.400, 0.467, 1.],
+                [0.667, 0.200, 0.467, 1.],
+                [0.733, 0.733, 0.733, 1.],
+            ],
+            DebugPalette::Saturated => &[
+                [1., 0., 0., 1.],
+                [0., 1., 0., 1.],
+                [0., 0., 1., 1.],
+                [1., 1., 0., 1.],
+                [0., 1., 1., 1.],
+                [1., 0., 1., 1.],
+                [1., 0.5, 0., 1.],
+                [0.5, 0., 1., 1.],
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DebugSettings {
+    pub mode: DebugOverride,
+    pub palette: DebugPalette,
+    /// Drop queued debug lines instead of drawing them.
+    pub hide_lines: bool,
+    /// Log [`crate::stats::BatchingHint`]s every [`crate::stats::BATCHING_HINT_INTERVAL`] frames.
+    pub log_batching_hints: bool,
+}
+
+//====================================================================
+
+pub(crate) const MAX_PALETTE_COLORS: usize = 8;
+
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Debug)]
+pub(crate) struct DebugUniformRaw {
+    mode: u32,
+    palette_len: u32,
+    pad: [u32; 2],
+    palette: [[f32; 4]; MAX_PALETTE_COLORS],
+}
+
+impl From<&DebugSettings> for DebugUniformRaw {
+    fn from(value: &DebugSettings) -> Self {
+        let colors = value.palette.colors();
+
+        let mut palette = [[0.; 4]; MAX_PALETTE_COLORS];
+        palette
+            .iter_mut()
+            .zip(colors)
+            .for_each(|(dst, src)| *dst = *src);
+
+        Self {
+            mode: match value.mode {
+                DebugOverride::None => 0,
+                DebugOverride::EntityColors => 1,
+            },
+            palette_len: colors.len().min(MAX_PALETTE_COLORS) as u32,
+            pad: [0; 2],
+            palette,
+        }
+    }
+}
+
+//====================================================================
+
+//====================================================================
+
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Debug)]
+pub struct DebugLineVertex {
+    pub position: glam::Vec3,
+    pub color: [f32; 4],
+}
+
+impl Vertex for DebugLineVertex {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![
+            0 => Float32x3, 1 => Float32x4
+        ];
+
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<DebugLineVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &VERTEX_ATTRIBUTES,
+        }
+    }
+}
+
+/// Lines drawn for a single frame by the debug line pipeline, then cleared.
+#[derive(Debug, Default)]
+pub struct DebugLines {
+    vertices: Vec<DebugLineVertex>,
+}
+
+impl DebugLines {
+    const CIRCLE_SEGMENTS: usize = 24;
+
+    #[inline]
+    pub fn line(&mut self, a: glam::Vec3, b: glam::Vec3, color: [f32; 4]) {
+        self.vertices.extend([
+            DebugLineVertex { position: a, color },
+            DebugLineVertex { position: b, color },
+        ]);
+    }
+
+    #[inline]
+    pub fn ray(&mut self, origin: glam::Vec3, direction: glam::Vec3, color: [f32; 4]) {
+        self.line(origin, origin + direction, color);
+    }
+
+    pub fn aabb(&mut self, min: glam::Vec3, max: glam::Vec3, color: [f32; 4]) {
+        let corner = |index: usize| {
+            glam::vec3(
+                if index & 1 == 0 { min.x } else { max.x },
+                if index & 2 == 0 { min.y } else { max.y },
+                if index & 4 == 0 { min.z } else { max.z },
+            )
+        };
+
+        // Each edge joins two corners differing in a single axis
+        (0..8).for_each(|index| {
+            [1, 2, 4]
+                .into_iter()
+                .filter(|axis| index & axis == 0)
+                .for_each(|axis| self.line(corner(index), corner(index | axis), color));
+        });
+    }
+
+    pub fn circle(&mut self, center: glam::Vec3, normal: glam::Vec3, radius: f32, color: [f32; 4]) {
+        let (a, b) = normal.normalize_or(glam::Vec3::Y).any_orthonormal_pair();
+        let point = |index: usize| {
+            let angle = index as f32 / Self::CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + (a * angle.cos() + b * angle.sin()) * radius
+        };
+
+        (0..Self::CIRCLE_SEGMENTS)
+            .for_each(|index| self.line(point(index), point(index + 1), color));
+    }
+
+    /// Three circles around the axes.
+    pub fn sphere(&mut self, center: glam::Vec3, radius: f32, color: [f32; 4]) {
+        self.circle(center, glam::Vec3::X, radius, color);
+        self.circle(center, glam::Vec3::Y, radius, color);
+        self.circle(center, glam::Vec3::Z, radius, color);
+    }
+
+    /// Red, green and blue lines along the transform's x, y and z axes.
+    pub fn axes(&mut self, transform: &glam::Affine3A, length: f32) {
+        let origin = transform.translation.into();
+
+        self.line(
+            origin,
+            transform.transform_point3(glam::Vec3::X * length),
+            [1., 0., 0., 1.],
+        );
+        self.line(
+            origin,
+            transform.transform_point3(glam::Vec3::Y * length),
+            [0., 1., 0., 1.],
+        );
+        self.line(
+            origin,
+            transform.transform_point3(glam::Vec3::Z * length),
+            [0., 0., 1., 1.],
+        );
+    }
+
+    #[inline]
+    pub fn vertices(&self) -> &[DebugLineVertex] {
+        &self.vertices
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+}
+
+//====================================================================
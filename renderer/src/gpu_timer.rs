@@ -0,0 +1,137 @@
+//====================================================================
+
+use common::profiler;
+use web_time::{Duration, Instant};
+
+//====================================================================
+
+/// Passes timed on the gpu while profiling.
+pub(crate) const TIMED_PASSES: [&str; 2] = ["Main Render Pass", "Depth Read Render Pass"];
+
+/// Timestamps written at the start and end of each pass in [`TIMED_PASSES`], read back into
+/// the profiler. Needs `wgpu::Features::TIMESTAMP_QUERY`.
+pub(crate) struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve: wgpu::Buffer,
+    readback: wgpu::Buffer,
+    /// Nanoseconds per timestamp tick.
+    period: f32,
+    written: [bool; TIMED_PASSES.len()],
+}
+
+impl GpuTimer {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let count = TIMED_PASSES.len() as u32 * 2;
+        let size = count as u64 * wgpu::QUERY_SIZE as u64;
+
+        Some(Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("Gpu Timer Queries"),
+                ty: wgpu::QueryType::Timestamp,
+                count,
+            }),
+            resolve: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Gpu Timer Resolve"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Gpu Timer Readback"),
+                size,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            period: queue.get_timestamp_period(),
+            written: [false; TIMED_PASSES.len()],
+        })
+    }
+
+    /// Timestamp writes for pass `index` of [`TIMED_PASSES`].
+    pub fn pass_writes(&mut self, index: usize) -> wgpu::RenderPassTimestampWrites<'_> {
+        self.written[index] = true;
+
+        wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(index as u32 * 2),
+            end_of_pass_write_index: Some(index as u32 * 2 + 1),
+        }
+    }
+
+    /// Copy this frame's timestamps somewhere they can be read.
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        self.written
+            .iter()
+            .enumerate()
+            .filter(|(_, written)| **written)
+            .for_each(|(index, _)| {
+                let first = index as u32 * 2;
+                let offset = first as u64 * wgpu::QUERY_SIZE as u64;
+
+                encoder.resolve_query_set(&self.query_set, first..first + 2, &self.resolve, offset);
+                encoder.copy_buffer_to_buffer(
+                    &self.resolve,
+                    offset,
+                    &self.readback,
+                    offset,
+                    2 * wgpu::QUERY_SIZE as u64,
+                );
+            });
+    }
+
+    /// Wait for the frame submitted at `submitted` and record its passes, placed relative
+    /// to the submit as the gpu and cpu clocks aren't comparable.
+    pub fn read(&mut self, device: &wgpu::Device, submitted: Instant) {
+        let written = std::mem::take(&mut self.written);
+        if !written.contains(&true) {
+            return;
+        }
+
+        let slice = self.readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        device.poll(wgpu::Maintain::Wait);
+
+        let timestamps = slice
+            .get_mapped_range()
+            .chunks_exact(8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        self.readback.unmap();
+
+        let Some(first) = written
+            .iter()
+            .enumerate()
+            .filter(|(_, written)| **written)
+            .map(|(index, _)| timestamps[index * 2])
+            .min()
+        else {
+            return;
+        };
+
+        let to_duration =
+            |ticks: u64| Duration::from_nanos((ticks as f64 * self.period as f64) as u64);
+
+        written
+            .iter()
+            .enumerate()
+            .filter(|(_, written)| **written)
+            .for_each(|(index, _)| {
+                let begin = timestamps[index * 2];
+                let end = timestamps[index * 2 + 1];
+
+                profiler::record_span(
+                    profiler::GPU_TRACK,
+                    TIMED_PASSES[index],
+                    "gpu",
+                    submitted + to_duration(begin.saturating_sub(first)),
+                    to_duration(end.saturating_sub(begin)),
+                );
+            });
+    }
+}
+
+//====================================================================
@@ -0,0 +1,87 @@
+//====================================================================
+
+use std::{borrow::Cow, cell::RefCell, collections::BTreeSet, path::PathBuf, time::SystemTime};
+
+//====================================================================
+
+// Shader files are polled for changes instead of using file system notifications so this
+// works the same everywhere with no extra dependencies.
+
+thread_local! {
+    /// Shaders loaded while a pipeline is being built by [`ShaderWatch::track`].
+    static LOADED: RefCell<Option<BTreeSet<PathBuf>>> = const { RefCell::new(None) };
+}
+
+/// Read a shader from disk, falling back to the copy embedded at compile time.
+pub(crate) fn load_shader(path: &'static str, embedded: &'static str) -> Cow<'static, str> {
+    LOADED.with_borrow_mut(|loaded| {
+        if let Some(loaded) = loaded {
+            loaded.insert(PathBuf::from(path));
+        }
+    });
+
+    match std::fs::read_to_string(path) {
+        Ok(source) => Cow::Owned(source),
+        Err(e) => {
+            log::warn!(
+                "Unable to read shader {} - using embedded copy: {}",
+                path,
+                e
+            );
+            Cow::Borrowed(embedded)
+        }
+    }
+}
+
+//====================================================================
+
+/// Shader files used by a pipeline and when they were last modified.
+#[derive(Debug, Default)]
+pub(crate) struct ShaderWatch {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl ShaderWatch {
+    /// Run `build`, watching every shader it loads with [`crate::include_shader`].
+    pub fn track<T>(build: impl FnOnce() -> T) -> (T, Self) {
+        let previous = LOADED.replace(Some(BTreeSet::new()));
+        let result = build();
+        let loaded = LOADED.replace(previous).unwrap_or_default();
+
+        let files = loaded
+            .into_iter()
+            .map(|path| {
+                let modified = modified(&path);
+                (path, modified)
+            })
+            .collect();
+
+        (result, Self { files })
+    }
+
+    /// Whether any file changed since the last check.
+    pub fn changed(&mut self) -> bool {
+        self.files
+            .iter_mut()
+            .fold(false, |changed, (path, last_modified)| {
+                let modified = modified(path);
+                match modified != *last_modified {
+                    true => {
+                        log::debug!("Shader {:?} changed", path);
+                        *last_modified = modified;
+                        true
+                    }
+                    false => changed,
+                }
+            })
+    }
+}
+
+#[inline]
+fn modified(path: &PathBuf) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+//====================================================================
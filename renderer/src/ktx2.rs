@@ -0,0 +1,438 @@
+//====================================================================
+
+use std::{error::Error, fmt::Display, io::Read};
+
+//====================================================================
+
+const IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+const SUPERCOMPRESSION_NONE: u32 = 0;
+const SUPERCOMPRESSION_ZLIB: u32 = 3;
+
+#[derive(Debug)]
+pub enum Ktx2Error {
+    Invalid(&'static str),
+    /// Vulkan format of the texture.
+    UnsupportedFormat(u32),
+    UnsupportedSupercompression(u32),
+    /// Only single 2d images (with mips) are supported.
+    UnsupportedLayout,
+    /// The adapter can't sample the format and it can't be decoded on the cpu.
+    NoFallback(wgpu::TextureFormat),
+    Io(std::io::Error),
+}
+
+impl Error for Ktx2Error {}
+
+impl Display for Ktx2Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Ktx2Error::Invalid(message) => write!(f, "Invalid ktx2 file: {}", message),
+            Ktx2Error::UnsupportedFormat(format) => {
+                write!(f, "Unsupported ktx2 vulkan format {}", format)
+            }
+            Ktx2Error::UnsupportedSupercompression(scheme) => {
+                write!(f, "Unsupported ktx2 supercompression scheme {}", scheme)
+            }
+            Ktx2Error::UnsupportedLayout => {
+                write!(
+                    f,
+                    "Only 2d ktx2 textures without layers or faces are supported"
+                )
+            }
+            Ktx2Error::NoFallback(format) => write!(
+                f,
+                "Adapter doesn't support {:?} and it can't be decoded on the cpu",
+                format
+            ),
+            Ktx2Error::Io(e) => write!(f, "Unable to decompress ktx2 level: {}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for Ktx2Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+//====================================================================
+
+/// Texture read from a KTX2 container, with each mip level as stored in the file.
+#[derive(Debug, Clone)]
+pub struct Ktx2 {
+    pub format: wgpu::TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    /// Data of each mip level, starting with the full size image.
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl Ktx2 {
+    /// Whether `bytes` start with the KTX2 identifier.
+    #[inline]
+    pub fn is_ktx2(bytes: &[u8]) -> bool {
+        bytes.starts_with(&IDENTIFIER)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, Ktx2Error> {
+        if !Self::is_ktx2(bytes) {
+            return Err(Ktx2Error::Invalid("missing identifier"));
+        }
+
+        let header = |index: usize| read_u32(bytes, 12 + index * 4);
+
+        let format = header(0).ok_or(Ktx2Error::Invalid("truncated header"))?;
+        let width = header(2).ok_or(Ktx2Error::Invalid("truncated header"))?;
+        let height = header(3).ok_or(Ktx2Error::Invalid("truncated header"))?;
+        let depth = header(4).ok_or(Ktx2Error::Invalid("truncated header"))?;
+        let layers = header(5).ok_or(Ktx2Error::Invalid("truncated header"))?;
+        let faces = header(6).ok_or(Ktx2Error::Invalid("truncated header"))?;
+        let level_count = header(7).ok_or(Ktx2Error::Invalid("truncated header"))?;
+        let supercompression = header(8).ok_or(Ktx2Error::Invalid("truncated header"))?;
+
+        if depth > 1 || layers > 1 || faces != 1 || height == 0 {
+            return Err(Ktx2Error::UnsupportedLayout);
+        }
+
+        if supercompression != SUPERCOMPRESSION_NONE && supercompression != SUPERCOMPRESSION_ZLIB {
+            return Err(Ktx2Error::UnsupportedSupercompression(supercompression));
+        }
+
+        let format = vk_format(format).ok_or(Ktx2Error::UnsupportedFormat(format))?;
+
+        // Level index follows the 48 byte header and 32 byte section index
+        let levels = (0..level_count.max(1) as usize)
+            .map(|level| {
+                let index = 80 + level * 24;
+                let offset = read_u64(bytes, index).ok_or(Ktx2Error::Invalid("truncated index"))?;
+                let length =
+                    read_u64(bytes, index + 8).ok_or(Ktx2Error::Invalid("truncated index"))?;
+
+                let range = offset
+                    .checked_add(length)
+                    .and_then(|end| Some(usize::try_from(offset).ok()?..usize::try_from(end).ok()?))
+                    .ok_or(Ktx2Error::Invalid("level data out of range"))?;
+
+                let data = bytes
+                    .get(range)
+                    .ok_or(Ktx2Error::Invalid("truncated level data"))?;
+
+                match supercompression {
+                    SUPERCOMPRESSION_ZLIB => {
+                        let mut decompressed = Vec::new();
+                        flate2::read::ZlibDecoder::new(data).read_to_end(&mut decompressed)?;
+                        Ok(decompressed)
+                    }
+                    _ => Ok(data.to_vec()),
+                }
+            })
+            .collect::<Result<Vec<_>, Ktx2Error>>()?;
+
+        Ok(Self {
+            format,
+            width,
+            height,
+            levels,
+        })
+    }
+
+    #[inline]
+    pub fn level_size(&self, level: u32) -> (u32, u32) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+
+    /// Whether the texture can be uploaded as is. Block compressed textures need the matching
+    /// device feature and a size that is a multiple of the block size.
+    pub fn is_supported(&self, device: &wgpu::Device) -> bool {
+        let (block_width, block_height) = self.format.block_dimensions();
+
+        self.format
+            .required_features()
+            .difference(device.features())
+            .is_empty()
+            && self.width.is_multiple_of(block_width)
+            && self.height.is_multiple_of(block_height)
+    }
+
+    /// Decode block compressed levels to rgba8 so they can be used without compression
+    /// support. BC6H and BC7 can't be decoded.
+    pub fn decompress(&self) -> Result<Self, Ktx2Error> {
+        let (decode, format): (BlockDecoder, _) = match self.format {
+            wgpu::TextureFormat::Bc1RgbaUnorm => (decode_bc1, wgpu::TextureFormat::Rgba8Unorm),
+            wgpu::TextureFormat::Bc1RgbaUnormSrgb => {
+                (decode_bc1, wgpu::TextureFormat::Rgba8UnormSrgb)
+            }
+            wgpu::TextureFormat::Bc2RgbaUnorm => (decode_bc2, wgpu::TextureFormat::Rgba8Unorm),
+            wgpu::TextureFormat::Bc2RgbaUnormSrgb => {
+                (decode_bc2, wgpu::TextureFormat::Rgba8UnormSrgb)
+            }
+            wgpu::TextureFormat::Bc3RgbaUnorm => (decode_bc3, wgpu::TextureFormat::Rgba8Unorm),
+            wgpu::TextureFormat::Bc3RgbaUnormSrgb => {
+                (decode_bc3, wgpu::TextureFormat::Rgba8UnormSrgb)
+            }
+            wgpu::TextureFormat::Bc4RUnorm => (decode_bc4, wgpu::TextureFormat::Rgba8Unorm),
+            wgpu::TextureFormat::Bc4RSnorm => (decode_bc4_snorm, wgpu::TextureFormat::Rgba8Snorm),
+            wgpu::TextureFormat::Bc5RgUnorm => (decode_bc5, wgpu::TextureFormat::Rgba8Unorm),
+            wgpu::TextureFormat::Bc5RgSnorm => (decode_bc5_snorm, wgpu::TextureFormat::Rgba8Snorm),
+            format if !format.is_compressed() => return Ok(self.clone()),
+            format => return Err(Ktx2Error::NoFallback(format)),
+        };
+
+        let block_size = self.format.block_copy_size(None).unwrap_or(16) as usize;
+
+        let levels = self
+            .levels
+            .iter()
+            .enumerate()
+            .map(|(level, data)| {
+                let (width, height) = self.level_size(level as u32);
+                let (width, height) = (width as usize, height as usize);
+                let (blocks_x, blocks_y) = (width.div_ceil(4), height.div_ceil(4));
+
+                let (Some(blocks), Some(pixel_bytes)) = (
+                    blocks_x.checked_mul(blocks_y),
+                    width
+                        .checked_mul(height)
+                        .and_then(|size| size.checked_mul(4)),
+                ) else {
+                    return Err(Ktx2Error::Invalid("level size overflows"));
+                };
+
+                if blocks
+                    .checked_mul(block_size)
+                    .is_none_or(|size| data.len() < size)
+                {
+                    return Err(Ktx2Error::Invalid("level is smaller than its size"));
+                }
+
+                let mut pixels = vec![0; pixel_bytes];
+                let mut block_pixels = [[0; 4]; 16];
+
+                data.chunks_exact(block_size)
+                    .take(blocks)
+                    .enumerate()
+                    .for_each(|(index, block)| {
+                        decode(block, &mut block_pixels);
+
+                        let block_x = index % blocks_x * 4;
+                        let block_y = index / blocks_x * 4;
+
+                        block_pixels.iter().enumerate().for_each(|(texel, pixel)| {
+                            let x = block_x + texel % 4;
+                            let y = block_y + texel / 4;
+
+                            if x < width && y < height {
+                                let start = (y * width + x) * 4;
+                                pixels[start..start + 4].copy_from_slice(pixel);
+                            }
+                        });
+                    });
+
+                Ok(pixels)
+            })
+            .collect::<Result<Vec<_>, Ktx2Error>>()?;
+
+        Ok(Self {
+            format,
+            width: self.width,
+            height: self.height,
+            levels,
+        })
+    }
+}
+
+fn vk_format(format: u32) -> Option<wgpu::TextureFormat> {
+    use wgpu::TextureFormat as F;
+
+    Some(match format {
+        37 => F::Rgba8Unorm,
+        43 => F::Rgba8UnormSrgb,
+        97 => F::Rgba16Float,
+        131 | 133 => F::Bc1RgbaUnorm,
+        132 | 134 => F::Bc1RgbaUnormSrgb,
+        135 => F::Bc2RgbaUnorm,
+        136 => F::Bc2RgbaUnormSrgb,
+        137 => F::Bc3RgbaUnorm,
+        138 => F::Bc3RgbaUnormSrgb,
+        139 => F::Bc4RUnorm,
+        140 => F::Bc4RSnorm,
+        141 => F::Bc5RgUnorm,
+        142 => F::Bc5RgSnorm,
+        143 => F::Bc6hRgbUfloat,
+        144 => F::Bc6hRgbFloat,
+        145 => F::Bc7RgbaUnorm,
+        146 => F::Bc7RgbaUnormSrgb,
+        _ => return None,
+    })
+}
+
+#[inline]
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().unwrap(),
+    ))
+}
+
+#[inline]
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        bytes.get(offset..offset + 8)?.try_into().unwrap(),
+    ))
+}
+
+//====================================================================
+
+// Block decoders for the cpu fallback. Each block covers 4x4 texels in row order.
+
+type BlockDecoder = fn(&[u8], &mut [[u8; 4]; 16]);
+
+fn rgb565(color: u16) -> [u8; 3] {
+    let r = (color >> 11) & 0x1f;
+    let g = (color >> 5) & 0x3f;
+    let b = color & 0x1f;
+    [
+        ((r << 3) | (r >> 2)) as u8,
+        ((g << 2) | (g >> 4)) as u8,
+        ((b << 3) | (b >> 2)) as u8,
+    ]
+}
+
+/// Color part of BC1-3. Only BC1 uses the three color mode with transparent black.
+fn decode_color(block: &[u8], pixels: &mut [[u8; 4]; 16], allow_transparent: bool) {
+    let color_0 = u16::from_le_bytes([block[0], block[1]]);
+    let color_1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+
+    let [c0, c1] = [rgb565(color_0), rgb565(color_1)];
+    let mix = |a: u8, b: u8, wa: u16, wb: u16| ((a as u16 * wa + b as u16 * wb) / (wa + wb)) as u8;
+
+    let mut palette = [
+        [c0[0], c0[1], c0[2], 255],
+        [c1[0], c1[1], c1[2], 255],
+        [0; 4],
+        [0; 4],
+    ];
+
+    if color_0 > color_1 || !allow_transparent {
+        palette[2] = [
+            mix(c0[0], c1[0], 2, 1),
+            mix(c0[1], c1[1], 2, 1),
+            mix(c0[2], c1[2], 2, 1),
+            255,
+        ];
+        palette[3] = [
+            mix(c0[0], c1[0], 1, 2),
+            mix(c0[1], c1[1], 1, 2),
+            mix(c0[2], c1[2], 1, 2),
+            255,
+        ];
+    } else {
+        palette[2] = [
+            mix(c0[0], c1[0], 1, 1),
+            mix(c0[1], c1[1], 1, 1),
+            mix(c0[2], c1[2], 1, 1),
+            255,
+        ];
+    }
+
+    pixels.iter_mut().enumerate().for_each(|(texel, pixel)| {
+        *pixel = palette[((indices >> (texel * 2)) & 0b11) as usize];
+    });
+}
+
+/// Single channel part of BC3-5, as interpolated endpoints with 3 bit indices.
+fn decode_channel(block: &[u8], signed: bool) -> [u8; 16] {
+    let indices = block[2..8]
+        .iter()
+        .rev()
+        .fold(0u64, |bits, byte| (bits << 8) | *byte as u64);
+
+    let palette: [i32; 8] = match signed {
+        false => endpoint_palette(block[0] as i32, block[1] as i32, 0, 255),
+        true => endpoint_palette(block[0] as i8 as i32, block[1] as i8 as i32, -127, 127),
+    };
+
+    std::array::from_fn(|texel| palette[((indices >> (texel * 3)) & 0b111) as usize] as u8)
+}
+
+fn endpoint_palette(a: i32, b: i32, min: i32, max: i32) -> [i32; 8] {
+    let a = a.max(min);
+    let b = b.max(min);
+
+    match a > b {
+        true => std::array::from_fn(|index| match index {
+            0 => a,
+            1 => b,
+            index => ((8 - index as i32) * a + (index as i32 - 1) * b) / 7,
+        }),
+        false => std::array::from_fn(|index| match index {
+            0 => a,
+            1 => b,
+            6 => min,
+            7 => max,
+            index => ((6 - index as i32) * a + (index as i32 - 1) * b) / 5,
+        }),
+    }
+}
+
+fn decode_bc1(block: &[u8], pixels: &mut [[u8; 4]; 16]) {
+    decode_color(block, pixels, true);
+}
+
+fn decode_bc2(block: &[u8], pixels: &mut [[u8; 4]; 16]) {
+    decode_color(&block[8..], pixels, false);
+
+    pixels.iter_mut().enumerate().for_each(|(texel, pixel)| {
+        let alpha = (block[texel / 2] >> ((texel % 2) * 4)) & 0xf;
+        pixel[3] = alpha * 17;
+    });
+}
+
+fn decode_bc3(block: &[u8], pixels: &mut [[u8; 4]; 16]) {
+    decode_color(&block[8..], pixels, false);
+
+    let alpha = decode_channel(block, false);
+    pixels
+        .iter_mut()
+        .zip(alpha)
+        .for_each(|(pixel, alpha)| pixel[3] = alpha);
+}
+
+fn decode_bc4(block: &[u8], pixels: &mut [[u8; 4]; 16]) {
+    let red = decode_channel(block, false);
+    pixels
+        .iter_mut()
+        .zip(red)
+        .for_each(|(pixel, red)| *pixel = [red, 0, 0, 255]);
+}
+
+fn decode_bc4_snorm(block: &[u8], pixels: &mut [[u8; 4]; 16]) {
+    let red = decode_channel(block, true);
+    pixels
+        .iter_mut()
+        .zip(red)
+        .for_each(|(pixel, red)| *pixel = [red, 0, 0, 127]);
+}
+
+fn decode_bc5(block: &[u8], pixels: &mut [[u8; 4]; 16]) {
+    let red = decode_channel(block, false);
+    let green = decode_channel(&block[8..], false);
+    pixels
+        .iter_mut()
+        .enumerate()
+        .for_each(|(texel, pixel)| *pixel = [red[texel], green[texel], 0, 255]);
+}
+
+fn decode_bc5_snorm(block: &[u8], pixels: &mut [[u8; 4]; 16]) {
+    let red = decode_channel(block, true);
+    let green = decode_channel(&block[8..], true);
+    pixels
+        .iter_mut()
+        .enumerate()
+        .for_each(|(texel, pixel)| *pixel = [red[texel], green[texel], 0, 127]);
+}
+
+//====================================================================
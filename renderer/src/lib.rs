@@ -1,16 +1,48 @@
 //====================================================================
 
-use std::sync::Arc;
+use std::{
+    error::Error,
+    fmt::Display,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
-use camera::CameraUniform;
-use common::Size;
-use hecs::World;
-use shared::SharedRenderResources;
+use camera::{
+    CameraUniform, CameraViewport, CameraWgpu, OrthographicCamera, PerspectiveCamera, RenderTarget,
+};
+use common::{profiler, Size};
+use debug::{DebugLines, DebugSettings};
+use gpu_timer::GpuTimer;
+use hecs::{Entity, Without, World};
+use lighting::AmbientLight;
+use ordering::{PipelineId, PipelineOrder, PipelineOrderError, PipelineSlot, RenderGroup};
+use post_process::{PostProcess, PostProcessChain};
+use shadow::ShadowSettings;
+use shared::{Globals, SharedRenderResources};
+use stats::RenderStats;
+use text_shared::{FontLoadStatus, FontPreload};
 use texture::{LoadedTexture, Texture};
+use web_time::Instant;
 use wgpu::SurfaceTarget;
 
+pub mod cache;
 pub mod camera;
+pub mod debug;
+mod gpu_timer;
+#[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+mod hot_reload;
+pub mod ktx2;
+pub mod lighting;
+pub mod ordering;
+pub mod post_process;
+pub mod reflection;
+pub mod shader_library;
+pub mod shadow;
 pub mod shared;
+pub mod stats;
 pub mod text_shared;
 pub mod texture;
 pub mod tools;
@@ -43,24 +75,209 @@ impl<T> WgpuWrapper<T> {
 
 //====================================================================
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SurfaceFormatPreference {
+    /// 8-bit sRGB format. Shader outputs are gamma encoded when written.
+    #[default]
+    Srgb,
+    /// Non-sRGB format. Shader outputs are written as is.
+    Linear,
+    /// 10-bit format where available, otherwise the same as `Linear`.
+    TenBit,
+}
+
+impl SurfaceFormatPreference {
+    fn pick(&self, formats: &[wgpu::TextureFormat]) -> wgpu::TextureFormat {
+        let find = |predicate: fn(&wgpu::TextureFormat) -> bool| {
+            formats.iter().find(|format| predicate(format)).copied()
+        };
+
+        let format = match self {
+            SurfaceFormatPreference::Srgb => find(|format| format.is_srgb()),
+            SurfaceFormatPreference::Linear => find(|format| !format.is_srgb()),
+            SurfaceFormatPreference::TenBit => {
+                find(|format| *format == wgpu::TextureFormat::Rgb10a2Unorm)
+                    .or_else(|| find(|format| !format.is_srgb()))
+            }
+        };
+
+        format.unwrap_or_else(|| {
+            log::warn!(
+                "No surface format matching {:?} - using {:?}",
+                self,
+                formats[0]
+            );
+            formats[0]
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RendererConfig {
+    pub depth_format: wgpu::TextureFormat,
+    pub surface_format: SurfaceFormatPreference,
+    /// Falls back to `AutoVsync` if the mode isn't supported by the surface.
+    pub present_mode: wgpu::PresentMode,
+    /// Enable backend validation and debug labels. Useful alongside RenderDoc captures.
+    pub debug: bool,
+    /// Directory to record a wgpu API trace into. Requires the `trace` feature.
+    pub trace_path: Option<PathBuf>,
+    /// Render the scene into an HDR texture and run [`PostProcess`] effects on it before
+    /// it's shown. Pipelines then target [`post_process::HDR_FORMAT`].
+    pub post_processing: bool,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            depth_format: Texture::DEPTH_FORMAT,
+            surface_format: SurfaceFormatPreference::default(),
+            present_mode: wgpu::PresentMode::AutoNoVsync,
+            debug: false,
+            trace_path: None,
+            post_processing: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RendererError {
+    CreateSurface(wgpu::CreateSurfaceError),
+    /// No adapter compatible with the surface, or no adapter at all when headless.
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+    /// Surface has no formats or alpha modes the adapter can present with.
+    UnsupportedSurface,
+}
+
+impl Error for RendererError {}
+
+impl Display for RendererError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RendererError::CreateSurface(e) => write!(f, "Unable to create surface: {}", e),
+            RendererError::NoAdapter => write!(f, "No suitable graphics adapter found"),
+            RendererError::RequestDevice(e) => write!(f, "Unable to create device: {}", e),
+            RendererError::UnsupportedSurface => {
+                write!(f, "Surface is not supported by the graphics adapter")
+            }
+        }
+    }
+}
+
+impl From<wgpu::CreateSurfaceError> for RendererError {
+    fn from(value: wgpu::CreateSurfaceError) -> Self {
+        Self::CreateSurface(value)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for RendererError {
+    fn from(value: wgpu::RequestDeviceError) -> Self {
+        Self::RequestDevice(value)
+    }
+}
+
+//====================================================================
+
 pub struct RendererState {
     core: RendererCore,
+    /// Texture frames are rendered into when there is no surface.
+    headless_target: Option<Texture>,
     depth_texture: Texture,
+    depth_copy: Option<Texture>,
 
     shared_resources: SharedRenderResources,
     pub default_texture: Arc<LoadedTexture>,
     pub clear_color: wgpu::Color,
 
     pipelines: Vec<RendererData>,
+    post_process: Option<PostProcessChain>,
+    capture_next_frame: bool,
+    main_camera: Option<Entity>,
+    /// Cameras reflection probes are captured with, see [`reflection::ReflectionProbe`].
+    probe_faces: Option<[Entity; 6]>,
+    /// Times passes on the gpu while profiling, when timestamps are supported.
+    gpu_timer: Option<GpuTimer>,
+    /// Set once the device has been recreated after being lost, until taken.
+    recovered: bool,
+    last_recovery: Option<Instant>,
+
+    started: Instant,
+    last_frame: Instant,
+    frame_index: u32,
+
+    #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+    last_shader_check: Instant,
 }
 
 impl RendererState {
+    /// Panics if the renderer can't be created, see [`RendererState::try_new`].
+    #[inline]
     pub fn new(window: impl Into<SurfaceTarget<'static>>, window_size: Size<u32>) -> Self {
-        let core = pollster::block_on(RendererCore::new(window, window_size));
-        let depth_texture =
-            Texture::create_depth_texture(&core.device, window_size, "Depth Texture");
+        Self::new_with_config(window, window_size, RendererConfig::default())
+    }
+
+    pub fn new_with_config(
+        window: impl Into<SurfaceTarget<'static>>,
+        window_size: Size<u32>,
+        config: RendererConfig,
+    ) -> Self {
+        Self::try_new_with_config(window, window_size, config)
+            .unwrap_or_else(|e| panic!("Unable to create renderer: {}", e))
+    }
+
+    /// Renderer without a window, for tests and servers. Frames are rendered into an
+    /// offscreen texture, read back with [`RendererState::read_frame`].
+    pub fn new_headless(size: Size<u32>, config: RendererConfig) -> Self {
+        Self::try_new_headless(size, config)
+            .unwrap_or_else(|e| panic!("Unable to create headless renderer: {}", e))
+    }
+
+    #[inline]
+    pub fn try_new(
+        window: impl Into<SurfaceTarget<'static>>,
+        window_size: Size<u32>,
+    ) -> Result<Self, RendererError> {
+        Self::try_new_with_config(window, window_size, RendererConfig::default())
+    }
 
-        let shared_resources = SharedRenderResources::new(&core.device);
+    pub fn try_new_with_config(
+        window: impl Into<SurfaceTarget<'static>>,
+        window_size: Size<u32>,
+        config: RendererConfig,
+    ) -> Result<Self, RendererError> {
+        let core = pollster::block_on(RendererCore::new(window, window_size, &config))?;
+        Ok(Self::from_core(core, window_size))
+    }
+
+    pub fn try_new_headless(
+        size: Size<u32>,
+        config: RendererConfig,
+    ) -> Result<Self, RendererError> {
+        let size = Size::new(size.width.max(1), size.height.max(1));
+        let core = pollster::block_on(RendererCore::new_headless(size, &config))?;
+        Ok(Self::from_core(core, size))
+    }
+
+    fn from_core(core: RendererCore, window_size: Size<u32>) -> Self {
+        let headless_target = core.headless().then(|| {
+            Texture::create_render_texture(
+                &core.device,
+                window_size,
+                core.surface_format(),
+                "Headless Target",
+            )
+        });
+
+        let depth_texture = Texture::create_depth_texture(
+            &core.device,
+            window_size,
+            core.depth_format,
+            "Depth Texture",
+        );
+
+        let mut shared_resources = SharedRenderResources::new(&core.device);
+        let depth_copy = Self::create_depth_copy(&core, &mut shared_resources, window_size);
 
         let default_texture = Arc::new(LoadedTexture::load_texture(
             &core.device,
@@ -74,6 +291,12 @@ impl RendererState {
             ),
         ));
 
+        let post_process = core
+            .post_processing
+            .then(|| PostProcessChain::new(&core, &shared_resources));
+
+        let gpu_timer = GpuTimer::new(&core.device, &core.queue);
+
         let clear_color = wgpu::Color {
             r: 0.2,
             g: 0.2,
@@ -83,48 +306,320 @@ impl RendererState {
 
         Self {
             core,
+            headless_target,
             depth_texture,
+            depth_copy,
             shared_resources,
             default_texture,
             clear_color,
             pipelines: Vec::new(),
+            post_process,
+            capture_next_frame: false,
+            main_camera: None,
+            probe_faces: None,
+            gpu_timer,
+            recovered: false,
+            last_recovery: None,
+            started: Instant::now(),
+            last_frame: Instant::now(),
+            frame_index: 0,
+
+            #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+            last_shader_check: Instant::now(),
         }
     }
 
     pub fn resize(&mut self, new_size: Size<u32>) {
         self.core.config.width = new_size.width;
         self.core.config.height = new_size.height;
-        self.core
-            .surface
-            .configure(&self.core.device, &self.core.config);
+        self.core.configure_surface();
+
+        if self.headless_target.is_some() {
+            self.headless_target = Some(Texture::create_render_texture(
+                &self.core.device,
+                new_size,
+                self.core.surface_format(),
+                "Headless Target",
+            ));
+        }
 
-        self.depth_texture =
-            Texture::create_depth_texture(&self.core.device, new_size, "Depth Texture");
+        self.depth_texture = Texture::create_depth_texture(
+            &self.core.device,
+            new_size,
+            self.core.depth_format,
+            "Depth Texture",
+        );
+        self.depth_copy = Self::create_depth_copy(&self.core, &mut self.shared_resources, new_size);
+
+        if let Some(post_process) = &mut self.post_process {
+            post_process.resize(&self.core, &self.shared_resources);
+        }
+
+        self.pipelines
+            .iter_mut()
+            .for_each(|pipeline_data| pipeline_data.pipeline.resize(&self.core));
+    }
+
+    fn create_depth_copy(
+        core: &RendererCore,
+        shared: &mut SharedRenderResources,
+        size: Size<u32>,
+    ) -> Option<Texture> {
+        if !Texture::depth_format_copyable(core.depth_format) {
+            log::warn!(
+                "Depth format {:?} can't be copied - pipelines won't be able to read depth",
+                core.depth_format
+            );
+            shared.set_depth_texture(&core.device, None);
+            return None;
+        }
+
+        let depth_copy = Texture::create_depth_texture(
+            &core.device,
+            size,
+            core.depth_format,
+            "Depth Texture Copy",
+        );
+        shared.set_depth_texture(&core.device, Some(&depth_copy));
+
+        Some(depth_copy)
+    }
+
+    /// Capture the next frame with an attached graphics debugger such as RenderDoc.
+    #[inline]
+    pub fn capture_next_frame(&mut self) {
+        self.capture_next_frame = true;
+    }
+
+    /// Camera used when no camera has a viewport. Falls back to the first
+    /// perspective or orthographic camera when unset or despawned.
+    #[inline]
+    pub fn main_camera(&self) -> Option<Entity> {
+        self.main_camera
+    }
+
+    #[inline]
+    pub fn set_main_camera(&mut self, camera: Option<Entity>) {
+        self.main_camera = camera;
     }
 
     pub fn tick(&mut self, world: &mut World) {
+        if self.core.device_lost() {
+            self.try_recover(world);
+            return;
+        }
+
+        let capturing = std::mem::take(&mut self.capture_next_frame);
+        if capturing {
+            log::info!("Starting frame capture");
+            self.core.device.start_capture();
+        }
+
+        #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+        self.reload_changed_shaders(world);
+
+        if self.shared_resources.debug_settings().hide_lines {
+            self.shared_resources.debug_lines_mut().clear();
+        }
+
+        self.render_frame(world);
+        self.shared_resources.debug_lines_mut().clear();
+
+        if self.shared_resources.debug_settings().log_batching_hints
+            && self
+                .frame_index
+                .is_multiple_of(stats::BATCHING_HINT_INTERVAL)
+        {
+            self.log_batching_hints();
+        }
+
+        if capturing {
+            self.core.device.stop_capture();
+            log::info!("Finished frame capture");
+        }
+    }
+
+    fn log_batching_hints(&self) {
+        self.stats()
+            .batching_hints(stats::BATCHING_HINT_MIN_DRAWS)
+            .iter()
+            .for_each(|hint| log::info!("Batching hint - {}", hint));
+    }
+
+    /// Whether the device was lost and recreated since this was last called. Pipelines,
+    /// post process effects and camera buffers are rebuilt, but textures, models and other
+    /// gpu resources held by the app need creating again.
+    #[inline]
+    pub fn take_recovered(&mut self) -> bool {
+        std::mem::take(&mut self.recovered)
+    }
+
+    fn try_recover(&mut self, world: &mut World) {
+        const RETRY_INTERVAL: web_time::Duration = web_time::Duration::from_secs(1);
+
+        if self
+            .last_recovery
+            .is_some_and(|last| last.elapsed() < RETRY_INTERVAL)
+        {
+            return;
+        }
+
+        log::warn!("Gpu device lost - recreating renderer");
+
+        match self.recover(world) {
+            Ok(()) => log::info!("Recovered from device loss"),
+            Err(e) => {
+                log::error!("Unable to recover from device loss: {}", e);
+                self.last_recovery = Some(Instant::now());
+            }
+        }
+    }
+
+    /// Recreate the device along with everything built on it, keeping the surface and
+    /// settings.
+    fn recover(&mut self, world: &mut World) -> Result<(), RendererError> {
+        let core = pollster::block_on(self.core.recreate())?;
+        let size = core.surface_size();
+
+        let mut old = std::mem::replace(self, Self::from_core(core, size));
+
+        self.clear_color = old.clear_color;
+        self.main_camera = old.main_camera;
+        self.probe_faces = old.probe_faces;
+        self.started = old.started;
+        self.frame_index = old.frame_index;
+        self.recovered = true;
+
+        self.set_debug_settings(*old.debug_settings());
+        self.set_ambient_light(*old.ambient_light());
+        self.set_shadow_settings(*old.shadow_settings());
+        self.shared_resources
+            .text_resources_mut()
+            .transfer_fonts(old.shared_resources.text_resources_mut());
+        std::mem::swap(
+            self.shared_resources.shader_library_mut(),
+            old.shared_resources.shader_library_mut(),
+        );
+
+        world
+            .query_mut::<(&PerspectiveCamera, &mut CameraWgpu)>()
+            .into_iter()
+            .for_each(|(_, (camera, camera_wgpu))| {
+                *camera_wgpu = self
+                    .shared_resources
+                    .create_camera(&self.core.device, camera)
+            });
+
+        world
+            .query_mut::<(&OrthographicCamera, &mut CameraWgpu)>()
+            .into_iter()
+            .for_each(|(_, (camera, camera_wgpu))| {
+                *camera_wgpu = self
+                    .shared_resources
+                    .create_camera(&self.core.device, camera)
+            });
+
+        if let (Some(post_process), Some(previous)) = (&mut self.post_process, &old.post_process) {
+            post_process.rebuild(previous, &self.core, &mut self.shared_resources);
+        }
+
+        self.pipelines = old
+            .pipelines
+            .drain(..)
+            .map(|pipeline_data| RendererData {
+                pipeline: (pipeline_data.build)(&self.core, &mut self.shared_resources, world),
+                ..pipeline_data
+            })
+            .collect();
+
+        Ok(())
+    }
+
+    fn update_globals(&mut self) {
+        let now = Instant::now();
+        let size = self.core.surface_size();
+
+        let globals = Globals {
+            time: (now - self.started).as_secs_f32(),
+            delta: (now - self.last_frame).as_secs_f32(),
+            frame: self.frame_index,
+            resolution: glam::vec2(size.width as f32, size.height as f32),
+        };
+
+        self.last_frame = now;
+        self.frame_index = self.frame_index.wrapping_add(1);
+
+        self.shared_resources.set_globals(&self.core.queue, globals);
+    }
+
+    fn render_frame(&mut self, world: &mut World) {
+        camera::sys_prep_target_cameras(world);
+        camera::sys_prep_viewport_cameras(world, self.core.surface_size());
         camera::sys_prep_perspective_cameras(world, &self.core.queue);
         camera::sys_prep_orthographic_cameras(world, &self.core.queue);
 
+        if let Some((_, camera)) = world.query_mut::<&PerspectiveCamera>().into_iter().next() {
+            self.shared_resources
+                .set_depth_params(&self.core.queue, camera.z_near, camera.z_far);
+        }
+
+        self.shared_resources.stats_mut().begin_frame();
+        self.shared_resources.gpu_cache_mut().begin_frame();
+        self.update_globals();
+
+        lighting::sys_prep_lights(world, &self.core.queue, &mut self.shared_resources);
+        shadow::sys_prep_shadows(
+            world,
+            &self.core.queue,
+            &mut self.shared_resources,
+            self.main_camera,
+        );
+
         // Prep pipelines
-        self.pipelines.iter_mut().for_each(|pipeline_data| {
-            pipeline_data
-                .pipeline
-                .prep(&self.core, &mut self.shared_resources, world)
-        });
+        self.pipelines
+            .iter_mut()
+            .filter(|pipeline_data| pipeline_data.enabled)
+            .for_each(|pipeline_data| {
+                self.shared_resources
+                    .stats_mut()
+                    .set_scope(pipeline_data.name);
+
+                let _scope = profiler::scope(pipeline_data.name, "prep");
+                pipeline_data
+                    .pipeline
+                    .prep(&self.core, &mut self.shared_resources, world)
+            });
 
         // Get and check surface
-        let (surface_texture, surface_view) = match self.core.surface.get_current_texture() {
-            Ok(texture) => {
-                let view = texture
+        let (surface_texture, surface_view) = match (&self.core.surface, &self.headless_target) {
+            (Some(surface), _) => match surface.get_current_texture() {
+                Ok(texture) => {
+                    let view = texture
+                        .texture
+                        .create_view(&wgpu::TextureViewDescriptor::default());
+                    (Some(texture), view)
+                }
+                Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                    log::debug!("Surface lost or outdated - reconfiguring");
+                    self.core.configure_surface();
+                    return;
+                }
+                Err(wgpu::SurfaceError::Timeout) => {
+                    log::warn!("Timed out getting surface texture - skipping frame");
+                    return;
+                }
+                Err(wgpu::SurfaceError::OutOfMemory) => {
+                    log::error!("Out of memory getting surface texture - skipping frame");
+                    return;
+                }
+            },
+            (None, Some(target)) => (
+                None,
+                target
                     .texture
-                    .create_view(&wgpu::TextureViewDescriptor::default());
-                (texture, view)
-            }
-            Err(_) => {
-                log::warn!("Unable to get surface texture - skipping frame");
-                return;
-            }
+                    .create_view(&wgpu::TextureViewDescriptor::default()),
+            ),
+            (None, None) => return,
         };
 
         // Create command encoder
@@ -133,52 +628,593 @@ impl RendererState {
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
 
-        // Begin main render pass
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Main Render Pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &surface_view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(self.clear_color),
-                    store: wgpu::StoreOp::Store,
-                },
-            })],
+        self.render_pre_passes(world, &mut encoder);
+        self.capture_probe(world, &mut encoder);
+        self.render_targets(world, &mut encoder);
+
+        // With post processing the scene is rendered offscreen first
+        let target_view = match &self.post_process {
+            Some(post_process) => post_process.scene_view(),
+            None => &surface_view,
+        };
+
+        let views = self.collect_views(world);
+
+        // Only time the gpu while profiling as reading timestamps back stalls the frame
+        let mut gpu_timer = self.gpu_timer.as_mut().filter(|_| profiler::is_recording());
+
+        // Pipelines are drawn in order, in a new pass wherever they start or stop reading
+        // depth, so depth can be copied for them to sample while still depth testing
+        let mut timed = [false; gpu_timer::TIMED_PASSES.len()];
+
+        Self::depth_runs(&self.pipelines)
+            .into_iter()
+            .enumerate()
+            .for_each(|(index, (range, reads_depth))| {
+                if reads_depth {
+                    if let Some(depth_copy) = &self.depth_copy {
+                        encoder.copy_texture_to_texture(
+                            self.depth_texture.texture.as_image_copy(),
+                            depth_copy.texture.as_image_copy(),
+                            self.depth_texture.texture.size(),
+                        );
+                    }
+                }
+
+                let (color_load, depth_load) = match index {
+                    0 => (
+                        wgpu::LoadOp::Clear(self.clear_color),
+                        wgpu::LoadOp::Clear(1.),
+                    ),
+                    _ => (wgpu::LoadOp::Load, wgpu::LoadOp::Load),
+                };
+
+                // Only the first pass of each kind is timed
+                let timer_index = reads_depth as usize;
+                let timestamp_writes = gpu_timer
+                    .as_mut()
+                    .filter(|_| !std::mem::replace(&mut timed[timer_index], true))
+                    .map(|timer| timer.pass_writes(timer_index));
+
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some(gpu_timer::TIMED_PASSES[timer_index]),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: target_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: color_load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &self.depth_texture.view,
+                        depth_ops: Some(wgpu::Operations {
+                            load: depth_load,
+                            store: wgpu::StoreOp::Store,
+                        }),
+                        stencil_ops: None,
+                    }),
+
+                    timestamp_writes,
+                    occlusion_query_set: None,
+                });
+
+                Self::render_pipelines(
+                    &mut self.pipelines[range],
+                    &mut self.shared_resources,
+                    world,
+                    &mut render_pass,
+                    &views,
+                    reads_depth,
+                );
+            });
+
+        self.shared_resources.set_active_camera(None);
+
+        if let Some(post_process) = &mut self.post_process {
+            post_process.run(
+                &self.core,
+                &mut self.shared_resources,
+                &mut encoder,
+                &surface_view,
+            );
+        }
 
-            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &self.depth_texture.view,
-                depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.),
-                    store: wgpu::StoreOp::Store,
+        if let Some(timer) = &gpu_timer {
+            timer.resolve(&mut encoder);
+        }
+
+        // Finish and submit
+        let submitted = Instant::now();
+        self.core.queue.submit(Some(encoder.finish()));
+
+        if let Some(timer) = gpu_timer {
+            timer.read(&self.core.device, submitted);
+        }
+
+        if let Some(surface_texture) = surface_texture {
+            let _scope = profiler::scope("present", "render");
+            surface_texture.present();
+        }
+    }
+
+    /// Render every [`PrePass`] the pipelines draw into, before anything samples them.
+    fn render_pre_passes(&mut self, world: &mut World, encoder: &mut wgpu::CommandEncoder) {
+        PrePass::ALL.iter().for_each(|pre_pass| {
+            let active = match pre_pass {
+                PrePass::Shadow => self.shared_resources.shadows_active(),
+            };
+
+            if !active
+                || !self.pipelines.iter().any(|pipeline_data| {
+                    pipeline_data.enabled && pipeline_data.pipeline.pre_passes().contains(pre_pass)
+                })
+            {
+                return;
+            }
+
+            let view = match pre_pass {
+                PrePass::Shadow => &self.shared_resources.shadow_map().view,
+            };
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(pre_pass.label()),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
                 }),
-                stencil_ops: None,
-            }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
 
-            timestamp_writes: None,
-            occlusion_query_set: None,
+            self.pipelines
+                .iter_mut()
+                .filter(|pipeline_data| {
+                    pipeline_data.enabled && pipeline_data.pipeline.pre_passes().contains(pre_pass)
+                })
+                .for_each(|pipeline_data| {
+                    self.shared_resources
+                        .stats_mut()
+                        .set_scope(pipeline_data.name);
+
+                    let _scope = profiler::scope(pipeline_data.name, "pre_pass");
+                    pipeline_data.pipeline.render_pre_pass(
+                        *pre_pass,
+                        &mut render_pass,
+                        &mut self.shared_resources,
+                        world,
+                    )
+                });
         });
+    }
 
-        // Render all pipelines
-        self.pipelines.iter_mut().for_each(|pipeline_data| {
-            pipeline_data
-                .pipeline
-                .render(&mut render_pass, &mut self.shared_resources, world)
+    /// Render the next pending reflection probe into each face of its cube texture.
+    fn capture_probe(&mut self, world: &mut World, encoder: &mut wgpu::CommandEncoder) {
+        if world
+            .query_mut::<&reflection::ReflectionProbe>()
+            .into_iter()
+            .all(|(_, probe)| !probe.capture_pending())
+        {
+            return;
+        }
+
+        let faces = reflection::face_cameras(
+            world,
+            &self.core,
+            &self.shared_resources,
+            &mut self.probe_faces,
+        );
+
+        let Some(probe) = reflection::prep_capture(world, &self.core, &faces) else {
+            return;
+        };
+
+        self.shared_resources.set_capturing_probe(true);
+
+        faces.iter().enumerate().for_each(|(index, face)| {
+            let view = probe.cube().cube_face_view(index as u32);
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Reflection Probe Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.clear_color),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &probe.depth().view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            Self::render_pipelines(
+                &mut self.pipelines,
+                &mut self.shared_resources,
+                world,
+                &mut render_pass,
+                &[RenderView {
+                    camera: Some(*face),
+                    viewport: None,
+                }],
+                false,
+            );
         });
 
-        std::mem::drop(render_pass);
+        self.shared_resources.set_capturing_probe(false);
+        self.shared_resources
+            .stats_mut()
+            .add_counter("probes_captured", 1);
+    }
 
-        // Finish and submit
-        self.core.queue.submit(Some(encoder.finish()));
-        surface_texture.present();
+    /// Render every active camera with a [`RenderTarget`] into its texture.
+    fn render_targets(&mut self, world: &mut World, encoder: &mut wgpu::CommandEncoder) {
+        let mut targets = world
+            .query_mut::<(&RenderTarget, &CameraWgpu)>()
+            .into_iter()
+            .filter(|(_, (target, _))| target.active)
+            .map(|(entity, (target, _))| {
+                (
+                    target.order,
+                    entity,
+                    target.texture().clone(),
+                    target.depth().clone(),
+                    target.clear_color,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        targets.sort_by_key(|(order, entity, ..)| (*order, *entity));
+
+        targets
+            .into_iter()
+            .for_each(|(_, entity, texture, depth, clear_color)| {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Render Target Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &texture.texture().view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(clear_color),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &depth.inner().view,
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(1.),
+                            store: wgpu::StoreOp::Store,
+                        }),
+                        stencil_ops: None,
+                    }),
+
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+
+                self.shared_resources.set_active_target(Some(texture.id()));
+
+                Self::render_pipelines(
+                    &mut self.pipelines,
+                    &mut self.shared_resources,
+                    world,
+                    &mut render_pass,
+                    &[RenderView {
+                        camera: Some(entity),
+                        viewport: None,
+                    }],
+                    false,
+                );
+
+                self.shared_resources.set_active_target(None);
+            });
+    }
+
+    /// Cameras with a viewport in render order, or the whole surface when there are none.
+    fn collect_views(&self, world: &mut World) -> Vec<RenderView> {
+        let surface_size = self.core.surface_size();
+
+        let mut views = world
+            .query_mut::<Without<(&CameraViewport, &CameraWgpu), &RenderTarget>>()
+            .into_iter()
+            .map(|(entity, (viewport, _))| {
+                (viewport.order, entity, viewport.to_pixels(surface_size))
+            })
+            .filter(|(_, _, (_, _, width, height))| *width > 0. && *height > 0.)
+            .collect::<Vec<_>>();
+
+        views.sort_by_key(|(order, entity, _)| (*order, *entity));
+
+        match views.is_empty() {
+            true => vec![RenderView {
+                camera: self
+                    .main_camera
+                    .filter(|entity| world.satisfies::<&CameraWgpu>(*entity).unwrap_or(false)),
+                viewport: None,
+            }],
+            false => views
+                .into_iter()
+                .map(|(_, entity, viewport)| RenderView {
+                    camera: Some(entity),
+                    viewport: Some(viewport),
+                })
+                .collect(),
+        }
+    }
+
+    /// Ranges of consecutive enabled pipelines that all read depth or all don't, in draw
+    /// order. Always has at least one range, for the pass clearing the target.
+    fn depth_runs(pipelines: &[RendererData]) -> Vec<(std::ops::Range<usize>, bool)> {
+        let mut runs: Vec<(std::ops::Range<usize>, bool)> = Vec::new();
+
+        pipelines
+            .iter()
+            .enumerate()
+            .filter(|(_, pipeline_data)| pipeline_data.enabled)
+            .for_each(|(index, pipeline_data)| {
+                let reads_depth = pipeline_data.pipeline.reads_depth();
+
+                match runs.last_mut() {
+                    Some((range, run_reads_depth)) if *run_reads_depth == reads_depth => {
+                        range.end = index + 1;
+                    }
+                    _ => runs.push((index..index + 1, reads_depth)),
+                }
+            });
+
+        // The first pass clears, so it can't be skipped even if it has nothing to draw
+        if runs.first().is_none_or(|(_, reads_depth)| *reads_depth) {
+            runs.insert(0, (0..0, false));
+        }
+
+        runs
+    }
+
+    fn render_pipelines(
+        pipelines: &mut [RendererData],
+        shared: &mut SharedRenderResources,
+        world: &mut World,
+        render_pass: &mut wgpu::RenderPass,
+        views: &[RenderView],
+        reads_depth: bool,
+    ) {
+        views.iter().for_each(|view| {
+            shared.set_active_camera(view.camera);
+
+            if let Some((x, y, width, height)) = view.viewport {
+                render_pass.set_viewport(x, y, width, height, 0., 1.);
+            }
+
+            pipelines
+                .iter_mut()
+                .filter(|pipeline_data| {
+                    pipeline_data.enabled && pipeline_data.pipeline.reads_depth() == reads_depth
+                })
+                .for_each(|pipeline_data| {
+                    shared.stats_mut().set_scope(pipeline_data.name);
+
+                    let _scope = profiler::scope(pipeline_data.name, "render");
+                    pipeline_data.pipeline.render(render_pass, shared, world)
+                });
+        });
     }
 }
 
+struct RenderView {
+    camera: Option<Entity>,
+    viewport: Option<(f32, f32, f32, f32)>,
+}
+
 impl RendererState {
-    pub fn add_pipeline<R: Renderer>(&mut self, world: &mut World, priority: usize) {
-        let pipeline = Box::new(R::new(&self.core, &mut self.shared_resources, world));
+    /// Add a pipeline drawn where `order` places it, failing if it can't be placed.
+    pub fn add_pipeline<R: Renderer>(
+        &mut self,
+        world: &mut World,
+        order: PipelineOrder,
+    ) -> Result<(), PipelineOrderError> {
+        let slot = PipelineSlot {
+            id: PipelineId::of::<R>(),
+            group: order.group.unwrap_or_else(R::group),
+            before: order.before,
+            after: order.after,
+        };
+
+        if self.pipelines.iter().any(|data| data.slot.id == slot.id) {
+            return Err(PipelineOrderError::AlreadyAdded(slot.id.name));
+        }
+
+        // Validate before building anything
+        let mut slots = self
+            .pipelines
+            .iter()
+            .map(|data| data.slot.clone())
+            .collect::<Vec<_>>();
+        slots.push(slot.clone());
+        let order = ordering::resolve(&slots)?;
 
-        self.pipelines.push(RendererData { priority, pipeline });
-        self.pipelines.sort_by_key(|val| val.priority);
+        #[cfg(not(all(feature = "hot-reload", not(target_arch = "wasm32"))))]
+        let pipeline = RendererData::build::<R>(&self.core, &mut self.shared_resources, world);
+
+        #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+        let (pipeline, shaders) = hot_reload::ShaderWatch::track(|| {
+            RendererData::build::<R>(&self.core, &mut self.shared_resources, world)
+        });
+
+        self.pipelines.push(RendererData {
+            name: stats::pipeline_name::<R>(),
+            slot,
+            enabled: true,
+            pipeline,
+            build: RendererData::build::<R>,
+            #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+            shaders,
+        });
+
+        let mut pipelines = self.pipelines.drain(..).map(Some).collect::<Vec<_>>();
+        self.pipelines = order
+            .into_iter()
+            .filter_map(|index| pipelines[index].take())
+            .collect();
+
+        Ok(())
+    }
+
+    /// Rebuild pipelines whose shaders changed on disk. Pipelines that fail to build log
+    /// the error and keep running with their previous shaders.
+    #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+    fn reload_changed_shaders(&mut self, world: &mut World) {
+        const POLL_INTERVAL: web_time::Duration = web_time::Duration::from_millis(500);
+
+        if self.last_shader_check.elapsed() < POLL_INTERVAL {
+            return;
+        }
+        self.last_shader_check = Instant::now();
+
+        let core = &self.core;
+        let shared = &mut self.shared_resources;
+
+        self.pipelines.iter_mut().for_each(|pipeline_data| {
+            if !pipeline_data.shaders.changed() {
+                return;
+            }
+
+            log::info!("Reloading shaders for {}", pipeline_data.name);
+
+            core.device.push_error_scope(wgpu::ErrorFilter::Validation);
+            let (pipeline, shaders) =
+                hot_reload::ShaderWatch::track(|| (pipeline_data.build)(core, shared, world));
+
+            match pollster::block_on(core.device.pop_error_scope()) {
+                Some(e) => log::error!("Unable to reload {}: {}", pipeline_data.name, e),
+                None => {
+                    pipeline_data.pipeline = pipeline;
+                    pipeline_data.shaders = shaders;
+                }
+            }
+        });
+    }
+
+    /// Names of the added pipelines in draw order, along with whether they're enabled.
+    pub fn pipelines(&self) -> impl Iterator<Item = (&'static str, bool)> + '_ {
+        self.pipelines
+            .iter()
+            .map(|pipeline_data| (pipeline_data.name, pipeline_data.enabled))
+    }
+
+    /// Enable or disable the pipeline named `name`, returning false if there isn't one.
+    pub fn set_pipeline_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self
+            .pipelines
+            .iter_mut()
+            .find(|pipeline_data| pipeline_data.name == name)
+        {
+            Some(pipeline_data) => {
+                pipeline_data.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[inline]
+    pub fn pipeline_enabled(&self, name: &str) -> Option<bool> {
+        self.pipelines
+            .iter()
+            .find(|pipeline_data| pipeline_data.name == name)
+            .map(|pipeline_data| pipeline_data.enabled)
+    }
+
+    /// Add a fullscreen effect, run in `order` with other effects. Needs
+    /// `RendererConfig::post_processing`.
+    pub fn add_post_process<P: PostProcess>(&mut self, order: usize) {
+        match &mut self.post_process {
+            Some(post_process) => {
+                post_process.add::<P>(&self.core, &mut self.shared_resources, order)
+            }
+            None => log::warn!(
+                "Post processing disabled - not adding {}",
+                stats::pipeline_name::<P>()
+            ),
+        }
+    }
+
+    /// Settings of an added post process effect.
+    #[inline]
+    pub fn post_process_mut<P: PostProcess>(&mut self) -> Option<&mut P> {
+        self.post_process.as_mut()?.get_mut::<P>()
+    }
+
+    #[inline]
+    pub fn shared_resources(&self) -> &SharedRenderResources {
+        &self.shared_resources
+    }
+
+    /// Modules pipelines can `#import` in their shaders.
+    #[inline]
+    pub fn shader_library_mut(&mut self) -> &mut shader_library::ShaderLibrary {
+        self.shared_resources.shader_library_mut()
+    }
+
+    #[inline]
+    pub fn debug_lines_mut(&mut self) -> &mut DebugLines {
+        self.shared_resources.debug_lines_mut()
+    }
+
+    #[inline]
+    pub fn load_texture(&self, texture: Texture) -> LoadedTexture {
+        LoadedTexture::load_texture(&self.core.device, &self.shared_resources, texture)
+    }
+
+    /// Load a texture with a generated mip chain and trilinear filtering.
+    #[inline]
+    pub fn load_texture_with_mipmaps(&self, texture: Texture) -> LoadedTexture {
+        LoadedTexture::load_texture_with_mipmaps(
+            &self.core.device,
+            &self.core.queue,
+            &self.shared_resources,
+            texture,
+            None,
+        )
+    }
+
+    /// Offscreen texture for a camera to render into. Add it to a camera entity.
+    pub fn create_render_target(&self, size: Size<u32>) -> RenderTarget {
+        let size = Size::new(size.width.max(1), size.height.max(1));
+
+        let texture = Texture::create_render_texture(
+            &self.core.device,
+            size,
+            self.core.target_format,
+            "Camera Render Target",
+        );
+        let depth = Texture::create_depth_texture(
+            &self.core.device,
+            size,
+            self.core.depth_format,
+            "Camera Render Target",
+        );
+
+        RenderTarget::new(
+            LoadedTexture::load_texture(&self.core.device, &self.shared_resources, texture),
+            depth,
+        )
     }
 
     pub fn spawn_camera<C: CameraUniform + 'static + Send + Sync>(
@@ -197,15 +1233,93 @@ impl RendererState {
     pub fn core(&self) -> &RendererCore {
         &self.core
     }
+
+    /// Last rendered frame of a headless renderer. `None` when rendering to a window.
+    pub fn read_frame(&self) -> Option<image::RgbaImage> {
+        self.headless_target
+            .as_ref()?
+            .read_pixels(&self.core.device, &self.core.queue)
+    }
+
+    #[inline]
+    pub fn stats(&self) -> &RenderStats {
+        self.shared_resources.stats()
+    }
+
+    #[inline]
+    pub fn load_font(&mut self, data: &[u8]) {
+        self.shared_resources.text_resources_mut().load_font(data);
+    }
+
+    #[inline]
+    pub fn preload_fonts(&mut self, preload: &FontPreload) -> &FontLoadStatus {
+        self.shared_resources.text_resources_mut().preload(
+            &self.core.device,
+            &self.core.queue,
+            preload,
+        )
+    }
+
+    #[inline]
+    pub fn font_status(&self) -> &FontLoadStatus {
+        self.shared_resources.text_resources().font_status()
+    }
+
+    #[inline]
+    pub fn debug_settings(&self) -> &DebugSettings {
+        self.shared_resources.debug_settings()
+    }
+
+    #[inline]
+    pub fn ambient_light(&self) -> &AmbientLight {
+        self.shared_resources.ambient_light()
+    }
+
+    #[inline]
+    pub fn set_ambient_light(&mut self, ambient: AmbientLight) {
+        self.shared_resources.set_ambient_light(ambient);
+    }
+
+    #[inline]
+    pub fn shadow_settings(&self) -> &ShadowSettings {
+        self.shared_resources.shadow_settings()
+    }
+
+    /// Apply shadow settings, recreating the shadow map if its resolution changed.
+    #[inline]
+    pub fn set_shadow_settings(&mut self, settings: ShadowSettings) {
+        self.shared_resources
+            .set_shadow_settings(&self.core.device, settings);
+    }
+
+    #[inline]
+    pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) {
+        self.core.set_present_mode(mode);
+    }
+
+    #[inline]
+    pub fn set_debug_settings(&mut self, settings: DebugSettings) {
+        self.shared_resources
+            .set_debug_settings(&self.core.queue, settings);
+    }
 }
 
 //====================================================================
 
 pub struct RendererCore {
+    instance: Arc<wgpu::Instance>,
+    renderer_config: RendererConfig,
     device: wgpu::Device,
     queue: wgpu::Queue,
-    surface: wgpu::Surface<'static>,
+    /// Set from the device lost callback.
+    device_lost: Arc<AtomicBool>,
+    /// None when headless.
+    surface: Option<wgpu::Surface<'static>>,
     config: wgpu::SurfaceConfiguration,
+    target_format: wgpu::TextureFormat,
+    post_processing: bool,
+    depth_format: wgpu::TextureFormat,
+    present_modes: Vec<wgpu::PresentMode>,
 }
 
 impl RendererCore {
@@ -223,86 +1337,364 @@ impl RendererCore {
     pub fn config(&self) -> &wgpu::SurfaceConfiguration {
         &self.config
     }
+
+    #[inline]
+    pub fn depth_format(&self) -> wgpu::TextureFormat {
+        self.depth_format
+    }
+
+    /// Whether the device was lost, e.g. from a driver reset. The renderer recreates it on
+    /// the next frame.
+    #[inline]
+    pub fn device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Acquire)
+    }
+
+    /// Rendering into an offscreen texture rather than a window.
+    #[inline]
+    pub fn headless(&self) -> bool {
+        self.surface.is_none()
+    }
+
+    /// Format negotiated with the surface, or of the offscreen texture when headless.
+    #[inline]
+    pub fn surface_format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+
+    /// Format pipelines render into. The surface format, or [`post_process::HDR_FORMAT`]
+    /// with post processing enabled. Custom pipelines should target this format.
+    #[inline]
+    pub fn target_format(&self) -> wgpu::TextureFormat {
+        self.target_format
+    }
+
+    /// Whether the scene is rendered offscreen and post processed before being shown.
+    #[inline]
+    pub fn post_processing(&self) -> bool {
+        self.post_processing
+    }
+
+    /// Whether shader outputs are converted from linear to sRGB before being shown.
+    /// Always true with post processing, as the scene is kept linear until the final blit.
+    #[inline]
+    pub fn gamma_encoded(&self) -> bool {
+        self.post_processing() || self.config.format.is_srgb()
+    }
+
+    #[inline]
+    pub fn surface_size(&self) -> Size<u32> {
+        Size::new(self.config.width, self.config.height)
+    }
+
+    #[inline]
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.config.present_mode
+    }
+
+    /// Present modes supported by the surface. The `Auto` modes are always supported.
+    #[inline]
+    pub fn supported_present_modes(&self) -> &[wgpu::PresentMode] {
+        &self.present_modes
+    }
+
+    fn pick_present_mode(&self, mode: wgpu::PresentMode) -> wgpu::PresentMode {
+        match mode {
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync => mode,
+            _ if self.present_modes.contains(&mode) => mode,
+            _ => {
+                log::warn!("Present mode {:?} not supported - using AutoVsync", mode);
+                wgpu::PresentMode::AutoVsync
+            }
+        }
+    }
+
+    /// Reconfigure the surface with a new present mode.
+    pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) {
+        let mode = self.pick_present_mode(mode);
+        if mode == self.config.present_mode {
+            return;
+        }
+
+        log::info!("Setting present mode {:?}", mode);
+
+        self.config.present_mode = mode;
+        self.configure_surface();
+    }
+
+    #[inline]
+    fn configure_surface(&self) {
+        // Minimized windows report a zero size, which can't be configured
+        if self.config.width == 0 || self.config.height == 0 {
+            return;
+        }
+
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.config);
+        }
+    }
 }
 
 impl RendererCore {
-    pub async fn new(window: impl Into<SurfaceTarget<'static>>, window_size: Size<u32>) -> Self {
+    pub async fn new(
+        window: impl Into<SurfaceTarget<'static>>,
+        window_size: Size<u32>,
+        renderer_config: &RendererConfig,
+    ) -> Result<Self, RendererError> {
         log::debug!("Creating core wgpu renderer components.");
 
         log::debug!("Window inner size = {:?}", window_size);
 
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        let instance = Self::create_instance(renderer_config);
+
+        let surface = instance.create_surface(window)?;
+        let (adapter, device, queue) =
+            Self::request_device(&instance, Some(&surface), renderer_config).await?;
+
+        let surface_capabilities = surface.get_capabilities(&adapter);
+        if surface_capabilities.formats.is_empty() || surface_capabilities.alpha_modes.is_empty() {
+            return Err(RendererError::UnsupportedSurface);
+        }
+
+        let surface_format = renderer_config
+            .surface_format
+            .pick(&surface_capabilities.formats);
+
+        log::info!(
+            "Using surface format {:?} (gamma encoded: {})",
+            surface_format,
+            surface_format.is_srgb()
+        );
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width: window_size.width,
+            height: window_size.height,
+            present_mode: wgpu::PresentMode::AutoNoVsync,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: surface_capabilities.alpha_modes[0],
+            view_formats: vec![],
+        };
+
+        let mut core = Self {
+            instance: Arc::new(instance),
+            renderer_config: renderer_config.clone(),
+            device_lost: Self::watch_device_lost(&device),
+            device,
+            queue,
+            surface: Some(surface),
+            config,
+            target_format: match renderer_config.post_processing {
+                true => post_process::HDR_FORMAT,
+                false => surface_format,
+            },
+            post_processing: renderer_config.post_processing,
+            depth_format: renderer_config.depth_format,
+            present_modes: surface_capabilities.present_modes,
+        };
+
+        core.config.present_mode = core.pick_present_mode(renderer_config.present_mode);
+        log::info!("Using present mode {:?}", core.config.present_mode);
+
+        core.configure_surface();
+
+        log::debug!("Successfully created core wgpu components.");
+
+        Ok(core)
+    }
+
+    pub async fn new_headless(
+        size: Size<u32>,
+        renderer_config: &RendererConfig,
+    ) -> Result<Self, RendererError> {
+        log::debug!("Creating headless wgpu renderer components.");
+
+        let instance = Self::create_instance(renderer_config);
+        let (_, device, queue) = Self::request_device(&instance, None, renderer_config).await?;
+
+        let format = match renderer_config.surface_format {
+            SurfaceFormatPreference::Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+            SurfaceFormatPreference::Linear | SurfaceFormatPreference::TenBit => {
+                wgpu::TextureFormat::Rgba8Unorm
+            }
+        };
+
+        log::info!("Rendering headless into {:?} at {:?}", format, size);
+
+        // Never used to configure a surface, only to describe the offscreen target
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::AutoNoVsync,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![],
+        };
+
+        Ok(Self {
+            instance: Arc::new(instance),
+            renderer_config: renderer_config.clone(),
+            device_lost: Self::watch_device_lost(&device),
+            device,
+            queue,
+            surface: None,
+            config,
+            target_format: match renderer_config.post_processing {
+                true => post_process::HDR_FORMAT,
+                false => format,
+            },
+            post_processing: renderer_config.post_processing,
+            depth_format: renderer_config.depth_format,
+            present_modes: Vec::new(),
+        })
+    }
+
+    /// New device for the same surface, after the current one was lost.
+    async fn recreate(&mut self) -> Result<Self, RendererError> {
+        let (_, device, queue) =
+            Self::request_device(&self.instance, self.surface.as_ref(), &self.renderer_config)
+                .await?;
+
+        let core = Self {
+            instance: self.instance.clone(),
+            renderer_config: self.renderer_config.clone(),
+            device_lost: Self::watch_device_lost(&device),
+            device,
+            queue,
+            surface: self.surface.take(),
+            config: self.config.clone(),
+            target_format: self.target_format,
+            post_processing: self.post_processing,
+            depth_format: self.depth_format,
+            present_modes: self.present_modes.clone(),
+        };
+
+        core.configure_surface();
+
+        Ok(core)
+    }
+
+    fn watch_device_lost(device: &wgpu::Device) -> Arc<AtomicBool> {
+        let lost = Arc::new(AtomicBool::new(false));
+        let flag = lost.clone();
+
+        device.set_device_lost_callback(move |reason, message| {
+            // Also called when the device is dropped or destroyed on purpose
+            if reason == wgpu::DeviceLostReason::Unknown {
+                log::error!("Gpu device lost: {}", message);
+                flag.store(true, Ordering::Release);
+            }
+        });
+
+        lost
+    }
+
+    fn create_instance(renderer_config: &RendererConfig) -> wgpu::Instance {
+        wgpu::Instance::new(wgpu::InstanceDescriptor {
             #[cfg(not(target_arch = "wasm32"))]
             backends: wgpu::Backends::PRIMARY,
             #[cfg(target_arch = "wasm32")]
             backends: wgpu::Backends::GL,
+            flags: match renderer_config.debug {
+                true => wgpu::InstanceFlags::debugging(),
+                false => wgpu::InstanceFlags::from_build_config(),
+            },
             ..Default::default()
-        });
-
-        // let surface = instance.create_surface(window.0.clone()).unwrap();
-        let surface = instance.create_surface(window).unwrap();
+        })
+    }
 
+    async fn request_device(
+        instance: &wgpu::Instance,
+        surface: Option<&wgpu::Surface<'static>>,
+        renderer_config: &RendererConfig,
+    ) -> Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue), RendererError> {
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
                 power_preference: wgpu::PowerPreference::default(),
                 force_fallback_adapter: false,
-                compatible_surface: Some(&surface),
+                compatible_surface: surface,
             })
             .await
-            .unwrap();
+            .ok_or(RendererError::NoAdapter)?;
 
         log::debug!("Chosen device adapter: {:#?}", adapter.get_info());
 
+        if let Some(trace_path) = &renderer_config.trace_path {
+            #[cfg(not(feature = "trace"))]
+            log::warn!("Trace path set but the renderer 'trace' feature is disabled");
+
+            if let Err(e) = std::fs::create_dir_all(trace_path) {
+                log::warn!("Unable to create trace directory {:?}: {}", trace_path, e);
+            }
+        }
+
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
+                    // Compressed textures fall back to being decoded when unsupported
+                    // Timestamps are only used for gpu timings while profiling
+                    required_features: adapter.features()
+                        & (wgpu::Features::TEXTURE_COMPRESSION_BC
+                            | wgpu::Features::TIMESTAMP_QUERY),
                     #[cfg(target_arch = "wasm32")]
                     required_limits: wgpu::Limits::downlevel_webgl2_defaults(),
                     ..Default::default()
                 },
-                None,
+                renderer_config.trace_path.as_deref(),
             )
-            .await
-            .unwrap();
+            .await?;
 
-        let surface_capabilities = surface.get_capabilities(&adapter);
-
-        let surface_format = surface_capabilities
-            .formats
-            .iter()
-            .find(|format| format.is_srgb())
-            .copied()
-            .unwrap_or(surface_capabilities.formats[0]);
+        Ok((adapter, device, queue))
+    }
+}
 
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: window_size.width,
-            height: window_size.height,
-            present_mode: wgpu::PresentMode::AutoNoVsync,
-            desired_maximum_frame_latency: 2,
-            alpha_mode: surface_capabilities.alpha_modes[0],
-            view_formats: vec![],
-        };
+//====================================================================
 
-        surface.configure(&device, &config);
+struct RendererData {
+    name: &'static str,
+    slot: PipelineSlot,
+    /// Disabled pipelines are neither prepped nor rendered.
+    enabled: bool,
+    pipeline: Box<dyn Renderer>,
 
-        log::debug!("Successfully created core wgpu components.");
+    /// Creates the pipeline again when its shaders change or the device is lost.
+    build: fn(&RendererCore, &mut SharedRenderResources, &mut World) -> Box<dyn Renderer>,
+    #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+    shaders: hot_reload::ShaderWatch,
+}
 
-        Self {
-            device,
-            queue,
-            surface,
-            config,
-        }
+impl RendererData {
+    fn build<R: Renderer>(
+        core: &RendererCore,
+        shared: &mut SharedRenderResources,
+        world: &mut World,
+    ) -> Box<dyn Renderer> {
+        Box::new(R::new(core, shared, world))
     }
 }
 
-//====================================================================
+/// Depth-only pass rendered before everything else each frame, into a texture other passes
+/// sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrePass {
+    /// Depth from the shadow casting light into the shared shadow map, with
+    /// `SharedRenderResources::shadow_camera_bind_group` in place of the camera. Only run
+    /// when something casts shadows.
+    Shadow,
+}
 
-struct RendererData {
-    priority: usize,
-    pipeline: Box<dyn Renderer>,
+impl PrePass {
+    const ALL: [PrePass; 1] = [PrePass::Shadow];
+
+    #[inline]
+    fn label(&self) -> &'static str {
+        match self {
+            PrePass::Shadow => "Shadow Pass",
+        }
+    }
 }
 
 pub trait Renderer: 'static {
@@ -310,10 +1702,42 @@ pub trait Renderer: 'static {
     where
         Self: Sized;
 
+    /// Group the pipeline is drawn in unless placed elsewhere when added.
+    fn group() -> RenderGroup
+    where
+        Self: Sized,
+    {
+        RenderGroup::Opaque
+    }
+
     fn prep(&mut self, core: &RendererCore, shared: &mut SharedRenderResources, world: &mut World);
     fn resize(&mut self, core: &RendererCore) {
         let _ = core;
     }
+
+    /// Pre-passes the pipeline draws into with [`Renderer::render_pre_pass`].
+    fn pre_passes(&self) -> &[PrePass] {
+        &[]
+    }
+
+    /// Draw into `pre_pass`, after the pipeline is prepped. Pipelines must use depth-only
+    /// pipelines targeting [`Texture::DEPTH_FORMAT`].
+    fn render_pre_pass(
+        &mut self,
+        pre_pass: PrePass,
+        render_pass: &mut wgpu::RenderPass,
+        shared: &mut SharedRenderResources,
+        world: &mut World,
+    ) {
+        let _ = (pre_pass, render_pass, shared, world);
+    }
+
+    /// Pipelines that sample `SharedRenderResources::depth_bind_group` must return true.
+    /// They're still drawn in order, in their own pass with a copy of the depth drawn so far.
+    /// Render targets and reflection probes leave them out.
+    fn reads_depth(&self) -> bool {
+        false
+    }
     fn render(
         &mut self,
         render_pass: &mut wgpu::RenderPass,
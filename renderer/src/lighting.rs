@@ -0,0 +1,169 @@
+//====================================================================
+
+use common::GlobalTransform;
+use hecs::World;
+
+use crate::shared::SharedRenderResources;
+
+//====================================================================
+
+pub const MAX_DIRECTIONAL_LIGHTS: usize = 4;
+pub const MAX_POINT_LIGHTS: usize = 32;
+
+/// Light shining in a single direction everywhere, like the sun.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirectionalLight {
+    pub direction: glam::Vec3,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Only the first light casting shadows does, see [`crate::shadow::ShadowSettings`].
+    pub casts_shadows: bool,
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            direction: glam::vec3(-0.3, -1., -0.5),
+            color: [1.; 3],
+            intensity: 1.,
+            casts_shadows: true,
+        }
+    }
+}
+
+/// Light positioned at the entity's [`GlobalTransform`], fading out to nothing at `range`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointLight {
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub range: f32,
+}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        Self {
+            color: [1.; 3],
+            intensity: 1.,
+            range: 10.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AmbientLight {
+    pub color: [f32; 3],
+    pub intensity: f32,
+}
+
+impl Default for AmbientLight {
+    fn default() -> Self {
+        Self {
+            color: [1.; 3],
+            intensity: 0.15,
+        }
+    }
+}
+
+//====================================================================
+
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Debug, Default)]
+struct DirectionalLightRaw {
+    direction: glam::Vec4,
+    color: glam::Vec4,
+}
+
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Debug, Default)]
+struct PointLightRaw {
+    position_range: glam::Vec4,
+    color: glam::Vec4,
+}
+
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Debug)]
+pub(crate) struct LightsUniformRaw {
+    ambient: glam::Vec4,
+    directional_count: u32,
+    point_count: u32,
+    pad: [u32; 2],
+    directional: [DirectionalLightRaw; MAX_DIRECTIONAL_LIGHTS],
+    point: [PointLightRaw; MAX_POINT_LIGHTS],
+}
+
+impl Default for LightsUniformRaw {
+    fn default() -> Self {
+        Self::new(&AmbientLight::default())
+    }
+}
+
+impl LightsUniformRaw {
+    fn new(ambient: &AmbientLight) -> Self {
+        Self {
+            ambient: (glam::Vec3::from(ambient.color) * ambient.intensity).extend(0.),
+            directional_count: 0,
+            point_count: 0,
+            pad: [0; 2],
+            directional: [DirectionalLightRaw::default(); MAX_DIRECTIONAL_LIGHTS],
+            point: [PointLightRaw::default(); MAX_POINT_LIGHTS],
+        }
+    }
+}
+
+//====================================================================
+
+pub(crate) fn sys_prep_lights(
+    world: &mut World,
+    queue: &wgpu::Queue,
+    shared: &mut SharedRenderResources,
+) {
+    let mut raw = LightsUniformRaw::new(shared.ambient_light());
+
+    let mut directional = world.query::<&DirectionalLight>();
+    let directional = directional
+        .iter()
+        .map(|(_, light)| light)
+        .collect::<Vec<_>>();
+
+    let mut point = world.query::<(&PointLight, &GlobalTransform)>();
+    let point = point.iter().map(|(_, light)| light).collect::<Vec<_>>();
+
+    if directional.len() > MAX_DIRECTIONAL_LIGHTS || point.len() > MAX_POINT_LIGHTS {
+        log::trace!(
+            "Too many lights ({} directional, {} point) - extra lights are ignored",
+            directional.len(),
+            point.len()
+        );
+    }
+
+    raw.directional
+        .iter_mut()
+        .zip(&directional)
+        .for_each(|(raw, light)| {
+            *raw = DirectionalLightRaw {
+                direction: light.direction.normalize_or(glam::Vec3::NEG_Y).extend(0.),
+                color: (glam::Vec3::from(light.color) * light.intensity).extend(0.),
+            }
+        });
+
+    raw.point
+        .iter_mut()
+        .zip(&point)
+        .for_each(|(raw, (light, transform))| {
+            *raw = PointLightRaw {
+                position_range: transform.translation().extend(light.range),
+                color: (glam::Vec3::from(light.color) * light.intensity).extend(0.),
+            }
+        });
+
+    raw.directional_count = directional.len().min(MAX_DIRECTIONAL_LIGHTS) as u32;
+    raw.point_count = point.len().min(MAX_POINT_LIGHTS) as u32;
+
+    let stats = shared.stats_mut();
+    stats.set_gauge("directional_lights", raw.directional_count as f64);
+    stats.set_gauge("point_lights", raw.point_count as f64);
+
+    shared.set_lights(queue, &raw);
+}
+
+//====================================================================
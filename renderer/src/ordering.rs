@@ -0,0 +1,183 @@
+//====================================================================
+
+use std::{any::TypeId, error::Error, fmt::Display};
+
+use crate::{stats, Renderer};
+
+//====================================================================
+
+/// Stage of the frame a pipeline draws in. Groups are drawn in order, with pipelines in the
+/// same group drawn in the order they were added unless constrained by a [`PipelineOrder`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RenderGroup {
+    /// Backgrounds such as skyboxes.
+    PreScene,
+    #[default]
+    Opaque,
+    /// Alpha blended geometry, drawn over everything opaque.
+    Transparent,
+    /// Drawn over the scene, e.g. debug lines and labels.
+    Overlay,
+    /// Last of the scene, before any post process effects.
+    PostProcess,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PipelineId {
+    type_id: TypeId,
+    pub name: &'static str,
+}
+
+impl PipelineId {
+    #[inline]
+    pub fn of<R: Renderer>() -> Self {
+        Self {
+            type_id: TypeId::of::<R>(),
+            name: stats::pipeline_name::<R>(),
+        }
+    }
+}
+
+/// Where a pipeline is drawn, given when adding it. Constraints on pipelines that haven't
+/// been added are applied once they are.
+#[derive(Debug, Clone, Default)]
+pub struct PipelineOrder {
+    pub(crate) group: Option<RenderGroup>,
+    pub(crate) before: Vec<PipelineId>,
+    pub(crate) after: Vec<PipelineId>,
+}
+
+impl PipelineOrder {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draw in `group` instead of the pipeline's own [`Renderer::group`].
+    #[inline]
+    pub fn in_group(mut self, group: RenderGroup) -> Self {
+        self.group = Some(group);
+        self
+    }
+
+    #[inline]
+    pub fn before<R: Renderer>(mut self) -> Self {
+        self.before.push(PipelineId::of::<R>());
+        self
+    }
+
+    #[inline]
+    pub fn after<R: Renderer>(mut self) -> Self {
+        self.after.push(PipelineId::of::<R>());
+        self
+    }
+}
+
+//--------------------------------------------------
+
+#[derive(Debug)]
+pub enum PipelineOrderError {
+    AlreadyAdded(&'static str),
+    /// Constraint against the group order, e.g. an overlay drawn before an opaque pipeline.
+    GroupConflict {
+        first: &'static str,
+        second: &'static str,
+    },
+    /// Constraints that can't all be met.
+    Cycle(&'static str),
+}
+
+impl Error for PipelineOrderError {}
+
+impl Display for PipelineOrderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PipelineOrderError::AlreadyAdded(name) => {
+                write!(f, "Pipeline {} has already been added", name)
+            }
+            PipelineOrderError::GroupConflict { first, second } => write!(
+                f,
+                "{} can't be drawn before {} as it's in a later group",
+                first, second
+            ),
+            PipelineOrderError::Cycle(name) => {
+                write!(f, "Ordering constraints of {} form a cycle", name)
+            }
+        }
+    }
+}
+
+//====================================================================
+
+/// Ordering of a single added pipeline.
+#[derive(Debug, Clone)]
+pub(crate) struct PipelineSlot {
+    pub id: PipelineId,
+    pub group: RenderGroup,
+    pub before: Vec<PipelineId>,
+    pub after: Vec<PipelineId>,
+}
+
+/// Draw order of `slots` as indices, sorted by group then constraints then the order they
+/// were added in.
+pub(crate) fn resolve(slots: &[PipelineSlot]) -> Result<Vec<usize>, PipelineOrderError> {
+    let index_of = |id: &PipelineId| slots.iter().position(|slot| slot.id == *id);
+
+    // Edges of pipelines drawn before others
+    let mut edges = Vec::new();
+
+    slots.iter().enumerate().for_each(|(index, slot)| {
+        edges.extend(
+            slot.before
+                .iter()
+                .filter_map(index_of)
+                .map(|other| (index, other)),
+        );
+        edges.extend(
+            slot.after
+                .iter()
+                .filter_map(index_of)
+                .map(|other| (other, index)),
+        );
+    });
+
+    for (first, second) in &edges {
+        if slots[*first].group > slots[*second].group {
+            return Err(PipelineOrderError::GroupConflict {
+                first: slots[*first].id.name,
+                second: slots[*second].id.name,
+            });
+        }
+    }
+
+    // Groups already order pipelines across them
+    edges.retain(|(first, second)| slots[*first].group == slots[*second].group);
+
+    let mut incoming = vec![0; slots.len()];
+    edges.iter().for_each(|(_, second)| incoming[*second] += 1);
+
+    let mut order = Vec::with_capacity(slots.len());
+    let mut placed = vec![false; slots.len()];
+
+    while order.len() < slots.len() {
+        let Some(next) = (0..slots.len())
+            .filter(|index| !placed[*index] && incoming[*index] == 0)
+            .min_by_key(|index| (slots[*index].group, *index))
+        else {
+            let stuck = (0..slots.len()).find(|index| !placed[*index]).unwrap();
+            return Err(PipelineOrderError::Cycle(slots[stuck].id.name));
+        };
+
+        placed[next] = true;
+        order.push(next);
+
+        edges
+            .iter()
+            .filter(|(first, _)| *first == next)
+            .for_each(|(_, second)| incoming[*second] -= 1);
+    }
+
+    Ok(order)
+}
+
+//====================================================================
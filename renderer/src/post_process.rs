@@ -0,0 +1,232 @@
+//====================================================================
+
+use std::any::Any;
+
+use common::Size;
+
+use crate::{
+    shared::SharedRenderResources,
+    stats,
+    texture::{LoadedTexture, Texture},
+    tools, RendererCore,
+};
+
+//====================================================================
+
+/// Format the scene is rendered in when post processing is enabled.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Fullscreen effect run on the rendered scene, such as tonemapping or bloom. Added with
+/// `RendererState::add_post_process` and run in order before the result is shown.
+pub trait PostProcess: Any {
+    fn new(core: &RendererCore, shared: &mut SharedRenderResources) -> Self
+    where
+        Self: Sized;
+
+    fn resize(&mut self, core: &RendererCore) {
+        let _ = core;
+    }
+
+    /// Disabled effects are skipped, passing the image on unchanged.
+    fn enabled(&self) -> bool {
+        true
+    }
+
+    /// Render `input` into `output`, both [`HDR_FORMAT`] textures the size of the surface.
+    /// `input`'s bind group uses `SharedRenderResources::texture_bind_group_layout`.
+    fn apply(
+        &mut self,
+        core: &RendererCore,
+        shared: &mut SharedRenderResources,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &LoadedTexture,
+        output: &wgpu::TextureView,
+    );
+}
+
+/// Begin a render pass drawing a single fullscreen triangle into `output`, for shaders with
+/// a `vs_main` like the one in `shaders/blit.wgsl`.
+pub fn fullscreen_pass(
+    encoder: &mut wgpu::CommandEncoder,
+    label: &str,
+    pipeline: &wgpu::RenderPipeline,
+    bind_groups: &[&wgpu::BindGroup],
+    output: &wgpu::TextureView,
+) {
+    let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: output,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+
+    pass.set_pipeline(pipeline);
+    bind_groups
+        .iter()
+        .enumerate()
+        .for_each(|(index, bind_group)| pass.set_bind_group(index as u32, *bind_group, &[]));
+    pass.draw(0..3, 0..1);
+}
+
+//====================================================================
+
+struct PostProcessData {
+    name: &'static str,
+    order: usize,
+    effect: Box<dyn PostProcess>,
+    /// Creates the effect again when the device is lost.
+    build: fn(&RendererCore, &mut SharedRenderResources) -> Box<dyn PostProcess>,
+}
+
+impl PostProcessData {
+    fn build<P: PostProcess>(
+        core: &RendererCore,
+        shared: &mut SharedRenderResources,
+    ) -> Box<dyn PostProcess> {
+        Box::new(P::new(core, shared))
+    }
+}
+
+/// Offscreen scene textures and the effects run on them.
+pub(crate) struct PostProcessChain {
+    /// Scene is rendered into the first, then effects swap between them.
+    targets: [LoadedTexture; 2],
+    effects: Vec<PostProcessData>,
+    blit: wgpu::RenderPipeline,
+}
+
+impl PostProcessChain {
+    pub fn new(core: &RendererCore, shared: &SharedRenderResources) -> Self {
+        let blit = tools::create_pipeline(
+            core.device(),
+            core.surface_format(),
+            "Post Process Blit Pipeline",
+            &[shared.texture_bind_group_layout()],
+            &[],
+            include_str!("shaders/blit.wgsl"),
+            tools::RenderPipelineDescriptor::default(),
+        );
+
+        Self {
+            targets: Self::create_targets(core, shared),
+            effects: Vec::new(),
+            blit,
+        }
+    }
+
+    fn create_targets(core: &RendererCore, shared: &SharedRenderResources) -> [LoadedTexture; 2] {
+        let size = core.surface_size();
+        let size = Size::new(size.width.max(1), size.height.max(1));
+
+        ["Post Process Target A", "Post Process Target B"].map(|label| {
+            let mut texture =
+                Texture::create_render_texture(core.device(), size, HDR_FORMAT, label);
+            texture.sampler = core.device().create_sampler(&wgpu::SamplerDescriptor {
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            });
+
+            LoadedTexture::load_texture(core.device(), shared, texture)
+        })
+    }
+
+    /// Texture the scene is rendered into.
+    #[inline]
+    pub fn scene_view(&self) -> &wgpu::TextureView {
+        &self.targets[0].texture().view
+    }
+
+    pub fn resize(&mut self, core: &RendererCore, shared: &SharedRenderResources) {
+        self.targets = Self::create_targets(core, shared);
+        self.effects
+            .iter_mut()
+            .for_each(|data| data.effect.resize(core));
+    }
+
+    pub fn add<P: PostProcess>(
+        &mut self,
+        core: &RendererCore,
+        shared: &mut SharedRenderResources,
+        order: usize,
+    ) {
+        self.effects.push(PostProcessData {
+            name: stats::pipeline_name::<P>(),
+            order,
+            effect: PostProcessData::build::<P>(core, shared),
+            build: PostProcessData::build::<P>,
+        });
+        self.effects.sort_by_key(|data| data.order);
+    }
+
+    /// Build the effects of `previous` again on a new device. Their settings are reset.
+    pub fn rebuild(
+        &mut self,
+        previous: &PostProcessChain,
+        core: &RendererCore,
+        shared: &mut SharedRenderResources,
+    ) {
+        self.effects = previous
+            .effects
+            .iter()
+            .map(|data| PostProcessData {
+                name: data.name,
+                order: data.order,
+                effect: (data.build)(core, shared),
+                build: data.build,
+            })
+            .collect();
+    }
+
+    pub fn get_mut<P: PostProcess>(&mut self) -> Option<&mut P> {
+        self.effects.iter_mut().find_map(|data| {
+            let effect: &mut dyn Any = data.effect.as_mut();
+            effect.downcast_mut::<P>()
+        })
+    }
+
+    /// Run every enabled effect on the scene and draw the result to `surface`.
+    pub fn run(
+        &mut self,
+        core: &RendererCore,
+        shared: &mut SharedRenderResources,
+        encoder: &mut wgpu::CommandEncoder,
+        surface: &wgpu::TextureView,
+    ) {
+        let mut current = 0;
+
+        self.effects
+            .iter_mut()
+            .filter(|data| data.effect.enabled())
+            .for_each(|data| {
+                shared.stats_mut().set_scope(data.name);
+
+                let (input, output) = match current {
+                    0 => (&self.targets[0], &self.targets[1]),
+                    _ => (&self.targets[1], &self.targets[0]),
+                };
+
+                data.effect
+                    .apply(core, shared, encoder, input, &output.texture().view);
+                current = 1 - current;
+            });
+
+        fullscreen_pass(
+            encoder,
+            "Post Process Blit Pass",
+            &self.blit,
+            &[self.targets[current].bind_group()],
+            surface,
+        );
+    }
+}
+
+//====================================================================
@@ -0,0 +1,218 @@
+//====================================================================
+
+use std::sync::{atomic::AtomicU32, Arc};
+
+use common::GlobalTransform;
+use hecs::{Entity, World};
+
+use crate::{
+    camera::{CameraWgpu, PerspectiveCamera},
+    shared::SharedRenderResources,
+    texture::Texture,
+    RendererCore, WgpuWrapper,
+};
+
+//====================================================================
+
+/// Capture a cube map of the surroundings at the entity's [`GlobalTransform`] for reflective
+/// models inside its box. Reflections are projected onto the box, so it should roughly match
+/// the walls of the room it's in. Rotation and scale are ignored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReflectionProbe {
+    pub half_extents: glam::Vec3,
+    /// Distance inside the box over which the probe fades into the one around it.
+    pub blend_distance: f32,
+    /// Width and height of each cube face.
+    pub resolution: u32,
+    pending: bool,
+}
+
+impl ReflectionProbe {
+    /// Probe captured as soon as it's spawned.
+    #[inline]
+    pub fn new(half_extents: glam::Vec3) -> Self {
+        Self {
+            half_extents,
+            blend_distance: 1.,
+            resolution: 128,
+            pending: true,
+        }
+    }
+
+    /// Probe only captured after [`ReflectionProbe::request_capture`].
+    #[inline]
+    pub fn on_demand(half_extents: glam::Vec3) -> Self {
+        Self {
+            pending: false,
+            ..Self::new(half_extents)
+        }
+    }
+
+    /// Recapture the probe. Probes are captured one per frame, so this may take a few frames.
+    #[inline]
+    pub fn request_capture(&mut self) {
+        self.pending = true;
+    }
+
+    #[inline]
+    pub fn capture_pending(&self) -> bool {
+        self.pending
+    }
+
+    #[inline]
+    pub fn contains(&self, center: glam::Vec3, point: glam::Vec3) -> bool {
+        (point - center).abs().cmple(self.half_extents).all()
+    }
+}
+
+//--------------------------------------------------
+
+pub type ProbeTextureId = u32;
+
+static CURRENT_PROBE_TEXTURE_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Cube map captured by a [`ReflectionProbe`], added to the probe by the renderer.
+#[derive(Clone)]
+pub struct ProbeTexture {
+    id: ProbeTextureId,
+    cube: Arc<WgpuWrapper<Texture>>,
+    depth: Arc<WgpuWrapper<Texture>>,
+}
+
+impl ProbeTexture {
+    fn new(core: &RendererCore, resolution: u32) -> Self {
+        Self {
+            id: CURRENT_PROBE_TEXTURE_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+            cube: Arc::new(WgpuWrapper::new(Texture::create_cube_render_texture(
+                core.device(),
+                resolution,
+                core.target_format(),
+                "Reflection Probe",
+            ))),
+            depth: Arc::new(WgpuWrapper::new(Texture::create_depth_texture(
+                core.device(),
+                common::Size::new(resolution, resolution),
+                core.depth_format(),
+                "Reflection Probe",
+            ))),
+        }
+    }
+
+    #[inline]
+    pub fn id(&self) -> ProbeTextureId {
+        self.id
+    }
+
+    /// Sampled with a cube view, in the renderer's target format.
+    #[inline]
+    pub fn cube(&self) -> &Texture {
+        self.cube.inner()
+    }
+
+    #[inline]
+    pub fn resolution(&self) -> u32 {
+        self.cube().texture.width()
+    }
+
+    #[inline]
+    pub(crate) fn depth(&self) -> &Texture {
+        self.depth.inner()
+    }
+}
+
+//====================================================================
+
+/// Camera rendering one face of a probe. Only has a [`CameraWgpu`], so it's never picked as
+/// the main camera.
+pub(crate) struct ProbeFaceCamera;
+
+/// Look direction and up of each cube face, in the order of the cube's layers.
+const FACES: [(glam::Vec3, glam::Vec3); 6] = [
+    (glam::Vec3::X, glam::Vec3::Y),
+    (glam::Vec3::NEG_X, glam::Vec3::Y),
+    (glam::Vec3::Y, glam::Vec3::NEG_Z),
+    (glam::Vec3::NEG_Y, glam::Vec3::Z),
+    (glam::Vec3::Z, glam::Vec3::Y),
+    (glam::Vec3::NEG_Z, glam::Vec3::Y),
+];
+
+/// Face cameras shared by every probe, respawned if they were despawned with the world.
+pub(crate) fn face_cameras(
+    world: &mut World,
+    core: &RendererCore,
+    shared: &SharedRenderResources,
+    faces: &mut Option<[Entity; 6]>,
+) -> [Entity; 6] {
+    if let Some(faces) = faces {
+        if faces
+            .iter()
+            .all(|face| world.satisfies::<&ProbeFaceCamera>(*face).unwrap_or(false))
+        {
+            return *faces;
+        }
+    }
+
+    let camera = PerspectiveCamera::default();
+    let spawned = [(); 6].map(|_| {
+        world.spawn((
+            ProbeFaceCamera,
+            shared.create_camera(core.device(), &camera),
+        ))
+    });
+
+    *faces = Some(spawned);
+    spawned
+}
+
+/// Take the next probe waiting to be captured, pointing the face cameras at it.
+pub(crate) fn prep_capture(
+    world: &mut World,
+    core: &RendererCore,
+    faces: &[Entity; 6],
+) -> Option<ProbeTexture> {
+    let (entity, position, resolution) = world
+        .query_mut::<(&mut ReflectionProbe, &GlobalTransform)>()
+        .into_iter()
+        .find(|(_, (probe, _))| probe.pending)
+        .map(|(entity, (probe, transform))| {
+            probe.pending = false;
+            (entity, transform.translation(), probe.resolution.max(1))
+        })?;
+
+    let recreate = world
+        .get::<&ProbeTexture>(entity)
+        .map_or(true, |texture| texture.resolution() != resolution);
+
+    if recreate {
+        log::debug!("Creating {0}x{0} reflection probe texture", resolution);
+        world
+            .insert_one(entity, ProbeTexture::new(core, resolution))
+            .ok();
+    }
+
+    faces.iter().zip(FACES).for_each(|(face, (direction, up))| {
+        let camera = PerspectiveCamera {
+            up,
+            aspect: 1.,
+            fovy: std::f32::consts::FRAC_PI_2,
+            z_near: 0.05,
+            ..Default::default()
+        };
+
+        let transform = glam::Affine3A::from_rotation_translation(
+            glam::Quat::from_rotation_arc(glam::Vec3::Z, direction),
+            position,
+        );
+
+        if let Ok(mut face) = world.get::<&mut CameraWgpu>(*face) {
+            face.update_camera(core.queue(), &camera, &transform);
+        }
+    });
+
+    world
+        .get::<&ProbeTexture>(entity)
+        .ok()
+        .map(|texture| (*texture).clone())
+}
+
+//====================================================================
@@ -0,0 +1,187 @@
+//====================================================================
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fmt::Display,
+};
+
+//====================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShaderError {
+    /// No module with this name, or no file at this path.
+    NotFound(String),
+    /// Directive without a name, e.g. `#include` missing its quotes.
+    Malformed(String),
+}
+
+impl Error for ShaderError {}
+
+impl Display for ShaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShaderError::NotFound(name) => write!(f, "Shader module {} not found", name),
+            ShaderError::Malformed(line) => write!(f, "Malformed shader directive '{}'", line),
+        }
+    }
+}
+
+//====================================================================
+
+enum ShaderModule {
+    /// Read from disk with the `hot-reload` feature, see [`crate::include_shader`].
+    File {
+        path: &'static str,
+        embedded: &'static str,
+    },
+    Source(Cow<'static, str>),
+}
+
+pub type ShaderLoader = Box<dyn Fn(&str) -> Option<String>>;
+
+/// Shared shader code pulled into shaders with directives on their own line:
+/// - `#import renderer::camera` for a module added by name.
+/// - `#include "shaders/common.wgsl"` for a module added by path, otherwise a file read with
+///   the loader, such as the engine's asset files.
+///
+/// Each module is only included once per shader, so modules can import each other.
+/// Built in modules are `renderer::camera`, `renderer::globals`, `renderer::lighting` and
+/// `renderer::debug`, holding the structs of the shared uniforms.
+pub struct ShaderLibrary {
+    modules: BTreeMap<String, ShaderModule>,
+    loader: Option<ShaderLoader>,
+}
+
+impl Default for ShaderLibrary {
+    fn default() -> Self {
+        let mut library = Self {
+            modules: BTreeMap::new(),
+            loader: None,
+        };
+
+        crate::add_shader_module!(
+            library,
+            "renderer::camera",
+            "src/shaders/modules/camera.wgsl"
+        );
+        crate::add_shader_module!(
+            library,
+            "renderer::globals",
+            "src/shaders/modules/globals.wgsl"
+        );
+        crate::add_shader_module!(
+            library,
+            "renderer::lighting",
+            "src/shaders/modules/lighting.wgsl"
+        );
+        crate::add_shader_module!(library, "renderer::debug", "src/shaders/modules/debug.wgsl");
+
+        library
+    }
+}
+
+impl ShaderLibrary {
+    /// Add or replace a module.
+    #[inline]
+    pub fn add(&mut self, name: impl Into<String>, source: impl Into<Cow<'static, str>>) {
+        self.modules
+            .insert(name.into(), ShaderModule::Source(source.into()));
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn add_file(
+        &mut self,
+        name: impl Into<String>,
+        path: &'static str,
+        embedded: &'static str,
+    ) {
+        self.modules
+            .insert(name.into(), ShaderModule::File { path, embedded });
+    }
+
+    #[inline]
+    pub fn contains(&self, name: &str) -> bool {
+        self.modules.contains_key(name)
+    }
+
+    /// Read `#include` files that aren't modules, returning None if there's no such file.
+    #[inline]
+    pub fn set_loader(&mut self, loader: impl Fn(&str) -> Option<String> + 'static) {
+        self.loader = Some(Box::new(loader));
+    }
+
+    /// Replace every directive in `source` with the module it names.
+    pub fn process<'a>(&self, source: &'a str) -> Result<Cow<'a, str>, ShaderError> {
+        if !source.lines().any(|line| directive(line).is_some()) {
+            return Ok(Cow::Borrowed(source));
+        }
+
+        let mut out = String::with_capacity(source.len());
+        self.process_into(source, &mut out, &mut BTreeSet::new())?;
+        Ok(Cow::Owned(out))
+    }
+
+    fn process_into(
+        &self,
+        source: &str,
+        out: &mut String,
+        included: &mut BTreeSet<String>,
+    ) -> Result<(), ShaderError> {
+        source.lines().try_for_each(|line| {
+            let Some(directive) = directive(line) else {
+                out.push_str(line);
+                out.push('\n');
+                return Ok(());
+            };
+
+            let name = directive?;
+            if !included.insert(name.to_string()) {
+                return Ok(());
+            }
+
+            let module = self.load(name)?;
+            self.process_into(&module, out, included)
+        })
+    }
+
+    fn load(&self, name: &str) -> Result<Cow<'static, str>, ShaderError> {
+        match self.modules.get(name) {
+            Some(ShaderModule::File { path, embedded }) => {
+                Ok(crate::tools::load_shader(path, embedded))
+            }
+            Some(ShaderModule::Source(source)) => Ok(source.clone()),
+            None => self
+                .loader
+                .as_ref()
+                .and_then(|loader| loader(name))
+                .map(Cow::Owned)
+                .ok_or_else(|| ShaderError::NotFound(name.to_string())),
+        }
+    }
+}
+
+/// Name of the module a directive line refers to, or None if the line isn't a directive.
+fn directive(line: &str) -> Option<Result<&str, ShaderError>> {
+    let line = line.trim();
+
+    if let Some(name) = line.strip_prefix("#import") {
+        let name = name.trim();
+        return Some(match name.is_empty() {
+            true => Err(ShaderError::Malformed(line.to_string())),
+            false => Ok(name),
+        });
+    }
+
+    let path = line.strip_prefix("#include")?.trim();
+    Some(
+        path.strip_prefix('"')
+            .and_then(|path| path.strip_suffix('"'))
+            .filter(|path| !path.is_empty())
+            .ok_or_else(|| ShaderError::Malformed(line.to_string())),
+    )
+}
+
+//====================================================================
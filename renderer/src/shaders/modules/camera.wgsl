@@ -0,0 +1,9 @@
+//====================================================================
+// Camera uniform, see `SharedRenderResources::camera_bind_group_layout`
+
+struct Camera {
+    projection: mat4x4<f32>,
+    position: vec3<f32>,
+}
+
+//====================================================================
@@ -0,0 +1,10 @@
+//====================================================================
+// Debug view override, see `SharedRenderResources::debug_bind_group_layout`
+
+struct DebugOverride {
+    mode: u32,
+    palette_len: u32,
+    palette: array<vec4<f32>, 8>,
+}
+
+//====================================================================
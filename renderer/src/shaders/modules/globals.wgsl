@@ -0,0 +1,11 @@
+//====================================================================
+// Frame globals, see `SharedRenderResources::globals_bind_group_layout`
+
+struct Globals {
+    time: f32,
+    delta: f32,
+    frame: u32,
+    resolution: vec2<f32>,
+}
+
+//====================================================================
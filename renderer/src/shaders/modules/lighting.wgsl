@@ -0,0 +1,33 @@
+//====================================================================
+// Scene lights, see `SharedRenderResources::lights_bind_group_layout`
+
+struct DirectionalLight {
+    direction: vec4<f32>,
+    color: vec4<f32>,
+}
+
+struct PointLight {
+    position_range: vec4<f32>,
+    color: vec4<f32>,
+}
+
+struct Lights {
+    ambient: vec4<f32>,
+    directional_count: u32,
+    point_count: u32,
+    directional: array<DirectionalLight, 4>,
+    point: array<PointLight, 32>,
+}
+
+// Light index when nothing casts shadows
+const NO_SHADOW: u32 = 0xffffffffu;
+
+struct Shadow {
+    view_projection: mat4x4<f32>,
+    bias: f32,
+    normal_bias: f32, // World units
+    texel_size: f32, // In uvs
+    light: u32, // Directional light casting the shadows
+}
+
+//====================================================================
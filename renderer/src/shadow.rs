@@ -0,0 +1,237 @@
+//====================================================================
+
+use common::Size;
+use hecs::{Entity, World};
+
+use crate::{
+    camera::{self, CameraUniformRaw, CameraWgpu},
+    lighting::{DirectionalLight, MAX_DIRECTIONAL_LIGHTS},
+    shared::SharedRenderResources,
+    texture::Texture,
+    tools,
+};
+
+//====================================================================
+
+/// Shadows cast by the first [`DirectionalLight`] with `casts_shadows`, over a square area
+/// centered on the main camera. Pipelines draw into the shadow map in the
+/// [`crate::PrePass::Shadow`] pre-pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    /// Width and height of the shadow map in texels.
+    pub resolution: u32,
+    /// Distance from the camera shadows reach out to.
+    pub extent: f32,
+    /// Length of the area along the light's direction that casts shadows.
+    pub depth: f32,
+    /// Depth offset against surfaces shadowing themselves.
+    pub bias: f32,
+    /// Offset along surface normals in shadow map texels, against acne on surfaces at
+    /// grazing angles to the light.
+    pub normal_bias: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            resolution: 2048,
+            extent: 30.,
+            depth: 200.,
+            bias: 0.002,
+            normal_bias: 1.5,
+        }
+    }
+}
+
+//====================================================================
+
+/// Light index of the shadow uniform when nothing casts shadows. Must match the model shader.
+const NO_SHADOW: u32 = u32::MAX;
+
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Debug)]
+pub(crate) struct ShadowUniformRaw {
+    view_projection: glam::Mat4,
+    bias: f32,
+    normal_bias: f32,
+    texel_size: f32,
+    /// Directional light casting the shadows.
+    light: u32,
+}
+
+impl Default for ShadowUniformRaw {
+    fn default() -> Self {
+        Self {
+            view_projection: glam::Mat4::IDENTITY,
+            bias: 0.,
+            normal_bias: 0.,
+            texel_size: 0.,
+            light: NO_SHADOW,
+        }
+    }
+}
+
+/// Depth from the shadow casting light, along with the camera it's rendered with.
+pub(crate) struct ShadowMap {
+    texture: Texture,
+    buffer: wgpu::Buffer,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+}
+
+impl ShadowMap {
+    pub fn new(
+        device: &wgpu::Device,
+        camera_layout: &wgpu::BindGroupLayout,
+        globals_buffer: &wgpu::Buffer,
+        resolution: u32,
+    ) -> Self {
+        let resolution = resolution.clamp(1, device.limits().max_texture_dimension_2d);
+
+        let texture = Texture::create_depth_texture(
+            device,
+            Size::new(resolution, resolution),
+            Texture::DEPTH_FORMAT,
+            "Shadow Map",
+        );
+
+        let buffer = tools::buffer(
+            device,
+            tools::BufferType::Uniform,
+            "Shadow",
+            &[ShadowUniformRaw::default()],
+        );
+
+        let camera_buffer = tools::buffer(
+            device,
+            tools::BufferType::Uniform,
+            "Shadow Camera",
+            &[CameraUniformRaw::new(
+                glam::Mat4::IDENTITY,
+                glam::Vec3::ZERO,
+            )],
+        );
+
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shadow Camera Bind Group"),
+            layout: camera_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: globals_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            texture,
+            buffer,
+            camera_buffer,
+            camera_bind_group,
+        }
+    }
+
+    #[inline]
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    #[inline]
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    #[inline]
+    pub fn camera_bind_group(&self) -> &wgpu::BindGroup {
+        &self.camera_bind_group
+    }
+
+    #[inline]
+    pub fn resolution(&self) -> u32 {
+        self.texture.texture.width()
+    }
+
+    pub fn write(&self, queue: &wgpu::Queue, shadow: &ShadowUniformRaw, camera: &CameraUniformRaw) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[*shadow]));
+        queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[*camera]));
+    }
+}
+
+//====================================================================
+
+/// Fit the shadow map around the main camera, or turn shadows off when nothing casts them.
+pub(crate) fn sys_prep_shadows(
+    world: &mut World,
+    queue: &wgpu::Queue,
+    shared: &mut SharedRenderResources,
+    main_camera: Option<Entity>,
+) {
+    let settings = *shared.shadow_settings();
+
+    // Indexed the same as the lights uniform
+    let light = world
+        .query_mut::<&DirectionalLight>()
+        .into_iter()
+        .take(MAX_DIRECTIONAL_LIGHTS)
+        .enumerate()
+        .find(|(_, (_, light))| light.casts_shadows)
+        .map(|(index, (_, light))| (index as u32, light.direction));
+
+    let Some((light, direction)) = light.filter(|_| settings.enabled) else {
+        shared.set_shadows(queue, None);
+        return;
+    };
+
+    let focus = main_camera
+        .or_else(|| camera::find_camera(world))
+        .and_then(|entity| world.get::<&CameraWgpu>(entity).ok())
+        .map(|camera| camera.matrices().position)
+        .unwrap_or(glam::Vec3::ZERO);
+
+    let direction = direction.normalize_or(glam::Vec3::NEG_Y);
+    let up = match direction.y.abs() > 0.99 {
+        true => glam::Vec3::Z,
+        false => glam::Vec3::Y,
+    };
+
+    let extent = settings.extent.max(0.01);
+    let texel = extent * 2. / shared.shadow_resolution() as f32;
+
+    // Snapped to whole texels so shadow edges don't shimmer as the camera moves
+    let rotation = glam::Mat4::look_to_rh(glam::Vec3::ZERO, direction, up);
+    let local = rotation.transform_point3(focus);
+    let snapped = glam::vec3(
+        (local.x / texel).round() * texel,
+        (local.y / texel).round() * texel,
+        local.z,
+    );
+    let center = rotation.inverse().transform_point3(snapped);
+
+    let depth = settings.depth.max(0.01);
+    let eye = center - direction * depth * 0.5;
+
+    let view = glam::Mat4::look_to_rh(eye, direction, up);
+    let projection = glam::Mat4::orthographic_rh(-extent, extent, -extent, extent, 0., depth);
+    let view_projection = projection * view;
+
+    let shadow = ShadowUniformRaw {
+        view_projection,
+        bias: settings.bias,
+        normal_bias: settings.normal_bias * texel,
+        texel_size: 1. / shared.shadow_resolution() as f32,
+        light,
+    };
+
+    shared.set_shadows(
+        queue,
+        Some((&shadow, &CameraUniformRaw::new(view_projection, eye))),
+    );
+}
+
+//====================================================================
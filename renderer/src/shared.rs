@@ -1,10 +1,19 @@
 //====================================================================
 
+use std::{borrow::Cow, collections::BTreeSet, sync::Mutex};
+
 use wgpu::util::DeviceExt;
 
 use crate::{
-    camera::{CameraUniform, CameraWgpu},
+    cache::{GpuCache, RetentionPolicy},
+    camera::{CameraUniform, CameraUniformRaw, CameraWgpu},
+    debug::{DebugLines, DebugSettings, DebugUniformRaw},
+    lighting::{AmbientLight, LightsUniformRaw},
+    shader_library::ShaderLibrary,
+    shadow::{ShadowMap, ShadowSettings, ShadowUniformRaw},
+    stats::RenderStats,
     text_shared::TextResources,
+    texture::{MipmapGenerator, TextureId},
     WgpuWrapper,
 };
 
@@ -18,11 +27,104 @@ pub trait Vertex: bytemuck::Pod {
 
 //====================================================================
 
+/// Vertex and index buffers for one of the shared primitive meshes.
+pub struct PrimitiveMesh {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    index_format: wgpu::IndexFormat,
+    index_count: u32,
+}
+
+impl PrimitiveMesh {
+    fn new<V: Vertex, I: bytemuck::Pod>(
+        device: &wgpu::Device,
+        label: &str,
+        vertices: &[V],
+        indices: &[I],
+        index_format: wgpu::IndexFormat,
+    ) -> Self {
+        Self {
+            vertex_buffer: tools::buffer(device, tools::BufferType::Vertex, label, vertices),
+            index_buffer: tools::buffer(device, tools::BufferType::Index, label, indices),
+            index_format,
+            index_count: indices.len() as u32,
+        }
+    }
+
+    #[inline]
+    pub fn vertex_buffer(&self) -> &wgpu::Buffer {
+        &self.vertex_buffer
+    }
+
+    #[inline]
+    pub fn index_buffer(&self) -> &wgpu::Buffer {
+        &self.index_buffer
+    }
+
+    #[inline]
+    pub fn index_format(&self) -> wgpu::IndexFormat {
+        self.index_format
+    }
+
+    #[inline]
+    pub fn index_count(&self) -> u32 {
+        self.index_count
+    }
+
+    /// Bind the vertex buffer to `slot` along with the index buffer.
+    #[inline]
+    pub fn bind(&self, pass: &mut wgpu::RenderPass, slot: u32) {
+        pass.set_vertex_buffer(slot, self.vertex_buffer.slice(..));
+        pass.set_index_buffer(self.index_buffer.slice(..), self.index_format);
+    }
+}
+
+//====================================================================
+
 pub struct SharedRenderResources {
     texture_bind_group_layout: wgpu::BindGroupLayout,
+    cube_texture_bind_group_layout: wgpu::BindGroupLayout,
     camera_bind_group_layout: wgpu::BindGroupLayout,
+    depth_bind_group_layout: wgpu::BindGroupLayout,
+    depth_bind_group: Option<wgpu::BindGroup>,
+    depth_params_buffer: wgpu::Buffer,
+
+    globals: Globals,
+    globals_buffer: wgpu::Buffer,
+    globals_bind_group_layout: wgpu::BindGroupLayout,
+    globals_bind_group: wgpu::BindGroup,
+
+    debug_settings: DebugSettings,
+    debug_lines: DebugLines,
+    debug_buffer: wgpu::Buffer,
+    debug_bind_group_layout: wgpu::BindGroupLayout,
+    debug_bind_group: wgpu::BindGroup,
+
+    ambient_light: AmbientLight,
+    lights_buffer: wgpu::Buffer,
+    lights_bind_group_layout: wgpu::BindGroupLayout,
+    lights_bind_group: wgpu::BindGroup,
+
+    shadow_settings: ShadowSettings,
+    shadow_map: ShadowMap,
+    /// Something casts shadows this frame.
+    shadows_active: bool,
+
+    active_camera: Option<hecs::Entity>,
+    capturing_probe: bool,
+    active_target: Option<TextureId>,
+    /// Targets already warned about being sampled while rendered into.
+    warned_targets: Mutex<BTreeSet<TextureId>>,
+
+    quad: PrimitiveMesh,
+    cube: PrimitiveMesh,
+    fullscreen_triangle: wgpu::Buffer,
 
     text_resources: TextResources,
+    shader_library: ShaderLibrary,
+    stats: RenderStats,
+    gpu_cache: GpuCache,
+    mipmap_generator: MipmapGenerator,
 }
 
 impl SharedRenderResources {
@@ -33,27 +135,197 @@ impl SharedRenderResources {
                 entries: &[tools::bgl_texture_entry(0), tools::bgl_sampler_entry(1)],
             });
 
+        let cube_texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Shared Cube Texture Bind Group Layout"),
+                entries: &[
+                    tools::bgl_cube_texture_entry(0),
+                    tools::bgl_sampler_entry(1),
+                ],
+            });
+
         let camera_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: Some("Camera Bind Group Layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
                     },
-                    count: None,
-                }],
+                    // Globals, for pipelines already using every bind group
+                    tools::bgl_uniform_entry(1, wgpu::ShaderStages::VERTEX_FRAGMENT),
+                ],
+            });
+
+        let depth_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Depth Bind Group Layout"),
+                entries: &[
+                    tools::bgl_depth_texture_entry(0),
+                    tools::bgl_uniform_entry(1, wgpu::ShaderStages::FRAGMENT),
+                ],
+            });
+
+        let depth_params_buffer = tools::buffer(
+            device,
+            tools::BufferType::Uniform,
+            "Depth Params",
+            &[DepthParamsRaw::default()],
+        );
+
+        let debug_settings = DebugSettings::default();
+
+        let debug_buffer = tools::buffer(
+            device,
+            tools::BufferType::Uniform,
+            "Debug Override",
+            &[DebugUniformRaw::from(&debug_settings)],
+        );
+
+        let debug_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Debug Bind Group Layout"),
+                entries: &[tools::bgl_uniform_entry(
+                    0,
+                    wgpu::ShaderStages::VERTEX_FRAGMENT,
+                )],
+            });
+
+        let debug_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Debug Bind Group"),
+            layout: &debug_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: debug_buffer.as_entire_binding(),
+            }],
+        });
+
+        let globals = Globals::default();
+        let globals_buffer = tools::buffer(
+            device,
+            tools::BufferType::Uniform,
+            "Globals",
+            &[GlobalsRaw::from(&globals)],
+        );
+
+        let globals_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Globals Bind Group Layout"),
+                entries: &[tools::bgl_uniform_entry(
+                    0,
+                    wgpu::ShaderStages::VERTEX_FRAGMENT,
+                )],
+            });
+
+        let globals_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Globals Bind Group"),
+            layout: &globals_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: globals_buffer.as_entire_binding(),
+            }],
+        });
+
+        let lights_buffer = tools::buffer(
+            device,
+            tools::BufferType::Uniform,
+            "Lights",
+            &[LightsUniformRaw::default()],
+        );
+
+        let lights_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Lights Bind Group Layout"),
+                entries: &[
+                    tools::bgl_uniform_entry(0, wgpu::ShaderStages::FRAGMENT),
+                    // Shadow map of the shadow casting directional light
+                    tools::bgl_uniform_entry(1, wgpu::ShaderStages::FRAGMENT),
+                    tools::bgl_depth_texture_entry(2),
+                    tools::bgl_comparison_sampler_entry(3),
+                ],
             });
 
+        let shadow_settings = ShadowSettings::default();
+        let shadow_map = ShadowMap::new(
+            device,
+            &camera_bind_group_layout,
+            &globals_buffer,
+            shadow_settings.resolution,
+        );
+
+        let lights_bind_group = Self::create_lights_bind_group(
+            device,
+            &lights_bind_group_layout,
+            &lights_buffer,
+            &shadow_map,
+        );
+
+        let quad = PrimitiveMesh::new(
+            device,
+            "Shared Quad",
+            &TEXTURE_RECT_VERTICES,
+            &TEXTURE_RECT_INDICES,
+            wgpu::IndexFormat::Uint16,
+        );
+
+        let cube = PrimitiveMesh::new(
+            device,
+            "Shared Cube",
+            &CUBE_VERTICES,
+            &CUBE_INDICES,
+            wgpu::IndexFormat::Uint32,
+        );
+
+        let fullscreen_triangle = tools::buffer(
+            device,
+            tools::BufferType::Vertex,
+            "Shared Fullscreen Triangle",
+            &FULLSCREEN_TRIANGLE_VERTICES,
+        );
+
         let text_resources = TextResources::new(device);
 
         Self {
             texture_bind_group_layout,
+            cube_texture_bind_group_layout,
             camera_bind_group_layout,
+            depth_bind_group_layout,
+            depth_bind_group: None,
+            depth_params_buffer,
+            globals,
+            globals_buffer,
+            globals_bind_group_layout,
+            globals_bind_group,
+            debug_settings,
+            debug_lines: DebugLines::default(),
+            debug_buffer,
+            debug_bind_group_layout,
+            debug_bind_group,
+            ambient_light: AmbientLight::default(),
+            lights_buffer,
+            lights_bind_group_layout,
+            lights_bind_group,
+            shadow_settings,
+            shadow_map,
+            shadows_active: false,
+            active_camera: None,
+            capturing_probe: false,
+            active_target: None,
+            warned_targets: Mutex::new(BTreeSet::new()),
+            quad,
+            cube,
+            fullscreen_triangle,
             text_resources,
+            shader_library: ShaderLibrary::default(),
+            stats: RenderStats::default(),
+            gpu_cache: GpuCache::default(),
+            mipmap_generator: MipmapGenerator::new(device),
         }
     }
 }
@@ -64,11 +336,205 @@ impl SharedRenderResources {
         &self.texture_bind_group_layout
     }
 
+    /// Same as the texture layout but for cube textures, such as skyboxes.
+    #[inline]
+    pub fn cube_texture_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.cube_texture_bind_group_layout
+    }
+
     #[inline]
     pub fn camera_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
         &self.camera_bind_group_layout
     }
 
+    #[inline]
+    pub fn depth_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.depth_bind_group_layout
+    }
+
+    /// Unit quad centered on the origin using [`TextureRectVertex`].
+    #[inline]
+    pub fn quad(&self) -> &PrimitiveMesh {
+        &self.quad
+    }
+
+    /// Unit cube centered on the origin using [`ModelVertex`].
+    #[inline]
+    pub fn cube(&self) -> &PrimitiveMesh {
+        &self.cube
+    }
+
+    /// Single triangle covering the whole screen using [`TextureRectVertex`]. Draw with `0..3`.
+    #[inline]
+    pub fn fullscreen_triangle(&self) -> &wgpu::Buffer {
+        &self.fullscreen_triangle
+    }
+
+    /// Copy of the depth buffer taken after all pipelines that don't read depth have rendered,
+    /// along with the near and far planes of the first perspective camera for linearizing it.
+    /// Only available to pipelines which return true from `Renderer::reads_depth` and
+    /// when the depth format can be copied.
+    #[inline]
+    pub fn depth_bind_group(&self) -> Option<&wgpu::BindGroup> {
+        self.depth_bind_group.as_ref()
+    }
+
+    #[inline]
+    pub fn debug_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.debug_bind_group_layout
+    }
+
+    #[inline]
+    pub fn debug_bind_group(&self) -> &wgpu::BindGroup {
+        &self.debug_bind_group
+    }
+
+    /// Uniform buffer behind the debug bind group, for pipelines binding it with their own
+    /// resources.
+    #[inline]
+    pub fn debug_buffer(&self) -> &wgpu::Buffer {
+        &self.debug_buffer
+    }
+
+    #[inline]
+    pub fn debug_settings(&self) -> &DebugSettings {
+        &self.debug_settings
+    }
+
+    /// Lines queued for this frame, cleared once the frame is rendered.
+    #[inline]
+    pub fn debug_lines(&self) -> &DebugLines {
+        &self.debug_lines
+    }
+
+    #[inline]
+    pub fn debug_lines_mut(&mut self) -> &mut DebugLines {
+        &mut self.debug_lines
+    }
+
+    /// Time, resolution and frame index, updated every frame. See [`Globals`].
+    #[inline]
+    pub fn globals_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.globals_bind_group_layout
+    }
+
+    #[inline]
+    pub fn globals_bind_group(&self) -> &wgpu::BindGroup {
+        &self.globals_bind_group
+    }
+
+    #[inline]
+    pub fn globals(&self) -> &Globals {
+        &self.globals
+    }
+
+    /// Directional, point and ambient lights, updated every frame. See `lighting`.
+    #[inline]
+    pub fn lights_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.lights_bind_group_layout
+    }
+
+    #[inline]
+    pub fn lights_bind_group(&self) -> &wgpu::BindGroup {
+        &self.lights_bind_group
+    }
+
+    #[inline]
+    pub fn ambient_light(&self) -> &AmbientLight {
+        &self.ambient_light
+    }
+
+    #[inline]
+    pub fn set_ambient_light(&mut self, ambient: AmbientLight) {
+        self.ambient_light = ambient;
+    }
+
+    #[inline]
+    pub fn shadow_settings(&self) -> &ShadowSettings {
+        &self.shadow_settings
+    }
+
+    /// Whether something casts shadows this frame, drawn in the [`crate::PrePass::Shadow`]
+    /// pre-pass.
+    #[inline]
+    pub fn shadows_active(&self) -> bool {
+        self.shadows_active
+    }
+
+    /// Camera of the shadow casting light, using the camera bind group layout. Bind it in
+    /// place of the camera in the [`crate::PrePass::Shadow`] pre-pass.
+    #[inline]
+    pub fn shadow_camera_bind_group(&self) -> &wgpu::BindGroup {
+        self.shadow_map.camera_bind_group()
+    }
+
+    #[inline]
+    pub fn shadow_resolution(&self) -> u32 {
+        self.shadow_map.resolution()
+    }
+
+    #[inline]
+    pub(crate) fn shadow_map(&self) -> &Texture {
+        self.shadow_map.texture()
+    }
+
+    /// Camera being rendered when cameras have viewports. See `camera::active_camera`.
+    #[inline]
+    pub fn active_camera(&self) -> Option<hecs::Entity> {
+        self.active_camera
+    }
+
+    #[inline]
+    pub(crate) fn set_active_camera(&mut self, camera: Option<hecs::Entity>) {
+        self.active_camera = camera;
+    }
+
+    /// Whether a reflection probe is being rendered. Pipelines must not sample probes then,
+    /// as the probe being captured can't be read while it's rendered into.
+    #[inline]
+    pub fn capturing_probe(&self) -> bool {
+        self.capturing_probe
+    }
+
+    #[inline]
+    pub(crate) fn set_capturing_probe(&mut self, capturing: bool) {
+        self.capturing_probe = capturing;
+    }
+
+    /// Texture of the [`crate::camera::RenderTarget`] being rendered into.
+    #[inline]
+    pub fn active_target(&self) -> Option<TextureId> {
+        self.active_target
+    }
+
+    #[inline]
+    pub(crate) fn set_active_target(&mut self, target: Option<TextureId>) {
+        self.active_target = target;
+    }
+
+    /// Whether `texture` is being rendered into, so draws sampling it must be skipped.
+    /// Warns the first time each target is sampled by its own camera.
+    pub fn is_active_target(&self, texture: TextureId) -> bool {
+        if self.active_target != Some(texture) {
+            return false;
+        }
+
+        let first = self
+            .warned_targets
+            .lock()
+            .map(|mut warned| warned.insert(texture))
+            .unwrap_or(false);
+
+        if first {
+            log::warn!(
+                "Skipping draws sampling render target texture {} while rendering into it",
+                texture
+            );
+        }
+
+        true
+    }
+
     #[inline]
     pub fn text_resources(&self) -> &TextResources {
         &self.text_resources
@@ -78,6 +544,61 @@ impl SharedRenderResources {
     pub fn text_resources_mut(&mut self) -> &mut TextResources {
         &mut self.text_resources
     }
+
+    #[inline]
+    pub fn shader_library(&self) -> &ShaderLibrary {
+        &self.shader_library
+    }
+
+    #[inline]
+    pub fn shader_library_mut(&mut self) -> &mut ShaderLibrary {
+        &mut self.shader_library
+    }
+
+    /// Resolve the directives in a shader, e.g. one from [`crate::include_shader`], with the
+    /// [`ShaderLibrary`]. Errors are logged and the directives left in, failing compilation.
+    pub fn preprocess_shader<'a>(&self, source: &'a str) -> Cow<'a, str> {
+        self.shader_library.process(source).unwrap_or_else(|e| {
+            log::error!("Unable to preprocess shader: {}", e);
+            Cow::Borrowed(source)
+        })
+    }
+
+    #[inline]
+    pub fn stats(&self) -> &RenderStats {
+        &self.stats
+    }
+
+    #[inline]
+    pub fn stats_mut(&mut self) -> &mut RenderStats {
+        &mut self.stats
+    }
+
+    #[inline]
+    pub fn mipmap_generator(&self) -> &MipmapGenerator {
+        &self.mipmap_generator
+    }
+
+    /// Retention of unused gpu resources shared by every pipeline.
+    #[inline]
+    pub fn gpu_cache(&self) -> &GpuCache {
+        &self.gpu_cache
+    }
+
+    #[inline]
+    pub fn gpu_cache_mut(&mut self) -> &mut GpuCache {
+        &mut self.gpu_cache
+    }
+
+    #[inline]
+    pub fn retention_policy(&self) -> &RetentionPolicy {
+        self.gpu_cache.policy()
+    }
+
+    #[inline]
+    pub fn set_retention_policy(&mut self, policy: RetentionPolicy) {
+        self.gpu_cache.set_policy(policy);
+    }
 }
 
 impl SharedRenderResources {
@@ -86,10 +607,28 @@ impl SharedRenderResources {
         device: &wgpu::Device,
         texture: &Texture,
         label: Option<&str>,
+    ) -> wgpu::BindGroup {
+        Self::texture_bind_group(device, &self.texture_bind_group_layout, texture, label)
+    }
+
+    pub fn create_cube_texture_bind_group(
+        &self,
+        device: &wgpu::Device,
+        texture: &Texture,
+        label: Option<&str>,
+    ) -> wgpu::BindGroup {
+        Self::texture_bind_group(device, &self.cube_texture_bind_group_layout, texture, label)
+    }
+
+    fn texture_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        texture: &Texture,
+        label: Option<&str>,
     ) -> wgpu::BindGroup {
         device.create_bind_group(&wgpu::BindGroupDescriptor {
             label,
-            layout: &self.texture_bind_group_layout,
+            layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
@@ -103,6 +642,134 @@ impl SharedRenderResources {
         })
     }
 
+    pub(crate) fn set_depth_texture(&mut self, device: &wgpu::Device, depth: Option<&Texture>) {
+        self.depth_bind_group = depth.map(|depth| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Depth Bind Group"),
+                layout: &self.depth_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&depth.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: self.depth_params_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        });
+    }
+
+    pub(crate) fn set_depth_params(&self, queue: &wgpu::Queue, z_near: f32, z_far: f32) {
+        queue.write_buffer(
+            &self.depth_params_buffer,
+            0,
+            bytemuck::cast_slice(&[DepthParamsRaw {
+                z_near,
+                z_far,
+                pad: [0.; 2],
+            }]),
+        );
+    }
+
+    pub(crate) fn set_debug_settings(&mut self, queue: &wgpu::Queue, settings: DebugSettings) {
+        self.debug_settings = settings;
+        queue.write_buffer(
+            &self.debug_buffer,
+            0,
+            bytemuck::cast_slice(&[DebugUniformRaw::from(&settings)]),
+        );
+    }
+
+    pub(crate) fn set_globals(&mut self, queue: &wgpu::Queue, globals: Globals) {
+        self.globals = globals;
+        queue.write_buffer(
+            &self.globals_buffer,
+            0,
+            bytemuck::cast_slice(&[GlobalsRaw::from(&globals)]),
+        );
+    }
+
+    pub(crate) fn set_lights(&self, queue: &wgpu::Queue, lights: &LightsUniformRaw) {
+        queue.write_buffer(&self.lights_buffer, 0, bytemuck::cast_slice(&[*lights]));
+    }
+
+    /// Recreates the shadow map, and the lights bind group holding it, when the resolution
+    /// changes.
+    pub(crate) fn set_shadow_settings(&mut self, device: &wgpu::Device, settings: ShadowSettings) {
+        self.shadow_settings = settings;
+
+        if settings
+            .resolution
+            .clamp(1, device.limits().max_texture_dimension_2d)
+            == self.shadow_map.resolution()
+        {
+            return;
+        }
+
+        self.shadow_map = ShadowMap::new(
+            device,
+            &self.camera_bind_group_layout,
+            &self.globals_buffer,
+            settings.resolution,
+        );
+        self.lights_bind_group = Self::create_lights_bind_group(
+            device,
+            &self.lights_bind_group_layout,
+            &self.lights_buffer,
+            &self.shadow_map,
+        );
+    }
+
+    /// Shadow and light camera uniforms, or None when nothing casts shadows.
+    pub(crate) fn set_shadows(
+        &mut self,
+        queue: &wgpu::Queue,
+        shadows: Option<(&ShadowUniformRaw, &CameraUniformRaw)>,
+    ) {
+        self.shadows_active = shadows.is_some();
+
+        match shadows {
+            Some((shadow, camera)) => self.shadow_map.write(queue, shadow, camera),
+            None => queue.write_buffer(
+                self.shadow_map.buffer(),
+                0,
+                bytemuck::cast_slice(&[ShadowUniformRaw::default()]),
+            ),
+        }
+    }
+
+    fn create_lights_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        lights_buffer: &wgpu::Buffer,
+        shadow_map: &ShadowMap,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Lights Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: lights_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: shadow_map.buffer().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&shadow_map.texture().view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&shadow_map.texture().sampler),
+                },
+            ],
+        })
+    }
+
     pub fn create_camera<C: CameraUniform>(&self, device: &wgpu::Device, camera: &C) -> CameraWgpu {
         let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Camera buffer"),
@@ -113,15 +780,24 @@ impl SharedRenderResources {
         let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Camera Bind Group"),
             layout: &self.camera_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::Buffer(camera_buffer.as_entire_buffer_binding()),
-            }],
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(
+                        camera_buffer.as_entire_buffer_binding(),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.globals_buffer.as_entire_binding(),
+                },
+            ],
         });
 
         CameraWgpu {
             camera_buffer: WgpuWrapper::new(camera_buffer),
             camera_bind_group: WgpuWrapper::new(camera_bind_group),
+            matrices: camera.get_matrices(&glam::Affine3A::IDENTITY),
         }
     }
 }
@@ -171,6 +847,22 @@ pub const TEXTURE_RECT_VERTICES: [TextureRectVertex; 4] = [
 pub const TEXTURE_RECT_INDICES: [u16; 6] = [0, 1, 3, 0, 3, 2];
 pub const TEXTURE_RECT_INDEX_COUNT: u32 = TEXTURE_RECT_INDICES.len() as u32;
 
+/// Clip space positions, with uvs covering 0-1 over the visible area.
+pub const FULLSCREEN_TRIANGLE_VERTICES: [TextureRectVertex; 3] = [
+    TextureRectVertex {
+        pos: glam::vec2(-1., -1.),
+        uv: glam::vec2(0., 1.),
+    },
+    TextureRectVertex {
+        pos: glam::vec2(3., -1.),
+        uv: glam::vec2(2., 1.),
+    },
+    TextureRectVertex {
+        pos: glam::vec2(-1., 3.),
+        uv: glam::vec2(0., -1.),
+    },
+];
+
 //====================================================================
 
 #[repr(C)]
@@ -179,14 +871,81 @@ pub struct ModelVertex {
     pos: glam::Vec3,
     uv: glam::Vec2,
     normal: glam::Vec3,
+    lightmap_uv: glam::Vec2,
+    joints: [u16; 4],
+    weights: [f32; 4],
+}
+
+impl ModelVertex {
+    /// Vertex with its lightmap uv set to `uv`.
+    #[inline]
+    pub const fn new(pos: glam::Vec3, uv: glam::Vec2, normal: glam::Vec3) -> Self {
+        Self {
+            pos,
+            uv,
+            normal,
+            lightmap_uv: uv,
+            joints: [0; 4],
+            weights: [0.; 4],
+        }
+    }
+
+    /// Second uv channel used to sample lightmaps. Must not overlap between faces.
+    #[inline]
+    pub const fn with_lightmap_uv(mut self, lightmap_uv: glam::Vec2) -> Self {
+        self.lightmap_uv = lightmap_uv;
+        self
+    }
+
+    /// Skeleton joints moving the vertex, by how much. Unskinned while the weights are zero.
+    #[inline]
+    pub const fn with_joints(mut self, joints: [u16; 4], weights: [f32; 4]) -> Self {
+        self.joints = joints;
+        self.weights = weights;
+        self
+    }
+
+    #[inline]
+    pub fn pos(&self) -> glam::Vec3 {
+        self.pos
+    }
+
+    #[inline]
+    pub fn uv(&self) -> glam::Vec2 {
+        self.uv
+    }
+
+    #[inline]
+    pub fn normal(&self) -> glam::Vec3 {
+        self.normal
+    }
+
+    #[inline]
+    pub fn lightmap_uv(&self) -> glam::Vec2 {
+        self.lightmap_uv
+    }
+
+    #[inline]
+    pub fn joints(&self) -> [u16; 4] {
+        self.joints
+    }
+
+    #[inline]
+    pub fn weights(&self) -> [f32; 4] {
+        self.weights
+    }
 }
 
 impl Vertex for ModelVertex {
     fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
-        const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![
+        const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
             0 => Float32x3,
             1 => Float32x2,
-            2 => Float32x3
+            2 => Float32x3,
+            // Placed around the model instance attributes
+            15 => Float32x2, // Lightmap uv
+            8 => Uint16x4,   // Joints
+            9 => Float32x4   // Weights
         ];
 
         wgpu::VertexBufferLayout {
@@ -197,6 +956,7 @@ impl Vertex for ModelVertex {
     }
 }
 
+/// Lightmap uvs give each face its own cell of a 3x2 grid.
 pub const CUBE_VERTICES: [ModelVertex; 24] = [
     // Back (-z)
     // Top Left - 0
@@ -204,24 +964,36 @@ pub const CUBE_VERTICES: [ModelVertex; 24] = [
         pos: glam::vec3(-0.5, 0.5, -0.5),
         uv: glam::vec2(0., 0.),
         normal: glam::Vec3::NEG_Z,
+        lightmap_uv: glam::vec2(0.0167, 0.025),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Top Right - 1
     ModelVertex {
         pos: glam::vec3(0.5, 0.5, -0.5),
         uv: glam::vec2(1., 0.),
         normal: glam::Vec3::NEG_Z,
+        lightmap_uv: glam::vec2(0.3167, 0.025),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Bottom Left - 2
     ModelVertex {
         pos: glam::vec3(-0.5, -0.5, -0.5),
         uv: glam::vec2(0., 1.),
         normal: glam::Vec3::NEG_Z,
+        lightmap_uv: glam::vec2(0.0167, 0.475),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Bottom Right - 3
     ModelVertex {
         pos: glam::vec3(0.5, -0.5, -0.5),
         uv: glam::vec2(1., 1.),
         normal: glam::Vec3::NEG_Z,
+        lightmap_uv: glam::vec2(0.3167, 0.475),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     //
     // Right (+x)
@@ -230,24 +1002,36 @@ pub const CUBE_VERTICES: [ModelVertex; 24] = [
         pos: glam::vec3(0.5, 0.5, -0.5),
         uv: glam::vec2(0., 0.),
         normal: glam::Vec3::X,
+        lightmap_uv: glam::vec2(0.35, 0.025),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Top Right - 5
     ModelVertex {
         pos: glam::vec3(0.5, 0.5, 0.5),
         uv: glam::vec2(1., 0.),
         normal: glam::Vec3::X,
+        lightmap_uv: glam::vec2(0.65, 0.025),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Bottom Left - 6
     ModelVertex {
         pos: glam::vec3(0.5, -0.5, -0.5),
         uv: glam::vec2(0., 1.),
         normal: glam::Vec3::X,
+        lightmap_uv: glam::vec2(0.35, 0.475),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Bottom Right - 7
     ModelVertex {
         pos: glam::vec3(0.5, -0.5, 0.5),
         uv: glam::vec2(1., 1.),
         normal: glam::Vec3::X,
+        lightmap_uv: glam::vec2(0.65, 0.475),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     //
     // Front (+z)
@@ -256,24 +1040,36 @@ pub const CUBE_VERTICES: [ModelVertex; 24] = [
         pos: glam::vec3(0.5, 0.5, 0.5),
         uv: glam::vec2(0., 0.),
         normal: glam::Vec3::Z,
+        lightmap_uv: glam::vec2(0.6833, 0.025),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Top Right - 9
     ModelVertex {
         pos: glam::vec3(-0.5, 0.5, 0.5),
         uv: glam::vec2(1., 0.),
         normal: glam::Vec3::Z,
+        lightmap_uv: glam::vec2(0.9833, 0.025),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Bottom Left - 10
     ModelVertex {
         pos: glam::vec3(0.5, -0.5, 0.5),
         uv: glam::vec2(0., 1.),
         normal: glam::Vec3::Z,
+        lightmap_uv: glam::vec2(0.6833, 0.475),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Bottom Right - 11
     ModelVertex {
         pos: glam::vec3(-0.5, -0.5, 0.5),
         uv: glam::vec2(1., 1.),
         normal: glam::Vec3::Z,
+        lightmap_uv: glam::vec2(0.9833, 0.475),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     //
     // Left (-x)
@@ -282,24 +1078,36 @@ pub const CUBE_VERTICES: [ModelVertex; 24] = [
         pos: glam::vec3(-0.5, 0.5, 0.5),
         uv: glam::vec2(0., 0.),
         normal: glam::Vec3::NEG_X,
+        lightmap_uv: glam::vec2(0.0167, 0.525),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Top Right - 13
     ModelVertex {
         pos: glam::vec3(-0.5, 0.5, -0.5),
         uv: glam::vec2(1., 0.),
         normal: glam::Vec3::NEG_X,
+        lightmap_uv: glam::vec2(0.3167, 0.525),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Bottom Left - 14
     ModelVertex {
         pos: glam::vec3(-0.5, -0.5, 0.5),
         uv: glam::vec2(0., 1.),
         normal: glam::Vec3::NEG_X,
+        lightmap_uv: glam::vec2(0.0167, 0.975),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Bottom Right - 15
     ModelVertex {
         pos: glam::vec3(-0.5, -0.5, -0.5),
         uv: glam::vec2(1., 1.),
         normal: glam::Vec3::NEG_X,
+        lightmap_uv: glam::vec2(0.3167, 0.975),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     //
     // Top
@@ -308,24 +1116,36 @@ pub const CUBE_VERTICES: [ModelVertex; 24] = [
         pos: glam::vec3(0.5, 0.5, -0.5),
         uv: glam::vec2(0., 0.),
         normal: glam::Vec3::Y,
+        lightmap_uv: glam::vec2(0.35, 0.525),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Top Right - 17
     ModelVertex {
         pos: glam::vec3(-0.5, 0.5, -0.5),
         uv: glam::vec2(1., 0.),
         normal: glam::Vec3::Y,
+        lightmap_uv: glam::vec2(0.65, 0.525),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Bottom Left - 18
     ModelVertex {
         pos: glam::vec3(0.5, 0.5, 0.5),
         uv: glam::vec2(0., 1.),
         normal: glam::Vec3::Y,
+        lightmap_uv: glam::vec2(0.35, 0.975),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Bottom Right - 19
     ModelVertex {
         pos: glam::vec3(-0.5, 0.5, 0.5),
         uv: glam::vec2(1., 1.),
         normal: glam::Vec3::Y,
+        lightmap_uv: glam::vec2(0.65, 0.975),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     //
     // Bottom
@@ -334,24 +1154,36 @@ pub const CUBE_VERTICES: [ModelVertex; 24] = [
         pos: glam::vec3(0.5, -0.5, -0.5),
         uv: glam::vec2(0., 0.),
         normal: glam::Vec3::NEG_Y,
+        lightmap_uv: glam::vec2(0.6833, 0.525),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Top Right - 21
     ModelVertex {
         pos: glam::vec3(-0.5, -0.5, -0.5),
         uv: glam::vec2(1., 0.),
         normal: glam::Vec3::NEG_Y,
+        lightmap_uv: glam::vec2(0.9833, 0.525),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Bottom Left - 22
     ModelVertex {
         pos: glam::vec3(0.5, -0.5, 0.5),
         uv: glam::vec2(0., 1.),
         normal: glam::Vec3::NEG_Y,
+        lightmap_uv: glam::vec2(0.6833, 0.975),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
     // Bottom Right - 23
     ModelVertex {
         pos: glam::vec3(-0.5, -0.5, 0.5),
         uv: glam::vec2(1., 1.),
         normal: glam::Vec3::NEG_Y,
+        lightmap_uv: glam::vec2(0.9833, 0.975),
+        joints: [0; 4],
+        weights: [0.; 4],
     },
 ];
 
@@ -367,3 +1199,71 @@ pub const CUBE_INDICES: [u32; 36] = [
 pub const CUBE_INDEX_COUNT: u32 = CUBE_INDICES.len() as u32;
 
 //====================================================================
+
+/// Per frame values shared by every pipeline through
+/// [`SharedRenderResources::globals_bind_group`]. Also bound at `@group(0) @binding(1)`
+/// next to every camera. In wgsl:
+///
+/// ```wgsl
+/// struct Globals {
+///     time: f32,
+///     delta: f32,
+///     frame: u32,
+///     resolution: vec2<f32>,
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Globals {
+    /// Seconds since the renderer was created.
+    pub time: f32,
+    /// Seconds since the previous frame.
+    pub delta: f32,
+    /// Frames rendered before this one. Wraps around.
+    pub frame: u32,
+    /// Surface size in pixels.
+    pub resolution: glam::Vec2,
+}
+
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Debug)]
+struct GlobalsRaw {
+    time: f32,
+    delta: f32,
+    frame: u32,
+    pad: u32,
+    resolution: glam::Vec2,
+    pad2: [f32; 2],
+}
+
+impl From<&Globals> for GlobalsRaw {
+    fn from(globals: &Globals) -> Self {
+        Self {
+            time: globals.time,
+            delta: globals.delta,
+            frame: globals.frame,
+            pad: 0,
+            resolution: globals.resolution,
+            pad2: [0.; 2],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Clone, Copy, Debug)]
+struct DepthParamsRaw {
+    z_near: f32,
+    z_far: f32,
+    pad: [f32; 2],
+}
+
+impl Default for DepthParamsRaw {
+    fn default() -> Self {
+        Self {
+            z_near: 0.1,
+            z_far: 1000.,
+            pad: [0.; 2],
+        }
+    }
+}
+
+//====================================================================
@@ -0,0 +1,200 @@
+//====================================================================
+
+use std::{collections::BTreeMap, fmt::Display};
+
+//====================================================================
+
+/// Draw calls made for one kind of batch (textures, meshes etc.) this frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BatchStats {
+    pub draws: u32,
+    pub instances: u32,
+    /// Draws with fewer instances than [`SMALL_BATCH_SIZE`].
+    pub small_draws: u32,
+}
+
+pub const SMALL_BATCH_SIZE: u32 = 3;
+
+/// Frames between batching hints being logged, see [`crate::debug::DebugSettings`].
+pub const BATCHING_HINT_INTERVAL: u32 = 300;
+/// Small draws a batch kind needs in a frame before a hint is logged for it.
+pub const BATCHING_HINT_MIN_DRAWS: u32 = 8;
+
+impl BatchStats {
+    #[inline]
+    pub fn average_instances(&self) -> f32 {
+        match self.draws {
+            0 => 0.,
+            draws => self.instances as f32 / draws as f32,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct PipelineStats {
+    counters: BTreeMap<&'static str, u64>,
+    gauges: BTreeMap<&'static str, f64>,
+    batches: BTreeMap<&'static str, BatchStats>,
+}
+
+impl PipelineStats {
+    #[inline]
+    pub fn counter(&self, name: &str) -> u64 {
+        self.counters.get(name).copied().unwrap_or(0)
+    }
+
+    #[inline]
+    pub fn gauge(&self, name: &str) -> Option<f64> {
+        self.gauges.get(name).copied()
+    }
+
+    #[inline]
+    pub fn counters(&self) -> impl Iterator<Item = (&'static str, u64)> + '_ {
+        self.counters.iter().map(|(name, value)| (*name, *value))
+    }
+
+    #[inline]
+    pub fn gauges(&self) -> impl Iterator<Item = (&'static str, f64)> + '_ {
+        self.gauges.iter().map(|(name, value)| (*name, *value))
+    }
+
+    #[inline]
+    pub fn batches(&self) -> impl Iterator<Item = (&'static str, &BatchStats)> + '_ {
+        self.batches.iter().map(|(name, value)| (*name, value))
+    }
+}
+
+//====================================================================
+
+/// Per pipeline statistics for the current frame.
+/// Counters are reset at the start of every frame while gauges keep their last value.
+#[derive(Debug, Default)]
+pub struct RenderStats {
+    scope: &'static str,
+    pipelines: BTreeMap<&'static str, PipelineStats>,
+}
+
+impl RenderStats {
+    /// Add to a counter belonging to the pipeline currently being prepped or rendered.
+    #[inline]
+    pub fn add_counter(&mut self, name: &'static str, value: u64) {
+        *self.current().counters.entry(name).or_insert(0) += value;
+    }
+
+    /// Set a gauge belonging to the pipeline currently being prepped or rendered.
+    #[inline]
+    pub fn set_gauge(&mut self, name: &'static str, value: f64) {
+        self.current().gauges.insert(name, value);
+    }
+
+    /// Record a batch of instances drawn together, of the given kind (e.g. "texture" or "mesh").
+    /// Used to produce [`RenderStats::batching_hints`].
+    #[inline]
+    pub fn record_batch(&mut self, kind: &'static str, instances: u32) {
+        let batch = self.current().batches.entry(kind).or_default();
+        batch.draws += 1;
+        batch.instances += instances;
+        if instances < SMALL_BATCH_SIZE {
+            batch.small_draws += 1;
+        }
+    }
+
+    #[inline]
+    pub fn pipeline(&self, name: &str) -> Option<&PipelineStats> {
+        self.pipelines.get(name)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &PipelineStats)> {
+        self.pipelines.iter().map(|(name, stats)| (*name, stats))
+    }
+
+    /// Sum of a counter across all pipelines.
+    pub fn total(&self, counter: &str) -> u64 {
+        self.pipelines
+            .values()
+            .map(|stats| stats.counter(counter))
+            .sum()
+    }
+
+    /// Suggestions for content changes that would reduce draw calls, based on this frame.
+    /// Only batch kinds with at least `min_draws` small draws are reported.
+    pub fn batching_hints(&self, min_draws: u32) -> Vec<BatchingHint> {
+        self.pipelines
+            .iter()
+            .flat_map(|(pipeline, stats)| {
+                stats.batches.iter().filter_map(|(kind, batch)| {
+                    (batch.small_draws >= min_draws.max(1)).then_some(BatchingHint {
+                        pipeline,
+                        kind,
+                        stats: *batch,
+                    })
+                })
+            })
+            .collect()
+    }
+
+    #[inline]
+    fn current(&mut self) -> &mut PipelineStats {
+        self.pipelines.entry(self.scope).or_default()
+    }
+}
+
+impl RenderStats {
+    pub(crate) fn begin_frame(&mut self) {
+        self.pipelines.values_mut().for_each(|stats| {
+            stats.counters.clear();
+            stats.batches.clear();
+        });
+    }
+
+    #[inline]
+    pub(crate) fn set_scope(&mut self, scope: &'static str) {
+        self.scope = scope;
+    }
+}
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatchingHint {
+    pub pipeline: &'static str,
+    pub kind: &'static str,
+    pub stats: BatchStats,
+}
+
+impl Display for BatchingHint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let small = self.stats.small_draws;
+
+        write!(
+            f,
+            "{}: {} of {} draws have fewer than {} instances (average {:.1}) - ",
+            self.pipeline,
+            small,
+            self.stats.draws,
+            SMALL_BATCH_SIZE,
+            self.stats.average_instances()
+        )?;
+
+        match self.kind {
+            "texture" => write!(f, "merge these {} textures into an atlas", small),
+            "mesh" => write!(
+                f,
+                "reuse fewer unique meshes or merge these {} static meshes",
+                small
+            ),
+            kind => write!(f, "reduce the number of unique {} batches", kind),
+        }
+    }
+}
+
+//====================================================================
+
+/// Short display name for a pipeline type, without the module path.
+pub(crate) fn pipeline_name<R>() -> &'static str {
+    let name = std::any::type_name::<R>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+//====================================================================
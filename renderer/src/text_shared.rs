@@ -15,7 +15,7 @@ use rustc_hash::FxHasher;
 
 use crate::{shared::Vertex, texture::Texture, tools};
 
-pub use cosmic_text::{Attrs, Color, Metrics, Shaping, Wrap};
+pub use cosmic_text::{Align, Attrs, Color, Family, Metrics, Shaping, Style, Weight, Wrap};
 
 //====================================================================
 
@@ -248,7 +248,7 @@ impl TextAtlas {
         self.packer.deallocate(val.alloc_id);
         self.cached_glyphs.pop(&key);
 
-        return Ok(());
+        Ok(())
     }
 
     #[inline]
@@ -259,19 +259,155 @@ impl TextAtlas {
 
 //====================================================================
 
+/// Fonts and glyphs to load before the first frame is rendered.
+/// Wasm builds have no system fonts so at least one font should be embedded there.
+#[derive(Debug, Clone)]
+pub struct FontPreload {
+    /// Raw font file data, usually from `include_bytes!`.
+    pub fonts: Vec<&'static [u8]>,
+    /// Characters to rasterize into the glyph atlas up front.
+    pub charset: String,
+    pub charset_metrics: Option<Metrics>,
+    pub charset_attributes: Attrs<'static>,
+}
+
+impl Default for FontPreload {
+    fn default() -> Self {
+        Self {
+            fonts: Vec::new(),
+            charset: String::new(),
+            charset_metrics: None,
+            charset_attributes: Attrs::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FontLoadStatus {
+    pub font_faces: usize,
+    pub preloaded_glyphs: usize,
+    /// Characters from the last preload's charset that no loaded font can display.
+    pub missing_chars: Vec<char>,
+    pub failed_glyphs: usize,
+}
+
+impl FontLoadStatus {
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.font_faces > 0 && self.missing_chars.is_empty() && self.failed_glyphs == 0
+    }
+}
+
 pub struct TextResources {
     pub font_system: cosmic_text::FontSystem,
     pub swash_cache: cosmic_text::SwashCache,
     pub text_atlas: TextAtlas,
+    font_status: FontLoadStatus,
 }
 
 impl TextResources {
     pub fn new(device: &wgpu::Device) -> Self {
+        let font_system = cosmic_text::FontSystem::new();
+        let font_status = FontLoadStatus {
+            font_faces: font_system.db().len(),
+            ..Default::default()
+        };
+
         Self {
-            font_system: cosmic_text::FontSystem::new(),
+            font_system,
             swash_cache: cosmic_text::SwashCache::new(),
             text_atlas: TextAtlas::new(device),
+            font_status,
+        }
+    }
+
+    #[inline]
+    pub fn font_status(&self) -> &FontLoadStatus {
+        &self.font_status
+    }
+
+    /// Load a font file so text can use it, e.g. with [`Attrs::family`]. Can be called at any time.
+    pub fn load_font(&mut self, data: &[u8]) {
+        self.font_system.db_mut().load_font_data(data.to_vec());
+        self.font_status.font_faces = self.font_system.db().len();
+    }
+
+    /// Take the fonts loaded into `other`, such as when the renderer is recreated.
+    pub(crate) fn transfer_fonts(&mut self, other: &mut TextResources) {
+        std::mem::swap(&mut self.font_system, &mut other.font_system);
+        std::mem::swap(&mut self.font_status, &mut other.font_status);
+    }
+
+    pub fn preload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        preload: &FontPreload,
+    ) -> &FontLoadStatus {
+        preload.fonts.iter().for_each(|font| self.load_font(font));
+
+        // Fonts loaded since the last preload may cover characters that were missing
+        self.font_status.missing_chars.clear();
+
+        if preload.charset.is_empty() {
+            return &self.font_status;
         }
+
+        let metrics = preload
+            .charset_metrics
+            .unwrap_or(Metrics::relative(30., 1.2));
+
+        let mut buffer = Buffer::new(&mut self.font_system, metrics);
+        buffer.set_size(&mut self.font_system, None, None);
+        buffer.set_text(
+            &mut self.font_system,
+            &preload.charset,
+            preload.charset_attributes,
+            Shaping::Advanced,
+        );
+
+        buffer.layout_runs().for_each(|run| {
+            run.glyphs.iter().for_each(|glyph| {
+                // Glyph 0 is the font's missing glyph
+                if glyph.glyph_id == 0 {
+                    let missing = &mut self.font_status.missing_chars;
+                    run.text[glyph.start..glyph.end].chars().for_each(|c| {
+                        if !missing.contains(&c) {
+                            missing.push(c);
+                        }
+                    });
+                    return;
+                }
+
+                let physical = glyph.physical((0., 0.), 1.);
+
+                match self.text_atlas.use_glyph(
+                    device,
+                    queue,
+                    &mut self.font_system,
+                    &mut self.swash_cache,
+                    &physical.cache_key,
+                ) {
+                    Ok(_) => self.font_status.preloaded_glyphs += 1,
+                    Err(e) => {
+                        log::warn!("Unable to preload glyph '{}': {}", glyph.glyph_id, e);
+                        self.font_status.failed_glyphs += 1;
+                    }
+                }
+            });
+        });
+
+        // Preloaded glyphs shouldn't stop the atlas from evicting them later
+        self.text_atlas.post_render_trim();
+
+        if !self.font_status.missing_chars.is_empty() {
+            log::warn!(
+                "Loaded fonts are missing characters: {:?}",
+                self.font_status.missing_chars
+            );
+        }
+
+        &self.font_status
     }
 }
 
@@ -315,6 +451,21 @@ pub struct TextBuffer {
 
     buffer: Buffer,
     color: Color,
+    align: Option<Align>,
+}
+
+/// Text laid out with its own attributes, such as family, weight, style and color.
+#[derive(Debug, Clone, Copy)]
+pub struct TextSpan<'a> {
+    pub text: &'a str,
+    pub attributes: Attrs<'a>,
+}
+
+impl<'a> TextSpan<'a> {
+    #[inline]
+    pub fn new(text: &'a str, attributes: Attrs<'a>) -> Self {
+        Self { text, attributes }
+    }
 }
 
 pub struct TextBufferDescriptor<'a> {
@@ -322,9 +473,13 @@ pub struct TextBufferDescriptor<'a> {
     pub word_wrap: Wrap,
     pub attributes: Attrs<'a>,
     pub text: &'a str,
+    /// Laid out instead of `text` when not empty. Spans without a color use `color`.
+    pub spans: &'a [TextSpan<'a>],
     pub width: Option<f32>,
     pub height: Option<f32>,
     pub color: Color,
+    /// Alignment of every line, left to right text is aligned left if unset.
+    pub align: Option<Align>,
 }
 
 impl<'a> Default for TextBufferDescriptor<'a> {
@@ -334,9 +489,11 @@ impl<'a> Default for TextBufferDescriptor<'a> {
             word_wrap: Wrap::WordOrGlyph,
             attributes: Attrs::new(),
             text: "",
+            spans: &[],
             width: Some(800.),
             height: None,
             color: Color::rgb(0, 0, 0),
+            align: None,
         }
     }
 }
@@ -360,21 +517,97 @@ impl TextBuffer {
         let mut buffer = Buffer::new(font_system, desc.metrics);
         buffer.set_size(font_system, desc.width, desc.height);
         buffer.set_wrap(font_system, desc.word_wrap);
-        buffer.set_text(font_system, desc.text, desc.attributes, Shaping::Advanced);
 
-        Self {
+        let mut text_buffer = Self {
             vertex_buffer,
             vertex_count,
             lines,
             buffer,
             color: desc.color,
+            align: desc.align,
+        };
+
+        match desc.spans.is_empty() {
+            true => text_buffer.set_text(font_system, desc.text, desc.attributes),
+            false => text_buffer.set_rich_text(font_system, desc.spans, desc.attributes),
         }
+
+        text_buffer
     }
 
     #[inline]
     pub fn set_metrics(&mut self, font_system: &mut cosmic_text::FontSystem, metrics: Metrics) {
         self.buffer.set_metrics(font_system, metrics);
     }
+
+    #[inline]
+    pub fn set_text(
+        &mut self,
+        font_system: &mut cosmic_text::FontSystem,
+        text: &str,
+        attributes: Attrs,
+    ) {
+        self.buffer
+            .set_text(font_system, text, attributes, Shaping::Advanced);
+        self.apply_align(font_system);
+    }
+
+    /// Replace the text with styled spans. `attributes` is used for empty lines.
+    pub fn set_rich_text(
+        &mut self,
+        font_system: &mut cosmic_text::FontSystem,
+        spans: &[TextSpan],
+        attributes: Attrs,
+    ) {
+        self.buffer.set_rich_text(
+            font_system,
+            spans.iter().map(|span| (span.text, span.attributes)),
+            attributes,
+            Shaping::Advanced,
+        );
+        self.apply_align(font_system);
+    }
+
+    #[inline]
+    pub fn set_align(&mut self, font_system: &mut cosmic_text::FontSystem, align: Option<Align>) {
+        self.align = align;
+        self.apply_align(font_system);
+    }
+
+    #[inline]
+    pub fn align(&self) -> Option<Align> {
+        self.align
+    }
+
+    // Lines are recreated whenever the text changes, so alignment is reapplied each time
+    fn apply_align(&mut self, font_system: &mut cosmic_text::FontSystem) {
+        let mut changed = false;
+        for line in self.buffer.lines.iter_mut() {
+            changed |= line.set_align(self.align);
+        }
+
+        if changed {
+            self.buffer.shape_until_scroll(font_system, false);
+        }
+    }
+
+    #[inline]
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    #[inline]
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Width of the widest laid out line.
+    pub fn width(&self) -> f32 {
+        self.buffer
+            .layout_runs()
+            .map(|run| run.line_w)
+            .fold(0., f32::max)
+    }
 }
 
 //====================================================================
@@ -424,13 +657,17 @@ pub fn prep(
                     let physical = glyph.physical((0., 0.), 1.);
 
                     // Try to prep glyph in atlas
-                    if let Err(_) = text_resources.text_atlas.use_glyph(
-                        device,
-                        queue,
-                        &mut text_resources.font_system,
-                        &mut text_resources.swash_cache,
-                        &physical.cache_key,
-                    ) {
+                    if text_resources
+                        .text_atlas
+                        .use_glyph(
+                            device,
+                            queue,
+                            &mut text_resources.font_system,
+                            &mut text_resources.swash_cache,
+                            &physical.cache_key,
+                        )
+                        .is_err()
+                    {
                         unimplemented!()
                     }
 
@@ -1,11 +1,15 @@
 //====================================================================
 
-use std::sync::atomic::AtomicU32;
+use std::sync::{atomic::AtomicU32, Mutex};
 
 use common::Size;
 use image::GenericImageView;
 
-use crate::{shared::SharedRenderResources, WgpuWrapper};
+use crate::{
+    ktx2::{Ktx2, Ktx2Error},
+    shared::SharedRenderResources,
+    tools, WgpuWrapper,
+};
 
 //====================================================================
 
@@ -35,6 +39,68 @@ impl LoadedTexture {
         }
     }
 
+    /// Load a texture with a full mip chain generated on the gpu from its first level, sampled
+    /// with `sampler` or [`Texture::TRILINEAR_SAMPLER`]. Textures that already have mips or
+    /// use a format that can't be rendered to are loaded as they are.
+    pub fn load_texture_with_mipmaps(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shared: &SharedRenderResources,
+        texture: Texture,
+        sampler: Option<&wgpu::SamplerDescriptor>,
+    ) -> Self {
+        let format = texture.texture.format();
+
+        if texture.texture.mip_level_count() > 1
+            || texture.texture.dimension() != wgpu::TextureDimension::D2
+            || !MipmapGenerator::supports(device, format)
+        {
+            log::debug!("Not generating mipmaps for {:?} texture", format);
+            return Self::load_texture(device, shared, texture);
+        }
+
+        let size = texture.texture.size();
+        let mipmapped = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Mipmapped Texture"),
+            size,
+            mip_level_count: size.max_mips(wgpu::TextureDimension::D2),
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+
+        shared
+            .mipmap_generator()
+            .generate_from(device, queue, &texture.view, &mipmapped);
+
+        let texture = Texture {
+            view: mipmapped.create_view(&wgpu::TextureViewDescriptor::default()),
+            sampler: device.create_sampler(sampler.unwrap_or(&Texture::TRILINEAR_SAMPLER)),
+            texture: mipmapped,
+        };
+
+        Self::load_texture(device, shared, texture)
+    }
+
+    /// Load a texture created with [`Texture::from_cube_faces`] or [`Texture::from_equirect`].
+    pub fn load_cube_texture(
+        device: &wgpu::Device,
+        shared: &SharedRenderResources,
+        texture: Texture,
+    ) -> Self {
+        let id = CURRENT_TEXTURE_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let bind_group = shared.create_cube_texture_bind_group(device, &texture, None);
+        Self {
+            id,
+            texture: WgpuWrapper::new(texture),
+            bind_group: WgpuWrapper::new(bind_group),
+        }
+    }
+
     #[inline]
     pub fn id(&self) -> TextureId {
         self.id
@@ -70,9 +136,35 @@ pub struct Texture {
 impl Texture {
     pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
 
+    /// Linear filtering within and between mip levels, so textures don't shimmer at a distance.
+    pub const TRILINEAR_SAMPLER: wgpu::SamplerDescriptor<'static> = wgpu::SamplerDescriptor {
+        label: Some("Trilinear Sampler"),
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Linear,
+        lod_min_clamp: 0.,
+        lod_max_clamp: 32.,
+        compare: None,
+        anisotropy_clamp: 1,
+        border_color: None,
+    };
+
+    /// Whether the depth aspect of a format can be copied and so sampled by pipelines.
+    #[inline]
+    pub fn depth_format_copyable(format: wgpu::TextureFormat) -> bool {
+        matches!(
+            format,
+            wgpu::TextureFormat::Depth16Unorm | wgpu::TextureFormat::Depth32Float
+        )
+    }
+
     pub fn create_depth_texture(
         device: &wgpu::Device,
         window_size: Size<u32>,
+        format: wgpu::TextureFormat,
         label: &str,
     ) -> Self {
         let size = wgpu::Extent3d {
@@ -87,9 +179,12 @@ impl Texture {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: Self::DEPTH_FORMAT,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[wgpu::TextureFormat::Depth32Float],
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
         });
 
         let view = texture.create_view(&wgpu::TextureViewDescriptor {
@@ -140,9 +235,9 @@ impl Texture {
         Self::from_image(device, queue, &rgba, label, sampler)
     }
 
-    /// Try to create a wgpu Texture from an array of bytes.
-    /// The image crate will return an error if it cannot determine the format
-    /// of the image.
+    /// Try to create a wgpu Texture from an array of bytes, either a KTX2 container, a baked
+    /// [`TextureData`] or an image. The image crate will return an error if it cannot
+    /// determine the format of the image.
     pub fn from_bytes(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
@@ -150,6 +245,20 @@ impl Texture {
         label: Option<&str>,
         sampler: Option<&wgpu::SamplerDescriptor>,
     ) -> Result<Self, image::ImageError> {
+        if Ktx2::is_ktx2(bytes) {
+            return Self::from_ktx2(device, queue, bytes, label, sampler).map_err(|e| {
+                image::ImageError::IoError(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    e.to_string(),
+                ))
+            });
+        }
+
+        if TextureData::is_baked(bytes) {
+            let data = TextureData::from_baked(bytes).map_err(image::ImageError::IoError)?;
+            return Ok(Self::from_data(device, queue, &data, label, sampler));
+        }
+
         let img = image::load_from_memory(bytes)?;
         Ok(Self::from_image(device, queue, &img, label, sampler))
     }
@@ -212,6 +321,156 @@ impl Texture {
         }
     }
 
+    /// Create a wgpu Texture with every mip level in `data`.
+    #[inline]
+    pub fn from_data(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        data: &TextureData,
+        label: Option<&str>,
+        sampler: Option<&wgpu::SamplerDescriptor>,
+    ) -> Self {
+        Self::from_data_with_format(
+            device,
+            queue,
+            data,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            label,
+            sampler,
+        )
+    }
+
+    /// Like [`Texture::from_data`] with an rgba8 `format` other than srgb, e.g.
+    /// `Rgba8Unorm` for normal maps and other data that isn't a color.
+    pub fn from_data_with_format(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        data: &TextureData,
+        format: wgpu::TextureFormat,
+        label: Option<&str>,
+        sampler: Option<&wgpu::SamplerDescriptor>,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width: data.width,
+                height: data.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: data.mips.len().max(1) as u32,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        data.mips.iter().enumerate().for_each(|(level, pixels)| {
+            let (width, height) = data.mip_size(level as u32);
+
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: level as u32,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                pixels,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * width),
+                    rows_per_image: None,
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(sampler.unwrap_or(&wgpu::SamplerDescriptor::default()));
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Create a wgpu Texture from a KTX2 container, keeping its format and mip levels.
+    /// Block compressed textures the adapter can't sample are decoded to rgba8 first.
+    pub fn from_ktx2(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: Option<&str>,
+        sampler: Option<&wgpu::SamplerDescriptor>,
+    ) -> Result<Self, Ktx2Error> {
+        let mut ktx2 = Ktx2::parse(bytes)?;
+
+        if !ktx2.is_supported(device) {
+            log::debug!(
+                "Decoding {:?} texture {:?} as the adapter doesn't support it",
+                ktx2.format,
+                label
+            );
+            ktx2 = ktx2.decompress()?;
+        }
+
+        let size = wgpu::Extent3d {
+            width: ktx2.width,
+            height: ktx2.height,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: ktx2.levels.len() as u32,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ktx2.format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let (block_width, _) = ktx2.format.block_dimensions();
+        let block_size = ktx2.format.block_copy_size(None).unwrap_or(4);
+
+        ktx2.levels.iter().enumerate().for_each(|(level, data)| {
+            let level_size = size
+                .mip_level_size(level as u32, wgpu::TextureDimension::D2)
+                .physical_size(ktx2.format);
+
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: level as u32,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(level_size.width / block_width * block_size),
+                    rows_per_image: None,
+                },
+                level_size,
+            );
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(sampler.unwrap_or(&wgpu::SamplerDescriptor::default()));
+
+        Ok(Self {
+            texture,
+            view,
+            sampler,
+        })
+    }
+
     pub fn from_size(
         device: &wgpu::Device,
         size: Size<u32>,
@@ -244,6 +503,266 @@ impl Texture {
     }
 }
 
+impl Texture {
+    /// Texture that can be rendered into and then sampled like any other texture.
+    pub fn create_render_texture(
+        device: &wgpu::Device,
+        size: Size<u32>,
+        format: wgpu::TextureFormat,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(&format!("Render Texture: {}", label)),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Cube texture whose faces can each be rendered into, see [`Texture::cube_face_view`].
+    pub fn create_cube_render_texture(
+        device: &wgpu::Device,
+        size: u32,
+        format: wgpu::TextureFormat,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(&format!("Cube Render Texture: {}", label)),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// View of a single face of a cube texture, ordered +X, -X, +Y, -Y, +Z, -Z.
+    pub fn cube_face_view(&self, face: u32) -> wgpu::TextureView {
+        self.texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Cube Face View"),
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            base_array_layer: face,
+            array_layer_count: Some(1),
+            ..Default::default()
+        })
+    }
+}
+
+impl Texture {
+    /// Cube texture from six square faces of the same size, ordered +X, -X, +Y, -Y, +Z, -Z
+    /// (right, left, top, bottom, front, back).
+    pub fn from_cube_faces(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        faces: &[image::DynamicImage; 6],
+        label: Option<&str>,
+    ) -> Self {
+        let size = faces[0].width();
+        let faces = faces
+            .iter()
+            .map(|face| {
+                let face = match face.dimensions() == (size, size) {
+                    true => face.to_rgba8(),
+                    false => {
+                        log::warn!(
+                            "Cube face is {:?} instead of {}x{} - resizing",
+                            face.dimensions(),
+                            size,
+                            size
+                        );
+                        face.resize_exact(size, size, image::imageops::FilterType::Triangle)
+                            .to_rgba8()
+                    }
+                };
+                face.into_raw()
+            })
+            .collect::<Vec<_>>();
+
+        Self::create_cube(
+            device,
+            queue,
+            size,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            &faces,
+            label,
+        )
+    }
+
+    /// Cube texture from an equirectangular (latitude/longitude) panorama, keeping high
+    /// dynamic range images in a float format. The center of the image faces +Z.
+    pub fn from_equirect(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: &image::DynamicImage,
+        label: Option<&str>,
+    ) -> Self {
+        let source = image.to_rgba32f();
+        let size = (source.width() / 4).max(1);
+
+        let sample = |direction: glam::Vec3| {
+            let direction = direction.normalize();
+            let u = 0.5 + direction.x.atan2(direction.z) / std::f32::consts::TAU;
+            let v = 0.5 - direction.y.asin() / std::f32::consts::PI;
+
+            let x = u * source.width() as f32 - 0.5;
+            let y = (v * source.height() as f32 - 0.5).clamp(0., source.height() as f32 - 1.);
+            let (x0, y0) = (x.floor(), y.floor());
+            let (tx, ty) = (x - x0, y - y0);
+
+            let pixel = |x: f32, y: f32| {
+                let x = (x as i64).rem_euclid(source.width() as i64) as u32;
+                let y = (y as u32).min(source.height() - 1);
+                glam::Vec4::from_array(source.get_pixel(x, y).0)
+            };
+
+            let top = pixel(x0, y0).lerp(pixel(x0 + 1., y0), tx);
+            let bottom = pixel(x0, y0 + 1.).lerp(pixel(x0 + 1., y0 + 1.), tx);
+            top.lerp(bottom, ty)
+        };
+
+        let faces = (0..6)
+            .map(|face| {
+                (0..size * size)
+                    .flat_map(|index| {
+                        let u = 2. * ((index % size) as f32 + 0.5) / size as f32 - 1.;
+                        let v = 2. * ((index / size) as f32 + 0.5) / size as f32 - 1.;
+
+                        let direction = match face {
+                            0 => glam::vec3(1., -v, -u),
+                            1 => glam::vec3(-1., -v, u),
+                            2 => glam::vec3(u, 1., v),
+                            3 => glam::vec3(u, -1., -v),
+                            4 => glam::vec3(u, -v, 1.),
+                            _ => glam::vec3(-u, -v, -1.),
+                        };
+
+                        sample(direction).to_array()
+                    })
+                    .flat_map(|channel| half::f16::from_f32(channel).to_le_bytes())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        Self::create_cube(
+            device,
+            queue,
+            size,
+            wgpu::TextureFormat::Rgba16Float,
+            &faces,
+            label,
+        )
+    }
+
+    fn create_cube(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: u32,
+        format: wgpu::TextureFormat,
+        faces: &[Vec<u8>],
+        label: Option<&str>,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let bytes_per_pixel = format.block_copy_size(None).unwrap_or(4);
+
+        faces.iter().enumerate().for_each(|(layer, pixels)| {
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as u32,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                pixels,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_pixel * size),
+                    rows_per_image: None,
+                },
+                wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: 1,
+                },
+            );
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+}
+
 impl Texture {
     pub fn update_area(
         &mut self,
@@ -278,6 +797,406 @@ impl Texture {
             },
         );
     }
+
+    /// Copy the first mip level back from the gpu, blocking until it arrives. Only 8 bit
+    /// rgba and bgra textures created with `COPY_SRC` (such as render textures) can be read.
+    /// Returns `None` for other formats or when the device can't block (the web).
+    pub fn read_pixels(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Option<image::RgbaImage> {
+        let bgra = match self.texture.format() {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            _ => return None,
+        };
+
+        let size = self.texture.size();
+        let unpadded_row = size.width * 4;
+        let padded_row = unpadded_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Texture Readback Buffer"),
+            size: (padded_row * size.height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Texture Readback Encoder"),
+        });
+
+        encoder.copy_texture_to_buffer(
+            self.texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                depth_or_array_layers: 1,
+                ..size
+            },
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+
+        if let Err(e) = receiver.try_recv().ok()? {
+            log::warn!("Unable to read texture: {}", e);
+            return None;
+        }
+
+        let mut pixels = slice
+            .get_mapped_range()
+            .chunks(padded_row as usize)
+            .flat_map(|row| row[..unpadded_row as usize].to_vec())
+            .collect::<Vec<_>>();
+        buffer.unmap();
+
+        if bgra {
+            pixels.chunks_mut(4).for_each(|pixel| pixel.swap(0, 2));
+        }
+
+        image::RgbaImage::from_raw(size.width, size.height, pixels)
+    }
+}
+
+//====================================================================
+
+/// Fills in the mip levels of textures on the gpu by repeatedly rendering each level into
+/// the next at half the size. Render pipelines are created for each format as needed.
+pub struct MipmapGenerator {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    sampler: wgpu::Sampler,
+    pipelines: Mutex<Vec<(wgpu::TextureFormat, wgpu::RenderPipeline)>>,
+}
+
+impl MipmapGenerator {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Mipmap Bind Group Layout"),
+            entries: &[tools::bgl_texture_entry(0), tools::bgl_sampler_entry(1)],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Mipmap Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Mipmap Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/blit.wgsl").into()),
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Mipmap Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            bind_group_layout,
+            pipeline_layout,
+            shader,
+            sampler,
+            pipelines: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Whether mips can be generated for textures of this format.
+    pub fn supports(device: &wgpu::Device, format: wgpu::TextureFormat) -> bool {
+        let features = format.guaranteed_format_features(device.features());
+
+        features
+            .allowed_usages
+            .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+            && features
+                .flags
+                .contains(wgpu::TextureFormatFeatureFlags::FILTERABLE)
+    }
+
+    /// Generate every mip level of `texture` after the first, for each of its layers. The
+    /// texture needs to be created with `RENDER_ATTACHMENT` and `TEXTURE_BINDING` usages.
+    pub fn generate(&self, device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) {
+        if texture.mip_level_count() < 2 {
+            return;
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Mipmap Encoder"),
+        });
+
+        self.encode_levels(device, &mut encoder, texture);
+        queue.submit(Some(encoder.finish()));
+    }
+
+    /// Copy `source` into the first level of `texture` before generating the rest.
+    pub fn generate_from(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: &wgpu::TextureView,
+        texture: &wgpu::Texture,
+    ) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Mipmap Encoder"),
+        });
+
+        let target = Self::level_view(texture, 0, 0);
+        self.draw(device, &mut encoder, texture.format(), source, &target);
+
+        self.encode_levels(device, &mut encoder, texture);
+        queue.submit(Some(encoder.finish()));
+    }
+
+    fn encode_levels(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+    ) {
+        (0..texture.depth_or_array_layers()).for_each(|layer| {
+            let views = (0..texture.mip_level_count())
+                .map(|level| Self::level_view(texture, level, layer))
+                .collect::<Vec<_>>();
+
+            views.windows(2).for_each(|levels| {
+                self.draw(device, encoder, texture.format(), &levels[0], &levels[1]);
+            });
+        });
+    }
+
+    fn level_view(texture: &wgpu::Texture, level: u32, layer: u32) -> wgpu::TextureView {
+        texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Mipmap Level View"),
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            base_mip_level: level,
+            mip_level_count: Some(1),
+            base_array_layer: layer,
+            array_layer_count: Some(1),
+            ..Default::default()
+        })
+    }
+
+    fn draw(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        format: wgpu::TextureFormat,
+        source: &wgpu::TextureView,
+        target: &wgpu::TextureView,
+    ) {
+        let mut pipelines = self.pipelines.lock().unwrap();
+
+        let index = match pipelines
+            .iter()
+            .position(|(existing, _)| *existing == format)
+        {
+            Some(index) => index,
+            None => {
+                pipelines.push((format, self.create_pipeline(device, format)));
+                pipelines.len() - 1
+            }
+        };
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Mipmap Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Mipmap Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        pass.set_pipeline(&pipelines[index].1);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&format!("Mipmap Pipeline {:?}", format)),
+            layout: Some(&self.pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &self.shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &self.shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        })
+    }
+}
+
+//====================================================================
+
+const BAKED_MAGIC: &[u8; 4] = b"HTEX";
+const BAKED_VERSION: u32 = 1;
+
+/// Rgba8 pixels with an optional mip chain, ready to upload without decoding.
+/// Baked with [`TextureData::to_baked`] so textures can skip image decoding when loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextureData {
+    pub width: u32,
+    pub height: u32,
+    /// Pixels of each mip level, starting with the full size image.
+    pub mips: Vec<Vec<u8>>,
+}
+
+impl TextureData {
+    /// Convert an image, generating the full mip chain down to 1x1 if `mipmaps` is set.
+    pub fn from_image(image: &image::DynamicImage, mipmaps: bool) -> Self {
+        let rgba = image.to_rgba8();
+        let (width, height) = rgba.dimensions();
+
+        let mut data = Self {
+            width,
+            height,
+            mips: Vec::new(),
+        };
+
+        let levels = match mipmaps {
+            true => 32 - width.max(height).max(1).leading_zeros(),
+            false => 1,
+        };
+
+        data.mips = (1..levels)
+            .map(|level| {
+                let (width, height) = data.mip_size(level);
+                image::imageops::resize(&rgba, width, height, image::imageops::FilterType::Triangle)
+                    .into_raw()
+            })
+            .collect();
+        data.mips.insert(0, rgba.into_raw());
+
+        data
+    }
+
+    #[inline]
+    pub fn mip_size(&self, level: u32) -> (u32, u32) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+
+    /// Whether `bytes` were written by [`TextureData::to_baked`].
+    #[inline]
+    pub fn is_baked(bytes: &[u8]) -> bool {
+        bytes.starts_with(BAKED_MAGIC)
+    }
+
+    /// Header (magic, version, width, height, mip count) followed by each mip level's pixels.
+    pub fn to_baked(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(20 + self.mips.iter().map(Vec::len).sum::<usize>());
+
+        bytes.extend_from_slice(BAKED_MAGIC);
+        [
+            BAKED_VERSION,
+            self.width,
+            self.height,
+            self.mips.len() as u32,
+        ]
+        .iter()
+        .for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+
+        self.mips
+            .iter()
+            .for_each(|mip| bytes.extend_from_slice(mip));
+        bytes
+    }
+
+    pub fn from_baked(bytes: &[u8]) -> std::io::Result<Self> {
+        let invalid = |message: &str| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+        };
+
+        if !Self::is_baked(bytes) || bytes.len() < 20 {
+            return Err(invalid("Not a baked texture"));
+        }
+
+        let header = |index: usize| {
+            u32::from_le_bytes(bytes[4 + index * 4..8 + index * 4].try_into().unwrap())
+        };
+
+        if header(0) != BAKED_VERSION {
+            return Err(invalid("Unsupported baked texture version"));
+        }
+
+        let mut data = Self {
+            width: header(1),
+            height: header(2),
+            mips: Vec::new(),
+        };
+
+        let mut offset = 20;
+        data.mips = (0..header(3))
+            .map(|level| {
+                let (width, height) = data.mip_size(level);
+                let length = (width * height * 4) as usize;
+
+                let mip = bytes
+                    .get(offset..offset + length)
+                    .ok_or_else(|| invalid("Baked texture is truncated"))?;
+                offset += length;
+
+                Ok(mip.to_vec())
+            })
+            .collect::<std::io::Result<_>>()?;
+
+        Ok(data)
+    }
 }
 
 //====================================================================
@@ -1,39 +1,31 @@
 //====================================================================
 
-use std::{marker::PhantomData, num::NonZeroU32};
+use std::{borrow::Cow, marker::PhantomData, num::NonZeroU32};
 
 use wgpu::util::DeviceExt;
 
-use super::texture::Texture;
-
 //====================================================================
 
+#[derive(Default)]
 pub struct RenderPipelineDescriptor<'a> {
     pub primitive: wgpu::PrimitiveState,
     pub depth_stencil: Option<wgpu::DepthStencilState>,
     pub multisample: wgpu::MultisampleState,
     pub fragment_targets: Option<&'a [Option<wgpu::ColorTargetState>]>,
+    /// Vertex shader entry point. Defaults to `vs_main`.
+    pub vertex_entry: Option<&'a str>,
+    /// Fragment shader entry point. Defaults to `fs_main`.
+    pub fragment_entry: Option<&'a str>,
+    /// No fragment stage, only writing depth, e.g. into a shadow map.
+    pub depth_only: bool,
     pub multiview: Option<NonZeroU32>,
     pub cache: Option<&'a wgpu::PipelineCache>,
 }
 
-impl<'a> Default for RenderPipelineDescriptor<'a> {
-    fn default() -> Self {
-        Self {
-            primitive: wgpu::PrimitiveState::default(),
-            depth_stencil: None,
-            multisample: Default::default(),
-            fragment_targets: None,
-            multiview: None,
-            cache: None,
-        }
-    }
-}
-
 impl RenderPipelineDescriptor<'_> {
-    pub fn with_depth_stencil(mut self) -> Self {
+    pub fn with_depth_stencil(mut self, format: wgpu::TextureFormat) -> Self {
         self.depth_stencil = Some(wgpu::DepthStencilState {
-            format: Texture::DEPTH_FORMAT,
+            format,
             depth_write_enabled: true,
             depth_compare: wgpu::CompareFunction::Less,
             stencil: wgpu::StencilState::default(),
@@ -47,11 +39,101 @@ impl RenderPipelineDescriptor<'_> {
         self.primitive.cull_mode = Some(wgpu::Face::Back);
         self
     }
+
+    /// Apply after [`Self::with_depth_stencil`] so the depth bias is kept.
+    pub fn with_raster_state(mut self, state: RasterState) -> Self {
+        self.primitive.cull_mode = state.cull_mode;
+        self.primitive.topology = state.topology;
+
+        // Depth bias is only valid for triangles
+        if let (Some(depth_stencil), false) = (&mut self.depth_stencil, state.is_line_or_point()) {
+            depth_stencil.bias = wgpu::DepthBiasState {
+                constant: state.depth_bias,
+                slope_scale: state.depth_bias_slope_scale,
+                clamp: 0.,
+            };
+        }
+
+        self
+    }
+}
+
+//--------------------------------------------------
+
+/// Culling, depth bias and topology for pipelines that create a variant per state used.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RasterState {
+    pub cull_mode: Option<wgpu::Face>,
+    /// Line and point topologies read the mesh's indices as lines or points.
+    pub topology: wgpu::PrimitiveTopology,
+    /// Negative values pull geometry towards the camera, e.g. decals over a wall.
+    pub depth_bias: i32,
+    pub depth_bias_slope_scale: f32,
+}
+
+impl Default for RasterState {
+    fn default() -> Self {
+        Self {
+            cull_mode: Some(wgpu::Face::Back),
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            depth_bias: 0,
+            depth_bias_slope_scale: 0.,
+        }
+    }
+}
+
+impl RasterState {
+    /// Render both faces, e.g. foliage quads.
+    #[inline]
+    pub fn double_sided() -> Self {
+        Self {
+            cull_mode: None,
+            ..Default::default()
+        }
+    }
+
+    /// Pulled towards the camera so it wins against coplanar geometry.
+    #[inline]
+    pub fn decal() -> Self {
+        Self {
+            depth_bias: -2,
+            depth_bias_slope_scale: -1.,
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    pub fn lines() -> Self {
+        Self {
+            cull_mode: None,
+            topology: wgpu::PrimitiveTopology::LineList,
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    pub fn points() -> Self {
+        Self {
+            cull_mode: None,
+            topology: wgpu::PrimitiveTopology::PointList,
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    fn is_line_or_point(&self) -> bool {
+        matches!(
+            self.topology,
+            wgpu::PrimitiveTopology::PointList
+                | wgpu::PrimitiveTopology::LineList
+                | wgpu::PrimitiveTopology::LineStrip
+        )
+    }
 }
 
 pub fn create_pipeline(
     device: &wgpu::Device,
-    config: &wgpu::SurfaceConfiguration,
+    format: wgpu::TextureFormat,
     label: &str,
     bind_group_layouts: &[&wgpu::BindGroupLayout],
     vertex_buffers: &[wgpu::VertexBufferLayout],
@@ -71,7 +153,7 @@ pub fn create_pipeline(
     });
 
     let default_fragment_targets = [Some(wgpu::ColorTargetState {
-        format: config.format,
+        format,
         blend: Some(wgpu::BlendState::REPLACE),
         write_mask: wgpu::ColorWrites::all(),
     })];
@@ -82,16 +164,16 @@ pub fn create_pipeline(
         layout: Some(&layout),
         vertex: wgpu::VertexState {
             module: &shader_module,
-            entry_point: Some("vs_main"),
+            entry_point: Some(desc.vertex_entry.unwrap_or("vs_main")),
             compilation_options: Default::default(),
             buffers: vertex_buffers,
         },
         primitive: desc.primitive,
         depth_stencil: desc.depth_stencil,
         multisample: desc.multisample,
-        fragment: Some(wgpu::FragmentState {
+        fragment: (!desc.depth_only).then(|| wgpu::FragmentState {
             module: &shader_module,
-            entry_point: Some("fs_main"),
+            entry_point: Some(desc.fragment_entry.unwrap_or("fs_main")),
             compilation_options: Default::default(),
             targets: fragment_targets,
         }),
@@ -102,6 +184,48 @@ pub fn create_pipeline(
 
 //====================================================================
 
+/// Include a wgsl file, relative to the calling crate's manifest, for
+/// [`create_pipeline`]. With the `hot-reload` feature the file is read from disk
+/// instead and pipelines using it are rebuilt when it changes.
+#[macro_export]
+macro_rules! include_shader {
+    ($path:literal) => {
+        $crate::tools::load_shader(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/", $path),
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $path)),
+        )
+    };
+}
+
+/// Add a wgsl file, relative to the calling crate's manifest, to a
+/// [`crate::shader_library::ShaderLibrary`] as the module `name`. Reloaded like
+/// [`include_shader`] with the `hot-reload` feature.
+#[macro_export]
+macro_rules! add_shader_module {
+    ($library:expr, $name:expr, $path:literal) => {
+        $library.add_file(
+            $name,
+            concat!(env!("CARGO_MANIFEST_DIR"), "/", $path),
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $path)),
+        )
+    };
+}
+
+#[doc(hidden)]
+#[inline]
+pub fn load_shader(path: &'static str, embedded: &'static str) -> Cow<'static, str> {
+    #[cfg(all(feature = "hot-reload", not(target_arch = "wasm32")))]
+    return crate::hot_reload::load_shader(path, embedded);
+
+    #[cfg(not(all(feature = "hot-reload", not(target_arch = "wasm32"))))]
+    {
+        let _ = path;
+        Cow::Borrowed(embedded)
+    }
+}
+
+//====================================================================
+
 /// bind group layout uniform entry
 pub fn bgl_uniform_entry(
     binding: u32,
@@ -148,6 +272,32 @@ pub fn bgl_texture_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
     }
 }
 
+pub fn bgl_cube_texture_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::Cube,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+pub fn bgl_depth_texture_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Depth,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
 pub fn bgl_sampler_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
     wgpu::BindGroupLayoutEntry {
         binding,
@@ -157,6 +307,390 @@ pub fn bgl_sampler_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
     }
 }
 
+pub fn bgl_comparison_sampler_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+        count: None,
+    }
+}
+
+//====================================================================
+
+#[doc(hidden)]
+pub use bytemuck;
+
+/// Type with a wgsl equivalent, laid out as it would be in a uniform buffer.
+/// Structs are defined with [`wgsl_struct!`].
+pub trait WgslType: bytemuck::Pod {
+    const ALIGN: usize;
+    const SIZE: usize;
+
+    fn wgsl_name() -> Cow<'static, str>;
+
+    /// Append the definitions of any structs the type uses, each only once.
+    fn wgsl_definitions(out: &mut String) {
+        let _ = out;
+    }
+}
+
+macro_rules! impl_wgsl_type {
+    ($($ty:ty => $name:literal, $align:literal, $size:literal;)*) => {
+        $(
+            impl WgslType for $ty {
+                const ALIGN: usize = $align;
+                const SIZE: usize = $size;
+
+                #[inline]
+                fn wgsl_name() -> Cow<'static, str> {
+                    Cow::Borrowed($name)
+                }
+            }
+        )*
+    };
+}
+
+impl_wgsl_type! {
+    f32 => "f32", 4, 4;
+    u32 => "u32", 4, 4;
+    i32 => "i32", 4, 4;
+    glam::Vec2 => "vec2<f32>", 8, 8;
+    glam::Vec3 => "vec3<f32>", 16, 12;
+    glam::Vec4 => "vec4<f32>", 16, 16;
+    glam::UVec2 => "vec2<u32>", 8, 8;
+    glam::UVec4 => "vec4<u32>", 16, 16;
+    glam::IVec2 => "vec2<i32>", 8, 8;
+    glam::IVec4 => "vec4<i32>", 16, 16;
+    glam::Mat4 => "mat4x4<f32>", 16, 64;
+}
+
+/// Arrays in uniform buffers have elements 16 byte aligned.
+impl<T: WgslType, const N: usize> WgslType for [T; N]
+where
+    [T; N]: bytemuck::Pod,
+{
+    const ALIGN: usize = if T::ALIGN > 16 { T::ALIGN } else { 16 };
+    const SIZE: usize = N * T::SIZE.next_multiple_of(Self::ALIGN);
+
+    #[inline]
+    fn wgsl_name() -> Cow<'static, str> {
+        Cow::Owned(format!("array<{}, {}>", T::wgsl_name(), N))
+    }
+
+    #[inline]
+    fn wgsl_definitions(out: &mut String) {
+        T::wgsl_definitions(out);
+    }
+}
+
+/// Wgsl definitions of `T` and every struct it uses.
+#[inline]
+pub fn wgsl_definitions<T: WgslType>() -> String {
+    let mut out = String::new();
+    T::wgsl_definitions(&mut out);
+    out
+}
+
+/// Wgsl offsets, alignment and size of a struct with fields of the given alignments and sizes.
+#[doc(hidden)]
+pub const fn wgsl_layout<const N: usize>(
+    fields: [(usize, usize); N],
+) -> ([usize; N], usize, usize) {
+    let mut offsets = [0; N];
+    let mut align = 1;
+    let mut end: usize = 0;
+
+    let mut index = 0;
+    while index < N {
+        let (field_align, field_size) = fields[index];
+        offsets[index] = end.next_multiple_of(field_align);
+        end = offsets[index] + field_size;
+        if field_align > align {
+            align = field_align;
+        }
+        index += 1;
+    }
+
+    (offsets, align, end.next_multiple_of(align))
+}
+
+/// Define a `#[repr(C)]` struct along with its wgsl definition, checking at compile time that
+/// its fields line up with the wgsl uniform layout. Padding must be spelled out as explicit
+/// fields, as any implicit padding fails to compile.
+///
+/// ```ignore
+/// renderer::wgsl_struct! {
+///     pub struct BlurUniform {
+///         pub direction: glam::Vec2,
+///         pub radius: f32,
+///         pub _pad: f32,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! wgsl_struct {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident: $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[repr(C)]
+        #[derive(Clone, Copy, Debug)]
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $ty),*
+        }
+
+        // Sound as every field is Pod and the checks below rule out padding
+        unsafe impl $crate::tools::bytemuck::Zeroable for $name {}
+        unsafe impl $crate::tools::bytemuck::Pod for $name {}
+
+        const _: fn() = || {
+            fn field_is_pod<T: $crate::tools::bytemuck::Pod>() {}
+            $(field_is_pod::<$ty>();)*
+        };
+
+        const _: () = {
+            const LAYOUT: ([usize; <[&str]>::len(&[$(stringify!($field)),*])], usize, usize) =
+                $crate::tools::wgsl_layout([$((
+                    <$ty as $crate::tools::WgslType>::ALIGN,
+                    <$ty as $crate::tools::WgslType>::SIZE,
+                )),*]);
+
+            let offsets = [$(::std::mem::offset_of!($name, $field)),*];
+            let mut index = 0;
+            while index < offsets.len() {
+                assert!(
+                    offsets[index] == LAYOUT.0[index],
+                    concat!("field of ", stringify!($name), " isn't at its wgsl offset - add padding before it"),
+                );
+                index += 1;
+            }
+
+            assert!(
+                ::std::mem::size_of::<$name>() == LAYOUT.2,
+                concat!(stringify!($name), " isn't its wgsl size - add padding at the end"),
+            );
+
+            // Padding shared by rust and wgsl would still pass the checks above
+            assert!(
+                0 $(+ ::std::mem::size_of::<$ty>())* == ::std::mem::size_of::<$name>(),
+                concat!(stringify!($name), " has implicit padding - add explicit `_pad` fields"),
+            );
+        };
+
+        impl $crate::tools::WgslType for $name {
+            // Structs in uniform buffers are 16 byte aligned
+            const ALIGN: usize = 16;
+            const SIZE: usize = ::std::mem::size_of::<$name>();
+
+            #[inline]
+            fn wgsl_name() -> ::std::borrow::Cow<'static, str> {
+                ::std::borrow::Cow::Borrowed(stringify!($name))
+            }
+
+            fn wgsl_definitions(out: &mut String) {
+                let header = concat!("struct ", stringify!($name), " {\n");
+                if out.contains(header) {
+                    return;
+                }
+
+                $(<$ty as $crate::tools::WgslType>::wgsl_definitions(out);)*
+
+                out.push_str(header);
+                $(
+                    out.push_str(&format!(
+                        "    {}: {},\n",
+                        stringify!($field),
+                        <$ty as $crate::tools::WgslType>::wgsl_name()
+                    ));
+                )*
+                out.push_str("}\n\n");
+            }
+        }
+    };
+}
+
+//--------------------------------------------------
+
+/// Builds a bind group layout one binding at a time, numbering bindings in order and
+/// keeping their wgsl declarations to match.
+///
+/// ```ignore
+/// let layout = tools::BindGroupLayoutBuilder::new("Blur")
+///     .uniform::<BlurUniform>("blur", wgpu::ShaderStages::FRAGMENT)
+///     .texture("source")
+///     .sampler("source_sampler")
+///     .build(device);
+///
+/// let bind_group = layout.bind_group(device, &[
+///     buffer.as_entire_binding(),
+///     wgpu::BindingResource::TextureView(&view),
+///     wgpu::BindingResource::Sampler(&sampler),
+/// ]);
+/// ```
+pub struct BindGroupLayoutBuilder {
+    label: String,
+    entries: Vec<wgpu::BindGroupLayoutEntry>,
+    /// Name and wgsl type of each binding.
+    declarations: Vec<(String, String)>,
+    definitions: String,
+}
+
+impl BindGroupLayoutBuilder {
+    #[inline]
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            entries: Vec::new(),
+            declarations: Vec::new(),
+            definitions: String::new(),
+        }
+    }
+
+    #[inline]
+    fn next_binding(&self) -> u32 {
+        self.entries.len() as u32
+    }
+
+    fn with_entry(
+        mut self,
+        entry: wgpu::BindGroupLayoutEntry,
+        name: &str,
+        declaration: String,
+    ) -> Self {
+        self.entries.push(entry);
+        self.declarations.push((name.to_string(), declaration));
+        self
+    }
+
+    pub fn uniform<T: WgslType>(mut self, name: &str, visibility: wgpu::ShaderStages) -> Self {
+        T::wgsl_definitions(&mut self.definitions);
+        let entry = bgl_uniform_entry(self.next_binding(), visibility);
+        self.with_entry(
+            entry,
+            name,
+            format!("var<uniform> {}: {}", name, T::wgsl_name()),
+        )
+    }
+
+    /// Read only storage buffer of `T`s.
+    pub fn storage<T: WgslType>(mut self, name: &str, visibility: wgpu::ShaderStages) -> Self {
+        T::wgsl_definitions(&mut self.definitions);
+        let entry = bgl_storage_entry(self.next_binding(), visibility);
+        self.with_entry(
+            entry,
+            name,
+            format!("var<storage, read> {}: array<{}>", name, T::wgsl_name()),
+        )
+    }
+
+    #[inline]
+    pub fn texture(self, name: &str) -> Self {
+        let entry = bgl_texture_entry(self.next_binding());
+        self.with_entry(entry, name, format!("var {}: texture_2d<f32>", name))
+    }
+
+    #[inline]
+    pub fn cube_texture(self, name: &str) -> Self {
+        let entry = bgl_cube_texture_entry(self.next_binding());
+        self.with_entry(entry, name, format!("var {}: texture_cube<f32>", name))
+    }
+
+    #[inline]
+    pub fn depth_texture(self, name: &str) -> Self {
+        let entry = bgl_depth_texture_entry(self.next_binding());
+        self.with_entry(entry, name, format!("var {}: texture_depth_2d", name))
+    }
+
+    #[inline]
+    pub fn sampler(self, name: &str) -> Self {
+        let entry = bgl_sampler_entry(self.next_binding());
+        self.with_entry(entry, name, format!("var {}: sampler", name))
+    }
+
+    pub fn build(self, device: &wgpu::Device) -> BindLayout {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(&format!("{} Bind Group Layout", self.label)),
+            entries: &self.entries,
+        });
+
+        BindLayout {
+            layout,
+            label: self.label,
+            declarations: self.declarations,
+            definitions: self.definitions,
+        }
+    }
+}
+
+/// Bind group layout made by a [`BindGroupLayoutBuilder`].
+pub struct BindLayout {
+    layout: wgpu::BindGroupLayout,
+    label: String,
+    declarations: Vec<(String, String)>,
+    definitions: String,
+}
+
+impl BindLayout {
+    #[inline]
+    pub fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    /// Bind group of `resources`, given in the order their bindings were added.
+    pub fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        resources: &[wgpu::BindingResource],
+    ) -> wgpu::BindGroup {
+        assert_eq!(
+            resources.len(),
+            self.declarations.len(),
+            "{} takes {} bindings",
+            self.label,
+            self.declarations.len()
+        );
+
+        let entries = resources
+            .iter()
+            .enumerate()
+            .map(|(binding, resource)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: resource.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{} Bind Group", self.label)),
+            layout: &self.layout,
+            entries: &entries,
+        })
+    }
+
+    /// Wgsl struct definitions and binding declarations for the layout used as `group`, to
+    /// prepend to a shader.
+    pub fn wgsl(&self, group: u32) -> String {
+        let mut out = self.definitions.clone();
+
+        self.declarations
+            .iter()
+            .enumerate()
+            .for_each(|(binding, (_, declaration))| {
+                out.push_str(&format!(
+                    "@group({}) @binding({}) {};\n",
+                    group, binding, declaration
+                ));
+            });
+
+        out
+    }
+}
+
+//====================================================================
+
 pub enum BufferType {
     Vertex,
     Index,
@@ -201,28 +735,30 @@ pub fn update_instance_buffer<T: bytemuck::Pod>(
     instance_count: &mut u32,
 
     data: &[T],
-) {
-    if data.len() == 0 {
+) -> bool {
+    if data.is_empty() {
         // Nothing to update
         if *instance_count != 0 {
             // Empty buffer and reset instance count
             *buffer = create_instance_buffer(device, label, data);
             *instance_count = 0;
+            return true;
         }
 
-        return;
+        return false;
     }
 
     // We can fit all data inside existing buffer
     if data.len() <= *instance_count as usize {
         queue.write_buffer(buffer, 0, bytemuck::cast_slice(data));
         *instance_count = data.len() as u32; // TODO - add additional variable for buffer size
-        return;
+        return false;
     }
 
     // Buffer is too small to fit new data. Create a new bigger one.
     *instance_count = data.len() as u32;
     *buffer = create_instance_buffer(device, label, data);
+    true
 }
 
 pub fn create_instance_buffer<T: bytemuck::Pod>(
@@ -260,8 +796,9 @@ impl<T: bytemuck::Pod> InstanceBuffer<T> {
         }
     }
 
+    /// Returns true if the underlying buffer had to be recreated.
     #[inline]
-    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[T]) {
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[T]) -> bool {
         update_instance_buffer(
             device,
             queue,
@@ -270,7 +807,7 @@ impl<T: bytemuck::Pod> InstanceBuffer<T> {
             &mut self.buffer,
             &mut self.count,
             data,
-        );
+        )
     }
 
     #[inline]
@@ -0,0 +1,259 @@
+//====================================================================
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use engine::vfs::{normalize_path, write_pak, BakeManifest, PakCompression, BAKE_MANIFEST};
+use pipelines::{model_loader::ModelData, texture_renderer::AtlasLayout};
+use renderer::texture::TextureData;
+
+//====================================================================
+
+const TEXTURE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "bmp", "tga"];
+const MODEL_EXTENSIONS: &[&str] = &["obj", "gltf", "glb"];
+const ATLAS_EXTENSION: &str = "atlas";
+
+const BAKED_TEXTURE_EXTENSION: &str = "htex";
+const BAKED_MODEL_EXTENSION: &str = "hmdl";
+
+#[derive(Debug, Clone)]
+pub struct BakeConfig {
+    /// Folder of source assets. Paths in the pack start with this path as given, so bake
+    /// from the folder the game runs in.
+    pub source: PathBuf,
+    /// Pak file to write.
+    pub output: PathBuf,
+    pub compression: PakCompression,
+    /// Generate mip chains for textures and model textures.
+    pub mipmaps: bool,
+    /// Pixels between images packed into atlases.
+    pub atlas_padding: u32,
+}
+
+impl Default for BakeConfig {
+    fn default() -> Self {
+        Self {
+            source: PathBuf::from("res"),
+            output: PathBuf::from("assets.pak"),
+            compression: PakCompression::Deflate,
+            mipmaps: true,
+            atlas_padding: 1,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BakeReport {
+    pub textures: usize,
+    pub models: usize,
+    pub atlases: usize,
+    pub copied: usize,
+    /// Files that couldn't be baked along with why. They are copied as is instead.
+    pub failed: Vec<(String, String)>,
+}
+
+//====================================================================
+
+/// Convert a folder of source assets into a single pak file that loads without decoding:
+///
+/// - Images become [`TextureData`] with mip chains.
+/// - Models become baked [`ModelData`] with tangents, bounds and their textures included.
+/// - Folders ending in `.atlas` are packed into one texture and an [`AtlasLayout`] json,
+///   e.g. `sprites.atlas/` becomes `sprites.atlas.htex` and `sprites.atlas.json`.
+/// - Everything else is copied as is.
+///
+/// The pack's [`BakeManifest`] maps original paths to baked ones, so once the pak is mounted
+/// loading `res/player.png` reads the baked texture. Can be run from a build script or with
+/// `cargo run --bin bake -- <source> <output>`.
+pub fn bake(config: &BakeConfig) -> std::io::Result<BakeReport> {
+    let mut baker = Baker {
+        config,
+        files: BTreeMap::new(),
+        manifest: BakeManifest {
+            version: 1,
+            files: BTreeMap::new(),
+        },
+        report: BakeReport::default(),
+    };
+
+    baker.bake_directory(&config.source)?;
+
+    let Baker {
+        mut files,
+        manifest,
+        report,
+        ..
+    } = baker;
+
+    files.insert(
+        BAKE_MANIFEST.to_string(),
+        serde_json::to_vec_pretty(&manifest)?,
+    );
+    write_pak(&config.output, files, config.compression)?;
+
+    Ok(report)
+}
+
+struct Baker<'a> {
+    config: &'a BakeConfig,
+    files: BTreeMap<String, Vec<u8>>,
+    manifest: BakeManifest,
+    report: BakeReport,
+}
+
+impl Baker<'_> {
+    fn bake_directory(&mut self, directory: &Path) -> std::io::Result<()> {
+        let mut paths = std::fs::read_dir(directory)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        paths.sort();
+
+        paths
+            .into_iter()
+            .try_for_each(|path| match (path.is_dir(), extension(&path).as_str()) {
+                (true, ATLAS_EXTENSION) => self.bake_atlas(&path),
+                (true, _) => self.bake_directory(&path),
+                (false, _) => self.bake_file(&path),
+            })
+    }
+
+    fn bake_file(&mut self, path: &Path) -> std::io::Result<()> {
+        let name = pack_path(path);
+        let extension = extension(path);
+
+        let baked = if TEXTURE_EXTENSIONS.contains(&extension.as_str()) {
+            image::open(path)
+                .map(|image| TextureData::from_image(&image, self.config.mipmaps).to_baked())
+                .map(|bytes| (bytes, BAKED_TEXTURE_EXTENSION))
+                .map_err(|e| e.to_string())
+        } else if MODEL_EXTENSIONS.contains(&extension.as_str()) {
+            ModelData::load(path)
+                .map(|model| self.bake_model(model).to_baked())
+                .map(|bytes| (bytes, BAKED_MODEL_EXTENSION))
+                .map_err(|e| e.to_string())
+        } else {
+            self.files.insert(name, std::fs::read(path)?);
+            self.report.copied += 1;
+            return Ok(());
+        };
+
+        match baked {
+            Ok((bytes, baked_extension)) => {
+                let baked_name = format!("{}.{}", name, baked_extension);
+
+                match baked_extension {
+                    BAKED_TEXTURE_EXTENSION => self.report.textures += 1,
+                    _ => self.report.models += 1,
+                }
+
+                self.files.insert(baked_name.clone(), bytes);
+                self.manifest.files.insert(name, baked_name);
+            }
+            Err(e) => {
+                self.files.insert(name.clone(), std::fs::read(path)?);
+                self.report.copied += 1;
+                self.report.failed.push((name, e));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn bake_model(&self, mut model: ModelData) -> ModelData {
+        model.meshes.iter_mut().for_each(|mesh| {
+            mesh.calculate_tangents();
+            mesh.calculate_bounds();
+        });
+
+        if self.config.mipmaps {
+            model.textures.iter_mut().for_each(|texture| {
+                let image = image::RgbaImage::from_raw(
+                    texture.width,
+                    texture.height,
+                    texture.mips[0].clone(),
+                );
+
+                if let Some(image) = image {
+                    *texture = TextureData::from_image(&image.into(), true);
+                }
+            });
+        }
+
+        model
+    }
+
+    /// Atlases don't get mip chains, as regions would bleed into each other.
+    fn bake_atlas(&mut self, directory: &Path) -> std::io::Result<()> {
+        let name = pack_path(directory);
+
+        let mut paths = std::fs::read_dir(directory)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        paths.sort();
+
+        let images = paths
+            .into_iter()
+            .filter(|path| TEXTURE_EXTENSIONS.contains(&extension(path).as_str()))
+            .filter_map(|path| match image::open(&path) {
+                Ok(image) => {
+                    let region = path.file_stem()?.to_string_lossy().to_string();
+                    Some((region, image.to_rgba8()))
+                }
+                Err(e) => {
+                    self.report.failed.push((pack_path(&path), e.to_string()));
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let sizes = images
+            .iter()
+            .map(|(region, image)| (region.clone(), glam::UVec2::from(image.dimensions())))
+            .collect::<Vec<_>>();
+
+        let texture_name = format!("{}.{}", name, BAKED_TEXTURE_EXTENSION);
+        let layout = AtlasLayout::pack(&texture_name, &sizes, self.config.atlas_padding);
+
+        let mut atlas = image::RgbaImage::new(layout.size[0], layout.size[1]);
+        layout
+            .regions
+            .iter()
+            .zip(&images)
+            .for_each(|(region, (_, image))| {
+                image::imageops::replace(
+                    &mut atlas,
+                    image,
+                    region.min[0] as i64,
+                    region.min[1] as i64,
+                );
+            });
+
+        self.files.insert(
+            texture_name,
+            TextureData::from_image(&atlas.into(), false).to_baked(),
+        );
+        self.files.insert(
+            format!("{}.json", name),
+            serde_json::to_vec_pretty(&layout)?,
+        );
+        self.report.atlases += 1;
+
+        Ok(())
+    }
+}
+
+#[inline]
+fn pack_path(path: &Path) -> String {
+    normalize_path(&path.to_string_lossy())
+}
+
+#[inline]
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+//====================================================================
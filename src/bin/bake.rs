@@ -0,0 +1,54 @@
+//====================================================================
+
+// Bake a folder of assets into a pak file.
+//
+// cargo run --bin bake -- [source] [output] [--no-mipmaps] [--store]
+
+use hecs_engine::{
+    bake::{bake, BakeConfig},
+    engine::vfs::PakCompression,
+};
+
+//====================================================================
+
+fn main() {
+    let mut config = BakeConfig::default();
+    let mut positional = 0;
+
+    std::env::args().skip(1).for_each(|arg| match arg.as_str() {
+        "--no-mipmaps" => config.mipmaps = false,
+        "--store" => config.compression = PakCompression::None,
+        _ => {
+            match positional {
+                0 => config.source = arg.into(),
+                _ => config.output = arg.into(),
+            }
+            positional += 1;
+        }
+    });
+
+    match bake(&config) {
+        Ok(report) => {
+            println!(
+                "Baked {:?} into {:?}: {} textures, {} models, {} atlases, {} copied",
+                config.source,
+                config.output,
+                report.textures,
+                report.models,
+                report.atlases,
+                report.copied
+            );
+
+            report
+                .failed
+                .iter()
+                .for_each(|(path, e)| eprintln!("Unable to bake '{}': {}", path, e));
+        }
+        Err(e) => {
+            eprintln!("Bake failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+//====================================================================
@@ -0,0 +1,49 @@
+//====================================================================
+
+// Render each example scene and compare it against stored reference images.
+//
+// cargo run --features visual-diff --bin visual_diff -- [scenes] [references] [--update]
+//     [--frames N] [--output dir]
+
+use hecs_engine::visual_diff::{run, VisualDiffConfig};
+
+//====================================================================
+
+fn main() {
+    let mut config = VisualDiffConfig::default();
+    let mut positional = 0;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--update" => config.update = true,
+            "--frames" => match args.next().and_then(|frames| frames.parse().ok()) {
+                Some(frames) => config.frames = frames,
+                None => {
+                    eprintln!("--frames expects a number");
+                    std::process::exit(2);
+                }
+            },
+            "--output" => match args.next() {
+                Some(output) => config.output = output.into(),
+                None => {
+                    eprintln!("--output expects a folder");
+                    std::process::exit(2);
+                }
+            },
+            _ => {
+                match positional {
+                    0 => config.scenes = arg.into(),
+                    _ => config.references = arg.into(),
+                }
+                positional += 1;
+            }
+        }
+    }
+
+    if !run(config) {
+        std::process::exit(1);
+    }
+}
+
+//====================================================================
@@ -0,0 +1,70 @@
+//====================================================================
+
+use std::sync::Arc;
+
+use common::GlobalTransform;
+use engine::{cloth::Cloth, State};
+use pipelines::{
+    material::Material,
+    model_renderer::{Mesh, Model},
+};
+use renderer::{shared::ModelVertex, texture::LoadedTexture, tools::RasterState};
+
+//====================================================================
+
+/// Model drawing both sides of a [`Cloth`] with a dynamic mesh. Insert it on the cloth's
+/// entity and call [`sync_cloth_models`] every update to follow the simulation.
+pub fn cloth_model(
+    state: &State,
+    cloth: &Cloth,
+    texture: Arc<LoadedTexture>,
+) -> (Model, RasterState) {
+    let mesh = Mesh::load_dynamic_mesh(
+        state.renderer().core().device(),
+        &cloth_vertices(cloth, &glam::Affine3A::IDENTITY),
+        &cloth.indices(),
+    );
+
+    let raster = RasterState {
+        cull_mode: None,
+        ..Default::default()
+    };
+
+    let material = Arc::new(Material::new(texture));
+
+    (Model::new(vec![(Arc::new(mesh), material)]), raster)
+}
+
+/// Upload the simulated particles of every [`Cloth`] with a model from [`cloth_model`].
+pub fn sync_cloth_models(state: &State) {
+    let renderer = state.renderer();
+    let queue = renderer.core().queue();
+
+    state
+        .world()
+        .query::<(&Cloth, &Model, &GlobalTransform)>()
+        .iter()
+        .for_each(|(_, (cloth, model, transform))| {
+            if let Some((mesh, _)) = model.meshes.first() {
+                mesh.update_vertices(queue, &cloth_vertices(cloth, &transform.0.inverse()));
+            }
+        });
+}
+
+/// Cloth particles moved from world space by `inverse`, the inverse of the model's transform.
+fn cloth_vertices(cloth: &Cloth, inverse: &glam::Affine3A) -> Vec<ModelVertex> {
+    cloth
+        .positions()
+        .zip(cloth.normals())
+        .zip(cloth.uvs())
+        .map(|((position, normal), uv)| {
+            ModelVertex::new(
+                inverse.transform_point3(position),
+                uv,
+                inverse.transform_vector3(normal).normalize_or(normal),
+            )
+        })
+        .collect()
+}
+
+//====================================================================
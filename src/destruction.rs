@@ -0,0 +1,539 @@
+//====================================================================
+
+use std::sync::Arc;
+
+use common::{GlobalTransform, Transform};
+use engine::{
+    collision::CollisionLayers,
+    physics2d::{Collider2D, RigidBody2D},
+    random::Rng,
+    State,
+};
+use hecs::Entity;
+use pipelines::{
+    material::Material,
+    model_renderer::{Mesh, Model},
+    texture_renderer::Sprite,
+};
+use renderer::{shared::ModelVertex, tools::RasterState};
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChunkPattern {
+    Grid {
+        columns: u32,
+        rows: u32,
+    },
+    /// Irregular chunks around randomly placed cells.
+    Voronoi {
+        cells: u32,
+    },
+}
+
+/// How an entity breaks apart with [`destroy_sprite`] or [`destroy_mesh`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Destructible {
+    pub pattern: ChunkPattern,
+    /// Speed chunks fly away from the point of impact at.
+    pub impulse: f32,
+    /// Spin in radians per second, picked between plus and minus this.
+    pub spin: f32,
+    /// Seconds before debris despawns.
+    pub lifetime: f32,
+    /// Seconds debris spends fading out at the end of its lifetime.
+    pub fade: f32,
+    /// Give sprite chunks a 2D rigid body and collider on these layers.
+    pub physics_layers: Option<CollisionLayers>,
+}
+
+impl Default for Destructible {
+    fn default() -> Self {
+        Self {
+            pattern: ChunkPattern::Grid {
+                columns: 3,
+                rows: 3,
+            },
+            impulse: 3.,
+            spin: 4.,
+            lifetime: 3.,
+            fade: 1.,
+            physics_layers: None,
+        }
+    }
+}
+
+/// Chunk of a destroyed entity, faded out and despawned by [`sys_tick_debris`].
+/// Flies through the air on its own unless it has a [`RigidBody2D`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Debris {
+    pub velocity: glam::Vec3,
+    pub angular_velocity: glam::Vec3,
+    age: f32,
+    lifetime: f32,
+    fade: f32,
+    alpha: f32,
+}
+
+impl Debris {
+    fn new(
+        destructible: &Destructible,
+        velocity: glam::Vec3,
+        spin: glam::Vec3,
+        alpha: f32,
+    ) -> Self {
+        Self {
+            velocity,
+            angular_velocity: spin,
+            age: 0.,
+            lifetime: destructible.lifetime,
+            fade: destructible.fade.clamp(0., destructible.lifetime),
+            alpha,
+        }
+    }
+
+    /// Opacity multiplier, from 1 to 0 over the fade.
+    fn opacity(&self) -> f32 {
+        match self.fade > 0. {
+            true => ((self.lifetime - self.age) / self.fade).clamp(0., 1.),
+            false => 1.,
+        }
+    }
+}
+
+//====================================================================
+
+/// Local space polygons covering a rectangle of `size` centered on the origin.
+fn chunk_polygons(pattern: ChunkPattern, size: glam::Vec2, rng: &mut Rng) -> Vec<Vec<glam::Vec2>> {
+    let half = size / 2.;
+    let rect = |min: glam::Vec2, max: glam::Vec2| {
+        vec![min, glam::vec2(max.x, min.y), max, glam::vec2(min.x, max.y)]
+    };
+
+    match pattern {
+        ChunkPattern::Grid { columns, rows } => {
+            let cells = glam::uvec2(columns.max(1), rows.max(1));
+            let cell = size / cells.as_vec2();
+
+            (0..cells.y)
+                .flat_map(|row| (0..cells.x).map(move |column| glam::uvec2(column, row)))
+                .map(|index| {
+                    let min = -half + cell * index.as_vec2();
+                    rect(min, min + cell)
+                })
+                .collect()
+        }
+
+        ChunkPattern::Voronoi { cells } => {
+            let sites = (0..cells.max(1))
+                .map(|_| {
+                    glam::vec2(
+                        rng.range_f32(-half.x, half.x),
+                        rng.range_f32(-half.y, half.y),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            // Each cell is the rectangle cut by the bisectors with every other site
+            sites
+                .iter()
+                .enumerate()
+                .map(|(index, site)| {
+                    sites
+                        .iter()
+                        .enumerate()
+                        .filter(|(other, _)| *other != index)
+                        .fold(rect(-half, half), |polygon, (_, other)| {
+                            let normal = *other - *site;
+                            let middle = (*site + *other) / 2.;
+                            clip_polygon(&polygon, normal, normal.dot(middle))
+                        })
+                })
+                .filter(|polygon| polygon.len() >= 3)
+                .collect()
+        }
+    }
+}
+
+/// Part of `polygon` where `point.dot(normal) <= distance`.
+fn clip_polygon(polygon: &[glam::Vec2], normal: glam::Vec2, distance: f32) -> Vec<glam::Vec2> {
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+
+    (0..polygon.len()).for_each(|index| {
+        let current = polygon[index];
+        let next = polygon[(index + 1) % polygon.len()];
+        let current_side = current.dot(normal) - distance;
+        let next_side = next.dot(normal) - distance;
+
+        if current_side <= 0. {
+            clipped.push(current);
+        }
+
+        if (current_side <= 0.) != (next_side <= 0.) {
+            let t = current_side / (current_side - next_side);
+            clipped.push(current.lerp(next, t));
+        }
+    });
+
+    clipped
+}
+
+#[inline]
+fn centroid(points: &[glam::Vec2]) -> glam::Vec2 {
+    points.iter().sum::<glam::Vec2>() / points.len().max(1) as f32
+}
+
+/// Velocity away from `origin` and a random spin for a chunk at `position`.
+fn chunk_motion(
+    destructible: &Destructible,
+    rng: &mut Rng,
+    origin: glam::Vec3,
+    position: glam::Vec3,
+    axis: glam::Vec3,
+) -> (glam::Vec3, glam::Vec3) {
+    let direction = (position - origin).normalize_or(glam::Vec3::Y);
+    let speed = destructible.impulse * rng.range_f32(0.5, 1.);
+    let spin = axis * rng.range_f32(-destructible.spin, destructible.spin);
+
+    (direction * speed, spin)
+}
+
+//====================================================================
+
+/// Replace a [`Sprite`] with chunks flying away from `origin`, using the entity's
+/// [`Destructible`] or the default one. Grid chunks stay sprites while voronoi chunks become
+/// flat [`Model`]s. Returns the chunks, or nothing if the entity has no sprite.
+pub fn destroy_sprite(state: &mut State, entity: Entity, origin: glam::Vec3) -> Vec<Entity> {
+    let world = state.world();
+
+    let destructible = world
+        .get::<&Destructible>(entity)
+        .map(|destructible| *destructible)
+        .unwrap_or_default();
+
+    let Some((sprite, transform)) = world
+        .query_one::<(&Sprite, &GlobalTransform)>(entity)
+        .ok()
+        .and_then(|mut query| {
+            query.get().map(|(sprite, transform)| {
+                let sprite = Sprite {
+                    texture: sprite.texture.clone(),
+                    ..*sprite
+                };
+                (sprite, transform.0)
+            })
+        })
+    else {
+        return Vec::new();
+    };
+
+    let (scale, rotation, _) = transform.to_scale_rotation_translation();
+    let forward = rotation * glam::Vec3::Z;
+    let polygons = chunk_polygons(destructible.pattern, sprite.size, state.rng_mut());
+
+    // Sprite uvs run down from the top left corner
+    let uv = |point: glam::Vec2| {
+        let uv = glam::vec2(point.x / sprite.size.x + 0.5, 0.5 - point.y / sprite.size.y);
+        sprite.uv_offset + uv * sprite.uv_scale
+    };
+
+    let chunks = polygons
+        .iter()
+        .map(|polygon| {
+            let center = centroid(polygon);
+            let position = transform.transform_point3(center.extend(0.));
+            let (velocity, spin) =
+                chunk_motion(&destructible, state.rng_mut(), origin, position, forward);
+
+            let chunk_transform = Transform {
+                translation: position,
+                rotation,
+                scale,
+            };
+            let debris = Debris::new(&destructible, velocity, spin, sprite.color[3]);
+
+            (polygon, center, chunk_transform, debris)
+        })
+        .collect::<Vec<_>>();
+
+    let spawned = match destructible.pattern {
+        ChunkPattern::Grid { .. } => {
+            let bundles = chunks
+                .iter()
+                .map(|(polygon, _, transform, debris)| {
+                    let size = polygon[2] - polygon[0];
+                    let uv_min = uv(glam::vec2(polygon[0].x, polygon[2].y));
+
+                    let chunk = Sprite {
+                        texture: sprite.texture.clone(),
+                        size,
+                        uv_offset: uv_min,
+                        uv_scale: sprite.uv_scale * size / sprite.size,
+                        trim: None,
+                        ..sprite
+                    };
+
+                    (
+                        transform.clone(),
+                        GlobalTransform::default(),
+                        chunk,
+                        *debris,
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            state.spawn_batch(bundles)
+        }
+
+        ChunkPattern::Voronoi { .. } => {
+            let material = Arc::new(Material::new(sprite.texture.clone()));
+
+            let bundles = chunks
+                .iter()
+                .map(|(polygon, center, transform, debris)| {
+                    let vertices = polygon
+                        .iter()
+                        .map(|point| {
+                            ModelVertex::new(
+                                (*point - *center).extend(0.),
+                                uv(*point),
+                                glam::Vec3::NEG_Z,
+                            )
+                        })
+                        .collect::<Vec<_>>();
+
+                    let mut model = polygon_model(state, &vertices, material.clone());
+                    model.color = sprite.color;
+
+                    let raster = RasterState {
+                        cull_mode: None,
+                        ..Default::default()
+                    };
+
+                    (
+                        transform.clone(),
+                        GlobalTransform::default(),
+                        model,
+                        raster,
+                        *debris,
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            state.spawn_batch(bundles)
+        }
+    };
+
+    if let Some(layers) = destructible.physics_layers {
+        state.insert_batch(spawned.iter().zip(&chunks).map(
+            |(entity, (polygon, center, _, debris))| {
+                let points = polygon.iter().map(|point| *point - *center).collect();
+
+                let body = RigidBody2D::dynamic().with_velocity(debris.velocity.truncate());
+                let body = RigidBody2D {
+                    angular_velocity: debris.angular_velocity.z,
+                    ..body
+                };
+
+                (
+                    *entity,
+                    (body, Collider2D::convex(points).with_layers(layers)),
+                )
+            },
+        ));
+    }
+
+    state.world_mut().despawn(entity).ok();
+    spawned
+}
+
+/// Replace an entity's [`Model`] with chunks of a simple mesh flying away from `origin`.
+/// The mesh's vertices and indices must be passed in as models only keep them on the gpu.
+/// Triangles are grouped into chunks by where they sit on the mesh's local XY plane.
+pub fn destroy_mesh(
+    state: &mut State,
+    entity: Entity,
+    origin: glam::Vec3,
+    vertices: &[ModelVertex],
+    indices: &[u32],
+    material: Arc<Material>,
+) -> Vec<Entity> {
+    let world = state.world();
+
+    let destructible = world
+        .get::<&Destructible>(entity)
+        .map(|destructible| *destructible)
+        .unwrap_or_default();
+
+    let Ok(transform) = world
+        .get::<&GlobalTransform>(entity)
+        .map(|transform| transform.0)
+    else {
+        return Vec::new();
+    };
+
+    let color = world
+        .get::<&Model>(entity)
+        .map(|model| model.color)
+        .unwrap_or([1., 1., 1., 1.]);
+
+    let (min, max) = vertices.iter().fold(
+        (glam::Vec2::INFINITY, glam::Vec2::NEG_INFINITY),
+        |(min, max), vertex| {
+            (
+                min.min(vertex.pos().truncate()),
+                max.max(vertex.pos().truncate()),
+            )
+        },
+    );
+    if min.x > max.x {
+        return Vec::new();
+    }
+
+    let center = (min + max) / 2.;
+    let polygons = chunk_polygons(destructible.pattern, max - min, state.rng_mut())
+        .into_iter()
+        .map(|polygon| polygon.into_iter().map(|point| point + center).collect())
+        .collect::<Vec<Vec<_>>>();
+
+    // Give each triangle to the chunk closest to its center
+    let mut triangles = vec![Vec::new(); polygons.len()];
+    let polygon_centers = polygons
+        .iter()
+        .map(|polygon| centroid(polygon))
+        .collect::<Vec<_>>();
+
+    indices.chunks_exact(3).for_each(|triangle| {
+        let middle = triangle
+            .iter()
+            .map(|index| vertices[*index as usize].pos())
+            .sum::<glam::Vec3>()
+            / 3.;
+
+        let closest = polygon_centers
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                a.distance_squared(middle.truncate())
+                    .total_cmp(&b.distance_squared(middle.truncate()))
+            })
+            .map(|(index, _)| index);
+
+        if let Some(closest) = closest {
+            triangles[closest].extend_from_slice(triangle);
+        }
+    });
+
+    let (scale, rotation, _) = transform.to_scale_rotation_translation();
+
+    let bundles = triangles
+        .into_iter()
+        .filter(|triangles| !triangles.is_empty())
+        .map(|triangles| {
+            let middle = triangles
+                .iter()
+                .map(|index| vertices[*index as usize].pos())
+                .sum::<glam::Vec3>()
+                / triangles.len() as f32;
+
+            let chunk_vertices = triangles
+                .iter()
+                .map(|index| {
+                    let vertex = vertices[*index as usize];
+                    ModelVertex::new(vertex.pos() - middle, vertex.uv(), vertex.normal())
+                        .with_lightmap_uv(vertex.lightmap_uv())
+                })
+                .collect::<Vec<_>>();
+            let chunk_indices = (0..chunk_vertices.len() as u32).collect::<Vec<_>>();
+
+            let mesh = Mesh::load_mesh(
+                state.renderer().core().device(),
+                &chunk_vertices,
+                &chunk_indices,
+            );
+            let mut model = Model::new(vec![(Arc::new(mesh), material.clone())]);
+            model.color = color;
+
+            let position = transform.transform_point3(middle);
+            let axis = rotation * glam::Vec3::Z;
+            let (velocity, spin) =
+                chunk_motion(&destructible, state.rng_mut(), origin, position, axis);
+
+            let chunk_transform = Transform {
+                translation: position,
+                rotation,
+                scale,
+            };
+
+            (
+                chunk_transform,
+                GlobalTransform::default(),
+                model,
+                Debris::new(&destructible, velocity, spin, color[3]),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let spawned = state.spawn_batch(bundles);
+    state.world_mut().despawn(entity).ok();
+    spawned
+}
+
+/// Flat model facing -Z from a convex polygon's vertices.
+fn polygon_model(state: &State, vertices: &[ModelVertex], material: Arc<Material>) -> Model {
+    let indices = (1..vertices.len() as u32 - 1)
+        .flat_map(|index| [0, index, index + 1])
+        .collect::<Vec<_>>();
+
+    let mesh = Mesh::load_mesh(state.renderer().core().device(), vertices, &indices);
+    Model::new(vec![(Arc::new(mesh), material)])
+}
+
+//====================================================================
+
+/// Move, fade and despawn every [`Debris`] chunk. Call every update.
+pub fn sys_tick_debris(state: &mut State, delta_seconds: f32) {
+    let gravity = state.forces().gravity_or(glam::vec3(0., -9.81, 0.));
+    let world = state.world_mut();
+
+    let mut finished = Vec::new();
+
+    world
+        .query_mut::<(
+            &mut Debris,
+            &mut Transform,
+            Option<&RigidBody2D>,
+            Option<&mut Sprite>,
+            Option<&mut Model>,
+        )>()
+        .into_iter()
+        .for_each(|(entity, (debris, transform, body, sprite, model))| {
+            debris.age += delta_seconds;
+            if debris.age >= debris.lifetime {
+                finished.push(entity);
+                return;
+            }
+
+            if body.is_none() {
+                debris.velocity += gravity * delta_seconds;
+                transform.translation += debris.velocity * delta_seconds;
+                transform.rotation =
+                    glam::Quat::from_scaled_axis(debris.angular_velocity * delta_seconds)
+                        * transform.rotation;
+            }
+
+            let alpha = debris.alpha * debris.opacity();
+            if let Some(sprite) = sprite {
+                sprite.color[3] = alpha;
+            }
+            if let Some(model) = model {
+                model.color[3] = alpha;
+            }
+        });
+
+    finished.into_iter().for_each(|entity| {
+        world.despawn(entity).ok();
+    });
+}
+
+//====================================================================
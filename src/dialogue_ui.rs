@@ -0,0 +1,196 @@
+//====================================================================
+
+use common::{
+    focus::{FocusAxis, FocusEvent, Focusable},
+    GlobalTransform, Transform,
+};
+use engine::{spatial::Billboard, State};
+use hecs::Entity;
+use pipelines::{
+    floating_text_renderer::{FloatingText, FloatingTextMotion},
+    ui3d_renderer::Ui3d,
+};
+
+//====================================================================
+
+/// Shows the running dialogue above an entity, with the line as floating text and the
+/// choices as a focusable [`Ui3d`] menu. Nodes without choices show a single continue option.
+/// Call [`sync_dialogue_displays`] every update.
+pub struct DialogueDisplay {
+    pub font_size: f32,
+    /// Screen space offset of the line in pixels.
+    pub text_offset: glam::Vec2,
+    /// Offset of the choice menu from the entity.
+    pub menu_offset: glam::Vec3,
+    pub continue_text: String,
+
+    text: Option<Entity>,
+    menu: Option<Entity>,
+}
+
+impl Default for DialogueDisplay {
+    fn default() -> Self {
+        Self {
+            font_size: 24.,
+            text_offset: glam::vec2(0., 80.),
+            menu_offset: glam::Vec3::ZERO,
+            continue_text: "Continue".into(),
+            text: None,
+            menu: None,
+        }
+    }
+}
+
+impl DialogueDisplay {
+    #[inline]
+    pub fn menu(&self) -> Option<Entity> {
+        self.menu
+    }
+}
+
+//====================================================================
+
+/// Update every [`DialogueDisplay`] to match the running dialogue and pick choices
+/// activated through the focus manager.
+pub fn sync_dialogue_displays(state: &mut State) {
+    let displays = state
+        .world_mut()
+        .query_mut::<&DialogueDisplay>()
+        .into_iter()
+        .map(|(entity, display)| (entity, display.menu))
+        .collect::<Vec<_>>();
+
+    // Choices activated last frame
+    let activated = state.focus().events().iter().find_map(|event| match event {
+        FocusEvent::Activated { entity, item } => displays
+            .iter()
+            .any(|(_, menu)| *menu == Some(*entity))
+            .then_some(*item),
+        _ => None,
+    });
+
+    if let Some(item) = activated {
+        match state.dialogue().choices().is_empty() {
+            true => state.dialogue_mut().advance(),
+            false => state.dialogue_mut().choose(item),
+        }
+    }
+
+    let line = state.dialogue().current().map(|node| {
+        let text = match &node.speaker {
+            Some(speaker) => format!("{}: {}", speaker, node.text),
+            None => node.text.clone(),
+        };
+
+        let options = state
+            .dialogue()
+            .choices()
+            .into_iter()
+            .map(|choice| choice.text.clone())
+            .collect::<Vec<_>>();
+
+        (text, options)
+    });
+
+    displays.into_iter().for_each(|(entity, _)| match &line {
+        Some((text, options)) => show_line(state, entity, text, options),
+        None => hide_line(state, entity),
+    });
+}
+
+fn show_line(state: &mut State, entity: Entity, line: &str, options: &[String]) {
+    let Ok(display) = state.world().get::<&DialogueDisplay>(entity) else {
+        return;
+    };
+
+    let (font_size, text_offset, menu_offset) =
+        (display.font_size, display.text_offset, display.menu_offset);
+    let options = match options.is_empty() {
+        true => vec![display.continue_text.clone()],
+        false => options.to_vec(),
+    };
+    let (text, menu) = (display.text, display.menu);
+    drop(display);
+
+    let text = match text.filter(|text| state.world().contains(*text)) {
+        Some(text) => text,
+        None => {
+            let text = state.world_mut().spawn((
+                Transform::default(),
+                GlobalTransform::default(),
+                FloatingText {
+                    font_size,
+                    offset: text_offset,
+                    motion: FloatingTextMotion::Static,
+                    lifetime: f32::INFINITY,
+                    fade_start: 1.,
+                    ..Default::default()
+                },
+            ));
+            state.attach(text, entity).ok();
+            text
+        }
+    };
+
+    let (menu, spawned) = match menu.filter(|menu| state.world().contains(*menu)) {
+        Some(menu) => (menu, false),
+        None => {
+            let menu = state.world_mut().spawn((
+                Transform::from_translation(menu_offset),
+                GlobalTransform::default(),
+                Ui3d {
+                    font_size,
+                    ..Default::default()
+                },
+                Focusable::new().with_items(options.len(), FocusAxis::Vertical),
+                Billboard::default(),
+            ));
+            state.attach(menu, entity).ok();
+            (menu, true)
+        }
+    };
+
+    let world = state.world_mut();
+
+    if let Ok(mut floating) = world.get::<&mut FloatingText>(text) {
+        if floating.text != line {
+            floating.text = line.to_string();
+        }
+    }
+
+    if let Ok((ui, focusable)) = world.query_one_mut::<(&mut Ui3d, &mut Focusable)>(menu) {
+        if ui.options != options {
+            focusable.items = options.len();
+            focusable.item = 0;
+            ui.options = options;
+            ui.selected = 0;
+        }
+    }
+
+    if let Ok(mut display) = world.get::<&mut DialogueDisplay>(entity) {
+        display.text = Some(text);
+        display.menu = Some(menu);
+    }
+
+    if spawned {
+        state.focus_mut().0.set_focus(Some(menu));
+    }
+}
+
+fn hide_line(state: &mut State, entity: Entity) {
+    let Ok(mut display) = state.world().get::<&mut DialogueDisplay>(entity) else {
+        return;
+    };
+
+    let children = [display.text.take(), display.menu.take()];
+    drop(display);
+
+    children.into_iter().flatten().for_each(|child| {
+        if state.focus().focused() == Some(child) {
+            state.focus_mut().0.set_focus(None);
+        }
+        state.despawn_recursive(child);
+    });
+}
+
+//====================================================================
@@ -0,0 +1,232 @@
+//====================================================================
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use common::{GlobalTransform, Transform};
+use engine::{
+    assets::Handle,
+    inventory::{Inventory, ItemDatabase},
+    State,
+};
+use hecs::Entity;
+use pipelines::{
+    floating_text_renderer::{FloatingText, FloatingTextMotion},
+    texture_renderer::Sprite,
+};
+use renderer::texture::LoadedTexture;
+
+//====================================================================
+
+/// Draws an entity's [`Inventory`] as a grid of item icons with stack counts.
+/// Add alongside an `Inventory` and `GlobalTransform`, then call [`sync_inventory_grids`]
+/// every update. Slots are laid out left to right, top to bottom from the entity's position.
+pub struct InventoryGrid {
+    pub items: Handle<ItemDatabase>,
+    pub columns: usize,
+    pub cell_size: glam::Vec2,
+    pub spacing: f32,
+    pub font_size: f32,
+
+    cells: Vec<Entity>,
+    icons: BTreeMap<String, Handle<LoadedTexture>>,
+}
+
+impl InventoryGrid {
+    #[inline]
+    pub fn new(items: Handle<ItemDatabase>, columns: usize, cell_size: glam::Vec2) -> Self {
+        Self {
+            items,
+            columns: columns.max(1),
+            cell_size,
+            spacing: 4.,
+            font_size: 16.,
+            cells: Vec::new(),
+            icons: BTreeMap::new(),
+        }
+    }
+
+    /// Entity drawing each slot.
+    #[inline]
+    pub fn cells(&self) -> &[Entity] {
+        &self.cells
+    }
+
+    fn cell_position(&self, index: usize) -> glam::Vec3 {
+        let column = (index % self.columns) as f32;
+        let row = (index / self.columns) as f32;
+        let step = self.cell_size + self.spacing;
+
+        glam::vec3(column * step.x, -row * step.y, 0.)
+    }
+}
+
+//====================================================================
+
+/// Spawn, update and despawn the cells of every [`InventoryGrid`].
+pub fn sync_inventory_grids(state: &mut State) {
+    let grids = state
+        .world_mut()
+        .query_mut::<(&InventoryGrid, &Inventory)>()
+        .into_iter()
+        .map(|(entity, (grid, inventory))| (entity, grid.items.clone(), inventory.clone()))
+        .collect::<Vec<_>>();
+
+    grids.into_iter().for_each(|(entity, items, inventory)| {
+        let Some(items) = items.get() else {
+            return;
+        };
+
+        // Start loading any icons that haven't been requested yet
+        let icons = inventory
+            .slots()
+            .iter()
+            .flatten()
+            .filter_map(|stack| items.get(&stack.item)?.icon.clone())
+            .collect::<Vec<_>>();
+
+        let requested = match state.world().get::<&InventoryGrid>(entity) {
+            Ok(grid) => grid.icons.keys().cloned().collect::<Vec<_>>(),
+            Err(_) => return,
+        };
+
+        let handles = icons
+            .into_iter()
+            .filter(|path| !requested.contains(path))
+            .map(|path| {
+                let handle = state.assets_mut().load_texture(&path);
+                (path, handle)
+            })
+            .collect::<Vec<_>>();
+
+        let (cells, positions) = {
+            let Ok(mut grid) = state.world_mut().get::<&mut InventoryGrid>(entity) else {
+                return;
+            };
+            grid.icons.extend(handles);
+
+            let stale = match grid.cells.len() > inventory.len() {
+                true => grid.cells.split_off(inventory.len()),
+                false => Vec::new(),
+            };
+            let positions = (0..inventory.len())
+                .map(|index| grid.cell_position(index))
+                .collect::<Vec<_>>();
+
+            (stale, positions)
+        };
+
+        cells.into_iter().for_each(|cell| {
+            state.despawn_recursive(cell);
+        });
+
+        positions
+            .into_iter()
+            .enumerate()
+            .for_each(|(index, position)| {
+                sync_cell(state, entity, &items, &inventory, index, position);
+            });
+    });
+}
+
+fn sync_cell(
+    state: &mut State,
+    grid_entity: Entity,
+    items: &ItemDatabase,
+    inventory: &Inventory,
+    index: usize,
+    position: glam::Vec3,
+) {
+    let (cell, cell_size, font_size, icon) = {
+        let Ok(grid) = state.world().get::<&InventoryGrid>(grid_entity) else {
+            return;
+        };
+
+        let icon = inventory
+            .slot(index)
+            .and_then(|stack| items.get(&stack.item)?.icon.as_ref())
+            .and_then(|path| grid.icons.get(path)?.get());
+
+        (
+            grid.cells.get(index).copied(),
+            grid.cell_size,
+            grid.font_size,
+            icon,
+        )
+    };
+
+    let cell = match cell {
+        Some(cell) if state.world().contains(cell) => cell,
+        _ => {
+            let cell = state
+                .world_mut()
+                .spawn((Transform::default(), GlobalTransform::default()));
+            state.attach(cell, grid_entity).ok();
+
+            let mut grid = state
+                .world_mut()
+                .get::<&mut InventoryGrid>(grid_entity)
+                .unwrap();
+            match grid.cells.get_mut(index) {
+                Some(old) => *old = cell,
+                None => grid.cells.push(cell),
+            }
+
+            cell
+        }
+    };
+
+    let world = state.world_mut();
+
+    if let Ok(mut transform) = world.get::<&mut Transform>(cell) {
+        transform.translation = position;
+    }
+
+    match icon {
+        Some(texture) => {
+            let current = world
+                .get::<&Sprite>(cell)
+                .ok()
+                .map(|sprite| sprite.texture.clone());
+            if !current.is_some_and(|current| Arc::ptr_eq(&current, &texture)) {
+                world.insert_one(cell, Sprite::new(texture, cell_size)).ok();
+            }
+        }
+        None => {
+            world.remove_one::<Sprite>(cell).ok();
+        }
+    }
+
+    let count = inventory
+        .slot(index)
+        .filter(|stack| stack.count > 1)
+        .map(|stack| stack.count.to_string());
+
+    let has_text = world.satisfies::<&FloatingText>(cell).unwrap_or(false);
+
+    match count {
+        Some(count) if has_text => {
+            let mut text = world.get::<&mut FloatingText>(cell).unwrap();
+            if text.text != count {
+                text.text = count;
+            }
+        }
+        Some(count) => {
+            let text = FloatingText {
+                text: count,
+                font_size,
+                offset: glam::vec2(cell_size.x / 2., -cell_size.y / 2.),
+                motion: FloatingTextMotion::Static,
+                lifetime: f32::INFINITY,
+                fade_start: 1.,
+                ..Default::default()
+            };
+            world.insert_one(cell, text).ok();
+        }
+        None if has_text => {
+            world.remove_one::<FloatingText>(cell).ok();
+        }
+        None => {}
+    }
+}
+
+//====================================================================
@@ -5,14 +5,32 @@ pub use engine;
 pub use pipelines;
 pub use renderer;
 
+pub mod bake;
+pub mod cloth_model;
+pub mod destruction;
+pub mod dialogue_ui;
+pub mod inventory_ui;
+pub mod quest_ui;
+pub mod rope_polyline;
+pub mod scene;
+pub mod skeletal_animation;
+pub mod sprite_animation;
+#[cfg(feature = "visual-diff")]
+pub mod visual_diff;
+
 pub mod prelude {
     pub use common::{GlobalTransform, Size, Transform};
     pub use engine::{
         tools::{Input, Time},
-        App, Runner, State,
+        window::{WindowConfig, WindowMode},
+        App, DefaultCamera, EngineConfig, Runner, State,
     };
     pub use pipelines::texture_renderer::Sprite;
-    pub use renderer::{camera::PerspectiveCamera, texture::LoadedTexture};
+    pub use renderer::{
+        camera::PerspectiveCamera,
+        lighting::{DirectionalLight, PointLight},
+        texture::LoadedTexture,
+    };
 }
 
 //====================================================================
@@ -0,0 +1,103 @@
+//====================================================================
+
+use engine::State;
+use pipelines::floating_text_renderer::{FloatingText, FloatingTextMotion};
+
+//====================================================================
+
+/// Lists active quests and their objectives as floating text at the entity's position.
+/// Add alongside a `GlobalTransform`, then call [`sync_quest_trackers`] every update.
+#[derive(Debug, Clone)]
+pub struct QuestTracker {
+    pub font_size: f32,
+    /// Screen space offset in pixels.
+    pub offset: glam::Vec2,
+    /// Show objectives that are already done.
+    pub show_completed: bool,
+}
+
+impl Default for QuestTracker {
+    fn default() -> Self {
+        Self {
+            font_size: 18.,
+            offset: glam::Vec2::ZERO,
+            show_completed: false,
+        }
+    }
+}
+
+//====================================================================
+
+fn tracker_text(state: &State, tracker: &QuestTracker) -> String {
+    state
+        .quests()
+        .active()
+        .map(|(_, quest, progress)| {
+            let objectives = quest
+                .objectives
+                .iter()
+                .zip(&progress.objectives)
+                .filter(|(objective, current)| {
+                    tracker.show_completed || **current < objective.count
+                })
+                .map(|(objective, current)| match objective.count > 1 {
+                    true => format!(
+                        "  - {} {}/{}",
+                        objective.description, current, objective.count
+                    ),
+                    false => format!("  - {}", objective.description),
+                })
+                .collect::<Vec<_>>();
+
+            std::iter::once(quest.name.clone())
+                .chain(objectives)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Update the text of every [`QuestTracker`].
+pub fn sync_quest_trackers(state: &mut State) {
+    let trackers = state
+        .world_mut()
+        .query_mut::<&QuestTracker>()
+        .into_iter()
+        .map(|(entity, tracker)| (entity, tracker.clone()))
+        .collect::<Vec<_>>();
+
+    trackers.into_iter().for_each(|(entity, tracker)| {
+        let text = tracker_text(state, &tracker);
+        let world = state.world_mut();
+
+        match world.satisfies::<&FloatingText>(entity).unwrap_or(false) {
+            true => {
+                let mut floating = world.get::<&mut FloatingText>(entity).unwrap();
+                if floating.text != text {
+                    floating.text = text;
+                }
+                floating.font_size = tracker.font_size;
+                floating.offset = tracker.offset;
+            }
+            false => {
+                world
+                    .insert_one(
+                        entity,
+                        FloatingText {
+                            text,
+                            font_size: tracker.font_size,
+                            offset: tracker.offset,
+                            motion: FloatingTextMotion::Static,
+                            lifetime: f32::INFINITY,
+                            fade_start: 1.,
+                            ..Default::default()
+                        },
+                    )
+                    .ok();
+            }
+        }
+    });
+}
+
+//====================================================================
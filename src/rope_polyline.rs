@@ -0,0 +1,21 @@
+//====================================================================
+
+use engine::{rope::Rope, State};
+use pipelines::polyline_renderer::Polyline;
+
+//====================================================================
+
+/// Copy the simulated points of every [`Rope`] into the [`Polyline`] on the same entity.
+/// Call every update.
+pub fn sync_rope_polylines(state: &mut State) {
+    state
+        .world_mut()
+        .query_mut::<(&Rope, &mut Polyline)>()
+        .into_iter()
+        .for_each(|(_, (rope, polyline))| {
+            polyline.points.clear();
+            polyline.points.extend_from_slice(rope.points());
+        });
+}
+
+//====================================================================
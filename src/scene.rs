@@ -0,0 +1,164 @@
+//====================================================================
+
+use std::sync::Arc;
+
+use engine::{
+    assets::Handle,
+    scene::{SceneContext, SceneRegistry},
+};
+use pipelines::{
+    model_loader::ModelData,
+    model_renderer::Model,
+    text3d_renderer::Text3d,
+    texture_renderer::{SoftSprite, Sprite},
+    ui3d_renderer::Ui3d,
+};
+use serde::{Deserialize, Serialize};
+
+//====================================================================
+
+/// Register the scene components provided by the default pipelines.
+pub fn register_pipeline_components(registry: &mut SceneRegistry) {
+    registry
+        .register::<SoftSprite>("SoftSprite")
+        .register::<Ui3d>("Ui3d")
+        .register::<Text3d>("Text3d")
+        .register_with(
+            "Sprite",
+            |entity, assets| {
+                let sprite = entity.get::<&Sprite>()?;
+                serde_json::to_value(SpriteData {
+                    texture: assets.find_path(&sprite.texture).map(str::to_string),
+                    size: sprite.size,
+                    color: sprite.color,
+                    uv_offset: sprite.uv_offset,
+                    uv_scale: sprite.uv_scale,
+                    layer: sprite.layer,
+                })
+                .ok()
+            },
+            |value, builder, context| {
+                let data =
+                    serde_json::from_value::<SpriteData>(value).map_err(|e| e.to_string())?;
+
+                // Textures that weren't loaded from a file can't be restored
+                let texture = data
+                    .texture
+                    .and_then(|path| {
+                        context
+                            .assets
+                            .load_texture_now(context.renderer, &path)
+                            .get()
+                    })
+                    .unwrap_or_else(|| context.renderer.default_texture.clone());
+
+                builder.add(Sprite {
+                    texture,
+                    size: data.size,
+                    color: data.color,
+                    uv_offset: data.uv_offset,
+                    uv_scale: data.uv_scale,
+                    layer: data.layer,
+                    trim: None,
+                });
+                Ok(())
+            },
+        )
+        .register_with(
+            "Model",
+            |entity, assets| {
+                let model = entity.get::<&Model>()?;
+                let mesh = &model.meshes.first()?.0;
+
+                // Models are matched by their meshes, as components hold a copy of the asset
+                let path = assets.find_path_by::<Model>(|loaded| {
+                    loaded
+                        .meshes
+                        .first()
+                        .is_some_and(|(loaded, _)| Arc::ptr_eq(loaded, mesh))
+                })?;
+
+                serde_json::to_value(ModelComponentData {
+                    path: path.to_string(),
+                    color: model.color,
+                    scale: model.scale,
+                    uv_offset: model.uv_offset,
+                    uv_scale: model.uv_scale,
+                    lightmap: model
+                        .lightmap
+                        .as_ref()
+                        .and_then(|lightmap| assets.find_path(lightmap))
+                        .map(str::to_string),
+                })
+                .ok()
+            },
+            |value, builder, context| {
+                let data = serde_json::from_value::<ModelComponentData>(value)
+                    .map_err(|e| e.to_string())?;
+
+                let model = load_model_now(context, &data.path)
+                    .get()
+                    .ok_or_else(|| format!("Unable to load model '{}'", data.path))?;
+
+                // Lightmaps baked at runtime and never saved can't be restored
+                let lightmap = data.lightmap.and_then(|path| {
+                    context
+                        .assets
+                        .load_texture_now(context.renderer, &path)
+                        .get()
+                });
+
+                builder.add(Model {
+                    color: data.color,
+                    scale: data.scale,
+                    uv_offset: data.uv_offset,
+                    uv_scale: data.uv_scale,
+                    lightmap,
+                    ..(*model).clone()
+                });
+                Ok(())
+            },
+        );
+}
+
+/// Load a model through the asset server so it can be saved in scenes.
+pub fn load_model_now(context: &mut SceneContext, path: &str) -> Handle<Model> {
+    let renderer = context.renderer;
+
+    let vfs = context.assets.vfs().clone();
+
+    context.assets.load_now_with(path, |_| {
+        let core = renderer.core();
+        let read = |path: &std::path::Path| vfs.read(&path.to_string_lossy());
+
+        Ok(ModelData::load_with(path, &read)
+            .map_err(|e| e.to_string())?
+            .build(core.device(), core.queue(), renderer.shared_resources()))
+    })
+}
+
+//--------------------------------------------------
+
+#[derive(Serialize, Deserialize)]
+struct SpriteData {
+    texture: Option<String>,
+    size: glam::Vec2,
+    color: [f32; 4],
+    uv_offset: glam::Vec2,
+    uv_scale: glam::Vec2,
+    #[serde(default)]
+    layer: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ModelComponentData {
+    path: String,
+    color: [f32; 4],
+    scale: glam::Vec3,
+    uv_offset: glam::Vec2,
+    uv_scale: glam::Vec2,
+    #[serde(default)]
+    lightmap: Option<String>,
+}
+
+//====================================================================
@@ -0,0 +1,22 @@
+//====================================================================
+
+use engine::parallel::{Access, SystemContext};
+use pipelines::{
+    model_renderer::Model,
+    skeleton::{sys_tick_skeletal_animations, SkeletalAnimation},
+};
+
+//====================================================================
+
+#[inline]
+pub fn skeletal_animation_access() -> Access {
+    Access::new().write::<SkeletalAnimation>().read::<Model>()
+}
+
+/// Advance every [`SkeletalAnimation`] by the frame's delta. Add with
+/// `state.add_parallel_system(Stage::Update, "skeletal_animation", skeletal_animation_access(), tick_skeletal_animations)`.
+pub fn tick_skeletal_animations(ctx: &mut SystemContext) {
+    sys_tick_skeletal_animations(ctx.world(), ctx.delta_seconds());
+}
+
+//====================================================================
@@ -0,0 +1,46 @@
+//====================================================================
+
+use engine::{
+    parallel::{Access, SystemContext},
+    schedule::Stage,
+    State,
+};
+use pipelines::{
+    sprite_animation::{sys_tick_sprite_animations, SpriteAnimation},
+    texture_renderer::{AtlasSprite, Sprite},
+};
+
+//====================================================================
+
+/// Name of the system added by [`add_sprite_animation`].
+pub const SPRITE_ANIMATION_SYSTEM: &str = "pipelines::sprite_animation";
+
+#[inline]
+pub fn sprite_animation_access() -> Access {
+    Access::new()
+        .write::<SpriteAnimation>()
+        .write::<Sprite>()
+        .write::<AtlasSprite>()
+}
+
+/// Advance every [`SpriteAnimation`] by the frame's delta.
+pub fn tick_sprite_animations(ctx: &mut SystemContext) {
+    sys_tick_sprite_animations(ctx.world(), ctx.delta_seconds());
+}
+
+/// Tick every [`SpriteAnimation`], including those playing sprite sheet clips, in
+/// [`Stage::Update`]. Adding it more than once does nothing.
+pub fn add_sprite_animation(state: &mut State) {
+    if state.schedule().contains(SPRITE_ANIMATION_SYSTEM) {
+        return;
+    }
+
+    state.add_parallel_system(
+        Stage::Update,
+        SPRITE_ANIMATION_SYSTEM,
+        sprite_animation_access(),
+        tick_sprite_animations,
+    );
+}
+
+//====================================================================
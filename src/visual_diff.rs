@@ -0,0 +1,405 @@
+//====================================================================
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+};
+
+use common::Size;
+use engine::{App, EngineConfig, Runner, State};
+use hecs::Entity;
+use image::RgbaImage;
+use pipelines::{
+    floating_text_renderer::FloatingTextRenderer, model_renderer::ModelRenderer,
+    skybox_renderer::SkyboxRenderer, text3d_renderer::Text3dRenderer,
+    texture_renderer::TextureRenderer, ui3d_renderer::Ui3dRenderer,
+};
+use renderer::{camera, texture::LoadedTexture};
+
+//====================================================================
+
+#[derive(Debug, Clone)]
+pub struct VisualDiffConfig {
+    /// Folder of scene files to render, see [`State::save_scene`].
+    pub scenes: PathBuf,
+    /// Folder of reference images, one png per scene with the same name.
+    pub references: PathBuf,
+    /// Folder side by side diff images are written to.
+    pub output: PathBuf,
+    /// Frames rendered before each scene is captured.
+    pub frames: u32,
+    pub size: Size<u32>,
+    /// Largest channel difference a pixel can have and still count as matching.
+    pub threshold: u8,
+    /// Fraction of pixels that can differ before a scene fails.
+    pub tolerance: f32,
+    /// Overwrite the references with the rendered images instead of comparing.
+    pub update: bool,
+}
+
+impl Default for VisualDiffConfig {
+    fn default() -> Self {
+        Self {
+            scenes: PathBuf::from("examples/scenes"),
+            references: PathBuf::from("examples/references"),
+            output: PathBuf::from("target/visual_diff"),
+            frames: 10,
+            size: Size::new(640, 360),
+            threshold: 2,
+            tolerance: 0.001,
+            update: false,
+        }
+    }
+}
+
+//--------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiffReport {
+    pub pixels: usize,
+    /// Pixels with a channel more than the threshold away from the reference.
+    pub differing: usize,
+    /// Largest channel difference of any pixel.
+    pub max_delta: u8,
+    /// Average of every pixel's largest channel difference.
+    pub mean_delta: f32,
+}
+
+impl DiffReport {
+    #[inline]
+    pub fn differing_fraction(&self) -> f32 {
+        self.differing as f32 / self.pixels.max(1) as f32
+    }
+}
+
+#[derive(Debug)]
+pub enum VisualDiffError {
+    SizeMismatch {
+        reference: (u32, u32),
+        actual: (u32, u32),
+    },
+    MissingReference(PathBuf),
+    NoCamera,
+    Readback,
+    Scene(String),
+    Image(image::ImageError),
+    Io(std::io::Error),
+}
+
+impl std::error::Error for VisualDiffError {}
+
+impl Display for VisualDiffError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VisualDiffError::SizeMismatch { reference, actual } => write!(
+                f,
+                "Size {}x{} doesn't match reference {}x{}",
+                actual.0, actual.1, reference.0, reference.1
+            ),
+            VisualDiffError::MissingReference(path) => {
+                write!(f, "No reference image at {:?}", path)
+            }
+            VisualDiffError::NoCamera => write!(f, "Scene has no camera"),
+            VisualDiffError::Readback => write!(f, "Unable to read back the rendered image"),
+            VisualDiffError::Scene(e) => write!(f, "Unable to load scene: {}", e),
+            VisualDiffError::Image(e) => write!(f, "Image error: {}", e),
+            VisualDiffError::Io(e) => write!(f, "Io error: {}", e),
+        }
+    }
+}
+
+impl From<image::ImageError> for VisualDiffError {
+    fn from(value: image::ImageError) -> Self {
+        Self::Image(value)
+    }
+}
+
+impl From<std::io::Error> for VisualDiffError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+//====================================================================
+
+/// Compare two images of the same size, returning the report and an image highlighting
+/// differing pixels in red over a faded copy of `actual`.
+pub fn compare(
+    reference: &RgbaImage,
+    actual: &RgbaImage,
+    threshold: u8,
+) -> Result<(DiffReport, RgbaImage), VisualDiffError> {
+    if reference.dimensions() != actual.dimensions() {
+        return Err(VisualDiffError::SizeMismatch {
+            reference: reference.dimensions(),
+            actual: actual.dimensions(),
+        });
+    }
+
+    let (width, height) = actual.dimensions();
+    let mut diff = RgbaImage::new(width, height);
+    let mut total_delta = 0u64;
+
+    let mut report = DiffReport {
+        pixels: (width * height) as usize,
+        differing: 0,
+        max_delta: 0,
+        mean_delta: 0.,
+    };
+
+    reference
+        .pixels()
+        .zip(actual.pixels())
+        .zip(diff.pixels_mut())
+        .for_each(|((a, b), out)| {
+            let delta = (0..4).map(|i| a.0[i].abs_diff(b.0[i])).max().unwrap_or(0);
+
+            total_delta += delta as u64;
+            report.max_delta = report.max_delta.max(delta);
+
+            let faded = (b.0[0] as u16 + b.0[1] as u16 + b.0[2] as u16) / 12;
+            *out = match delta > threshold {
+                true => {
+                    report.differing += 1;
+                    image::Rgba([128 + delta / 2, faded as u8, faded as u8, 255])
+                }
+                false => image::Rgba([faded as u8, faded as u8, faded as u8, 255]),
+            };
+        });
+
+    report.mean_delta = total_delta as f32 / report.pixels.max(1) as f32;
+
+    Ok((report, diff))
+}
+
+/// Reference, actual and diff images next to each other.
+pub fn side_by_side(reference: &RgbaImage, actual: &RgbaImage, diff: &RgbaImage) -> RgbaImage {
+    let (width, height) = actual.dimensions();
+    let mut out = RgbaImage::new(width * 3, height);
+
+    [reference, actual, diff]
+        .into_iter()
+        .enumerate()
+        .for_each(|(index, image)| {
+            image::imageops::replace(&mut out, image, (width * index as u32) as i64, 0)
+        });
+
+    out
+}
+
+//====================================================================
+
+static CONFIG: OnceLock<VisualDiffConfig> = OnceLock::new();
+static PASSED: AtomicBool = AtomicBool::new(false);
+
+/// Render every scene in `config.scenes` and compare it against its reference image,
+/// printing a report. Returns false if any scene differs.
+///
+/// `cargo run --features visual-diff --bin visual_diff -- [scenes] [references] [--update]`
+pub fn run(config: VisualDiffConfig) -> bool {
+    let engine_config = EngineConfig {
+        window: engine::window::WindowConfig {
+            title: "Visual Diff".into(),
+            size: Some(config.size),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    CONFIG.set(config).expect("Visual diff already run");
+    Runner::<VisualDiffApp>::run_headless(engine_config);
+
+    PASSED.load(Ordering::Relaxed)
+}
+
+/// Scene being rendered and captured.
+struct Capture {
+    name: String,
+    entities: Vec<Entity>,
+    target: Arc<LoadedTexture>,
+    frames: u32,
+}
+
+struct VisualDiffApp {
+    config: &'static VisualDiffConfig,
+    scenes: Vec<PathBuf>,
+    capture: Option<Capture>,
+    results: Vec<(String, Result<DiffReport, VisualDiffError>)>,
+}
+
+impl App for VisualDiffApp {
+    fn new(state: &mut State) -> Self {
+        let config = CONFIG.get().expect("Visual diff config not set");
+
+        crate::scene::register_pipeline_components(state.scene_registry_mut());
+        state
+            .renderer_mut()
+            .add_renderer::<SkyboxRenderer>()
+            .add_renderer::<ModelRenderer>()
+            .add_renderer::<TextureRenderer>()
+            .add_renderer::<Ui3dRenderer>()
+            .add_renderer::<Text3dRenderer>()
+            .add_renderer::<FloatingTextRenderer>();
+
+        let mut scenes = std::fs::read_dir(&config.scenes)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| Some(entry.ok()?.path()))
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_else(|e| {
+                log::error!("Unable to read scenes from {:?}: {}", config.scenes, e);
+                Vec::new()
+            });
+
+        // Scenes are popped from the back
+        scenes.sort();
+        scenes.reverse();
+
+        Self {
+            config,
+            scenes,
+            capture: None,
+            results: Vec::new(),
+        }
+    }
+
+    fn resize(&mut self, _state: &mut State, _size: Size<u32>) {}
+
+    fn update(&mut self, state: &mut State) {
+        match &mut self.capture {
+            Some(capture) if capture.frames < self.config.frames => capture.frames += 1,
+
+            Some(_) => {
+                let capture = self.capture.take().unwrap();
+                let result = self.finish_capture(state, &capture);
+
+                capture
+                    .entities
+                    .into_iter()
+                    .for_each(|entity| state.despawn_recursive(entity));
+                self.results.push((capture.name, result));
+            }
+
+            None => match self.scenes.pop() {
+                Some(path) => self.start_capture(state, &path),
+                None => self.report(state),
+            },
+        }
+    }
+}
+
+impl VisualDiffApp {
+    fn start_capture(&mut self, state: &mut State, path: &Path) {
+        let name = path.file_stem().unwrap().to_string_lossy().to_string();
+
+        let entities = match state.load_scene(path) {
+            Ok(entities) => entities,
+            Err(e) => {
+                let error = VisualDiffError::Scene(e.to_string());
+                self.results.push((name, Err(error)));
+                return;
+            }
+        };
+
+        let Some(camera) = camera::find_camera(state.world()) else {
+            entities
+                .into_iter()
+                .for_each(|entity| state.despawn_recursive(entity));
+            self.results.push((name, Err(VisualDiffError::NoCamera)));
+            return;
+        };
+
+        let target = state.renderer().create_render_target(self.config.size);
+        let texture = target.texture().clone();
+        state.world_mut().insert_one(camera, target).ok();
+
+        self.capture = Some(Capture {
+            name,
+            entities,
+            target: texture,
+            frames: 0,
+        });
+    }
+
+    fn finish_capture(
+        &self,
+        state: &State,
+        capture: &Capture,
+    ) -> Result<DiffReport, VisualDiffError> {
+        let renderer = state.renderer();
+        let core = renderer.core();
+        let actual = capture
+            .target
+            .texture()
+            .read_pixels(core.device(), core.queue())
+            .ok_or(VisualDiffError::Readback)?;
+
+        let file = format!("{}.png", capture.name);
+        let reference_path = self.config.references.join(&file);
+
+        if self.config.update {
+            std::fs::create_dir_all(&self.config.references)?;
+            actual.save(&reference_path)?;
+            return compare(&actual, &actual, self.config.threshold).map(|(report, _)| report);
+        }
+
+        std::fs::create_dir_all(&self.config.output)?;
+
+        if !reference_path.exists() {
+            actual.save(self.config.output.join(&file))?;
+            return Err(VisualDiffError::MissingReference(reference_path));
+        }
+
+        let reference = image::open(&reference_path)?.to_rgba8();
+        let (report, diff) = compare(&reference, &actual, self.config.threshold)?;
+
+        side_by_side(&reference, &actual, &diff).save(self.config.output.join(&file))?;
+
+        Ok(report)
+    }
+
+    fn report(&self, state: &mut State) {
+        let mut failed = 0;
+
+        self.results.iter().for_each(|(name, result)| match result {
+            Ok(report) if report.differing_fraction() <= self.config.tolerance => println!(
+                "ok    {}: {} of {} pixels differ, max delta {}, mean delta {:.3}",
+                name, report.differing, report.pixels, report.max_delta, report.mean_delta
+            ),
+            Ok(report) => {
+                failed += 1;
+                println!(
+                    "FAIL  {}: {} of {} pixels differ ({:.2}%), max delta {}, mean delta {:.3}",
+                    name,
+                    report.differing,
+                    report.pixels,
+                    report.differing_fraction() * 100.,
+                    report.max_delta,
+                    report.mean_delta
+                );
+            }
+            Err(e) => {
+                failed += 1;
+                println!("FAIL  {}: {}", name, e);
+            }
+        });
+
+        println!(
+            "{} scenes, {} failed. Diff images written to {:?}",
+            self.results.len(),
+            failed,
+            self.config.output
+        );
+
+        PASSED.store(failed == 0, Ordering::Relaxed);
+        state.exit();
+    }
+}
+
+//====================================================================